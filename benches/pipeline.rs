@@ -0,0 +1,61 @@
+//! Stage benchmarks over the synthetic workload in `yinx::bench`
+//!
+//! `cargo bench` runs every stage; `cargo bench -- filtering` runs the
+//! stages whose name contains "filtering".
+
+use std::sync::Arc;
+use yinx::bench::{self, StageTiming};
+use yinx::patterns::PatternRegistry;
+
+const CAPTURES: usize = 500;
+const LINES: usize = 1000;
+const SEARCHES: usize = 200;
+
+fn report(stage: &StageTiming) {
+    println!(
+        "{:<16} {:>6} runs  p50 {:>9.3}ms  p95 {:>9.3}ms  {:>8.1} runs/s  {:>7.1} MB/s",
+        stage.name,
+        stage.iterations,
+        stage.p50.as_secs_f64() * 1000.0,
+        stage.p95.as_secs_f64() * 1000.0,
+        stage.per_sec(),
+        stage.mb_per_sec()
+    );
+}
+
+fn main() -> yinx::Result<()> {
+    // cargo passes --bench; anything else is a stage filter
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let wanted = |name: &str| filter.as_deref().is_none_or(|f| name.contains(f));
+
+    let patterns = PatternRegistry::bundled()?;
+    let captures = bench::synthetic_captures(CAPTURES, LINES);
+    let scratch = tempfile::tempdir().expect("scratch directory");
+
+    if wanted("filtering") {
+        report(&bench::bench_filtering(
+            Arc::new(patterns.clone()),
+            &captures,
+        )?);
+    }
+    if wanted("extraction") {
+        report(&bench::bench_extraction(&patterns, &captures));
+    }
+    if wanted("blob writes") {
+        report(&bench::bench_blob_writes(
+            &scratch.path().join("blobs"),
+            &captures,
+        )?);
+    }
+    if wanted("ingest") || wanted("keyword search") {
+        let (ingest, search) = bench::bench_ingest_and_search(
+            &scratch.path().join("data"),
+            patterns,
+            &captures,
+            SEARCHES,
+        )?;
+        report(&ingest);
+        report(&search);
+    }
+    Ok(())
+}
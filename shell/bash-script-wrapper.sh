@@ -6,16 +6,29 @@
 # It may cause slight latency and is more invasive than the basic hook.
 # Use this for penetration testing sessions where you need full output capture.
 
+# Data directory (should match storage.data_dir)
+if [[ "$(uname -s)" == Darwin ]]; then
+    YINX_HOME="${YINX_HOME:-${HOME}/Library/Application Support/yinx}"
+else
+    YINX_HOME="${YINX_HOME:-${HOME}/.yinx}"
+fi
+
 # Socket path (should match daemon config)
-YINX_SOCKET="${HOME}/.yinx/daemon.sock"
+YINX_SOCKET="${YINX_SOCKET:-${YINX_HOME}/daemon.sock}"
 
 # Directory for temporary output files
-YINX_TMP_DIR="${HOME}/.yinx/tmp"
+YINX_TMP_DIR="${YINX_HOME}/tmp"
 mkdir -p "$YINX_TMP_DIR" 2>/dev/null
 
 # Path to yinx binary
 YINX_BIN="${YINX_BIN:-yinx}"
 
+# Commands matching capture.exclude_commands are skipped here and again by the daemon
+YINX_EXCLUDE_RE="$("$YINX_BIN" _internal exclude-pattern 2>/dev/null)"
+
+# Terminal this shell runs in, recorded with each capture
+YINX_TTY="$(tty 2>/dev/null)"
+
 # Variable to store current capture file
 __YINX_CURRENT_CAPTURE_FILE=""
 
@@ -40,6 +53,16 @@ __yinx_preexec() {
 
     # Create unique temp file for this command's output
     __YINX_CURRENT_CAPTURE_FILE="${YINX_TMP_DIR}/yinx_$$_${RANDOM}.out"
+
+    # Register the command's targets before it returns
+    if [[ -z "$YINX_EXCLUDE_RE" || ! "$cmd" =~ $YINX_EXCLUDE_RE ]]; then
+        (
+            RUST_LOG="${RUST_LOG:-yinx=error}" "$YINX_BIN" _internal intent \
+                --session-id "${YINX_SESSION_ID:-default}" \
+                --timestamp "$(date +%s)" \
+                --command "$cmd"
+        ) &
+    fi
 }
 
 # Function to send captured command
@@ -59,6 +82,11 @@ __yinx_postexec() {
         return $exit_code
     fi
 
+    # Skip excluded commands
+    if [[ -n "$YINX_EXCLUDE_RE" && "$last_cmd" =~ $YINX_EXCLUDE_RE ]]; then
+        return $exit_code
+    fi
+
     # Check if daemon socket exists
     if [[ ! -S "$YINX_SOCKET" ]]; then
         return $exit_code
@@ -72,13 +100,15 @@ __yinx_postexec() {
     # Send capture asynchronously
     (
         if command -v "$YINX_BIN" &> /dev/null; then
-            "$YINX_BIN" _internal capture \
+            # Only yinx's one-line warnings (dead session, busy daemon) reach the terminal
+            RUST_LOG="${RUST_LOG:-yinx=error}" "$YINX_BIN" _internal capture \
                 --session-id "$session_id" \
                 --timestamp "$timestamp" \
                 --command "$last_cmd" \
                 --output-file "${output_file:-/dev/null}" \
                 --exit-code "$exit_code" \
-                --cwd "$cwd" 2>/dev/null
+                --cwd "$cwd" \
+                --tty "$YINX_TTY"
 
             # Cleanup temp file after send
             if [[ -n "$output_file" ]] && [[ -f "$output_file" ]]; then
@@ -2,16 +2,30 @@
 # Yinx bash shell hook for terminal capture
 # Source this file in your ~/.bashrc: source /path/to/yinx/shell/bash.sh
 
+# Data directory (should match storage.data_dir)
+if [[ "$(uname -s)" == Darwin ]]; then
+    YINX_HOME="${YINX_HOME:-${HOME}/Library/Application Support/yinx}"
+else
+    YINX_HOME="${YINX_HOME:-${HOME}/.yinx}"
+fi
+
 # Socket path (should match daemon config)
-YINX_SOCKET="${HOME}/.yinx/daemon.sock"
+YINX_SOCKET="${YINX_SOCKET:-${YINX_HOME}/daemon.sock}"
 
 # Directory for temporary output files
-YINX_TMP_DIR="${HOME}/.yinx/tmp"
+YINX_TMP_DIR="${YINX_HOME}/tmp"
 mkdir -p "$YINX_TMP_DIR" 2>/dev/null
 
 # Path to yinx binary (customize if needed)
 YINX_BIN="${YINX_BIN:-yinx}"
 
+# Commands matching capture.exclude_commands are skipped here and again by the daemon
+# (patterns POSIX ERE can't express are left to the daemon)
+YINX_EXCLUDE_RE="$("$YINX_BIN" _internal exclude-pattern 2>/dev/null)"
+
+# Terminal this shell runs in, recorded with each capture
+YINX_TTY="$(tty 2>/dev/null)"
+
 # Post-execution hook: Capture and send command output
 __yinx_capture() {
     local exit_code=$?
@@ -29,6 +43,11 @@ __yinx_capture() {
         return $exit_code
     fi
 
+    # Skip excluded commands
+    if [[ -n "$YINX_EXCLUDE_RE" && "$last_cmd" =~ $YINX_EXCLUDE_RE ]]; then
+        return $exit_code
+    fi
+
     # Check if daemon socket exists
     if [[ ! -S "$YINX_SOCKET" ]]; then
         return $exit_code
@@ -52,13 +71,15 @@ __yinx_capture() {
 
         # Send via yinx _internal subcommand
         if command -v "$YINX_BIN" &> /dev/null; then
-            "$YINX_BIN" _internal capture \
+            # Only yinx's one-line warnings (dead session, busy daemon) reach the terminal
+            RUST_LOG="${RUST_LOG:-yinx=error}" "$YINX_BIN" _internal capture \
                 --session-id "$session_id" \
                 --timestamp "$timestamp" \
                 --command "$last_cmd" \
                 --output-file "$output_file" \
                 --exit-code "$exit_code" \
-                --cwd "$cwd" 2>/dev/null
+                --cwd "$cwd" \
+                --tty "$YINX_TTY"
 
             # Cleanup temp file after send
             rm -f "$output_file" 2>/dev/null
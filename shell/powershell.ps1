@@ -0,0 +1,63 @@
+# Yinx PowerShell hook for terminal capture
+# Dot-source this file in your $PROFILE: . C:\path\to\yinx\shell\powershell.ps1
+
+# Path to yinx binary (customize if needed)
+$YinxBin = if ($env:YINX_BIN) { $env:YINX_BIN } else { 'yinx' }
+
+# Directory for temporary output files
+$YinxTmpDir = Join-Path $HOME '.yinx\tmp'
+New-Item -ItemType Directory -Force -Path $YinxTmpDir -ErrorAction SilentlyContinue | Out-Null
+
+# Commands matching capture.exclude_commands are skipped here and again by the daemon
+$YinxExcludeRe = & $YinxBin _internal exclude-pattern 2>$null
+
+# History id of the last command sent, so an empty prompt isn't sent twice
+$global:YinxLastHistoryId = -1
+
+# Keep whatever prompt was set before this file was loaded
+$global:YinxOriginalPrompt = $function:prompt
+
+# Capture and send the command that just finished, then draw the prompt
+function global:prompt {
+    # Read these first; anything below overwrites them
+    $succeeded = $?
+    $nativeExit = $global:LASTEXITCODE
+
+    $last = Get-History -Count 1
+    if ($last -and $last.Id -ne $global:YinxLastHistoryId) {
+        $global:YinxLastHistoryId = $last.Id
+        $command = $last.CommandLine.Trim()
+
+        $skip = (-not $command) -or ($command -match '^yinx') -or
+            ($YinxExcludeRe -and $command -match $YinxExcludeRe)
+        if (-not $skip) {
+            # $LASTEXITCODE is only set by native programs; cmdlets only set $?
+            $exitCode = if ($succeeded) { 0 } elseif ($nativeExit) { $nativeExit } else { 1 }
+            $sessionId = if ($env:YINX_SESSION_ID) { $env:YINX_SESSION_ID } else { 'default' }
+            $timestamp = [DateTimeOffset]::new($last.StartExecutionTime).ToUnixTimeSeconds()
+
+            # Output can't be captured retroactively, so an empty file is sent
+            $outputFile = Join-Path $YinxTmpDir ("yinx_{0}_{1}.out" -f $PID, (Get-Random))
+            New-Item -ItemType File -Force -Path $outputFile | Out-Null
+
+            & $YinxBin _internal capture `
+                --session-id $sessionId `
+                --timestamp $timestamp `
+                --command $command `
+                --output-file $outputFile `
+                --exit-code $exitCode `
+                --cwd (Get-Location).ProviderPath `
+                --tty "console:$PID" 2>$null | Out-Null
+
+            Remove-Item -Force -Path $outputFile -ErrorAction SilentlyContinue
+        }
+    }
+
+    # Leave $LASTEXITCODE as the user's command set it
+    $global:LASTEXITCODE = $nativeExit
+    if ($global:YinxOriginalPrompt) { & $global:YinxOriginalPrompt } else { "PS $($executionContext.SessionState.Path.CurrentLocation)> " }
+}
+
+$sessionLabel = if ($env:YINX_SESSION_ID) { $env:YINX_SESSION_ID } else { 'default' }
+Write-Host "Yinx PowerShell hook loaded (basic). Session: $sessionLabel"
+Write-Host "Note: This hook captures commands but NOT output."
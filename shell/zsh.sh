@@ -2,16 +2,49 @@
 # Yinx zsh shell hook for terminal capture
 # Source this file in your ~/.zshrc: source /path/to/yinx/shell/zsh.sh
 
+# Data directory (should match storage.data_dir)
+if [[ "$(uname -s)" == Darwin ]]; then
+    YINX_HOME="${YINX_HOME:-${HOME}/Library/Application Support/yinx}"
+else
+    YINX_HOME="${YINX_HOME:-${HOME}/.yinx}"
+fi
+
 # Socket path (should match daemon config)
-YINX_SOCKET="${HOME}/.yinx/daemon.sock"
+YINX_SOCKET="${YINX_SOCKET:-${YINX_HOME}/daemon.sock}"
 
 # Directory for temporary output files
-YINX_TMP_DIR="${HOME}/.yinx/tmp"
+YINX_TMP_DIR="${YINX_HOME}/tmp"
 mkdir -p "$YINX_TMP_DIR" 2>/dev/null
 
 # Path to yinx binary (customize if needed)
 YINX_BIN="${YINX_BIN:-yinx}"
 
+# Commands matching capture.exclude_commands are skipped here and again by the daemon
+# (patterns POSIX ERE can't express are left to the daemon)
+YINX_EXCLUDE_RE="$("$YINX_BIN" _internal exclude-pattern 2>/dev/null)"
+
+# Terminal this shell runs in, recorded with each capture
+YINX_TTY="${TTY:-$(tty 2>/dev/null)}"
+
+# Pre-execution hook: register the command's targets before it returns
+__yinx_preexec() {
+    local cmd="$1"
+
+    if [[ -z "$cmd" || "$cmd" =~ ^yinx || ! -S "$YINX_SOCKET" ]]; then
+        return
+    fi
+    if [[ -n "$YINX_EXCLUDE_RE" && "$cmd" =~ $YINX_EXCLUDE_RE ]]; then
+        return
+    fi
+
+    (
+        RUST_LOG="${RUST_LOG:-yinx=error}" "$YINX_BIN" _internal intent \
+            --session-id "${YINX_SESSION_ID:-default}" \
+            --timestamp "$(date +%s)" \
+            --command "$cmd"
+    ) &!
+}
+
 # Post-execution hook: Capture and send command output
 __yinx_precmd() {
     local exit_code=$?
@@ -29,6 +62,11 @@ __yinx_precmd() {
         return $exit_code
     fi
 
+    # Skip excluded commands
+    if [[ -n "$YINX_EXCLUDE_RE" && "$last_cmd" =~ $YINX_EXCLUDE_RE ]]; then
+        return $exit_code
+    fi
+
     # Check if daemon socket exists
     if [[ ! -S "$YINX_SOCKET" ]]; then
         return $exit_code
@@ -52,13 +90,15 @@ __yinx_precmd() {
 
         # Send via yinx _internal subcommand
         if command -v "$YINX_BIN" &> /dev/null; then
-            "$YINX_BIN" _internal capture \
+            # Only yinx's one-line warnings (dead session, busy daemon) reach the terminal
+            RUST_LOG="${RUST_LOG:-yinx=error}" "$YINX_BIN" _internal capture \
                 --session-id "$session_id" \
                 --timestamp "$timestamp" \
                 --command "$last_cmd" \
                 --output-file "$output_file" \
                 --exit-code "$exit_code" \
-                --cwd "$cwd" 2>/dev/null
+                --cwd "$cwd" \
+                --tty "$YINX_TTY"
 
             # Cleanup temp file after send
             rm -f "$output_file" 2>/dev/null
@@ -68,7 +108,8 @@ __yinx_precmd() {
     return $exit_code
 }
 
-# Add to precmd hooks
+# Add to preexec and precmd hooks
+preexec_functions+=(__yinx_preexec)
 precmd_functions+=(__yinx_precmd)
 
 echo "Yinx zsh hook loaded (basic). Session: ${YINX_SESSION_ID:-default}"
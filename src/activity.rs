@@ -0,0 +1,461 @@
+//! Engagement activity over an interval
+//!
+//! `yinx summary` condenses what happened since a point in time into the
+//! things a stand-up or a report's activity appendix asks about: hosts and
+//! services seen for the first time, findings, cracked credentials, proofs,
+//! and the commands that produced them. Secret values never appear; only
+//! the number of secrets seen per entity type does.
+
+use crate::error::Result;
+use crate::storage::{StorageManager, HOST_ENTITY_TYPES};
+use rusqlite::params;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Commands listed as notable, at most
+const MAX_NOTABLE: usize = 8;
+
+/// Entity types whose values are secrets; only their counts are reported
+const SECRET_TYPE_PREFIXES: &[&str] = &["credential_", "aws_", "jwt_"];
+
+/// A host or service first seen inside the interval
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sighting {
+    pub value: String,
+    /// Host the first sighting's capture involved, for services
+    pub host: Option<String>,
+    pub capture_id: i64,
+    pub first_seen: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityFinding {
+    pub id: i64,
+    pub title: String,
+    pub severity: String,
+    pub created_at: i64,
+}
+
+/// A cracked credential, without its plaintext
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityCredential {
+    pub username: Option<String>,
+    pub hash_type: String,
+    pub source: String,
+    pub cracked_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityProof {
+    pub host: Option<String>,
+    pub file: String,
+    pub created_at: i64,
+}
+
+/// A command worth calling out, with why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NotableCommand {
+    pub capture_id: i64,
+    pub timestamp: i64,
+    pub command: String,
+    pub reason: String,
+}
+
+/// What happened between `since` and `until`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActivitySummary {
+    pub since: i64,
+    pub until: i64,
+    pub captures: usize,
+    /// Captures per detected tool, busiest first
+    pub tools: Vec<(String, usize)>,
+    pub new_hosts: Vec<Sighting>,
+    pub new_services: Vec<Sighting>,
+    pub findings: Vec<ActivityFinding>,
+    pub credentials: Vec<ActivityCredential>,
+    /// Secrets seen in output, counted per entity type
+    pub secrets: BTreeMap<String, usize>,
+    pub proofs: Vec<ActivityProof>,
+    pub notable: Vec<NotableCommand>,
+}
+
+impl ActivitySummary {
+    /// Check if nothing was captured or recorded in the interval
+    pub fn is_empty(&self) -> bool {
+        self.captures == 0
+            && self.findings.is_empty()
+            && self.credentials.is_empty()
+            && self.proofs.is_empty()
+    }
+
+    /// Markdown for a report's activity appendix
+    pub fn to_markdown(&self) -> String {
+        let when = |ts: i64| {
+            chrono::DateTime::from_timestamp(ts, 0)
+                .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_default()
+        };
+        let mut md = String::new();
+        let _ = writeln!(
+            md,
+            "## Activity {} – {}\n",
+            when(self.since),
+            when(self.until)
+        );
+        let tools: Vec<String> = self
+            .tools
+            .iter()
+            .map(|(tool, n)| format!("{} ({})", tool, n))
+            .collect();
+        let _ = writeln!(
+            md,
+            "{} commands captured{}.\n",
+            self.captures,
+            if tools.is_empty() {
+                String::new()
+            } else {
+                format!(", mostly {}", tools.join(", "))
+            }
+        );
+
+        let mut section = |title: &str, lines: Vec<String>| {
+            if lines.is_empty() {
+                return;
+            }
+            let _ = writeln!(md, "### {}\n", title);
+            for line in lines {
+                let _ = writeln!(md, "- {}", line);
+            }
+            md.push('\n');
+        };
+        section(
+            "New hosts",
+            self.new_hosts.iter().map(|h| h.value.clone()).collect(),
+        );
+        section(
+            "New services",
+            self.new_services
+                .iter()
+                .map(|s| match &s.host {
+                    Some(host) => format!("{} on {}", s.value, host),
+                    None => s.value.clone(),
+                })
+                .collect(),
+        );
+        section(
+            "Findings",
+            self.findings
+                .iter()
+                .map(|f| format!("[{}] {}", f.severity, f.title))
+                .collect(),
+        );
+        section(
+            "Credentials",
+            self.credentials
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{} ({}, cracked with {})",
+                        c.username.as_deref().unwrap_or("unknown user"),
+                        c.hash_type,
+                        c.source
+                    )
+                })
+                .chain(
+                    self.secrets
+                        .iter()
+                        .map(|(kind, n)| format!("{} × {} seen in output", n, kind)),
+                )
+                .collect(),
+        );
+        section(
+            "Proofs",
+            self.proofs
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{} on {}",
+                        p.file,
+                        p.host.as_deref().unwrap_or("unknown host")
+                    )
+                })
+                .collect(),
+        );
+        section(
+            "Notable commands",
+            self.notable
+                .iter()
+                .map(|n| format!("`{}` — {}", n.command, n.reason))
+                .collect(),
+        );
+        md.trim_end().to_string() + "\n"
+    }
+}
+
+/// Summarize activity from `since` up to `until`; all sessions when `session_id` is None
+pub fn summarize_activity(
+    storage: &StorageManager,
+    session_id: Option<&str>,
+    since: i64,
+    until: i64,
+) -> Result<ActivitySummary> {
+    let conn = storage.database.read_conn()?;
+    let mut summary = ActivitySummary {
+        since,
+        until,
+        ..ActivitySummary::default()
+    };
+
+    let captures: Vec<(i64, i64, Option<String>, Option<String>)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, command, tool FROM captures
+             WHERE timestamp >= ?1 AND timestamp <= ?2 AND (?3 IS NULL OR session_id = ?3)
+             ORDER BY timestamp, id",
+        )?;
+        let rows = stmt.query_map(params![since, until, session_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()?
+    };
+    summary.captures = captures.len();
+    let mut tools: HashMap<String, usize> = HashMap::new();
+    for (_, _, _, tool) in &captures {
+        if let Some(tool) = tool {
+            *tools.entry(tool.clone()).or_default() += 1;
+        }
+    }
+    summary.tools = tools.into_iter().collect();
+    summary
+        .tools
+        .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    summary.new_hosts = first_sightings(&conn, HOST_ENTITY_TYPES, session_id, since, until)?;
+    summary.new_services = first_sightings(&conn, &["service_version"], session_id, since, until)?;
+
+    // Entities per capture in the window, split into secrets and the rest
+    let mut entity_counts: HashMap<i64, usize> = HashMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT e.capture_id, e.type, e.occurrences FROM entities e
+             JOIN captures c ON e.capture_id = c.id
+             WHERE c.timestamp >= ?1 AND c.timestamp <= ?2 AND (?3 IS NULL OR c.session_id = ?3)",
+        )?;
+        let rows = stmt.query_map(params![since, until, session_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (capture_id, entity_type, occurrences) = row?;
+            if SECRET_TYPE_PREFIXES
+                .iter()
+                .any(|prefix| entity_type.starts_with(prefix))
+            {
+                *summary.secrets.entry(entity_type).or_default() += occurrences.max(1) as usize;
+            }
+            *entity_counts.entry(capture_id).or_default() += 1;
+        }
+    }
+
+    let in_window = |ts: i64| ts >= since && ts <= until;
+    let mut reasons: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+    for finding in storage.database.list_findings(session_id)? {
+        if !in_window(finding.created_at) {
+            continue;
+        }
+        if let Some(capture_id) = finding.capture_id {
+            reasons
+                .entry(capture_id)
+                .or_default()
+                .push(format!("finding: {}", finding.title));
+        }
+        summary.findings.push(ActivityFinding {
+            id: finding.id,
+            title: finding.title,
+            severity: finding.severity,
+            created_at: finding.created_at,
+        });
+    }
+    summary.findings.reverse();
+    for credential in storage.database.list_credentials(session_id)? {
+        if in_window(credential.cracked_at) {
+            summary.credentials.push(ActivityCredential {
+                username: credential.username,
+                hash_type: credential.hash_type,
+                source: credential.source,
+                cracked_at: credential.cracked_at,
+            });
+        }
+    }
+    for proof in storage.database.list_proofs(session_id)? {
+        if !in_window(proof.created_at) {
+            continue;
+        }
+        if let Some(capture_id) = proof.capture_id {
+            reasons
+                .entry(capture_id)
+                .or_default()
+                .push(format!("proof: {}", proof.file));
+        }
+        summary.proofs.push(ActivityProof {
+            host: proof.host,
+            file: proof.file,
+            created_at: proof.created_at,
+        });
+    }
+
+    // Commands that opened findings or read proofs first, then the ones
+    // whose output named the most entities
+    let mut ranked: Vec<(bool, usize, NotableCommand)> = captures
+        .iter()
+        .filter_map(|(capture_id, timestamp, command, _)| {
+            let entities = entity_counts.get(capture_id).copied().unwrap_or(0);
+            let reason = match reasons.get(capture_id) {
+                Some(reasons) => reasons.join(", "),
+                None if entities > 0 => format!("{} entities", entities),
+                None => return None,
+            };
+            Some((
+                reasons.contains_key(capture_id),
+                entities,
+                NotableCommand {
+                    capture_id: *capture_id,
+                    timestamp: *timestamp,
+                    command: command.clone()?,
+                    reason,
+                },
+            ))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+    let mut notable: Vec<NotableCommand> = ranked
+        .into_iter()
+        .take(MAX_NOTABLE)
+        .map(|(_, _, notable)| notable)
+        .collect();
+    notable.sort_by_key(|n| (n.timestamp, n.capture_id));
+    summary.notable = notable;
+
+    Ok(summary)
+}
+
+/// Values of the given entity types whose first sighting falls in the window
+fn first_sightings(
+    conn: &rusqlite::Connection,
+    types: &[&str],
+    session_id: Option<&str>,
+    since: i64,
+    until: i64,
+) -> Result<Vec<Sighting>> {
+    let placeholders: Vec<String> = (0..types.len()).map(|i| format!("?{}", i + 4)).collect();
+    let host_types: Vec<String> = HOST_ENTITY_TYPES
+        .iter()
+        .map(|t| format!("'{}'", t))
+        .collect();
+    // SQLite takes the bare columns from the row MIN() picked
+    let sql = format!(
+        "SELECT e.value, c.id, MIN(c.timestamp) AS first_seen,
+            (SELECT h.value FROM entities h
+             WHERE h.capture_id = c.id AND h.type IN ({})
+             ORDER BY h.id LIMIT 1)
+         FROM entities e JOIN captures c ON e.capture_id = c.id
+         WHERE e.type IN ({}) AND (?1 IS NULL OR c.session_id = ?1)
+         GROUP BY e.value
+         HAVING first_seen >= ?2 AND first_seen <= ?3
+         ORDER BY first_seen, e.value",
+        host_types.join(", "),
+        placeholders.join(", ")
+    );
+    let mut values: Vec<rusqlite::types::Value> = vec![
+        session_id.map(str::to_string).into(),
+        since.into(),
+        until.into(),
+    ];
+    values.extend(types.iter().map(|t| t.to_string().into()));
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+        let value: String = row.get(0)?;
+        let host: Option<String> = row.get(3)?;
+        Ok(Sighting {
+            host: host.filter(|h| *h != value),
+            value,
+            capture_id: row.get(1)?,
+            first_seen: row.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_summarize_activity() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let now = chrono::Utc::now().timestamp();
+        let entity = |entity_type: &str, value: &str| {
+            (
+                entity_type.to_string(),
+                value.to_string(),
+                String::new(),
+                1.0,
+            )
+        };
+        let nmap = |timestamp: i64, command: &str| {
+            capture("s1", command, b"")
+                .timestamp(timestamp)
+                .tool("nmap")
+                .insert(&storage)
+                .id
+        };
+        let old = nmap(now - 86_400, "nmap -sn 10.0.0.0/24");
+        let new = nmap(now - 60, "nmap -sV 10.0.0.1 10.0.0.2");
+        storage
+            .database
+            .insert_entities(old, &[entity("ip_address", "10.0.0.1")])
+            .unwrap();
+        storage
+            .database
+            .insert_entities(
+                new,
+                &[
+                    entity("ip_address", "10.0.0.1"),
+                    entity("ip_address", "10.0.0.2"),
+                    entity("service_version", "OpenSSH 8.2"),
+                    entity("credential_password", "password=hunter2"),
+                ],
+            )
+            .unwrap();
+        storage
+            .database
+            .insert_finding("s1", Some(new), "SSH exposed", "low", Some("ssh"))
+            .unwrap();
+
+        let summary = summarize_activity(&storage, Some("s1"), now - 3600, now + 60).unwrap();
+        assert_eq!(summary.captures, 1);
+        assert_eq!(summary.tools, vec![("nmap".to_string(), 1)]);
+        let hosts: Vec<&str> = summary.new_hosts.iter().map(|h| h.value.as_str()).collect();
+        assert_eq!(hosts, vec!["10.0.0.2"]);
+        assert_eq!(summary.new_services[0].value, "OpenSSH 8.2");
+        assert_eq!(summary.new_services[0].host.as_deref(), Some("10.0.0.1"));
+        assert_eq!(summary.secrets["credential_password"], 1);
+        assert_eq!(summary.notable.len(), 1);
+        assert_eq!(summary.notable[0].reason, "finding: SSH exposed");
+
+        let markdown = summary.to_markdown();
+        assert!(markdown.contains("### New hosts\n\n- 10.0.0.2"));
+        assert!(!markdown.contains("hunter2"));
+
+        // The day before saw the first host come up
+        let earlier = summarize_activity(&storage, None, now - 90_000, now - 3600).unwrap();
+        assert_eq!(earlier.new_hosts[0].value, "10.0.0.1");
+        assert!(earlier.findings.is_empty());
+    }
+}
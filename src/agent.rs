@@ -0,0 +1,472 @@
+//! Capture forwarder for pivot boxes
+//!
+//! `yinx agent` stands in for the daemon on a remote host: it listens on the
+//! usual daemon socket so the shell hooks work unchanged, appends every
+//! capture to an on-disk spool, and forwards the spool in batches to the main
+//! yinx instance. The upstream is either the remote JSON-RPC API
+//! (`host:port`) or a daemon socket forwarded over SSH (`unix:/path`).
+//!
+//! Delivery is at-least-once. The spool records how far it has been
+//! acknowledged, so an agent restarted after a crash or a lost link resumes
+//! where it stopped; a batch cut off mid-delivery may be sent twice
+//! (`capture.dedup_window` on the server folds those repeats).
+
+use crate::config::IpcEncoding;
+use crate::daemon::{
+    accept_message, peer_uid, write_response_as, CaptureContext, IpcClient, IpcErrorCode,
+    IpcMessage, IpcResponse, IpcServer,
+};
+use crate::error::{Result, YinxError};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// A capture waiting in the spool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpooledCapture {
+    pub session_id: String,
+    pub timestamp: i64,
+    pub command: String,
+    pub output: String,
+    pub exit_code: i32,
+    pub cwd: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<CaptureContext>,
+}
+
+/// Append-only capture queue with a delivery offset
+///
+/// `queue.jsonl` holds one capture per line and `offset` the byte position
+/// up to which captures were acknowledged upstream. Both are reset once
+/// everything has been delivered.
+pub struct Spool {
+    queue: PathBuf,
+    offset: PathBuf,
+}
+
+impl Spool {
+    /// Open (or create) a spool directory
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to create spool directory: {:?}", dir),
+        })?;
+        Ok(Self {
+            queue: dir.join("queue.jsonl"),
+            offset: dir.join("offset"),
+        })
+    }
+
+    /// Queue a capture
+    pub fn push(&self, capture: &SpooledCapture) -> Result<()> {
+        let mut line = serde_json::to_vec(capture).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to serialize spooled capture".to_string(),
+        })?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.queue)
+            .map_err(|e| self.io_error(e))?;
+        file.write_all(&line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| self.io_error(e))
+    }
+
+    /// Up to `limit` undelivered captures, each with the offset to ack after it
+    pub fn pending(&self, limit: usize) -> Result<Vec<(u64, SpooledCapture)>> {
+        let file = match File::open(&self.queue) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.io_error(e)),
+        };
+        let mut position = self.acked()?;
+        let mut reader = BufReader::new(file);
+        reader
+            .seek(SeekFrom::Start(position))
+            .map_err(|e| self.io_error(e))?;
+
+        let mut pending = Vec::new();
+        let mut line = String::new();
+        while pending.len() < limit {
+            line.clear();
+            let read = reader.read_line(&mut line).map_err(|e| self.io_error(e))?;
+            if read == 0 || !line.ends_with('\n') {
+                // End of queue, or a line still being written
+                break;
+            }
+            position += read as u64;
+            match serde_json::from_str(&line) {
+                Ok(capture) => pending.push((position, capture)),
+                Err(e) => tracing::warn!("Dropping corrupt spool entry: {}", e),
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Record delivery up to `offset`, resetting the spool once it is drained
+    pub fn ack(&self, offset: u64) -> Result<()> {
+        let size = std::fs::metadata(&self.queue).map(|m| m.len()).unwrap_or(0);
+        if offset >= size {
+            File::create(&self.queue).map_err(|e| self.io_error(e))?;
+            return std::fs::write(&self.offset, "0").map_err(|e| self.io_error(e));
+        }
+        std::fs::write(&self.offset, offset.to_string()).map_err(|e| self.io_error(e))
+    }
+
+    fn acked(&self) -> Result<u64> {
+        match std::fs::read_to_string(&self.offset) {
+            Ok(content) => Ok(content.trim().parse().unwrap_or(0)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(self.io_error(e)),
+        }
+    }
+
+    fn io_error(&self, source: std::io::Error) -> YinxError {
+        YinxError::Io {
+            source,
+            context: format!("Spool I/O failed: {:?}", self.queue),
+        }
+    }
+}
+
+/// Where the agent delivers captures
+#[derive(Debug, Clone, PartialEq)]
+pub enum Upstream {
+    /// Remote JSON-RPC API (`host:port`)
+    Tcp(String),
+    /// Daemon socket, typically forwarded with `ssh -L local.sock:daemon.sock`
+    Unix(PathBuf),
+}
+
+impl FromStr for Upstream {
+    type Err = YinxError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+        if s.rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+        {
+            return Ok(Self::Tcp(s.to_string()));
+        }
+        Err(YinxError::InvalidConfigValue {
+            path: "--upstream".to_string(),
+            message: format!("expected host:port or unix:/path, got '{}'", s),
+        })
+    }
+}
+
+/// Settings for `yinx agent`
+#[derive(Debug, Clone)]
+pub struct AgentOptions {
+    pub upstream: Upstream,
+    /// Remote API token (TCP upstream only)
+    pub token: Option<String>,
+    /// Socket the local shell hooks send captures to
+    pub socket_path: PathBuf,
+    pub spool_dir: PathBuf,
+    /// Upstream session; captures keep the hook's session id when unset
+    /// (Unix upstream) or go to the server's tracked session (TCP upstream)
+    pub session: Option<String>,
+    pub batch_size: usize,
+    /// Pause between delivery attempts while the upstream is unreachable
+    pub retry_interval: Duration,
+    /// Name recorded as the capturing user on the server
+    pub user: Option<String>,
+}
+
+/// Run the agent until Ctrl-C
+pub async fn run(options: AgentOptions) -> Result<()> {
+    if matches!(options.upstream, Upstream::Tcp(_)) && options.token.is_none() {
+        return Err(YinxError::Config(
+            "A token is required for a TCP upstream".to_string(),
+        ));
+    }
+    let spool = Arc::new(Mutex::new(Spool::open(&options.spool_dir)?));
+    let mut server = IpcServer::new(options.socket_path.clone());
+    server.bind().await?;
+
+    let wake = Arc::new(tokio::sync::Notify::new());
+    let forwarder = tokio::spawn(forward_loop(options.clone(), spool.clone(), wake.clone()));
+
+    loop {
+        tokio::select! {
+            accepted = server.accept() => {
+                let mut stream = match accepted {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        tracing::warn!("Agent accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let (message, encoding) = match accept_message(&mut stream).await {
+                    Ok((message, encoding)) => (Ok(message), encoding),
+                    Err(e) => (Err(e), IpcEncoding::Json),
+                };
+                let response = match message {
+                    Ok(IpcMessage::Capture { dry_run: true, .. }) => {
+                        IpcResponse::error(IpcErrorCode::Unsupported, "Dry runs need a local daemon, not the agent")
+                    }
+                    Ok(IpcMessage::Capture { session_id, timestamp, command, output, exit_code, cwd, context, handoff, .. }) => {
+                        let output = match handoff {
+                            Some(handoff) => peer_uid(&stream).and_then(|uid| handoff.claim(uid)),
+                            None => Ok(output),
+                        };
+                        match output {
+                            Ok(output) => {
+                                let context = context.map(|context| *context);
+                                let capture = SpooledCapture { session_id, timestamp, command, output, exit_code, cwd, context };
+                                match spool.lock().unwrap().push(&capture) {
+                                    Ok(()) => {
+                                        wake.notify_one();
+                                        IpcResponse::success("Capture spooled")
+                                    }
+                                    Err(e) => IpcResponse::error(IpcErrorCode::Internal, format!("Failed to spool capture: {}", e)),
+                                }
+                            }
+                            Err(e) => IpcResponse::error(IpcErrorCode::HandoffRejected, e.to_string()),
+                        }
+                    }
+                    Ok(IpcMessage::Status) => IpcResponse::success("Agent is running"),
+                    Ok(_) => IpcResponse::error(IpcErrorCode::Unsupported, "Not supported by the agent"),
+                    Err(e) => IpcResponse::error(IpcErrorCode::InvalidMessage, e.to_string()),
+                };
+                if let Err(e) = write_response_as(&mut stream, &response, encoding).await {
+                    tracing::debug!("Failed to answer hook: {}", e);
+                }
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    forwarder.abort();
+    server.shutdown()
+}
+
+/// Deliver spooled captures whenever there are some and the upstream answers
+async fn forward_loop(
+    options: AgentOptions,
+    spool: Arc<Mutex<Spool>>,
+    wake: Arc<tokio::sync::Notify>,
+) {
+    loop {
+        let batch = match spool.lock().unwrap().pending(options.batch_size) {
+            Ok(batch) => batch,
+            Err(e) => {
+                tracing::error!("Failed to read spool: {}", e);
+                Vec::new()
+            }
+        };
+        if batch.is_empty() {
+            // Also poll now and then so captures spooled by an earlier run go out
+            let _ = tokio::time::timeout(options.retry_interval, wake.notified()).await;
+            continue;
+        }
+
+        let (delivered, error) = deliver(&options, &batch).await;
+        if delivered > 0 {
+            let offset = batch[delivered - 1].0;
+            if let Err(e) = spool.lock().unwrap().ack(offset) {
+                tracing::error!("Failed to record delivery: {}", e);
+            }
+            tracing::info!("Forwarded {} capture(s)", delivered);
+        }
+        if let Some(e) = error {
+            tracing::warn!("Upstream delivery failed, retrying: {}", e);
+            tokio::time::sleep(options.retry_interval).await;
+        }
+    }
+}
+
+/// Send a batch in order; returns how many were accepted and the first error
+async fn deliver(
+    options: &AgentOptions,
+    batch: &[(u64, SpooledCapture)],
+) -> (usize, Option<YinxError>) {
+    match &options.upstream {
+        Upstream::Unix(path) => {
+            let client = IpcClient::new(path.clone());
+            for (delivered, (_, capture)) in batch.iter().enumerate() {
+                let message = IpcMessage::Capture {
+                    session_id: options
+                        .session
+                        .clone()
+                        .unwrap_or_else(|| capture.session_id.clone()),
+                    timestamp: capture.timestamp,
+                    command: capture.command.clone(),
+                    output: capture.output.clone(),
+                    exit_code: capture.exit_code,
+                    cwd: capture.cwd.clone(),
+                    context: capture.context.clone().map(Box::new),
+                    dry_run: false,
+                    handoff: None,
+                };
+                match client.send_with_backoff(&message).await {
+                    Ok(response) if response.success => {}
+                    // Resending can't fix these; don't let one block the spool
+                    Ok(response)
+                        if matches!(
+                            response.code,
+                            Some(IpcErrorCode::SessionUnknown | IpcErrorCode::PayloadTooLarge)
+                        ) =>
+                    {
+                        tracing::warn!(
+                            "Upstream refused capture '{}': {}",
+                            capture.command,
+                            response.message.unwrap_or_default()
+                        );
+                    }
+                    Ok(response) => {
+                        let reason = response.message.unwrap_or_default();
+                        return (delivered, Some(YinxError::Daemon(reason)));
+                    }
+                    Err(e) => return (delivered, Some(e)),
+                }
+            }
+            (batch.len(), None)
+        }
+        Upstream::Tcp(addr) => deliver_rpc(addr, options, batch).await,
+    }
+}
+
+async fn deliver_rpc(
+    addr: &str,
+    options: &AgentOptions,
+    batch: &[(u64, SpooledCapture)],
+) -> (usize, Option<YinxError>) {
+    let io_error = |e: std::io::Error| YinxError::Io {
+        source: e,
+        context: format!("Remote API {} unreachable", addr),
+    };
+    let stream = match TcpStream::connect(addr).await {
+        Ok(stream) => stream,
+        Err(e) => return (0, Some(io_error(e))),
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+
+    let token = options.token.as_deref().unwrap_or_default();
+    let mut requests = vec![json!({
+        "jsonrpc": "2.0", "id": 0, "method": "auth", "params": { "token": token },
+    })];
+    for (i, (_, capture)) in batch.iter().enumerate() {
+        requests.push(json!({
+            "jsonrpc": "2.0",
+            "id": i + 1,
+            "method": "capture.submit",
+            "params": {
+                "session_id": options.session,
+                "timestamp": capture.timestamp,
+                "command": capture.command,
+                "output": capture.output,
+                "exit_code": capture.exit_code,
+                "cwd": capture.cwd,
+                "user": options.user,
+                "context": capture.context,
+            },
+        }));
+    }
+
+    let mut payload = String::new();
+    for request in &requests {
+        payload.push_str(&request.to_string());
+        payload.push('\n');
+    }
+    if let Err(e) = writer.write_all(payload.as_bytes()).await {
+        return (0, Some(io_error(e)));
+    }
+
+    // Responses come back in request order; the first one answers auth
+    for delivered in 0..=batch.len() {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                let e = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+                return (delivered.saturating_sub(1), Some(io_error(e)));
+            }
+            Err(e) => return (delivered.saturating_sub(1), Some(io_error(e))),
+        };
+        let response: Value = serde_json::from_str(&line).unwrap_or(Value::Null);
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            return (
+                delivered.saturating_sub(1),
+                Some(YinxError::Daemon(format!(
+                    "Upstream rejected request: {}",
+                    message
+                ))),
+            );
+        }
+    }
+    (batch.len(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn capture(command: &str) -> SpooledCapture {
+        SpooledCapture {
+            session_id: "default".to_string(),
+            timestamp: 0,
+            command: command.to_string(),
+            output: "out\nput".to_string(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            context: None,
+        }
+    }
+
+    #[test]
+    fn test_spool_resumes_after_ack() {
+        let temp_dir = TempDir::new().unwrap();
+        let spool = Spool::open(temp_dir.path()).unwrap();
+        for command in ["id", "whoami", "ip a"] {
+            spool.push(&capture(command)).unwrap();
+        }
+
+        let batch = spool.pending(2).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[1].1.command, "whoami");
+        spool.ack(batch[0].0).unwrap();
+
+        // A new agent picks up after the acknowledged capture
+        let spool = Spool::open(temp_dir.path()).unwrap();
+        let batch = spool.pending(10).unwrap();
+        let commands: Vec<_> = batch.iter().map(|(_, c)| c.command.as_str()).collect();
+        assert_eq!(commands, vec!["whoami", "ip a"]);
+
+        spool.ack(batch[1].0).unwrap();
+        assert!(spool.pending(10).unwrap().is_empty());
+        assert_eq!(
+            std::fs::metadata(temp_dir.path().join("queue.jsonl"))
+                .unwrap()
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_parse_upstream() {
+        assert_eq!(
+            "10.0.0.5:7701".parse::<Upstream>().unwrap(),
+            Upstream::Tcp("10.0.0.5:7701".to_string())
+        );
+        assert_eq!(
+            "unix:/tmp/yinx.sock".parse::<Upstream>().unwrap(),
+            Upstream::Unix(PathBuf::from("/tmp/yinx.sock"))
+        );
+        assert!("10.0.0.5".parse::<Upstream>().is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! Synthetic capture workload for `yinx bench` and `cargo bench`
+//!
+//! Generates scanner-style output (nmap service scans, gobuster runs with
+//! their progress noise) and times each stage a capture goes through:
+//! filtering, entity extraction, blob writes, the full ingest into a
+//! scratch data directory, and keyword search over what was ingested. The
+//! ingest rate is what the daemon can sustain on this machine with these
+//! patterns, before embeddings.
+
+use crate::daemon::CaptureEvent;
+use crate::error::{Result, YinxError};
+use crate::facade::Yinx;
+use crate::filtering::FilterPipeline;
+use crate::patterns::PatternRegistry;
+use crate::storage::{latency, BlobStore};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Size of the synthetic workload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// Captures generated and ingested
+    pub captures: usize,
+    /// Output lines per capture
+    pub lines: usize,
+    /// Keyword searches run against the ingested captures
+    pub searches: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            captures: 200,
+            lines: 400,
+            searches: 50,
+        }
+    }
+}
+
+/// Timing of one stage over the whole workload
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageTiming {
+    pub name: &'static str,
+    /// Times the stage ran
+    pub iterations: usize,
+    pub total: Duration,
+    /// Median and 95th percentile of a single run
+    pub p50: Duration,
+    pub p95: Duration,
+    /// Output bytes the stage went through
+    pub bytes: usize,
+}
+
+impl StageTiming {
+    fn from_samples(name: &'static str, mut samples: Vec<Duration>, bytes: usize) -> Self {
+        samples.sort_unstable();
+        let at = |pct: usize| {
+            let rank = (samples.len() * pct).div_ceil(100).max(1);
+            samples.get(rank - 1).copied().unwrap_or_default()
+        };
+        Self {
+            name,
+            iterations: samples.len(),
+            total: samples.iter().sum(),
+            p50: at(50),
+            p95: at(95),
+            bytes,
+        }
+    }
+
+    /// Runs per second over the whole stage
+    pub fn per_sec(&self) -> f64 {
+        match self.total.as_secs_f64() {
+            secs if secs > 0.0 => self.iterations as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// Output megabytes per second over the whole stage
+    pub fn mb_per_sec(&self) -> f64 {
+        match self.total.as_secs_f64() {
+            secs if secs > 0.0 => self.bytes as f64 / (1 << 20) as f64 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Stage timings of a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub options: BenchOptions,
+    pub stages: Vec<StageTiming>,
+}
+
+impl BenchReport {
+    /// Captures per second the full pipeline kept up with
+    pub fn captures_per_sec(&self) -> f64 {
+        self.stages
+            .iter()
+            .find(|stage| stage.name == INGEST)
+            .map(StageTiming::per_sec)
+            .unwrap_or_default()
+    }
+}
+
+const INGEST: &str = "ingest";
+
+/// A generated capture
+#[derive(Debug, Clone)]
+pub struct SyntheticCapture {
+    pub command: String,
+    pub tool: &'static str,
+    pub output: String,
+}
+
+/// `count` captures of about `lines` lines each, the same every time
+pub fn synthetic_captures(count: usize, lines: usize) -> Vec<SyntheticCapture> {
+    (0..count)
+        .map(|i| {
+            let host = format!("10.10.{}.{}", i / 250 % 250, i % 250 + 1);
+            if i % 2 == 0 {
+                SyntheticCapture {
+                    command: format!("nmap -sV -p- {}", host),
+                    tool: "nmap",
+                    output: nmap_output(&host, i, lines),
+                }
+            } else {
+                SyntheticCapture {
+                    command: format!("gobuster dir -u http://{} -w common.txt", host),
+                    tool: "gobuster",
+                    output: gobuster_output(&host, i, lines),
+                }
+            }
+        })
+        .collect()
+}
+
+const SERVICES: [(&str, &str); 8] = [
+    ("ssh", "OpenSSH 8.2p1 Ubuntu 4ubuntu0.5"),
+    ("http", "Apache httpd 2.4.41"),
+    ("smb", "Samba smbd 4.6.2"),
+    ("mysql", "MySQL 5.7.33"),
+    ("ftp", "vsftpd 3.0.3"),
+    ("rdp", "Microsoft Terminal Services"),
+    ("ldap", "Microsoft Windows Active Directory LDAP"),
+    ("http-proxy", "Squid http proxy 4.10"),
+];
+
+fn nmap_output(host: &str, seed: usize, lines: usize) -> String {
+    let mut out = format!(
+        "Starting Nmap 7.94 ( https://nmap.org ) at 2024-03-01 12:00 UTC\n\
+         Nmap scan report for {}\nHost is up (0.041s latency).\n\
+         PORT      STATE    SERVICE VERSION\n",
+        host
+    );
+    for line in 0..lines.saturating_sub(4) {
+        let port = 20 + (seed * 7 + line * 13) % 9000;
+        if line % 10 == 0 {
+            let (service, version) = SERVICES[(seed + line) % SERVICES.len()];
+            out.push_str(&format!("{}/tcp open {} {}\n", port, service, version));
+        } else {
+            out.push_str(&format!("{}/tcp filtered unknown\n", port));
+        }
+    }
+    out
+}
+
+fn gobuster_output(host: &str, seed: usize, lines: usize) -> String {
+    let mut out = format!("Url: http://{}\nWordlist: common.txt\n", host);
+    for line in 0..lines.saturating_sub(2) {
+        if line % 15 == 0 {
+            out.push_str(&format!(
+                "/admin{} (Status: 301) [Size: {}] [--> http://{}/admin{}/]\n",
+                line,
+                178 + seed % 40,
+                host,
+                line
+            ));
+        } else {
+            out.push_str(&format!(
+                "Progress: {} / 4615 ({:.2}%)\n",
+                line * 11,
+                line as f64 * 11.0 / 46.15
+            ));
+        }
+    }
+    out
+}
+
+fn bytes_of(captures: &[SyntheticCapture]) -> usize {
+    captures.iter().map(|c| c.output.len()).sum()
+}
+
+/// Time the three filter tiers over every capture
+pub fn bench_filtering(
+    patterns: Arc<PatternRegistry>,
+    captures: &[SyntheticCapture],
+) -> Result<StageTiming> {
+    let pipeline = FilterPipeline::new(patterns);
+    let mut samples = Vec::with_capacity(captures.len());
+    for capture in captures {
+        let started = Instant::now();
+        pipeline.process_tool_capture("bench", Some(capture.tool), &capture.output)?;
+        samples.push(started.elapsed());
+    }
+    Ok(StageTiming::from_samples(
+        "filtering",
+        samples,
+        bytes_of(captures),
+    ))
+}
+
+/// Time entity extraction over every capture
+pub fn bench_extraction(patterns: &PatternRegistry, captures: &[SyntheticCapture]) -> StageTiming {
+    let samples = captures
+        .iter()
+        .map(|capture| {
+            let started = Instant::now();
+            let tool = patterns.tool(capture.tool);
+            std::hint::black_box(patterns.extract_entities_for_tool(&capture.output, tool));
+            started.elapsed()
+        })
+        .collect();
+    StageTiming::from_samples("extraction", samples, bytes_of(captures))
+}
+
+/// Time writing every capture's output to a blob store in `dir`
+pub fn bench_blob_writes(dir: &Path, captures: &[SyntheticCapture]) -> Result<StageTiming> {
+    let store = BlobStore::new(dir.to_path_buf(), 1024)?;
+    let mut samples = Vec::with_capacity(captures.len());
+    for capture in captures {
+        let started = Instant::now();
+        store.write(capture.output.as_bytes())?;
+        samples.push(started.elapsed());
+    }
+    Ok(StageTiming::from_samples(
+        "blob writes",
+        samples,
+        bytes_of(captures),
+    ))
+}
+
+/// Time ingesting every capture into a fresh data directory in `dir`, then
+/// `searches` keyword searches over the result
+pub fn bench_ingest_and_search(
+    dir: &Path,
+    patterns: PatternRegistry,
+    captures: &[SyntheticCapture],
+    searches: usize,
+) -> Result<(StageTiming, StageTiming)> {
+    let yinx = Yinx::with_patterns(dir, patterns)?;
+    let session = yinx.start_session("bench")?;
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to start async runtime".to_string(),
+    })?;
+
+    let mut samples = Vec::with_capacity(captures.len());
+    for (i, capture) in captures.iter().enumerate() {
+        let event = CaptureEvent {
+            session_id: session.id.to_string(),
+            timestamp: 1_700_000_000 + i as i64,
+            command: capture.command.clone(),
+            output: capture.output.clone(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        };
+        let started = Instant::now();
+        runtime.block_on(yinx.ingest(event))?;
+        samples.push(started.elapsed());
+    }
+    let ingest = StageTiming::from_samples(INGEST, samples, bytes_of(captures));
+
+    const QUERIES: [&str; 5] = ["ssh", "apache httpd", "admin", "samba smbd", "vsftpd"];
+    let mut samples = Vec::with_capacity(searches);
+    for i in 0..searches {
+        let started = Instant::now();
+        std::hint::black_box(yinx.search(QUERIES[i % QUERIES.len()], 10)?);
+        samples.push(started.elapsed());
+    }
+    let search = StageTiming::from_samples("keyword search", samples, 0);
+
+    Ok((ingest, search))
+}
+
+/// Run every stage on a workload of `options`' size in a scratch directory
+pub fn run(options: BenchOptions, patterns: PatternRegistry) -> Result<BenchReport> {
+    let _paused = latency::pause();
+    let scratch = tempfile::tempdir().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to create scratch directory".to_string(),
+    })?;
+    let captures = synthetic_captures(options.captures, options.lines);
+    let shared = Arc::new(patterns.clone());
+
+    let filtering = bench_filtering(shared.clone(), &captures)?;
+    let extraction = bench_extraction(&shared, &captures);
+    let blobs = bench_blob_writes(&scratch.path().join("blobs"), &captures)?;
+    let (ingest, search) = bench_ingest_and_search(
+        &scratch.path().join("data"),
+        patterns,
+        &captures,
+        options.searches,
+    )?;
+
+    Ok(BenchReport {
+        options,
+        stages: vec![filtering, extraction, blobs, ingest, search],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_captures_are_stable() {
+        let first = synthetic_captures(4, 50);
+        let second = synthetic_captures(4, 50);
+        assert_eq!(first.len(), 4);
+        assert_eq!(first[1].tool, "gobuster");
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a.output, b.output);
+            assert_eq!(a.output.lines().count(), 50);
+        }
+    }
+
+    #[test]
+    fn test_run_times_every_stage() {
+        let options = BenchOptions {
+            captures: 6,
+            lines: 40,
+            searches: 3,
+        };
+        let report = run(options, PatternRegistry::bundled().unwrap()).unwrap();
+
+        let names: Vec<_> = report.stages.iter().map(|s| s.name).collect();
+        assert_eq!(
+            names,
+            [
+                "filtering",
+                "extraction",
+                "blob writes",
+                "ingest",
+                "keyword search"
+            ]
+        );
+        assert_eq!(report.stages[3].iterations, 6);
+        assert_eq!(report.stages[4].iterations, 3);
+        assert!(report.captures_per_sec() > 0.0);
+    }
+}
@@ -0,0 +1,399 @@
+//! BloodHound export of Active Directory entities
+//!
+//! Domains, accounts, computers and SPNs extracted from a session are
+//! written as a BloodHound OpenGraph file (`{"graph": {"nodes", "edges"}}`).
+//! Node ids are BloodHound-style names (`ALICE@CORP.LOCAL`) and edges match
+//! their ends by name, so the file can be uploaded next to SharpHound data
+//! and attach to the objects it already holds. Accounts with a cracked
+//! password are marked as owned.
+
+use crate::error::Result;
+use crate::storage::StorageManager;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Entity types the export reads
+pub const AD_ENTITY_TYPES: &[&str] = &["ad_domain", "ad_user", "spn", "hostname", "email"];
+
+/// Export file root
+#[derive(Debug, Serialize)]
+pub struct BloodHoundGraph {
+    pub metadata: Metadata,
+    pub graph: Graph,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Metadata {
+    pub source_kind: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Node {
+    pub id: String,
+    pub kinds: Vec<String>,
+    pub properties: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Edge {
+    pub kind: String,
+    pub start: EdgeEnd,
+    pub end: EdgeEnd,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EdgeEnd {
+    pub value: String,
+    pub match_by: String,
+}
+
+impl EdgeEnd {
+    fn name(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            match_by: "name".to_string(),
+        }
+    }
+}
+
+/// Objects collected before they are turned into nodes
+#[derive(Debug, Default)]
+struct Directory {
+    domains: BTreeSet<String>,
+    /// `ALICE@CORP.LOCAL` -> SPNs registered to the account
+    users: BTreeMap<String, BTreeSet<String>>,
+    computers: BTreeSet<String>,
+    /// (account, computer) pairs from MSSQLSvc SPNs
+    sql_admins: BTreeSet<(String, String)>,
+}
+
+impl Directory {
+    /// Fully qualified domain for a NetBIOS name, when one is known
+    fn qualify(&self, netbios: &str) -> String {
+        let netbios = netbios.to_ascii_uppercase();
+        self.domains
+            .iter()
+            .find(|domain| domain.split('.').next() == Some(netbios.as_str()))
+            .cloned()
+            .unwrap_or(netbios)
+    }
+
+    /// AD domain a host name belongs to
+    fn domain_of(&self, host: &str) -> Option<&String> {
+        let host = host.to_ascii_uppercase();
+        self.domains
+            .iter()
+            .find(|domain| host.ends_with(&format!(".{}", domain)))
+    }
+
+    /// Host name as a computer in a known domain; bare names need a single domain
+    fn computer(&self, host: &str) -> Option<String> {
+        let host = host.trim_end_matches('.').to_ascii_uppercase();
+        if host.contains('.') {
+            return self.domain_of(&host).map(|_| host);
+        }
+        match self.domains.len() {
+            1 => self
+                .domains
+                .first()
+                .map(|domain| format!("{}.{}", host, domain)),
+            _ => None,
+        }
+    }
+
+    fn add_account(&mut self, name: &str, domain: &str) {
+        let name = name.to_ascii_uppercase();
+        match name.strip_suffix('$') {
+            Some(computer) => {
+                self.computers.insert(format!("{}.{}", computer, domain));
+            }
+            None => {
+                self.users
+                    .entry(format!("{}@{}", name, domain))
+                    .or_default();
+            }
+        }
+    }
+}
+
+/// Build the graph from `(type, value, context)` entities
+///
+/// `owned` holds user names of accounts with a cracked password, in any of
+/// the `user`, `DOMAIN\user` or `user@domain` forms.
+pub fn build_graph(entities: &[(String, String, String)], owned: &[String]) -> BloodHoundGraph {
+    let mut directory = Directory::default();
+    let of_type = |kind: &'static str| {
+        entities
+            .iter()
+            .filter(move |(entity_type, _, _)| entity_type == kind)
+    };
+
+    // Domains first, so accounts and hosts can be placed in them
+    for (_, value, _) in of_type("ad_domain") {
+        if let Some(domain) = domain_name(value) {
+            directory.domains.insert(domain);
+        }
+    }
+    for (_, value, _) in of_type("spn") {
+        if let Some((_, host, _)) = split_spn(value) {
+            if let Some((_, domain)) = host.split_once('.') {
+                directory.domains.insert(domain.to_ascii_uppercase());
+            }
+        }
+    }
+
+    for (_, value, _) in of_type("ad_user") {
+        if let Some((netbios, name)) = value.split_once('\\') {
+            let domain = directory.qualify(netbios);
+            directory.add_account(name, &domain);
+        }
+    }
+    for (_, value, _) in of_type("email") {
+        if let Some((name, domain)) = value.split_once('@') {
+            let domain = domain.to_ascii_uppercase();
+            if directory.domains.contains(&domain) {
+                directory.add_account(name, &domain);
+            }
+        }
+    }
+    for (_, value, _) in of_type("hostname") {
+        if value.contains('.') {
+            if let Some(computer) = directory.computer(value) {
+                directory.computers.insert(computer);
+            }
+        }
+    }
+    for (_, value, context) in of_type("spn") {
+        let Some((service, host, _)) = split_spn(value) else {
+            continue;
+        };
+        let Some(computer) = directory.computer(host) else {
+            continue;
+        };
+        directory.computers.insert(computer.clone());
+        let Some(account) = spn_account(value, context) else {
+            continue;
+        };
+        let domain = directory.domain_of(&computer).cloned().unwrap_or_default();
+        let user = format!("{}@{}", account.to_ascii_uppercase(), domain);
+        directory
+            .users
+            .entry(user.clone())
+            .or_default()
+            .insert(value.clone());
+        if service == "MSSQLSvc" {
+            directory.sql_admins.insert((user, computer));
+        }
+    }
+
+    let owned: BTreeSet<String> = owned.iter().map(|name| account_name(name)).collect();
+    let mut graph = Graph::default();
+    for domain in &directory.domains {
+        graph.nodes.push(node(domain, "Domain", domain, Vec::new()));
+    }
+    for (user, spns) in &directory.users {
+        let (name, domain) = user.split_once('@').unwrap_or((user, ""));
+        let mut properties = vec![("samaccountname", name.to_lowercase().into())];
+        if !spns.is_empty() {
+            properties.push(("hasspn", true.into()));
+            properties.push((
+                "serviceprincipalnames",
+                spns.iter().cloned().collect::<Vec<_>>().into(),
+            ));
+        }
+        if owned.contains(name) {
+            properties.push(("owned", true.into()));
+        }
+        graph.nodes.push(node(user, "User", domain, properties));
+    }
+    for computer in &directory.computers {
+        let domain = computer.split_once('.').map_or("", |(_, domain)| domain);
+        graph
+            .nodes
+            .push(node(computer, "Computer", domain, Vec::new()));
+    }
+    for (user, computer) in &directory.sql_admins {
+        graph.edges.push(Edge {
+            kind: "SQLAdmin".to_string(),
+            start: EdgeEnd::name(user),
+            end: EdgeEnd::name(computer),
+        });
+    }
+
+    BloodHoundGraph {
+        metadata: Metadata {
+            source_kind: "Yinx".to_string(),
+        },
+        graph,
+    }
+}
+
+/// Build the graph for one session
+pub fn export_session(storage: &StorageManager, session_id: &str) -> Result<BloodHoundGraph> {
+    let mut entities = Vec::new();
+    for capture in storage.database.get_session_captures(session_id)? {
+        for entity in storage.database.get_entities_for_capture(capture.id)? {
+            if AD_ENTITY_TYPES.contains(&entity.entity_type.as_str()) {
+                entities.push((
+                    entity.entity_type,
+                    entity.value,
+                    entity.context.unwrap_or_default(),
+                ));
+            }
+        }
+    }
+    let owned: Vec<String> = storage
+        .database
+        .list_credentials(Some(session_id))?
+        .into_iter()
+        .filter_map(|credential| credential.username)
+        .collect();
+    Ok(build_graph(&entities, &owned))
+}
+
+fn node(id: &str, kind: &str, domain: &str, properties: Vec<(&str, serde_json::Value)>) -> Node {
+    let mut map: BTreeMap<String, serde_json::Value> = properties
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+    map.insert("name".to_string(), id.into());
+    if !domain.is_empty() {
+        map.insert("domain".to_string(), domain.into());
+    }
+    Node {
+        id: id.to_string(),
+        kinds: vec![kind.to_string()],
+        properties: map,
+    }
+}
+
+/// `CORP.LOCAL` from `DC=corp,DC=local` or nxc's `(domain:corp.local)`
+fn domain_name(value: &str) -> Option<String> {
+    let domain = match value.strip_prefix("(domain:") {
+        Some(rest) => rest.trim_end_matches(')').to_string(),
+        None => value
+            .split(',')
+            .filter_map(|part| {
+                let (key, value) = part.trim().split_once('=')?;
+                (key.eq_ignore_ascii_case("dc") && !value.is_empty()).then_some(value)
+            })
+            .collect::<Vec<_>>()
+            .join("."),
+    };
+    (domain.contains('.')).then(|| domain.to_ascii_uppercase())
+}
+
+/// `MSSQLSvc/sql01.corp.local:1433` -> (service, host, port)
+fn split_spn(spn: &str) -> Option<(&str, &str, Option<&str>)> {
+    let (service, target) = spn.split_once('/')?;
+    let (host, port) = match target.split_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (target, None),
+    };
+    Some((service, host, port))
+}
+
+/// Account an SPN is registered to, from tabular output like GetUserSPNs'
+/// (`MSSQLSvc/sql01.corp.local:1433  svc_sql  CN=...`)
+fn spn_account(spn: &str, context: &str) -> Option<String> {
+    let (_, rest) = context.split_once(spn)?;
+    let name = rest.lines().next()?.split_whitespace().next()?;
+    let is_account = !name.contains(['/', '=', ',', ':'])
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '$'));
+    is_account.then(|| account_name(name))
+}
+
+/// Bare upper-case account name from `user`, `DOMAIN\user` or `user@domain`
+fn account_name(name: &str) -> String {
+    let name = name.rsplit('\\').next().unwrap_or(name);
+    let name = name.split('@').next().unwrap_or(name);
+    name.to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(entity_type: &str, value: &str, context: &str) -> (String, String, String) {
+        (
+            entity_type.to_string(),
+            value.to_string(),
+            context.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_build_graph() {
+        let entities = vec![
+            entity("ad_domain", "DC=corp,DC=local", ""),
+            entity("ad_user", "CORP\\alice", ""),
+            entity("ad_user", "CORP\\DC01$", ""),
+            entity("email", "bob@corp.local", ""),
+            entity("email", "someone@example.com", ""),
+            entity("hostname", "ws01.corp.local", ""),
+            entity("hostname", "www.example.com", ""),
+            entity(
+                "spn",
+                "MSSQLSvc/sql01.corp.local:1433",
+                "----\nMSSQLSvc/sql01.corp.local:1433  svc_sql  CN=SQL Admins,CN=Users",
+            ),
+            entity("spn", "HTTP/web01", "HTTP/web01"),
+        ];
+        let graph = build_graph(&entities, &["CORP\\Alice".to_string()]).graph;
+
+        let ids: Vec<(&str, &str)> = graph
+            .nodes
+            .iter()
+            .map(|n| (n.kinds[0].as_str(), n.id.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("Domain", "CORP.LOCAL"),
+                ("User", "ALICE@CORP.LOCAL"),
+                ("User", "BOB@CORP.LOCAL"),
+                ("User", "SVC_SQL@CORP.LOCAL"),
+                ("Computer", "DC01.CORP.LOCAL"),
+                ("Computer", "SQL01.CORP.LOCAL"),
+                ("Computer", "WEB01.CORP.LOCAL"),
+                ("Computer", "WS01.CORP.LOCAL"),
+            ]
+        );
+
+        let alice = &graph.nodes[1].properties;
+        assert_eq!(alice["owned"], true);
+        assert_eq!(alice["domain"], "CORP.LOCAL");
+        let svc = &graph.nodes[3].properties;
+        assert_eq!(svc["hasspn"], true);
+        assert_eq!(
+            svc["serviceprincipalnames"][0],
+            "MSSQLSvc/sql01.corp.local:1433"
+        );
+
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].kind, "SQLAdmin");
+        assert_eq!(graph.edges[0].start.value, "SVC_SQL@CORP.LOCAL");
+        assert_eq!(graph.edges[0].end.value, "SQL01.CORP.LOCAL");
+    }
+
+    #[test]
+    fn test_domain_name() {
+        assert_eq!(
+            domain_name("DC=htb, DC=local"),
+            Some("HTB.LOCAL".to_string())
+        );
+        assert_eq!(
+            domain_name("(domain:corp.example.org)"),
+            Some("CORP.EXAMPLE.ORG".to_string())
+        );
+        assert_eq!(domain_name("DC=local,DC="), None);
+    }
+}
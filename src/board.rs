@@ -0,0 +1,236 @@
+//! Host status board
+//!
+//! Tracks how far along each target is, from first sighting to done.
+//! Commands put the hosts and ranges they name on the board as soon as they
+//! start, captures move hosts forward automatically, and `yinx board set`
+//! moves them anywhere by hand.
+
+use crate::entities::{Entity, EntityExtractor};
+use crate::proof::ProofArtifact;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Column a host sits in, in order of progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostStage {
+    NotStarted,
+    Enumerating,
+    Foothold,
+    Root,
+    Done,
+}
+
+impl HostStage {
+    pub const ALL: [HostStage; 5] = [
+        Self::NotStarted,
+        Self::Enumerating,
+        Self::Foothold,
+        Self::Root,
+        Self::Done,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotStarted => "not-started",
+            Self::Enumerating => "enumerating",
+            Self::Foothold => "foothold",
+            Self::Root => "root",
+            Self::Done => "done",
+        }
+    }
+
+    /// Column heading
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::NotStarted => "Not started",
+            Self::Enumerating => "Enumerating",
+            Self::Foothold => "Foothold",
+            Self::Root => "Root",
+            Self::Done => "Done",
+        }
+    }
+}
+
+impl FromStr for HostStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase().replace(['_', ' '], "-");
+        Self::ALL
+            .into_iter()
+            .find(|stage| stage.as_str() == normalized)
+            .ok_or_else(|| {
+                format!(
+                    "unknown host status '{}' (expected one of: {})",
+                    s,
+                    Self::ALL.map(|stage| stage.as_str()).join(", ")
+                )
+            })
+    }
+}
+
+/// Stages a capture suggests for the hosts it involves
+///
+/// Hosts named on the command line are being enumerated; hosts only seen in
+/// the output are not started. A credential in the output of a command aimed
+/// at a host means a foothold on it, and a proof file means a foothold
+/// (local/user proof) or root (anything else).
+pub fn observe(
+    targets: &[Entity],
+    entities: &[Entity],
+    proof: Option<&ProofArtifact>,
+) -> BTreeMap<String, HostStage> {
+    let mut stages = BTreeMap::new();
+    let mut suggest = |host: &str, stage: HostStage| {
+        let current = stages.entry(host.to_string()).or_insert(stage);
+        *current = (*current).max(stage);
+    };
+
+    for host in hosts(entities) {
+        suggest(host, HostStage::NotStarted);
+    }
+    let credential = entities
+        .iter()
+        .any(|e| e.entity_type.starts_with("credential"));
+    for host in hosts(targets) {
+        suggest(
+            host,
+            if credential {
+                HostStage::Foothold
+            } else {
+                HostStage::Enumerating
+            },
+        );
+    }
+
+    if let Some(proof) = proof {
+        if let Some(host) = &proof.host {
+            let file = proof.file.to_ascii_lowercase();
+            let stage = if file == "local.txt" || file == "user.txt" {
+                HostStage::Foothold
+            } else {
+                HostStage::Root
+            };
+            suggest(host, stage);
+        }
+    }
+    stages
+}
+
+/// Hosts and address ranges named on a command line
+#[derive(Debug, Clone, Default)]
+pub struct CommandTargets {
+    /// Addresses and hostnames outside any range
+    pub hosts: Vec<Entity>,
+    /// IPv4 CIDR ranges such as `10.10.10.0/24`
+    pub ranges: Vec<String>,
+}
+
+impl CommandTargets {
+    /// Split a command into the ranges it names and the hosts in the rest,
+    /// so `10.10.10.0/24` isn't read as the host `10.10.10.0`
+    pub fn parse(command: &str, extractor: &EntityExtractor) -> Self {
+        let (ranges, rest): (Vec<&str>, Vec<&str>) =
+            command.split_whitespace().partition(|word| is_cidr(word));
+        Self {
+            hosts: extractor.extract(&rest.join(" ")),
+            ranges: ranges.into_iter().map(str::to_string).collect(),
+        }
+    }
+
+    /// Stages for a command that has started but returned nothing yet:
+    /// named hosts are being enumerated, ranges are registered as not started
+    pub fn intent(&self) -> BTreeMap<String, HostStage> {
+        let mut stages = observe(&self.hosts, &[], None);
+        for range in &self.ranges {
+            stages.entry(range.clone()).or_insert(HostStage::NotStarted);
+        }
+        stages
+    }
+}
+
+/// Whether a word is an IPv4 CIDR range
+fn is_cidr(word: &str) -> bool {
+    word.split_once('/').is_some_and(|(address, bits)| {
+        address.parse::<std::net::Ipv4Addr>().is_ok()
+            && bits.parse::<u8>().is_ok_and(|bits| bits <= 32)
+    })
+}
+
+/// Non-loopback addresses and hostnames among some entities
+fn hosts(entities: &[Entity]) -> impl Iterator<Item = &str> {
+    entities
+        .iter()
+        .filter(|e| match e.entity_type.as_str() {
+            "ip_address" => !e.value.starts_with("127."),
+            "hostname" => true,
+            _ => false,
+        })
+        .map(|e| e.value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(entity_type: &str, value: &str) -> Entity {
+        Entity {
+            entity_type: entity_type.to_string(),
+            value: value.to_string(),
+            context: String::new(),
+            start: 0,
+            end: 0,
+            confidence: 0.9,
+            should_redact: false,
+        }
+    }
+
+    #[test]
+    fn test_stage_names() {
+        assert_eq!("Not_Started".parse(), Ok(HostStage::NotStarted));
+        assert_eq!("foothold".parse(), Ok(HostStage::Foothold));
+        assert!("pwned".parse::<HostStage>().is_err());
+        assert!(HostStage::Root > HostStage::Foothold);
+    }
+
+    #[test]
+    fn test_observe_heuristics() {
+        let target = vec![entity("ip_address", "10.0.0.5")];
+        let output = vec![
+            entity("ip_address", "10.0.0.7"),
+            entity("ip_address", "127.0.0.1"),
+        ];
+        let stages = observe(&target, &output, None);
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages["10.0.0.5"], HostStage::Enumerating);
+        assert_eq!(stages["10.0.0.7"], HostStage::NotStarted);
+
+        let output = vec![entity("credential_password", "Winter2025!")];
+        assert_eq!(
+            observe(&target, &output, None)["10.0.0.5"],
+            HostStage::Foothold
+        );
+
+        let proof = ProofArtifact {
+            file: "proof.txt".to_string(),
+            value: "4f1b2c3d4e5f60718293a4b5c6d7e8f9".to_string(),
+            host: Some("10.0.0.9".to_string()),
+        };
+        assert_eq!(observe(&[], &[], Some(&proof))["10.0.0.9"], HostStage::Root);
+    }
+
+    #[test]
+    fn test_command_intent() {
+        let extractor = EntityExtractor::new(crate::patterns::PatternRegistry::bundled().unwrap());
+        let targets = CommandTargets::parse("nmap -sn 10.10.10.0/24 10.10.11.7", &extractor);
+        assert_eq!(targets.ranges, vec!["10.10.10.0/24".to_string()]);
+
+        let stages = targets.intent();
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages["10.10.10.0/24"], HostStage::NotStarted);
+        assert_eq!(stages["10.10.11.7"], HostStage::Enumerating);
+        assert!(!stages.contains_key("10.10.10.0"));
+    }
+}
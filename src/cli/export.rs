@@ -0,0 +1,144 @@
+//! Table export for query results and entity listings
+//!
+//! `yinx query --export csv|md` and `yinx entities ... --export` write
+//! results as CSV for spreadsheets or as a Markdown table that can be pasted
+//! straight into a report.
+//! `yinx export --format` picks what a session export is written as.
+
+use std::str::FromStr;
+
+/// Table format for `--export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "md" | "markdown" => Ok(Self::Markdown),
+            _ => Err(format!(
+                "unknown export format '{}' (expected csv or md)",
+                s
+            )),
+        }
+    }
+}
+
+/// Format for `yinx export --format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionExportFormat {
+    /// Active Directory entities as BloodHound OpenGraph JSON
+    BloodHound,
+    /// Host → service → finding notes as a CherryTree document
+    CherryTree,
+    /// The same notes as an OPML outline (XMind, outliners)
+    Opml,
+}
+
+impl SessionExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BloodHound => "bloodhound",
+            Self::CherryTree => "cherrytree",
+            Self::Opml => "opml",
+        }
+    }
+}
+
+impl FromStr for SessionExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bloodhound" | "bh" => Ok(Self::BloodHound),
+            "cherrytree" | "ctd" => Ok(Self::CherryTree),
+            "opml" => Ok(Self::Opml),
+            _ => Err(format!(
+                "unknown export format '{}' (expected bloodhound, cherrytree or opml)",
+                s
+            )),
+        }
+    }
+}
+
+/// Render a header row and data rows in `format`
+pub fn render_table(format: ExportFormat, headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    match format {
+        ExportFormat::Csv => {
+            let line = |cells: Vec<String>| cells.join(",") + "\n";
+            out.push_str(&line(headers.iter().map(|h| csv_field(h)).collect()));
+            for row in rows {
+                out.push_str(&line(row.iter().map(|c| csv_field(c)).collect()));
+            }
+        }
+        ExportFormat::Markdown => {
+            let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+            out.push_str(&line(headers.iter().map(|h| md_cell(h)).collect()));
+            out.push_str(&line(headers.iter().map(|_| "---".to_string()).collect()));
+            for row in rows {
+                out.push_str(&line(row.iter().map(|c| md_cell(c)).collect()));
+            }
+        }
+    }
+    out
+}
+
+/// Quote a CSV field when it needs it (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Keep a value on one table row
+fn md_cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_table() {
+        let rows = vec![
+            vec![
+                "10.0.0.5".to_string(),
+                "signing: disabled, \"yes\"".to_string(),
+            ],
+            vec!["10.0.0.7".to_string(), "a|b\nc".to_string()],
+        ];
+
+        let csv = render_table(ExportFormat::Csv, &["host", "text"], &rows);
+        assert_eq!(
+            csv,
+            "host,text\n10.0.0.5,\"signing: disabled, \"\"yes\"\"\"\n10.0.0.7,\"a|b\nc\"\n"
+        );
+
+        let md = render_table(ExportFormat::Markdown, &["host", "text"], &rows);
+        assert_eq!(
+            md.lines().collect::<Vec<_>>(),
+            vec![
+                "| host | text |",
+                "| --- | --- |",
+                "| 10.0.0.5 | signing: disabled, \"yes\" |",
+                "| 10.0.0.7 | a\\|b<br>c |",
+            ]
+        );
+        assert_eq!("MD".parse(), Ok(ExportFormat::Markdown));
+        assert!("xlsx".parse::<ExportFormat>().is_err());
+        assert_eq!("BloodHound".parse(), Ok(SessionExportFormat::BloodHound));
+        assert_eq!("ctd".parse(), Ok(SessionExportFormat::CherryTree));
+    }
+}
@@ -1,5 +1,12 @@
 //! CLI command definitions and parsing
+pub mod export;
+pub mod output;
+
+use crate::board::HostStage;
+use crate::sync::ConflictStrategy;
+use crate::tickets::TicketTarget;
 use clap::{Parser, Subcommand};
+use export::{ExportFormat, SessionExportFormat};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -21,6 +28,10 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -36,6 +47,14 @@ pub enum Commands {
         /// Profile to use (e.g., "exam", "accuracy", "fast")
         #[arg(short, long)]
         profile: Option<String>,
+
+        /// Engagement deadline, e.g. 2025-07-01T08:00 (local time) or 24h
+        #[arg(long)]
+        deadline: Option<String>,
+
+        /// Restart the daemon if it crashes (daemon.supervisor.enabled)
+        #[arg(long)]
+        supervise: bool,
     },
 
     /// Stop the Yinx daemon
@@ -44,6 +63,12 @@ pub enum Commands {
     /// Show daemon and current session status
     Status,
 
+    /// Run the daemon as a login service
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+
     /// Query captured data using semantic and keyword search
     Query {
         /// Search query text
@@ -57,9 +82,117 @@ pub enum Commands {
         #[arg(short, long)]
         tool: Option<String>,
 
-        /// Show results in JSON format
+        /// Show only results from commands run against this host (matches
+        /// the parsed target, including CIDR ranges covering it)
         #[arg(long)]
-        json: bool,
+        target: Option<String>,
+
+        /// Show only evidence about this host: chunks of its own section of
+        /// a multi-host scan or of captures aimed at it alone, not captures
+        /// that merely mention it
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Show only evidence about public addresses announced by this AS
+        /// (AS13335 or 13335), as annotated from `[enrichment.asn]`
+        #[arg(long, value_name = "AS")]
+        asn: Option<String>,
+
+        /// Show only evidence about public addresses in this country (US)
+        #[arg(long)]
+        country: Option<String>,
+
+        /// Show only evidence about public addresses whose AS organisation
+        /// contains this
+        #[arg(long)]
+        org: Option<String>,
+
+        /// Show only results captured under this context, as KEY=VALUE with
+        /// KEY one of git_root, branch, venv, vpn or a recorded environment
+        /// variable (repeatable; VALUE matches as a substring)
+        #[arg(long = "context", value_name = "KEY=VALUE")]
+        context: Vec<String>,
+
+        /// Show only results typed in this terminal: a tty (pts/3), a
+        /// hostname, or both as host:pts/3
+        #[arg(long)]
+        terminal: Option<String>,
+
+        /// Show only what was known at this time: results from captures
+        /// taken up to it, as Unix seconds, RFC 3339, `2025-07-01 14:30` or
+        /// an interval ago (e.g. 2h)
+        #[arg(long, value_name = "TIME")]
+        as_of: Option<String>,
+
+        /// Write results as a table: csv or md
+        #[arg(long)]
+        export: Option<ExportFormat>,
+
+        /// File to write the export to (default: stdout)
+        #[arg(short, long, requires = "export")]
+        output: Option<PathBuf>,
+
+        /// Columns to export, comma-separated (score, capture_id, session_id,
+        /// timestamp, tool, command, hosts, sections, text)
+        #[arg(long, value_delimiter = ',', requires = "export")]
+        columns: Vec<String>,
+
+        /// Show how each result was ranked and why other candidates were dropped
+        #[arg(long, conflicts_with = "export")]
+        explain: bool,
+
+        /// Search the vector index harder for better recall, at some latency
+        /// (retrieval.hnsw_ef_search_accurate)
+        #[arg(long, conflicts_with = "fast")]
+        accurate: bool,
+
+        /// Search the vector index faster, possibly missing near matches
+        /// (retrieval.hnsw_ef_search_fast)
+        #[arg(long)]
+        fast: bool,
+    },
+
+    /// Search the full stored output of every capture for a regular
+    /// expression, unranked
+    Grep {
+        /// Regular expression matched against each output line
+        pattern: String,
+
+        /// Only search this session (ID or name)
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Only search captures of this tool
+        #[arg(short, long)]
+        tool: Option<String>,
+
+        /// Match case-insensitively
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+
+        /// Threads scanning output (default: one per CPU)
+        #[arg(short, long, default_value = "0")]
+        jobs: usize,
+
+        /// Matched lines held in memory at once (e.g. 64MB); matches past it
+        /// are counted but not shown
+        #[arg(long, default_value = "64MB")]
+        max_memory: String,
+    },
+
+    /// Follow incoming captures and show how each one is filtered
+    Watch {
+        /// Only show captures from this session (ID or name)
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Lines to show per tier for each capture
+        #[arg(long, default_value = "10")]
+        max_lines: usize,
+
+        /// Polling interval in milliseconds
+        #[arg(long, default_value = "500")]
+        interval: u64,
     },
 
     /// Ask a question with optional LLM assistance
@@ -74,6 +207,10 @@ pub enum Commands {
         /// Number of context chunks to retrieve
         #[arg(short = 'n', long, default_value = "20")]
         context_size: usize,
+
+        /// Session ID or name (defaults to current session)
+        #[arg(short, long)]
+        session: Option<String>,
     },
 
     /// Generate a penetration test report
@@ -83,16 +220,21 @@ pub enum Commands {
         output: Option<PathBuf>,
 
         /// Report format
-        #[arg(short, long, value_parser = ["markdown", "html", "json"], default_value = "markdown")]
+        #[arg(short, long, value_parser = ["markdown", "json"], default_value = "markdown")]
         format: String,
 
         /// Session ID or name (defaults to current session)
         #[arg(short, long)]
         session: Option<String>,
 
-        /// Include evidence files in export
+        /// List evidence files in the report and write a hash manifest
+        /// (manifest.json) for chain of custody
         #[arg(long)]
         include_evidence: bool,
+
+        /// Sign the evidence manifest with this minisign secret key
+        #[arg(long, value_name = "KEY", requires = "include_evidence")]
+        sign: Option<PathBuf>,
     },
 
     /// Export session data for sharing or backup
@@ -107,6 +249,356 @@ pub enum Commands {
         /// Include vector and keyword indexes
         #[arg(long)]
         include_indexes: bool,
+
+        /// Write a different format instead of an archive (bloodhound, cherrytree, opml)
+        #[arg(long)]
+        format: Option<SessionExportFormat>,
+
+        /// Encrypt the archive with age under a passphrase it prompts for
+        #[arg(long, conflicts_with = "format")]
+        encrypt: bool,
+
+        /// Encrypt the archive with age to this public key (repeatable)
+        #[arg(
+            short,
+            long = "recipient",
+            value_name = "KEY",
+            conflicts_with = "format"
+        )]
+        recipients: Vec<String>,
+
+        /// Only captures involving this host (repeatable)
+        #[arg(long = "host", value_name = "HOST", conflicts_with = "format")]
+        hosts: Vec<String>,
+
+        /// Only captures of this tool (repeatable)
+        #[arg(long = "tool", value_name = "TOOL", conflicts_with = "format")]
+        tools: Vec<String>,
+
+        /// Only captures with this tag (repeatable)
+        #[arg(long = "tag", value_name = "TAG", conflicts_with = "format")]
+        tags: Vec<String>,
+
+        /// Only captures from this time on: a time (Unix seconds, RFC 3339,
+        /// `2025-07-01 14:30`) or an interval ago (e.g. 24h)
+        #[arg(long, conflicts_with = "format")]
+        since: Option<String>,
+
+        /// Only captures up to this time, in the same forms as --since
+        #[arg(long, conflicts_with = "format")]
+        until: Option<String>,
+    },
+
+    /// Exchange captured data with another yinx store over SSH
+    Sync {
+        /// SSH destination of the peer (e.g. user@attack-vm)
+        #[arg(long)]
+        peer: String,
+
+        /// Path to the yinx binary on the peer
+        #[arg(long, default_value = "yinx")]
+        remote_bin: String,
+    },
+
+    /// Browse captured data in a read-only local web UI
+    Serve {
+        /// Address to serve the web UI on
+        #[arg(
+            long,
+            value_name = "ADDR",
+            num_args = 0..=1,
+            default_missing_value = "127.0.0.1:7777"
+        )]
+        web: Option<String>,
+    },
+
+    /// Spool captures on a pivot host and forward them to the main yinx
+    ///
+    /// Listens on the daemon socket, so don't run the daemon alongside it.
+    Agent {
+        /// Remote API address (HOST:PORT) or forwarded daemon socket (unix:PATH)
+        #[arg(long, value_name = "UPSTREAM")]
+        upstream: String,
+
+        /// File holding the remote API token; YINX_AGENT_TOKEN also works
+        #[arg(long)]
+        token_file: Option<PathBuf>,
+
+        /// Upstream session to record captures in (ID)
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Spool directory (default: <data_dir>/agent)
+        #[arg(long)]
+        spool: Option<PathBuf>,
+
+        /// Captures per delivery batch
+        #[arg(long, default_value_t = 50)]
+        batch_size: usize,
+    },
+
+    /// Purge a value from all captured history
+    Scrub {
+        /// Value to remove (a literal string unless --regex is given)
+        value: String,
+
+        /// Treat the value as a regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Report where the value appears without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Re-run filtering and entity extraction over stored output
+    ///
+    /// Use after editing filters.toml or entities.toml; nothing is recaptured.
+    Refilter {
+        /// Session name or id (default: every session)
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compare how two config profiles would filter a session's output
+    ///
+    /// Replays the stored output under both profiles side by side and shows
+    /// the lines only one of them keeps; nothing is written.
+    Tune {
+        /// Session name or id
+        #[arg(long)]
+        session: String,
+
+        /// Profile to compare from ("default" is the config without overrides)
+        #[arg(long, default_value = "default")]
+        profile_a: String,
+
+        /// Profile to compare against
+        #[arg(long)]
+        profile_b: String,
+
+        /// Differing lines to print per capture and side (0 for all)
+        #[arg(long, default_value = "10")]
+        lines: usize,
+    },
+
+    /// Check the database, blob store and keyword index agree with each other
+    ///
+    /// Exits with an error while any inconsistency is left unrepaired.
+    Verify {
+        /// Fix what can be fixed: drop or detach orphaned rows, recount
+        /// session counters and catch up or rebuild the keyword index
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Manage capture sessions
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+
+    /// Inspect how the filter tiers are performing
+    Filters {
+        #[command(subcommand)]
+        action: FiltersAction,
+    },
+
+    /// Check the pattern files
+    #[command(visible_alias = "pattern")]
+    Patterns {
+        #[command(subcommand)]
+        action: PatternsAction,
+    },
+
+    /// List findings opened by rules, or push them to an issue tracker
+    #[command(visible_alias = "finding")]
+    Findings {
+        /// Only list findings from this session (ID or name)
+        #[arg(short, long)]
+        session: Option<String>,
+
+        #[command(subcommand)]
+        action: Option<FindingsAction>,
+    },
+
+    /// Search extracted entities (hosts, CVEs, credentials...) across sessions
+    #[command(visible_alias = "entity")]
+    Entities {
+        #[command(subcommand)]
+        action: EntitiesAction,
+    },
+
+    /// Rebuild a session's terminal log from its captures
+    Transcript {
+        /// Session ID or name
+        session: String,
+
+        /// Only include captures from this tool
+        #[arg(long)]
+        tool: Option<String>,
+
+        /// Only include captures involving this host
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Only include captures typed in this terminal (pts/3, a hostname,
+        /// or host:pts/3)
+        #[arg(long)]
+        terminal: Option<String>,
+
+        /// Write one transcript per host into the session's evidence directory
+        #[arg(long)]
+        per_host: bool,
+    },
+
+    /// Show the host status board
+    Board {
+        #[command(subcommand)]
+        action: Option<BoardAction>,
+
+        /// Session to show (ID or name); defaults to the active session
+        #[arg(short, long, global = true)]
+        session: Option<String>,
+    },
+
+    /// Map hosts by subnet with scan coverage, or show one host in detail
+    Hosts {
+        #[command(subcommand)]
+        action: Option<HostsAction>,
+
+        /// Prefix length hosts are grouped by
+        #[arg(long, default_value_t = 24, value_parser = clap::value_parser!(u8).range(0..=32))]
+        prefix: u8,
+
+        /// Only use captures from this session (ID or name); defaults to all
+        /// sessions
+        #[arg(short, long, global = true)]
+        session: Option<String>,
+    },
+
+    /// Show the exam checklist of proof files found in captures
+    Proofs {
+        /// Only list proofs from this session (ID or name)
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+
+    /// Summarize engagement activity over an interval (stand-ups, report appendix)
+    Summary {
+        /// How far back to look (e.g. 24h, 90m, 2d)
+        #[arg(long, default_value = "24h")]
+        since: String,
+
+        /// Only summarize this session (ID or name)
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Print Markdown for the report's activity appendix
+        #[arg(long, conflicts_with = "json")]
+        markdown: bool,
+    },
+
+    /// Store the clipboard (or stdin) as a capture, for evidence copied
+    /// from a browser or RDP session
+    Clip {
+        /// What the text is, recorded as the capture's command line
+        #[arg(short, long)]
+        note: Option<String>,
+
+        /// Read stdin instead of the clipboard (implied when stdin is piped)
+        #[arg(long)]
+        stdin: bool,
+
+        /// Session to store into (ID or name); defaults to the active session
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+
+    /// Run a text file, stdin or a fetched URL through the capture pipeline
+    Add {
+        /// File path, `-` for stdin, or an http(s) URL
+        source: String,
+
+        /// Tool whose output this is, for entity hints and section tagging
+        #[arg(short, long)]
+        tool: Option<String>,
+
+        /// When the output was produced: a local time such as
+        /// "2025-07-01 14:30", RFC 3339 or Unix seconds (default: now)
+        #[arg(long)]
+        timestamp: Option<String>,
+
+        /// Session to store into (ID or name); defaults to the active session
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+
+    /// Attach an evidence file (screenshot, loot) to a session
+    Attach {
+        /// File to copy into the session's evidence directory
+        file: PathBuf,
+
+        /// Link the file to this capture
+        #[arg(long)]
+        capture: Option<i64>,
+
+        /// Link the file to this finding
+        #[arg(long)]
+        finding: Option<i64>,
+
+        /// Session to attach to (ID or name); defaults to the capture's,
+        /// the finding's, or the active session
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+
+    /// Import network evidence or an export archive
+    Import {
+        #[command(subcommand)]
+        action: ImportAction,
+    },
+
+    /// Feed a directory of saved tool output through the capture pipeline,
+    /// one file per command, to try filtering and reports on known input
+    Replay {
+        /// Directory of output files, replayed in name order; a first line
+        /// of `$ <command>` sets the command line
+        dir: PathBuf,
+
+        /// Session to replay into (name), created if missing; defaults to
+        /// replay-<directory name>
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Simulated time between captures (e.g. 30s, 5m)
+        #[arg(long, default_value = "1m")]
+        interval: String,
+
+        /// Time of the first capture (Unix seconds, RFC 3339 or
+        /// `2025-07-01 14:30`); defaults to now
+        #[arg(long)]
+        start: Option<String>,
+
+        /// Wait the interval between captures, to follow along in `yinx watch`
+        #[arg(long)]
+        realtime: bool,
+    },
+
+    /// Time filtering, extraction, storage and search on a synthetic
+    /// workload, to see how many captures per second this machine keeps up with
+    Bench {
+        /// Captures to generate
+        #[arg(long, default_value_t = 200)]
+        captures: usize,
+
+        /// Output lines per capture
+        #[arg(long, default_value_t = 400)]
+        lines: usize,
     },
 
     /// Manage configuration
@@ -115,8 +607,23 @@ pub enum Commands {
         action: ConfigAction,
     },
 
+    /// Browse another data directory, or a .tar/.tar.gz/.tar.zst of one,
+    /// without a daemon; commands that would change it are refused
+    Open {
+        /// Open without writing anything (required; the only mode for now)
+        #[arg(long, required = true)]
+        read_only: bool,
+
+        /// Data directory or archive to open
+        path: PathBuf,
+
+        /// Run one command against it instead of starting a prompt
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+
     /// Internal commands (not for direct use)
-    #[command(hide = true)]
+    #[command(name = "_internal", hide = true)]
     Internal {
         #[command(subcommand)]
         action: InternalAction,
@@ -150,6 +657,319 @@ pub enum InternalAction {
         /// Current working directory
         #[arg(long)]
         cwd: String,
+
+        /// Terminal the command ran in, as printed by tty(1)
+        #[arg(long)]
+        tty: Option<String>,
+
+        /// Store nothing; print what filtering and extraction would keep
+        /// as JSON (the output file is left in place)
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Tell the daemon a command has started so its targets reach the board
+    Intent {
+        /// Session ID
+        #[arg(long)]
+        session_id: String,
+
+        /// Unix timestamp
+        #[arg(long)]
+        timestamp: i64,
+
+        /// Command being executed
+        #[arg(long)]
+        command: String,
+    },
+
+    /// Print capture.exclude_commands as one POSIX ERE for shell hooks
+    ExcludePattern,
+
+    /// Answer a `yinx sync` from a peer on stdin/stdout
+    SyncServe,
+
+    /// Rebuild the keyword index from the database (started by `yinx query`
+    /// when it finds the index empty)
+    Reindex,
+
+    /// Run the daemon in the foreground (how `yinx start` runs it on Windows)
+    RunDaemon {
+        /// Capture into this session instead of the most recent active one
+        #[arg(long)]
+        session_id: Option<String>,
+
+        /// Leave panic backtraces and pipeline snapshots here for a supervisor
+        #[arg(long)]
+        crash_dir: Option<PathBuf>,
+    },
+
+    /// Run the daemon as a child process, restarting it when it crashes
+    Supervise {
+        /// Capture into this session instead of the most recent active one
+        #[arg(long)]
+        session_id: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DaemonAction {
+    /// Start the daemon at login: a launchd agent on macOS, a systemd user
+    /// unit on Linux
+    InstallService {
+        /// Only write the service file; don't load it
+        #[arg(long)]
+        no_load: bool,
+
+        /// Replace an existing service file
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Unload and remove the service installed by install-service
+    UninstallService,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionsAction {
+    /// List all sessions
+    List,
+
+    /// Move a stopped session into cold storage
+    Archive {
+        /// Session ID or name
+        session: String,
+    },
+
+    /// Resume capturing into an interrupted session
+    Resume {
+        /// Session ID or name
+        session: String,
+    },
+
+    /// Close an interrupted session at its last captured activity
+    Close {
+        /// Session ID or name
+        session: String,
+    },
+
+    /// Restore an archived session so it can be queried again
+    Unarchive {
+        /// Session ID or name
+        session: String,
+    },
+
+    /// Delete a session with its captures, blobs and index entries
+    ///
+    /// Without --purge, shows what would be deleted and changes nothing.
+    Delete {
+        /// Session ID or name
+        session: String,
+
+        /// Actually delete; there is no undo
+        #[arg(long)]
+        purge: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FindingsAction {
+    /// Create tracker issues for findings, updating those pushed before
+    Push {
+        /// Tracker configured under [tickets]: jira or gitlab
+        #[arg(long)]
+        target: TicketTarget,
+
+        /// Finding IDs; defaults to every finding of the session
+        ids: Vec<i64>,
+
+        /// Session whose findings to push (ID or name); defaults to the active session
+        #[arg(short, long, conflicts_with = "ids")]
+        session: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EntitiesAction {
+    /// List entity values, most frequent first
+    List {
+        /// Only this entity type (cve, ip_address, url...)
+        #[arg(short = 't', long = "type")]
+        entity_type: Option<String>,
+
+        /// Only entities from this session (ID or name); defaults to all sessions
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Only entities seen within this interval (e.g. 24h, 1d)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Maximum number of values to list
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+
+        /// Write the list as a table: csv or md
+        #[arg(long)]
+        export: Option<ExportFormat>,
+    },
+
+    /// Show the most frequent values of each entity type
+    Top {
+        /// Only this entity type
+        #[arg(short = 't', long = "type")]
+        entity_type: Option<String>,
+
+        /// Only entities from this session (ID or name); defaults to all sessions
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Only entities seen within this interval (e.g. 24h, 1d)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Values to show per type
+        #[arg(short, long, default_value = "5")]
+        limit: usize,
+
+        /// Write the values as a table: csv or md
+        #[arg(long)]
+        export: Option<ExportFormat>,
+    },
+
+    /// List entity values matching a regular expression
+    Grep {
+        /// Regular expression matched against each value
+        pattern: String,
+
+        /// Only this entity type
+        #[arg(short = 't', long = "type")]
+        entity_type: Option<String>,
+
+        /// Only entities from this session (ID or name); defaults to all sessions
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Only entities seen within this interval (e.g. 24h, 1d)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Write the matches as a table: csv or md
+        #[arg(long)]
+        export: Option<ExportFormat>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportAction {
+    /// An archive written by `yinx export`, encrypted or not
+    Archive {
+        file: PathBuf,
+
+        /// age identity file to decrypt with; without one age asks for the
+        /// passphrase
+        #[arg(short, long)]
+        identity: Option<PathBuf>,
+
+        /// What to do with captures this store already has: skip, overwrite,
+        /// duplicate (import clashing sessions as new ones) or merge (add
+        /// the entities the local copy lacks)
+        #[arg(long, default_value = "skip")]
+        strategy: ConflictStrategy,
+
+        /// Report how the archive overlaps with this store without
+        /// importing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Packet captures (pcap or pcapng)
+    Pcap {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Session to import into (ID or name); defaults to the active session
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+
+    /// Zeek logs (conn, http, ftp, ssl), as files or log directories
+    Zeek {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Session to import into (ID or name); defaults to the active session
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+
+    /// Cracked hashes from hashcat potfiles or `hashcat --show`
+    Hashcat {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Only match hashes from this session (ID or name); defaults to all sessions
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+
+    /// Cracked hashes from john.pot or `john --show`
+    John {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Only match hashes from this session (ID or name); defaults to all sessions
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FiltersAction {
+    /// Show per-tool reduction ratios and score distributions
+    Stats {
+        /// Only include captures from this session (ID or name)
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PatternsAction {
+    /// Compile every pattern file and report errors and likely mistakes
+    Validate {
+        /// Fail on warnings too
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Show match counts and regex time per entity pattern, slowest first
+    Stats {
+        /// Patterns to show
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BoardAction {
+    /// Move a host to a column by hand
+    Set {
+        /// Host address or name
+        host: String,
+
+        /// not-started, enumerating, foothold, root or done
+        stage: HostStage,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HostsAction {
+    /// Show a host's ports, services and versions, with the history of any
+    /// that captures disagreed on
+    Show {
+        /// Host address or name
+        host: String,
     },
 }
 
@@ -205,6 +1025,54 @@ impl Cli {
     }
 }
 
+impl Commands {
+    /// Whether the command only reads the store, so it can run under
+    /// `yinx open --read-only`
+    ///
+    /// Daemon control, capture and anything that edits sessions, findings,
+    /// the board or the config is left out.
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Self::Query { .. }
+            | Self::Grep { .. }
+            | Self::Watch { .. }
+            | Self::Ask { .. }
+            | Self::Report { .. }
+            | Self::Export { .. }
+            | Self::Serve { .. }
+            | Self::Entities { .. }
+            | Self::Filters { .. }
+            | Self::Patterns { .. }
+            | Self::Hosts { .. }
+            | Self::Proofs { .. }
+            | Self::Summary { .. } => true,
+            Self::Sessions { action } => matches!(action, SessionsAction::List),
+            Self::Findings { action, .. } => action.is_none(),
+            Self::Board { action, .. } => action.is_none(),
+            Self::Transcript { per_host, .. } => !per_host,
+            _ => false,
+        }
+    }
+
+    /// Whether the command can print its result as JSON
+    ///
+    /// Interactive commands, daemons and commands whose output is already a
+    /// document of its own don't.
+    pub fn supports_json(&self) -> bool {
+        !matches!(
+            self,
+            Self::Start { .. }
+                | Self::Stop
+                | Self::Ask { .. }
+                | Self::Serve { .. }
+                | Self::Agent { .. }
+                | Self::Transcript { .. }
+                | Self::Config { .. }
+                | Self::Internal { .. }
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,4 +1082,38 @@ mod tests {
         use clap::CommandFactory;
         Cli::command().debug_assert();
     }
+
+    #[test]
+    fn test_read_only_commands() {
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(std::iter::once("yinx").chain(args.iter().copied()))
+                .unwrap()
+                .command
+        };
+        assert!(parse(&["query", "smb shares"]).is_read_only());
+        assert!(parse(&["grep", "-i", "password"]).is_read_only());
+        assert!(parse(&["findings"]).is_read_only());
+        assert!(parse(&["patterns", "validate", "--strict"]).is_read_only());
+        assert!(parse(&["patterns", "stats"]).is_read_only());
+        assert!(parse(&["hosts", "show", "10.10.10.3"]).is_read_only());
+        assert!(parse(&["hosts", "--prefix", "16"]).is_read_only());
+        assert!(!parse(&["findings", "push", "--target", "jira"]).is_read_only());
+        assert!(!parse(&["transcript", "exam", "--per-host"]).is_read_only());
+        assert!(!parse(&["board", "set", "10.10.10.3", "root"]).is_read_only());
+        assert!(!parse(&["open", "--read-only", "/tmp/exam"]).is_read_only());
+    }
+
+    #[test]
+    fn test_json_support() {
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(std::iter::once("yinx").chain(args.iter().copied()))
+                .unwrap()
+                .command
+        };
+        assert!(parse(&["--json", "status"]).supports_json());
+        assert!(parse(&["query", "smb shares", "--json"]).supports_json());
+        assert!(!parse(&["--json", "stop"]).supports_json());
+        assert!(!parse(&["ask", "what ports are open?"]).supports_json());
+        assert!(!parse(&["transcript", "exam"]).supports_json());
+    }
 }
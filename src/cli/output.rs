@@ -0,0 +1,1280 @@
+//! Machine-readable command output
+//!
+//! Everything printed under `--json` is built from the types here rather than
+//! from internal structs, so the format only changes when these do. Fields
+//! are only ever added; renaming or removing one is a breaking change for
+//! scripts wrapping yinx.
+
+use crate::bench::BenchReport;
+use crate::board::HostStage;
+use crate::config::SearchEffort;
+use crate::daemon::{ModelStatus, PipelineHealth};
+use crate::entities::{AsnInfo, Entity, HostInfo, Observation};
+use crate::error::{Result, YinxError};
+use crate::filtering::FilterTrace;
+use crate::refilter::RefilterReport;
+use crate::retrieval::{Dropped, Explanation, RebuildProgress};
+use crate::session::{Session, SessionStatus};
+use crate::storage::latency::OpLatency;
+use crate::storage::{
+    ArchiveStats, AttachmentRecord, CaptureRecord, EmbeddingBacklog, EntityTotal, FindingRecord,
+    GrepHit, GrepLine, HostGraph, HostNode, HostStageRecord, ProofRecord, PurgeStats,
+    RecentFailure, ScrubReport, StorageStats, ToolFilterStats,
+};
+use crate::sync::SyncStats;
+use crate::tune::{TuneReport, TuneStats};
+use crate::verify::VerifyReport;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Print a value as pretty JSON on stdout
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize command output".to_string(),
+    })?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Print a value as one line of JSON, for streaming output
+pub fn print_json_line<T: Serialize>(value: &T) -> Result<()> {
+    let json = serde_json::to_string(value).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize command output".to_string(),
+    })?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// `yinx status`
+#[derive(Debug, Serialize)]
+pub struct StatusOutput {
+    pub daemon: DaemonOutput,
+    pub sessions_total: usize,
+    pub interrupted: Vec<SessionOutput>,
+    pub recent: Vec<SessionOutput>,
+    pub storage: Option<StorageOutput>,
+    /// Hosts per board column in the active session
+    pub board: BTreeMap<String, usize>,
+    /// Captures per originating terminal in the active session
+    pub terminals: BTreeMap<String, usize>,
+    /// Recent storage and search call latency
+    pub latency: Vec<LatencyOutput>,
+    /// Chunks waiting for embeddings, when vector search expects them
+    pub embedding_backlog: Option<EmbeddingBacklog>,
+    /// Warnings for `daemon.alarms` thresholds crossed
+    pub alarms: Vec<String>,
+    /// Latest triaged failures in the active session
+    pub failures: Vec<RecentFailure>,
+}
+
+/// Recent latency of one storage or search operation
+#[derive(Debug, Serialize)]
+pub struct LatencyOutput {
+    pub op: String,
+    pub samples: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+impl From<&OpLatency> for LatencyOutput {
+    fn from(latency: &OpLatency) -> Self {
+        Self {
+            op: latency.op.clone(),
+            samples: latency.samples,
+            p50_ms: latency.p50.as_secs_f64() * 1000.0,
+            p95_ms: latency.p95.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Daemon process state
+#[derive(Debug, Serialize)]
+pub struct DaemonOutput {
+    pub running: bool,
+    pub pid: Option<i32>,
+    /// Model readiness reported by a running daemon
+    pub models: Option<ModelStatus>,
+    /// Pipeline counters reported by a running daemon
+    pub health: Option<PipelineHealth>,
+    /// Why a running daemon uses the bundled patterns, if it does
+    pub pattern_fallback: Option<String>,
+}
+
+/// A session as listed by `status` and `sessions`
+#[derive(Debug, Serialize)]
+pub struct SessionOutput {
+    pub id: String,
+    pub name: String,
+    pub status: SessionStatus,
+    pub started_at: DateTime<Utc>,
+    pub stopped_at: Option<DateTime<Utc>>,
+    pub capture_count: u64,
+    pub blob_count: u64,
+    /// Seconds of missed capture, for interrupted sessions
+    pub interruption_gap_secs: Option<i64>,
+    pub deadline: Option<DateTime<Utc>>,
+    /// Seconds until the deadline; negative once it has passed
+    pub deadline_remaining_secs: Option<i64>,
+}
+
+impl From<&Session> for SessionOutput {
+    fn from(session: &Session) -> Self {
+        Self {
+            id: session.id.to_string(),
+            name: session.name.clone(),
+            status: session.status,
+            started_at: session.started_at,
+            stopped_at: session.stopped_at,
+            capture_count: session.capture_count,
+            blob_count: session.blob_count,
+            interruption_gap_secs: session.interruption_gap(),
+            deadline: session.deadline(),
+            deadline_remaining_secs: session
+                .time_remaining(Utc::now())
+                .map(|remaining| remaining.num_seconds()),
+        }
+    }
+}
+
+/// Store-wide counts and sizes
+#[derive(Debug, Serialize)]
+pub struct StorageOutput {
+    pub sessions: usize,
+    pub captures: usize,
+    pub blobs: usize,
+    pub chunks: usize,
+    pub entities: usize,
+    pub database_bytes: u64,
+    pub machine_zone_bytes: u64,
+    pub human_zone_bytes: u64,
+}
+
+impl From<&StorageStats> for StorageOutput {
+    fn from(stats: &StorageStats) -> Self {
+        Self {
+            sessions: stats.db.session_count,
+            captures: stats.db.capture_count,
+            blobs: stats.db.blob_count,
+            chunks: stats.db.chunk_count,
+            entities: stats.db.entity_count,
+            database_bytes: stats.db.total_size_bytes,
+            machine_zone_bytes: stats.machine_zone_size,
+            human_zone_bytes: stats.human_zone_size,
+        }
+    }
+}
+
+/// A `sessions` subcommand that changed one session
+#[derive(Debug, Serialize)]
+pub struct SessionActionOutput {
+    /// What was done: "archived", "unarchived", "resumed", "closed",
+    /// "deleted", "would_delete" or "unchanged"
+    pub action: &'static str,
+    pub session: SessionOutput,
+    pub archive: Option<ArchiveOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purge: Option<PurgeOutput>,
+}
+
+/// What deleting a session removed, or would remove
+#[derive(Debug, Serialize)]
+pub struct PurgeOutput {
+    pub captures: usize,
+    pub chunks: usize,
+    pub embeddings: usize,
+    pub entities: usize,
+    pub findings: usize,
+    pub blobs_deleted: usize,
+    pub blobs_shared: usize,
+    pub bytes_freed: u64,
+    pub lookups: usize,
+}
+
+impl From<&PurgeStats> for PurgeOutput {
+    fn from(stats: &PurgeStats) -> Self {
+        Self {
+            captures: stats.captures,
+            chunks: stats.chunks,
+            embeddings: stats.embeddings,
+            entities: stats.entities,
+            findings: stats.findings,
+            blobs_deleted: stats.blobs_deleted,
+            blobs_shared: stats.blobs_shared,
+            bytes_freed: stats.bytes_freed,
+            lookups: stats.lookups,
+        }
+    }
+}
+
+/// Result of archiving or unarchiving a session
+#[derive(Debug, Serialize)]
+pub struct ArchiveOutput {
+    pub blobs_recompressed: usize,
+    pub bytes_saved: u64,
+    pub chunks_indexed: usize,
+    pub embeddings_dropped: usize,
+}
+
+impl From<&ArchiveStats> for ArchiveOutput {
+    fn from(stats: &ArchiveStats) -> Self {
+        Self {
+            blobs_recompressed: stats.blobs_recompressed,
+            bytes_saved: stats.bytes_saved(),
+            chunks_indexed: stats.chunks_indexed,
+            embeddings_dropped: stats.embeddings_dropped,
+        }
+    }
+}
+
+/// `yinx sync`
+#[derive(Debug, Serialize)]
+pub struct SyncOutput {
+    pub peer: String,
+    pub sent: usize,
+    pub received: usize,
+    pub blobs_sent: usize,
+    pub blobs_received: usize,
+}
+
+impl SyncOutput {
+    pub fn new(peer: &str, stats: &SyncStats) -> Self {
+        Self {
+            peer: peer.to_string(),
+            sent: stats.sent,
+            received: stats.received,
+            blobs_sent: stats.blobs_sent,
+            blobs_received: stats.blobs_received,
+        }
+    }
+}
+
+/// `yinx import`
+#[derive(Debug, Serialize)]
+pub struct ImportOutput {
+    pub session_id: String,
+    pub source: String,
+    /// Packets (pcap) or log records (zeek) read
+    pub records: usize,
+    pub conversations: usize,
+    pub hosts: usize,
+    pub credentials: usize,
+}
+
+/// `yinx export` of an archive
+#[derive(Debug, Serialize)]
+pub struct ArchiveExportOutput {
+    pub session_id: String,
+    pub path: String,
+    pub captures: usize,
+    pub blobs: usize,
+    pub bytes: u64,
+    pub encrypted: bool,
+}
+
+/// `yinx import archive`
+#[derive(Debug, Serialize)]
+pub struct ArchiveImportOutput {
+    pub path: String,
+    pub sessions: usize,
+    /// Captures in the archive
+    pub captures: usize,
+    /// Captures that weren't in the store yet
+    pub imported: usize,
+    /// Local captures overwritten or merged into
+    pub updated: usize,
+    pub blobs: usize,
+    pub encrypted: bool,
+    pub strategy: String,
+    pub dry_run: bool,
+    pub diff: ImportDiffOutput,
+}
+
+/// How an archive overlapped with the store before import
+#[derive(Debug, Serialize)]
+pub struct ImportDiffOutput {
+    pub new_sessions: usize,
+    pub existing_sessions: usize,
+    pub new_captures: usize,
+    pub existing_captures: usize,
+    pub new_entities: usize,
+    pub new_blobs: usize,
+    pub existing_blobs: usize,
+}
+
+/// `yinx replay`
+#[derive(Debug, Serialize)]
+pub struct ReplayOutput {
+    pub session_id: String,
+    pub captures: Vec<ReplayCaptureOutput>,
+}
+
+/// One replayed fixture
+#[derive(Debug, Serialize)]
+pub struct ReplayCaptureOutput {
+    pub file: String,
+    pub command: String,
+    /// None when the output repeated the previous fixture and was folded into it
+    pub capture_id: Option<i64>,
+    pub timestamp: i64,
+}
+
+/// `yinx bench`
+#[derive(Debug, Serialize)]
+pub struct BenchOutput {
+    pub captures: usize,
+    pub lines: usize,
+    pub captures_per_sec: f64,
+    pub stages: Vec<BenchStageOutput>,
+}
+
+/// Timing of one `yinx bench` stage
+#[derive(Debug, Serialize)]
+pub struct BenchStageOutput {
+    pub name: String,
+    pub iterations: usize,
+    pub total_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub mb_per_sec: f64,
+}
+
+impl From<&BenchReport> for BenchOutput {
+    fn from(report: &BenchReport) -> Self {
+        let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+        Self {
+            captures: report.options.captures,
+            lines: report.options.lines,
+            captures_per_sec: report.captures_per_sec(),
+            stages: report
+                .stages
+                .iter()
+                .map(|stage| BenchStageOutput {
+                    name: stage.name.to_string(),
+                    iterations: stage.iterations,
+                    total_ms: ms(stage.total),
+                    p50_ms: ms(stage.p50),
+                    p95_ms: ms(stage.p95),
+                    mb_per_sec: stage.mb_per_sec(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `yinx clip` and `yinx add`
+#[derive(Debug, Serialize)]
+pub struct ManualCaptureOutput {
+    pub session_id: String,
+    /// None when the text repeated a recent capture and was folded into it
+    pub capture_id: Option<i64>,
+    pub bytes: usize,
+}
+
+/// `yinx export --format bloodhound`
+#[derive(Debug, Serialize)]
+pub struct GraphExportOutput {
+    pub session_id: String,
+    pub path: String,
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+/// `yinx export --format cherrytree|opml`
+#[derive(Debug, Serialize)]
+pub struct NotesExportOutput {
+    pub session_id: String,
+    pub format: String,
+    pub path: String,
+    pub hosts: usize,
+    pub notes: usize,
+}
+
+/// `yinx daemon install-service` / `uninstall-service`
+#[derive(Debug, Serialize)]
+pub struct ServiceOutput {
+    pub manager: String,
+    /// None when there was nothing to uninstall
+    pub path: Option<String>,
+    pub installed: bool,
+    pub loaded: bool,
+}
+
+/// `yinx report`
+#[derive(Debug, Serialize)]
+pub struct ReportOutput {
+    pub session_id: String,
+    pub format: String,
+    pub path: String,
+    pub sections: Vec<String>,
+    pub findings: usize,
+    /// Evidence manifest, with `--include-evidence`
+    pub manifest: Option<String>,
+    pub signature: Option<String>,
+    /// Evidence files missing or changed since they were attached
+    pub tampered: Vec<String>,
+}
+
+/// `yinx import hashcat|john`
+#[derive(Debug, Serialize)]
+pub struct CrackImportOutput {
+    pub source: String,
+    /// Result lines read
+    pub lines: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub credentials: usize,
+    pub findings: usize,
+    pub escalated: usize,
+}
+
+/// `yinx scrub`
+#[derive(Debug, Serialize)]
+pub struct ScrubOutput {
+    pub dry_run: bool,
+    pub occurrences: usize,
+    pub hits: Vec<ScrubHitOutput>,
+    pub blobs_rewritten: usize,
+    pub chunks_reindexed: usize,
+    pub entities_removed: usize,
+    pub embeddings_dropped: usize,
+    pub positions_dropped: usize,
+    pub answers_dropped: usize,
+    pub slow_log_entries: usize,
+    pub sealed_unchecked: usize,
+}
+
+/// One record a scrubbed value appeared in
+#[derive(Debug, Serialize)]
+pub struct ScrubHitOutput {
+    pub location: &'static str,
+    pub session_id: String,
+    pub capture_id: Option<i64>,
+    pub occurrences: usize,
+}
+
+impl ScrubOutput {
+    pub fn new(report: &ScrubReport, dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            occurrences: report.total_occurrences(),
+            hits: report
+                .hits
+                .iter()
+                .map(|hit| ScrubHitOutput {
+                    location: hit.location.as_str(),
+                    session_id: hit.session_id.clone(),
+                    capture_id: hit.capture_id,
+                    occurrences: hit.occurrences,
+                })
+                .collect(),
+            blobs_rewritten: report.blobs_rewritten,
+            chunks_reindexed: report.chunks_reindexed,
+            entities_removed: report.entities_removed,
+            embeddings_dropped: report.embeddings_dropped,
+            positions_dropped: report.positions_dropped,
+            answers_dropped: report.answers_dropped,
+            slow_log_entries: report.slow_log_entries,
+            sealed_unchecked: report.sealed_unchecked,
+        }
+    }
+}
+
+/// `yinx refilter`
+#[derive(Debug, Serialize)]
+pub struct RefilterOutput {
+    pub dry_run: bool,
+    pub captures: usize,
+    pub changed: Vec<RefilterChangeOutput>,
+    pub chunks_before: usize,
+    pub chunks_after: usize,
+    pub entities_added: usize,
+    pub entities_removed: usize,
+    pub missing_blobs: usize,
+    pub archived_skipped: usize,
+}
+
+/// One capture whose chunks or entities changed
+#[derive(Debug, Serialize)]
+pub struct RefilterChangeOutput {
+    pub capture_id: i64,
+    pub session_id: String,
+    pub tool: Option<String>,
+    pub chunks_changed: bool,
+    pub chunks_before: usize,
+    pub chunks_after: usize,
+    pub entities_added: Vec<EntityRefOutput>,
+    pub entities_removed: Vec<EntityRefOutput>,
+}
+
+/// An entity by type and value
+#[derive(Debug, Serialize)]
+pub struct EntityRefOutput {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub value: String,
+}
+
+impl RefilterOutput {
+    pub fn new(report: &RefilterReport, dry_run: bool) -> Self {
+        let refs = |entities: &[(String, String)]| {
+            entities
+                .iter()
+                .map(|(entity_type, value)| EntityRefOutput {
+                    entity_type: entity_type.clone(),
+                    value: value.clone(),
+                })
+                .collect()
+        };
+        Self {
+            dry_run,
+            captures: report.captures,
+            changed: report
+                .changed
+                .iter()
+                .map(|change| RefilterChangeOutput {
+                    capture_id: change.capture_id,
+                    session_id: change.session_id.clone(),
+                    tool: change.tool.clone(),
+                    chunks_changed: change.chunks_changed,
+                    chunks_before: change.chunks_before,
+                    chunks_after: change.chunks_after,
+                    entities_added: refs(&change.entities_added),
+                    entities_removed: refs(&change.entities_removed),
+                })
+                .collect(),
+            chunks_before: report.chunks_before(),
+            chunks_after: report.chunks_after(),
+            entities_added: report.entities_added(),
+            entities_removed: report.entities_removed(),
+            missing_blobs: report.missing_blobs,
+            archived_skipped: report.archived_skipped,
+        }
+    }
+}
+
+/// `yinx verify`
+#[derive(Debug, Serialize)]
+pub struct VerifyOutput {
+    pub repair: bool,
+    pub sessions: usize,
+    pub captures: usize,
+    pub chunks: usize,
+    pub blobs: usize,
+    pub embeddings_queued: usize,
+    pub index_pending: usize,
+    pub unrepaired: usize,
+    pub issues: Vec<VerifyIssueOutput>,
+}
+
+/// One inconsistency `yinx verify` found
+#[derive(Debug, Serialize)]
+pub struct VerifyIssueOutput {
+    pub check: &'static str,
+    pub subject: String,
+    pub count: usize,
+    pub detail: String,
+    pub repaired: bool,
+}
+
+impl VerifyOutput {
+    pub fn new(report: &VerifyReport, repair: bool) -> Self {
+        Self {
+            repair,
+            sessions: report.sessions,
+            captures: report.captures,
+            chunks: report.chunks,
+            blobs: report.blobs,
+            embeddings_queued: report.embeddings_queued,
+            index_pending: report.index_pending,
+            unrepaired: report.unrepaired(),
+            issues: report
+                .issues
+                .iter()
+                .map(|issue| VerifyIssueOutput {
+                    check: issue.check.as_str(),
+                    subject: issue.subject.clone(),
+                    count: issue.count,
+                    detail: issue.detail.clone(),
+                    repaired: issue.repaired,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `yinx tune`
+#[derive(Debug, Serialize)]
+pub struct TuneOutput {
+    pub session_id: String,
+    pub profile_a: String,
+    pub profile_b: String,
+    pub a: TuneStatsOutput,
+    pub b: TuneStatsOutput,
+    pub captures: Vec<TuneCaptureOutput>,
+    pub missing_blobs: usize,
+}
+
+/// Counts for one profile, over a capture or the whole session
+#[derive(Debug, Serialize)]
+pub struct TuneStatsOutput {
+    pub input_lines: usize,
+    pub boilerplate_lines: usize,
+    pub tier1_lines: usize,
+    pub kept_lines: usize,
+    pub chunks: usize,
+    pub reduction: f64,
+}
+
+/// One replayed capture and the kept lines the profiles disagree on
+#[derive(Debug, Serialize)]
+pub struct TuneCaptureOutput {
+    pub capture_id: i64,
+    pub command: Option<String>,
+    pub tool: Option<String>,
+    pub a: TuneStatsOutput,
+    pub b: TuneStatsOutput,
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+}
+
+impl From<&TuneStats> for TuneStatsOutput {
+    fn from(stats: &TuneStats) -> Self {
+        Self {
+            input_lines: stats.input_lines,
+            boilerplate_lines: stats.boilerplate_lines,
+            tier1_lines: stats.tier1_lines,
+            kept_lines: stats.kept_lines,
+            chunks: stats.chunks,
+            reduction: stats.reduction(),
+        }
+    }
+}
+
+impl TuneOutput {
+    pub fn new(report: &TuneReport, session_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            profile_a: report.profile_a.clone(),
+            profile_b: report.profile_b.clone(),
+            a: (&report.a).into(),
+            b: (&report.b).into(),
+            captures: report
+                .captures
+                .iter()
+                .map(|capture| TuneCaptureOutput {
+                    capture_id: capture.capture_id,
+                    command: capture.command.clone(),
+                    tool: capture.tool.clone(),
+                    a: (&capture.a).into(),
+                    b: (&capture.b).into(),
+                    only_a: capture.only_a.clone(),
+                    only_b: capture.only_b.clone(),
+                })
+                .collect(),
+            missing_blobs: report.missing_blobs,
+        }
+    }
+}
+
+/// `yinx filters stats`, one entry per tool
+#[derive(Debug, Serialize)]
+pub struct FilterStatsOutput {
+    /// Detected tool, or null when no tool matched
+    pub tool: Option<String>,
+    pub captures: usize,
+    pub input_lines: u64,
+    pub tier1_output: u64,
+    pub tier2_output: u64,
+    pub tier3_clusters: u64,
+    pub reduction: f64,
+    pub avg_tier2_percentile: f64,
+    pub avg_tier2_threshold: f64,
+    pub avg_score_median: f64,
+    pub avg_score_p90: f64,
+    pub avg_processing_ms: f64,
+}
+
+impl From<&ToolFilterStats> for FilterStatsOutput {
+    fn from(stats: &ToolFilterStats) -> Self {
+        Self {
+            tool: (!stats.tool.is_empty()).then(|| stats.tool.clone()),
+            captures: stats.captures,
+            input_lines: stats.input_lines,
+            tier1_output: stats.tier1_output,
+            tier2_output: stats.tier2_output,
+            tier3_clusters: stats.tier3_clusters,
+            reduction: stats.reduction(),
+            avg_tier2_percentile: stats.avg_percentile,
+            avg_tier2_threshold: stats.avg_threshold,
+            avg_score_median: stats.avg_score_median,
+            avg_score_p90: stats.avg_score_p90,
+            avg_processing_ms: stats.avg_processing_ms,
+        }
+    }
+}
+
+/// `yinx findings push`
+#[derive(Debug, Serialize)]
+pub struct TicketOutput {
+    pub finding_id: i64,
+    pub target: String,
+    /// "created" or "updated"
+    pub action: String,
+    pub remote_id: String,
+    pub url: Option<String>,
+}
+
+/// `yinx findings`
+#[derive(Debug, Serialize)]
+pub struct FindingOutput {
+    pub id: i64,
+    pub session_id: String,
+    pub capture_id: Option<i64>,
+    pub title: String,
+    pub severity: String,
+    pub rule: Option<String>,
+    pub created_at: i64,
+}
+
+impl From<&FindingRecord> for FindingOutput {
+    fn from(finding: &FindingRecord) -> Self {
+        Self {
+            id: finding.id,
+            session_id: finding.session_id.clone(),
+            capture_id: finding.capture_id,
+            title: finding.title.clone(),
+            severity: finding.severity.clone(),
+            rule: finding.rule.clone(),
+            created_at: finding.created_at,
+        }
+    }
+}
+
+/// One value listed by `yinx entities`
+#[derive(Debug, Serialize)]
+pub struct EntityOutput {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub value: String,
+    pub occurrences: u32,
+    pub captures: u32,
+    pub sessions: u32,
+    pub max_confidence: f32,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+impl From<&EntityTotal> for EntityOutput {
+    fn from(entity: &EntityTotal) -> Self {
+        Self {
+            entity_type: entity.entity_type.clone(),
+            value: entity.value.clone(),
+            occurrences: entity.occurrences,
+            captures: entity.captures,
+            sessions: entity.sessions,
+            max_confidence: entity.max_confidence,
+            first_seen: entity.first_seen,
+            last_seen: entity.last_seen,
+        }
+    }
+}
+
+/// One `yinx query` result
+#[derive(Debug, Serialize)]
+pub struct QueryResultOutput {
+    pub chunk_id: i64,
+    pub score: f32,
+    pub capture_id: i64,
+    pub session_id: Option<String>,
+    pub timestamp: Option<i64>,
+    pub tool: Option<String>,
+    pub command: Option<String>,
+    /// Addresses and hostnames extracted from the capture
+    pub hosts: Vec<String>,
+    pub sections: Vec<String>,
+    pub text: String,
+    /// Digest of the whole capture, when it was large enough to get one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Ranking breakdown, with `--explain`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Explanation>,
+}
+
+/// `yinx query --explain`
+#[derive(Debug, Serialize)]
+pub struct QueryExplainOutput {
+    pub results: Vec<QueryResultOutput>,
+    /// Candidates left out, with the reason
+    pub dropped: Vec<Dropped>,
+    /// How far the keyword index rebuild has got, while one is running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rebuild: Option<RebuildProgress>,
+    /// Vector search effort asked for and the HNSW ef_search it maps to
+    pub effort: SearchEffort,
+    pub ef_search: usize,
+}
+
+impl QueryResultOutput {
+    /// Columns `--export` can select
+    pub const COLUMNS: &'static [&'static str] = &[
+        "score",
+        "capture_id",
+        "session_id",
+        "timestamp",
+        "tool",
+        "command",
+        "hosts",
+        "sections",
+        "text",
+        "summary",
+    ];
+
+    /// Used when `--columns` is not given
+    pub const DEFAULT_COLUMNS: &'static [&'static str] = &["hosts", "tool", "command", "text"];
+
+    /// A column as table text; None for an unknown column
+    pub fn column(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "score" => format!("{:.3}", self.score),
+            "capture_id" => self.capture_id.to_string(),
+            "session_id" => self.session_id.clone().unwrap_or_default(),
+            "timestamp" => self
+                .timestamp
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+            "tool" => self.tool.clone().unwrap_or_default(),
+            "command" => self.command.clone().unwrap_or_default(),
+            "hosts" => self.hosts.join(" "),
+            "sections" => self.sections.join(" "),
+            "text" => self.text.clone(),
+            "summary" => self.summary.clone().unwrap_or_default(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// A host on the status board
+#[derive(Debug, Serialize)]
+pub struct HostStageOutput {
+    pub host: String,
+    pub stage: HostStage,
+    pub manual: bool,
+    pub updated_at: i64,
+}
+
+impl From<&HostStageRecord> for HostStageOutput {
+    fn from(record: &HostStageRecord) -> Self {
+        Self {
+            host: record.host.clone(),
+            stage: record.stage,
+            manual: record.manual,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// What captures established about a host
+#[derive(Debug, Serialize)]
+pub struct HostOutput {
+    pub host: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub os: Option<String>,
+    pub mac: Option<String>,
+    pub vendor: Option<String>,
+    pub banners: Vec<String>,
+    /// Names or addresses DNS resolved the host to or from
+    pub aliases: Vec<AliasOutput>,
+    /// Network a public address belongs to
+    pub asn: Option<AsnOutput>,
+    pub ports: Vec<HostPortOutput>,
+    /// Resolved version of each product seen
+    pub versions: BTreeMap<String, String>,
+    pub vulnerabilities: Vec<String>,
+    pub conflicts: Vec<HostConflictOutput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AsnOutput {
+    pub asn: u32,
+    pub org: String,
+    pub country: String,
+}
+
+impl From<&AsnInfo> for AsnOutput {
+    fn from(info: &AsnInfo) -> Self {
+        Self {
+            asn: info.asn,
+            org: info.org.clone(),
+            country: info.country.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for AsnOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AS{} {}", self.asn, self.org)?;
+        if !self.country.is_empty() {
+            write!(f, " ({})", self.country)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AliasOutput {
+    pub name: String,
+    pub resolved_at: i64,
+}
+
+/// `yinx hosts`: hosts grouped by subnet
+#[derive(Debug, Serialize)]
+pub struct NetworkOutput {
+    pub prefix: u8,
+    pub subnets: Vec<SubnetOutput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubnetOutput {
+    /// None for hosts known only by name or by IPv6 address
+    pub subnet: Option<String>,
+    pub scanned: usize,
+    pub responsive: usize,
+    pub hosts: Vec<NetworkHostOutput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NetworkHostOutput {
+    pub host: String,
+    pub captures: usize,
+    pub scanned: bool,
+    pub open_ports: Vec<u16>,
+    pub asn: Option<AsnOutput>,
+}
+
+impl NetworkOutput {
+    pub fn new(graph: &HostGraph, prefix: u8) -> Self {
+        let nodes: HashMap<&str, &HostNode> =
+            graph.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        Self {
+            prefix,
+            subnets: graph
+                .group_by_subnet(prefix)
+                .into_iter()
+                .map(|group| SubnetOutput {
+                    subnet: group.subnet,
+                    scanned: group.scanned,
+                    responsive: group.responsive,
+                    hosts: group
+                        .hosts
+                        .iter()
+                        .filter_map(|host| nodes.get(host.as_str()))
+                        .map(|node| NetworkHostOutput {
+                            host: node.id.clone(),
+                            captures: node.captures,
+                            scanned: node.scanned,
+                            open_ports: node.open_ports.clone(),
+                            asn: node.asn.as_ref().map(AsnOutput::from),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A port with its resolved state and service
+#[derive(Debug, Serialize)]
+pub struct HostPortOutput {
+    pub port: u16,
+    pub state: String,
+    pub service: Option<String>,
+    /// Whether captures disagreed on its state or service
+    pub conflicted: bool,
+}
+
+/// A fact captures disagreed on, with every value seen, oldest first
+#[derive(Debug, Serialize)]
+pub struct HostConflictOutput {
+    pub subject: String,
+    pub kind: String,
+    pub resolved: String,
+    pub history: Vec<ObservationOutput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ObservationOutput {
+    pub value: String,
+    pub confidence: f32,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+impl From<&Observation> for ObservationOutput {
+    fn from(observation: &Observation) -> Self {
+        Self {
+            value: observation.value.clone(),
+            confidence: observation.confidence,
+            first_seen: observation.first_seen,
+            last_seen: observation.last_seen,
+        }
+    }
+}
+
+impl From<&HostInfo> for HostOutput {
+    fn from(host: &HostInfo) -> Self {
+        let conflicts = host.conflicts();
+        let ports = host
+            .ports
+            .iter()
+            .filter_map(|(port, history)| {
+                let subject = format!("port {}", port);
+                Some(HostPortOutput {
+                    port: *port,
+                    state: history.resolved()?.value.clone(),
+                    service: host.service(*port).map(str::to_string),
+                    conflicted: conflicts.iter().any(|c| c.subject == subject),
+                })
+            })
+            .collect();
+        let mut vulnerabilities: Vec<String> = host.vulnerabilities.iter().cloned().collect();
+        vulnerabilities.sort();
+        Self {
+            host: host.identifier.clone(),
+            first_seen: host.first_seen,
+            last_seen: host.last_seen,
+            os: host.os.resolved().map(|o| o.value.clone()),
+            mac: host.mac.resolved().map(|o| o.value.clone()),
+            vendor: host.vendor.resolved().map(|o| o.value.clone()),
+            banners: host.banners.iter().cloned().collect(),
+            aliases: host
+                .aliases
+                .iter()
+                .map(|(name, &resolved_at)| AliasOutput {
+                    name: name.clone(),
+                    resolved_at,
+                })
+                .collect(),
+            asn: host.asn.as_ref().map(AsnOutput::from),
+            ports,
+            versions: host
+                .products
+                .iter()
+                .filter_map(|(product, history)| {
+                    Some((product.clone(), history.resolved()?.value.clone()))
+                })
+                .collect(),
+            vulnerabilities,
+            conflicts: conflicts
+                .iter()
+                .map(|c| HostConflictOutput {
+                    subject: c.subject.clone(),
+                    kind: c.kind.to_string(),
+                    resolved: c.resolved.value.clone(),
+                    history: c.history.iter().map(ObservationOutput::from).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A proof artifact on the exam checklist
+#[derive(Debug, Serialize)]
+pub struct ProofOutput {
+    pub id: i64,
+    pub session_id: String,
+    pub capture_id: Option<i64>,
+    pub host: Option<String>,
+    pub file: String,
+    pub value: String,
+    pub created_at: i64,
+}
+
+impl From<&ProofRecord> for ProofOutput {
+    fn from(proof: &ProofRecord) -> Self {
+        Self {
+            id: proof.id,
+            session_id: proof.session_id.clone(),
+            capture_id: proof.capture_id,
+            host: proof.host.clone(),
+            file: proof.file.clone(),
+            value: proof.value.clone(),
+            created_at: proof.created_at,
+        }
+    }
+}
+
+/// An evidence file recorded by `yinx attach`
+#[derive(Debug, Serialize)]
+pub struct AttachmentOutput {
+    pub id: i64,
+    pub session_id: String,
+    pub capture_id: Option<i64>,
+    pub finding_id: Option<i64>,
+    pub filename: String,
+    /// Path relative to the session's report directory
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+    pub created_at: i64,
+}
+
+impl From<&AttachmentRecord> for AttachmentOutput {
+    fn from(attachment: &AttachmentRecord) -> Self {
+        Self {
+            id: attachment.id,
+            session_id: attachment.session_id.clone(),
+            capture_id: attachment.capture_id,
+            finding_id: attachment.finding_id,
+            filename: attachment.filename.clone(),
+            path: attachment.report_path(),
+            hash: attachment.hash.clone(),
+            size: attachment.size,
+            created_at: attachment.created_at,
+        }
+    }
+}
+
+/// A capture with lines matched by `yinx grep` (printed one per line)
+#[derive(Debug, Serialize)]
+pub struct GrepOutput {
+    pub capture_id: i64,
+    pub session_id: String,
+    pub timestamp: i64,
+    pub tool: Option<String>,
+    pub command: Option<String>,
+    pub lines: Vec<GrepLineOutput>,
+    /// Matches not shown because of --max-memory
+    pub dropped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrepLineOutput {
+    pub number: usize,
+    pub text: String,
+}
+
+impl From<GrepHit> for GrepOutput {
+    fn from(hit: GrepHit) -> Self {
+        Self {
+            capture_id: hit.capture.id,
+            session_id: hit.capture.session_id,
+            timestamp: hit.capture.timestamp,
+            tool: hit.capture.tool,
+            command: hit.capture.command,
+            lines: hit
+                .lines
+                .into_iter()
+                .map(|GrepLine { number, text }| GrepLineOutput { number, text })
+                .collect(),
+            dropped: hit.dropped,
+        }
+    }
+}
+
+/// One capture seen by `yinx watch` (printed one per line)
+#[derive(Debug, Serialize)]
+pub struct WatchOutput {
+    pub capture_id: i64,
+    pub session_id: String,
+    pub command: Option<String>,
+    pub tool: Option<String>,
+    pub input_lines: usize,
+    pub boilerplate: Vec<String>,
+    pub tier1_output: usize,
+    pub tier1_dropped: Vec<String>,
+    pub tier2_output: usize,
+    pub tier2_threshold: f32,
+    pub tier2: Vec<WatchLineOutput>,
+    pub clusters: Vec<WatchClusterOutput>,
+    pub entities: Vec<WatchEntityOutput>,
+}
+
+/// A Tier 2 scored line
+#[derive(Debug, Serialize)]
+pub struct WatchLineOutput {
+    pub line: String,
+    pub score: f32,
+    pub passed: bool,
+}
+
+/// A Tier 3 cluster
+#[derive(Debug, Serialize)]
+pub struct WatchClusterOutput {
+    pub representative: String,
+    pub size: usize,
+}
+
+/// An extracted entity; sensitive values are withheld
+#[derive(Debug, Serialize)]
+pub struct WatchEntityOutput {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub value: Option<String>,
+    pub confidence: f32,
+}
+
+impl WatchOutput {
+    pub fn new(capture: &CaptureRecord, trace: &FilterTrace, entities: &[Entity]) -> Self {
+        Self {
+            capture_id: capture.id,
+            session_id: capture.session_id.clone(),
+            command: capture.command.clone(),
+            tool: capture.tool.clone(),
+            input_lines: trace.stats.input_lines,
+            boilerplate: trace.boilerplate.clone(),
+            tier1_output: trace.stats.tier1_output,
+            tier1_dropped: trace.tier1_dropped.clone(),
+            tier2_output: trace.stats.tier2_output,
+            tier2_threshold: trace.tier2_threshold,
+            tier2: trace
+                .tier2_scored
+                .iter()
+                .map(|s| WatchLineOutput {
+                    line: s.line.clone(),
+                    score: s.score,
+                    passed: s.score >= trace.tier2_threshold,
+                })
+                .collect(),
+            clusters: trace
+                .clusters
+                .iter()
+                .map(|c| WatchClusterOutput {
+                    representative: c.representative.clone(),
+                    size: c.size,
+                })
+                .collect(),
+            entities: entities
+                .iter()
+                .map(|e| WatchEntityOutput {
+                    entity_type: e.entity_type.clone(),
+                    value: (!e.should_redact).then(|| e.value.clone()),
+                    confidence: e.confidence,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_output_shape() {
+        let session = Session::new("htb-box");
+        let value = serde_json::to_value(SessionOutput::from(&session)).unwrap();
+
+        assert_eq!(value["name"], "htb-box");
+        assert_eq!(value["status"], "active");
+        assert_eq!(value["id"], session.id.to_string());
+        assert!(value["stopped_at"].is_null());
+        assert!(value["started_at"].is_string());
+    }
+}
@@ -0,0 +1,102 @@
+//! Clipboard evidence
+//!
+//! `yinx clip` stores text that never went through a shell hook (a page
+//! copied from a browser, output from an RDP session) as a manual capture.
+//! The capture is tagged `clipboard` and its command line carries the note,
+//! so it shows up in queries and transcripts like any other command.
+
+use crate::daemon::CaptureEvent;
+use crate::error::{Result, YinxError};
+use std::process::Command;
+
+/// Tag and source recorded on clipboard captures
+pub const CLIPBOARD_SOURCE: &str = "clipboard";
+
+/// Clipboard readers, tried in order: Wayland, X11, macOS
+const READERS: &[(&str, &[&str])] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+];
+
+/// Current clipboard text, read through the first reader that works
+pub fn read_clipboard() -> Result<String> {
+    for (program, args) in READERS {
+        // wl-paste fails without a Wayland session; fall through to X11
+        if *program == "wl-paste" && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            continue;
+        }
+        match Command::new(program).args(*args).output() {
+            Ok(output) if output.status.success() => {
+                return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+            }
+            Ok(output) => tracing::debug!("{} failed: {}", program, output.status),
+            Err(e) => tracing::debug!("{} unavailable: {}", program, e),
+        }
+    }
+    Err(YinxError::Other(anyhow::anyhow!(
+        "No clipboard reader worked; install wl-clipboard, xclip or xsel, or pipe the text to `yinx clip`"
+    )))
+}
+
+/// Capture event for clipped text, with the note as its command line
+pub fn clip_event(session_id: &str, text: String, note: Option<&str>, cwd: &str) -> CaptureEvent {
+    let command = match note {
+        Some(note) => format!("yinx clip --note {:?}", note),
+        None => "yinx clip".to_string(),
+    };
+    CaptureEvent {
+        session_id: session_id.to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+        command,
+        output: text,
+        exit_code: 0,
+        cwd: cwd.to_string(),
+        username: None,
+        source: Some(CLIPBOARD_SOURCE.to_string()),
+        tool: None,
+        context: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Yinx;
+
+    #[tokio::test]
+    async fn test_clip_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let yinx = Yinx::open(dir.path()).unwrap();
+        let session = yinx.start_session("rdp").unwrap();
+        let session_id = session.id.to_string();
+
+        let event = clip_event(
+            &session_id,
+            "Administrator:500:aad3b435b51404eeaad3b435b51404ee:31d6cfe0d16ae931b73c59d7e0c089c0:::"
+                .to_string(),
+            Some("SAM dump from \"DC01\" RDP"),
+            "/home/kali",
+        );
+        assert_eq!(
+            event.command,
+            r#"yinx clip --note "SAM dump from \"DC01\" RDP""#
+        );
+
+        let capture_id = yinx.ingest(event).await.unwrap().unwrap();
+        let tags = yinx
+            .storage()
+            .database
+            .get_capture_tags(capture_id)
+            .unwrap();
+        assert_eq!(tags, vec![CLIPBOARD_SOURCE.to_string()]);
+        let capture = yinx
+            .storage()
+            .database
+            .get_capture(capture_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(capture.tool, None);
+    }
+}
@@ -3,9 +3,11 @@
 //! This module handles loading, validation, and management of configuration
 //! following the configuration-driven design principles outlined in ARCHITECTURE.md
 
+use crate::embedding::KeywordTokenizers;
 use crate::error::{Result, YinxError};
+use crate::storage::SqliteOptions;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 mod validator;
@@ -21,11 +23,37 @@ pub struct Config {
     pub capture: CaptureConfig,
     pub daemon: DaemonConfig,
     pub patterns: PatternsConfig,
+    #[serde(default)]
+    pub tools: ToolMappingConfig,
     pub embedding: EmbeddingConfig,
     pub llm: LlmConfig,
     pub indexing: IndexingConfig,
     pub retrieval: RetrievalConfig,
     #[serde(default)]
+    pub vault: VaultConfig,
+    #[serde(default)]
+    pub acl: AclConfig,
+    #[serde(default)]
+    pub hooks: Vec<HookConfig>,
+    #[serde(default)]
+    pub deadline: DeadlineConfig,
+    #[serde(default)]
+    pub proof: ProofConfig,
+    #[serde(default)]
+    pub triage: TriageConfig,
+    #[serde(default)]
+    pub remote: RemoteConfig,
+    #[serde(default)]
+    pub sources: SourcesConfig,
+    #[serde(default)]
+    pub tickets: TicketsConfig,
+    #[serde(default)]
+    pub summaries: SummariesConfig,
+    #[serde(default)]
+    pub enrichment: EnrichmentConfig,
+    #[serde(default)]
+    pub report: ReportConfig,
+    #[serde(default)]
     pub profiles: HashMap<String, ProfileOverrides>,
 }
 
@@ -43,11 +71,126 @@ fn current_timestamp() -> String {
     chrono::Utc::now().to_rfc3339()
 }
 
+/// Default data directory, in `~` form
+///
+/// macOS keeps application data in Application Support rather than a
+/// dotdir in the home directory.
+#[cfg(target_os = "macos")]
+fn default_home() -> PathBuf {
+    PathBuf::from("~/Library/Application Support/yinx")
+}
+
+#[cfg(not(target_os = "macos"))]
+fn default_home() -> PathBuf {
+    PathBuf::from("~/.yinx")
+}
+
+/// Default directory for pattern files, next to where `yinx config init`
+/// writes config.toml (`dirs::config_dir()`)
+#[cfg(target_os = "macos")]
+fn default_config_dir() -> PathBuf {
+    PathBuf::from("~/Library/Application Support/yinx")
+}
+
+#[cfg(not(target_os = "macos"))]
+fn default_config_dir() -> PathBuf {
+    PathBuf::from("~/.config/yinx")
+}
+
 /// Storage configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub data_dir: PathBuf,
+    /// Larger capture output keeps only a head and a tail ("0" disables)
     pub max_blob_size: String,
+    /// Share of `max_blob_size` kept from the start of the output
+    #[serde(default = "default_truncate_head")]
+    pub truncate_head: f32,
+    /// Storage and search calls taking longer go to logs/slow.log ("0" disables)
+    #[serde(default = "default_slow_query")]
+    pub slow_query: String,
+    /// Connection pool and SQLite tuning
+    #[serde(default)]
+    pub sqlite: SqliteConfig,
+}
+
+impl StorageConfig {
+    /// Threshold for the slow-query log, None when it is off
+    pub fn slow_query_threshold(&self) -> Option<std::time::Duration> {
+        parse_duration(&self.slow_query).filter(|threshold| !threshold.is_zero())
+    }
+}
+
+/// Connection pool and SQLite tuning for the capture database
+///
+/// `profile` picks starting values for the disk the data directory is on;
+/// any field set here overrides the profile's value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SqliteConfig {
+    pub profile: DiskProfile,
+    /// Connections kept open for queries; writes always share one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_size: Option<u32>,
+    /// How long a statement waits for a lock held by another connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busy_timeout: Option<String>,
+    /// WAL pages written before SQLite checkpoints them into the database
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wal_autocheckpoint: Option<u32>,
+    /// Memory-mapped I/O window ("0" disables)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mmap_size: Option<String>,
+    /// Page cache per connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_size: Option<String>,
+}
+
+impl SqliteConfig {
+    /// The profile's settings with this block's overrides applied
+    ///
+    /// Values that don't parse keep the profile's; the validator reports them.
+    pub fn options(&self) -> SqliteOptions {
+        let mut options = match self.profile {
+            DiskProfile::Ssd => SqliteOptions::ssd(),
+            DiskProfile::Hdd => SqliteOptions::hdd(),
+        };
+        if let Some(pool_size) = self.pool_size {
+            options.pool_size = pool_size;
+        }
+        if let Some(timeout) = self.busy_timeout.as_deref().and_then(parse_duration) {
+            options.busy_timeout = timeout;
+        }
+        if let Some(pages) = self.wal_autocheckpoint {
+            options.wal_autocheckpoint = pages;
+        }
+        if let Some(size) = self.mmap_size.as_deref().and_then(parse_size) {
+            options.mmap_size = size;
+        }
+        if let Some(size) = self.cache_size.as_deref().and_then(parse_size) {
+            options.cache_size = size;
+        }
+        options
+    }
+}
+
+/// Kind of disk the data directory lives on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskProfile {
+    #[default]
+    Ssd,
+    /// Fewer connections, longer lock waits and rarer checkpoints, which
+    /// keeps a spinning disk from seeking between the WAL and the database
+    Hdd,
+}
+
+fn default_truncate_head() -> f32 {
+    0.3
+}
+
+fn default_slow_query() -> String {
+    "500ms".to_string()
 }
 
 /// Capture configuration
@@ -56,6 +199,72 @@ pub struct CaptureConfig {
     pub buffer_size: usize,
     pub batch_size: usize,
     pub flush_interval: String,
+    /// Pause the active session after this long without captures ("0" disables)
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout: String,
+    /// Regexes for commands that are never captured (checked by the hook and the daemon)
+    #[serde(default = "default_exclude_commands")]
+    pub exclude_commands: Vec<String>,
+    /// Fold identical re-runs within this window into one capture ("0" disables)
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: String,
+    /// Put hosts and ranges on the board as soon as a command naming them starts
+    #[serde(default = "default_register_targets")]
+    pub register_targets: bool,
+    /// Environment variables stored with each capture when set
+    #[serde(default = "default_context_env")]
+    pub context_env: Vec<String>,
+    /// Store the git repo, virtualenv and VPN interface a command ran with
+    #[serde(default = "default_record_context")]
+    pub record_context: bool,
+    /// Filter and extract captures but store nothing, logging what would
+    /// have been kept; for checking exclude rules and redaction first
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Output at least this big is handed to the daemon as a file path
+    /// instead of inside the IPC message ("0" always sends it inline)
+    #[serde(default = "default_handoff_threshold")]
+    pub handoff_threshold: String,
+}
+
+fn default_context_env() -> Vec<String> {
+    [
+        "HTTP_PROXY",
+        "HTTPS_PROXY",
+        "ALL_PROXY",
+        "NO_PROXY",
+        "http_proxy",
+        "https_proxy",
+        "all_proxy",
+        "no_proxy",
+        "KRB5CCNAME",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_record_context() -> bool {
+    true
+}
+
+fn default_register_targets() -> bool {
+    true
+}
+
+fn default_handoff_threshold() -> String {
+    "1MB".to_string()
+}
+
+fn default_dedup_window() -> String {
+    "0".to_string()
+}
+
+fn default_exclude_commands() -> Vec<String> {
+    vec!["^history( |$)".to_string(), "^vault login".to_string()]
+}
+
+fn default_idle_timeout() -> String {
+    "30m".to_string()
 }
 
 /// Daemon configuration for process and IPC management
@@ -64,7 +273,119 @@ pub struct DaemonConfig {
     pub socket_path: PathBuf,
     pub pid_file: PathBuf,
     pub log_file: PathBuf,
+    /// Client connections handled at once; more are turned away to retry
     pub max_connections: usize,
+    /// How often one user's clients may send requests
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Encoding the CLI asks for when it connects
+    #[serde(default)]
+    pub ipc_encoding: IpcEncoding,
+    /// Restart the daemon from a parent process when it crashes
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+    /// When `yinx status` warns about the capture pipeline
+    #[serde(default)]
+    pub alarms: AlarmConfig,
+}
+
+/// Self-restart for a daemon that panics or is killed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    /// Run the daemon under a supervisor (also `yinx start --supervise`)
+    pub enabled: bool,
+    /// Wait before the first restart; doubles with each crash
+    pub initial_backoff: String,
+    /// Longest wait between restarts
+    pub max_backoff: String,
+    /// Give up after this many crashes within `crash_loop_window`
+    pub crash_loop_restarts: u32,
+    /// A daemon that stays up this long resets the backoff
+    pub crash_loop_window: String,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            initial_backoff: "1s".to_string(),
+            max_backoff: "5m".to_string(),
+            crash_loop_restarts: 5,
+            crash_loop_window: "10m".to_string(),
+        }
+    }
+}
+
+/// Thresholds past which `yinx status` warns that captures may be going
+/// missing; counts warn when exceeded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlarmConfig {
+    /// Captures dropped because the pipeline queue stayed full
+    pub dropped_captures: u64,
+    /// Client connections that failed mid-request
+    pub ipc_errors: u64,
+    /// Longest acceptable flush of a capture batch to storage
+    pub flush_latency: String,
+    /// Longest a chunk may wait for its embedding
+    pub embedding_backlog_age: String,
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        Self {
+            dropped_captures: 0,
+            ipc_errors: 10,
+            flush_latency: "5s".to_string(),
+            embedding_backlog_age: "1h".to_string(),
+        }
+    }
+}
+
+/// Encoding of IPC messages after the connection handshake
+///
+/// JSON can be read off the socket with standard tools; bincode skips
+/// escaping command output and is several times faster for big captures.
+/// A client that asks for bincode falls back to JSON when the daemon is too
+/// old to know it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcEncoding {
+    #[default]
+    Json,
+    Bincode,
+}
+
+impl IpcEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Bincode => "bincode",
+        }
+    }
+}
+
+/// Per-user request rate on the IPC socket
+///
+/// Each user gets a bucket of `burst` requests, refilled at `per_second`;
+/// requests that find it empty are answered with how long to back off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Sustained requests per second; 0 disables the limit
+    pub per_second: f64,
+    /// Requests allowed in a burst above the sustained rate
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_second: 20.0,
+            burst: 100,
+        }
+    }
 }
 
 /// Pattern configuration - paths to pattern definition files
@@ -73,6 +394,21 @@ pub struct PatternsConfig {
     pub entities_file: PathBuf,
     pub tools_file: PathBuf,
     pub filters_file: PathBuf,
+    #[serde(default = "default_rules_file")]
+    pub rules_file: PathBuf,
+}
+
+fn default_rules_file() -> PathBuf {
+    PathBuf::from("~/.config/yinx/rules.toml")
+}
+
+/// Commands that run a known tool under another name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolMappingConfig {
+    /// Alias or wrapper script to the tool it runs, e.g. `nmapAll = "nmap"`
+    /// or `"scan.sh" = "nmap"`; a script matches by its path or file name
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
 }
 
 /// Embedding configuration
@@ -85,6 +421,19 @@ pub struct EmbeddingConfig {
     pub mode: String,
     /// Batch size for embedding generation
     pub batch_size: usize,
+    /// When the daemon loads models: "lazy" (on first use) or "eager" (at start)
+    #[serde(default)]
+    pub preload: ModelPreload,
+}
+
+/// When models are loaded into the daemon
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelPreload {
+    #[default]
+    Lazy,
+    /// Load and warm up the embedding model and reranker at daemon start
+    Eager,
 }
 
 impl Default for EmbeddingConfig {
@@ -93,6 +442,7 @@ impl Default for EmbeddingConfig {
             model: "all-MiniLM-L6-v2".to_string(), // Preset: 90MB, 384 dims, recommended
             mode: "offline".to_string(),
             batch_size: 32,
+            preload: ModelPreload::Lazy,
         }
     }
 }
@@ -105,6 +455,45 @@ pub struct LlmConfig {
     pub api_key_env: String,
     pub model: String,
     pub temperature: f32,
+    /// Reuse answers to near-identical questions while the session's index is unchanged
+    #[serde(default = "default_answer_cache")]
+    pub answer_cache: bool,
+    /// Cosine similarity a question needs to a cached one to reuse its answer
+    #[serde(default = "default_answer_cache_threshold")]
+    pub answer_cache_threshold: f32,
+    /// Token budget for retrieved context in a prompt
+    #[serde(default = "default_context_tokens")]
+    pub context_tokens: usize,
+    /// Hugging Face `tokenizer.json` for exact token counts; estimated from
+    /// the model family when unset
+    #[serde(default)]
+    pub tokenizer_file: Option<PathBuf>,
+    /// What to do with instruction-like lines in retrieved output
+    #[serde(default)]
+    pub injection_action: InjectionAction,
+}
+
+/// Handling of instruction-like content found in captured output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectionAction {
+    /// Replace the line with a placeholder before it reaches the model
+    #[default]
+    Strip,
+    /// Keep the line but report it
+    Flag,
+}
+
+fn default_answer_cache() -> bool {
+    true
+}
+
+fn default_answer_cache_threshold() -> f32 {
+    0.95
+}
+
+fn default_context_tokens() -> usize {
+    4000
 }
 
 /// Indexing configuration
@@ -116,6 +505,29 @@ pub struct IndexingConfig {
     pub hnsw_ef_construction: usize,
     /// HNSW M parameter (connections per layer)
     pub hnsw_m: usize,
+    /// Rebuild the vector index once this share of it (0.0 to 1.0) is
+    /// deleted chunks
+    #[serde(default = "default_rebuild_threshold")]
+    pub rebuild_threshold: f32,
+    /// Merge adjacent cluster representatives into chunks of up to this
+    /// many tokens before they are stored and embedded (0 keeps one chunk
+    /// per cluster)
+    #[serde(default)]
+    pub chunk_max_tokens: usize,
+    /// Keep lines from different tool output sections in separate chunks
+    #[serde(default = "default_chunk_keep_sections")]
+    pub chunk_keep_sections: bool,
+    /// Tokenizers of the keyword index fields
+    #[serde(default)]
+    pub tokenizers: KeywordTokenizers,
+}
+
+fn default_chunk_keep_sections() -> bool {
+    true
+}
+
+fn default_rebuild_threshold() -> f32 {
+    crate::embedding::DEFAULT_REBUILD_THRESHOLD
 }
 
 impl Default for IndexingConfig {
@@ -124,6 +536,10 @@ impl Default for IndexingConfig {
             vector_dim: 384,           // Matches all-MiniLM-L6-v2 preset
             hnsw_ef_construction: 200, // Good balance of speed/accuracy
             hnsw_m: 16,                // Standard value
+            rebuild_threshold: default_rebuild_threshold(),
+            chunk_max_tokens: 0,
+            chunk_keep_sections: default_chunk_keep_sections(),
+            tokenizers: KeywordTokenizers::default(),
         }
     }
 }
@@ -133,20 +549,50 @@ impl Default for IndexingConfig {
 pub struct RetrievalConfig {
     /// Search results multiplier for initial retrieval (limit * multiplier)
     pub search_multiplier: usize,
+    /// Which channels are searched: hybrid, keyword or vector
+    #[serde(default)]
+    pub mode: RetrievalMode,
+    /// How channel results are combined: rrf, weighted_linear or max_score
+    #[serde(default)]
+    pub fusion_strategy: FusionStrategy,
     /// Reciprocal Rank Fusion K constant (higher = less aggressive fusion)
     pub rrf_k: f32,
+    /// RRF K for the vector channel (default: rrf_k)
+    #[serde(default)]
+    pub semantic_rrf_k: Option<f32>,
+    /// RRF K for the keyword channel (default: rrf_k)
+    #[serde(default)]
+    pub keyword_rrf_k: Option<f32>,
     /// Weight for semantic search results (0.0 to 1.0)
     pub semantic_weight: f32,
     /// Weight for keyword search results (0.0 to 1.0)
     pub keyword_weight: f32,
     /// HNSW ef_search parameter (higher = better recall, slower search)
     pub hnsw_ef_search: usize,
+    /// ef_search for `yinx query --fast`
+    #[serde(default = "default_ef_search_fast")]
+    pub hnsw_ef_search_fast: usize,
+    /// ef_search for `yinx query --accurate`
+    #[serde(default = "default_ef_search_accurate")]
+    pub hnsw_ef_search_accurate: usize,
     /// Enable reranking with cross-encoder
     pub enable_reranking: bool,
     /// Reranker model name
     pub reranker_model: String,
     /// Maximum number of candidates to rerank
     pub rerank_candidates_limit: usize,
+    /// Candidates scored per cross-encoder forward pass
+    #[serde(default = "default_rerank_batch_size")]
+    pub rerank_batch_size: usize,
+    /// Threads running rerank batches in parallel (0 = one per core)
+    #[serde(default)]
+    pub rerank_threads: usize,
+    /// Execution provider for the reranker: cpu, cuda or rocm
+    #[serde(default)]
+    pub rerank_device: RerankDevice,
+    /// GPU to run on with cuda or rocm
+    #[serde(default)]
+    pub rerank_device_id: i32,
     /// Minimum similarity score threshold (0.0 to 1.0)
     pub min_similarity_threshold: f32,
 }
@@ -155,18 +601,665 @@ impl Default for RetrievalConfig {
     fn default() -> Self {
         Self {
             search_multiplier: 2,
+            mode: RetrievalMode::default(),
+            fusion_strategy: FusionStrategy::default(),
             rrf_k: 60.0,
+            semantic_rrf_k: None,
+            keyword_rrf_k: None,
             semantic_weight: 0.7,
             keyword_weight: 0.3,
             hnsw_ef_search: 50,
+            hnsw_ef_search_fast: default_ef_search_fast(),
+            hnsw_ef_search_accurate: default_ef_search_accurate(),
             enable_reranking: true,
             reranker_model: "Xenova/ms-marco-MiniLM-L-6-v2".to_string(),
             rerank_candidates_limit: 100,
+            rerank_batch_size: default_rerank_batch_size(),
+            rerank_threads: 0,
+            rerank_device: RerankDevice::Cpu,
+            rerank_device_id: 0,
             min_similarity_threshold: 0.0,
         }
     }
 }
 
+impl RetrievalConfig {
+    /// ef_search a vector search at `effort` runs with
+    pub fn ef_search(&self, effort: SearchEffort) -> usize {
+        match effort {
+            SearchEffort::Fast => self.hnsw_ef_search_fast,
+            SearchEffort::Balanced => self.hnsw_ef_search,
+            SearchEffort::Accurate => self.hnsw_ef_search_accurate,
+        }
+    }
+}
+
+fn default_rerank_batch_size() -> usize {
+    32
+}
+
+fn default_ef_search_fast() -> usize {
+    16
+}
+
+fn default_ef_search_accurate() -> usize {
+    200
+}
+
+/// How hard vector search looks for nearest neighbours, trading latency
+/// for recall
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchEffort {
+    /// `retrieval.hnsw_ef_search_fast`
+    Fast,
+    /// `retrieval.hnsw_ef_search`
+    #[default]
+    Balanced,
+    /// `retrieval.hnsw_ef_search_accurate`
+    Accurate,
+}
+
+impl SearchEffort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Balanced => "balanced",
+            Self::Accurate => "accurate",
+        }
+    }
+}
+
+/// Hardware the reranker's cross-encoder runs on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankDevice {
+    #[default]
+    Cpu,
+    /// NVIDIA GPU; needs yinx built with the `cuda` feature
+    Cuda,
+    /// AMD GPU; needs yinx built with the `rocm` feature
+    Rocm,
+}
+
+impl RerankDevice {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            Self::Cuda => "cuda",
+            Self::Rocm => "rocm",
+        }
+    }
+}
+
+/// Search channels queried by retrieval
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetrievalMode {
+    /// Vector and keyword search, fused
+    #[default]
+    Hybrid,
+    /// Keyword index only, for hunting exact artifacts (hashes, paths, flags)
+    Keyword,
+    /// Vector index only, for conceptual questions
+    Vector,
+}
+
+impl RetrievalMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hybrid => "hybrid",
+            Self::Keyword => "keyword",
+            Self::Vector => "vector",
+        }
+    }
+
+    /// Check if the vector channel is searched
+    pub fn uses_vector(&self) -> bool {
+        matches!(self, Self::Hybrid | Self::Vector)
+    }
+
+    /// Check if the keyword channel is searched
+    pub fn uses_keyword(&self) -> bool {
+        matches!(self, Self::Hybrid | Self::Keyword)
+    }
+}
+
+/// How ranked results from the search channels are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionStrategy {
+    /// Reciprocal Rank Fusion: sum of weight / (k + rank)
+    #[default]
+    Rrf,
+    /// Sum of weighted scores, each channel min-max normalized
+    WeightedLinear,
+    /// Best weighted, normalized score from any channel
+    MaxScore,
+}
+
+impl FusionStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rrf => "rrf",
+            Self::WeightedLinear => "weighted_linear",
+            Self::MaxScore => "max_score",
+        }
+    }
+}
+
+/// Vault configuration for sealing sensitive originals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultConfig {
+    /// Keep encrypted copies of command lines that were redacted
+    pub enabled: bool,
+    /// 32-byte key file, created with 0600 permissions on first use
+    pub key_file: PathBuf,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_file: default_home().join("vault.key"),
+        }
+    }
+}
+
+/// Access control for a daemon shared between several local users
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AclConfig {
+    /// Accept connections from other users (socket becomes world-connectable;
+    /// peers are authenticated by their socket credentials)
+    pub shared: bool,
+    /// Users allowed to connect in shared mode (empty = any local user)
+    #[serde(default)]
+    pub allowed_users: Vec<String>,
+    /// Query scope for users without an entry in `scopes`: "self" or "all"
+    #[serde(default = "default_acl_scope")]
+    pub default_scope: String,
+    /// Per-user list of whose captures they may query ("*" = everyone)
+    #[serde(default)]
+    pub scopes: HashMap<String, Vec<String>>,
+}
+
+fn default_acl_scope() -> String {
+    "self".to_string()
+}
+
+impl Default for AclConfig {
+    fn default() -> Self {
+        Self {
+            shared: false,
+            allowed_users: Vec::new(),
+            default_scope: default_acl_scope(),
+            scopes: HashMap::new(),
+        }
+    }
+}
+
+/// JSON-RPC API over TCP for captures streamed from other hosts
+///
+/// The token is sent in clear text; put the port behind an SSH tunnel or a
+/// VPN when it leaves the local network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    pub enabled: bool,
+    pub listen: String,
+    /// Shared secret clients must send in an `auth` call
+    pub token: String,
+    /// User the token queries as: its `[acl]` scope applies (empty = the
+    /// daemon owner)
+    pub user: String,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:7701".to_string(),
+            token: String::new(),
+            user: String::new(),
+        }
+    }
+}
+
+/// System log sources fed through the capture pipeline
+///
+/// Log lines are grouped per source and facility (or unit) and become one
+/// capture per group every `flush_interval`, tagged with the source name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourcesConfig {
+    /// How long lines collect before they are captured
+    pub flush_interval: String,
+    /// Lines per capture; a full group is captured early
+    pub max_lines: usize,
+    pub syslog: SyslogSourceConfig,
+    pub journald: JournaldSourceConfig,
+}
+
+impl Default for SourcesConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: "10s".to_string(),
+            max_lines: 500,
+            syslog: SyslogSourceConfig::default(),
+            journald: JournaldSourceConfig::default(),
+        }
+    }
+}
+
+/// Syslog receiver (RFC 3164 or 5424 datagrams)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyslogSourceConfig {
+    pub enabled: bool,
+    /// `udp:HOST:PORT` for rsyslog forwarding, or `unix:PATH` for a datagram socket
+    pub listen: String,
+    /// Facility names to keep (`auth`, `authpriv`, `local0`, ...); empty keeps all
+    pub facilities: Vec<String>,
+}
+
+impl Default for SyslogSourceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "udp:127.0.0.1:5514".to_string(),
+            facilities: vec!["auth".to_string(), "authpriv".to_string()],
+        }
+    }
+}
+
+/// Follows the local journal through `journalctl`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournaldSourceConfig {
+    pub enabled: bool,
+    /// Units to follow (`sshd.service`); empty follows the whole journal
+    pub units: Vec<String>,
+}
+
+/// Background digests of large captures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SummariesConfig {
+    pub enabled: bool,
+    /// Captures with fewer input lines than this are left alone
+    pub min_lines: usize,
+    /// Upper bound on a digest's length in characters
+    pub max_chars: usize,
+    /// Captures summarized per flush tick of the daemon
+    pub batch_size: usize,
+}
+
+impl Default for SummariesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_lines: 200,
+            max_chars: 600,
+            batch_size: 8,
+        }
+    }
+}
+
+/// Background lookups that add to what captures found about hosts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnrichmentConfig {
+    /// Never make network requests, whatever the passes below say
+    pub offline: bool,
+    pub dns: DnsEnrichmentConfig,
+    pub asn: AsnEnrichmentConfig,
+}
+
+/// Forward and reverse DNS lookups of discovered hosts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsEnrichmentConfig {
+    pub enabled: bool,
+    /// Names and addresses looked up per flush tick of the daemon
+    pub batch_size: usize,
+    /// Lookups older than this are repeated
+    pub refresh: String,
+}
+
+impl Default for DnsEnrichmentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batch_size: 16,
+            refresh: "24h".to_string(),
+        }
+    }
+}
+
+/// Network owner of public addresses, from a local ASN database
+///
+/// The database is an ip2asn-style TSV (plain or gzipped) of
+/// `range_start range_end AS_number country AS_description` lines, such
+/// as iptoasn.com's `ip2asn-combined.tsv.gz`. Lookups never leave the
+/// machine, so they run in offline mode too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsnEnrichmentConfig {
+    /// Path to the database; unset disables the pass
+    pub database: Option<PathBuf>,
+    /// Addresses annotated per flush tick of the daemon
+    pub batch_size: usize,
+}
+
+impl Default for AsnEnrichmentConfig {
+    fn default() -> Self {
+        Self {
+            database: None,
+            batch_size: 256,
+        }
+    }
+}
+
+/// Layout and branding of `yinx report`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportConfig {
+    /// Sections rendered, in this order; sections not listed are left out
+    pub sections: Vec<ReportSection>,
+    /// Defaults to "Penetration Test Report: <session>"
+    pub title: Option<String>,
+    pub client: Option<String>,
+    pub author: Option<String>,
+    /// Image path or URL shown above the title
+    pub logo: Option<String>,
+    /// Marking printed at the top and bottom (`CONFIDENTIAL`, `TLP:AMBER`)
+    pub classification: Option<String>,
+    pub severity_scheme: SeverityScheme,
+    /// Names replacing the scheme's, by severity (`critical = "P1 - Urgent"`)
+    pub severity_labels: BTreeMap<String, String>,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            sections: vec![
+                ReportSection::ExecutiveSummary,
+                ReportSection::Methodology,
+                ReportSection::Findings,
+                ReportSection::HostDetails,
+                ReportSection::Appendix,
+            ],
+            title: None,
+            client: None,
+            author: None,
+            logo: None,
+            classification: None,
+            severity_scheme: SeverityScheme::default(),
+            severity_labels: BTreeMap::new(),
+        }
+    }
+}
+
+impl ReportConfig {
+    /// How a finding severity (`critical`, `high`, ...) is shown in the report
+    pub fn severity_label(&self, severity: &str) -> String {
+        self.severity_labels
+            .get(severity)
+            .cloned()
+            .unwrap_or_else(|| self.severity_scheme.label(severity))
+    }
+}
+
+/// A part of the report that can be turned off or moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportSection {
+    ExecutiveSummary,
+    Methodology,
+    Findings,
+    HostDetails,
+    Appendix,
+}
+
+impl ReportSection {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::ExecutiveSummary => "Executive Summary",
+            Self::Methodology => "Methodology",
+            Self::Findings => "Findings",
+            Self::HostDetails => "Host Details",
+            Self::Appendix => "Appendix",
+        }
+    }
+}
+
+/// Rating scale findings are presented in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeverityScheme {
+    /// Critical, High, Medium, Low, Informational
+    #[default]
+    Standard,
+    /// CVSS v3 qualitative ratings with their score ranges
+    Cvss,
+    /// P1 (critical) to P5 (informational)
+    Priority,
+}
+
+impl SeverityScheme {
+    /// Display name of a severity; unknown severities are shown as stored
+    pub fn label(&self, severity: &str) -> String {
+        let label = match (self, severity) {
+            (Self::Standard, "critical") => "Critical",
+            (Self::Standard, "high") => "High",
+            (Self::Standard, "medium") => "Medium",
+            (Self::Standard, "low") => "Low",
+            (Self::Standard, "info") => "Informational",
+            (Self::Cvss, "critical") => "Critical (9.0-10.0)",
+            (Self::Cvss, "high") => "High (7.0-8.9)",
+            (Self::Cvss, "medium") => "Medium (4.0-6.9)",
+            (Self::Cvss, "low") => "Low (0.1-3.9)",
+            (Self::Cvss, "info") => "None (0.0)",
+            (Self::Priority, "critical") => "P1",
+            (Self::Priority, "high") => "P2",
+            (Self::Priority, "medium") => "P3",
+            (Self::Priority, "low") => "P4",
+            (Self::Priority, "info") => "P5",
+            _ => severity,
+        };
+        label.to_string()
+    }
+}
+
+/// Issue trackers findings are pushed to with `yinx findings push`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TicketsConfig {
+    pub jira: JiraConfig,
+    pub gitlab: GitlabConfig,
+}
+
+/// Jira Cloud or Data Center project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JiraConfig {
+    /// Base URL (`https://example.atlassian.net`); empty disables the target
+    pub url: String,
+    /// Project key
+    pub project: String,
+    pub issue_type: String,
+    /// Variable holding the API token (Cloud) or personal access token
+    pub token_env: String,
+    /// Variable holding the account email; Cloud only, Data Center uses the token alone
+    pub user_env: String,
+}
+
+impl Default for JiraConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            project: String::new(),
+            issue_type: "Bug".to_string(),
+            token_env: "JIRA_TOKEN".to_string(),
+            user_env: "JIRA_USER".to_string(),
+        }
+    }
+}
+
+/// GitLab project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitlabConfig {
+    /// Instance URL; empty disables the target
+    pub url: String,
+    /// Project ID or path (`group/project`)
+    pub project: String,
+    /// Variable holding a token with the `api` scope
+    pub token_env: String,
+    /// Labels added to every issue, next to `severity::<level>`
+    pub labels: Vec<String>,
+}
+
+impl Default for GitlabConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            project: String::new(),
+            token_env: "GITLAB_TOKEN".to_string(),
+            labels: vec!["yinx".to_string()],
+        }
+    }
+}
+
+/// Exam proof-file detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProofConfig {
+    pub enabled: bool,
+    /// Files whose contents count as proof when read
+    pub files: Vec<String>,
+    /// Commands that print a file (`cat`, `type`, ...)
+    pub readers: Vec<String>,
+    /// Regex an output line must match to be taken as the proof value
+    pub flag_pattern: String,
+}
+
+impl Default for ProofConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            files: vec!["proof.txt".to_string(), "local.txt".to_string()],
+            readers: [
+                "cat",
+                "type",
+                "more",
+                "less",
+                "head",
+                "tail",
+                "Get-Content",
+                "gc",
+            ]
+            .iter()
+            .map(|r| r.to_string())
+            .collect(),
+            flag_pattern: "^[0-9a-fA-F]{32}$".to_string(),
+        }
+    }
+}
+
+/// Exam clock for sessions started with `--deadline`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadlineConfig {
+    /// Warn when this much time is left, e.g. ["4h", "1h", "15m"]
+    #[serde(default = "default_deadline_warnings")]
+    pub warn_at: Vec<String>,
+}
+
+fn default_deadline_warnings() -> Vec<String> {
+    vec!["4h".to_string(), "1h".to_string(), "15m".to_string()]
+}
+
+impl Default for DeadlineConfig {
+    fn default() -> Self {
+        Self {
+            warn_at: default_deadline_warnings(),
+        }
+    }
+}
+
+/// Hints attached to captures whose command failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriageConfig {
+    pub enabled: bool,
+    /// Extra rules, tried before the built-in ones
+    pub rules: Vec<TriageRuleConfig>,
+}
+
+impl Default for TriageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// One `[[triage.rules]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageRuleConfig {
+    pub name: String,
+    /// Regex over the failed command's output, case-insensitive
+    pub pattern: String,
+    pub hint: String,
+}
+
+/// Pipeline stage a hook runs at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+    /// After the capture record is stored
+    PostCapture,
+    /// After entities are extracted from the output
+    PostExtraction,
+    /// Before a report is written (reserved until reports exist)
+    PreReport,
+    /// After a failed command is triaged; an `explanation` string in the
+    /// answer is shown with the hint
+    Triage,
+}
+
+impl HookStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PostCapture => "post_capture",
+            Self::PostExtraction => "post_extraction",
+            Self::PreReport => "pre_report",
+            Self::Triage => "triage",
+        }
+    }
+}
+
+/// External executable run at a pipeline stage (`[[hooks]]`)
+///
+/// The hook gets the stage payload as JSON on stdin and may print a JSON
+/// object on stdout, which is stored as an annotation on the capture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub name: String,
+    pub stage: HookStage,
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Killed and skipped after this long
+    #[serde(default = "default_hook_timeout")]
+    pub timeout: String,
+}
+
+fn default_hook_timeout() -> String {
+    "5s".to_string()
+}
+
 /// Profile-specific configuration overrides
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProfileOverrides {
@@ -176,6 +1269,81 @@ pub struct ProfileOverrides {
     pub embedding_model: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub llm_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hnsw_ef_search: Option<usize>,
+    /// Filter patterns file used instead of `patterns.filters_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filters_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_max_tokens: Option<usize>,
+}
+
+/// Parse a size string such as "512", "64KB", "10MB" or "1GB" (powers of 1024)
+///
+/// Bare numbers are bytes.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim().to_ascii_uppercase();
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s.as_str(), ""),
+    };
+    let multiplier: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "KB" => 1 << 10,
+        "MB" => 1 << 20,
+        "GB" => 1 << 30,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Parse a duration string such as "500ms", "30s", "10m" or "2h"
+///
+/// Bare numbers are treated as seconds.
+pub fn parse_duration(s: &str) -> Option<std::time::Duration> {
+    use std::time::Duration;
+
+    let s = s.trim();
+    // Check "ms" before "s" because "ms" ends with "s"
+    if let Some(ms) = s.strip_suffix("ms") {
+        ms.trim().parse().ok().map(Duration::from_millis)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        secs.trim().parse().ok().map(Duration::from_secs)
+    } else if let Some(mins) = s.strip_suffix('m') {
+        mins.trim()
+            .parse::<u64>()
+            .ok()
+            .map(|m| Duration::from_secs(m * 60))
+    } else if let Some(hours) = s.strip_suffix('h') {
+        hours
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .map(|h| Duration::from_secs(h * 3600))
+    } else if let Some(days) = s.strip_suffix('d') {
+        days.trim()
+            .parse::<u64>()
+            .ok()
+            .map(|d| Duration::from_secs(d * 86_400))
+    } else {
+        s.parse().ok().map(Duration::from_secs)
+    }
+}
+
+/// Segments of a dotted config key; double quotes keep dots in a segment
+fn split_key(key: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut quoted = false;
+    for c in key.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => segments.push(std::mem::take(&mut segment)),
+            c => segment.push(c),
+        }
+    }
+    segments.push(segment);
+    (!quoted && segments.iter().all(|s| !s.is_empty())).then_some(segments)
 }
 
 impl Config {
@@ -212,6 +1380,65 @@ impl Config {
         Ok(())
     }
 
+    /// Set one value in the config file at `path`, keeping the rest
+    ///
+    /// `key` is dotted (`llm.enabled`); quote a segment that has dots of its
+    /// own (`tools.aliases."scan.sh"`). `value` is read as a TOML value when
+    /// it parses as one (`true`, `42`, `["a", "b"]`) and as a string
+    /// otherwise. The key must be one yinx knows, and the result must
+    /// validate, before anything is written.
+    pub fn set_value(path: &Path, key: &str, value: &str) -> Result<()> {
+        let invalid = |message: String| YinxError::InvalidConfigValue {
+            path: key.to_string(),
+            message,
+        };
+        let segments = split_key(key).ok_or_else(|| invalid("Malformed key".to_string()))?;
+        let content = std::fs::read_to_string(path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read config file: {:?}", path),
+        })?;
+        let mut document: toml::Table = toml::from_str(&content)?;
+
+        let value = toml::from_str::<toml::Table>(&format!("value = {}", value))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+        let (last, parents) = segments
+            .split_last()
+            .expect("split_key never returns empty");
+        let mut table = &mut document;
+        for segment in parents {
+            table = table
+                .entry(segment.as_str())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| invalid(format!("'{}' is not a section", segment)))?;
+        }
+        table.insert(last.clone(), value);
+
+        let content = toml::to_string_pretty(&document)?;
+        let config: Config =
+            toml::from_str(&content).map_err(|e| invalid(e.message().trim().to_string()))?;
+        // Unknown keys deserialize fine and would be silently ignored
+        let known = toml::Table::try_from(&config)?;
+        let mut node = Some(&known);
+        for segment in parents {
+            node = node
+                .and_then(|table| table.get(segment.as_str()))
+                .and_then(toml::Value::as_table);
+        }
+        if !node.is_some_and(|table| table.contains_key(last.as_str())) {
+            return Err(invalid("Unknown configuration key".to_string()));
+        }
+        ConfigValidator::validate(&config)?;
+
+        std::fs::write(path, content).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write config file: {:?}", path),
+        })?;
+        Ok(())
+    }
+
     /// Load configuration with a specific profile applied
     pub fn load_with_profile(path: &Path, profile: &str) -> Result<Self> {
         let mut config = Self::load(path)?;
@@ -231,6 +1458,15 @@ impl Config {
             if let Some(enabled) = overrides.llm_enabled {
                 self.llm.enabled = enabled;
             }
+            if let Some(ef_search) = overrides.hnsw_ef_search {
+                self.retrieval.hnsw_ef_search = ef_search;
+            }
+            if let Some(filters_file) = &overrides.filters_file {
+                self.patterns.filters_file = filters_file.clone();
+            }
+            if let Some(max_tokens) = overrides.chunk_max_tokens {
+                self.indexing.chunk_max_tokens = max_tokens;
+            }
         }
         Ok(())
     }
@@ -285,14 +1521,15 @@ impl Config {
         let home_dir = dirs::home_dir()
             .ok_or_else(|| YinxError::Config("Cannot determine home directory".to_string()))?;
 
-        Ok(home_dir.join(".yinx"))
+        let data_dir = default_home();
+        Ok(home_dir.join(data_dir.strip_prefix("~").unwrap_or(&data_dir)))
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
-        let data_dir = PathBuf::from("~/.yinx");
-        let config_dir = PathBuf::from("~/.config/yinx");
+        let data_dir = default_home();
+        let config_dir = default_config_dir();
 
         Self {
             meta: MetaConfig {
@@ -303,27 +1540,44 @@ impl Default for Config {
             storage: StorageConfig {
                 data_dir: data_dir.clone(),
                 max_blob_size: "10MB".to_string(),
+                truncate_head: default_truncate_head(),
+                slow_query: default_slow_query(),
+                sqlite: SqliteConfig::default(),
             },
             capture: CaptureConfig {
                 buffer_size: 10000,
                 batch_size: 100,
                 flush_interval: "5s".to_string(),
+                idle_timeout: default_idle_timeout(),
+                exclude_commands: default_exclude_commands(),
+                dedup_window: default_dedup_window(),
+                register_targets: default_register_targets(),
+                context_env: default_context_env(),
+                record_context: default_record_context(),
+                dry_run: false,
+                handoff_threshold: default_handoff_threshold(),
             },
             daemon: DaemonConfig {
                 socket_path: data_dir.join("daemon.sock"),
                 pid_file: data_dir.join("daemon.pid"),
                 log_file: data_dir.join("logs").join("daemon.log"),
                 max_connections: 10,
+                rate_limit: RateLimitConfig::default(),
+                ipc_encoding: IpcEncoding::default(),
+                supervisor: SupervisorConfig::default(),
+                alarms: AlarmConfig::default(),
             },
             patterns: PatternsConfig {
                 entities_file: config_dir.join("entities.toml"),
                 tools_file: config_dir.join("tools.toml"),
                 filters_file: config_dir.join("filters.toml"),
+                rules_file: config_dir.join("rules.toml"),
             },
             embedding: EmbeddingConfig {
                 model: "all-MiniLM-L6-v2".to_string(),
                 mode: "offline".to_string(),
                 batch_size: 32,
+                preload: ModelPreload::Lazy,
             },
             llm: LlmConfig {
                 enabled: false,
@@ -331,10 +1585,128 @@ impl Default for Config {
                 api_key_env: "GROQ_API_KEY".to_string(),
                 model: "llama-3.1-70b".to_string(),
                 temperature: 0.1,
+                answer_cache: default_answer_cache(),
+                answer_cache_threshold: default_answer_cache_threshold(),
+                context_tokens: default_context_tokens(),
+                tokenizer_file: None,
+                injection_action: InjectionAction::default(),
             },
             indexing: IndexingConfig::default(),
             retrieval: RetrievalConfig::default(),
+            tools: ToolMappingConfig::default(),
+            vault: VaultConfig::default(),
+            acl: AclConfig::default(),
+            hooks: Vec::new(),
+            deadline: DeadlineConfig::default(),
+            proof: ProofConfig::default(),
+            triage: TriageConfig::default(),
+            remote: RemoteConfig::default(),
+            sources: SourcesConfig::default(),
+            tickets: TicketsConfig::default(),
+            summaries: SummariesConfig::default(),
+            enrichment: EnrichmentConfig::default(),
+            report: ReportConfig::default(),
             profiles: HashMap::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_set_value() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        Config::default().save(&path).unwrap();
+
+        Config::set_value(&path, "tools.aliases.nmapAll", "nmap").unwrap();
+        Config::set_value(&path, "tools.aliases.\"scan.sh\"", "nmap").unwrap();
+        Config::set_value(&path, "capture.dry_run", "true").unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.tools.aliases["nmapAll"], "nmap");
+        assert_eq!(config.tools.aliases["scan.sh"], "nmap");
+        assert!(config.capture.dry_run);
+
+        // Typos, wrong types and invalid values leave the file alone
+        let before = std::fs::read_to_string(&path).unwrap();
+        assert!(Config::set_value(&path, "capture.dryrun", "true").is_err());
+        assert!(Config::set_value(&path, "capture.buffer_size", "lots").is_err());
+        assert!(Config::set_value(&path, "capture.buffer_size", "0").is_err());
+        assert!(Config::set_value(&path, "tools.aliases.\"scan.sh", "nmap").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("64KB"), Some(64 * 1024));
+        assert_eq!(parse_size("10 mb"), Some(10 * 1024 * 1024));
+        assert_eq!(parse_size("1GB"), Some(1 << 30));
+        assert_eq!(parse_size("MB"), None);
+        assert_eq!(parse_size("10TB"), None);
+    }
+
+    #[test]
+    fn test_sqlite_profile_overrides() {
+        let sqlite: SqliteConfig = toml::from_str(
+            r#"
+            profile = "hdd"
+            pool_size = 2
+            cache_size = "8MB"
+            "#,
+        )
+        .unwrap();
+        let options = sqlite.options();
+        assert_eq!(options.pool_size, 2);
+        assert_eq!(options.cache_size, 8 << 20);
+        assert_eq!(options.busy_timeout, SqliteOptions::hdd().busy_timeout);
+        assert_eq!(SqliteConfig::default().options(), SqliteOptions::ssd());
+    }
+
+    #[test]
+    fn test_ef_search_effort() {
+        // Configs from before the per-query knob keep working
+        let retrieval: RetrievalConfig = toml::from_str(
+            r#"
+            search_multiplier = 2
+            rrf_k = 60.0
+            semantic_weight = 0.7
+            keyword_weight = 0.3
+            hnsw_ef_search = 64
+            enable_reranking = false
+            reranker_model = "none"
+            rerank_candidates_limit = 10
+            min_similarity_threshold = 0.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(retrieval.ef_search(SearchEffort::Fast), 16);
+        assert_eq!(retrieval.ef_search(SearchEffort::Balanced), 64);
+        assert_eq!(retrieval.ef_search(SearchEffort::Accurate), 200);
+
+        let mut config = Config {
+            retrieval,
+            ..Config::default()
+        };
+        config.profiles.insert(
+            "recall".to_string(),
+            toml::from_str("hnsw_ef_search = 400").unwrap(),
+        );
+        config.apply_profile("recall").unwrap();
+        assert_eq!(config.retrieval.ef_search(SearchEffort::default()), 400);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86_400)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("later"), None);
+    }
+}
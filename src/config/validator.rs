@@ -1,6 +1,10 @@
-use crate::config::Config;
+use crate::config::{Config, RerankDevice};
+use crate::embedding::KeywordTokenizer;
 use crate::error::{Result, ValidationError, YinxError};
 
+/// Shortest shared secret accepted for the remote API
+const MIN_REMOTE_TOKEN_LEN: usize = 16;
+
 /// Configuration validator
 pub struct ConfigValidator;
 
@@ -21,6 +25,8 @@ impl ConfigValidator {
         // Validate pattern file paths
         Self::validate_patterns(config, &mut errors);
 
+        Self::validate_tool_aliases(config, &mut errors);
+
         // Validate embedding settings
         Self::validate_embedding(config, &mut errors);
 
@@ -30,6 +36,38 @@ impl ConfigValidator {
         // Validate indexing settings
         Self::validate_indexing(config, &mut errors);
 
+        // Validate search fusion
+        Self::validate_retrieval(config, &mut errors);
+
+        // Validate shared-daemon access control
+        Self::validate_acl(config, &mut errors);
+
+        // Validate pipeline hooks
+        Self::validate_hooks(config, &mut errors);
+
+        // Validate exam clock warnings
+        Self::validate_deadline(config, &mut errors);
+        Self::validate_supervisor(config, &mut errors);
+        Self::validate_alarms(config, &mut errors);
+        Self::validate_ipc_limits(config, &mut errors);
+
+        // Validate the remote API
+        Self::validate_remote(config, &mut errors);
+        Self::validate_sources(config, &mut errors);
+        Self::validate_tickets(config, &mut errors);
+        Self::validate_summaries(config, &mut errors);
+        Self::validate_enrichment(config, &mut errors);
+        Self::validate_triage(config, &mut errors);
+        Self::validate_report(config, &mut errors);
+
+        // Validate proof detection
+        if let Err(e) = regex::Regex::new(&config.proof.flag_pattern) {
+            errors.push(ValidationError::new(
+                "proof.flag_pattern",
+                format!("Invalid regex: {}", e),
+            ));
+        }
+
         if errors.is_empty() {
             Ok(())
         } else {
@@ -50,12 +88,77 @@ impl ConfigValidator {
     fn validate_storage(config: &Config, errors: &mut Vec<ValidationError>) {
         // Validate max_blob_size format
         let size_str = &config.storage.max_blob_size;
-        if !Self::is_valid_size_string(size_str) {
+        if super::parse_size(size_str).is_none() {
             errors.push(ValidationError::new(
                 "storage.max_blob_size",
                 format!("Invalid size format: {}", size_str),
             ));
         }
+
+        if !(0.0..=1.0).contains(&config.storage.truncate_head) {
+            errors.push(ValidationError::new(
+                "storage.truncate_head",
+                "Must be between 0.0 and 1.0",
+            ));
+        }
+
+        let slow = &config.storage.slow_query;
+        if super::parse_duration(slow).is_none() {
+            errors.push(ValidationError::new(
+                "storage.slow_query",
+                format!("Invalid duration: {}", slow),
+            ));
+        }
+
+        Self::validate_sqlite(config, errors);
+    }
+
+    fn validate_sqlite(config: &Config, errors: &mut Vec<ValidationError>) {
+        let sqlite = &config.storage.sqlite;
+        if let Some(pool_size) = sqlite.pool_size {
+            if !(1..=64).contains(&pool_size) {
+                errors.push(ValidationError::new(
+                    "storage.sqlite.pool_size",
+                    "Must be between 1 and 64",
+                ));
+            }
+        }
+        if let Some(timeout) = &sqlite.busy_timeout {
+            if super::parse_duration(timeout).is_none() {
+                errors.push(ValidationError::new(
+                    "storage.sqlite.busy_timeout",
+                    format!("Invalid duration: {}", timeout),
+                ));
+            }
+        }
+        // Without automatic checkpoints nothing would ever shrink the WAL
+        if sqlite.wal_autocheckpoint == Some(0) {
+            errors.push(ValidationError::new(
+                "storage.sqlite.wal_autocheckpoint",
+                "Must be at least 1 page",
+            ));
+        }
+        if let Some(size) = &sqlite.mmap_size {
+            if super::parse_size(size).is_none() {
+                errors.push(ValidationError::new(
+                    "storage.sqlite.mmap_size",
+                    format!("Invalid size format: {}", size),
+                ));
+            }
+        }
+        if let Some(size) = &sqlite.cache_size {
+            match super::parse_size(size) {
+                None => errors.push(ValidationError::new(
+                    "storage.sqlite.cache_size",
+                    format!("Invalid size format: {}", size),
+                )),
+                Some(bytes) if bytes < 1 << 20 => errors.push(ValidationError::new(
+                    "storage.sqlite.cache_size",
+                    "Must be at least 1MB",
+                )),
+                Some(_) => {}
+            }
+        }
     }
 
     fn validate_capture(config: &Config, errors: &mut Vec<ValidationError>) {
@@ -83,6 +186,320 @@ impl ConfigValidator {
                 format!("Invalid duration format: {}", interval),
             ));
         }
+
+        let idle = &config.capture.idle_timeout;
+        if super::parse_duration(idle).is_none() {
+            errors.push(ValidationError::new(
+                "capture.idle_timeout",
+                format!("Invalid duration format: {}", idle),
+            ));
+        }
+
+        let window = &config.capture.dedup_window;
+        if super::parse_duration(window).is_none() {
+            errors.push(ValidationError::new(
+                "capture.dedup_window",
+                format!("Invalid duration format: {}", window),
+            ));
+        }
+
+        let threshold = &config.capture.handoff_threshold;
+        if super::parse_size(threshold).is_none() {
+            errors.push(ValidationError::new(
+                "capture.handoff_threshold",
+                format!("Invalid size format: {}", threshold),
+            ));
+        }
+
+        for pattern in &config.capture.exclude_commands {
+            if let Err(e) = regex::Regex::new(pattern) {
+                errors.push(ValidationError::new(
+                    "capture.exclude_commands",
+                    format!("Invalid pattern '{}': {}", pattern, e),
+                ));
+            }
+        }
+    }
+
+    fn validate_acl(config: &Config, errors: &mut Vec<ValidationError>) {
+        let scope = &config.acl.default_scope;
+        if scope != "self" && scope != "all" {
+            errors.push(ValidationError::new(
+                "acl.default_scope",
+                format!("Expected 'self' or 'all', got '{}'", scope),
+            ));
+        }
+    }
+
+    fn validate_remote(config: &Config, errors: &mut Vec<ValidationError>) {
+        let remote = &config.remote;
+        if !remote.enabled {
+            return;
+        }
+        if remote.listen.parse::<std::net::SocketAddr>().is_err() {
+            errors.push(ValidationError::new(
+                "remote.listen",
+                format!(
+                    "Expected an address like 127.0.0.1:7701, got '{}'",
+                    remote.listen
+                ),
+            ));
+        }
+        if remote.token.len() < MIN_REMOTE_TOKEN_LEN {
+            errors.push(ValidationError::new(
+                "remote.token",
+                format!(
+                    "Must be at least {} characters when the remote API is enabled",
+                    MIN_REMOTE_TOKEN_LEN
+                ),
+            ));
+        }
+    }
+
+    fn validate_tickets(config: &Config, errors: &mut Vec<ValidationError>) {
+        let tickets = &config.tickets;
+        for (target, url, project) in [
+            ("jira", &tickets.jira.url, &tickets.jira.project),
+            ("gitlab", &tickets.gitlab.url, &tickets.gitlab.project),
+        ] {
+            if url.is_empty() {
+                continue;
+            }
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                errors.push(ValidationError::new(
+                    format!("tickets.{}.url", target),
+                    format!("Expected an http(s) URL, got '{}'", url),
+                ));
+            }
+            if project.is_empty() {
+                errors.push(ValidationError::new(
+                    format!("tickets.{}.project", target),
+                    "Required when the URL is set",
+                ));
+            }
+        }
+    }
+
+    fn validate_summaries(config: &Config, errors: &mut Vec<ValidationError>) {
+        let summaries = &config.summaries;
+        if summaries.max_chars < 100 {
+            errors.push(ValidationError::new(
+                "summaries.max_chars",
+                format!("Must be at least 100, got {}", summaries.max_chars),
+            ));
+        }
+        if summaries.batch_size == 0 {
+            errors.push(ValidationError::new(
+                "summaries.batch_size",
+                "Must be greater than 0",
+            ));
+        }
+    }
+
+    fn validate_enrichment(config: &Config, errors: &mut Vec<ValidationError>) {
+        let dns = &config.enrichment.dns;
+        if dns.batch_size == 0 {
+            errors.push(ValidationError::new(
+                "enrichment.dns.batch_size",
+                "Must be greater than 0",
+            ));
+        }
+        if super::parse_duration(&dns.refresh).is_none() {
+            errors.push(ValidationError::new(
+                "enrichment.dns.refresh",
+                format!("Invalid duration format: {}", dns.refresh),
+            ));
+        }
+        if config.enrichment.asn.batch_size == 0 {
+            errors.push(ValidationError::new(
+                "enrichment.asn.batch_size",
+                "Must be greater than 0",
+            ));
+        }
+    }
+
+    fn validate_triage(config: &Config, errors: &mut Vec<ValidationError>) {
+        for (i, rule) in config.triage.rules.iter().enumerate() {
+            if let Err(e) = regex::Regex::new(&rule.pattern) {
+                errors.push(ValidationError::new(
+                    format!("triage.rules[{}].pattern", i),
+                    format!("Invalid regex in '{}': {}", rule.name, e),
+                ));
+            }
+        }
+    }
+
+    fn validate_report(config: &Config, errors: &mut Vec<ValidationError>) {
+        let sections = &config.report.sections;
+        if sections.is_empty() {
+            errors.push(ValidationError::new(
+                "report.sections",
+                "At least one section is required",
+            ));
+        }
+        for (i, section) in sections.iter().enumerate() {
+            if sections[..i].contains(section) {
+                errors.push(ValidationError::new(
+                    "report.sections",
+                    format!("'{}' is listed more than once", section.title()),
+                ));
+            }
+        }
+    }
+
+    fn validate_sources(config: &Config, errors: &mut Vec<ValidationError>) {
+        let sources = &config.sources;
+        if crate::config::parse_duration(&sources.flush_interval).is_none_or(|d| d.is_zero()) {
+            errors.push(ValidationError::new(
+                "sources.flush_interval",
+                format!("Invalid duration: {}", sources.flush_interval),
+            ));
+        }
+        if sources.max_lines == 0 {
+            errors.push(ValidationError::new(
+                "sources.max_lines",
+                "Must be greater than 0",
+            ));
+        }
+
+        let syslog = &sources.syslog;
+        if !syslog.enabled {
+            return;
+        }
+        let listen_ok = match syslog.listen.strip_prefix("unix:") {
+            Some(path) => !path.is_empty(),
+            None => syslog
+                .listen
+                .strip_prefix("udp:")
+                .unwrap_or(&syslog.listen)
+                .parse::<std::net::SocketAddr>()
+                .is_ok(),
+        };
+        if !listen_ok {
+            errors.push(ValidationError::new(
+                "sources.syslog.listen",
+                format!(
+                    "Expected udp:HOST:PORT or unix:PATH, got '{}'",
+                    syslog.listen
+                ),
+            ));
+        }
+        for (i, facility) in syslog.facilities.iter().enumerate() {
+            if !crate::daemon::is_facility(facility) {
+                errors.push(ValidationError::new(
+                    format!("sources.syslog.facilities[{}]", i),
+                    format!("Unknown syslog facility: {}", facility),
+                ));
+            }
+        }
+    }
+
+    fn validate_deadline(config: &Config, errors: &mut Vec<ValidationError>) {
+        for (i, warning) in config.deadline.warn_at.iter().enumerate() {
+            if crate::config::parse_duration(warning).is_none() {
+                errors.push(ValidationError::new(
+                    format!("deadline.warn_at[{}]", i),
+                    format!("Invalid duration: {}", warning),
+                ));
+            }
+        }
+    }
+
+    fn validate_supervisor(config: &Config, errors: &mut Vec<ValidationError>) {
+        let supervisor = &config.daemon.supervisor;
+        let mut duration = |field: &str, value: &str| {
+            let parsed = crate::config::parse_duration(value);
+            if parsed.is_none() {
+                errors.push(ValidationError::new(
+                    format!("daemon.supervisor.{}", field),
+                    format!("Invalid duration: {}", value),
+                ));
+            }
+            parsed
+        };
+        let initial = duration("initial_backoff", &supervisor.initial_backoff);
+        let max = duration("max_backoff", &supervisor.max_backoff);
+        duration("crash_loop_window", &supervisor.crash_loop_window);
+        if let (Some(initial), Some(max)) = (initial, max) {
+            if initial > max {
+                errors.push(ValidationError::new(
+                    "daemon.supervisor.initial_backoff",
+                    "Must not be longer than max_backoff",
+                ));
+            }
+        }
+        if supervisor.crash_loop_restarts == 0 {
+            errors.push(ValidationError::new(
+                "daemon.supervisor.crash_loop_restarts",
+                "Must be at least 1",
+            ));
+        }
+    }
+
+    fn validate_alarms(config: &Config, errors: &mut Vec<ValidationError>) {
+        let alarms = &config.daemon.alarms;
+        for (field, value) in [
+            ("flush_latency", &alarms.flush_latency),
+            ("embedding_backlog_age", &alarms.embedding_backlog_age),
+        ] {
+            if crate::config::parse_duration(value).is_none() {
+                errors.push(ValidationError::new(
+                    format!("daemon.alarms.{}", field),
+                    format!("Invalid duration: {}", value),
+                ));
+            }
+        }
+    }
+
+    fn validate_ipc_limits(config: &Config, errors: &mut Vec<ValidationError>) {
+        let daemon = &config.daemon;
+        if daemon.max_connections == 0 {
+            errors.push(ValidationError::new(
+                "daemon.max_connections",
+                "Must be at least 1",
+            ));
+        }
+        let limit = &daemon.rate_limit;
+        if !limit.per_second.is_finite() || limit.per_second < 0.0 {
+            errors.push(ValidationError::new(
+                "daemon.rate_limit.per_second",
+                "Must be a non-negative number",
+            ));
+        } else if limit.per_second > 0.0 && limit.burst == 0 {
+            errors.push(ValidationError::new(
+                "daemon.rate_limit.burst",
+                "Must be at least 1 while the rate limit is on",
+            ));
+        }
+    }
+
+    fn validate_hooks(config: &Config, errors: &mut Vec<ValidationError>) {
+        let mut names = std::collections::HashSet::new();
+        for (i, hook) in config.hooks.iter().enumerate() {
+            if hook.name.is_empty() {
+                errors.push(ValidationError::new(
+                    format!("hooks[{}].name", i),
+                    "Hook name cannot be empty",
+                ));
+            } else if !names.insert(hook.name.as_str()) {
+                errors.push(ValidationError::new(
+                    format!("hooks[{}].name", i),
+                    format!("Duplicate hook name: {}", hook.name),
+                ));
+            }
+            if hook.command.as_os_str().is_empty() {
+                errors.push(ValidationError::new(
+                    format!("hooks[{}].command", i),
+                    "Hook command cannot be empty",
+                ));
+            }
+            if crate::config::parse_duration(&hook.timeout).is_none() {
+                errors.push(ValidationError::new(
+                    format!("hooks[{}].timeout", i),
+                    format!("Invalid duration: {}", hook.timeout),
+                ));
+            }
+        }
     }
 
     fn validate_patterns(config: &Config, errors: &mut Vec<ValidationError>) {
@@ -114,6 +531,23 @@ impl ConfigValidator {
         }
     }
 
+    fn validate_tool_aliases(config: &Config, errors: &mut Vec<ValidationError>) {
+        for (alias, tool) in &config.tools.aliases {
+            if alias.is_empty() || alias.contains(char::is_whitespace) {
+                errors.push(ValidationError::new(
+                    "tools.aliases",
+                    format!("'{}' is not a command name", alias),
+                ));
+            }
+            if tool.trim().is_empty() {
+                errors.push(ValidationError::new(
+                    format!("tools.aliases.{}", alias),
+                    "Tool name cannot be empty",
+                ));
+            }
+        }
+    }
+
     fn validate_embedding(config: &Config, errors: &mut Vec<ValidationError>) {
         // Validate mode
         let mode = &config.embedding.mode;
@@ -169,6 +603,36 @@ impl ConfigValidator {
             ));
         }
 
+        let threshold = config.llm.answer_cache_threshold;
+        if !(0.0..=1.0).contains(&threshold) {
+            errors.push(ValidationError::new(
+                "llm.answer_cache_threshold",
+                format!(
+                    "Cosine threshold must be between 0.0 and 1.0, got {}",
+                    threshold
+                ),
+            ));
+        }
+
+        if config.llm.context_tokens < 256 {
+            errors.push(ValidationError::new(
+                "llm.context_tokens",
+                format!(
+                    "Context budget must be at least 256 tokens, got {}",
+                    config.llm.context_tokens
+                ),
+            ));
+        }
+
+        if let Some(path) = &config.llm.tokenizer_file {
+            if !path.is_file() {
+                errors.push(ValidationError::new(
+                    "llm.tokenizer_file",
+                    format!("Tokenizer file not found: {}", path.display()),
+                ));
+            }
+        }
+
         // Validate provider
         let provider = &config.llm.provider;
         let valid_providers = ["groq", "openai", "anthropic", "ollama"];
@@ -206,16 +670,98 @@ impl ConfigValidator {
                 "HNSW M must be greater than 0",
             ));
         }
+
+        let threshold = config.indexing.rebuild_threshold;
+        if !(threshold > 0.0 && threshold <= 1.0) {
+            errors.push(ValidationError::new(
+                "indexing.rebuild_threshold",
+                "Rebuild threshold must be greater than 0 and at most 1",
+            ));
+        }
+
+        let tokenizers = &config.indexing.tokenizers;
+        if tokenizers.text == KeywordTokenizer::Off {
+            errors.push(ValidationError::new(
+                "indexing.tokenizers.text",
+                "The text field can't be turned off",
+            ));
+        }
+        if tokenizers.ngram_min == 0 || tokenizers.ngram_min > tokenizers.ngram_max {
+            errors.push(ValidationError::new(
+                "indexing.tokenizers.ngram_min",
+                format!(
+                    "N-gram lengths must satisfy 0 < ngram_min <= ngram_max (got {} and {})",
+                    tokenizers.ngram_min, tokenizers.ngram_max
+                ),
+            ));
+        }
     }
 
-    fn is_valid_size_string(s: &str) -> bool {
-        // Simple validation for size strings like "10MB", "1GB"
-        let s = s.to_uppercase();
-        s.ends_with("KB")
-            || s.ends_with("MB")
-            || s.ends_with("GB")
-            || s.ends_with("B")
-            || s.chars().all(|c| c.is_ascii_digit())
+    fn validate_retrieval(config: &Config, errors: &mut Vec<ValidationError>) {
+        let retrieval = &config.retrieval;
+        let ks = [
+            ("retrieval.rrf_k", Some(retrieval.rrf_k)),
+            ("retrieval.semantic_rrf_k", retrieval.semantic_rrf_k),
+            ("retrieval.keyword_rrf_k", retrieval.keyword_rrf_k),
+        ];
+        for (path, k) in ks {
+            if k.is_some_and(|k| k.is_nan() || k < 0.0) {
+                errors.push(ValidationError::new(path, "RRF K must not be negative"));
+            }
+        }
+
+        // A searched channel with no weight would contribute nothing
+        if retrieval.mode.uses_vector() && retrieval.semantic_weight <= 0.0 {
+            errors.push(ValidationError::new(
+                "retrieval.semantic_weight",
+                format!(
+                    "Must be greater than 0 in {} mode (use mode = \"keyword\" to disable vector search)",
+                    retrieval.mode.as_str()
+                ),
+            ));
+        }
+        if retrieval.mode.uses_keyword() && retrieval.keyword_weight <= 0.0 {
+            errors.push(ValidationError::new(
+                "retrieval.keyword_weight",
+                format!(
+                    "Must be greater than 0 in {} mode (use mode = \"vector\" to disable keyword search)",
+                    retrieval.mode.as_str()
+                ),
+            ));
+        }
+
+        let ef_searches = [
+            ("retrieval.hnsw_ef_search", retrieval.hnsw_ef_search),
+            (
+                "retrieval.hnsw_ef_search_fast",
+                retrieval.hnsw_ef_search_fast,
+            ),
+            (
+                "retrieval.hnsw_ef_search_accurate",
+                retrieval.hnsw_ef_search_accurate,
+            ),
+        ];
+        for (path, ef_search) in ef_searches {
+            if ef_search == 0 {
+                errors.push(ValidationError::new(
+                    path,
+                    "HNSW ef_search must be greater than 0",
+                ));
+            }
+        }
+
+        if retrieval.rerank_batch_size == 0 {
+            errors.push(ValidationError::new(
+                "retrieval.rerank_batch_size",
+                "Must be greater than 0",
+            ));
+        }
+        if retrieval.rerank_device != RerankDevice::Cpu && retrieval.rerank_device_id < 0 {
+            errors.push(ValidationError::new(
+                "retrieval.rerank_device_id",
+                "GPU device id must not be negative",
+            ));
+        }
     }
 
     fn is_valid_duration_string(s: &str) -> bool {
@@ -230,6 +776,7 @@ impl ConfigValidator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{HookConfig, HookStage, ReportSection, RetrievalMode};
     use std::path::PathBuf;
 
     #[test]
@@ -251,4 +798,301 @@ mod tests {
         config.embedding.mode = "invalid".to_string();
         assert!(ConfigValidator::validate(&config).is_err());
     }
+
+    #[test]
+    fn test_invalid_idle_timeout() {
+        let mut config = Config::default();
+        config.capture.idle_timeout = "soon".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_dedup_window() {
+        let mut config = Config::default();
+        config.capture.dedup_window = "a bit".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.capture.handoff_threshold = "huge".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_exclude_pattern() {
+        let mut config = Config::default();
+        config.capture.exclude_commands = vec!["[unclosed".to_string()];
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_hooks() {
+        let hook = |name: &str, timeout: &str| HookConfig {
+            name: name.to_string(),
+            stage: HookStage::PostCapture,
+            command: PathBuf::from("/usr/local/bin/enrich"),
+            args: vec![],
+            timeout: timeout.to_string(),
+        };
+
+        let mut config = Config {
+            hooks: vec![hook("enrich", "2s")],
+            ..Config::default()
+        };
+        assert!(ConfigValidator::validate(&config).is_ok());
+
+        config.hooks = vec![hook("enrich", "2s"), hook("enrich", "2s")];
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        config.hooks = vec![hook("enrich", "later")];
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_truncation_settings() {
+        let mut config = Config::default();
+        config.storage.max_blob_size = "lots".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.storage.truncate_head = 1.5;
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_keyword_tokenizers() {
+        let mut config = Config::default();
+        config.indexing.tokenizers.partial = KeywordTokenizer::Ngram;
+        assert!(ConfigValidator::validate(&config).is_ok());
+
+        config.indexing.tokenizers.ngram_min = 5;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.indexing.tokenizers.text = KeywordTokenizer::Off;
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_slow_query_threshold() {
+        let mut config = Config::default();
+        config.storage.slow_query = "0".to_string();
+        assert!(ConfigValidator::validate(&config).is_ok());
+
+        config.storage.slow_query = "sluggish".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_sqlite_settings() {
+        let mut config = Config::default();
+        config.storage.sqlite.pool_size = Some(0);
+        config.storage.sqlite.busy_timeout = Some("a while".to_string());
+        config.storage.sqlite.wal_autocheckpoint = Some(0);
+        config.storage.sqlite.cache_size = Some("64KB".to_string());
+        match ConfigValidator::validate(&config) {
+            Err(YinxError::ConfigValidation { errors }) => assert_eq!(errors.len(), 4),
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_remote_requires_token() {
+        let mut config = Config::default();
+        config.remote.enabled = true;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        config.remote.token = "0123456789abcdef".to_string();
+        assert!(ConfigValidator::validate(&config).is_ok());
+
+        config.remote.listen = "anywhere".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_syslog_source() {
+        let mut config = Config::default();
+        config.sources.syslog.enabled = true;
+        assert!(ConfigValidator::validate(&config).is_ok());
+
+        config.sources.syslog.facilities.push("secure".to_string());
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        config.sources.syslog.facilities.pop();
+        config.sources.syslog.listen = "tcp:0.0.0.0:514".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_summaries() {
+        let mut config = Config::default();
+        config.summaries.max_chars = 40;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.summaries.batch_size = 0;
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_enrichment() {
+        let mut config = Config::default();
+        config.enrichment.dns.refresh = "daily".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.enrichment.dns.batch_size = 0;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.enrichment.asn.batch_size = 0;
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_ipc_limits() {
+        let mut config = Config::default();
+        config.daemon.max_connections = 0;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.daemon.rate_limit.burst = 0;
+        assert!(ConfigValidator::validate(&config).is_err());
+        config.daemon.rate_limit.per_second = 0.0;
+        assert!(ConfigValidator::validate(&config).is_ok());
+        config.daemon.rate_limit.per_second = -1.0;
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_triage_rule() {
+        let mut config = Config::default();
+        config.triage.rules.push(crate::config::TriageRuleConfig {
+            name: "vpn".to_string(),
+            pattern: "tun0 (down".to_string(),
+            hint: "Reconnect the VPN".to_string(),
+        });
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_supervisor_backoff() {
+        let mut config = Config::default();
+        config.daemon.supervisor.initial_backoff = "10m".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.daemon.supervisor.crash_loop_window = "soon".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_alarm_duration() {
+        let mut config = Config::default();
+        config.daemon.alarms.flush_latency = "quick".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_tool_alias() {
+        let mut config = Config::default();
+        config
+            .tools
+            .aliases
+            .insert("nmap all".to_string(), "nmap".to_string());
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_report_sections() {
+        let mut config = Config::default();
+        config.report.sections.clear();
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.report.sections.push(ReportSection::Findings);
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_ticket_target() {
+        let mut config = Config::default();
+        config.tickets.gitlab.url = "https://gitlab.example.com".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        config.tickets.gitlab.project = "red/exam".to_string();
+        assert!(ConfigValidator::validate(&config).is_ok());
+
+        config.tickets.jira.url = "jira.example.com".to_string();
+        config.tickets.jira.project = "SEC".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_retrieval_channel_weights() {
+        let mut config = Config::default();
+        config.retrieval.keyword_weight = 0.0;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        // Fine once the keyword channel is switched off
+        config.retrieval.mode = RetrievalMode::Vector;
+        assert!(ConfigValidator::validate(&config).is_ok());
+
+        config.retrieval.semantic_rrf_k = Some(-1.0);
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_rerank_settings() {
+        let mut config = Config::default();
+        config.retrieval.rerank_batch_size = 0;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.retrieval.hnsw_ef_search_fast = 0;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.retrieval.rerank_device = RerankDevice::Cuda;
+        config.retrieval.rerank_device_id = -1;
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_answer_cache_threshold() {
+        let mut config = Config::default();
+        config.llm.answer_cache_threshold = 1.5;
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_context_budget() {
+        let mut config = Config::default();
+        config.llm.context_tokens = 100;
+        assert!(ConfigValidator::validate(&config).is_err());
+
+        let mut config = Config::default();
+        config.llm.tokenizer_file = Some("/nonexistent/tokenizer.json".into());
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_proof_pattern() {
+        let mut config = Config::default();
+        config.proof.flag_pattern = "[0-9a-f{32}".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_deadline_warning() {
+        let mut config = Config::default();
+        config.deadline.warn_at = vec!["1h".to_string(), "soonish".to_string()];
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_acl_scope() {
+        let mut config = Config::default();
+        config.acl.default_scope = "team".to_string();
+        assert!(ConfigValidator::validate(&config).is_err());
+    }
 }
@@ -0,0 +1,390 @@
+//! Cracked hash import (hashcat potfiles, john output)
+//!
+//! Cracking results are matched against the hash entities already extracted
+//! from captures. Each match is stored as a credential with its password
+//! sealed in the vault, opens a finding in the capture's session, and raises
+//! findings already open on that capture to high severity.
+
+use crate::error::{Result, YinxError};
+use crate::storage::{CredentialRecord, EntityRecord, StorageManager};
+use std::collections::HashMap;
+
+/// Entity types that hold password hashes
+pub const HASH_ENTITY_TYPES: &[&str] = &["hash_ntlm", "hash_md5", "hash_sha1", "hash_sha256"];
+
+/// Rule recorded on findings opened for cracked hashes
+const FINDING_RULE: &str = "cracked-hash";
+
+/// A cracking result matched to an extracted hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrackedHash {
+    pub session_id: String,
+    pub capture_id: i64,
+    pub hash_type: String,
+    /// Hash as it was extracted
+    pub hash: String,
+    pub username: Option<String>,
+    pub password: String,
+}
+
+/// Result of matching a cracking tool's output
+#[derive(Debug, Default)]
+pub struct CrackMatch {
+    /// Result lines read
+    pub lines: usize,
+    /// One entry per capture the cracked hash was seen in
+    pub matched: Vec<CrackedHash>,
+    /// Lines that matched no extracted hash
+    pub unmatched: usize,
+}
+
+/// What recording the matches changed
+#[derive(Debug, Default)]
+pub struct CrackImportStats {
+    pub credentials: usize,
+    pub findings: usize,
+    pub escalated: usize,
+}
+
+/// An extracted hash with what's known about it
+struct KnownHash {
+    entity: EntityRecord,
+    session_id: String,
+    username: Option<String>,
+}
+
+/// Match hashcat or john output against the hashes of one session (all when None)
+///
+/// Accepts `hash:password` lines (potfiles, `hashcat --show`, `john.pot`)
+/// and john `--show` lines (`user:password:...`), which are matched by a
+/// hash among the trailing fields or by the user name found next to a hash.
+pub fn match_cracked(
+    storage: &StorageManager,
+    output: &str,
+    session_id: Option<&str>,
+) -> Result<CrackMatch> {
+    let known = known_hashes(storage, session_id)?;
+    let mut by_hash: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, hash) in known.iter().enumerate() {
+        let value = hash.entity.value.to_ascii_lowercase();
+        // hashcat cracks the NT half of an LM:NT pair on its own (mode 1000)
+        if let Some((_, nt)) = value.split_once(':') {
+            by_hash.entry(nt.to_string()).or_default().push(i);
+        }
+        by_hash.entry(value).or_default().push(i);
+    }
+
+    let mut result = CrackMatch::default();
+    for line in output.lines().map(str::trim_end) {
+        // john ends --show with "N password hashes cracked, M left"
+        if line.is_empty() || line.contains(" cracked, ") {
+            continue;
+        }
+        result.lines += 1;
+        let Some((indices, password)) =
+            pot_entry(line, &by_hash).or_else(|| show_entry(line, &known, &by_hash))
+        else {
+            result.unmatched += 1;
+            continue;
+        };
+        for i in indices {
+            let hash = &known[i];
+            result.matched.push(CrackedHash {
+                session_id: hash.session_id.clone(),
+                capture_id: hash.entity.capture_id,
+                hash_type: hash.entity.entity_type.clone(),
+                hash: hash.entity.value.clone(),
+                username: hash.username.clone(),
+                password: password.clone(),
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// Store matches as vault-sealed credentials and update findings
+pub fn record_cracked(
+    storage: &StorageManager,
+    matches: &[CrackedHash],
+    source: &str,
+) -> Result<CrackImportStats> {
+    let vault = storage.vault().ok_or_else(|| {
+        YinxError::Vault("Enable the vault (vault.enabled) to store cracked passwords".to_string())
+    })?;
+
+    let mut stats = CrackImportStats::default();
+    let now = chrono::Utc::now().timestamp();
+    for cracked in matches {
+        let credential = CredentialRecord {
+            id: 0,
+            session_id: cracked.session_id.clone(),
+            capture_id: Some(cracked.capture_id),
+            hash_type: cracked.hash_type.clone(),
+            hash: cracked.hash.clone(),
+            username: cracked.username.clone(),
+            sealed: vault.seal(cracked.password.as_bytes())?,
+            source: source.to_string(),
+            cracked_at: now,
+        };
+        if storage.database.insert_credential(&credential)?.is_some() {
+            stats.credentials += 1;
+        }
+
+        let title = match &cracked.username {
+            Some(user) => format!("Cracked password for {}", user),
+            None => format!("Cracked {} {}", cracked.hash_type, cracked.hash),
+        };
+        if storage
+            .database
+            .insert_finding(
+                &cracked.session_id,
+                Some(cracked.capture_id),
+                &title,
+                "high",
+                Some(FINDING_RULE),
+            )?
+            .is_some()
+        {
+            stats.findings += 1;
+        }
+        stats.escalated += storage
+            .database
+            .escalate_capture_findings(cracked.capture_id)?;
+    }
+    Ok(stats)
+}
+
+fn known_hashes(storage: &StorageManager, session_id: Option<&str>) -> Result<Vec<KnownHash>> {
+    let mut sessions: HashMap<i64, Option<String>> = HashMap::new();
+    let mut known = Vec::new();
+    for entity_type in HASH_ENTITY_TYPES {
+        for entity in storage.database.get_entities_by_type(entity_type)? {
+            let session = match sessions.get(&entity.capture_id) {
+                Some(session) => session.clone(),
+                None => {
+                    let session = storage
+                        .database
+                        .get_capture(entity.capture_id)?
+                        .map(|c| c.session_id);
+                    sessions.insert(entity.capture_id, session.clone());
+                    session
+                }
+            };
+            let Some(session) = session else {
+                continue;
+            };
+            if session_id.is_some_and(|id| id != session) {
+                continue;
+            }
+            let username = entity
+                .context
+                .as_deref()
+                .and_then(|context| username_from_context(context, &entity.value));
+            known.push(KnownHash {
+                entity,
+                session_id: session,
+                username,
+            });
+        }
+    }
+    Ok(known)
+}
+
+/// `hash:password`, where the hash may itself contain colons
+fn pot_entry(line: &str, by_hash: &HashMap<String, Vec<usize>>) -> Option<(Vec<usize>, String)> {
+    line.match_indices(':').find_map(|(i, _)| {
+        by_hash
+            .get(&normalize_hash(&line[..i]))
+            .map(|indices| (indices.clone(), decode_password(&line[i + 1..])))
+    })
+}
+
+/// john `--show`: `user:password` followed by the rest of the hash file's fields
+fn show_entry(
+    line: &str,
+    known: &[KnownHash],
+    by_hash: &HashMap<String, Vec<usize>>,
+) -> Option<(Vec<usize>, String)> {
+    let fields: Vec<&str> = line.split(':').collect();
+    let (user, password) = (*fields.first()?, *fields.get(1)?);
+
+    let mut indices: Vec<usize> = fields[2..]
+        .iter()
+        .filter_map(|field| by_hash.get(&normalize_hash(field)))
+        .flatten()
+        .copied()
+        .collect();
+    if indices.is_empty() && user != "?" {
+        indices = known
+            .iter()
+            .enumerate()
+            .filter(|(_, hash)| {
+                hash.username
+                    .as_deref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(user))
+            })
+            .map(|(i, _)| i)
+            .collect();
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    (!indices.is_empty()).then(|| (indices, password.to_string()))
+}
+
+/// Lowercase a hash and drop john's format tag (`$NT$`, `$dynamic_0$`)
+fn normalize_hash(hash: &str) -> String {
+    let hash = hash.trim();
+    let hash = hash
+        .strip_prefix('$')
+        .and_then(|rest| rest.split_once('$'))
+        .map_or(hash, |(_, rest)| rest);
+    hash.to_ascii_lowercase()
+}
+
+/// Decode hashcat's `$HEX[...]` form for passwords with unusual bytes
+fn decode_password(password: &str) -> String {
+    let Some(hex) = password
+        .strip_prefix("$HEX[")
+        .and_then(|rest| rest.strip_suffix(']'))
+    else {
+        return password.to_string();
+    };
+    let bytes: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect();
+    match bytes {
+        Some(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        None => password.to_string(),
+    }
+}
+
+/// User name in front of a hash, as in pwdump/secretsdump (`user:rid:lm:nt:::`)
+fn username_from_context(context: &str, hash: &str) -> Option<String> {
+    let pos = context
+        .to_ascii_lowercase()
+        .find(&hash.to_ascii_lowercase())?;
+    let line = context[..pos].rsplit('\n').next()?;
+    let name = line.strip_suffix(':')?.split(':').next()?.trim();
+    let is_name = !name.is_empty()
+        && !name.chars().all(|c| c.is_ascii_hexdigit())
+        && !name.contains(char::is_whitespace);
+    is_name.then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use crate::vault::Vault;
+    use chacha20poly1305::Key;
+    use rusqlite::params;
+    use tempfile::TempDir;
+
+    const NT: &str = "8846f7eaee8fb117ad06bdd830b7586c";
+    const LM: &str = "aad3b435b51404eeaad3b435b51404ee";
+
+    #[test]
+    fn test_import_cracked_hashes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .with_vault(Vault::from_key(Key::from_slice(&[7u8; 32])));
+        capture("s1", "secretsdump.py", b"").insert(&storage);
+        let dump = format!("Administrator:500:{}:{}:::", LM, NT);
+        storage
+            .database
+            .insert_entities(
+                1,
+                &[
+                    (
+                        "hash_ntlm".to_string(),
+                        format!("{}:{}", LM, NT),
+                        dump.clone(),
+                        0.9,
+                    ),
+                    (
+                        "hash_md5".to_string(),
+                        "5f4dcc3b5aa765d61d8327deb882cf99".to_string(),
+                        "md5: 5f4dcc3b5aa765d61d8327deb882cf99".to_string(),
+                        0.8,
+                    ),
+                ],
+            )
+            .unwrap();
+        storage
+            .database
+            .insert_finding("s1", Some(1), "Hashes dumped", "medium", Some("dump"))
+            .unwrap();
+
+        let potfile = format!(
+            "{}:password\n5f4dcc3b5aa765d61d8327deb882cf99:$HEX[70617373776f7264]\ndeadbeef:nope\n",
+            NT
+        );
+        let result = match_cracked(&storage, &potfile, None).unwrap();
+        assert_eq!(result.lines, 3);
+        assert_eq!(result.unmatched, 1);
+        assert_eq!(result.matched.len(), 2);
+        assert_eq!(result.matched[0].username.as_deref(), Some("Administrator"));
+        assert_eq!(result.matched[1].password, "password");
+
+        // john --show names the user rather than the hash
+        let show = "administrator:password:500:::\n\n1 password hash cracked, 0 left\n";
+        let result = match_cracked(&storage, show, Some("s1")).unwrap();
+        assert_eq!(result.lines, 1);
+        assert_eq!(result.matched[0].hash_type, "hash_ntlm");
+        assert!(match_cracked(&storage, show, Some("s2"))
+            .unwrap()
+            .matched
+            .is_empty());
+
+        let stats = record_cracked(&storage, &result.matched, "john").unwrap();
+        assert_eq!(stats.credentials, 1);
+        assert_eq!(stats.findings, 1);
+        assert_eq!(stats.escalated, 1);
+
+        let credentials = storage.database.list_credentials(Some("s1")).unwrap();
+        assert_eq!(credentials[0].username.as_deref(), Some("Administrator"));
+        let vault = storage.vault().unwrap();
+        assert_eq!(vault.open(&credentials[0].sealed).unwrap(), b"password");
+
+        let findings = storage.database.list_findings(Some("s1")).unwrap();
+        assert!(findings.iter().all(|f| f.severity == "high"));
+        let severity: String = storage
+            .database
+            .read_conn()
+            .unwrap()
+            .query_row(
+                "SELECT severity FROM findings WHERE title = ?1",
+                params!["Hashes dumped"],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(severity, "high");
+
+        // Importing again changes nothing
+        let stats = record_cracked(&storage, &result.matched, "john").unwrap();
+        assert_eq!((stats.credentials, stats.findings), (0, 0));
+    }
+
+    #[test]
+    fn test_record_requires_vault() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        assert!(record_cracked(&storage, &[], "hashcat").is_err());
+    }
+
+    #[test]
+    fn test_normalize_hash() {
+        assert_eq!(normalize_hash(&format!("$NT${}", NT.to_uppercase())), NT);
+        assert_eq!(normalize_hash("$dynamic_0$abc"), "abc");
+        assert_eq!(decode_password("$HEX[c3a9]"), "é");
+        assert_eq!(
+            username_from_context("[*] Dumping\nguest:501:aa:bb:::", "aa:bb"),
+            Some("guest".to_string())
+        );
+    }
+}
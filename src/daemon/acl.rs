@@ -0,0 +1,207 @@
+// Shared-daemon access control: peer authentication and per-user query scoping
+
+use crate::config::AclConfig;
+use crate::error::{Result, YinxError};
+use std::collections::HashMap;
+
+/// Wildcard entry granting access to every user
+const ALL_USERS: &str = "*";
+
+/// Whose captures a user may query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserScope {
+    All,
+    Users(Vec<String>),
+}
+
+impl UserScope {
+    /// Check whether captures by `username` are visible
+    pub fn allows(&self, username: Option<&str>) -> bool {
+        match self {
+            UserScope::All => true,
+            UserScope::Users(users) => username.is_some_and(|u| users.iter().any(|v| v == u)),
+        }
+    }
+}
+
+/// Compiled `[acl]` section
+#[derive(Debug, Clone)]
+pub struct Acl {
+    shared: bool,
+    owner_uid: u32,
+    allowed_users: Vec<String>,
+    default_all: bool,
+    scopes: HashMap<String, Vec<String>>,
+}
+
+impl Acl {
+    /// Build the ACL for a daemon owned by `owner_uid`
+    pub fn new(config: &AclConfig, owner_uid: u32) -> Result<Self> {
+        let default_all = match config.default_scope.as_str() {
+            "self" => false,
+            "all" => true,
+            other => {
+                return Err(YinxError::InvalidConfigValue {
+                    path: "acl.default_scope".to_string(),
+                    message: format!("Expected 'self' or 'all', got '{}'", other),
+                })
+            }
+        };
+
+        Ok(Self {
+            shared: config.shared,
+            owner_uid,
+            allowed_users: config.allowed_users.clone(),
+            default_all,
+            scopes: config.scopes.clone(),
+        })
+    }
+
+    /// Whether other local users may connect to the daemon
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Whether `uid` is the user running the daemon
+    pub fn is_owner(&self, uid: u32) -> bool {
+        uid == self.owner_uid
+    }
+
+    /// Authenticate a connecting peer by uid, returning its username
+    pub fn authorize(&self, uid: u32) -> std::result::Result<String, String> {
+        let username = username_for_uid(uid).unwrap_or_else(|| uid.to_string());
+
+        if self.is_owner(uid) {
+            return Ok(username);
+        }
+        if !self.shared {
+            return Err(format!(
+                "User {} may not connect (daemon is not in shared mode)",
+                username
+            ));
+        }
+        if !self.allowed_users.is_empty()
+            && !self
+                .allowed_users
+                .iter()
+                .any(|u| u == &username || u == ALL_USERS)
+        {
+            return Err(format!("User {} is not in acl.allowed_users", username));
+        }
+
+        Ok(username)
+    }
+
+    /// Whose captures `username` may query
+    pub fn scope_for(&self, username: &str) -> UserScope {
+        match self.scopes.get(username) {
+            Some(users) if users.iter().any(|u| u == ALL_USERS) => UserScope::All,
+            Some(users) => {
+                let mut users = users.clone();
+                if !users.iter().any(|u| u == username) {
+                    users.push(username.to_string());
+                }
+                UserScope::Users(users)
+            }
+            None if self.default_all || !self.shared => UserScope::All,
+            None => UserScope::Users(vec![username.to_string()]),
+        }
+    }
+}
+
+/// Uid of the user running yinx
+#[cfg(unix)]
+pub fn current_uid() -> u32 {
+    nix::unistd::getuid().as_raw()
+}
+
+/// Uid of the user running yinx
+///
+/// Windows has SIDs rather than uids; named pipe clients are always the
+/// owner (see `ipc::peer_uid`), so a fixed uid stands in for them.
+#[cfg(windows)]
+pub fn current_uid() -> u32 {
+    0
+}
+
+/// Look up the login name for a uid
+#[cfg(unix)]
+pub fn username_for_uid(uid: u32) -> Option<String> {
+    nix::unistd::User::from_uid(nix::unistd::Uid::from_raw(uid))
+        .ok()
+        .flatten()
+        .map(|u| u.name)
+}
+
+/// Look up the login name for a uid
+#[cfg(windows)]
+pub fn username_for_uid(uid: u32) -> Option<String> {
+    (uid == current_uid())
+        .then(|| std::env::var("USERNAME").ok())
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acl(shared: bool, allowed: &[&str], scopes: &[(&str, &[&str])]) -> Acl {
+        let config = AclConfig {
+            shared,
+            allowed_users: allowed.iter().map(|u| u.to_string()).collect(),
+            default_scope: "self".to_string(),
+            scopes: scopes
+                .iter()
+                .map(|(user, users)| {
+                    (
+                        user.to_string(),
+                        users.iter().map(|u| u.to_string()).collect(),
+                    )
+                })
+                .collect(),
+        };
+        Acl::new(&config, current_uid()).unwrap()
+    }
+
+    #[test]
+    fn test_owner_always_authorized() {
+        let uid = current_uid();
+        assert!(acl(false, &[], &[]).authorize(uid).is_ok());
+        assert!(acl(true, &["nobody-else"], &[]).authorize(uid).is_ok());
+    }
+
+    #[test]
+    fn test_other_users_need_shared_mode() {
+        // Any uid other than ours
+        let other = current_uid().wrapping_add(65534);
+        assert!(acl(false, &[], &[]).authorize(other).is_err());
+        assert!(acl(true, &[], &[]).authorize(other).is_ok());
+        assert!(acl(true, &["alice"], &[]).authorize(other).is_err());
+        assert!(acl(true, &["*"], &[]).authorize(other).is_ok());
+    }
+
+    #[test]
+    fn test_query_scopes() {
+        let acl = acl(true, &[], &[("lead", &["*"]), ("alice", &["bob"])]);
+
+        assert_eq!(acl.scope_for("lead"), UserScope::All);
+        let alice = acl.scope_for("alice");
+        assert!(alice.allows(Some("alice")));
+        assert!(alice.allows(Some("bob")));
+        assert!(!alice.allows(Some("carol")));
+        assert!(!alice.allows(None));
+        assert_eq!(
+            acl.scope_for("carol"),
+            UserScope::Users(vec!["carol".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_invalid_default_scope() {
+        let config = AclConfig {
+            default_scope: "everyone".to_string(),
+            ..AclConfig::default()
+        };
+        assert!(Acl::new(&config, 0).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+// Last-ditch handling when the daemon panics
+//
+// While the daemon runs, the storage worker's pending batch, the session
+// lifecycle and the database are registered here. If anything panics, the
+// hook spills the pending captures to a journal in the data directory, marks
+// the session interrupted and checkpoints SQLite, then exits instead of
+// leaving a daemon whose pipeline task has died. The next daemon replays the
+// journal on startup.
+
+use super::{CaptureEvent, Pipeline, SessionLifecycle};
+use crate::error::{Result, YinxError};
+use crate::storage::Database;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Captures spilled by a panicking daemon, replayed on the next start
+const PENDING_PREFIX: &str = "pending-";
+/// The capture being processed when the daemon panicked; kept for
+/// inspection rather than replayed, as it may well panic again
+const POISONED_PREFIX: &str = "poisoned-";
+
+/// What the panic hook needs from a running daemon
+static ARMED: Mutex<Option<Emergency>> = Mutex::new(None);
+
+/// Captures the storage worker has received but not yet stored
+#[derive(Clone, Default)]
+pub(crate) struct PendingBatch {
+    inner: Arc<Mutex<PendingState>>,
+}
+
+#[derive(Default)]
+struct PendingState {
+    queue: VecDeque<Arc<CaptureEvent>>,
+    /// Whether the front of the queue is being processed
+    in_flight: bool,
+}
+
+impl PendingBatch {
+    fn lock(&self) -> MutexGuard<'_, PendingState> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn push(&self, event: CaptureEvent) {
+        self.lock().queue.push_back(Arc::new(event));
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The next capture to store; it stays queued until [`Self::finish`]
+    pub(crate) fn begin(&self) -> Option<Arc<CaptureEvent>> {
+        let mut state = self.lock();
+        let next = state.queue.front().cloned();
+        state.in_flight = next.is_some();
+        next
+    }
+
+    /// Drop the capture returned by [`Self::begin`]
+    pub(crate) fn finish(&self) {
+        let mut state = self.lock();
+        state.queue.pop_front();
+        state.in_flight = false;
+    }
+
+    /// Queued captures and the one in flight, without blocking
+    fn try_snapshot(&self) -> Option<Snapshot> {
+        let state = self.inner.try_lock().ok()?;
+        let mut queue: Vec<_> = state.queue.iter().cloned().collect();
+        let in_flight = if state.in_flight && !queue.is_empty() {
+            Some(queue.remove(0))
+        } else {
+            None
+        };
+        Some(Snapshot { queue, in_flight })
+    }
+}
+
+struct Snapshot {
+    queue: Vec<Arc<CaptureEvent>>,
+    in_flight: Option<Arc<CaptureEvent>>,
+}
+
+/// Everything the panic hook rescues
+pub(crate) struct Emergency {
+    pub spill_dir: PathBuf,
+    pub pending: PendingBatch,
+    pub lifecycle: Arc<Mutex<SessionLifecycle>>,
+    pub database: Database,
+}
+
+impl Emergency {
+    fn rescue(&self) {
+        match self.pending.try_snapshot() {
+            Some(Snapshot { queue, in_flight }) => {
+                let spilled = spill(&self.spill_dir, PENDING_PREFIX, &queue);
+                let poisoned = spill(&self.spill_dir, POISONED_PREFIX, in_flight.as_slice());
+                match (spilled, poisoned) {
+                    (Ok(_), Ok(_)) => eprintln!(
+                        "yinx: spilled {} pending capture(s) to {}",
+                        queue.len() + in_flight.iter().len(),
+                        self.spill_dir.display()
+                    ),
+                    (Err(e), _) | (_, Err(e)) => {
+                        eprintln!("yinx: failed to spill pending captures: {}", e)
+                    }
+                }
+            }
+            None => eprintln!("yinx: pending captures are locked; not spilled"),
+        }
+
+        match self.lifecycle.try_lock() {
+            Ok(mut lifecycle) => {
+                if let Err(e) = lifecycle.interrupt() {
+                    eprintln!("yinx: failed to mark session interrupted: {}", e);
+                }
+            }
+            Err(_) => eprintln!("yinx: session state is locked; not marked interrupted"),
+        }
+
+        if let Err(e) = self.database.checkpoint() {
+            eprintln!("yinx: failed to checkpoint database: {}", e);
+        }
+    }
+}
+
+/// Register a running daemon with the panic hook
+pub(crate) fn arm(emergency: Emergency) {
+    *ARMED.lock().unwrap_or_else(|e| e.into_inner()) = Some(emergency);
+}
+
+/// The daemon has shut down cleanly; nothing left to rescue
+pub(crate) fn disarm() {
+    ARMED.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
+
+/// Rescue pending captures and session state when the daemon panics
+///
+/// Only for processes that run the daemon: once a daemon is armed, any
+/// panic ends the process after the rescue, since a panic in the storage
+/// worker would otherwise leave it accepting captures it never stores.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        let emergency = ARMED.try_lock().ok().and_then(|mut armed| armed.take());
+        if let Some(emergency) = emergency {
+            emergency.rescue();
+            std::process::exit(101);
+        }
+    }));
+}
+
+/// Directory spill journals are kept in
+pub(crate) fn spill_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("spill")
+}
+
+/// Write `events` as a JSON-lines journal, synced to disk
+fn spill(dir: &Path, prefix: &str, events: &[Arc<CaptureEvent>]) -> Result<Option<PathBuf>> {
+    if events.is_empty() {
+        return Ok(None);
+    }
+    let io_error = |e, path: &Path| YinxError::Io {
+        source: e,
+        context: format!("Failed to write spill journal: {}", path.display()),
+    };
+    std::fs::create_dir_all(dir).map_err(|e| io_error(e, dir))?;
+    let path = dir.join(format!(
+        "{}{}-{}.jsonl",
+        prefix,
+        chrono::Utc::now().timestamp(),
+        std::process::id()
+    ));
+    let mut file = std::fs::File::create(&path).map_err(|e| io_error(e, &path))?;
+    for event in events {
+        let mut line = serde_json::to_vec(event.as_ref()).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to serialize spilled capture".to_string(),
+        })?;
+        line.push(b'\n');
+        file.write_all(&line).map_err(|e| io_error(e, &path))?;
+    }
+    file.sync_all().map_err(|e| io_error(e, &path))?;
+    Ok(Some(path))
+}
+
+/// Read and remove the pending journals a crashed daemon left behind
+pub(crate) fn take_spilled(dir: &Path) -> Result<Vec<CaptureEvent>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut journals: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(PENDING_PREFIX))
+        })
+        .collect();
+    journals.sort();
+
+    let mut events = Vec::new();
+    for path in journals {
+        let file = std::fs::File::open(&path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read spill journal: {}", path.display()),
+        })?;
+        for line in BufReader::new(file).lines().map_while(|line| line.ok()) {
+            // A line cut short by the crash is all that can be lost here
+            match serde_json::from_str(&line) {
+                Ok(event) => events.push(event),
+                Err(e) => tracing::warn!("Skipping unreadable spilled capture: {}", e),
+            }
+        }
+        std::fs::remove_file(&path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to remove spill journal: {}", path.display()),
+        })?;
+    }
+    Ok(events)
+}
+
+/// Queue captures spilled by a crashed daemon; returns how many
+pub(crate) async fn replay_spilled(dir: &Path, pipeline: &Pipeline) -> Result<usize> {
+    let events = take_spilled(dir)?;
+    let count = events.len();
+    for event in events {
+        pipeline.send(event).await?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn event(command: &str) -> CaptureEvent {
+        CaptureEvent {
+            session_id: "s1".to_string(),
+            timestamp: 1_700_000_000,
+            command: command.to_string(),
+            output: "output".to_string(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn test_spill_and_replay_pending_batch() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = spill_dir(temp_dir.path());
+        let pending = PendingBatch::default();
+        for command in ["nmap -sV 10.10.10.3", "gobuster dir", "whoami"] {
+            pending.push(event(command));
+        }
+        // The daemon panicked while storing the first one
+        assert_eq!(pending.begin().unwrap().command, "nmap -sV 10.10.10.3");
+
+        let Snapshot { queue, in_flight } = pending.try_snapshot().unwrap();
+        spill(&dir, PENDING_PREFIX, &queue).unwrap();
+        let poisoned = spill(&dir, POISONED_PREFIX, in_flight.as_slice())
+            .unwrap()
+            .unwrap();
+        assert!(poisoned.exists());
+
+        let replayed = take_spilled(&dir).unwrap();
+        let commands: Vec<_> = replayed.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, ["gobuster dir", "whoami"]);
+        assert!(take_spilled(&dir).unwrap().is_empty());
+        // Not replayed, but not thrown away either
+        assert!(poisoned.exists());
+    }
+}
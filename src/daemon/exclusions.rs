@@ -0,0 +1,196 @@
+// Command exclusion rules: commands matching any pattern are never captured
+
+use crate::error::{Result, YinxError};
+use regex::Regex;
+
+/// Characters POSIX ERE gives a meaning to, and so may be escaped
+const ERE_SPECIAL: &str = ".[]{}()\\*+?|^$";
+
+/// Compiled `capture.exclude_commands` patterns
+#[derive(Debug, Clone, Default)]
+pub struct CommandExclusions {
+    patterns: Vec<Regex>,
+}
+
+impl CommandExclusions {
+    /// Compile exclusion patterns from config
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p).map_err(|e| YinxError::InvalidConfigValue {
+                    path: "capture.exclude_commands".to_string(),
+                    message: format!("Invalid pattern '{}': {}", p, e),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// Return the first pattern matching the command, if any
+    pub fn matching(&self, command: &str) -> Option<&str> {
+        let command = command.trim_start();
+        self.patterns
+            .iter()
+            .find(|re| re.is_match(command))
+            .map(|re| re.as_str())
+    }
+
+    /// Check whether a command should be skipped
+    pub fn is_excluded(&self, command: &str) -> bool {
+        self.matching(command).is_some()
+    }
+
+    /// Patterns combined into one alternation, for shell hooks to pre-filter with
+    ///
+    /// The hooks match with `[[ =~ ]]`, which is POSIX ERE. Patterns using
+    /// syntax ERE lacks or reads differently (`\d`, `\b`, `(?i)`, lazy
+    /// repetition, ...) are left out; the daemon still applies them.
+    pub fn combined_pattern(&self) -> String {
+        self.patterns
+            .iter()
+            .filter(|re| is_posix_ere(re.as_str()))
+            .map(|re| format!("({})", re.as_str()))
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    /// Check if there are no exclusion rules
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+/// Whether POSIX ERE reads a pattern the way the regex crate does
+///
+/// Conservative: only literals, escaped metacharacters, `.`, anchors,
+/// groups, alternation, greedy repetition and plain bracket expressions.
+fn is_posix_ere(pattern: &str) -> bool {
+    let mut chars = pattern.chars().peekable();
+    let mut after_quantifier = false;
+
+    while let Some(c) = chars.next() {
+        let quantifier = match c {
+            '\\' => match chars.next() {
+                Some(escaped) if ERE_SPECIAL.contains(escaped) => false,
+                _ => return false,
+            },
+            // Non-capturing groups and flags
+            '(' if chars.peek() == Some(&'?') => return false,
+            '[' => {
+                let mut set = String::new();
+                if chars.peek() == Some(&'^') {
+                    chars.next();
+                }
+                // A leading ']' is a literal in both
+                if chars.peek() == Some(&']') {
+                    set.push(chars.next().unwrap());
+                }
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        // Escapes, nested classes and set operations
+                        Some('\\' | '[') | None => return false,
+                        Some(c) => set.push(c),
+                    }
+                }
+                if ["&&", "--", "~~"].iter().any(|op| set.contains(op)) {
+                    return false;
+                }
+                false
+            }
+            '{' => {
+                let mut bounds = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_digit() || c == ',' => bounds.push(c),
+                        _ => return false,
+                    }
+                }
+                // ERE needs a lower bound
+                if bounds.is_empty() || bounds.starts_with(',') {
+                    return false;
+                }
+                true
+            }
+            '*' | '+' | '?' => true,
+            _ => false,
+        };
+        // Lazy (`*?`) and stacked repetition
+        if quantifier && after_quantifier {
+            return false;
+        }
+        after_quantifier = quantifier;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(patterns: &[&str]) -> CommandExclusions {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        CommandExclusions::new(&patterns).unwrap()
+    }
+
+    #[test]
+    fn test_exclusion_matching() {
+        let rules = rules(&["^ls", "^cd ", "password"]);
+
+        assert!(rules.is_excluded("ls -la"));
+        assert!(rules.is_excluded("  cd /tmp"));
+        assert!(rules.is_excluded("mysql --password=hunter2"));
+        assert_eq!(rules.matching("cd /opt"), Some("^cd "));
+        assert!(!rules.is_excluded("nmap -sV 10.0.0.1"));
+        assert!(!rules.is_excluded("cdk deploy"));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(CommandExclusions::new(&["(".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_combined_pattern() {
+        assert_eq!(rules(&["^ls", "^cd "]).combined_pattern(), "(^ls)|(^cd )");
+        assert_eq!(rules(&[]).combined_pattern(), "");
+
+        // Only patterns ERE reads the same way reach the shell
+        let mixed = rules(&["^cd\\s", "^(ls|ll)$", "(?i)passw", "pass[^ ]*=", "\\d+"]);
+        assert_eq!(mixed.combined_pattern(), "(^(ls|ll)$)|(pass[^ ]*=)");
+        assert!(mixed.is_excluded("cd /tmp"));
+        assert!(mixed.is_excluded("export PASSWORD=x"));
+    }
+
+    #[test]
+    fn test_posix_ere_subset() {
+        for pattern in [
+            "^ls",
+            "^ssh .*-p",
+            "a{2,3}",
+            "x{2}",
+            "\\.bash_history$",
+            "[]a-z]+",
+            "^(cat|less) .*\\.key$",
+        ] {
+            assert!(is_posix_ere(pattern), "{}", pattern);
+        }
+        for pattern in [
+            "\\bls\\b",
+            "\\w+",
+            "(?i)ls",
+            "(?:ls|cd)",
+            "a+?",
+            "[[:alpha:]]",
+            "[a-z&&[^x]]",
+            "[\\d]",
+            "a{,3}",
+        ] {
+            assert!(!is_posix_ere(pattern), "{}", pattern);
+        }
+    }
+}
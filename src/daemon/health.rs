@@ -0,0 +1,174 @@
+// Pipeline health: the counters behind `yinx status` alarms
+//
+// Captures turned away by a full queue, client connections that fail, and
+// how long the storage worker takes to flush are counted for the life of
+// the daemon. `yinx status` asks for them over IPC, adds the age of the
+// embedding backlog from the database, and warns about anything past its
+// `[daemon.alarms]` threshold, so losing captures is never silent.
+
+use crate::config::{parse_duration, AlarmConfig};
+use crate::storage::EmbeddingBacklog;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How long a capture waits for room in a full queue before it's dropped
+pub const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client whose capture was dropped should wait before resending
+pub const QUEUE_FULL_RETRY: Duration = Duration::from_secs(1);
+
+static DROPPED_CAPTURES: AtomicU64 = AtomicU64::new(0);
+static IPC_ERRORS: AtomicU64 = AtomicU64::new(0);
+static THROTTLED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+static FLUSHES: AtomicU64 = AtomicU64::new(0);
+static LAST_FLUSH_MS: AtomicU64 = AtomicU64::new(0);
+static SLOWEST_FLUSH_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Count a capture the pipeline had no room for
+pub fn record_drop() {
+    DROPPED_CAPTURES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a client connection that failed mid-request
+pub fn record_ipc_error() {
+    IPC_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a request turned away by the rate limit or connection cap
+pub fn record_throttled() {
+    THROTTLED_REQUESTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record how long a batch took to reach storage
+pub fn record_flush(elapsed: Duration) {
+    let ms = elapsed.as_millis() as u64;
+    FLUSHES.fetch_add(1, Ordering::Relaxed);
+    LAST_FLUSH_MS.store(ms, Ordering::Relaxed);
+    SLOWEST_FLUSH_MS.fetch_max(ms, Ordering::Relaxed);
+}
+
+/// Pipeline counters since the daemon started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipelineHealth {
+    pub dropped_captures: u64,
+    pub ipc_errors: u64,
+    /// Requests told to back off; absent from older daemons
+    #[serde(default)]
+    pub throttled_requests: u64,
+    pub flushes: u64,
+    pub last_flush_ms: u64,
+    pub slowest_flush_ms: u64,
+}
+
+impl PipelineHealth {
+    /// This process's counters
+    pub fn current() -> Self {
+        Self {
+            dropped_captures: DROPPED_CAPTURES.load(Ordering::Relaxed),
+            ipc_errors: IPC_ERRORS.load(Ordering::Relaxed),
+            throttled_requests: THROTTLED_REQUESTS.load(Ordering::Relaxed),
+            flushes: FLUSHES.load(Ordering::Relaxed),
+            last_flush_ms: LAST_FLUSH_MS.load(Ordering::Relaxed),
+            slowest_flush_ms: SLOWEST_FLUSH_MS.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A warning for each threshold in `alarms` that `health` or `backlog`
+/// crosses, as of `now`
+///
+/// Thresholds that don't parse were already reported by config validation
+/// and are skipped.
+pub fn check_alarms(
+    alarms: &AlarmConfig,
+    health: Option<&PipelineHealth>,
+    backlog: Option<&EmbeddingBacklog>,
+    now: i64,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(health) = health {
+        if health.dropped_captures > alarms.dropped_captures {
+            warnings.push(format!(
+                "{} capture(s) dropped because the pipeline queue was full",
+                health.dropped_captures
+            ));
+        }
+        if health.ipc_errors > alarms.ipc_errors {
+            warnings.push(format!(
+                "{} client connection(s) failed; shell hooks may be losing captures",
+                health.ipc_errors
+            ));
+        }
+        if let Some(limit) = parse_duration(&alarms.flush_latency) {
+            if Duration::from_millis(health.last_flush_ms) > limit {
+                warnings.push(format!(
+                    "Last flush to storage took {:.1}s (alarm at {})",
+                    health.last_flush_ms as f64 / 1000.0,
+                    alarms.flush_latency
+                ));
+            }
+        }
+    }
+    if let (Some(backlog), Some(limit)) = (backlog, parse_duration(&alarms.embedding_backlog_age)) {
+        if let Some(oldest) = backlog.oldest {
+            let age = now.saturating_sub(oldest).max(0) as u64;
+            if age > limit.as_secs() {
+                warnings.push(format!(
+                    "{} chunk(s) waiting for embeddings, the oldest for {}m (alarm at {}); \
+                     vector search misses them",
+                    backlog.chunks,
+                    age / 60,
+                    alarms.embedding_backlog_age
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_alarms() {
+        let alarms = AlarmConfig::default();
+        let quiet = PipelineHealth {
+            flushes: 3,
+            last_flush_ms: 120,
+            slowest_flush_ms: 400,
+            ..Default::default()
+        };
+        assert!(check_alarms(&alarms, Some(&quiet), None, 0).is_empty());
+
+        let troubled = PipelineHealth {
+            dropped_captures: 2,
+            ipc_errors: 11,
+            last_flush_ms: 6_000,
+            ..quiet
+        };
+        let warnings = check_alarms(&alarms, Some(&troubled), None, 0);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].starts_with("2 capture(s) dropped"));
+
+        let backlog = EmbeddingBacklog {
+            chunks: 40,
+            oldest: Some(1_000),
+        };
+        assert!(check_alarms(&alarms, None, Some(&backlog), 1_000 + 3_600).is_empty());
+        assert_eq!(
+            check_alarms(&alarms, None, Some(&backlog), 1_000 + 3_601).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_record_flush_keeps_slowest() {
+        record_flush(Duration::from_millis(900));
+        record_flush(Duration::from_millis(10));
+        let health = PipelineHealth::current();
+        assert!(health.flushes >= 2);
+        assert!(health.slowest_flush_ms >= 900);
+    }
+}
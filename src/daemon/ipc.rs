@@ -1,14 +1,41 @@
-// Inter-process communication via Unix domain sockets with length-prefixed JSON protocol
+// Inter-process communication with a length-prefixed protocol: Unix domain
+// sockets on Unix, named pipes on Windows. Messages are JSON unless the
+// client negotiates another encoding (see `wire`).
 
+use super::{wire, CaptureContext};
+use crate::config::IpcEncoding;
 use crate::error::{Result, YinxError};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+#[cfg(unix)]
 use tokio::net::{UnixListener, UnixStream};
 
+/// A connected client, as handed out by [`IpcServer::accept`]
+#[cfg(unix)]
+pub type IpcStream = UnixStream;
+#[cfg(windows)]
+pub type IpcStream = NamedPipeServer;
+
 /// Maximum message size (10MB)
 const MAX_MESSAGE_SIZE: u32 = 10 * 1024 * 1024;
 
+/// Largest handed-off output the daemon reads (256MB)
+pub const MAX_HANDOFF_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Resends `IpcClient::send_with_backoff` makes before giving up
+const MAX_RETRIES: u32 = 3;
+
+/// Back-off for a retryable error that didn't say how long to wait
+const DEFAULT_RETRY_WAIT: Duration = Duration::from_millis(250);
+
+/// Longest back-off `IpcClient::send_with_backoff` waits out; a shell
+/// prompt shouldn't hang behind a daemon that's drowning
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(2);
+
 /// IPC message types sent from shell hooks or CLI to daemon
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -21,6 +48,23 @@ pub enum IpcMessage {
         output: String,
         exit_code: i32,
         cwd: String,
+        /// Environment the command ran in, when the hook collected it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<Box<CaptureContext>>,
+        /// Filter and extract but store nothing; the response carries a
+        /// summary of what would have been kept
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        dry_run: bool,
+        /// Output left in a file for the receiver to read, in place of
+        /// `output`, when it's too big to send inline
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        handoff: Option<OutputHandoff>,
+    },
+    /// A command has started; its targets can be registered before it returns
+    Intent {
+        session_id: String,
+        timestamp: i64,
+        command: String,
     },
     /// Request daemon status
     Status,
@@ -28,6 +72,164 @@ pub enum IpcMessage {
     Stop,
     /// Query for data
     Query { query: String, limit: usize },
+    /// Opens a connection: the encodings the client can use, preferred
+    /// first. The daemon answers with the one it picked, and the next
+    /// message and its response use it.
+    Hello { encodings: Vec<IpcEncoding> },
+}
+
+/// Command output passed by file path rather than inside the message
+///
+/// The hook already has the output in a temp file, so big captures skip
+/// the JSON encoding and the copy through the socket, and aren't bound by
+/// the message size limit. The receiver reads the file itself, checks it
+/// against the hash, and removes it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputHandoff {
+    pub path: PathBuf,
+    pub size: u64,
+    /// BLAKE3 of the contents, hex
+    pub hash: String,
+}
+
+impl OutputHandoff {
+    /// Describe the output in `path`, hashing it
+    pub fn for_file(path: &Path) -> Result<Self> {
+        let io_error = |e| YinxError::Io {
+            source: e,
+            context: format!("Failed to hash output file {:?}", path),
+        };
+        let mut file = std::fs::File::open(path).map_err(io_error)?;
+        let mut hasher = blake3::Hasher::new();
+        let size = std::io::copy(&mut file, &mut hasher).map_err(io_error)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            size,
+            hash: hasher.finalize().to_hex().to_string(),
+        })
+    }
+
+    /// Read the output on behalf of the client running as `uid`, then
+    /// remove the file
+    ///
+    /// Only a regular file the client owns is read, so a client can't get
+    /// the daemon to ingest someone else's files; symlinks aren't followed.
+    /// Files over [`MAX_HANDOFF_SIZE`] are refused unread.
+    pub fn claim(&self, uid: u32) -> Result<String> {
+        let io_error = |e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read handed-off output {:?}", self.path),
+        };
+        let mut options = std::fs::OpenOptions::new();
+        options.read(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.custom_flags(libc::O_NOFOLLOW);
+        }
+        let mut file = options.open(&self.path).map_err(io_error)?;
+        let metadata = file.metadata().map_err(io_error)?;
+        if !metadata.is_file() {
+            return Err(YinxError::Daemon(format!(
+                "Handed-off output {:?} is not a regular file",
+                self.path
+            )));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.uid() != uid {
+                return Err(YinxError::Daemon(format!(
+                    "Handed-off output {:?} is not owned by the client",
+                    self.path
+                )));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = uid;
+        if self.size > MAX_HANDOFF_SIZE || metadata.len() > MAX_HANDOFF_SIZE {
+            return Err(YinxError::Daemon(format!(
+                "Handed-off output {:?} is over the {} byte limit",
+                self.path, MAX_HANDOFF_SIZE
+            )));
+        }
+
+        // One byte past the announced size is enough to tell it grew
+        let mut contents = Vec::with_capacity(self.size as usize);
+        std::io::Read::read_to_end(
+            &mut std::io::Read::take(&mut file, self.size + 1),
+            &mut contents,
+        )
+        .map_err(io_error)?;
+        if contents.len() as u64 != self.size
+            || blake3::hash(&contents).to_hex().as_str() != self.hash
+        {
+            return Err(YinxError::Daemon(format!(
+                "Handed-off output {:?} changed after it was sent",
+                self.path
+            )));
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::debug!("Failed to remove handed-off output {:?}: {}", self.path, e);
+        }
+        Ok(String::from_utf8_lossy(&contents).into_owned())
+    }
+}
+
+/// Why the daemon refused a request, for clients to act on without
+/// parsing the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IpcErrorCode {
+    /// The pipeline queue stayed full; retry later
+    QueueFull,
+    /// Too many requests from this user (daemon.rate_limit); retry later
+    RateLimited,
+    /// Every connection slot is taken (daemon.max_connections); retry later
+    Busy,
+    /// No session has the message's session id
+    SessionUnknown,
+    /// The session is paused and not taking captures
+    Paused,
+    /// The message is over the size limit; hand the output off as a file
+    PayloadTooLarge,
+    /// Handed-off output couldn't be read or didn't match its hash
+    HandoffRejected,
+    /// The client isn't allowed to make this request
+    Forbidden,
+    /// The message couldn't be understood
+    InvalidMessage,
+    /// The receiver doesn't handle this kind of message
+    Unsupported,
+    /// The daemon failed while handling the request
+    Internal,
+    /// A code from a newer daemon
+    #[serde(other)]
+    Unknown,
+}
+
+impl IpcErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::QueueFull => "QUEUE_FULL",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::Busy => "BUSY",
+            Self::SessionUnknown => "SESSION_UNKNOWN",
+            Self::Paused => "PAUSED",
+            Self::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            Self::HandoffRejected => "HANDOFF_REJECTED",
+            Self::Forbidden => "FORBIDDEN",
+            Self::InvalidMessage => "INVALID_MESSAGE",
+            Self::Unsupported => "UNSUPPORTED",
+            Self::Internal => "INTERNAL",
+            Self::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// Whether sending the same message again later can succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::QueueFull | Self::RateLimited | Self::Busy)
+    }
 }
 
 /// IPC response message sent from daemon back to client
@@ -38,6 +240,16 @@ pub struct IpcResponse {
     pub message: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
+    /// Set when a capture was deliberately not stored (e.g. excluded command)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    /// Set when the daemon turned the request away for now; wait this
+    /// long before sending it again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// Why the request failed; set on every error from this daemon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<IpcErrorCode>,
 }
 
 impl IpcResponse {
@@ -47,6 +259,21 @@ impl IpcResponse {
             success: true,
             message: Some(message.into()),
             data: None,
+            skipped: false,
+            retry_after_ms: None,
+            code: None,
+        }
+    }
+
+    /// Create a successful response acknowledging a skipped capture
+    pub fn skipped(message: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            message: Some(message.into()),
+            data: None,
+            skipped: true,
+            retry_after_ms: None,
+            code: None,
         }
     }
 
@@ -56,23 +283,81 @@ impl IpcResponse {
             success: true,
             message: None,
             data: Some(data),
+            skipped: false,
+            retry_after_ms: None,
+            code: None,
         }
     }
 
+    /// Create an error response asking the client to back off
+    pub fn busy(code: IpcErrorCode, message: impl Into<String>, retry_after: Duration) -> Self {
+        Self {
+            retry_after_ms: Some(retry_after.as_millis().max(1) as u64),
+            ..Self::error(code, message)
+        }
+    }
+
+    /// Attach data to a response
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
     /// Create an error response
-    pub fn error(message: impl Into<String>) -> Self {
+    pub fn error(code: IpcErrorCode, message: impl Into<String>) -> Self {
         Self {
             success: false,
             message: Some(message.into()),
             data: None,
+            skipped: false,
+            retry_after_ms: None,
+            code: Some(code),
         }
     }
 }
 
-/// Unix domain socket server for IPC
+/// Name of the pipe standing in for `socket_path` on Windows
+///
+/// Named pipes live in their own namespace rather than on disk, so the
+/// configured socket path is hashed into a name that stays unique per
+/// data directory.
+pub fn pipe_name(socket_path: &Path) -> String {
+    let hash = blake3::hash(socket_path.to_string_lossy().as_bytes());
+    format!(r"\\.\pipe\yinx-{}", &hash.to_hex()[..16])
+}
+
+/// Uid of the process on the other end of `stream`
+#[cfg(unix)]
+pub fn peer_uid(stream: &IpcStream) -> Result<u32> {
+    Ok(stream
+        .peer_cred()
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to read peer credentials".to_string(),
+        })?
+        .uid())
+}
+
+/// Uid of the process on the other end of `stream`
+///
+/// The pipe's default security descriptor only lets the owner and
+/// administrators write to it, so every client counts as the owner.
+#[cfg(windows)]
+pub fn peer_uid(_stream: &IpcStream) -> Result<u32> {
+    Ok(super::acl::current_uid())
+}
+
+/// IPC server: a Unix domain socket, or a named pipe on Windows
 pub struct IpcServer {
     socket_path: PathBuf,
+    #[cfg(unix)]
     listener: Option<UnixListener>,
+    /// The pipe instance waiting for the next client
+    #[cfg(windows)]
+    pipe: Option<NamedPipeServer>,
+    /// Socket file permissions applied after binding (Unix only)
+    #[cfg_attr(windows, allow(dead_code))]
+    mode: Option<u32>,
 }
 
 impl IpcServer {
@@ -80,11 +365,23 @@ impl IpcServer {
     pub fn new(socket_path: PathBuf) -> Self {
         Self {
             socket_path,
+            #[cfg(unix)]
             listener: None,
+            #[cfg(windows)]
+            pipe: None,
+            mode: None,
         }
     }
 
+    /// Set the socket file permissions (e.g. 0o666 for a shared daemon);
+    /// ignored for named pipes
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
     /// Bind to the socket path and start listening
+    #[cfg(unix)]
     pub async fn bind(&mut self) -> Result<()> {
         // Remove existing socket file if it exists
         if self.socket_path.exists() {
@@ -108,14 +405,42 @@ impl IpcServer {
             context: format!("Failed to bind to socket: {:?}", self.socket_path),
         })?;
 
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.socket_path, std::fs::Permissions::from_mode(mode))
+                .map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to set socket permissions: {:?}", self.socket_path),
+                })?;
+        }
+
         self.listener = Some(listener);
 
         tracing::info!("IPC server listening on {:?}", self.socket_path);
         Ok(())
     }
 
+    /// Create the first pipe instance and start listening
+    #[cfg(windows)]
+    pub async fn bind(&mut self) -> Result<()> {
+        let name = pipe_name(&self.socket_path);
+        // Fails if another daemon already serves this pipe
+        let pipe = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&name)
+            .map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to create named pipe: {}", name),
+            })?;
+        self.pipe = Some(pipe);
+
+        tracing::info!("IPC server listening on {}", name);
+        Ok(())
+    }
+
     /// Accept incoming connections
-    pub async fn accept(&mut self) -> Result<UnixStream> {
+    #[cfg(unix)]
+    pub async fn accept(&mut self) -> Result<IpcStream> {
         let listener = self
             .listener
             .as_ref()
@@ -129,6 +454,31 @@ impl IpcServer {
         Ok(stream)
     }
 
+    /// Accept incoming connections
+    #[cfg(windows)]
+    pub async fn accept(&mut self) -> Result<IpcStream> {
+        let name = pipe_name(&self.socket_path);
+        let pipe = self
+            .pipe
+            .as_mut()
+            .ok_or_else(|| YinxError::Daemon("Server not bound".to_string()))?;
+
+        pipe.connect().await.map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to accept connection".to_string(),
+        })?;
+
+        // Put up the next instance before handing this one to the client
+        let next = ServerOptions::new()
+            .create(&name)
+            .map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to create named pipe: {}", name),
+            })?;
+
+        Ok(std::mem::replace(pipe, next))
+    }
+
     /// Shutdown the server and clean up socket file
     pub fn shutdown(&self) -> Result<()> {
         if self.socket_path.exists() {
@@ -146,8 +496,8 @@ impl IpcServer {
     }
 }
 
-/// Read a length-prefixed message from a Unix stream
-pub async fn read_message(stream: &mut UnixStream) -> Result<IpcMessage> {
+/// Read one length-prefixed frame
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Vec<u8>> {
     // Read 4-byte length prefix
     let length = stream.read_u32().await.map_err(|e| YinxError::Io {
         source: e,
@@ -156,13 +506,13 @@ pub async fn read_message(stream: &mut UnixStream) -> Result<IpcMessage> {
 
     // Validate length
     if length > MAX_MESSAGE_SIZE {
-        return Err(YinxError::Daemon(format!(
-            "Message too large: {} bytes (max: {})",
-            length, MAX_MESSAGE_SIZE
-        )));
+        return Err(YinxError::MessageTooLarge {
+            size: length as usize,
+            max: MAX_MESSAGE_SIZE as usize,
+        });
     }
 
-    // Read message payload
+    // Read payload
     let mut buffer = vec![0u8; length as usize];
     stream
         .read_exact(&mut buffer)
@@ -171,138 +521,250 @@ pub async fn read_message(stream: &mut UnixStream) -> Result<IpcMessage> {
             source: e,
             context: "Failed to read message payload".to_string(),
         })?;
-
-    // Deserialize JSON
-    let message: IpcMessage = serde_json::from_slice(&buffer).map_err(|e| YinxError::Json {
-        source: e,
-        context: "Failed to deserialize IPC message".to_string(),
-    })?;
-
-    Ok(message)
+    Ok(buffer)
 }
 
-/// Write a length-prefixed message to a Unix stream
-pub async fn write_response(stream: &mut UnixStream, response: &IpcResponse) -> Result<()> {
-    // Serialize to JSON
-    let payload = serde_json::to_vec(response).map_err(|e| YinxError::Json {
-        source: e,
-        context: "Failed to serialize IPC response".to_string(),
-    })?;
-
+/// Write one length-prefixed frame
+async fn write_frame<S: AsyncWrite + Unpin>(stream: &mut S, payload: &[u8]) -> Result<()> {
     // Check size
     if payload.len() > MAX_MESSAGE_SIZE as usize {
-        return Err(YinxError::Daemon(format!(
-            "Response too large: {} bytes (max: {})",
-            payload.len(),
-            MAX_MESSAGE_SIZE
-        )));
+        return Err(YinxError::MessageTooLarge {
+            size: payload.len(),
+            max: MAX_MESSAGE_SIZE as usize,
+        });
     }
 
     // Write length prefix (4 bytes, big-endian)
     let length = payload.len() as u32;
     stream.write_u32(length).await.map_err(|e| YinxError::Io {
         source: e,
-        context: "Failed to write response length".to_string(),
+        context: "Failed to write message length".to_string(),
     })?;
 
     // Write payload
-    stream
-        .write_all(&payload)
-        .await
-        .map_err(|e| YinxError::Io {
-            source: e,
-            context: "Failed to write response payload".to_string(),
-        })?;
+    stream.write_all(payload).await.map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to write message payload".to_string(),
+    })?;
 
     // Flush
     stream.flush().await.map_err(|e| YinxError::Io {
         source: e,
-        context: "Failed to flush response".to_string(),
+        context: "Failed to flush message".to_string(),
     })?;
 
     Ok(())
 }
 
+/// Read a client's message, answering its handshake first if it opened with one
+///
+/// Returns the message and the encoding the rest of the connection uses;
+/// clients that send their message straight away speak JSON.
+pub async fn accept_message<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+) -> Result<(IpcMessage, IpcEncoding)> {
+    let payload = read_client_frame(stream, IpcEncoding::Json).await?;
+    let message = wire::decode_message(IpcEncoding::Json, &payload)?;
+    let IpcMessage::Hello { encodings } = message else {
+        return Ok((message, IpcEncoding::Json));
+    };
+    let encoding = wire::negotiate(&encodings);
+    let answer = IpcResponse::success(format!("Speaking {}", encoding.as_str()))
+        .with_data(serde_json::json!({ "encoding": encoding }));
+    write_response(stream, &answer).await?;
+
+    let payload = read_client_frame(stream, encoding).await?;
+    Ok((wire::decode_message(encoding, &payload)?, encoding))
+}
+
+/// Read a client's frame, telling it when the frame is over the limit
+async fn read_client_frame<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    encoding: IpcEncoding,
+) -> Result<Vec<u8>> {
+    let result = read_frame(stream).await;
+    if let Err(e @ YinxError::MessageTooLarge { .. }) = &result {
+        let response = IpcResponse::error(IpcErrorCode::PayloadTooLarge, e.to_string());
+        // Best effort: the client may already have given up
+        let _ = write_response_as(stream, &response, encoding).await;
+    }
+    result
+}
+
+/// Write a JSON response to a client stream
+pub async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: &IpcResponse,
+) -> Result<()> {
+    write_response_as(stream, response, IpcEncoding::Json).await
+}
+
+/// Write a response in the encoding the connection settled on
+pub async fn write_response_as<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: &IpcResponse,
+    encoding: IpcEncoding,
+) -> Result<()> {
+    let payload = wire::encode_response(encoding, response)?;
+    write_frame(stream, &payload).await
+}
+
 /// IPC client for sending messages to the daemon
 pub struct IpcClient {
     socket_path: PathBuf,
+    /// Encoding offered in the handshake; JSON skips the handshake
+    encoding: IpcEncoding,
 }
 
 impl IpcClient {
     /// Create a new IPC client
     pub fn new(socket_path: PathBuf) -> Self {
-        Self { socket_path }
+        Self {
+            socket_path,
+            encoding: IpcEncoding::Json,
+        }
+    }
+
+    /// Offer `encoding` to the daemon before each message
+    pub fn with_encoding(mut self, encoding: IpcEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Send a message, waiting and resending while the daemon asks to back off
+    ///
+    /// Only errors whose code is retryable are resent. Gives up after `MAX_RETRIES` resends, or as soon as the daemon asks
+    /// for a wait longer than `MAX_RETRY_WAIT`, and returns its last answer.
+    pub async fn send_with_backoff(&self, message: &IpcMessage) -> Result<IpcResponse> {
+        let mut retries = 0;
+        loop {
+            let response = self.send(message).await?;
+            // Daemons from before error codes only set the wait
+            let retryable = match response.code {
+                Some(code) => code.is_retryable(),
+                None => response.retry_after_ms.is_some(),
+            };
+            if !retryable || retries >= MAX_RETRIES {
+                return Ok(response);
+            }
+            let wait = response
+                .retry_after_ms
+                .map_or(DEFAULT_RETRY_WAIT, Duration::from_millis);
+            if wait > MAX_RETRY_WAIT {
+                return Ok(response);
+            }
+            tracing::debug!("Daemon busy, retrying in {:?}", wait);
+            tokio::time::sleep(wait).await;
+            retries += 1;
+        }
     }
 
     /// Connect to the daemon and send a message, returning the response
+    ///
+    /// A message over the size limit isn't sent; it's answered here with a
+    /// `PAYLOAD_TOO_LARGE` error, as the daemon would.
     pub async fn send(&self, message: &IpcMessage) -> Result<IpcResponse> {
-        // Connect to socket
-        let mut stream =
-            UnixStream::connect(&self.socket_path)
-                .await
-                .map_err(|e| YinxError::Io {
-                    source: e,
-                    context: format!("Failed to connect to daemon at {:?}", self.socket_path),
-                })?;
+        let payload = wire::encode_message(self.encoding, message)?;
+        if payload.len() > MAX_MESSAGE_SIZE as usize {
+            let e = YinxError::MessageTooLarge {
+                size: payload.len(),
+                max: MAX_MESSAGE_SIZE as usize,
+            };
+            return Ok(IpcResponse::error(
+                IpcErrorCode::PayloadTooLarge,
+                e.to_string(),
+            ));
+        }
 
-        // Serialize message
-        let payload = serde_json::to_vec(message).map_err(|e| YinxError::Json {
-            source: e,
-            context: "Failed to serialize IPC message".to_string(),
-        })?;
+        let payload = if self.encoding == IpcEncoding::Json {
+            payload
+        } else {
+            if let Some(response) = self.send_negotiated(message, &payload).await? {
+                return Ok(response);
+            }
+            tracing::debug!(
+                "Daemon predates the IPC handshake, sending JSON instead of {}",
+                self.encoding.as_str()
+            );
+            wire::encode_message(IpcEncoding::Json, message)?
+        };
 
-        // Write length prefix
-        let length = payload.len() as u32;
-        stream.write_u32(length).await.map_err(|e| YinxError::Io {
-            source: e,
-            context: "Failed to write message length".to_string(),
-        })?;
+        let mut stream = self.open().await?;
+        write_frame(&mut stream, &payload).await?;
+        let payload = read_frame(&mut stream).await?;
+        wire::decode_response(IpcEncoding::Json, &payload)
+    }
 
-        // Write payload
-        stream
-            .write_all(&payload)
-            .await
-            .map_err(|e| YinxError::Io {
-                source: e,
-                context: "Failed to write message payload".to_string(),
-            })?;
+    /// Offer the client's encoding, then send the message in whichever one
+    /// the daemon picked
+    ///
+    /// None when the daemon hung up on the hello, as daemons from before
+    /// the handshake do.
+    async fn send_negotiated(
+        &self,
+        message: &IpcMessage,
+        payload: &[u8],
+    ) -> Result<Option<IpcResponse>> {
+        let mut stream = self.open().await?;
+        let hello = IpcMessage::Hello {
+            encodings: vec![self.encoding, IpcEncoding::Json],
+        };
+        write_frame(
+            &mut stream,
+            &wire::encode_message(IpcEncoding::Json, &hello)?,
+        )
+        .await?;
+        let answer = match read_frame(&mut stream).await {
+            Ok(payload) => wire::decode_response(IpcEncoding::Json, &payload)?,
+            Err(_) => return Ok(None),
+        };
+        if !answer.success {
+            // Turned away before the handshake, e.g. by the ACL
+            return Ok(Some(answer));
+        }
+        let encoding = answer
+            .data
+            .as_ref()
+            .and_then(|data| data.get("encoding"))
+            .and_then(|encoding| serde_json::from_value(encoding.clone()).ok())
+            .unwrap_or_default();
+
+        if encoding == self.encoding {
+            write_frame(&mut stream, payload).await?;
+        } else {
+            write_frame(&mut stream, &wire::encode_message(encoding, message)?).await?;
+        }
+        let payload = read_frame(&mut stream).await?;
+        Ok(Some(wire::decode_response(encoding, &payload)?))
+    }
 
-        stream.flush().await.map_err(|e| YinxError::Io {
+    async fn open(&self) -> Result<impl AsyncRead + AsyncWrite + Unpin> {
+        self.connect().await.map_err(|e| YinxError::Io {
             source: e,
-            context: "Failed to flush message".to_string(),
-        })?;
+            context: format!("Failed to connect to daemon at {:?}", self.socket_path),
+        })
+    }
 
-        // Read response length
-        let response_length = stream.read_u32().await.map_err(|e| YinxError::Io {
-            source: e,
-            context: "Failed to read response length".to_string(),
-        })?;
+    #[cfg(unix)]
+    async fn connect(&self) -> std::io::Result<UnixStream> {
+        UnixStream::connect(&self.socket_path).await
+    }
 
-        if response_length > MAX_MESSAGE_SIZE {
-            return Err(YinxError::Daemon(format!(
-                "Response too large: {} bytes",
-                response_length
-            )));
+    #[cfg(windows)]
+    async fn connect(&self) -> std::io::Result<impl AsyncRead + AsyncWrite + Unpin> {
+        use tokio::net::windows::named_pipe::ClientOptions;
+        // Every instance is busy until the server puts up the next one
+        const ERROR_PIPE_BUSY: i32 = 231;
+
+        let name = pipe_name(&self.socket_path);
+        loop {
+            match ClientOptions::new().open(&name) {
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await
+                }
+                result => return result,
+            }
         }
-
-        // Read response payload
-        let mut response_buffer = vec![0u8; response_length as usize];
-        stream
-            .read_exact(&mut response_buffer)
-            .await
-            .map_err(|e| YinxError::Io {
-                source: e,
-                context: "Failed to read response payload".to_string(),
-            })?;
-
-        // Deserialize response
-        let response: IpcResponse =
-            serde_json::from_slice(&response_buffer).map_err(|e| YinxError::Json {
-                source: e,
-                context: "Failed to deserialize IPC response".to_string(),
-            })?;
-
-        Ok(response)
     }
 }
 
@@ -319,6 +781,9 @@ mod tests {
             output: "total 0\ndrwxr-xr-x".to_string(),
             exit_code: 0,
             cwd: "/home/user".to_string(),
+            context: None,
+            dry_run: false,
+            handoff: None,
         };
 
         let json = serde_json::to_string(&msg).unwrap();
@@ -330,14 +795,103 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_error_codes() {
+        let response = IpcResponse::error(IpcErrorCode::QueueFull, "Pipeline backed up");
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["code"], "QUEUE_FULL");
+        assert_eq!(json["code"], IpcErrorCode::QueueFull.as_str());
+        assert!(IpcErrorCode::QueueFull.is_retryable());
+        assert!(!IpcErrorCode::SessionUnknown.is_retryable());
+
+        // Codes from a newer daemon still parse
+        let response: IpcResponse =
+            serde_json::from_str(r#"{"success":false,"code":"DISK_FULL"}"#).unwrap();
+        assert_eq!(response.code, Some(IpcErrorCode::Unknown));
+        let response: IpcResponse = serde_json::from_str(r#"{"success":true}"#).unwrap();
+        assert_eq!(response.code, None);
+    }
+
     #[test]
     fn test_ipc_response_creation() {
         let success = IpcResponse::success("Operation completed");
         assert!(success.success);
         assert_eq!(success.message.unwrap(), "Operation completed");
 
-        let error = IpcResponse::error("Operation failed");
+        let error = IpcResponse::error(IpcErrorCode::Internal, "Operation failed");
         assert!(!error.success);
         assert_eq!(error.message.unwrap(), "Operation failed");
+
+        let skipped = IpcResponse::skipped("Excluded");
+        assert!(skipped.success && skipped.skipped);
+
+        let busy = IpcResponse::busy(
+            IpcErrorCode::RateLimited,
+            "Slow down",
+            Duration::from_millis(250),
+        );
+        assert!(!busy.success);
+        assert_eq!(busy.retry_after_ms, Some(250));
+    }
+
+    #[test]
+    fn test_ipc_response_skipped_flag_optional() {
+        // Responses from older daemons have no `skipped` field
+        let response: IpcResponse = serde_json::from_str(r#"{"success":true}"#).unwrap();
+        assert!(!response.skipped);
+
+        let json = serde_json::to_string(&IpcResponse::success("ok")).unwrap();
+        assert!(!json.contains("skipped"));
+    }
+
+    #[test]
+    fn test_output_handoff() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("yinx_1.out");
+        std::fs::write(&path, "PORT   STATE SERVICE\n22/tcp open  ssh\n").unwrap();
+        let uid = super::super::current_uid();
+
+        let handoff = OutputHandoff::for_file(&path).unwrap();
+        assert_eq!(handoff.size, 38);
+        let output = handoff.claim(uid).unwrap();
+        assert!(output.starts_with("PORT"));
+        assert!(!path.exists());
+
+        // A file rewritten after the hook hashed it is refused
+        std::fs::write(&path, "original").unwrap();
+        let handoff = OutputHandoff::for_file(&path).unwrap();
+        std::fs::write(&path, "tampered").unwrap();
+        assert!(handoff.claim(uid).is_err());
+        assert!(path.exists());
+
+        #[cfg(unix)]
+        {
+            std::fs::write(&path, "original").unwrap();
+            assert!(handoff.claim(uid + 1).is_err());
+
+            let link = temp_dir.path().join("link.out");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            let linked = OutputHandoff {
+                path: link,
+                ..handoff.clone()
+            };
+            assert!(linked.claim(uid).is_err());
+        }
+
+        // Oversized output is refused before it's read
+        let oversized = OutputHandoff {
+            size: MAX_HANDOFF_SIZE + 1,
+            ..handoff
+        };
+        assert!(oversized.claim(uid).is_err());
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_pipe_name_per_socket_path() {
+        let name = pipe_name(Path::new("~/.yinx/daemon.sock"));
+        assert!(name.starts_with(r"\\.\pipe\yinx-"));
+        assert_eq!(name, pipe_name(Path::new("~/.yinx/daemon.sock")));
+        assert_ne!(name, pipe_name(Path::new("/srv/yinx/daemon.sock")));
     }
 }
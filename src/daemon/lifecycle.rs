@@ -0,0 +1,353 @@
+// Session lifecycle: idle auto-pause, resume on activity, clean stop on shutdown
+
+use crate::error::Result;
+use crate::session::{Session, SessionManager, SessionStatus};
+use crate::storage::Database;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Mark sessions orphaned by a previous daemon as interrupted
+///
+/// Runs before the daemon starts tracking `keep`. The gap is measured from the
+/// session's last stored capture.
+pub fn reconcile_stale_sessions(
+    data_dir: PathBuf,
+    database: &Database,
+    keep: Option<&Uuid>,
+) -> Result<Vec<Session>> {
+    let manager = SessionManager::new(data_dir);
+    let interrupted = manager.reconcile_stale(keep, |session| {
+        database
+            .last_capture_timestamp(&session.id.to_string())
+            .ok()
+            .flatten()
+            .and_then(|ts| DateTime::from_timestamp(ts, 0))
+    })?;
+
+    for session in &interrupted {
+        tracing::warn!(
+            "Session {} ({}) was left active by a previous daemon; marked interrupted",
+            session.name,
+            session.id
+        );
+        database.set_session_status(&session.id.to_string(), "interrupted", None)?;
+    }
+
+    Ok(interrupted)
+}
+
+/// Tracks the session the daemon is capturing into
+pub struct SessionLifecycle {
+    data_dir: PathBuf,
+    database: Database,
+    session: Option<Session>,
+    /// None disables idle pausing
+    idle_timeout: Option<Duration>,
+    last_activity: Instant,
+    /// Time-left thresholds to warn at when the session has a deadline
+    deadline_warnings: Vec<Duration>,
+}
+
+impl SessionLifecycle {
+    /// Track `session_id`, or the most recent unstopped session if none is given
+    pub fn new(
+        data_dir: PathBuf,
+        database: Database,
+        idle_timeout: Option<Duration>,
+        session_id: Option<&Uuid>,
+    ) -> Self {
+        let found = match session_id {
+            Some(id) => Session::load(&data_dir, id).map(Some),
+            None => SessionManager::new(data_dir.clone()).find_active(),
+        };
+        let session = match found {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::warn!("Failed to look up active session: {}", e);
+                None
+            }
+        };
+
+        if let Some(session) = &session {
+            tracing::info!("Tracking session {} ({})", session.name, session.id);
+        }
+
+        let mut lifecycle = Self {
+            data_dir,
+            database,
+            session,
+            idle_timeout: idle_timeout.filter(|d| !d.is_zero()),
+            last_activity: Instant::now(),
+            deadline_warnings: Vec::new(),
+        };
+
+        // Restarted for a session the crashed daemon marked interrupted
+        if let Some(session) = &mut lifecycle.session {
+            if session_id.is_some() && session.status == SessionStatus::Interrupted {
+                session.resume();
+                session.metadata.insert(
+                    "resumed_at".to_string(),
+                    serde_json::Value::String(Utc::now().to_rfc3339()),
+                );
+                tracing::info!("Resuming interrupted session {}", session.name);
+                if let Err(e) = lifecycle.persist("active", None) {
+                    tracing::warn!("Failed to resume session: {}", e);
+                }
+            }
+        }
+        lifecycle
+    }
+
+    /// Warn when the session deadline is this close
+    pub fn with_deadline_warnings(mut self, warnings: Vec<Duration>) -> Self {
+        self.deadline_warnings = warnings;
+        self
+    }
+
+    /// The session being tracked, if any
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Record capture activity, resuming the session if it was paused
+    pub fn touch(&mut self) -> Result<()> {
+        self.last_activity = Instant::now();
+
+        if let Some(session) = &mut self.session {
+            if session.status == SessionStatus::Paused {
+                session.resume();
+                tracing::info!("Session {} resumed after activity", session.name);
+                self.persist("active", None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pause the session if nothing has been captured for `idle_timeout`
+    ///
+    /// Returns true if the session was paused by this call.
+    pub fn check_idle(&mut self) -> Result<bool> {
+        let Some(timeout) = self.idle_timeout else {
+            return Ok(false);
+        };
+        if self.last_activity.elapsed() < timeout {
+            return Ok(false);
+        }
+
+        match &mut self.session {
+            Some(session) if session.status == SessionStatus::Active => {
+                session.pause();
+                tracing::info!(
+                    "Session {} paused after {}s idle",
+                    session.name,
+                    timeout.as_secs()
+                );
+                self.persist("paused", None)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Check the exam clock against the warning thresholds
+    ///
+    /// Returns the smallest threshold newly crossed at `now`. Warnings already
+    /// given are recorded in the session so a restarted daemon doesn't repeat
+    /// them, and thresholds skipped over (e.g. while the daemon was down) are
+    /// folded into one warning.
+    pub fn check_deadline(&mut self, now: DateTime<Utc>) -> Result<Option<Duration>> {
+        let Some(session) = &mut self.session else {
+            return Ok(None);
+        };
+        if session.status == SessionStatus::Stopped {
+            return Ok(None);
+        }
+        let Some(remaining) = session.time_remaining(now) else {
+            return Ok(None);
+        };
+        let warned = session
+            .metadata
+            .get("deadline_warned")
+            .and_then(|v| v.as_u64());
+
+        let crossed = self
+            .deadline_warnings
+            .iter()
+            .copied()
+            .filter(|t| chrono::Duration::from_std(*t).is_ok_and(|t| remaining <= t))
+            .filter(|t| warned.is_none_or(|w| t.as_secs() < w))
+            .min();
+        let Some(threshold) = crossed else {
+            return Ok(None);
+        };
+
+        session.metadata.insert(
+            "deadline_warned".to_string(),
+            serde_json::Value::from(threshold.as_secs()),
+        );
+        session.save(&self.data_dir)?;
+        Ok(Some(threshold))
+    }
+
+    /// Stop the tracked session (daemon is shutting down)
+    pub fn stop(&mut self) -> Result<()> {
+        if let Some(session) = &mut self.session {
+            if session.status != SessionStatus::Stopped {
+                session.stop();
+                tracing::info!("Session {} stopped on shutdown", session.name);
+                self.persist("stopped", Some(Utc::now().timestamp()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark the tracked session interrupted (the daemon is dying)
+    pub fn interrupt(&mut self) -> Result<()> {
+        let last_activity = chrono::Duration::from_std(self.last_activity.elapsed())
+            .ok()
+            .map(|idle| Utc::now() - idle);
+        if let Some(session) = &mut self.session {
+            if session.status != SessionStatus::Stopped {
+                session.interrupt(last_activity);
+                self.persist("interrupted", None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the session state file and mirror the status into the database
+    fn persist(&self, status: &str, stopped_at: Option<i64>) -> Result<()> {
+        if let Some(session) = &self.session {
+            session.save(&self.data_dir)?;
+            self.database
+                .set_session_status(&session.id.to_string(), status, stopped_at)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn lifecycle(temp_dir: &TempDir, idle: Option<Duration>) -> SessionLifecycle {
+        let data_dir = temp_dir.path().to_path_buf();
+        SessionManager::new(data_dir.clone())
+            .create_session(Some("test".to_string()))
+            .unwrap();
+        let database = Database::new(&data_dir.join("db.sqlite")).unwrap();
+        SessionLifecycle::new(data_dir, database, idle, None)
+    }
+
+    #[test]
+    fn test_idle_pause_and_resume() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut lc = lifecycle(&temp_dir, Some(Duration::from_millis(10)));
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(lc.check_idle().unwrap());
+        assert_eq!(lc.session().unwrap().status, SessionStatus::Paused);
+        // Already paused, nothing to do
+        assert!(!lc.check_idle().unwrap());
+
+        lc.touch().unwrap();
+        assert_eq!(lc.session().unwrap().status, SessionStatus::Active);
+
+        let on_disk = Session::load(temp_dir.path(), &lc.session().unwrap().id).unwrap();
+        assert_eq!(on_disk.status, SessionStatus::Active);
+    }
+
+    #[test]
+    fn test_idle_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut lc = lifecycle(&temp_dir, Some(Duration::ZERO));
+        assert!(!lc.check_idle().unwrap());
+    }
+
+    #[test]
+    fn test_stop_persists() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut lc = lifecycle(&temp_dir, None);
+        lc.stop().unwrap();
+
+        let id = lc.session().unwrap().id;
+        let on_disk = Session::load(temp_dir.path(), &id).unwrap();
+        assert_eq!(on_disk.status, SessionStatus::Stopped);
+        assert!(on_disk.stopped_at.is_some());
+    }
+
+    #[test]
+    fn test_interrupt_and_resume_on_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut lc = lifecycle(&temp_dir, None);
+        lc.interrupt().unwrap();
+
+        let id = lc.session().unwrap().id;
+        let on_disk = Session::load(temp_dir.path(), &id).unwrap();
+        assert_eq!(on_disk.status, SessionStatus::Interrupted);
+
+        // A daemon restarted for that session picks it back up
+        let database = Database::new(&temp_dir.path().join("db.sqlite")).unwrap();
+        let lc = SessionLifecycle::new(temp_dir.path().to_path_buf(), database, None, Some(&id));
+        assert_eq!(lc.session().unwrap().status, SessionStatus::Active);
+    }
+
+    #[test]
+    fn test_deadline_warnings_fire_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().to_path_buf();
+        let now = Utc::now();
+        let mut manager = SessionManager::new(data_dir.clone());
+        manager.create_session(Some("exam".to_string())).unwrap();
+        let session = manager.current_session_mut().unwrap();
+        session.set_deadline(now + chrono::Duration::hours(3));
+        session.save(&data_dir).unwrap();
+        let id = session.id;
+
+        let hour = Duration::from_secs(3600);
+        let warnings = vec![4 * hour, hour, Duration::from_secs(900)];
+        let database = Database::new(&data_dir.join("db.sqlite")).unwrap();
+        let mut lc = SessionLifecycle::new(data_dir.clone(), database.clone(), None, Some(&id))
+            .with_deadline_warnings(warnings.clone());
+
+        assert_eq!(lc.check_deadline(now).unwrap(), Some(4 * hour));
+        assert_eq!(lc.check_deadline(now).unwrap(), None);
+
+        // Both remaining thresholds passed while the daemon was down: one warning
+        let mut lc = SessionLifecycle::new(data_dir, database, None, Some(&id))
+            .with_deadline_warnings(warnings);
+        let later = now + chrono::Duration::minutes(170);
+        assert_eq!(
+            lc.check_deadline(later).unwrap(),
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(lc.check_deadline(later).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reconcile_marks_orphans() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().to_path_buf();
+        let mut manager = SessionManager::new(data_dir.clone());
+        let orphan = manager
+            .create_session(Some("orphan".to_string()))
+            .unwrap()
+            .id;
+        let current = manager
+            .create_session(Some("current".to_string()))
+            .unwrap()
+            .id;
+        let database = Database::new(&data_dir.join("db.sqlite")).unwrap();
+
+        let interrupted =
+            reconcile_stale_sessions(data_dir.clone(), &database, Some(&current)).unwrap();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].id, orphan);
+
+        let lc = SessionLifecycle::new(data_dir, database, None, Some(&current));
+        assert_eq!(lc.session().unwrap().id, current);
+    }
+}
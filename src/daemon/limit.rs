@@ -0,0 +1,125 @@
+// Flood protection for the IPC socket
+//
+// A hook stuck in a loop, or a script hammering `yinx internal capture`,
+// shouldn't be able to starve the pipeline or the other users of a shared
+// daemon. Each peer uid draws requests from its own token bucket; an empty
+// bucket gets a polite answer saying how long to wait rather than a dropped
+// connection. The number of connections handled at once is capped
+// separately with a semaphore in the accept loop.
+
+use crate::config::RateLimitConfig;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Buckets kept before full, idle ones are forgotten
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+/// How long a client turned away for lack of a free connection should wait
+pub const BUSY_RETRY: Duration = Duration::from_millis(250);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets per peer uid; the default limits nothing
+#[derive(Default)]
+pub struct ClientLimiter {
+    per_second: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<u32, Bucket>>,
+}
+
+impl ClientLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            per_second: config.per_second,
+            burst: f64::from(config.burst.max(1)),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a request from `uid`'s bucket at `now`
+    ///
+    /// Returns how long to back off when the bucket is empty.
+    pub fn check(&self, uid: u32, now: Instant) -> Result<(), Duration> {
+        if self.per_second <= 0.0 {
+            return Ok(());
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&uid) {
+            let (per_second, burst) = (self.per_second, self.burst);
+            buckets.retain(|_, bucket| refilled(bucket, per_second, burst, now) < burst);
+        }
+        let bucket = buckets.entry(uid).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = refilled(bucket, self.per_second, self.burst, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.per_second,
+            ))
+        }
+    }
+}
+
+/// Tokens in `bucket` at `now`
+fn refilled(bucket: &Bucket, per_second: f64, burst: f64, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    (bucket.tokens + elapsed * per_second).min(burst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_second: f64, burst: u32) -> ClientLimiter {
+        ClientLimiter::new(&RateLimitConfig { per_second, burst })
+    }
+
+    #[test]
+    fn test_burst_then_backoff() {
+        let limiter = limiter(10.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check(1000, start).is_ok());
+        }
+        let wait = limiter.check(1000, start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(100));
+
+        // Other users have their own bucket
+        assert!(limiter.check(1001, start).is_ok());
+
+        // One token back after a tenth of a second
+        let later = start + Duration::from_millis(100);
+        assert!(limiter.check(1000, later).is_ok());
+        assert!(limiter.check(1000, later).is_err());
+    }
+
+    #[test]
+    fn test_refill_capped_at_burst() {
+        let limiter = limiter(10.0, 2);
+        let start = Instant::now();
+        assert!(limiter.check(1000, start).is_ok());
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.check(1000, later).is_ok());
+        assert!(limiter.check(1000, later).is_ok());
+        assert!(limiter.check(1000, later).is_err());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let start = Instant::now();
+        for limiter in [limiter(0.0, 1), ClientLimiter::default()] {
+            for _ in 0..1_000 {
+                assert!(limiter.check(1000, start).is_ok());
+            }
+        }
+    }
+}
@@ -1,22 +1,71 @@
 // Daemon module: background process management for terminal capture
 
+mod acl;
+mod context;
+mod emergency;
+mod exclusions;
+mod health;
 mod ipc;
+mod lifecycle;
+mod limit;
+mod models;
 mod pipeline;
 mod process;
+mod remote;
+mod service;
 mod signals;
+mod sources;
+mod supervisor;
+mod wire;
 
-pub use ipc::{IpcClient, IpcMessage, IpcResponse, IpcServer};
-pub use pipeline::{CaptureEvent, Pipeline};
+pub use acl::{current_uid, username_for_uid, Acl, UserScope};
+pub use context::CaptureContext;
+pub use emergency::install_panic_hook;
+pub use exclusions::CommandExclusions;
+pub use health::{check_alarms, PipelineHealth};
+pub(crate) use ipc::{accept_message, write_response_as};
+pub use ipc::{
+    peer_uid, pipe_name, IpcClient, IpcErrorCode, IpcMessage, IpcResponse, IpcServer, IpcStream,
+    OutputHandoff,
+};
+pub use lifecycle::{reconcile_stale_sessions, SessionLifecycle};
+use limit::ClientLimiter;
+pub use models::{ModelHub, ModelState, ModelStatus};
+pub(crate) use pipeline::{capture_host, chunk_output, process_capture, scope_to_host};
+pub use pipeline::{
+    dry_run, CaptureEvent, DryRunEntity, DryRunSummary, Pipeline, PipelineExtensions,
+};
 pub use process::ProcessManager;
+use remote::RemoteContext;
+pub use remote::RemoteServer;
+pub use service::{ServiceManager, ServiceSpec};
 pub use signals::SignalHandler;
+pub(crate) use sources::is_facility;
+use sources::LogSources;
+pub use supervisor::{crash_dir, install_crash_handler, supervise, CrashReport, PipelineState};
 
-use crate::config::Config;
+use crate::board::CommandTargets;
+use crate::config::{parse_duration, parse_size, Config, ModelPreload};
+use crate::enrichment::{AsnEnricher, DnsEnricher};
+use crate::entities::EntityExtractor;
 use crate::error::{Result, YinxError};
+use crate::facade::{search_index, SearchHit};
+use crate::filtering::ChunkPolicy;
+use crate::hooks::HookRunner;
 use crate::patterns::PatternRegistry;
-use crate::storage::StorageManager;
+use crate::proof::ProofDetector;
+use crate::rules::RuleEngine;
+use crate::session::{Session, SessionStatus};
+use crate::storage::{OutputLimit, StorageManager};
+use crate::summary::Summarizer;
+use crate::triage::Triage;
+use crate::vault::Vault;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use tokio::task;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify, Semaphore};
+use tokio::{task, time};
+use uuid::Uuid;
 
 /// Main daemon struct that manages the lifecycle and components
 pub struct Daemon {
@@ -24,18 +73,61 @@ pub struct Daemon {
     process_manager: ProcessManager,
     storage: Arc<StorageManager>,
     patterns: Arc<PatternRegistry>,
+    extensions: Arc<PipelineExtensions>,
+    exclusions: Arc<CommandExclusions>,
+    acl: Arc<Acl>,
+    models: Arc<ModelHub>,
     pipeline: Option<Pipeline>,
     ipc_server: Option<IpcServer>,
+    /// Session this daemon was started for (set by `yinx start`)
+    session_id: Option<Uuid>,
+    /// Why the configured pattern files were passed over for the bundled ones
+    pattern_fallback: Option<String>,
+}
+
+/// Open the configured data directory with vault and size limits applied
+pub(crate) fn open_storage(config: &Config) -> Result<StorageManager> {
+    let mut storage = StorageManager::new(expand_tilde(&config.storage.data_dir))?
+        .with_chunk_policy(ChunkPolicy {
+            max_tokens: config.indexing.chunk_max_tokens,
+            keep_sections: config.indexing.chunk_keep_sections,
+        })
+        .with_keyword_tokenizers(config.indexing.tokenizers.clone());
+    if config.vault.enabled {
+        storage = storage.with_vault(Vault::open_or_create(&expand_tilde(
+            &config.vault.key_file,
+        ))?);
+    }
+    match parse_size(&config.storage.max_blob_size) {
+        Some(0) | None => {}
+        Some(max_bytes) => {
+            storage = storage.with_output_limit(OutputLimit {
+                max_bytes: max_bytes as usize,
+                head_ratio: config.storage.truncate_head,
+            })
+        }
+    }
+    Ok(storage)
+}
+
+/// Hooks, rules and detectors the pipeline runs on every capture
+pub(crate) fn load_extensions(config: &Config) -> Result<PipelineExtensions> {
+    Ok(PipelineExtensions {
+        hooks: HookRunner::new(config.hooks.clone()),
+        rules: RuleEngine::from_file(&expand_tilde(&config.patterns.rules_file))?,
+        proofs: ProofDetector::new(&config.proof)?,
+        triage: Triage::new(&config.triage)?,
+        summarizer: Summarizer::new(&config.summaries),
+        dns: DnsEnricher::new(&config.enrichment),
+        asn: AsnEnricher::new(&config.enrichment),
+        dedup_window: parse_duration(&config.capture.dedup_window).unwrap_or_default(),
+    })
 }
 
 impl Daemon {
     /// Create a new daemon instance
     pub fn new(config: Config) -> Result<Self> {
-        // Expand tilde in data_dir if needed
-        let data_dir = expand_tilde(&config.storage.data_dir);
-
-        // Initialize storage
-        let storage = Arc::new(StorageManager::new(data_dir.clone())?);
+        let storage = Arc::new(open_storage(&config)?);
 
         // Initialize process manager
         let pid_file = expand_tilde(&config.daemon.pid_file);
@@ -46,25 +138,54 @@ impl Daemon {
         let tools_path = expand_tilde(&config.patterns.tools_file);
         let filters_path = expand_tilde(&config.patterns.filters_file);
 
-        let patterns = Arc::new(
-            PatternRegistry::from_config_files(&entities_path, &tools_path, &filters_path)
-                .map_err(|e| {
-                    tracing::warn!("Failed to load pattern registry: {}", e);
-                    tracing::warn!("Using default/empty patterns. Run 'yinx config init' to install pattern files.");
-                    e
-                })?,
-        );
+        // Capture still starts without them, on the patterns shipped with yinx
+        let (patterns, error) = PatternRegistry::from_config_files_or_bundled(
+            &entities_path,
+            &tools_path,
+            &filters_path,
+        )?;
+        let pattern_fallback = error.map(|e| {
+            tracing::warn!("Failed to load pattern registry: {}", e);
+            tracing::warn!(
+                "Capturing with the bundled patterns. Run 'yinx config init' to install pattern files."
+            );
+            e.to_string()
+        });
+        let patterns = Arc::new(patterns.with_aliases(&config.tools.aliases));
+
+        let extensions = Arc::new(load_extensions(&config)?);
+
+        let exclusions = Arc::new(CommandExclusions::new(&config.capture.exclude_commands)?);
+        let acl = Arc::new(Acl::new(&config.acl, current_uid())?);
+        let models = Arc::new(ModelHub::new(&config));
 
         Ok(Self {
             config,
             process_manager,
             storage,
             patterns,
+            extensions,
+            exclusions,
+            acl,
+            models,
             pipeline: None,
             ipc_server: None,
+            session_id: None,
+            pattern_fallback,
         })
     }
 
+    /// Why the bundled patterns are in use, if the configured ones failed to load
+    pub fn pattern_fallback(&self) -> Option<&str> {
+        self.pattern_fallback.as_deref()
+    }
+
+    /// Capture into the given session instead of the most recent active one
+    pub fn with_session(mut self, session_id: Uuid) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+
     /// Start the daemon in the foreground (for testing)
     pub async fn run_foreground(&mut self) -> Result<()> {
         // Acquire PID and lock
@@ -82,16 +203,39 @@ impl Daemon {
 
         // Setup signal handler
         let mut signal_handler = SignalHandler::new()?;
+        // Raised by an IPC Stop from the owner
+        let stop_requested = Arc::new(Notify::new());
+        let limiter = Arc::new(ClientLimiter::new(&self.config.daemon.rate_limit));
+        let connections = Arc::new(Semaphore::new(self.config.daemon.max_connections.max(1)));
 
         // Start IPC server
         let socket_path = expand_tilde(&self.config.daemon.socket_path);
         let mut ipc_server = IpcServer::new(socket_path);
+        if self.acl.is_shared() {
+            // Any local user can reach the socket; handle_client authenticates them
+            ipc_server = ipc_server.with_mode(0o666);
+            tracing::info!("Shared mode enabled: accepting captures from other users");
+        }
         ipc_server.bind().await?;
 
+        let remote_server = if self.config.remote.enabled {
+            Some(RemoteServer::bind(&self.config.remote.listen).await?)
+        } else {
+            None
+        };
+        let remote_token: Arc<str> = Arc::from(self.config.remote.token.as_str());
+        let remote_user: Arc<str> = if self.config.remote.user.is_empty() {
+            let uid = current_uid();
+            Arc::from(username_for_uid(uid).unwrap_or_else(|| uid.to_string()))
+        } else {
+            Arc::from(self.config.remote.user.as_str())
+        };
+
         // Start pipeline
         let pipeline = Pipeline::new(
             self.storage.clone(),
             self.patterns.clone(),
+            self.extensions.clone(),
             self.config.capture.buffer_size,
             self.config.capture.batch_size,
             parse_flush_interval(&self.config.capture.flush_interval),
@@ -100,21 +244,165 @@ impl Daemon {
         self.pipeline = Some(pipeline);
         self.ipc_server = Some(ipc_server);
 
+        // Anything still Active/Paused now was orphaned by a daemon that died
+        let data_dir = expand_tilde(&self.config.storage.data_dir);
+        if let Err(e) = reconcile_stale_sessions(
+            data_dir.clone(),
+            &self.storage.database,
+            self.session_id.as_ref(),
+        ) {
+            tracing::error!("Failed to reconcile stale sessions: {}", e);
+        }
+
+        let spill_dir = emergency::spill_dir(&data_dir);
+
+        // Track the active session for idle pausing and clean shutdown
+        let idle_timeout = crate::config::parse_duration(&self.config.capture.idle_timeout);
+        let lifecycle = Arc::new(Mutex::new(
+            SessionLifecycle::new(
+                data_dir,
+                self.storage.database.clone(),
+                idle_timeout,
+                self.session_id.as_ref(),
+            )
+            .with_deadline_warnings(deadline_warnings(&self.config)),
+        ));
+
+        // Captures a panicking daemon spilled go in ahead of new ones
+        let pipeline = self.pipeline.as_ref().unwrap();
+        match emergency::replay_spilled(&spill_dir, pipeline).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Replayed {} spilled capture(s)", count),
+            Err(e) => tracing::error!("Failed to replay spilled captures: {}", e),
+        }
+        emergency::arm(emergency::Emergency {
+            spill_dir,
+            pending: pipeline.pending().clone(),
+            lifecycle: lifecycle.clone(),
+            database: self.storage.database.clone(),
+        });
+
+        let log_sources = LogSources::start(
+            &self.config.sources,
+            self.pipeline.as_ref().unwrap().clone_sender(),
+            lifecycle.clone(),
+        )
+        .await?;
+        let mut idle_timer = time::interval(idle_check_interval(idle_timeout));
+        idle_timer.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+
         tracing::info!("Daemon started successfully");
 
+        // Load models off the event loop; Status reports progress meanwhile
+        if self.config.embedding.preload == ModelPreload::Eager {
+            let models = self.models.clone();
+            let config = self.config.clone();
+            task::spawn_blocking(move || models.warm_up(&config));
+        }
+
         // Main event loop
         loop {
             tokio::select! {
                 // Accept IPC connections
-                Ok(stream) = self.ipc_server.as_mut().unwrap().accept() => {
-                    let pipeline = self.pipeline.as_ref().unwrap().clone_sender();
+                Ok(mut stream) = self.ipc_server.as_mut().unwrap().accept() => {
+                    let Ok(permit) = connections.clone().try_acquire_owned() else {
+                        health::record_throttled();
+                        tracing::warn!(
+                            "Turned a client away: {} connections already open (daemon.max_connections)",
+                            self.config.daemon.max_connections
+                        );
+                        task::spawn(async move {
+                            let response = IpcResponse::busy(
+                                IpcErrorCode::Busy,
+                                "Daemon busy: too many open connections",
+                                limit::BUSY_RETRY,
+                            );
+                            let _ = ipc::write_response(&mut stream, &response).await;
+                        });
+                        continue;
+                    };
+                    let ctx = ClientContext {
+                        pipeline: self.pipeline.as_ref().unwrap().clone_sender(),
+                        lifecycle: lifecycle.clone(),
+                        exclusions: self.exclusions.clone(),
+                        acl: self.acl.clone(),
+                        models: self.models.clone(),
+                        stop: stop_requested.clone(),
+                        targets: self.config.capture.register_targets.then(|| TargetRegistrar {
+                            storage: self.storage.clone(),
+                            patterns: self.patterns.clone(),
+                        }),
+                        storage: self.storage.clone(),
+                        patterns: self.patterns.clone(),
+                        pattern_fallback: self.pattern_fallback.clone(),
+                        dry_run: self.config.capture.dry_run,
+                        limiter: limiter.clone(),
+                    };
                     task::spawn(async move {
-                        if let Err(e) = handle_client(stream, pipeline).await {
+                        if let Err(e) = handle_client(stream, ctx).await {
+                            health::record_ipc_error();
                             tracing::error!("Client handler error: {}", e);
                         }
+                        drop(permit);
                     });
                 }
 
+                // Accept remote API connections
+                Ok((stream, peer)) = async {
+                    match &remote_server {
+                        Some(server) => server.accept().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    let ctx = RemoteContext {
+                        pipeline: self.pipeline.as_ref().unwrap().clone_sender(),
+                        lifecycle: lifecycle.clone(),
+                        exclusions: self.exclusions.clone(),
+                        storage: self.storage.clone(),
+                        token: remote_token.clone(),
+                        user: remote_user.clone(),
+                        acl: self.acl.clone(),
+                    };
+                    task::spawn(async move {
+                        if let Err(e) = remote::handle_remote(stream, peer, ctx).await {
+                            tracing::error!("Remote client {} error: {}", peer, e);
+                        }
+                    });
+                }
+
+                // Pause the session once capture has gone quiet
+                _ = idle_timer.tick() => {
+                    let paused = lifecycle.lock().unwrap().check_idle();
+                    match paused {
+                        Ok(true) => {
+                            if let Err(e) = self.pipeline.as_ref().unwrap().persist_filter_state() {
+                                tracing::error!("Failed to persist filter state: {}", e);
+                            }
+                        }
+                        Ok(false) => {}
+                        Err(e) => tracing::error!("Idle check failed: {}", e),
+                    }
+
+                    let crossed = lifecycle.lock().unwrap().check_deadline(chrono::Utc::now());
+                    match crossed {
+                        Ok(Some(threshold)) => {
+                            let message = format!(
+                                "Exam clock: {} left until the deadline",
+                                format_threshold(threshold)
+                            );
+                            tracing::warn!("{}", message);
+                            notify_desktop(&message);
+                        }
+                        Ok(None) => {}
+                        Err(e) => tracing::error!("Deadline check failed: {}", e),
+                    }
+                }
+
+                _ = stop_requested.notified() => {
+                    tracing::info!("Stop requested over IPC");
+                    break;
+                }
+
                 // Handle signals
                 sig = signal_handler.wait() => {
                     if signals::should_shutdown(sig) {
@@ -127,14 +415,20 @@ impl Daemon {
             }
         }
 
-        // Shutdown
+        // Shutdown (drains the pipeline, which persists filter state)
+        log_sources.stop().await;
         self.shutdown().await?;
+        emergency::disarm();
+        if let Err(e) = lifecycle.lock().unwrap().stop() {
+            tracing::error!("Failed to stop session cleanly: {}", e);
+        }
         cleanup();
 
         Ok(())
     }
 
     /// Start the daemon as a background process
+    #[cfg(unix)]
     pub fn start_daemon(&mut self) -> Result<()> {
         // Check if already running
         if self.process_manager.is_running() {
@@ -142,7 +436,8 @@ impl Daemon {
         }
 
         // Ensure log directory exists FIRST
-        if let Some(parent) = self.config.daemon.log_file.parent() {
+        let log_file = expand_tilde(&self.config.daemon.log_file);
+        if let Some(parent) = log_file.parent() {
             std::fs::create_dir_all(parent).map_err(|e| YinxError::Io {
                 source: e,
                 context: format!("Failed to create log directory: {:?}", parent),
@@ -150,23 +445,24 @@ impl Daemon {
         }
 
         // Setup daemonization log files
-        let stdout_path = std::fs::File::create(
-            self.config.daemon.log_file.with_extension("stdout"),
-        )
-        .map_err(|e| YinxError::Io {
-            source: e,
-            context: "Failed to create stdout log file".to_string(),
-        })?;
-        let stderr_path = std::fs::File::create(
-            self.config.daemon.log_file.with_extension("stderr"),
-        )
-        .map_err(|e| YinxError::Io {
-            source: e,
-            context: "Failed to create stderr log file".to_string(),
-        })?;
+        let stdout_path =
+            std::fs::File::create(log_file.with_extension("stdout")).map_err(|e| {
+                YinxError::Io {
+                    source: e,
+                    context: "Failed to create stdout log file".to_string(),
+                }
+            })?;
+        let stderr_path =
+            std::fs::File::create(log_file.with_extension("stderr")).map_err(|e| {
+                YinxError::Io {
+                    source: e,
+                    context: "Failed to create stderr log file".to_string(),
+                }
+            })?;
 
+        // run_foreground writes the PID file, from whichever process ends up
+        // running the daemon
         let daemon = daemonize::Daemonize::new()
-            .pid_file(&self.config.daemon.pid_file)
             .working_directory(std::env::current_dir().map_err(|e| YinxError::Io {
                 source: e,
                 context: "Failed to get current directory".to_string(),
@@ -179,7 +475,12 @@ impl Daemon {
             .start()
             .map_err(|e| YinxError::Daemon(format!("Failed to daemonize: {}", e)))?;
 
+        if self.config.daemon.supervisor.enabled {
+            return supervise(&self.config, self.session_id);
+        }
+
         // In the daemon process now, start the runtime
+        install_panic_hook();
         let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
             source: e,
             context: "Failed to create tokio runtime".to_string(),
@@ -193,15 +494,65 @@ impl Daemon {
         Ok(())
     }
 
+    /// Start the daemon as a background process
+    ///
+    /// There is no fork on Windows: the effective config is written next to
+    /// the log file and a detached `yinx _internal run-daemon` loads it and
+    /// runs the daemon in the foreground, service-style (`_internal
+    /// supervise` in supervisor mode).
+    #[cfg(windows)]
+    pub fn start_daemon(&mut self) -> Result<()> {
+        if self.process_manager.is_running() {
+            return Err(YinxError::Daemon("Daemon is already running".to_string()));
+        }
+
+        let log_file = expand_tilde(&self.config.daemon.log_file);
+        if let Some(parent) = log_file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to create log directory: {:?}", parent),
+            })?;
+        }
+        let create_log = |extension: &str| {
+            std::fs::File::create(log_file.with_extension(extension)).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to create {} log file", extension),
+            })
+        };
+        let (stdout, stderr) = (create_log("stdout")?, create_log("stderr")?);
+
+        // The child must see the same profile and overrides this process did
+        let config_file = log_file.with_file_name("daemon.toml");
+        self.config.save(&config_file)?;
+
+        let action = if self.config.daemon.supervisor.enabled {
+            "supervise"
+        } else {
+            "run-daemon"
+        };
+        let mut args: Vec<std::ffi::OsString> = vec![
+            "--config".into(),
+            config_file.into(),
+            "_internal".into(),
+            action.into(),
+        ];
+        if let Some(session_id) = self.session_id {
+            args.push("--session-id".into());
+            args.push(session_id.to_string().into());
+        }
+        let pid = process::spawn_detached(&args, stdout, stderr)?;
+        tracing::info!("Started background daemon (PID {})", pid);
+
+        Ok(())
+    }
+
     /// Stop the daemon
     pub fn stop_daemon(&self) -> Result<()> {
         if !self.process_manager.is_running() {
             return Err(YinxError::Daemon("Daemon is not running".to_string()));
         }
 
-        // Send SIGTERM
-        self.process_manager
-            .signal(nix::sys::signal::Signal::SIGTERM)?;
+        self.process_manager.terminate()?;
 
         tracing::info!("Sent shutdown signal to daemon");
 
@@ -245,37 +596,356 @@ impl Pipeline {
     }
 }
 
+/// Shared state handed to each client connection
+#[derive(Clone)]
+struct ClientContext {
+    pipeline: mpsc::Sender<CaptureEvent>,
+    lifecycle: Arc<Mutex<SessionLifecycle>>,
+    exclusions: Arc<CommandExclusions>,
+    acl: Arc<Acl>,
+    models: Arc<ModelHub>,
+    /// Ends the daemon's event loop
+    stop: Arc<Notify>,
+    /// Registers command targets on the board; None when disabled
+    targets: Option<TargetRegistrar>,
+    /// What dry runs are checked against
+    storage: Arc<StorageManager>,
+    patterns: Arc<PatternRegistry>,
+    /// Reported by Status when the bundled patterns stand in
+    pattern_fallback: Option<String>,
+    /// Treat every capture as a dry run (capture.dry_run)
+    dry_run: bool,
+    /// Requests per user (daemon.rate_limit)
+    limiter: Arc<ClientLimiter>,
+}
+
+/// Puts the hosts a starting command names on the board
+#[derive(Clone)]
+struct TargetRegistrar {
+    storage: Arc<StorageManager>,
+    patterns: Arc<PatternRegistry>,
+}
+
+impl TargetRegistrar {
+    /// Register a command's targets, returning how many moved on the board
+    fn register(&self, session_id: &str, command: &str) -> Result<usize> {
+        let extractor = EntityExtractor::new((*self.patterns).clone());
+        let targets = CommandTargets::parse(command, &extractor);
+        let mut registered = 0;
+        for (host, stage) in targets.intent() {
+            if self
+                .storage
+                .database
+                .advance_host_stage(session_id, &host, stage)?
+            {
+                tracing::debug!("Registered target {} as {}", host, stage.as_str());
+                registered += 1;
+            }
+        }
+        Ok(registered)
+    }
+}
+
+/// Turn away messages for sessions captures can't go into
+///
+/// Sessions start out as state files; one the database has no row for yet
+/// gets its row here, so its captures don't fail on the foreign key later.
+/// The session the daemon tracks is never paused from a client's point of
+/// view, since its next capture resumes it. Reads the database and the
+/// state file, so it runs on the blocking pool.
+fn check_session(
+    lifecycle: &Mutex<SessionLifecycle>,
+    storage: &StorageManager,
+    session_id: &str,
+) -> Option<IpcResponse> {
+    let tracked = lifecycle
+        .lock()
+        .unwrap()
+        .session()
+        .is_some_and(|session| session.id.to_string() == session_id);
+    let paused = || {
+        IpcResponse::error(
+            IpcErrorCode::Paused,
+            format!("Session {} is paused", session_id),
+        )
+    };
+
+    match storage.database.session_status(session_id) {
+        Ok(Some(status)) => return (status == "paused" && !tracked).then(paused),
+        Ok(None) => {}
+        Err(e) => {
+            // Let the pipeline have it rather than lose a capture
+            tracing::warn!("Failed to look up session {}: {}", session_id, e);
+            return None;
+        }
+    }
+
+    let session = Uuid::parse_str(session_id)
+        .ok()
+        .and_then(|id| Session::load(storage.base_path(), &id).ok());
+    let Some(session) = session else {
+        return Some(IpcResponse::error(
+            IpcErrorCode::SessionUnknown,
+            format!("No session {}", session_id),
+        ));
+    };
+    if let Err(e) = storage.database.ensure_session(
+        session_id,
+        &session.name,
+        session.started_at.timestamp(),
+        &format!("{:?}", session.status).to_lowercase(),
+    ) {
+        tracing::warn!("Failed to record session {}: {}", session_id, e);
+    }
+    (session.status == SessionStatus::Paused && !tracked).then(paused)
+}
+
 /// Handle a client connection
-async fn handle_client(
-    mut stream: tokio::net::UnixStream,
-    pipeline: tokio::sync::mpsc::Sender<CaptureEvent>,
-) -> Result<()> {
-    // Read message
-    let message = ipc::read_message(&mut stream).await?;
+async fn handle_client(mut stream: IpcStream, ctx: ClientContext) -> Result<()> {
+    // Identify the peer from its socket credentials
+    let uid = ipc::peer_uid(&stream)?;
+    let username = match ctx.acl.authorize(uid) {
+        Ok(username) => username,
+        Err(reason) => {
+            tracing::warn!("Rejected connection from uid {}: {}", uid, reason);
+            ipc::write_response(
+                &mut stream,
+                &IpcResponse::error(IpcErrorCode::Forbidden, reason),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // Read message, after the handshake if the client opened with one
+    let (message, encoding) = ipc::accept_message(&mut stream).await?;
+
+    if let Err(wait) = ctx.limiter.check(uid, std::time::Instant::now()) {
+        health::record_throttled();
+        tracing::debug!("Rate limited {}: retry in {:?}", username, wait);
+        let response = IpcResponse::busy(
+            IpcErrorCode::RateLimited,
+            "Too many requests: slow down (daemon.rate_limit)",
+            wait,
+        );
+        ipc::write_response_as(&mut stream, &response, encoding).await?;
+        return Ok(());
+    }
+
+    // Captures and intents need a session to go into; dry runs store nothing
+    if let IpcMessage::Capture {
+        session_id,
+        dry_run: false,
+        ..
+    }
+    | IpcMessage::Intent { session_id, .. } = &message
+    {
+        let refused = if ctx.dry_run {
+            None
+        } else {
+            let lifecycle = ctx.lifecycle.clone();
+            let storage = ctx.storage.clone();
+            let session_id = session_id.clone();
+            task::spawn_blocking(move || check_session(&lifecycle, &storage, &session_id))
+                .await
+                .map_err(|e| YinxError::Daemon(format!("Session check failed: {}", e)))?
+        };
+        if let Some(response) = refused {
+            tracing::debug!("Refused message from {}: {:?}", username, response.message);
+            ipc::write_response_as(&mut stream, &response, encoding).await?;
+            return Ok(());
+        }
+    }
+
+    // Big output comes as a file; read it before the hook cleans it up,
+    // and only once the session is known to take it
+    let mut message = message;
+    if let IpcMessage::Capture {
+        output, handoff, ..
+    } = &mut message
+    {
+        if let Some(handoff) = handoff.take() {
+            match task::spawn_blocking(move || handoff.claim(uid)).await {
+                Ok(Ok(contents)) => *output = contents,
+                Ok(Err(e)) => {
+                    tracing::warn!("Rejected handed-off output from {}: {}", username, e);
+                    let response = IpcResponse::error(IpcErrorCode::HandoffRejected, e.to_string());
+                    ipc::write_response_as(&mut stream, &response, encoding).await?;
+                    return Ok(());
+                }
+                Err(e) => return Err(YinxError::Daemon(format!("Handoff task failed: {}", e))),
+            }
+        }
+    }
 
     // Process message
     let response = match message {
+        IpcMessage::Stop if !ctx.acl.is_owner(uid) => {
+            tracing::warn!("User {} tried to stop the shared daemon", username);
+            IpcResponse::error(IpcErrorCode::Forbidden, "Only the daemon owner can stop it")
+        }
+        IpcMessage::Intent { ref command, .. } | IpcMessage::Capture { ref command, .. }
+            if ctx.exclusions.is_excluded(command) =>
+        {
+            tracing::debug!("Skipping excluded command");
+            IpcResponse::skipped("Command excluded by capture.exclude_commands")
+        }
+        IpcMessage::Capture { dry_run, .. } if dry_run || ctx.dry_run => {
+            match Option::<CaptureEvent>::from(message) {
+                Some(event) => dry_run_capture(event, &ctx).await,
+                None => IpcResponse::error(IpcErrorCode::InvalidMessage, "Invalid capture message"),
+            }
+        }
         IpcMessage::Capture { .. } => {
-            if let Some(event) = Option::<CaptureEvent>::from(message) {
-                match pipeline.send(event).await {
+            if let Err(e) = ctx.lifecycle.lock().unwrap().touch() {
+                tracing::warn!("Failed to record session activity: {}", e);
+            }
+            if let Some(mut event) = Option::<CaptureEvent>::from(message) {
+                tracing::debug!("Capture from {}", username);
+                event.username = Some(username);
+                match ctx
+                    .pipeline
+                    .send_timeout(event, health::QUEUE_TIMEOUT)
+                    .await
+                {
                     Ok(_) => IpcResponse::success("Capture queued"),
-                    Err(e) => IpcResponse::error(format!("Failed to queue capture: {}", e)),
+                    Err(mpsc::error::SendTimeoutError::Timeout(_)) => {
+                        health::record_drop();
+                        tracing::warn!("Dropped a capture: the pipeline queue stayed full");
+                        IpcResponse::busy(
+                            IpcErrorCode::QueueFull,
+                            "Capture dropped: the pipeline is backed up",
+                            health::QUEUE_FULL_RETRY,
+                        )
+                    }
+                    Err(e) => IpcResponse::error(
+                        IpcErrorCode::Internal,
+                        format!("Failed to queue capture: {}", e),
+                    ),
                 }
             } else {
-                IpcResponse::error("Invalid capture message")
+                IpcResponse::error(IpcErrorCode::InvalidMessage, "Invalid capture message")
             }
         }
-        IpcMessage::Status => IpcResponse::success("Daemon is running"),
-        IpcMessage::Stop => IpcResponse::success("Shutdown initiated"),
-        IpcMessage::Query { .. } => IpcResponse::error("Query not implemented yet (Phase 8)"),
+        IpcMessage::Intent {
+            ref session_id,
+            ref command,
+            ..
+        } => match &ctx.targets {
+            Some(registrar) => match registrar.register(session_id, command) {
+                Ok(n) => IpcResponse::success(format!("Registered {} target(s)", n)),
+                Err(e) => IpcResponse::error(
+                    IpcErrorCode::Internal,
+                    format!("Failed to register targets: {}", e),
+                ),
+            },
+            None => {
+                IpcResponse::skipped("Target registration disabled by capture.register_targets")
+            }
+        },
+        IpcMessage::Status => {
+            // `yinx status` reads the latency window right after asking
+            if let Err(e) = crate::storage::latency::flush() {
+                tracing::debug!("Failed to save latency samples: {}", e);
+            }
+            IpcResponse::success("Daemon is running").with_data(serde_json::json!({
+                "models": ctx.models.status(),
+                "health": PipelineHealth::current(),
+                "pattern_fallback": ctx.pattern_fallback,
+                "patterns": ctx.patterns.pattern_stats(),
+            }))
+        }
+        IpcMessage::Stop => {
+            ctx.stop.notify_one();
+            IpcResponse::success("Shutdown initiated")
+        }
+        IpcMessage::Query { query, limit } => run_query(&ctx, &username, query, limit).await,
+        IpcMessage::Hello { .. } => {
+            IpcResponse::error(IpcErrorCode::InvalidMessage, "Handshake already done")
+        }
     };
 
     // Write response
-    ipc::write_response(&mut stream, &response).await?;
+    ipc::write_response_as(&mut stream, &response, encoding).await?;
 
     Ok(())
 }
 
+/// Keyword search limited to captures `scope` allows
+///
+/// More hits are fetched than asked for when scoped, since filtering drops
+/// some. Runs on the blocking pool.
+fn scoped_search(
+    storage: &StorageManager,
+    scope: &UserScope,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchHit>> {
+    let fetch = match scope {
+        UserScope::All => limit,
+        UserScope::Users(_) => limit * 4,
+    };
+    // The index sync holds the writer
+    let Some(index) = storage.open_keyword_index_read_only()? else {
+        return Ok(Vec::new());
+    };
+    let mut hits = search_index(storage, &index, query, fetch)?;
+    hits.retain(|hit| scope.allows(hit.username.as_deref()));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+/// Answer a keyword query with the captures `username` may see
+///
+/// A scoped user only gets hits on captures by the users in their
+/// `[acl]` scope.
+async fn run_query(
+    ctx: &ClientContext,
+    username: &str,
+    query: String,
+    limit: usize,
+) -> IpcResponse {
+    let scope = ctx.acl.scope_for(username);
+    tracing::info!("Query from {} scoped to {:?}", username, scope);
+    let limit = limit.clamp(1, remote::MAX_QUERY_LIMIT);
+    let storage = ctx.storage.clone();
+    let hits = task::spawn_blocking(move || scoped_search(&storage, &scope, &query, limit)).await;
+    match hits {
+        Ok(Ok(hits)) => IpcResponse::success(format!("{} result(s)", hits.len()))
+            .with_data(serde_json::json!(hits)),
+        Ok(Err(e)) => IpcResponse::error(IpcErrorCode::Internal, format!("Query failed: {}", e)),
+        Err(e) => IpcResponse::error(IpcErrorCode::Internal, format!("Query failed: {}", e)),
+    }
+}
+
+/// Answer a dry-run capture with what storing it would have kept
+async fn dry_run_capture(event: CaptureEvent, ctx: &ClientContext) -> IpcResponse {
+    let storage = ctx.storage.clone();
+    let patterns = ctx.patterns.clone();
+    let summary = task::spawn_blocking(move || dry_run(&event, &patterns, &storage)).await;
+    match summary {
+        Ok(Ok(summary)) => {
+            tracing::info!(
+                "Dry run of '{}': {} lines -> {} kept, {} entities, {} chunks",
+                summary.command,
+                summary.input_lines,
+                summary.kept_lines,
+                summary.entities.len(),
+                summary.chunks.len()
+            );
+            match serde_json::to_value(&summary) {
+                Ok(data) => IpcResponse::skipped("Dry run: nothing stored").with_data(data),
+                Err(e) => IpcResponse::error(
+                    IpcErrorCode::Internal,
+                    format!("Failed to serialize dry run: {}", e),
+                ),
+            }
+        }
+        Ok(Err(e)) => IpcResponse::error(IpcErrorCode::Internal, format!("Dry run failed: {}", e)),
+        Err(e) => IpcResponse::error(IpcErrorCode::Internal, format!("Dry run failed: {}", e)),
+    }
+}
+
 /// Daemon status
 #[derive(Debug, Clone)]
 pub enum DaemonStatus {
@@ -284,7 +954,7 @@ pub enum DaemonStatus {
 }
 
 /// Expand tilde in path
-fn expand_tilde(path: &Path) -> PathBuf {
+pub(crate) fn expand_tilde(path: &Path) -> PathBuf {
     if path.starts_with("~") {
         if let Some(home) = dirs::home_dir() {
             return home.join(path.strip_prefix("~").unwrap());
@@ -293,6 +963,44 @@ fn expand_tilde(path: &Path) -> PathBuf {
     path.to_path_buf()
 }
 
+/// Exam clock warning thresholds from the config; unparseable entries are skipped
+fn deadline_warnings(config: &Config) -> Vec<Duration> {
+    config
+        .deadline
+        .warn_at
+        .iter()
+        .filter_map(|w| crate::config::parse_duration(w))
+        .collect()
+}
+
+/// Render a warning threshold as e.g. "1h 30m" or "15m"
+fn format_threshold(threshold: Duration) -> String {
+    let minutes = threshold.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+/// Best-effort desktop notification; headless hosts simply have no notify-send
+pub(crate) fn notify_desktop(message: &str) {
+    let _ = std::process::Command::new("notify-send")
+        .args(["yinx", message])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+}
+
+/// How often to check for idle sessions: half the timeout, clamped to 1s..60s
+fn idle_check_interval(idle_timeout: Option<Duration>) -> Duration {
+    idle_timeout
+        .filter(|d| !d.is_zero())
+        .map(|d| d / 2)
+        .unwrap_or(Duration::from_secs(60))
+        .clamp(Duration::from_secs(1), Duration::from_secs(60))
+}
+
 /// Parse flush interval string (e.g., "5s", "100ms")
 fn parse_flush_interval(interval: &str) -> u64 {
     let interval = interval.trim();
@@ -320,6 +1028,26 @@ mod tests {
         assert_eq!(parse_flush_interval("7"), 7);
     }
 
+    #[test]
+    fn test_format_threshold() {
+        assert_eq!(format_threshold(Duration::from_secs(4 * 3600)), "4h");
+        assert_eq!(format_threshold(Duration::from_secs(5400)), "1h 30m");
+        assert_eq!(format_threshold(Duration::from_secs(900)), "15m");
+    }
+
+    #[test]
+    fn test_idle_check_interval() {
+        assert_eq!(idle_check_interval(None), Duration::from_secs(60));
+        assert_eq!(
+            idle_check_interval(Some(Duration::from_secs(30))),
+            Duration::from_secs(15)
+        );
+        assert_eq!(
+            idle_check_interval(Some(Duration::from_millis(100))),
+            Duration::from_secs(1)
+        );
+    }
+
     #[test]
     fn test_expand_tilde() {
         let home = dirs::home_dir().unwrap();
@@ -331,4 +1059,87 @@ mod tests {
         let expanded = expand_tilde(&path);
         assert_eq!(expanded, PathBuf::from("/tmp/yinx"));
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_query_is_scoped_by_acl() {
+        use crate::config::AclConfig;
+        use crate::storage::fixtures::capture;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = temp_dir.path().join("data");
+        let storage = Arc::new(StorageManager::new(data_dir.clone()).unwrap());
+        let me = username_for_uid(current_uid()).unwrap_or_else(|| current_uid().to_string());
+        let mut index = storage.open_keyword_index().unwrap();
+        for user in [me.as_str(), "alice", "bob"] {
+            let stored = capture("s1", "nmap 10.0.0.1", b"22/tcp open ssh\n")
+                .prompt(user, "/tmp")
+                .chunks(&["22/tcp open ssh"])
+                .insert(&storage);
+            index
+                .insert(stored.chunk_ids[0] as u64, "22/tcp open ssh")
+                .unwrap();
+        }
+        index.commit().unwrap();
+        drop(index);
+
+        // Shared, and this user may also see alice's captures
+        let config = Config {
+            acl: AclConfig {
+                shared: true,
+                scopes: [(me.clone(), vec!["alice".to_string()])].into(),
+                ..AclConfig::default()
+            },
+            ..Config::default()
+        };
+        let ctx = ClientContext {
+            pipeline: mpsc::channel(1).0,
+            lifecycle: Arc::new(Mutex::new(SessionLifecycle::new(
+                data_dir,
+                storage.database.clone(),
+                None,
+                None,
+            ))),
+            exclusions: Arc::new(CommandExclusions::new(&[]).unwrap()),
+            acl: Arc::new(Acl::new(&config.acl, current_uid()).unwrap()),
+            models: Arc::new(ModelHub::new(&config)),
+            stop: Arc::new(Notify::new()),
+            targets: None,
+            storage,
+            patterns: Arc::new(PatternRegistry::bundled().unwrap()),
+            pattern_fallback: None,
+            dry_run: false,
+            limiter: Arc::new(ClientLimiter::new(&config.daemon.rate_limit)),
+        };
+
+        let socket_path = temp_dir.path().join("yinx.sock");
+        let mut server = IpcServer::new(socket_path.clone());
+        server.bind().await.unwrap();
+        let handler = tokio::spawn(async move {
+            let stream = server.accept().await.unwrap();
+            handle_client(stream, ctx).await
+        });
+        let response = IpcClient::new(socket_path)
+            .send(&IpcMessage::Query {
+                query: "ssh".to_string(),
+                limit: 10,
+            })
+            .await
+            .unwrap();
+        handler.await.unwrap().unwrap();
+
+        assert!(response.success, "{:?}", response.message);
+        let mut users: Vec<String> = response
+            .data
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|hit| hit["username"].as_str().unwrap().to_string())
+            .collect();
+        users.sort();
+        let mut expected = vec!["alice".to_string(), me];
+        expected.sort();
+        assert_eq!(users, expected);
+    }
 }
@@ -0,0 +1,171 @@
+// Model preloading: load the embedding model and reranker at daemon start
+
+use crate::config::{Config, ModelPreload};
+use crate::embedding::{EmbeddingProvider, FastEmbedProvider};
+use crate::retrieval::{RerankOptions, Reranker};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Text run through each model once loaded, so the first real call
+/// doesn't pay for ONNX Runtime's allocations
+const WARM_UP_TEXT: &str = "22/tcp open ssh OpenSSH 8.2p1 Ubuntu";
+
+/// Readiness of one model in the daemon
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ModelState {
+    /// Loaded on first use (embedding.preload = "lazy")
+    Lazy,
+    Loading,
+    Ready {
+        load_ms: u64,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl ModelState {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Self::Ready { .. })
+    }
+}
+
+impl std::fmt::Display for ModelState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lazy => write!(f, "lazy"),
+            Self::Loading => write!(f, "loading"),
+            Self::Ready { load_ms } => write!(f, "ready ({:.1}s)", *load_ms as f64 / 1000.0),
+            Self::Failed { error } => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/// Model readiness as reported by `Status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelStatus {
+    pub embedding: ModelState,
+    /// None when reranking is disabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reranker: Option<ModelState>,
+}
+
+/// Models held by the daemon and their readiness
+pub struct ModelHub {
+    status: Mutex<ModelStatus>,
+    embedder: Mutex<Option<Arc<dyn EmbeddingProvider>>>,
+    reranker: Mutex<Option<Arc<Reranker>>>,
+}
+
+impl ModelHub {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            status: Mutex::new(ModelStatus {
+                embedding: ModelState::Lazy,
+                reranker: config
+                    .retrieval
+                    .enable_reranking
+                    .then_some(ModelState::Lazy),
+            }),
+            embedder: Mutex::new(None),
+            reranker: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> ModelStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// The loaded embedding model, if warm-up has finished
+    pub fn embedder(&self) -> Option<Arc<dyn EmbeddingProvider>> {
+        self.embedder.lock().unwrap().clone()
+    }
+
+    /// The loaded reranker, if warm-up has finished
+    pub fn reranker(&self) -> Option<Arc<Reranker>> {
+        self.reranker.lock().unwrap().clone()
+    }
+
+    /// Load every configured model and run one warm-up pass through each
+    ///
+    /// Blocking: model files may be downloaded on first run. Does nothing
+    /// under `embedding.preload = "lazy"`.
+    pub fn warm_up(&self, config: &Config) {
+        if config.embedding.preload == ModelPreload::Lazy {
+            return;
+        }
+
+        let model = config.embedding.model.clone();
+        self.status.lock().unwrap().embedding = ModelState::Loading;
+        let state = load("embedding", &model, || {
+            let provider = FastEmbedProvider::new(&model).map_err(|e| e.to_string())?;
+            provider.embed(WARM_UP_TEXT).map_err(|e| e.to_string())?;
+            *self.embedder.lock().unwrap() = Some(Arc::new(provider));
+            Ok(())
+        });
+        self.status.lock().unwrap().embedding = state;
+
+        if config.retrieval.enable_reranking {
+            let model = config.retrieval.reranker_model.clone();
+            self.status.lock().unwrap().reranker = Some(ModelState::Loading);
+            let state = load("reranker", &model, || {
+                let options = RerankOptions::from_config(&config.retrieval);
+                let reranker =
+                    Reranker::with_options(&model, options).map_err(|e| e.to_string())?;
+                reranker
+                    .rerank(WARM_UP_TEXT, &[WARM_UP_TEXT.to_string()], 1)
+                    .map_err(|e| e.to_string())?;
+                *self.reranker.lock().unwrap() = Some(Arc::new(reranker));
+                Ok(())
+            });
+            self.status.lock().unwrap().reranker = Some(state);
+        }
+    }
+}
+
+/// Run one model's loader, logging its progress
+fn load(
+    kind: &str,
+    model: &str,
+    loader: impl FnOnce() -> std::result::Result<(), String>,
+) -> ModelState {
+    tracing::info!("Preloading {} model {}", kind, model);
+    let started = Instant::now();
+    match loader() {
+        Ok(()) => {
+            let load_ms = started.elapsed().as_millis() as u64;
+            tracing::info!("{} model {} ready in {}ms", kind, model, load_ms);
+            ModelState::Ready { load_ms }
+        }
+        Err(error) => {
+            tracing::warn!("Failed to preload {} model {}: {}", kind, model, error);
+            ModelState::Failed { error }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_preload() {
+        let mut config = Config::default();
+        config.retrieval.enable_reranking = false;
+        let hub = ModelHub::new(&config);
+        hub.warm_up(&config);
+
+        let status = hub.status();
+        assert_eq!(status.embedding, ModelState::Lazy);
+        assert!(status.reranker.is_none());
+        assert!(hub.embedder().is_none());
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json, serde_json::json!({"embedding": {"state": "lazy"}}));
+        let ready = ModelState::Ready { load_ms: 1500 };
+        assert!(ready.is_ready());
+        assert_eq!(ready.to_string(), "ready (1.5s)");
+    }
+}
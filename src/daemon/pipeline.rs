@@ -1,20 +1,41 @@
 // Async processing pipeline with bounded channels for backpressure handling
 
+use crate::board;
+use crate::config::HookStage;
+use crate::daemon::emergency::PendingBatch;
 use crate::daemon::ipc::IpcMessage;
-use crate::entities::EntityExtractor;
+use crate::daemon::{health, supervisor, CaptureContext};
+use crate::enrichment::{
+    annotate_pending, resolve_pending, AsnEnricher, DnsEnricher, SystemResolver,
+};
+use crate::entities::{Entity, EntityExtractor};
 use crate::error::Result;
-use crate::filtering::FilterPipeline;
-use crate::patterns::PatternRegistry;
-use crate::storage::StorageManager;
+use crate::filtering::{ChunkPolicy, Cluster, FilterPipeline, FilterStats};
+use crate::hooks::{HookAnnotation, HookRunner};
+use crate::patterns::{
+    host_at, CompiledToolMatcher, DetectionMethod, HostSection, PatternRegistry, ToolArguments,
+};
+use crate::proof::{ProofArtifact, ProofDetector};
+use crate::retrieval::{index_is_behind, sync_keyword_index, RebuildProgress, KEYWORD_INDEX};
+use crate::rules::{RuleAction, RuleContext, RuleEngine, RuleMatch};
+use crate::storage::{latency, EntityPosition, StorageManager};
+use crate::summary::{summarize_pending, Summarizer};
+use crate::triage::Triage;
 use chrono::Utc;
 use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::time;
 
+/// Webhooks run off the storage worker, but shouldn't pile up forever
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Capture event to be processed through the pipeline
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaptureEvent {
     pub session_id: String,
     pub timestamp: i64,
@@ -22,6 +43,15 @@ pub struct CaptureEvent {
     pub output: String,
     pub exit_code: i32,
     pub cwd: String,
+    /// Local user the capture came from (set by the daemon, not the client)
+    pub username: Option<String>,
+    /// Log source for captures that didn't come from a shell (`syslog`, `journald`)
+    pub source: Option<String>,
+    /// Tool to record instead of detecting one from the command line, for
+    /// output added by hand (`yinx add --tool`)
+    pub tool: Option<String>,
+    /// Environment the command ran in (git repo, virtualenv, whitelisted variables)
+    pub context: Option<CaptureContext>,
 }
 
 impl From<IpcMessage> for Option<CaptureEvent> {
@@ -34,6 +64,8 @@ impl From<IpcMessage> for Option<CaptureEvent> {
                 output,
                 exit_code,
                 cwd,
+                context,
+                ..
             } => Some(CaptureEvent {
                 session_id,
                 timestamp,
@@ -41,12 +73,34 @@ impl From<IpcMessage> for Option<CaptureEvent> {
                 output,
                 exit_code,
                 cwd,
+                username: None,
+                source: None,
+                tool: None,
+                context: context.map(|context| *context),
             }),
             _ => None,
         }
     }
 }
 
+/// User-configured steps run on every capture
+#[derive(Default)]
+pub struct PipelineExtensions {
+    pub hooks: HookRunner,
+    pub rules: RuleEngine,
+    pub proofs: ProofDetector,
+    /// Digests large captures in the background
+    pub summarizer: Summarizer,
+    /// Hints for why failed commands failed
+    pub triage: Triage,
+    /// Looks up discovered hosts in DNS in the background
+    pub dns: DnsEnricher,
+    /// Annotates public addresses from a local ASN database
+    pub asn: AsnEnricher,
+    /// Identical re-runs within this window bump a repeat counter (zero disables)
+    pub dedup_window: Duration,
+}
+
 /// Processing pipeline that receives captures and stores them
 pub struct Pipeline {
     /// Channel for receiving capture events
@@ -59,8 +113,16 @@ pub struct Pipeline {
     #[allow(dead_code)] // Used in storage_worker via move before spawn
     batch_size: usize,
     /// Filter pipeline for three-tier filtering
-    #[allow(dead_code)] // Used in storage_worker via clone before spawn
     filter_pipeline: Arc<FilterPipeline>,
+    /// Captures received but not yet stored
+    pending: PendingBatch,
+}
+
+/// Where the storage worker takes captures from
+struct WorkerQueue {
+    capture_rx: mpsc::Receiver<CaptureEvent>,
+    /// Received captures waiting for the next flush
+    pending: PendingBatch,
 }
 
 impl Pipeline {
@@ -68,6 +130,7 @@ impl Pipeline {
     pub fn new(
         storage: Arc<StorageManager>,
         patterns: Arc<PatternRegistry>,
+        extensions: Arc<PipelineExtensions>,
         buffer_size: usize,
         batch_size: usize,
         flush_interval_secs: u64,
@@ -75,16 +138,31 @@ impl Pipeline {
         let (capture_tx, capture_rx) = mpsc::channel(buffer_size);
         let flush_interval = Duration::from_secs(flush_interval_secs);
 
-        // Create filter pipeline
-        let filter_pipeline = Arc::new(FilterPipeline::new(patterns.clone()));
+        // Create filter pipeline (Tier1 state lives next to the session state files)
+        let filter_pipeline = Arc::new(FilterPipeline::with_state_dir(
+            patterns.clone(),
+            storage.sessions_dir(),
+        ));
+        if patterns.tier2_config.auto_threshold.enabled {
+            match storage.database.tier2_volume_means() {
+                Ok(means) => filter_pipeline.seed_volumes(&means),
+                Err(e) => tracing::warn!("Failed to load filter volume history: {}", e),
+            }
+        }
 
         // Spawn storage worker task
         let filter_pipeline_clone = filter_pipeline.clone();
+        let pending = PendingBatch::default();
+        let queue = WorkerQueue {
+            capture_rx,
+            pending: pending.clone(),
+        };
         let storage_handle = Some(tokio::spawn(async move {
             storage_worker(
-                capture_rx,
+                queue,
                 storage,
                 patterns,
+                extensions,
                 filter_pipeline_clone,
                 flush_interval,
                 batch_size,
@@ -98,6 +176,7 @@ impl Pipeline {
             flush_interval,
             batch_size,
             filter_pipeline,
+            pending,
         }
     }
 
@@ -124,6 +203,16 @@ impl Pipeline {
         }
     }
 
+    /// Persist per-session filter state without stopping the pipeline
+    pub fn persist_filter_state(&self) -> Result<usize> {
+        self.filter_pipeline.persist_state()
+    }
+
+    /// Captures received but not yet stored
+    pub(crate) fn pending(&self) -> &PendingBatch {
+        &self.pending
+    }
+
     /// Get the flush interval
     pub fn flush_interval(&self) -> Duration {
         self.flush_interval
@@ -132,9 +221,10 @@ impl Pipeline {
 
 /// Storage worker that receives captures and writes them to storage
 async fn storage_worker(
-    mut capture_rx: mpsc::Receiver<CaptureEvent>,
+    mut queue: WorkerQueue,
     storage: Arc<StorageManager>,
     patterns: Arc<PatternRegistry>,
+    extensions: Arc<PipelineExtensions>,
     filter_pipeline: Arc<FilterPipeline>,
     flush_interval: Duration,
     batch_size: usize,
@@ -142,28 +232,40 @@ async fn storage_worker(
     let mut flush_timer = time::interval(flush_interval);
     flush_timer.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
 
-    let mut pending_captures: Vec<CaptureEvent> = Vec::new();
     let mut stats = WorkerStats::default();
+    let mut summary_task: Option<tokio::task::JoinHandle<()>> = None;
+    let mut enrichment_task: Option<tokio::task::JoinHandle<()>> = None;
+    // Catching up at startup also recovers an update a crash interrupted
+    let mut index_task: Option<tokio::task::JoinHandle<()>> =
+        Some(spawn_index_sync(storage.clone()));
 
     loop {
         tokio::select! {
             // Receive capture event
-            maybe_event = capture_rx.recv() => {
+            maybe_event = queue.capture_rx.recv() => {
                 match maybe_event {
                     Some(event) => {
-                        pending_captures.push(event);
+                        queue.pending.push(event);
 
                         // Flush if batch size threshold reached (from config)
-                        if pending_captures.len() >= batch_size {
-                            flush_batch(&mut pending_captures, &storage, &patterns, &filter_pipeline, &mut stats).await;
+                        if queue.pending.len() >= batch_size {
+                            flush_batch(&queue.pending, &storage, &patterns, &extensions, &filter_pipeline, &mut stats).await;
                         }
                     }
                     None => {
                         // Channel closed, drain remaining
-                        if !pending_captures.is_empty() {
-                            tracing::info!("Draining {} pending captures", pending_captures.len());
-                            flush_batch(&mut pending_captures, &storage, &patterns, &filter_pipeline, &mut stats).await;
+                        if !queue.pending.is_empty() {
+                            tracing::info!("Draining {} pending captures", queue.pending.len());
+                            flush_batch(&queue.pending, &storage, &patterns, &extensions, &filter_pipeline, &mut stats).await;
+                        }
+                        match filter_pipeline.persist_state() {
+                            Ok(n) => tracing::info!("Persisted filter state for {} sessions", n),
+                            Err(e) => tracing::error!("Failed to persist filter state: {}", e),
+                        }
+                        if let Some(task) = index_task.take() {
+                            let _ = task.await;
                         }
+                        let _ = spawn_index_sync(storage.clone()).await;
                         tracing::info!(
                             "Storage worker finished: {} captures processed, {} errors",
                             stats.processed,
@@ -176,19 +278,94 @@ async fn storage_worker(
 
             // Time-based flush
             _ = flush_timer.tick() => {
-                if !pending_captures.is_empty() {
-                    flush_batch(&mut pending_captures, &storage, &patterns, &filter_pipeline, &mut stats).await;
+                if !queue.pending.is_empty() {
+                    flush_batch(&queue.pending, &storage, &patterns, &extensions, &filter_pipeline, &mut stats).await;
+                }
+                // Digests trail the captures; one batch in flight at a time
+                if extensions.summarizer.is_enabled()
+                    && summary_task.as_ref().is_none_or(|task| task.is_finished())
+                {
+                    summary_task = Some(spawn_summaries(storage.clone(), patterns.clone(), extensions.clone()));
+                }
+                if (extensions.dns.is_enabled() || extensions.asn.is_enabled())
+                    && enrichment_task.as_ref().is_none_or(|task| task.is_finished())
+                {
+                    enrichment_task = Some(spawn_enrichment(storage.clone(), extensions.clone()));
+                }
+                // Chunks reach the keyword index once committed to SQLite
+                if index_task.as_ref().is_none_or(|task| task.is_finished()) {
+                    index_task = Some(spawn_index_sync(storage.clone()));
                 }
             }
         }
     }
 }
 
+/// Summarize the next batch of large captures off the async runtime
+fn spawn_summaries(
+    storage: Arc<StorageManager>,
+    patterns: Arc<PatternRegistry>,
+    extensions: Arc<PipelineExtensions>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        match summarize_pending(&storage, &extensions.summarizer, &patterns) {
+            Ok(0) => {}
+            Ok(n) => tracing::debug!("Summarized {} captures", n),
+            Err(e) => tracing::warn!("Failed to summarize captures: {}", e),
+        }
+    })
+}
+
+/// Enrich the next batch of discovered hosts off the async runtime
+fn spawn_enrichment(
+    storage: Arc<StorageManager>,
+    extensions: Arc<PipelineExtensions>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        let now = Utc::now().timestamp();
+        match resolve_pending(&storage, &extensions.dns, &SystemResolver, now) {
+            Ok(0) => {}
+            Ok(n) => tracing::debug!("Looked up {} hosts in DNS", n),
+            Err(e) => tracing::warn!("Failed to look up hosts in DNS: {}", e),
+        }
+        match annotate_pending(&storage, &extensions.asn, now) {
+            Ok(0) => {}
+            Ok(n) => tracing::debug!("Annotated {} addresses with their ASN", n),
+            Err(e) => tracing::warn!("Failed to annotate addresses with their ASN: {}", e),
+        }
+    })
+}
+
+/// Write chunks committed since the keyword index's watermark through to
+/// it, off the async runtime
+fn spawn_index_sync(storage: Arc<StorageManager>) -> tokio::task::JoinHandle<()> {
+    tokio::task::spawn_blocking(move || {
+        // A rebuild holds the index and records the watermark when done
+        let progress = storage.machine_zone().join("keywords.rebuild");
+        if RebuildProgress::read(&progress).is_some() {
+            return;
+        }
+        let result = index_is_behind(&storage.database, KEYWORD_INDEX).and_then(|behind| {
+            if !behind {
+                return Ok(0);
+            }
+            let mut index = storage.open_keyword_index()?;
+            sync_keyword_index(&storage.database, &mut index)
+        });
+        match result {
+            Ok(0) => {}
+            Ok(n) => tracing::debug!("Indexed {} chunks", n),
+            Err(e) => tracing::warn!("Failed to update the keyword index: {}", e),
+        }
+    })
+}
+
 /// Flush a batch of captures to storage
 async fn flush_batch(
-    captures: &mut Vec<CaptureEvent>,
+    captures: &PendingBatch,
     storage: &StorageManager,
     patterns: &PatternRegistry,
+    extensions: &PipelineExtensions,
     filter_pipeline: &FilterPipeline,
     stats: &mut WorkerStats,
 ) {
@@ -197,50 +374,441 @@ async fn flush_batch(
     }
 
     tracing::debug!("Flushing {} captures to storage", captures.len());
+    let started = std::time::Instant::now();
+    let batch_size = captures.len();
+    supervisor::update_pipeline_state(true, |state| state.batch_size = batch_size);
 
-    for capture in captures.drain(..) {
-        if let Err(e) = process_capture(&capture, storage, patterns, filter_pipeline).await {
+    // Each capture stays pending until stored, so a panic can spill it
+    while let Some(capture) = captures.begin() {
+        supervisor::update_pipeline_state(false, |state| {
+            state.session_id = Some(capture.session_id.clone());
+            state.program = capture
+                .command
+                .split_whitespace()
+                .next()
+                .map(str::to_string);
+            state.captured_at = Some(capture.timestamp);
+        });
+        if let Err(e) =
+            process_capture(&capture, storage, patterns, extensions, filter_pipeline).await
+        {
             tracing::error!("Failed to process capture: {}", e);
             stats.errors += 1;
         } else {
             stats.processed += 1;
         }
+        captures.finish();
+    }
+
+    supervisor::update_pipeline_state(true, |state| {
+        state.processed = stats.processed;
+        state.errors = stats.errors;
+        state.batch_size = 0;
+    });
+    health::record_flush(started.elapsed());
+}
+
+/// Record which output sections each cluster's lines came from
+pub(crate) fn tag_sections(clusters: &mut [Cluster], tool: &CompiledToolMatcher, output: &str) {
+    if tool.output_patterns.is_empty() {
+        return;
+    }
+
+    let mut by_line: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for (line, section) in output.lines().zip(tool.section_lines(output)) {
+        if let Some(section) = section {
+            by_line.entry(line).or_default().insert(section);
+        }
+    }
+
+    for cluster in clusters {
+        let sections: BTreeSet<&str> = cluster
+            .members
+            .iter()
+            .filter_map(|line| by_line.get(line.as_str()))
+            .flatten()
+            .copied()
+            .collect();
+        if sections.is_empty() {
+            continue;
+        }
+        if let Some(metadata) = cluster.metadata.as_object_mut() {
+            metadata.insert("sections".to_string(), serde_json::json!(sections));
+        }
+    }
+}
+
+/// Filter a capture's output into the chunks stored for it
+///
+/// With host `sections` each host's clusters are tagged and assembled
+/// against that host's part of the output alone, so chunks never mix hosts
+/// and keep the host in their metadata.
+pub(crate) fn chunk_output(
+    filter_pipeline: &FilterPipeline,
+    policy: &ChunkPolicy,
+    session_id: &str,
+    tool: Option<&str>,
+    matcher: Option<&CompiledToolMatcher>,
+    output: &str,
+    sections: &[HostSection],
+) -> Result<(Vec<Cluster>, FilterStats)> {
+    let (clusters, stats) =
+        filter_pipeline.process_host_sections(session_id, tool, output, sections)?;
+
+    let mut by_host: Vec<(Option<String>, Vec<Cluster>)> = Vec::new();
+    for cluster in clusters {
+        let host = cluster
+            .metadata
+            .get("host")
+            .and_then(|h| h.as_str())
+            .map(str::to_string);
+        match by_host.iter_mut().find(|(h, _)| *h == host) {
+            Some((_, clusters)) => clusters.push(cluster),
+            None => by_host.push((host, vec![cluster])),
+        }
+    }
+
+    let mut chunks = Vec::new();
+    for (host, mut clusters) in by_host {
+        // From the host's first section to its last
+        let mut spans = sections
+            .iter()
+            .filter(|s| s.host == host)
+            .map(|s| s.bytes.clone());
+        let text = match spans.next() {
+            Some(first) => {
+                let end = spans.next_back().map_or(first.end, |last| last.end);
+                &output[first.start..end]
+            }
+            None => output,
+        };
+        if let Some(matcher) = matcher {
+            tag_sections(&mut clusters, matcher, text);
+        }
+        chunks.extend(policy.assemble(clusters, text));
+    }
+    Ok((chunks, stats))
+}
+
+/// The one host a capture not split by host is about: the command's single
+/// target, or else the only address its output names
+pub(crate) fn capture_host(arguments: &ToolArguments, entities: &[Entity]) -> Option<String> {
+    if let Some(host) = arguments.single_host() {
+        return Some(host.to_string());
+    }
+    let mut addresses = entities
+        .iter()
+        .filter(|e| e.entity_type == "ip_address")
+        .map(|e| e.value.as_str());
+    let first = addresses.next()?;
+    addresses.all(|a| a == first).then(|| first.to_string())
+}
+
+/// Record `host` in the metadata of clusters not already about a host
+pub(crate) fn scope_to_host(clusters: &mut [Cluster], host: &str) {
+    for cluster in clusters {
+        if let Some(metadata) = cluster.metadata.as_object_mut() {
+            metadata
+                .entry("host")
+                .or_insert_with(|| serde_json::json!(host));
+        }
+    }
+}
+
+/// Keep what hooks returned; a failure here shouldn't lose the capture
+fn store_annotations(
+    storage: &StorageManager,
+    capture_id: i64,
+    stage: HookStage,
+    annotations: &[HookAnnotation],
+) {
+    for annotation in annotations {
+        let data = serde_json::Value::Object(annotation.data.clone()).to_string();
+        if let Err(e) =
+            storage
+                .database
+                .insert_annotation(capture_id, &annotation.hook, stage.as_str(), &data)
+        {
+            tracing::warn!(
+                "Failed to store annotation from hook '{}': {}",
+                annotation.hook,
+                e
+            );
+        }
+    }
+}
+
+/// Carry out a matched rule's actions; failures are logged, not returned
+fn apply_rule(
+    storage: &StorageManager,
+    capture_id: i64,
+    event: &CaptureEvent,
+    tool: Option<&str>,
+    matched: &RuleMatch,
+) {
+    for action in matched.actions {
+        let result = match action {
+            RuleAction::Tag { tag } => storage.database.add_capture_tag(capture_id, tag),
+            RuleAction::Finding { title, severity } => storage
+                .database
+                .insert_finding(
+                    &event.session_id,
+                    Some(capture_id),
+                    title,
+                    severity.as_str(),
+                    Some(matched.rule),
+                )
+                .map(|id| {
+                    if let Some(id) = id {
+                        tracing::info!("Rule '{}' opened finding {}: {}", matched.rule, id, title);
+                    }
+                }),
+            RuleAction::Notify { message } => {
+                let message = message.clone().unwrap_or_else(|| {
+                    format!("Rule '{}' matched capture {}", matched.rule, capture_id)
+                });
+                tracing::warn!("{}", message);
+                super::notify_desktop(&message);
+                Ok(())
+            }
+            RuleAction::Webhook { url } => {
+                let body = webhook_body(capture_id, event, tool, matched);
+                let url = url.clone();
+                let rule = matched.rule.to_string();
+                tokio::task::spawn_blocking(move || {
+                    let result = ureq::post(&url).timeout(WEBHOOK_TIMEOUT).send_json(body);
+                    if let Err(e) = result {
+                        tracing::warn!("Webhook for rule '{}' failed: {}", rule, e);
+                    }
+                });
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Rule '{}' action failed: {}", matched.rule, e);
+        }
+    }
+}
+
+/// What a webhook action POSTs; values of sensitive entities are left out
+/// the same way they are for hooks, since the URL is usually off-box
+fn webhook_body(
+    capture_id: i64,
+    event: &CaptureEvent,
+    tool: Option<&str>,
+    matched: &RuleMatch,
+) -> serde_json::Value {
+    let entities: Vec<_> = matched
+        .entities
+        .iter()
+        .map(|e| {
+            serde_json::json!({
+                "type": &e.entity_type,
+                "value": (!e.should_redact).then_some(&e.value),
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "rule": matched.rule,
+        "capture_id": capture_id,
+        "session_id": &event.session_id,
+        "timestamp": event.timestamp,
+        "tool": tool,
+        "exit_code": event.exit_code,
+        "entities": entities,
+    })
+}
+
+/// Tag the capture and add the proof to the exam checklist
+fn record_proof(
+    storage: &StorageManager,
+    capture_id: i64,
+    event: &CaptureEvent,
+    proof: &ProofArtifact,
+) {
+    let host = proof.host.as_deref();
+    let result = storage
+        .database
+        .add_capture_tag(capture_id, "proof")
+        .and_then(|_| {
+            storage.database.insert_proof(
+                &event.session_id,
+                capture_id,
+                host,
+                &proof.file,
+                &proof.value,
+            )
+        });
+    match result {
+        Ok(Some(_)) => tracing::info!(
+            "Proof {} found on {} (capture {})",
+            proof.file,
+            host.unwrap_or("unknown host"),
+            capture_id
+        ),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to record proof from capture {}: {}", capture_id, e),
     }
 }
 
 /// Process a single capture: write blob and insert database record
-async fn process_capture(
+///
+/// Returns the new capture's id, or None when it was folded into a repeat.
+pub(crate) async fn process_capture(
     event: &CaptureEvent,
     storage: &StorageManager,
     patterns: &PatternRegistry,
+    extensions: &PipelineExtensions,
     filter_pipeline: &FilterPipeline,
-) -> Result<()> {
+) -> Result<Option<i64>> {
+    // Only slow captures are logged; their command goes through redaction first
+    let _timer = latency::timer("pipeline.capture", || {
+        let redacted = patterns.redact_sensitive(&event.command);
+        let command = redacted.as_deref().unwrap_or(&event.command);
+        format!("command={:?} bytes={}", command, event.output.len())
+    });
+    let hooks = &extensions.hooks;
+
+    // Oversized output keeps its head and tail; the dropped middle is never
+    // extracted, chunked or embedded
+    let truncated = storage
+        .output_limit()
+        .and_then(|limit| limit.apply(&event.output));
+    let (stored, output) = match &truncated {
+        Some(cut) => (Cow::Owned(cut.stored()), Cow::Owned(cut.indexed())),
+        None => (
+            Cow::Borrowed(event.output.as_str()),
+            Cow::Borrowed(event.output.as_str()),
+        ),
+    };
+    let original_size = truncated.as_ref().map(|cut| cut.original_size as i64);
+    if let Some(cut) = &truncated {
+        tracing::info!(
+            "Truncated capture output from {} to {} bytes",
+            cut.original_size,
+            stored.len()
+        );
+    }
+
     // Write output to blob storage
-    let (output_hash, compressed, _is_new) = storage.blob_store.write(event.output.as_bytes())?;
+    let (output_hash, compressed, _is_new) = storage.blob_store.write(stored.as_bytes())?;
 
-    // Detect tool from command using pattern registry
-    let tool = patterns.detect_tool(&event.command).map(|t| t.name.clone());
+    // Detect tool from the command, or the output when the command is a
+    // wrapper, unless the event names it
+    let (tool_matcher, tool, detection) = identify_tool(event, patterns, &output);
+    // Multi-host scans are filed host by host
+    let sections = tool_matcher
+        .map(|t| t.host_sections(&output))
+        .unwrap_or_default();
 
-    // Insert capture record in database
-    let conn = storage.database.get_conn()?;
-    conn.execute(
-        "INSERT INTO captures (session_id, timestamp, command, output_hash, tool, exit_code, cwd)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
+    // Strip secrets from the command line before it is stored
+    let redacted = patterns.redact_sensitive(&event.command);
+    let command = redacted.as_deref().unwrap_or(&event.command);
+    let arguments = tool_arguments(tool_matcher, detection, command);
+
+    if !extensions.dedup_window.is_zero() {
+        let since = event.timestamp - extensions.dedup_window.as_secs() as i64;
+        if let Some(capture_id) = storage.database.coalesce_repeat(
             &event.session_id,
-            event.timestamp,
-            &event.command,
+            command,
             &output_hash,
-            tool.as_deref(),
-            event.exit_code,
-            &event.cwd,
-        ],
-    )?;
-    let capture_id = conn.last_insert_rowid();
+            event.timestamp,
+            since,
+        )? {
+            tracing::debug!("Coalesced repeat of capture {}", capture_id);
+            return Ok(None);
+        }
+    }
+
+    // Insert capture record in database; the write connection is released
+    // before hooks run and the other tables are written
+    let capture_id = {
+        let conn = storage.database.get_conn()?;
+        conn.execute(
+            "INSERT INTO captures (session_id, timestamp, command, output_hash, tool, exit_code, cwd, username, original_size,
+                                   target, ports, wordlist, url, service, tool_detection, tool_confidence)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                &event.session_id,
+                event.timestamp,
+                command,
+                &output_hash,
+                tool.as_deref(),
+                event.exit_code,
+                &event.cwd,
+                &event.username,
+                original_size,
+                &arguments.target,
+                &arguments.ports,
+                &arguments.wordlist,
+                &arguments.url,
+                &arguments.service,
+                detection.map(|(method, _)| method.as_str()),
+                detection.map(|(_, confidence)| confidence),
+            ],
+        )?;
+        conn.last_insert_rowid()
+    };
+    if let Some(source) = &event.source {
+        storage.database.add_capture_tag(capture_id, source)?;
+    }
+    if !sections.is_empty() {
+        storage.database.set_subcaptures(capture_id, &sections)?;
+    }
+    let context = event.context.clone().map(|mut context| {
+        context.redact(patterns);
+        context
+    });
+    if let Some(context) = &context {
+        storage
+            .database
+            .insert_capture_context(capture_id, context)?;
+    }
+
+    if redacted.is_some() {
+        match storage.vault() {
+            Some(vault) => {
+                let sealed = vault.seal(event.command.as_bytes())?;
+                storage
+                    .database
+                    .insert_command_original(capture_id, &sealed)?;
+            }
+            None => tracing::debug!("Redacted command line of capture {}", capture_id),
+        }
+    }
+
+    let mut hook_payload = serde_json::json!({
+        "capture_id": capture_id,
+        "session_id": &event.session_id,
+        "timestamp": event.timestamp,
+        "command": command,
+        "tool": &tool,
+        "detection": detection.map(|(method, confidence)| serde_json::json!({
+            "method": method,
+            "confidence": confidence,
+        })),
+        "arguments": &arguments,
+        "context": &context,
+        "exit_code": event.exit_code,
+        "cwd": &event.cwd,
+        "username": &event.username,
+        "output": &output,
+    });
+    if hooks.has_stage(HookStage::PostCapture) {
+        let annotations = hooks
+            .run(HookStage::PostCapture, hook_payload.clone())
+            .await;
+        store_annotations(storage, capture_id, HookStage::PostCapture, &annotations);
+    }
 
     // Extract entities from output using PatternRegistry
     let extractor = EntityExtractor::new(patterns.clone());
-    let entities = extractor.extract(&event.output);
+    let entities = extractor.extract_for_tool(&output, tool_matcher);
+    // Output not split by host is all about the one host it names, if any
+    let host = sections
+        .is_empty()
+        .then(|| capture_host(&arguments, &entities))
+        .flatten();
 
     // Insert entities into database
     if !entities.is_empty() {
@@ -259,18 +827,146 @@ async fn process_capture(
         let entity_count = storage
             .database
             .insert_entities(capture_id, &entity_records)?;
+        let positions: Vec<EntityPosition> = entities
+            .iter()
+            .map(|e| {
+                EntityPosition::in_text(&output, &e.entity_type, &e.value, e.start, e.end)
+                    .with_host(host_at(&sections, e.start).or(host.as_deref()))
+            })
+            .collect();
+        storage
+            .database
+            .set_entity_positions(capture_id, &positions)?;
 
         tracing::debug!(
             "Extracted {} entities from capture {} (types: {})",
             entity_count,
             capture_id,
-            extractor.get_entity_types(&event.output).join(", ")
+            entities
+                .iter()
+                .map(|e| e.entity_type.as_str())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+                .join(", ")
         );
     }
 
+    if hooks.has_stage(HookStage::PostExtraction) {
+        hook_payload["entities"] = entities
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "type": &e.entity_type,
+                    "value": (!e.should_redact).then_some(&e.value),
+                    "confidence": e.confidence,
+                })
+            })
+            .collect();
+        let annotations = hooks
+            .run(HookStage::PostExtraction, hook_payload.clone())
+            .await;
+        store_annotations(storage, capture_id, HookStage::PostExtraction, &annotations);
+    }
+
+    if !extensions.rules.is_empty() {
+        let matches = extensions.rules.evaluate(&RuleContext {
+            tool: tool.as_deref(),
+            command,
+            output: &output,
+            exit_code: event.exit_code,
+            entities: &entities,
+        });
+        for matched in &matches {
+            apply_rule(storage, capture_id, event, tool.as_deref(), matched);
+        }
+    }
+
+    if let Some(mut hint) = extensions.triage.diagnose(&output, event.exit_code) {
+        if hooks.has_stage(HookStage::Triage) {
+            hook_payload["triage"] = serde_json::json!(&hint);
+            let annotations = hooks.run(HookStage::Triage, hook_payload).await;
+            store_annotations(storage, capture_id, HookStage::Triage, &annotations);
+            hint.explanation = annotations.iter().find_map(|a| {
+                a.data
+                    .get("explanation")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            });
+        }
+        match storage.store_triage_hint(capture_id, &hint) {
+            Ok(()) => tracing::debug!("Capture {} failed: {}", capture_id, hint.rule),
+            Err(e) => tracing::warn!(
+                "Failed to record triage hint for capture {}: {}",
+                capture_id,
+                e
+            ),
+        }
+    }
+
+    let proof = extensions
+        .proofs
+        .detect(command, &output, event.exit_code, &entities)
+        .map(|mut proof| {
+            // Proofs are usually read in a shell on the target, after it was
+            // last named; fall back to that when the capture shows no address
+            if proof.host.is_none() {
+                proof.host = storage
+                    .database
+                    .last_session_host(&event.session_id, capture_id)
+                    .ok()
+                    .flatten();
+            }
+            proof
+        });
+    if let Some(proof) = &proof {
+        record_proof(storage, capture_id, event, proof);
+    }
+
+    // Move the hosts this capture involves along the status board
+    let targets = board::CommandTargets::parse(command, &extractor);
+    let mut stages = board::observe(&targets.hosts, &entities, proof.as_ref());
+    for (range, stage) in targets.intent() {
+        stages.entry(range).or_insert(stage);
+    }
+    for (host, stage) in stages {
+        match storage
+            .database
+            .advance_host_stage(&event.session_id, &host, stage)
+        {
+            Ok(true) => tracing::debug!("Host {} is now {}", host, stage.as_str()),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to update board for {}: {}", host, e),
+        }
+    }
+
     // Run output through filtering pipeline
-    let (clusters, filter_stats) =
-        filter_pipeline.process_capture(&event.session_id, &event.output)?;
+    let (mut clusters, filter_stats) = chunk_output(
+        filter_pipeline,
+        storage.chunk_policy(),
+        &event.session_id,
+        tool.as_deref(),
+        tool_matcher,
+        &output,
+        &sections,
+    )?;
+    if let Some(host) = &host {
+        scope_to_host(&mut clusters, host);
+    }
+
+    // Analytics only; a failure here shouldn't lose the capture
+    if let Err(e) = storage.database.insert_filter_stats(
+        capture_id,
+        &event.session_id,
+        tool.as_deref(),
+        &filter_stats,
+    ) {
+        tracing::warn!(
+            "Failed to record filter stats for capture {}: {}",
+            capture_id,
+            e
+        );
+    }
 
     tracing::debug!(
         "Filtered capture {}: {} lines → {} clusters ({:.1}% reduction) in {}ms",
@@ -285,6 +981,26 @@ async fn process_capture(
         filter_stats.processing_time_ms
     );
 
+    if truncated.is_some() {
+        for cluster in &mut clusters {
+            if let Some(metadata) = cluster.metadata.as_object_mut() {
+                metadata.insert("truncated".to_string(), serde_json::json!(true));
+            }
+        }
+    }
+
+    // Insert/update blob metadata (chunks reference it)
+    let blob_size = stored.len() as i64;
+    let now = Utc::now().timestamp();
+
+    let conn = storage.database.get_conn()?;
+    conn.execute(
+        "INSERT INTO blobs (hash, size, created_at, compressed, ref_count)
+         VALUES (?1, ?2, ?3, ?4, 1)
+         ON CONFLICT(hash) DO UPDATE SET ref_count = ref_count + 1",
+        params![&output_hash, blob_size, now, compressed],
+    )?;
+
     // Insert chunks for each cluster
     for cluster in clusters {
         let metadata_json =
@@ -303,17 +1019,6 @@ async fn process_capture(
         )?;
     }
 
-    // Insert/update blob metadata
-    let blob_size = event.output.len() as i64;
-    let now = Utc::now().timestamp();
-
-    conn.execute(
-        "INSERT INTO blobs (hash, size, created_at, compressed, ref_count)
-         VALUES (?1, ?2, ?3, ?4, 1)
-         ON CONFLICT(hash) DO UPDATE SET ref_count = ref_count + 1",
-        params![&output_hash, blob_size, now, compressed],
-    )?;
-
     // Update session capture count
     conn.execute(
         "UPDATE sessions SET capture_count = capture_count + 1 WHERE id = ?1",
@@ -329,7 +1034,182 @@ async fn process_capture(
         entities.len()
     );
 
-    Ok(())
+    Ok(Some(capture_id))
+}
+
+/// What storing a capture would keep, from [`dry_run`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunSummary {
+    /// Command line as it would be stored
+    pub command: String,
+    /// Whether secrets were stripped from the command line
+    pub command_redacted: bool,
+    pub tool: Option<String>,
+    /// How the tool was identified, and how sure the match is
+    pub detection: Option<DetectionMethod>,
+    pub detection_confidence: Option<f32>,
+    /// Output bytes received, and how many would be stored after truncation
+    pub output_bytes: usize,
+    pub stored_bytes: usize,
+    pub input_lines: usize,
+    /// Lines left after Tier 1 deduplication and Tier 2 scoring
+    pub kept_lines: usize,
+    /// Hosts the chunks would be filed under: each host of a multi-host
+    /// scan in output order, or the one host the capture is about
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub entities: Vec<DryRunEntity>,
+    /// Representative text of each chunk that would be indexed
+    pub chunks: Vec<String>,
+}
+
+/// An entity a dry run extracted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunEntity {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    /// None for sensitive values, which reports redact
+    pub value: Option<String>,
+    pub confidence: f32,
+    /// Host whose part of the output it came from
+    #[serde(default)]
+    pub host: Option<String>,
+}
+
+/// The capture's tool: its matcher, its name, and how it was identified
+///
+/// An event naming its tool keeps that name even when the registry has no
+/// such tool.
+fn identify_tool<'a>(
+    event: &CaptureEvent,
+    patterns: &'a PatternRegistry,
+    output: &str,
+) -> (
+    Option<&'a CompiledToolMatcher>,
+    Option<String>,
+    Option<(DetectionMethod, f32)>,
+) {
+    if let Some(name) = &event.tool {
+        return (
+            patterns.tool(name),
+            Some(name.clone()),
+            Some((DetectionMethod::Named, 1.0)),
+        );
+    }
+    match patterns.identify_tool(&event.command, output) {
+        Some(detection) => {
+            if detection.method == DetectionMethod::Output {
+                tracing::debug!(
+                    "Identified {} from its output ({:.0}% of its patterns)",
+                    detection.tool.name,
+                    detection.confidence * 100.0
+                );
+            }
+            (
+                Some(detection.tool),
+                Some(detection.tool.name.clone()),
+                Some((detection.method, detection.confidence)),
+            )
+        }
+        None => (None, None, None),
+    }
+}
+
+/// Arguments of the capture's command line for its tool
+///
+/// Only a command line the tool was detected from, or an alias of it,
+/// takes the tool's arguments.
+fn tool_arguments(
+    tool: Option<&CompiledToolMatcher>,
+    detection: Option<(DetectionMethod, f32)>,
+    command: &str,
+) -> ToolArguments {
+    tool.filter(|_| {
+        matches!(
+            detection,
+            Some((DetectionMethod::Command | DetectionMethod::Alias, _))
+        )
+    })
+    .map(|t| t.parse_arguments(command))
+    .unwrap_or_default()
+}
+
+/// Run a capture through truncation, redaction, extraction and filtering
+/// without writing anything
+///
+/// Filtering starts from empty Tier 1 state rather than the session's, so
+/// lines repeated from earlier captures are kept here where the daemon may
+/// drop them.
+pub fn dry_run(
+    event: &CaptureEvent,
+    patterns: &Arc<PatternRegistry>,
+    storage: &StorageManager,
+) -> Result<DryRunSummary> {
+    let truncated = storage
+        .output_limit()
+        .and_then(|limit| limit.apply(&event.output));
+    let (stored_bytes, output) = match &truncated {
+        Some(cut) => (cut.stored().len(), Cow::Owned(cut.indexed())),
+        None => (event.output.len(), Cow::Borrowed(event.output.as_str())),
+    };
+
+    let (tool_matcher, tool, detection) = identify_tool(event, patterns, &output);
+    let sections = tool_matcher
+        .map(|t| t.host_sections(&output))
+        .unwrap_or_default();
+    let redacted = patterns.redact_sensitive(&event.command);
+    let arguments = tool_arguments(
+        tool_matcher,
+        detection,
+        redacted.as_deref().unwrap_or(&event.command),
+    );
+
+    let extractor = EntityExtractor::new((**patterns).clone());
+    let extracted = extractor.extract_for_tool(&output, tool_matcher);
+    let host = sections
+        .is_empty()
+        .then(|| capture_host(&arguments, &extracted))
+        .flatten();
+    let entities = extracted
+        .into_iter()
+        .map(|e| DryRunEntity {
+            host: host_at(&sections, e.start)
+                .or(host.as_deref())
+                .map(str::to_string),
+            value: (!e.should_redact).then_some(e.value),
+            entity_type: e.entity_type,
+            confidence: e.confidence,
+        })
+        .collect();
+
+    let filter_pipeline = FilterPipeline::new(patterns.clone());
+    let (clusters, filter_stats) = chunk_output(
+        &filter_pipeline,
+        storage.chunk_policy(),
+        &event.session_id,
+        tool.as_deref(),
+        tool_matcher,
+        &output,
+        &sections,
+    )?;
+
+    Ok(DryRunSummary {
+        command_redacted: redacted.is_some(),
+        command: redacted.unwrap_or_else(|| event.command.clone()),
+        tool,
+        detection: detection.map(|(method, _)| method),
+        detection_confidence: detection.map(|(_, confidence)| confidence),
+        output_bytes: event.output.len(),
+        stored_bytes,
+        input_lines: filter_stats.input_lines,
+        kept_lines: filter_stats.tier2_output,
+        hosts: match host {
+            Some(host) => vec![host],
+            None => sections.iter().filter_map(|s| s.host.clone()).collect(),
+        },
+        entities,
+        chunks: clusters.into_iter().map(|c| c.representative).collect(),
+    })
 }
 
 /// Statistics for the storage worker
@@ -364,6 +1244,7 @@ mod tests {
                 score_threshold_percentile: 0.8,
                 technical_patterns: vec![],
                 max_technical_score: 10.0,
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -372,6 +1253,7 @@ mod tests {
                 cluster_patterns: vec![],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         Arc::new(
@@ -379,6 +1261,43 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_tag_sections() {
+        let tool = CompiledToolMatcher {
+            name: "nmap".to_string(),
+            command_patterns: vec![],
+            entity_hints: vec![],
+            hints_only_above: None,
+            arguments: vec![],
+            value_flags: vec![],
+            host_pattern: None,
+            output_patterns: vec![(
+                regex::Regex::new(r"^PORT\s+STATE").unwrap(),
+                "ports".to_string(),
+            )],
+        };
+        let cluster = |line: &str| Cluster {
+            pattern: line.to_string(),
+            representative: line.to_string(),
+            members: vec![line.to_string()],
+            size: 1,
+            metadata: serde_json::json!({}),
+        };
+        let mut clusters = vec![cluster("Starting Nmap"), cluster("22/tcp open ssh")];
+
+        tag_sections(
+            &mut clusters,
+            &tool,
+            "Starting Nmap\nPORT   STATE SERVICE\n22/tcp open ssh\n",
+        );
+
+        assert!(clusters[0].metadata.get("sections").is_none());
+        assert_eq!(
+            clusters[1].metadata["sections"],
+            serde_json::json!(["ports"])
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_pipeline_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -386,7 +1305,7 @@ mod tests {
         let patterns = create_test_patterns();
 
         // Use shorter interval for testing (1 second instead of 5)
-        let pipeline = Pipeline::new(storage, patterns, 1000, 100, 1);
+        let pipeline = Pipeline::new(storage, patterns, Arc::default(), 1000, 100, 1);
         assert_eq!(pipeline.flush_interval(), Duration::from_secs(1));
 
         // Clean shutdown
@@ -400,16 +1319,19 @@ mod tests {
         let patterns = create_test_patterns();
 
         // Create test session first
-        let conn = storage.database.get_conn().unwrap();
-        conn.execute(
-            "INSERT INTO sessions (id, name, started_at, status, capture_count, blob_count)
+        storage
+            .database
+            .get_conn()
+            .unwrap()
+            .execute(
+                "INSERT INTO sessions (id, name, started_at, status, capture_count, blob_count)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params!["test-session", "Test", 1000000, "active", 0, 0],
-        )
-        .unwrap();
+                params!["test-session", "Test", 1000000, "active", 0, 0],
+            )
+            .unwrap();
 
         // Use shorter flush interval for testing (100ms)
-        let pipeline = Pipeline::new(storage.clone(), patterns, 1000, 100, 1);
+        let pipeline = Pipeline::new(storage.clone(), patterns, Arc::default(), 1000, 100, 1);
 
         // Send a capture
         let event = CaptureEvent {
@@ -419,6 +1341,10 @@ mod tests {
             output: "Nmap scan report...".to_string(),
             exit_code: 0,
             cwd: "/tmp".to_string(),
+            username: Some("alice".to_string()),
+            source: None,
+            tool: None,
+            context: None,
         };
 
         pipeline.send(event).await.unwrap();
@@ -430,9 +1356,242 @@ mod tests {
         pipeline.shutdown().await;
 
         // Verify capture was stored
+        let conn = storage.database.read_conn().unwrap();
         let count: i64 = conn
             .query_row("SELECT COUNT(*) FROM captures", [], |row| row.get(0))
             .unwrap();
         assert_eq!(count, 1);
+
+        let username: Option<String> = conn
+            .query_row("SELECT username FROM captures", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(username.as_deref(), Some("alice"));
+
+        let filtered: i64 = conn
+            .query_row("SELECT COUNT(*) FROM filter_stats", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(filtered, 1);
+    }
+
+    #[tokio::test]
+    async fn test_process_capture_splits_hosts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "hosts", 0, "active")
+            .unwrap();
+        let patterns = Arc::new(PatternRegistry::bundled().unwrap());
+        let filter_pipeline = FilterPipeline::new(patterns.clone());
+
+        let event = CaptureEvent {
+            session_id: "s1".to_string(),
+            timestamp: 1_700_000_000,
+            command: "nmap -sV 10.0.0.0/24".to_string(),
+            output: "Starting Nmap 7.94\n\
+                     Nmap scan report for 10.0.0.5\n\
+                     PORT   STATE SERVICE VERSION\n\
+                     22/tcp open  ssh     OpenSSH 8.2p1\n\
+                     Nmap scan report for web.lab (10.0.0.6)\n\
+                     PORT   STATE SERVICE VERSION\n\
+                     22/tcp open  ssh     OpenSSH 8.2p1\n\
+                     80/tcp open  http    Apache httpd 2.4.41\n"
+                .to_string(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        };
+        let capture_id = process_capture(
+            &event,
+            &storage,
+            &patterns,
+            &PipelineExtensions::default(),
+            &filter_pipeline,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let sections = storage.database.get_subcaptures(capture_id).unwrap();
+        let hosts: Vec<_> = sections.iter().map(|s| s.host.as_deref()).collect();
+        assert_eq!(hosts, [None, Some("10.0.0.5"), Some("10.0.0.6")]);
+        assert_eq!(sections[2].lines, 4..8);
+
+        let entities = storage
+            .database
+            .get_entities_for_capture(capture_id)
+            .unwrap();
+        let host_of = |value: &str| {
+            entities
+                .iter()
+                .find(|e| e.value == value)
+                .and_then(|e| e.host.as_deref())
+        };
+        assert_eq!(host_of("80/tcp"), Some("10.0.0.6"));
+        assert_eq!(host_of("web.lab"), Some("10.0.0.6"));
+        assert_eq!(host_of("22/tcp"), Some("10.0.0.5"));
+
+        // Each chunk is about one host
+        let chunks = storage.database.get_capture_chunks(capture_id).unwrap();
+        let chunk_hosts: BTreeSet<_> = chunks.iter().filter_map(|c| c.host()).collect();
+        assert_eq!(
+            chunk_hosts.iter().map(String::as_str).collect::<Vec<_>>(),
+            ["10.0.0.5", "10.0.0.6"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_capture_scopes_single_host() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "hosts", 0, "active")
+            .unwrap();
+        let patterns = Arc::new(PatternRegistry::bundled().unwrap());
+        let filter_pipeline = FilterPipeline::new(patterns.clone());
+
+        let event = CaptureEvent {
+            session_id: "s1".to_string(),
+            timestamp: 1_700_000_000,
+            command: "gobuster dir -u http://10.0.0.7/ -w words.txt".to_string(),
+            output: "/admin (Status: 301)\n/backup (Status: 200)\n".to_string(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        };
+        let capture_id = process_capture(
+            &event,
+            &storage,
+            &patterns,
+            &PipelineExtensions::default(),
+            &filter_pipeline,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // The output never names the host; the command's target does
+        let chunks = storage.database.get_capture_chunks(capture_id).unwrap();
+        assert!(!chunks.is_empty());
+        assert!(chunks
+            .iter()
+            .all(|c| c.host().as_deref() == Some("10.0.0.7")));
+
+        // A range isn't one host
+        let arguments = ToolArguments {
+            target: Some("10.0.0.0/24".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(capture_host(&arguments, &[]), None);
+    }
+
+    #[test]
+    fn test_dry_run_summarizes_capture() {
+        let patterns = Arc::new(PatternRegistry::bundled().unwrap());
+        let event = CaptureEvent {
+            session_id: "s1".to_string(),
+            timestamp: 1_700_000_000,
+            command: "sshpass -p hunter2 nmap -sV 10.0.0.5".to_string(),
+            output: "Nmap scan report for 10.0.0.5\n22/tcp open ssh OpenSSH 8.2p1\n\
+                     80/tcp open http Apache httpd 2.4.41\n"
+                .to_string(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        };
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let summary = dry_run(&event, &patterns, &storage).unwrap();
+        assert!(summary.command_redacted);
+        assert!(!summary.command.contains("hunter2"));
+        assert_eq!(summary.input_lines, 3);
+        assert_eq!(summary.stored_bytes, event.output.len());
+        assert!(summary
+            .entities
+            .iter()
+            .any(|e| e.value.as_deref() == Some("10.0.0.5")));
+        assert!(!summary.chunks.is_empty());
+        assert_eq!(summary.detection, Some(DetectionMethod::Command));
+        assert_eq!(summary.hosts, ["10.0.0.5"]);
+
+        // Run through a wrapper, nmap is recognized by its output
+        let wrapped = CaptureEvent {
+            command: "./recon.sh 10.0.0.5".to_string(),
+            output: format!("PORT   STATE SERVICE\n{}", event.output),
+            ..event
+        };
+        let summary = dry_run(&wrapped, &patterns, &storage).unwrap();
+        assert_eq!(summary.tool.as_deref(), Some("nmap"));
+        assert_eq!(summary.detection, Some(DetectionMethod::Output));
+        assert!(summary.detection_confidence.unwrap() < 1.0);
+    }
+
+    #[test]
+    fn test_webhook_body_leaves_out_sensitive_values() {
+        let engine = RuleEngine::from_config(
+            toml::from_str(
+                r#"
+[[rule]]
+name = "creds"
+[rule.when]
+entity_type = "credential_*"
+[[rule.then]]
+action = "webhook"
+url = "https://hooks.example/yinx"
+"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let entity = |entity_type: &str, value: &str, should_redact: bool| Entity {
+            entity_type: entity_type.to_string(),
+            value: value.to_string(),
+            context: String::new(),
+            start: 0,
+            end: 0,
+            confidence: 0.9,
+            should_redact,
+        };
+        let entities = vec![
+            entity("credential_password", "Winter2025!", true),
+            entity("credential_username", "admin", false),
+        ];
+        let ctx = RuleContext {
+            tool: Some("crackmapexec"),
+            command: "cme smb 10.0.0.1",
+            output: "",
+            exit_code: 0,
+            entities: &entities,
+        };
+        let matches = engine.evaluate(&ctx);
+        let event = CaptureEvent {
+            session_id: "s1".to_string(),
+            timestamp: 0,
+            command: ctx.command.to_string(),
+            output: String::new(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        };
+
+        let body = webhook_body(7, &event, ctx.tool, &matches[0]);
+        assert!(!body.to_string().contains("Winter2025!"));
+        assert_eq!(body["entities"][0]["type"], "credential_password");
+        assert!(body["entities"][0]["value"].is_null());
+        assert_eq!(body["entities"][1]["value"], "admin");
     }
 }
@@ -1,11 +1,15 @@
 // Process management for daemon: PID files, lock files, and process checks
 
 use crate::error::{Result, YinxError};
+#[cfg(unix)]
 use nix::sys::signal::{kill, Signal};
+#[cfg(unix)]
 use nix::unistd::Pid;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use std::process::{Command, Stdio};
 
 /// Manages PID and lock files for the daemon process
 #[derive(Clone)]
@@ -26,11 +30,9 @@ impl ProcessManager {
 
     /// Check if the daemon is currently running
     pub fn is_running(&self) -> bool {
-        if let Ok(pid) = self.read_pid() {
-            // Check if process exists by sending signal 0
-            kill(Pid::from_raw(pid), None).is_ok()
-        } else {
-            false
+        match self.read_pid() {
+            Ok(pid) => process_exists(pid),
+            Err(_) => false,
         }
     }
 
@@ -152,6 +154,7 @@ impl ProcessManager {
     }
 
     /// Send signal to daemon process
+    #[cfg(unix)]
     pub fn signal(&self, sig: Signal) -> Result<()> {
         let pid = self.read_pid()?;
         kill(Pid::from_raw(pid), sig)
@@ -159,6 +162,33 @@ impl ProcessManager {
         Ok(())
     }
 
+    /// Ask the daemon to shut down (SIGTERM on Unix)
+    #[cfg(unix)]
+    pub fn terminate(&self) -> Result<()> {
+        self.signal(Signal::SIGTERM)
+    }
+
+    /// Kill the daemon outright (SIGKILL on Unix)
+    #[cfg(unix)]
+    pub fn force_kill(&self) -> Result<()> {
+        self.signal(Signal::SIGKILL)
+    }
+
+    /// Ask the daemon to shut down
+    ///
+    /// A detached process has no console to receive Ctrl+Break, so this is
+    /// taskkill's close request; `yinx stop` asks over IPC first.
+    #[cfg(windows)]
+    pub fn terminate(&self) -> Result<()> {
+        taskkill(self.read_pid()?, false)
+    }
+
+    /// Kill the daemon outright
+    #[cfg(windows)]
+    pub fn force_kill(&self) -> Result<()> {
+        taskkill(self.read_pid()?, true)
+    }
+
     /// Get the PID file path
     pub fn pid_file(&self) -> &Path {
         &self.pid_file
@@ -170,6 +200,79 @@ impl ProcessManager {
     }
 }
 
+/// Whether a process with this PID exists
+#[cfg(unix)]
+fn process_exists(pid: i32) -> bool {
+    // Signal 0 only checks that the process can be signalled
+    kill(Pid::from_raw(pid), None).is_ok()
+}
+
+/// Whether a process with this PID exists
+#[cfg(windows)]
+fn process_exists(pid: i32) -> bool {
+    Command::new("tasklist")
+        .args(["/NH", "/FO", "CSV", "/FI"])
+        .arg(format!("PID eq {}", pid))
+        .stderr(Stdio::null())
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).contains(&format!("\"{}\"", pid)))
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn taskkill(pid: i32, force: bool) -> Result<()> {
+    let mut command = Command::new("taskkill");
+    if force {
+        command.arg("/F");
+    }
+    let status = command
+        .arg("/PID")
+        .arg(pid.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to run taskkill".to_string(),
+        })?;
+    if !status.success() {
+        return Err(YinxError::Daemon(format!(
+            "Failed to terminate process {}",
+            pid
+        )));
+    }
+    Ok(())
+}
+
+/// Start this executable again as a background process with no console
+///
+/// Windows has no fork, so the daemon is a fresh `yinx` process running
+/// `args`; its output goes to `stdout` and `stderr`. Returns the new PID.
+#[cfg(windows)]
+pub fn spawn_detached(args: &[std::ffi::OsString], stdout: File, stderr: File) -> Result<u32> {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let exe = std::env::current_exe().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to locate the yinx executable".to_string(),
+    })?;
+    let child = Command::new(exe)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr)
+        .creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW)
+        .spawn()
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to start the background daemon".to_string(),
+        })?;
+    Ok(child.id())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
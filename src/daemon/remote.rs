@@ -0,0 +1,480 @@
+// Remote API: newline-delimited JSON-RPC 2.0 over TCP for captures from implants and jump hosts
+
+use super::acl::Acl;
+use super::context::CaptureContext;
+use super::exclusions::CommandExclusions;
+use super::health;
+use super::lifecycle::SessionLifecycle;
+use super::pipeline::CaptureEvent;
+use crate::error::{Result, YinxError};
+use crate::storage::StorageManager;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// Longest request line accepted (captures included)
+const MAX_LINE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Cap on results per query call
+pub(crate) const MAX_QUERY_LIMIT: usize = 200;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const INTERNAL_ERROR: i32 = -32603;
+const UNAUTHORIZED: i32 = -32001;
+
+/// TCP listener for the remote API
+pub struct RemoteServer {
+    listener: TcpListener,
+}
+
+impl RemoteServer {
+    /// Bind to `addr` (host:port)
+    pub async fn bind(addr: &str) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await.map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to bind remote API to {}", addr),
+        })?;
+        tracing::info!("Remote API listening on {}", addr);
+        Ok(Self { listener })
+    }
+
+    /// Address actually bound (useful with port 0)
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to read remote API address".to_string(),
+        })
+    }
+
+    /// Accept the next connection
+    pub async fn accept(&self) -> Result<(TcpStream, SocketAddr)> {
+        self.listener.accept().await.map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to accept remote connection".to_string(),
+        })
+    }
+}
+
+/// Shared state handed to each remote connection
+#[derive(Clone)]
+pub(super) struct RemoteContext {
+    pub pipeline: mpsc::Sender<CaptureEvent>,
+    pub lifecycle: Arc<Mutex<SessionLifecycle>>,
+    pub exclusions: Arc<CommandExclusions>,
+    pub storage: Arc<StorageManager>,
+    pub token: Arc<str>,
+    /// User the token queries as (`remote.user`)
+    pub user: Arc<str>,
+    pub acl: Arc<Acl>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthParams {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct CaptureParams {
+    /// Defaults to the session the daemon is capturing into
+    session_id: Option<String>,
+    timestamp: Option<i64>,
+    command: String,
+    #[serde(default)]
+    output: String,
+    #[serde(default)]
+    exit_code: i32,
+    #[serde(default)]
+    cwd: String,
+    /// Remote user; recorded as `user@peer`
+    user: Option<String>,
+    #[serde(default)]
+    context: Option<CaptureContext>,
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    query: String,
+    #[serde(default = "default_query_limit")]
+    limit: usize,
+}
+
+fn default_query_limit() -> usize {
+    10
+}
+
+/// Compare tokens without stopping at the first differing byte
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// Serve one connection until the peer hangs up
+///
+/// Every request is one JSON object per line. The first call must be `auth`;
+/// a failed `auth` closes the connection. Requests without an `id` are
+/// notifications and get no reply, which lets a client stream captures.
+pub(super) async fn handle_remote(
+    stream: TcpStream,
+    peer: SocketAddr,
+    ctx: RemoteContext,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut authenticated = false;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        let read = (&mut reader)
+            .take(MAX_LINE_SIZE + 1)
+            .read_until(b'\n', &mut line)
+            .await
+            .map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to read from {}", peer),
+            })?;
+        if read == 0 {
+            return Ok(());
+        }
+        if line.len() as u64 > MAX_LINE_SIZE {
+            let error = RpcError::new(PARSE_ERROR, "Request too large");
+            write_response(&mut writer, Value::Null, Err(error)).await?;
+            return Ok(());
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let request: Request = match serde_json::from_slice(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, e.to_string());
+                write_response(&mut writer, Value::Null, Err(error)).await?;
+                continue;
+            }
+        };
+
+        let result = if request.method == "auth" {
+            match parse_params::<AuthParams>(request.params) {
+                Ok(params) if tokens_match(&params.token, &ctx.token) => {
+                    authenticated = true;
+                    Ok(json!(true))
+                }
+                Ok(_) => {
+                    tracing::warn!("Rejected remote API token from {}", peer);
+                    let error = RpcError::new(UNAUTHORIZED, "Invalid token");
+                    let id = request.id.unwrap_or(Value::Null);
+                    write_response(&mut writer, id, Err(error)).await?;
+                    return Ok(());
+                }
+                Err(e) => Err(e),
+            }
+        } else if !authenticated {
+            Err(RpcError::new(UNAUTHORIZED, "Call auth first"))
+        } else {
+            dispatch(&request.method, request.params, peer, &ctx).await
+        };
+
+        if let Some(id) = request.id {
+            write_response(&mut writer, id, result).await?;
+        } else if let Err(e) = result {
+            tracing::debug!("Remote notification from {} failed: {}", peer, e.message);
+        }
+    }
+}
+
+async fn dispatch(
+    method: &str,
+    params: Value,
+    peer: SocketAddr,
+    ctx: &RemoteContext,
+) -> std::result::Result<Value, RpcError> {
+    match method {
+        "status" => {
+            let lifecycle = ctx.lifecycle.lock().unwrap();
+            Ok(match lifecycle.session() {
+                Some(session) => json!({
+                    "session_id": session.id.to_string(),
+                    "session_name": session.name,
+                }),
+                None => json!({ "session_id": null, "session_name": null }),
+            })
+        }
+        "capture.submit" => {
+            let params: CaptureParams = parse_params(params)?;
+            if ctx.exclusions.is_excluded(&params.command) {
+                return Ok(json!({ "queued": false, "skipped": true }));
+            }
+
+            let session_id = {
+                let mut lifecycle = ctx.lifecycle.lock().unwrap();
+                if let Err(e) = lifecycle.touch() {
+                    tracing::warn!("Failed to record session activity: {}", e);
+                }
+                params
+                    .session_id
+                    .or_else(|| lifecycle.session().map(|s| s.id.to_string()))
+            };
+            let Some(session_id) = session_id else {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "No active session; pass session_id",
+                ));
+            };
+            let lifecycle = ctx.lifecycle.clone();
+            let storage = ctx.storage.clone();
+            let checked = session_id.clone();
+            let refused = tokio::task::spawn_blocking(move || {
+                super::check_session(&lifecycle, &storage, &checked)
+            })
+            .await
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, format!("Session check failed: {}", e)))?;
+            if let Some(response) = refused {
+                let message = response
+                    .message
+                    .unwrap_or_else(|| "Session refused".to_string());
+                return Err(RpcError::new(INVALID_PARAMS, message));
+            }
+
+            let event = CaptureEvent {
+                session_id,
+                timestamp: params
+                    .timestamp
+                    .unwrap_or_else(|| chrono::Utc::now().timestamp()),
+                command: params.command,
+                output: params.output,
+                exit_code: params.exit_code,
+                cwd: params.cwd,
+                username: Some(format!(
+                    "{}@{}",
+                    params.user.as_deref().unwrap_or("remote"),
+                    peer.ip()
+                )),
+                source: None,
+                tool: None,
+                context: params.context,
+            };
+            ctx.pipeline
+                .send_timeout(event, health::QUEUE_TIMEOUT)
+                .await
+                .map_err(|e| match e {
+                    mpsc::error::SendTimeoutError::Timeout(_) => {
+                        health::record_drop();
+                        RpcError::new(INTERNAL_ERROR, "Capture dropped: the pipeline is backed up")
+                    }
+                    mpsc::error::SendTimeoutError::Closed(_) => {
+                        RpcError::new(INTERNAL_ERROR, "Pipeline channel closed")
+                    }
+                })?;
+            Ok(json!({ "queued": true, "skipped": false }))
+        }
+        "query" => {
+            let params: QueryParams = parse_params(params)?;
+            let limit = params.limit.clamp(1, MAX_QUERY_LIMIT);
+            // Same `[acl]` scoping as a local query by the token's user
+            let scope = ctx.acl.scope_for(&ctx.user);
+            tracing::info!(
+                "Remote query from {} as {} scoped to {:?}",
+                peer,
+                ctx.user,
+                scope
+            );
+            let storage = ctx.storage.clone();
+            let hits = tokio::task::spawn_blocking(move || {
+                super::scoped_search(&storage, &scope, &params.query, limit)
+            })
+            .await
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?
+            .map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))?;
+            serde_json::to_value(hits).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", method),
+        )),
+    }
+}
+
+async fn write_response(
+    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    id: Value,
+    result: std::result::Result<Value, RpcError>,
+) -> Result<()> {
+    let (result, error) = match result {
+        Ok(value) => (Some(value), None),
+        Err(error) => (None, Some(error)),
+    };
+    let response = Response {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    };
+    let mut bytes = serde_json::to_vec(&response).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize remote response".to_string(),
+    })?;
+    bytes.push(b'\n');
+    writer.write_all(&bytes).await.map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to write remote response".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AclConfig;
+    use crate::session::SessionManager;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("0123456789abcdef", "0123456789abcdef"));
+        assert!(!tokens_match("0123456789abcdeX", "0123456789abcdef"));
+        assert!(!tokens_match("0123", "0123456789abcdef"));
+    }
+
+    #[tokio::test]
+    async fn test_remote_auth_and_capture() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().to_path_buf();
+        let storage = Arc::new(StorageManager::new(data_dir.clone()).unwrap());
+        let session_id = SessionManager::new(data_dir.clone())
+            .create_session(Some("exam".to_string()))
+            .unwrap()
+            .id;
+        let lifecycle =
+            SessionLifecycle::new(data_dir, storage.database.clone(), None, Some(&session_id));
+        let mut index = storage.open_keyword_index().unwrap();
+        for user in ["alice", "bob"] {
+            let stored = capture("s1", "nmap 10.0.0.1", b"22/tcp open ssh\n")
+                .prompt(user, "/tmp")
+                .chunks(&["22/tcp open ssh"])
+                .insert(&storage);
+            index
+                .insert(stored.chunk_ids[0] as u64, "22/tcp open ssh")
+                .unwrap();
+        }
+        index.commit().unwrap();
+        drop(index);
+        // The token queries as alice, who only sees her own captures
+        let acl = AclConfig {
+            shared: true,
+            ..AclConfig::default()
+        };
+        let (tx, mut rx) = mpsc::channel(8);
+        let ctx = RemoteContext {
+            pipeline: tx,
+            lifecycle: Arc::new(Mutex::new(lifecycle)),
+            exclusions: Arc::new(CommandExclusions::new(&["^history".to_string()]).unwrap()),
+            storage,
+            token: Arc::from("0123456789abcdef"),
+            user: Arc::from("alice"),
+            acl: Arc::new(Acl::new(&acl, 0).unwrap()),
+        };
+
+        let server = RemoteServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, peer) = server.accept().await.unwrap();
+            handle_remote(stream, peer, ctx).await.unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        for request in [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "status"}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "auth", "params": {"token": "0123456789abcdef"}}),
+            json!({"jsonrpc": "2.0", "method": "capture.submit", "params": {"command": "history"}}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "capture.submit",
+                   "params": {"command": "id", "output": "uid=0(root)", "user": "kali"}}),
+            json!({"jsonrpc": "2.0", "id": 4, "method": "capture.submit",
+                   "params": {"command": "id", "session_id": "no-such-session"}}),
+            json!({"jsonrpc": "2.0", "id": 5, "method": "query", "params": {"query": "ssh"}}),
+        ] {
+            writer
+                .write_all(format!("{}\n", request).as_bytes())
+                .await
+                .unwrap();
+        }
+
+        let unauthorized: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(unauthorized["error"]["code"], UNAUTHORIZED);
+        let auth: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(auth["result"], true);
+        // The excluded notification gets no reply, so the next line answers id 3
+        let queued: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(queued["id"], 3);
+        assert_eq!(queued["result"]["queued"], true);
+        let unknown: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(unknown["error"]["code"], INVALID_PARAMS);
+        let hits: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        let users: Vec<&str> = hits["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|hit| hit["username"].as_str().unwrap())
+            .collect();
+        assert_eq!(users, ["alice"]);
+
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.command, "id");
+        assert_eq!(event.session_id, session_id.to_string());
+        assert_eq!(event.username.as_deref(), Some("kali@127.0.0.1"));
+    }
+}
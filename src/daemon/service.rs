@@ -0,0 +1,325 @@
+// Login services that start the daemon: launchd agents on macOS, systemd
+// user units on Linux
+
+use super::expand_tilde;
+use crate::config::Config;
+use crate::error::{Result, YinxError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// launchd job label, also the plist's file name
+pub const LAUNCHD_LABEL: &str = "io.yinx.daemon";
+
+/// systemd user unit name
+pub const SYSTEMD_UNIT: &str = "yinx.service";
+
+/// Service manager the daemon can be installed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    Launchd,
+    Systemd,
+}
+
+/// What the service runs
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    /// The yinx executable
+    pub exe: PathBuf,
+    /// Config file passed with `--config`, if not the default
+    pub config: Option<PathBuf>,
+    /// Daemon log file; stdout and stderr go next to it
+    pub log_file: PathBuf,
+}
+
+impl ServiceSpec {
+    /// Run the current executable with `config`'s log file
+    pub fn current(config: &Config, config_path: Option<PathBuf>) -> Result<Self> {
+        let exe = std::env::current_exe().map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to locate the yinx executable".to_string(),
+        })?;
+        Ok(Self {
+            exe,
+            config: config_path,
+            log_file: expand_tilde(&config.daemon.log_file),
+        })
+    }
+
+    /// Command line the service starts: the daemon in the foreground
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![self.exe.display().to_string()];
+        if let Some(config) = &self.config {
+            args.push("--config".to_string());
+            args.push(config.display().to_string());
+        }
+        args.push("_internal".to_string());
+        args.push("run-daemon".to_string());
+        args
+    }
+}
+
+impl ServiceManager {
+    /// The service manager of the platform yinx was built for
+    pub fn native() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            Some(Self::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Some(Self::Systemd)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Launchd => "launchd",
+            Self::Systemd => "systemd",
+        }
+    }
+
+    /// Where the service file is installed for the current user
+    pub fn service_path(&self) -> Result<PathBuf> {
+        let missing = || YinxError::Config("Cannot determine home directory".to_string());
+        Ok(match self {
+            Self::Launchd => dirs::home_dir()
+                .ok_or_else(missing)?
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", LAUNCHD_LABEL)),
+            Self::Systemd => dirs::config_dir()
+                .ok_or_else(missing)?
+                .join("systemd/user")
+                .join(SYSTEMD_UNIT),
+        })
+    }
+
+    /// Service file contents for `spec`
+    ///
+    /// The daemon starts at login and is restarted only when it exits
+    /// abnormally, so `yinx stop` still stops it.
+    pub fn render(&self, spec: &ServiceSpec) -> String {
+        match self {
+            Self::Launchd => launchd_plist(spec),
+            Self::Systemd => systemd_unit(spec),
+        }
+    }
+
+    /// Write the service file, loading it unless `load` is false
+    pub fn install(&self, spec: &ServiceSpec, load: bool, force: bool) -> Result<PathBuf> {
+        let path = self.service_path()?;
+        if path.exists() && !force {
+            return Err(YinxError::Daemon(format!(
+                "{} already exists; pass --force to replace it",
+                path.display()
+            )));
+        }
+        // launchd won't create the log directory itself
+        for dir in [path.parent(), spec.log_file.parent()]
+            .into_iter()
+            .flatten()
+        {
+            std::fs::create_dir_all(dir).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to create {}", dir.display()),
+            })?;
+        }
+        std::fs::write(&path, self.render(spec)).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write {}", path.display()),
+        })?;
+
+        if load {
+            match self {
+                Self::Launchd => {
+                    // Replacing a loaded agent needs it booted out first
+                    if force {
+                        launchctl("bootout", &path).ok();
+                    }
+                    launchctl("bootstrap", &path)?;
+                }
+                Self::Systemd => {
+                    systemctl(&["daemon-reload"])?;
+                    systemctl(&["enable", "--now", SYSTEMD_UNIT])?;
+                }
+            }
+        }
+        Ok(path)
+    }
+
+    /// Unload and remove the service file; None if it wasn't installed
+    pub fn uninstall(&self) -> Result<Option<PathBuf>> {
+        let path = self.service_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        // Not being loaded is fine; the file still goes
+        match self {
+            Self::Launchd => launchctl("bootout", &path).ok(),
+            Self::Systemd => systemctl(&["disable", "--now", SYSTEMD_UNIT]).ok(),
+        };
+        std::fs::remove_file(&path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to remove {}", path.display()),
+        })?;
+        if *self == Self::Systemd {
+            systemctl(&["daemon-reload"]).ok();
+        }
+        Ok(Some(path))
+    }
+}
+
+fn launchd_plist(spec: &ServiceSpec) -> String {
+    let args: String = spec
+        .args()
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+        .collect();
+    let log = |extension: &str| {
+        xml_escape(
+            &spec
+                .log_file
+                .with_extension(extension)
+                .display()
+                .to_string(),
+        )
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{args}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ThrottleInterval</key>
+    <integer>10</integer>
+    <key>ProcessType</key>
+    <string>Background</string>
+    <key>StandardOutPath</key>
+    <string>{stdout}</string>
+    <key>StandardErrorPath</key>
+    <string>{stderr}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        args = args,
+        stdout = log("stdout"),
+        stderr = log("stderr"),
+    )
+}
+
+fn systemd_unit(spec: &ServiceSpec) -> String {
+    let exec: Vec<String> = spec.args().iter().map(|arg| systemd_quote(arg)).collect();
+    format!(
+        "[Unit]\n\
+         Description=yinx terminal capture daemon\n\
+         \n\
+         [Service]\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         RestartSec=10\n\
+         StandardOutput=append:{}\n\
+         StandardError=append:{}\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exec.join(" "),
+        spec.log_file.with_extension("stdout").display(),
+        spec.log_file.with_extension("stderr").display(),
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Quote an ExecStart argument; `%` would otherwise start a specifier
+fn systemd_quote(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%");
+    format!("\"{}\"", escaped)
+}
+
+fn launchctl(action: &str, plist: &Path) -> Result<()> {
+    let domain = format!("gui/{}", super::current_uid());
+    run(
+        "launchctl",
+        &[action, &domain, &plist.display().to_string()],
+    )
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let mut all = vec!["--user"];
+    all.extend_from_slice(args);
+    run("systemctl", &all)
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to run {}", program),
+        })?;
+    if !status.success() {
+        return Err(YinxError::Daemon(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            exe: PathBuf::from("/usr/local/bin/yinx"),
+            config: Some(PathBuf::from(
+                "/Users/a&b/Library/Application Support/yinx/config.toml",
+            )),
+            log_file: PathBuf::from("/Users/a&b/Library/Application Support/yinx/logs/daemon.log"),
+        }
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = ServiceManager::Launchd.render(&spec());
+        assert!(plist.contains("<string>io.yinx.daemon</string>"));
+        assert!(plist.contains(
+            "<string>/Users/a&amp;b/Library/Application Support/yinx/config.toml</string>"
+        ));
+        assert!(plist.contains("<string>run-daemon</string>"));
+        assert!(plist.contains("yinx/logs/daemon.stderr</string>"));
+        assert!(!plist.contains("a&b"));
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let mut spec = spec();
+        spec.config = Some(PathBuf::from("/home/me/100%/config.toml"));
+        let unit = ServiceManager::Systemd.render(&spec);
+        assert!(unit.contains(
+            "ExecStart=\"/usr/local/bin/yinx\" \"--config\" \"/home/me/100%%/config.toml\" \"_internal\" \"run-daemon\"\n"
+        ));
+        assert!(unit.contains("Restart=on-failure"));
+    }
+}
@@ -1,9 +1,13 @@
 // Signal handling for graceful daemon shutdown
 
 use crate::error::{Result, YinxError};
+#[cfg(unix)]
 use tokio::signal::unix::{signal, Signal as TokioSignal, SignalKind};
+#[cfg(windows)]
+use tokio::signal::windows;
 
 /// Signal handler that manages multiple Unix signals
+#[cfg(unix)]
 pub struct SignalHandler {
     sigterm: TokioSignal,
     sigint: TokioSignal,
@@ -11,6 +15,7 @@ pub struct SignalHandler {
     sigusr1: TokioSignal,
 }
 
+#[cfg(unix)]
 impl SignalHandler {
     /// Create a new signal handler
     /// Sets up handlers for SIGTERM, SIGINT, SIGHUP, and SIGUSR1
@@ -64,6 +69,58 @@ impl SignalHandler {
     }
 }
 
+/// Console control events, the Windows stand-in for signals
+///
+/// Ctrl+C and Ctrl+Break map to "interrupt", closing the console to
+/// "hangup" and logoff/shutdown to "terminate", so the daemon shuts down
+/// cleanly on each. There is no reload event.
+#[cfg(windows)]
+pub struct SignalHandler {
+    ctrl_c: windows::CtrlC,
+    ctrl_break: windows::CtrlBreak,
+    ctrl_close: windows::CtrlClose,
+    ctrl_shutdown: windows::CtrlShutdown,
+}
+
+#[cfg(windows)]
+impl SignalHandler {
+    /// Create a new signal handler
+    pub fn new() -> Result<Self> {
+        let setup = |name: &str, e: std::io::Error| YinxError::Io {
+            source: e,
+            context: format!("Failed to setup {} handler", name),
+        };
+        Ok(Self {
+            ctrl_c: windows::ctrl_c().map_err(|e| setup("Ctrl+C", e))?,
+            ctrl_break: windows::ctrl_break().map_err(|e| setup("Ctrl+Break", e))?,
+            ctrl_close: windows::ctrl_close().map_err(|e| setup("console close", e))?,
+            ctrl_shutdown: windows::ctrl_shutdown().map_err(|e| setup("shutdown", e))?,
+        })
+    }
+
+    /// Wait for any console event to be received
+    pub async fn wait(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.ctrl_c.recv() => {
+                tracing::info!("Received Ctrl+C");
+                "interrupt"
+            }
+            _ = self.ctrl_break.recv() => {
+                tracing::info!("Received Ctrl+Break");
+                "interrupt"
+            }
+            _ = self.ctrl_close.recv() => {
+                tracing::info!("Console closed");
+                "hangup"
+            }
+            _ = self.ctrl_shutdown.recv() => {
+                tracing::info!("System shutting down");
+                "terminate"
+            }
+        }
+    }
+}
+
 /// Check if the signal should trigger shutdown
 pub fn should_shutdown(sig: &str) -> bool {
     matches!(sig, "terminate" | "interrupt" | "hangup")
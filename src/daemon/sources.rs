@@ -0,0 +1,515 @@
+// System log sources: syslog datagrams and the journal, batched into pipeline captures
+
+use super::lifecycle::SessionLifecycle;
+use super::pipeline::CaptureEvent;
+use crate::config::{parse_duration, SourcesConfig, SyslogSourceConfig};
+use crate::error::{Result, YinxError};
+use chrono::{TimeZone, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{UdpSocket, UnixDatagram};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Largest syslog datagram read
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+/// Syslog facility names, indexed by facility code
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+/// Whether `name` is a syslog facility
+pub(crate) fn is_facility(name: &str) -> bool {
+    FACILITIES.contains(&name)
+}
+
+/// A received log line
+#[derive(Debug, PartialEq)]
+struct LogLine {
+    /// Source name, also used as the capture tag
+    source: &'static str,
+    /// Facility or unit the line is grouped under
+    group: String,
+    host: Option<String>,
+    text: String,
+}
+
+/// Parse a syslog message into its facility, sending host and display text
+///
+/// The text drops the priority so lines read like a local auth.log.
+fn parse_syslog(message: &str) -> Option<(&'static str, Option<String>, String)> {
+    let message = message.trim_end_matches(['\r', '\n', '\0']);
+    let (priority, rest) = message.strip_prefix('<')?.split_once('>')?;
+    let priority: u8 = priority.parse().ok().filter(|p| *p < 192)?;
+    let facility = FACILITIES[(priority >> 3) as usize];
+
+    // RFC 5424: VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID SD [MSG]
+    if let Some(rest) = rest.strip_prefix("1 ") {
+        let mut fields = rest.splitn(6, ' ');
+        let timestamp = fields.next()?;
+        let host = fields.next()?;
+        let app = fields.next()?;
+        let pid = fields.next()?;
+        let _msgid = fields.next()?;
+        let msg = skip_structured_data(fields.next().unwrap_or(""));
+        let msg = msg.trim_start_matches('\u{feff}');
+
+        let host = (host != "-").then(|| host.to_string());
+        let mut text = String::new();
+        for part in [timestamp, host.as_deref().unwrap_or("-")] {
+            if part != "-" {
+                text.push_str(part);
+                text.push(' ');
+            }
+        }
+        if app != "-" {
+            text.push_str(app);
+            if pid != "-" {
+                text.push_str(&format!("[{}]", pid));
+            }
+            text.push_str(": ");
+        }
+        text.push_str(msg);
+        return Some((facility, host, text));
+    }
+
+    // RFC 3164: "Mmm dd hh:mm:ss HOST TAG: MSG", though local senders often
+    // leave the host out
+    let host = rest
+        .get(16..)
+        .filter(|_| rest.as_bytes().get(15) == Some(&b' '))
+        .and_then(|after| after.split_once(' '))
+        .map(|(token, _)| token)
+        .filter(|token| !token.contains([':', '[']))
+        .map(str::to_string);
+    Some((facility, host, rest.to_string()))
+}
+
+/// Skip RFC 5424 structured data (`-` or `[id k="v"]...`), returning the message
+fn skip_structured_data(s: &str) -> &str {
+    if let Some(rest) = s.strip_prefix('-') {
+        return rest.trim_start();
+    }
+    let mut in_element = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => in_element = true,
+            ']' => in_element = false,
+            ' ' if !in_element => return &s[i + 1..],
+            _ if !in_element => return &s[i..],
+            _ => {}
+        }
+    }
+    ""
+}
+
+/// Parse a `journalctl -o json` entry
+fn parse_journal_entry(line: &str) -> Option<LogLine> {
+    let entry: Value = serde_json::from_str(line).ok()?;
+    let field = |name: &str| entry.get(name).and_then(Value::as_str);
+    let message = match entry.get("MESSAGE")? {
+        Value::String(text) => text.clone(),
+        // Non-UTF-8 messages come as byte arrays
+        Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .filter_map(|b| b.as_u64().map(|b| b as u8))
+                .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => return None,
+    };
+
+    let unit = field("_SYSTEMD_UNIT");
+    let ident = field("SYSLOG_IDENTIFIER").or(unit).unwrap_or("journal");
+    let host = field("_HOSTNAME").map(str::to_string);
+
+    let mut text = String::new();
+    if let Some(time) = field("__REALTIME_TIMESTAMP")
+        .and_then(|us| us.parse::<i64>().ok())
+        .and_then(|us| Utc.timestamp_micros(us).single())
+    {
+        text.push_str(&time.format("%b %e %H:%M:%S ").to_string());
+    }
+    if let Some(host) = &host {
+        text.push_str(host);
+        text.push(' ');
+    }
+    text.push_str(ident);
+    if let Some(pid) = field("_PID") {
+        text.push_str(&format!("[{}]", pid));
+    }
+    text.push_str(": ");
+    text.push_str(&message);
+
+    Some(LogLine {
+        source: "journald",
+        group: unit.unwrap_or(ident).to_string(),
+        host,
+        text,
+    })
+}
+
+type GroupKey = (&'static str, String, Option<String>);
+
+/// Collects lines per source, group and host
+struct Batcher {
+    max_lines: usize,
+    groups: HashMap<GroupKey, Vec<String>>,
+}
+
+impl Batcher {
+    fn new(max_lines: usize) -> Self {
+        Self {
+            max_lines,
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Add a line; returns its group once the group is full
+    fn push(&mut self, line: LogLine) -> Option<(GroupKey, Vec<String>)> {
+        let key = (line.source, line.group, line.host);
+        let lines = self.groups.entry(key.clone()).or_default();
+        lines.push(line.text);
+        if lines.len() < self.max_lines {
+            return None;
+        }
+        self.groups.remove_entry(&key)
+    }
+
+    fn drain(&mut self) -> Vec<(GroupKey, Vec<String>)> {
+        self.groups.drain().collect()
+    }
+}
+
+/// Turn a group of lines into a capture, e.g. `syslog auth@web01`
+fn to_event(key: GroupKey, lines: Vec<String>, session_id: String) -> CaptureEvent {
+    let (source, group, host) = key;
+    let command = match host {
+        Some(host) => format!("{} {}@{}", source, group, host),
+        None => format!("{} {}", source, group),
+    };
+    CaptureEvent {
+        session_id,
+        timestamp: Utc::now().timestamp(),
+        command,
+        output: lines.join("\n"),
+        exit_code: 0,
+        cwd: String::new(),
+        username: None,
+        source: Some(source.to_string()),
+        tool: None,
+        context: None,
+    }
+}
+
+/// Running log sources
+pub(super) struct LogSources {
+    readers: Vec<JoinHandle<()>>,
+    batcher: Option<JoinHandle<()>>,
+}
+
+impl LogSources {
+    /// Start every enabled source
+    pub(super) async fn start(
+        config: &SourcesConfig,
+        pipeline: mpsc::Sender<CaptureEvent>,
+        lifecycle: Arc<Mutex<SessionLifecycle>>,
+    ) -> Result<Self> {
+        let (tx, rx) = mpsc::channel(1024);
+        let mut readers = Vec::new();
+        if config.syslog.enabled {
+            readers.push(spawn_syslog(&config.syslog, tx.clone()).await?);
+        }
+        if config.journald.enabled {
+            readers.push(spawn_journald(&config.journald.units, tx.clone())?);
+        }
+        if readers.is_empty() {
+            return Ok(Self {
+                readers,
+                batcher: None,
+            });
+        }
+
+        let flush_interval = parse_duration(&config.flush_interval)
+            .filter(|d| !d.is_zero())
+            .unwrap_or(Duration::from_secs(10));
+        let batcher = Batcher::new(config.max_lines.max(1));
+        Ok(Self {
+            readers,
+            batcher: Some(tokio::spawn(run_batcher(
+                rx,
+                batcher,
+                flush_interval,
+                pipeline,
+                lifecycle,
+            ))),
+        })
+    }
+
+    /// Stop the readers and capture whatever lines are still buffered
+    pub(super) async fn stop(self) {
+        for reader in self.readers {
+            reader.abort();
+        }
+        if let Some(batcher) = self.batcher {
+            let _ = batcher.await;
+        }
+    }
+}
+
+async fn run_batcher(
+    mut rx: mpsc::Receiver<LogLine>,
+    mut batcher: Batcher,
+    flush_interval: Duration,
+    pipeline: mpsc::Sender<CaptureEvent>,
+    lifecycle: Arc<Mutex<SessionLifecycle>>,
+) {
+    let mut ticker = tokio::time::interval(flush_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        let groups = tokio::select! {
+            line = rx.recv() => match line {
+                Some(line) => batcher.push(line).into_iter().collect(),
+                None => batcher.drain(),
+            },
+            _ = ticker.tick() => batcher.drain(),
+        };
+
+        if !groups.is_empty() {
+            let session = lifecycle
+                .lock()
+                .unwrap()
+                .session()
+                .map(|s| s.id.to_string());
+            match session {
+                Some(session_id) => {
+                    for (key, lines) in groups {
+                        let event = to_event(key, lines, session_id.clone());
+                        if pipeline.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                None => {
+                    tracing::debug!("No active session; dropping {} log group(s)", groups.len())
+                }
+            }
+        }
+        if rx.is_closed() && rx.is_empty() && batcher.groups.is_empty() {
+            return;
+        }
+    }
+}
+
+async fn spawn_syslog(
+    config: &SyslogSourceConfig,
+    tx: mpsc::Sender<LogLine>,
+) -> Result<JoinHandle<()>> {
+    let bind_error = |e: std::io::Error| YinxError::Io {
+        source: e,
+        context: format!("Failed to bind syslog source to {}", config.listen),
+    };
+    let socket = match config.listen.strip_prefix("unix:") {
+        Some(path) => {
+            // A socket left behind by an earlier run would make bind fail
+            let _ = std::fs::remove_file(path);
+            SyslogSocket::Unix(UnixDatagram::bind(path).map_err(bind_error)?)
+        }
+        None => {
+            let addr = config.listen.strip_prefix("udp:").unwrap_or(&config.listen);
+            SyslogSocket::Udp(UdpSocket::bind(addr).await.map_err(bind_error)?)
+        }
+    };
+    tracing::info!("Syslog source listening on {}", config.listen);
+
+    let facilities = config.facilities.clone();
+    Ok(tokio::spawn(async move {
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        loop {
+            let received = match &socket {
+                SyslogSocket::Udp(socket) => socket.recv(&mut buf).await,
+                SyslogSocket::Unix(socket) => socket.recv(&mut buf).await,
+            };
+            let len = match received {
+                Ok(len) => len,
+                Err(e) => {
+                    tracing::warn!("Syslog receive failed: {}", e);
+                    continue;
+                }
+            };
+            let datagram = String::from_utf8_lossy(&buf[..len]);
+            for message in datagram.lines().filter(|l| !l.trim().is_empty()) {
+                let Some((facility, host, text)) = parse_syslog(message) else {
+                    tracing::debug!("Ignoring malformed syslog message");
+                    continue;
+                };
+                if !facilities.is_empty() && !facilities.iter().any(|f| f == facility) {
+                    continue;
+                }
+                let line = LogLine {
+                    source: "syslog",
+                    group: facility.to_string(),
+                    host,
+                    text,
+                };
+                if tx.send(line).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }))
+}
+
+enum SyslogSocket {
+    Udp(UdpSocket),
+    Unix(UnixDatagram),
+}
+
+fn spawn_journald(units: &[String], tx: mpsc::Sender<LogLine>) -> Result<JoinHandle<()>> {
+    let mut command = Command::new("journalctl");
+    command.args(["--follow", "--lines=0", "--output=json"]);
+    for unit in units {
+        command.arg("--unit").arg(unit);
+    }
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to start journalctl".to_string(),
+        })?;
+    let stdout = child.stdout.take().expect("journalctl stdout is piped");
+    tracing::info!("Journald source following {} unit(s)", units.len());
+
+    Ok(tokio::spawn(async move {
+        // Owning the child here kills journalctl when the task is aborted
+        let _child = child;
+        let mut lines = BufReader::new(stdout).lines();
+        loop {
+            match lines.next_line().await {
+                Ok(Some(line)) => {
+                    if let Some(entry) = parse_journal_entry(&line) {
+                        if tx.send(entry).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Ok(None) => {
+                    tracing::warn!("journalctl exited; journald source stopped");
+                    return;
+                }
+                Err(e) => tracing::warn!("Failed to read journal entry: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_syslog() {
+        let (facility, host, text) = parse_syslog(
+            "<38>Oct 15 10:00:01 web01 sshd[812]: Accepted password for root from 10.0.0.5 port 51234 ssh2\n",
+        )
+        .unwrap();
+        assert_eq!(facility, "auth");
+        assert_eq!(host.as_deref(), Some("web01"));
+        assert!(text.starts_with("Oct 15 10:00:01 web01 sshd[812]: Accepted"));
+
+        // Local senders (logger, /dev/log) leave the host out
+        let (facility, host, _) =
+            parse_syslog("<86>Oct  5 09:12:44 sudo:   alice : TTY=pts/0 ; COMMAND=/bin/bash")
+                .unwrap();
+        assert_eq!(facility, "authpriv");
+        assert_eq!(host, None);
+
+        let (facility, host, text) = parse_syslog(
+            r#"<34>1 2026-10-15T10:00:01Z dc01 sshd 812 - [meta x="a\]b"] Failed password for admin"#,
+        )
+        .unwrap();
+        assert_eq!(facility, "auth");
+        assert_eq!(host.as_deref(), Some("dc01"));
+        assert_eq!(
+            text,
+            "2026-10-15T10:00:01Z dc01 sshd[812]: Failed password for admin"
+        );
+
+        assert!(parse_syslog("no priority here").is_none());
+        assert!(parse_syslog("<999>Oct 15 10:00:01 x").is_none());
+    }
+
+    #[test]
+    fn test_parse_journal_entry() {
+        let line = r#"{"MESSAGE":"Accepted publickey for kali","_SYSTEMD_UNIT":"ssh.service","SYSLOG_IDENTIFIER":"sshd","_PID":"901","_HOSTNAME":"pivot","__REALTIME_TIMESTAMP":"1760522401000000"}"#;
+        let entry = parse_journal_entry(line).unwrap();
+        assert_eq!(entry.group, "ssh.service");
+        assert_eq!(entry.host.as_deref(), Some("pivot"));
+        assert!(entry
+            .text
+            .ends_with("pivot sshd[901]: Accepted publickey for kali"));
+
+        let binary = r#"{"MESSAGE":[104,105],"SYSLOG_IDENTIFIER":"kernel"}"#;
+        assert_eq!(parse_journal_entry(binary).unwrap().text, "kernel: hi");
+    }
+
+    #[test]
+    fn test_batcher_groups_lines() {
+        let line = |group: &str, text: &str| LogLine {
+            source: "syslog",
+            group: group.to_string(),
+            host: Some("web01".to_string()),
+            text: text.to_string(),
+        };
+        let mut batcher = Batcher::new(2);
+        assert!(batcher.push(line("auth", "a")).is_none());
+        assert!(batcher.push(line("cron", "c")).is_none());
+        let (key, lines) = batcher.push(line("auth", "b")).unwrap();
+        assert_eq!(lines, vec!["a", "b"]);
+
+        let event = to_event(key, lines, "s1".to_string());
+        assert_eq!(event.command, "syslog auth@web01");
+        assert_eq!(event.output, "a\nb");
+        assert_eq!(event.source.as_deref(), Some("syslog"));
+
+        assert_eq!(batcher.drain().len(), 1);
+        assert!(batcher.drain().is_empty());
+    }
+}
@@ -0,0 +1,431 @@
+// Supervisor mode: a thin parent process that restarts a crashed daemon
+//
+// The supervisor runs `yinx _internal run-daemon` as a child and waits on
+// it. A clean exit (`yinx stop`) ends supervision; anything else writes a
+// crash report and restarts the daemon after an exponential backoff, until
+// it crashes too often within the crash-loop window. The child leaves its
+// panic backtrace and a snapshot of the pipeline in the crash directory for
+// the report, since a panicking or OOM-killed process can't be asked later.
+
+use super::{expand_tilde, ProcessManager};
+use crate::config::{parse_duration, Config, SupervisorConfig};
+use crate::error::{Result, YinxError};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Where this process writes its panic and pipeline files, when supervised
+static CRASH_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// What the pipeline was doing most recently
+static PIPELINE_STATE: Mutex<Option<PipelineState>> = Mutex::new(None);
+
+/// Snapshot of the storage worker, kept for crash reports
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipelineState {
+    pub processed: u64,
+    pub errors: u64,
+    /// Captures in the batch being flushed
+    pub batch_size: usize,
+    /// Session of the capture being processed or last processed
+    pub session_id: Option<String>,
+    /// Program name only; full command lines may hold secrets
+    pub program: Option<String>,
+    pub captured_at: Option<i64>,
+    pub updated_at: i64,
+}
+
+/// One daemon crash, as written to `crash-<time>-<pid>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub yinx_version: String,
+    pub pid: u32,
+    pub crashed_at: i64,
+    /// Exit code or signal
+    pub exit: String,
+    pub uptime_secs: u64,
+    /// Restarts before this crash
+    pub restarts: u32,
+    /// Whether the supervisor stopped restarting after this crash
+    pub gave_up: bool,
+    /// Panic message, location and backtrace, if the daemon panicked
+    pub panic: Option<String>,
+    pub pipeline: Option<PipelineState>,
+}
+
+/// Directory crash reports are written to, next to the daemon log
+pub fn crash_dir(config: &Config) -> PathBuf {
+    expand_tilde(&config.daemon.log_file).with_file_name("crashes")
+}
+
+/// Record panics and pipeline snapshots in `dir` for the supervisor
+pub fn install_crash_handler(dir: PathBuf) -> Result<()> {
+    std::fs::create_dir_all(&dir).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to create crash directory: {}", dir.display()),
+    })?;
+    let path = dir.join(format!("panic-{}.txt", std::process::id()));
+    CRASH_DIR.get_or_init(|| dir);
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = std::thread::current();
+        let report = format!(
+            "thread '{}' {}\n\n{}\n",
+            thread.name().unwrap_or("<unnamed>"),
+            info,
+            std::backtrace::Backtrace::force_capture()
+        );
+        // A panic in a spawned task doesn't end the daemon; keep them all
+        use std::io::Write;
+        if let Ok(mut file) = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+        {
+            let _ = file.write_all(report.as_bytes());
+        }
+        default_hook(info);
+    }));
+    Ok(())
+}
+
+/// Update the pipeline snapshot; `persist` also writes it to the crash
+/// directory so it survives the daemon being killed outright
+pub(crate) fn update_pipeline_state(persist: bool, update: impl FnOnce(&mut PipelineState)) {
+    let Ok(mut guard) = PIPELINE_STATE.lock() else {
+        return;
+    };
+    let state = guard.get_or_insert_with(PipelineState::default);
+    update(state);
+    state.updated_at = chrono::Utc::now().timestamp();
+    if let (true, Some(dir)) = (persist, CRASH_DIR.get()) {
+        if let Ok(json) = serde_json::to_vec(state) {
+            let path = dir.join(format!("pipeline-{}.json", std::process::id()));
+            let _ = std::fs::write(path, json);
+        }
+    }
+}
+
+/// Exponential backoff between restarts
+#[derive(Debug)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// Wait before the next restart, doubling the one after
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+/// Counts crashes within a sliding window
+#[derive(Debug)]
+pub struct CrashLoop {
+    window: Duration,
+    max_crashes: u32,
+    crashes: VecDeque<Instant>,
+}
+
+impl CrashLoop {
+    pub fn new(window: Duration, max_crashes: u32) -> Self {
+        Self {
+            window,
+            max_crashes,
+            crashes: VecDeque::new(),
+        }
+    }
+
+    /// Record a crash at `at`; true once there are more than allowed in
+    /// the window
+    pub fn record(&mut self, at: Instant) -> bool {
+        while let Some(first) = self.crashes.front() {
+            if at.duration_since(*first) > self.window {
+                self.crashes.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.crashes.push_back(at);
+        self.crashes.len() > self.max_crashes as usize
+    }
+}
+
+/// Run the daemon as a child process, restarting it when it crashes
+///
+/// Returns when the daemon exits cleanly, or with an error once it is
+/// crash-looping.
+pub fn supervise(config: &Config, session_id: Option<Uuid>) -> Result<()> {
+    let settings = Settings::from_config(&config.daemon.supervisor);
+    let crash_dir = crash_dir(config);
+    std::fs::create_dir_all(&crash_dir).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to create crash directory: {}", crash_dir.display()),
+    })?;
+
+    // Each restart loads exactly the config this supervisor was given
+    let config_file = expand_tilde(&config.daemon.log_file).with_file_name("daemon.toml");
+    config.save(&config_file)?;
+    let mut args: Vec<OsString> = vec![
+        "--config".into(),
+        config_file.into(),
+        "_internal".into(),
+        "run-daemon".into(),
+        "--crash-dir".into(),
+        crash_dir.clone().into(),
+    ];
+    if let Some(session_id) = session_id {
+        args.push("--session-id".into());
+        args.push(session_id.to_string().into());
+    }
+    let exe = std::env::current_exe().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to locate the yinx executable".to_string(),
+    })?;
+    let process_manager = ProcessManager::new(expand_tilde(&config.daemon.pid_file));
+
+    let mut backoff = Backoff::new(settings.initial_backoff, settings.max_backoff);
+    let mut crash_loop = CrashLoop::new(settings.window, settings.max_crashes);
+    let mut restarts = 0;
+    loop {
+        let started = Instant::now();
+        let mut child = Command::new(&exe)
+            .args(&args)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|e| YinxError::Io {
+                source: e,
+                context: "Failed to start the daemon".to_string(),
+            })?;
+        let pid = child.id();
+        tracing::info!("Supervisor started daemon (PID {})", pid);
+
+        let status = child.wait().map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to wait for the daemon".to_string(),
+        })?;
+        if status.success() {
+            tracing::info!("Daemon exited cleanly; supervisor stopping");
+            discard_child_files(&crash_dir, pid);
+            return Ok(());
+        }
+
+        let uptime = started.elapsed();
+        if uptime >= settings.window {
+            backoff.reset();
+        }
+        let gave_up = crash_loop.record(Instant::now());
+        let report = collect_crash_report(&crash_dir, pid, &status, uptime, restarts, gave_up);
+        match write_crash_report(&crash_dir, &report) {
+            Ok(path) => tracing::error!(
+                "Daemon (PID {}) {}; crash report: {}",
+                pid,
+                report.exit,
+                path.display()
+            ),
+            Err(e) => tracing::error!("Failed to write crash report: {}", e),
+        }
+
+        // The crashed daemon couldn't remove its PID and lock files
+        if !process_manager.is_running() {
+            if let Err(e) = process_manager.release() {
+                tracing::warn!("Failed to clear stale PID file: {}", e);
+            }
+        }
+
+        if gave_up {
+            return Err(YinxError::Daemon(format!(
+                "Daemon crashed {} times within {:?}; not restarting",
+                crash_loop.crashes.len(),
+                settings.window
+            )));
+        }
+
+        let delay = backoff.next_delay();
+        tracing::warn!("Restarting daemon in {:?}", delay);
+        std::thread::sleep(delay);
+        restarts += 1;
+    }
+}
+
+/// Supervisor settings with durations parsed (validated with the config)
+struct Settings {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    window: Duration,
+    max_crashes: u32,
+}
+
+impl Settings {
+    fn from_config(config: &SupervisorConfig) -> Self {
+        let defaults = SupervisorConfig::default();
+        let parse = |value: &str, default: &str| {
+            parse_duration(value)
+                .or_else(|| parse_duration(default))
+                .unwrap_or_default()
+        };
+        Self {
+            initial_backoff: parse(&config.initial_backoff, &defaults.initial_backoff),
+            max_backoff: parse(&config.max_backoff, &defaults.max_backoff),
+            window: parse(&config.crash_loop_window, &defaults.crash_loop_window),
+            max_crashes: config.crash_loop_restarts.max(1),
+        }
+    }
+}
+
+/// Build the report for a crashed child from the files it left behind
+pub fn collect_crash_report(
+    crash_dir: &Path,
+    pid: u32,
+    status: &ExitStatus,
+    uptime: Duration,
+    restarts: u32,
+    gave_up: bool,
+) -> CrashReport {
+    let panic_file = crash_dir.join(format!("panic-{}.txt", pid));
+    let pipeline_file = crash_dir.join(format!("pipeline-{}.json", pid));
+    let report = CrashReport {
+        yinx_version: env!("CARGO_PKG_VERSION").to_string(),
+        pid,
+        crashed_at: chrono::Utc::now().timestamp(),
+        exit: describe_exit(status),
+        uptime_secs: uptime.as_secs(),
+        restarts,
+        gave_up,
+        panic: std::fs::read_to_string(&panic_file).ok(),
+        pipeline: std::fs::read(&pipeline_file)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok()),
+    };
+    discard_child_files(crash_dir, pid);
+    report
+}
+
+fn write_crash_report(crash_dir: &Path, report: &CrashReport) -> Result<PathBuf> {
+    let path = crash_dir.join(format!("crash-{}-{}.json", report.crashed_at, report.pid));
+    let json = serde_json::to_string_pretty(report).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize crash report".to_string(),
+    })?;
+    std::fs::write(&path, json).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to write crash report: {}", path.display()),
+    })?;
+    Ok(path)
+}
+
+fn discard_child_files(crash_dir: &Path, pid: u32) {
+    for name in [
+        format!("panic-{}.txt", pid),
+        format!("pipeline-{}.json", pid),
+    ] {
+        let _ = std::fs::remove_file(crash_dir.join(name));
+    }
+}
+
+#[cfg(unix)]
+fn describe_exit(status: &ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    match (status.code(), status.signal()) {
+        (_, Some(9)) => "was killed by SIGKILL (out of memory?)".to_string(),
+        (_, Some(signal)) => format!("was killed by signal {}", signal),
+        (Some(101), _) => "panicked (exit code 101)".to_string(),
+        (Some(code), _) => format!("exited with code {}", code),
+        (None, None) => "exited".to_string(),
+    }
+}
+
+#[cfg(windows)]
+fn describe_exit(status: &ExitStatus) -> String {
+    match status.code() {
+        Some(101) => "panicked (exit code 101)".to_string(),
+        Some(code) => format!("exited with code {}", code),
+        None => "exited".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        let delays: Vec<u64> = (0..5).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_crash_loop_window() {
+        let mut crash_loop = CrashLoop::new(Duration::from_secs(60), 2);
+        let start = Instant::now();
+        assert!(!crash_loop.record(start));
+        assert!(!crash_loop.record(start + Duration::from_secs(10)));
+        // The first crash has left the window by now
+        assert!(!crash_loop.record(start + Duration::from_secs(65)));
+        assert!(crash_loop.record(start + Duration::from_secs(70)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crash_report_collects_child_files() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(
+            dir.join("panic-42.txt"),
+            "thread 'main' panicked at x.rs:1:1",
+        )
+        .unwrap();
+        let state = PipelineState {
+            processed: 7,
+            program: Some("nmap".to_string()),
+            ..PipelineState::default()
+        };
+        std::fs::write(
+            dir.join("pipeline-42.json"),
+            serde_json::to_vec(&state).unwrap(),
+        )
+        .unwrap();
+
+        let status = ExitStatus::from_raw(101 << 8);
+        let report = collect_crash_report(dir, 42, &status, Duration::from_secs(3), 1, false);
+        assert_eq!(report.exit, "panicked (exit code 101)");
+        assert!(report.panic.unwrap().contains("panicked at x.rs"));
+        let pipeline = report.pipeline.unwrap();
+        assert_eq!(pipeline.processed, 7);
+        assert_eq!(pipeline.program.as_deref(), Some("nmap"));
+        assert!(!dir.join("panic-42.txt").exists());
+
+        let killed =
+            collect_crash_report(dir, 43, &ExitStatus::from_raw(9), Duration::ZERO, 2, true);
+        assert!(killed.exit.contains("SIGKILL"));
+        assert!(killed.panic.is_none() && killed.pipeline.is_none());
+    }
+}
@@ -0,0 +1,317 @@
+// Payload encodings for IPC frames
+//
+// Every frame is a 4-byte big-endian length and a payload. Payloads are
+// JSON unless a connection opened with a `hello` and the daemon picked
+// another encoding from the ones offered. Bincode can't carry serde's
+// internally tagged enums or skipped fields, so binary frames go through
+// the mirror types below; the small optional parts (capture context,
+// response data) stay JSON inside them so they can grow fields freely.
+
+use super::{CaptureContext, IpcErrorCode, IpcMessage, IpcResponse, OutputHandoff};
+use crate::config::IpcEncoding;
+use crate::error::{Result, YinxError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Encodings this build can speak, most preferred first
+pub const SUPPORTED_ENCODINGS: &[IpcEncoding] = &[IpcEncoding::Bincode, IpcEncoding::Json];
+
+/// First of the client's offered encodings this build also speaks
+pub fn negotiate(offered: &[IpcEncoding]) -> IpcEncoding {
+    offered
+        .iter()
+        .copied()
+        .find(|encoding| SUPPORTED_ENCODINGS.contains(encoding))
+        .unwrap_or_default()
+}
+
+/// Borrows the message's strings when encoding, so big output isn't copied
+#[derive(Serialize, Deserialize)]
+enum WireMessage<'a> {
+    Capture {
+        session_id: Cow<'a, str>,
+        timestamp: i64,
+        command: Cow<'a, str>,
+        output: Cow<'a, str>,
+        exit_code: i32,
+        cwd: Cow<'a, str>,
+        context: Option<String>,
+        dry_run: bool,
+        handoff: Option<OutputHandoff>,
+    },
+    Intent {
+        session_id: Cow<'a, str>,
+        timestamp: i64,
+        command: Cow<'a, str>,
+    },
+    Status,
+    Stop,
+    Query {
+        query: Cow<'a, str>,
+        limit: usize,
+    },
+    Hello {
+        encodings: Vec<IpcEncoding>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct WireResponse {
+    success: bool,
+    message: Option<String>,
+    data: Option<String>,
+    skipped: bool,
+    retry_after_ms: Option<u64>,
+    code: Option<IpcErrorCode>,
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize IPC payload".to_string(),
+    })
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to deserialize IPC payload".to_string(),
+    })
+}
+
+fn encode_bincode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    bincode::serialize(value)
+        .map_err(|e| YinxError::Daemon(format!("Failed to encode IPC payload: {}", e)))
+}
+
+fn decode_bincode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    bincode::deserialize(bytes)
+        .map_err(|e| YinxError::Daemon(format!("Failed to decode IPC payload: {}", e)))
+}
+
+impl<'a> From<&'a IpcMessage> for WireMessage<'a> {
+    fn from(message: &'a IpcMessage) -> Self {
+        match message {
+            IpcMessage::Capture {
+                session_id,
+                timestamp,
+                command,
+                output,
+                exit_code,
+                cwd,
+                context,
+                dry_run,
+                handoff,
+            } => WireMessage::Capture {
+                session_id: session_id.into(),
+                timestamp: *timestamp,
+                command: command.into(),
+                output: output.into(),
+                exit_code: *exit_code,
+                cwd: cwd.into(),
+                // Serializing plain strings and maps can't fail
+                context: context
+                    .as_ref()
+                    .and_then(|context| serde_json::to_string(context).ok()),
+                dry_run: *dry_run,
+                handoff: handoff.clone(),
+            },
+            IpcMessage::Intent {
+                session_id,
+                timestamp,
+                command,
+            } => WireMessage::Intent {
+                session_id: session_id.into(),
+                timestamp: *timestamp,
+                command: command.into(),
+            },
+            IpcMessage::Status => WireMessage::Status,
+            IpcMessage::Stop => WireMessage::Stop,
+            IpcMessage::Query { query, limit } => WireMessage::Query {
+                query: query.into(),
+                limit: *limit,
+            },
+            IpcMessage::Hello { encodings } => WireMessage::Hello {
+                encodings: encodings.clone(),
+            },
+        }
+    }
+}
+
+impl TryFrom<WireMessage<'_>> for IpcMessage {
+    type Error = YinxError;
+
+    fn try_from(message: WireMessage<'_>) -> Result<Self> {
+        Ok(match message {
+            WireMessage::Capture {
+                session_id,
+                timestamp,
+                command,
+                output,
+                exit_code,
+                cwd,
+                context,
+                dry_run,
+                handoff,
+            } => IpcMessage::Capture {
+                session_id: session_id.into_owned(),
+                timestamp,
+                command: command.into_owned(),
+                output: output.into_owned(),
+                exit_code,
+                cwd: cwd.into_owned(),
+                context: context
+                    .map(|json| from_json::<CaptureContext>(&json).map(Box::new))
+                    .transpose()?,
+                dry_run,
+                handoff,
+            },
+            WireMessage::Intent {
+                session_id,
+                timestamp,
+                command,
+            } => IpcMessage::Intent {
+                session_id: session_id.into_owned(),
+                timestamp,
+                command: command.into_owned(),
+            },
+            WireMessage::Status => IpcMessage::Status,
+            WireMessage::Stop => IpcMessage::Stop,
+            WireMessage::Query { query, limit } => IpcMessage::Query {
+                query: query.into_owned(),
+                limit,
+            },
+            WireMessage::Hello { encodings } => IpcMessage::Hello { encodings },
+        })
+    }
+}
+
+/// Encode a client message
+pub fn encode_message(encoding: IpcEncoding, message: &IpcMessage) -> Result<Vec<u8>> {
+    match encoding {
+        IpcEncoding::Json => Ok(to_json(message)?.into_bytes()),
+        IpcEncoding::Bincode => encode_bincode(&WireMessage::from(message)),
+    }
+}
+
+/// Decode a client message
+pub fn decode_message(encoding: IpcEncoding, payload: &[u8]) -> Result<IpcMessage> {
+    match encoding {
+        IpcEncoding::Json => serde_json::from_slice(payload).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to deserialize IPC message".to_string(),
+        }),
+        IpcEncoding::Bincode => decode_bincode::<WireMessage>(payload)?.try_into(),
+    }
+}
+
+/// Encode a daemon response
+pub fn encode_response(encoding: IpcEncoding, response: &IpcResponse) -> Result<Vec<u8>> {
+    match encoding {
+        IpcEncoding::Json => Ok(to_json(response)?.into_bytes()),
+        IpcEncoding::Bincode => encode_bincode(&WireResponse {
+            success: response.success,
+            message: response.message.clone(),
+            data: response.data.as_ref().map(to_json).transpose()?,
+            skipped: response.skipped,
+            retry_after_ms: response.retry_after_ms,
+            code: response.code,
+        }),
+    }
+}
+
+/// Decode a daemon response
+pub fn decode_response(encoding: IpcEncoding, payload: &[u8]) -> Result<IpcResponse> {
+    match encoding {
+        IpcEncoding::Json => serde_json::from_slice(payload).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to deserialize IPC response".to_string(),
+        }),
+        IpcEncoding::Bincode => {
+            let response: WireResponse = decode_bincode(payload)?;
+            Ok(IpcResponse {
+                success: response.success,
+                message: response.message,
+                data: response.data.as_deref().map(from_json).transpose()?,
+                skipped: response.skipped,
+                retry_after_ms: response.retry_after_ms,
+                code: response.code,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn capture() -> IpcMessage {
+        IpcMessage::Capture {
+            session_id: "s1".to_string(),
+            timestamp: 1_700_000_000,
+            command: "nmap -sV 10.0.0.5".to_string(),
+            output: "22/tcp open  ssh \"OpenSSH 8.9\"\n".repeat(100),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            context: Some(Box::new(CaptureContext {
+                hostname: Some("kali".to_string()),
+                env: BTreeMap::from([(
+                    "HTTP_PROXY".to_string(),
+                    "http://127.0.0.1:8080".to_string(),
+                )]),
+                ..Default::default()
+            })),
+            dry_run: false,
+            handoff: None,
+        }
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        for encoding in SUPPORTED_ENCODINGS {
+            let payload = encode_message(*encoding, &capture()).unwrap();
+            match decode_message(*encoding, &payload).unwrap() {
+                IpcMessage::Capture {
+                    output, context, ..
+                } => {
+                    assert!(output.starts_with("22/tcp"));
+                    let context = context.unwrap();
+                    assert_eq!(context.hostname.as_deref(), Some("kali"));
+                    assert_eq!(context.env.len(), 1);
+                }
+                _ => panic!("Wrong message type"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        let response = IpcResponse::success("Daemon is running")
+            .with_data(serde_json::json!({"health": {"flushes": 3}}));
+        for encoding in SUPPORTED_ENCODINGS {
+            let payload = encode_response(*encoding, &response).unwrap();
+            let decoded = decode_response(*encoding, &payload).unwrap();
+            assert!(decoded.success);
+            assert_eq!(decoded.data, response.data);
+            assert_eq!(decoded.retry_after_ms, None);
+        }
+    }
+
+    #[test]
+    fn test_bincode_smaller_for_output() {
+        let json = encode_message(IpcEncoding::Json, &capture()).unwrap();
+        let binary = encode_message(IpcEncoding::Bincode, &capture()).unwrap();
+        assert!(binary.len() < json.len());
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            negotiate(&[IpcEncoding::Bincode, IpcEncoding::Json]),
+            IpcEncoding::Bincode
+        );
+        assert_eq!(negotiate(&[IpcEncoding::Json]), IpcEncoding::Json);
+        assert_eq!(negotiate(&[]), IpcEncoding::Json);
+    }
+}
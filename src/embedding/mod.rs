@@ -13,9 +13,11 @@ mod provider;
 mod vector_index;
 
 pub use batch::{BatchItem, BatchProcessor, BatchResult};
-pub use keyword_index::{KeywordIndex, KeywordIndexError, KeywordSearchResult};
+pub use keyword_index::{
+    KeywordIndex, KeywordIndexError, KeywordSearchResult, KeywordTokenizer, KeywordTokenizers,
+};
 pub use provider::{EmbeddingError, EmbeddingProvider, FastEmbedProvider};
-pub use vector_index::{SearchResult, VectorIndex, VectorIndexError};
+pub use vector_index::{SearchResult, VectorIndex, VectorIndexError, DEFAULT_REBUILD_THRESHOLD};
 
 use serde::{Deserialize, Serialize};
 
@@ -52,6 +54,13 @@ pub struct IndexConfig {
     pub hnsw_ef_construction: usize,
     /// HNSW M parameter (number of connections per layer)
     pub hnsw_m: usize,
+    /// Share of deleted vectors (0.0 to 1.0) at which the index is rebuilt
+    #[serde(default = "default_rebuild_threshold")]
+    pub rebuild_threshold: f32,
+}
+
+fn default_rebuild_threshold() -> f32 {
+    vector_index::DEFAULT_REBUILD_THRESHOLD
 }
 
 impl Default for IndexConfig {
@@ -60,6 +69,7 @@ impl Default for IndexConfig {
             vector_dim: 384,
             hnsw_ef_construction: 200,
             hnsw_m: 16,
+            rebuild_threshold: default_rebuild_threshold(),
         }
     }
 }
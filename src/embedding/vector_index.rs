@@ -1,5 +1,7 @@
 /// HNSW vector index for similarity search
+use crate::storage::latency;
 use hnsw_rs::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 use thiserror::Error;
@@ -37,20 +39,76 @@ pub struct SearchResult {
     pub score: f32,
 }
 
+/// Default share of tombstoned vectors that triggers a rebuild
+pub const DEFAULT_REBUILD_THRESHOLD: f32 = 0.2;
+
 /// HNSW vector index wrapper
 ///
 /// Provides efficient approximate nearest neighbor search.
 /// Uses cosine similarity (dot product on normalized vectors).
+///
+/// HNSW can't remove points, so deleted IDs are tombstoned: they stay in
+/// the graph but are filtered out of searches. Re-inserting an ID adds a
+/// new point and leaves the old one behind the same way; only the newest
+/// point of an ID is ever returned. Once dead points make up
+/// `rebuild_threshold` of the index, [`VectorIndex::rebuild`] recreates the
+/// graph from the live vectors.
 pub struct VectorIndex {
     /// Inner HNSW index
     index: Arc<RwLock<Hnsw<'static, f32, DistCosine>>>,
     /// Vector dimension
     dimension: usize,
+    /// HNSW construction parameters, kept for rebuilds
+    ef_construction: usize,
+    m: usize,
     /// Index file path (for future persistence)
     #[allow(dead_code)]
     index_path: PathBuf,
-    /// Number of indexed vectors
-    count: Arc<RwLock<u64>>,
+    /// Every point in the graph and the newest one of each ID
+    points: Arc<RwLock<Points>>,
+    /// Deleted IDs still present in the graph
+    tombstones: Arc<RwLock<HashSet<u64>>>,
+    /// Share of tombstoned vectors at which a rebuild is due
+    rebuild_threshold: f32,
+}
+
+/// Points in the graph, each inserted under its own data ID
+///
+/// The data ID is the point's position in `origins`, so points superseded
+/// by a re-insert can be told apart from the current one.
+#[derive(Default)]
+struct Points {
+    /// ID each point was inserted for, by data ID
+    origins: Vec<u64>,
+    /// Data ID of the newest point of each ID, tombstoned ones included
+    latest: HashMap<u64, usize>,
+}
+
+impl Points {
+    /// Record a new point for `id`, returning its data ID
+    fn push(&mut self, id: u64) -> usize {
+        let data_id = self.origins.len();
+        self.origins.push(id);
+        self.latest.insert(id, data_id);
+        data_id
+    }
+
+    /// Whether the point is the newest of its ID
+    fn is_current(&self, data_id: usize) -> bool {
+        self.origins
+            .get(data_id)
+            .is_some_and(|id| self.latest.get(id) == Some(&data_id))
+    }
+}
+
+fn new_hnsw(dimension: usize, ef_construction: usize, m: usize) -> Hnsw<'static, f32, DistCosine> {
+    Hnsw::<f32, DistCosine>::new(
+        m,
+        dimension,
+        ef_construction,
+        200, // max_nb_connection
+        DistCosine,
+    )
 }
 
 impl VectorIndex {
@@ -72,23 +130,28 @@ impl VectorIndex {
             Self::load(index_path)
         } else {
             // Create new index
-            let index = Hnsw::<f32, DistCosine>::new(
-                m,
-                dimension,
-                ef_construction,
-                200, // max_nb_connection
-                DistCosine,
-            );
+            let index = new_hnsw(dimension, ef_construction, m);
 
             Ok(Self {
                 index: Arc::new(RwLock::new(index)),
                 dimension,
+                ef_construction,
+                m,
                 index_path,
-                count: Arc::new(RwLock::new(0)),
+                points: Arc::new(RwLock::new(Points::default())),
+                tombstones: Arc::new(RwLock::new(HashSet::new())),
+                rebuild_threshold: DEFAULT_REBUILD_THRESHOLD,
             })
         }
     }
 
+    /// Set the share of tombstoned vectors (0.0 to 1.0) at which
+    /// [`VectorIndex::needs_rebuild`] reports a rebuild is due
+    pub fn with_rebuild_threshold(mut self, threshold: f32) -> Self {
+        self.rebuild_threshold = threshold;
+        self
+    }
+
     /// Load existing index from file (not yet implemented)
     pub fn load(_index_path: PathBuf) -> Result<Self, VectorIndexError> {
         // TODO: Implement persistence with bincode or serde
@@ -121,14 +184,14 @@ impl VectorIndex {
         // Convert to owned Vec for HNSW
         let data = vector.to_vec();
 
-        // Insert into index
-        #[allow(unused_mut)]
-        let mut index = self.index.write().unwrap();
-        index.insert((&data, id as usize));
+        // Insert into index; any earlier point of the ID is superseded
+        let index = self.index.write().unwrap();
+        let mut tombstones = self.tombstones.write().unwrap();
+        let data_id = self.points.write().unwrap().push(id);
+        index.insert((&data, data_id));
 
-        // Update count
-        let mut count = self.count.write().unwrap();
-        *count += 1;
+        // A re-inserted ID is live again
+        tombstones.remove(&id);
 
         Ok(())
     }
@@ -163,26 +226,99 @@ impl VectorIndex {
             });
         }
 
+        let _timer = latency::timer("vector.search", || format!("k={} ef={}", k, ef_search));
         let index = self.index.read().unwrap();
+        let tombstones = self.tombstones.read().unwrap();
+        let points = self.points.read().unwrap();
 
-        // Perform search
-        let results = index.search(query, k, ef_search);
+        // Perform search, skipping dead points inside the graph walk so
+        // deletions and re-inserts don't cost results
+        let results = if tombstones.is_empty() && points.origins.len() == points.latest.len() {
+            index.search(query, k, ef_search)
+        } else {
+            let live = |data_id: &usize| {
+                points.is_current(*data_id) && !tombstones.contains(&points.origins[*data_id])
+            };
+            index.search_filter(query, k, ef_search, Some(&live))
+        };
 
         // Convert to SearchResult
         let search_results = results
             .into_iter()
-            .map(|neighbor| SearchResult {
-                id: neighbor.d_id as u64,
-                score: 1.0 - neighbor.distance, // Convert distance to similarity
+            .filter_map(|neighbor| {
+                Some(SearchResult {
+                    id: *points.origins.get(neighbor.d_id)?,
+                    score: 1.0 - neighbor.distance, // Convert distance to similarity
+                })
             })
             .collect();
 
         Ok(search_results)
     }
 
-    /// Get the number of vectors in the index
+    /// Tombstone IDs so searches stop returning them
+    ///
+    /// Returns how many were newly tombstoned; IDs that were never inserted
+    /// are ignored. Call [`VectorIndex::needs_rebuild`] afterwards to see
+    /// whether the graph should be rebuilt.
+    pub fn delete(&self, ids: &[u64]) -> usize {
+        // Same lock order as rebuild
+        let mut tombstones = self.tombstones.write().unwrap();
+        let points = self.points.read().unwrap();
+        ids.iter()
+            .filter(|id| points.latest.contains_key(id) && tombstones.insert(**id))
+            .count()
+    }
+
+    /// Number of tombstoned vectors still in the graph
+    pub fn tombstoned(&self) -> u64 {
+        self.tombstones.read().unwrap().len() as u64
+    }
+
+    /// Whether tombstoned and superseded points have reached the rebuild
+    /// threshold
+    pub fn needs_rebuild(&self) -> bool {
+        let total = self.points.read().unwrap().origins.len() as u64;
+        let dead = total - self.len();
+        dead > 0 && dead as f32 >= total as f32 * self.rebuild_threshold
+    }
+
+    /// Recreate the graph from its live vectors, dropping tombstoned and
+    /// superseded ones
+    ///
+    /// Searches block until the rebuild is done. Returns the number of
+    /// vectors kept.
+    pub fn rebuild(&self) -> Result<u64, VectorIndexError> {
+        let mut index = self.index.write().unwrap();
+        let mut tombstones = self.tombstones.write().unwrap();
+        let tombstoned = tombstones.len();
+        let _timer = latency::timer("vector.rebuild", || format!("tombstoned={}", tombstoned));
+
+        let rebuilt = new_hnsw(self.dimension, self.ef_construction, self.m);
+        let mut points = self.points.write().unwrap();
+        let mut kept = Points::default();
+        for point in index.get_point_indexation() {
+            let data_id = point.get_origin_id();
+            if !points.is_current(data_id) {
+                continue;
+            }
+            let id = points.origins[data_id];
+            if !tombstones.contains(&id) {
+                rebuilt.insert((point.get_v(), kept.push(id)));
+            }
+        }
+
+        *index = rebuilt;
+        tombstones.clear();
+        *points = kept;
+
+        Ok(points.origins.len() as u64)
+    }
+
+    /// Get the number of live vectors in the index
     pub fn len(&self) -> u64 {
-        *self.count.read().unwrap()
+        let ids = self.points.read().unwrap().latest.len() as u64;
+        ids.saturating_sub(self.tombstoned())
     }
 
     /// Check if index is empty
@@ -198,16 +334,10 @@ impl VectorIndex {
     /// Clear the index (remove all vectors)
     pub fn clear(&self) -> Result<(), VectorIndexError> {
         let mut index = self.index.write().unwrap();
-        *index = Hnsw::<f32, DistCosine>::new(
-            16, // default M
-            self.dimension,
-            200, // default ef_construction
-            200, // max_nb_connection
-            DistCosine,
-        );
+        *index = new_hnsw(self.dimension, self.ef_construction, self.m);
 
-        let mut count = self.count.write().unwrap();
-        *count = 0;
+        self.tombstones.write().unwrap().clear();
+        *self.points.write().unwrap() = Points::default();
 
         Ok(())
     }
@@ -303,6 +433,78 @@ mod tests {
         assert_eq!(index.len(), 10);
     }
 
+    #[test]
+    fn test_delete_and_rebuild() {
+        let temp = TempDir::new().unwrap();
+        let index_path = temp.path().join("test.hnsw");
+
+        let index = VectorIndex::new(8, 200, 16, index_path)
+            .unwrap()
+            .with_rebuild_threshold(0.5);
+
+        let items: Vec<(u64, Vec<f32>)> = (0..4)
+            .map(|i| {
+                let mut vec = vec![0.1; 8];
+                vec[i] = 1.0;
+                (i as u64, vec)
+            })
+            .collect();
+        index.insert_batch(&items).unwrap();
+
+        // IDs never inserted aren't tombstoned
+        assert_eq!(index.delete(&[42]), 0);
+        assert_eq!((index.len(), index.tombstoned()), (4, 0));
+
+        // Deleted IDs vanish from results
+        assert_eq!(index.delete(&[0, 0]), 1);
+        assert_eq!(index.len(), 3);
+        assert!(!index.needs_rebuild());
+        let results = index.search(&items[0].1, 3, 50).unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.id != 0));
+
+        assert_eq!(index.delete(&[1]), 1);
+        assert!(index.needs_rebuild());
+        assert_eq!(index.rebuild().unwrap(), 2);
+        assert_eq!((index.len(), index.tombstoned()), (2, 0));
+        let results = index.search(&items[0].1, 4, 50).unwrap();
+        assert!(results.iter().all(|r| r.id == 2 || r.id == 3));
+    }
+
+    #[test]
+    fn test_reinsert_replaces_vector() {
+        let temp = TempDir::new().unwrap();
+        let index_path = temp.path().join("test.hnsw");
+
+        let index = VectorIndex::new(4, 200, 16, index_path).unwrap();
+        let old = [1.0, 0.0, 0.0, 0.0];
+        let new = [0.0, 1.0, 0.0, 0.0];
+        index.insert(1, &old).unwrap();
+        index.insert(2, &[0.0, 0.0, 1.0, 0.0]).unwrap();
+
+        // Re-embedded: deleted, then inserted with a different vector
+        assert_eq!(index.delete(&[1]), 1);
+        index.insert(1, &new).unwrap();
+        assert_eq!(index.len(), 2);
+        assert!(index.needs_rebuild());
+
+        let check = |index: &VectorIndex| {
+            // Only the new vector answers for ID 1
+            let results = index.search(&old, 3, 50).unwrap();
+            let hit = results.iter().find(|r| r.id == 1).unwrap();
+            assert!(hit.score < 0.5);
+            assert_eq!(results.iter().filter(|r| r.id == 1).count(), 1);
+            let results = index.search(&new, 1, 50).unwrap();
+            assert_eq!(results[0].id, 1);
+            assert!(results[0].score > 0.99);
+        };
+        check(&index);
+
+        assert_eq!(index.rebuild().unwrap(), 2);
+        assert!(!index.needs_rebuild());
+        check(&index);
+    }
+
     #[test]
     fn test_dimension_validation() {
         let temp = TempDir::new().unwrap();
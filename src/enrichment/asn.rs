@@ -0,0 +1,393 @@
+//! ASN, organisation and country of public addresses
+//!
+//! External engagements find addresses that belong to someone: a CDN, a
+//! cloud provider, the client's own AS. A local ip2asn database maps each
+//! public address to the AS announcing it, so `yinx hosts` can say whose
+//! it is and `yinx query --asn/--country/--org` can keep to one network.
+//! Nothing is sent anywhere; the database is read once, the first time the
+//! pass runs, and re-annotates every address when it's newer than they are.
+
+use crate::config::EnrichmentConfig;
+use crate::daemon::expand_tilde;
+use crate::entities::AsnInfo;
+use crate::error::{Result, YinxError};
+use crate::storage::StorageManager;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Address ranges and the network announcing each
+#[derive(Debug, Default)]
+pub struct AsnDatabase {
+    v4: Vec<(u32, u32, usize)>,
+    v6: Vec<(u128, u128, usize)>,
+    /// Networks the ranges point into, each stored once
+    networks: Vec<AsnInfo>,
+    /// When the database file was last modified
+    modified: i64,
+}
+
+impl AsnDatabase {
+    /// Load an ip2asn-style TSV, gzipped when the name ends in `.gz`
+    pub fn load(path: &Path) -> Result<Self> {
+        let io_error = |e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read ASN database {}", path.display()),
+        };
+        let file = std::fs::File::open(path).map_err(io_error)?;
+        let modified = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs() as i64);
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|e| e == "gz") {
+            Box::new(flate2::read::GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        let mut database = Self::parse(BufReader::new(reader)).map_err(io_error)?;
+        database.modified = modified;
+        Ok(database)
+    }
+
+    /// Read `start end asn country description` lines
+    ///
+    /// Lines that don't parse and ranges announced by no AS (AS 0) are
+    /// skipped.
+    pub fn parse(reader: impl BufRead) -> std::io::Result<Self> {
+        let mut database = Self::default();
+        let mut interned: HashMap<AsnInfo, usize> = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            let mut fields = line.splitn(5, '\t');
+            let (Some(start), Some(end), Some(asn), Some(country)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let Ok(asn) = asn.trim().trim_start_matches("AS").parse::<u32>() else {
+                continue;
+            };
+            if asn == 0 {
+                continue;
+            }
+            let info = AsnInfo {
+                asn,
+                org: fields.next().unwrap_or_default().trim().to_string(),
+                country: match country.trim() {
+                    "None" => String::new(),
+                    country => country.to_string(),
+                },
+            };
+            let next = database.networks.len();
+            let network = *interned.entry(info.clone()).or_insert(next);
+            if network == next {
+                database.networks.push(info);
+            }
+            match (start.trim().parse(), end.trim().parse()) {
+                (Ok(IpAddr::V4(start)), Ok(IpAddr::V4(end))) => {
+                    database
+                        .v4
+                        .push((u32::from(start), u32::from(end), network))
+                }
+                (Ok(IpAddr::V6(start)), Ok(IpAddr::V6(end))) => {
+                    database
+                        .v6
+                        .push((u128::from(start), u128::from(end), network))
+                }
+                _ => {}
+            }
+        }
+        database.v4.sort_unstable();
+        database.v6.sort_unstable();
+        Ok(database)
+    }
+
+    /// The network announcing `ip`
+    pub fn lookup(&self, ip: IpAddr) -> Option<&AsnInfo> {
+        let network = match ip {
+            IpAddr::V4(ip) => find(&self.v4, u32::from(ip)),
+            IpAddr::V6(ip) => find(&self.v6, u128::from(ip)),
+        }?;
+        self.networks.get(network)
+    }
+
+    pub fn len(&self) -> usize {
+        self.v4.len() + self.v6.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The range in `ranges`, sorted by start, that holds `ip`
+fn find<T: Ord + Copy>(ranges: &[(T, T, usize)], ip: T) -> Option<usize> {
+    let after = ranges.partition_point(|&(start, _, _)| start <= ip);
+    let &(_, end, network) = ranges[..after].last()?;
+    (ip <= end).then_some(network)
+}
+
+/// Whether `ip` is routed on the internet, so a database could know it
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                // Benchmarking, 198.18.0.0/15
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (first & 0xffc0) == 0xfe80
+                // Documentation, 2001:db8::/32
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// Annotates public addresses from a local database; the default annotates
+/// nothing
+#[derive(Debug, Default)]
+pub struct AsnEnricher {
+    path: Option<PathBuf>,
+    batch_size: usize,
+    /// Loaded the first time it's needed; None when it failed to load
+    database: OnceLock<Option<AsnDatabase>>,
+}
+
+impl AsnEnricher {
+    pub fn new(config: &EnrichmentConfig) -> Self {
+        Self {
+            path: config.asn.database.as_deref().map(expand_tilde),
+            batch_size: config.asn.batch_size,
+            database: OnceLock::new(),
+        }
+    }
+
+    /// Annotate from an already loaded database
+    pub fn with_database(database: AsnDatabase, batch_size: usize) -> Self {
+        Self {
+            path: None,
+            batch_size,
+            database: OnceLock::from(Some(database)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.path.is_some() || self.database.get().is_some_and(Option::is_some)
+    }
+
+    fn database(&self) -> Option<&AsnDatabase> {
+        self.database
+            .get_or_init(|| {
+                let path = self.path.as_ref()?;
+                match AsnDatabase::load(path) {
+                    Ok(database) => {
+                        tracing::info!(
+                            "Loaded {} ranges from ASN database {}",
+                            database.len(),
+                            path.display()
+                        );
+                        Some(database)
+                    }
+                    Err(e) => {
+                        tracing::warn!("ASN enrichment disabled: {}", e);
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+}
+
+/// Annotate the next batch of addresses that are due, as of `now`
+///
+/// Returns the number of addresses recorded, with or without a network.
+pub fn annotate_pending(
+    storage: &StorageManager,
+    enricher: &AsnEnricher,
+    now: i64,
+) -> Result<usize> {
+    let Some(database) = enricher.database() else {
+        return Ok(0);
+    };
+    let pending = storage.asn_candidates(database.modified, enricher.batch_size)?;
+    for ip in &pending {
+        let asn = match ip.parse::<IpAddr>() {
+            Ok(address) if is_public(address) => database.lookup(address),
+            _ => None,
+        };
+        storage.store_asn(ip, asn, now.max(database.modified))?;
+    }
+    Ok(pending.len())
+}
+
+/// `yinx query` restriction to one network
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsnFilter {
+    pub asn: Option<u32>,
+    /// Country code, any case
+    pub country: Option<String>,
+    /// Part of the organisation's name, any case
+    pub org: Option<String>,
+}
+
+impl AsnFilter {
+    /// Parse `AS13335` or `13335`
+    pub fn parse_asn(asn: &str) -> Option<u32> {
+        let asn = asn.trim();
+        let digits = asn
+            .strip_prefix("AS")
+            .or_else(|| asn.strip_prefix("as"))
+            .unwrap_or(asn);
+        digits.parse().ok()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.asn.is_none() && self.country.is_none() && self.org.is_none()
+    }
+
+    pub fn matches(&self, info: &AsnInfo) -> bool {
+        self.asn.is_none_or(|asn| asn == info.asn)
+            && self
+                .country
+                .as_ref()
+                .is_none_or(|country| country.eq_ignore_ascii_case(&info.country))
+            && self.org.as_ref().is_none_or(|org| {
+                info.org
+                    .to_ascii_lowercase()
+                    .contains(&org.to_ascii_lowercase())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+    use tempfile::TempDir;
+
+    const DATABASE: &str = "1.0.0.0\t1.0.0.255\t13335\tUS\tCLOUDFLARENET\n\
+        1.0.1.0\t1.0.3.255\t0\tNone\tNot routed\n\
+        8.8.8.0\t8.8.8.255\t15169\tUS\tGOOGLE\n\
+        2606:4700::\t2606:4700:ffff:ffff:ffff:ffff:ffff:ffff\t13335\tUS\tCLOUDFLARENET\n";
+
+    #[test]
+    fn test_lookup() {
+        let database = AsnDatabase::parse(DATABASE.as_bytes()).unwrap();
+        assert_eq!(database.len(), 3);
+        assert_eq!(database.networks.len(), 2);
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(database.lookup(ip("1.0.0.1")).unwrap().asn, 13335);
+        assert_eq!(database.lookup(ip("8.8.8.8")).unwrap().org, "GOOGLE");
+        assert_eq!(database.lookup(ip("2606:4700::1111")).unwrap().asn, 13335);
+        assert!(database.lookup(ip("1.0.2.1")).is_none());
+        assert!(database.lookup(ip("9.9.9.9")).is_none());
+        assert!(database.lookup(ip("0.0.0.1")).is_none());
+    }
+
+    #[test]
+    fn test_is_public() {
+        for public in ["8.8.8.8", "1.1.1.1", "2606:4700::1111"] {
+            assert!(is_public(public.parse().unwrap()), "{}", public);
+        }
+        for private in [
+            "10.10.10.3",
+            "192.168.1.1",
+            "172.16.0.1",
+            "127.0.0.1",
+            "100.64.0.1",
+            "169.254.1.1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public(private.parse().unwrap()), "{}", private);
+        }
+    }
+
+    #[test]
+    fn test_annotate_pending() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "s1", 0, "active")
+            .unwrap();
+        let conn = storage.database.get_conn().unwrap();
+        for value in ["8.8.8.8", "10.0.0.5"] {
+            conn.execute(
+                "INSERT INTO session_entities (session_id, type, value, occurrences, captures,
+                    max_confidence, first_seen, last_seen)
+                 VALUES ('s1', 'ip_address', ?1, 1, 1, 1.0, 0, 0)",
+                params![value],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        assert!(!AsnEnricher::default().is_enabled());
+        assert_eq!(
+            annotate_pending(&storage, &AsnEnricher::default(), 100).unwrap(),
+            0
+        );
+
+        let database = AsnDatabase::parse(DATABASE.as_bytes()).unwrap();
+        let enricher = AsnEnricher::with_database(database, 10);
+        assert!(enricher.is_enabled());
+        assert_eq!(annotate_pending(&storage, &enricher, 100).unwrap(), 2);
+        assert_eq!(annotate_pending(&storage, &enricher, 200).unwrap(), 0);
+
+        let annotations = storage.asn_annotations().unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations["8.8.8.8"].to_string(), "AS15169 GOOGLE (US)");
+    }
+
+    #[test]
+    fn test_filter() {
+        let google = AsnInfo {
+            asn: 15169,
+            org: "GOOGLE".to_string(),
+            country: "US".to_string(),
+        };
+        assert_eq!(AsnFilter::parse_asn("AS15169"), Some(15169));
+        assert_eq!(AsnFilter::parse_asn("15169"), Some(15169));
+        assert_eq!(AsnFilter::parse_asn("google"), None);
+        assert!(AsnFilter::default().matches(&google));
+        let filter = AsnFilter {
+            country: Some("us".to_string()),
+            org: Some("goog".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&google));
+        let filter = AsnFilter {
+            asn: Some(13335),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&google));
+    }
+}
@@ -0,0 +1,282 @@
+//! Forward and reverse DNS of discovered hosts
+//!
+//! Hostnames are resolved to addresses and addresses back to their PTR
+//! names through the system resolver, so `/etc/hosts` entries and the VPN's
+//! DNS server both count. A name that resolves nowhere is still recorded,
+//! with no answers, so it isn't retried until the refresh period passes.
+
+use crate::config::EnrichmentConfig;
+use crate::error::Result;
+use crate::storage::{DnsLookup, LookupKind, StorageManager};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::Duration;
+
+/// Last labels that mark a "hostname" as a file name picked up from output
+const FILE_EXTENSIONS: &[&str] = &[
+    "asp", "aspx", "bak", "conf", "css", "dll", "exe", "gif", "htm", "html", "jpg", "js", "json",
+    "jsp", "log", "php", "png", "py", "sh", "txt", "xml", "zip",
+];
+
+/// Where lookups are sent; tests answer from a table instead
+pub trait Resolver {
+    /// Addresses `name` resolves to
+    fn forward(&self, name: &str) -> Vec<IpAddr>;
+    /// Names `ip` resolves back to
+    fn reverse(&self, ip: IpAddr) -> Vec<String>;
+}
+
+/// The operating system's resolver
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn forward(&self, name: &str) -> Vec<IpAddr> {
+        match (name, 0).to_socket_addrs() {
+            Ok(addrs) => {
+                let mut ips: Vec<IpAddr> = addrs.map(|a| a.ip()).collect();
+                ips.sort();
+                ips.dedup();
+                ips
+            }
+            Err(e) => {
+                tracing::debug!("Failed to resolve {}: {}", name, e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn reverse(&self, ip: IpAddr) -> Vec<String> {
+        reverse_lookup(ip).into_iter().collect()
+    }
+}
+
+#[cfg(unix)]
+fn reverse_lookup(ip: IpAddr) -> Option<String> {
+    use nix::sys::socket::{SockaddrLike, SockaddrStorage};
+    use std::ffi::CStr;
+
+    // NI_MAXHOST, whose type differs between platforms
+    const MAX_HOST: usize = 1025;
+
+    let addr = SockaddrStorage::from(std::net::SocketAddr::new(ip, 0));
+    let mut host = [0 as libc::c_char; MAX_HOST];
+    // SAFETY: `addr` is a valid socket address of `addr.len()` bytes and
+    // `host` a writable buffer of the length given; getnameinfo
+    // nul-terminates what it writes there
+    let status = unsafe {
+        libc::getnameinfo(
+            addr.as_ptr(),
+            addr.len(),
+            host.as_mut_ptr(),
+            MAX_HOST as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if status != 0 {
+        return None;
+    }
+    // SAFETY: getnameinfo succeeded, so `host` holds a nul-terminated name
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(unix))]
+fn reverse_lookup(_ip: IpAddr) -> Option<String> {
+    None
+}
+
+/// Looks up discovered hosts in the background; the default looks up nothing
+#[derive(Debug, Clone, Default)]
+pub struct DnsEnricher {
+    enabled: bool,
+    batch_size: usize,
+    refresh: Duration,
+}
+
+impl DnsEnricher {
+    pub fn new(config: &EnrichmentConfig) -> Self {
+        Self {
+            enabled: config.dns.enabled && !config.offline,
+            batch_size: config.dns.batch_size,
+            refresh: crate::config::parse_duration(&config.dns.refresh).unwrap_or_default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// Whether looking up `value` could tell us anything about a target
+fn worth_resolving(kind: LookupKind, value: &str) -> bool {
+    match kind {
+        LookupKind::Reverse => value
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| !ip.is_loopback() && !ip.is_unspecified() && !ip.is_multicast()),
+        LookupKind::Forward => {
+            let name = value.trim_end_matches('.').to_ascii_lowercase();
+            let last = name.rsplit('.').next().unwrap_or_default();
+            !name.is_empty()
+                && name != "localhost"
+                && !name.contains(['*', '/', ' '])
+                && !last.chars().all(|c| c.is_ascii_digit())
+                && !FILE_EXTENSIONS.contains(&last)
+        }
+    }
+}
+
+/// Look up the next batch of hosts that are due, as of `now`
+///
+/// Returns the number of lookups recorded.
+pub fn resolve_pending(
+    storage: &StorageManager,
+    enricher: &DnsEnricher,
+    resolver: &dyn Resolver,
+    now: i64,
+) -> Result<usize> {
+    if !enricher.is_enabled() {
+        return Ok(0);
+    }
+    let stale_before = now.saturating_sub(enricher.refresh.as_secs() as i64);
+    let pending = storage.dns_candidates(stale_before, enricher.batch_size)?;
+    for (kind, name) in &pending {
+        let answers = if !worth_resolving(*kind, name) {
+            Vec::new()
+        } else {
+            match kind {
+                LookupKind::Forward => resolver
+                    .forward(name)
+                    .into_iter()
+                    .map(|ip| ip.to_string())
+                    .collect(),
+                LookupKind::Reverse => match name.parse() {
+                    Ok(ip) => resolver.reverse(ip),
+                    Err(_) => Vec::new(),
+                },
+            }
+        };
+        storage.store_dns_lookup(&DnsLookup {
+            name: name.clone(),
+            kind: *kind,
+            answers,
+            resolved_at: now,
+        })?;
+    }
+    Ok(pending.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DnsEnrichmentConfig, EnrichmentConfig};
+    use rusqlite::params;
+    use std::cell::RefCell;
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct TableResolver {
+        asked: RefCell<Vec<String>>,
+    }
+
+    impl Resolver for TableResolver {
+        fn forward(&self, name: &str) -> Vec<IpAddr> {
+            self.asked.borrow_mut().push(name.to_string());
+            match name {
+                "dc01.corp.local" => vec!["10.0.0.5".parse().unwrap()],
+                _ => Vec::new(),
+            }
+        }
+
+        fn reverse(&self, ip: IpAddr) -> Vec<String> {
+            self.asked.borrow_mut().push(ip.to_string());
+            match ip.to_string().as_str() {
+                "10.0.0.5" => vec!["dc01.corp.local".to_string()],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    fn enabled() -> EnrichmentConfig {
+        EnrichmentConfig {
+            offline: false,
+            dns: DnsEnrichmentConfig {
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_worth_resolving() {
+        assert!(worth_resolving(LookupKind::Forward, "dc01.corp.local"));
+        assert!(worth_resolving(LookupKind::Forward, "DC01"));
+        assert!(!worth_resolving(LookupKind::Forward, "index.php"));
+        assert!(!worth_resolving(LookupKind::Forward, "localhost"));
+        assert!(!worth_resolving(LookupKind::Forward, "*.corp.local"));
+        assert!(worth_resolving(LookupKind::Reverse, "10.0.0.5"));
+        assert!(!worth_resolving(LookupKind::Reverse, "127.0.0.1"));
+        assert!(!worth_resolving(LookupKind::Reverse, "10.0.0"));
+    }
+
+    #[test]
+    fn test_offline_disables_lookups() {
+        let mut config = enabled();
+        assert!(DnsEnricher::new(&config).is_enabled());
+        config.offline = true;
+        assert!(!DnsEnricher::new(&config).is_enabled());
+        assert!(!DnsEnricher::new(&EnrichmentConfig::default()).is_enabled());
+    }
+
+    #[test]
+    fn test_resolve_pending() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "s1", 0, "active")
+            .unwrap();
+        let conn = storage.database.get_conn().unwrap();
+        for (entity_type, value) in [
+            ("hostname", "dc01.corp.local"),
+            ("hostname", "backup.zip"),
+            ("ip_address", "10.0.0.5"),
+            ("ip_address", "10.0.0.9"),
+        ] {
+            conn.execute(
+                "INSERT INTO session_entities (session_id, type, value, occurrences, captures,
+                    max_confidence, first_seen, last_seen)
+                 VALUES ('s1', ?1, ?2, 1, 1, 1.0, 0, 0)",
+                params![entity_type, value],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let enricher = DnsEnricher::new(&enabled());
+        let resolver = TableResolver::default();
+        assert_eq!(
+            resolve_pending(&storage, &enricher, &resolver, 1_000).unwrap(),
+            4
+        );
+        // The file name was recorded without being looked up
+        assert_eq!(resolver.asked.borrow().len(), 3);
+        assert!(!resolver.asked.borrow().contains(&"backup.zip".to_string()));
+
+        let lookups = storage.dns_lookups().unwrap();
+        assert_eq!(lookups.len(), 2);
+        assert!(lookups.iter().all(|l| l.resolved_at == 1_000));
+
+        // Nothing is due again until the refresh period passes
+        assert_eq!(
+            resolve_pending(&storage, &enricher, &resolver, 2_000).unwrap(),
+            0
+        );
+        assert_eq!(
+            resolve_pending(&storage, &enricher, &resolver, 1_000 + 86_401).unwrap(),
+            4
+        );
+    }
+}
@@ -0,0 +1,13 @@
+//! Background enrichment of discovered hosts
+//!
+//! Captures only know what the tools printed. Enrichment passes run by the
+//! daemon between flushes add what can be looked up about the hosts they
+//! found, store it beside the entities, and leave the correlation graph to
+//! fold it in when it's replayed. Every pass is off by default and none
+//! makes a network request while `[enrichment] offline` is set.
+
+pub mod asn;
+pub mod dns;
+
+pub use asn::{annotate_pending, is_public, AsnDatabase, AsnEnricher, AsnFilter};
+pub use dns::{resolve_pending, DnsEnricher, Resolver, SystemResolver};
@@ -2,7 +2,7 @@
 //!
 //! Provides configuration-driven entity extraction with ZERO hardcoded patterns
 
-use crate::patterns::{ExtractedEntity, PatternRegistry};
+use crate::patterns::{CompiledToolMatcher, ExtractedEntity, PatternRegistry};
 use serde::{Deserialize, Serialize};
 
 /// Extracted entity with metadata
@@ -14,6 +14,11 @@ pub struct Entity {
     pub value: String,
     /// Context surrounding the entity
     pub context: String,
+    /// Byte range of the value in the text it was extracted from
+    #[serde(default)]
+    pub start: usize,
+    #[serde(default)]
+    pub end: usize,
     /// Confidence score (0.0 - 1.0)
     pub confidence: f32,
     /// Whether this entity should be redacted in reports
@@ -26,6 +31,8 @@ impl From<ExtractedEntity> for Entity {
             entity_type: extracted.type_name,
             value: extracted.value,
             context: extracted.context,
+            start: extracted.start,
+            end: extracted.end,
             confidence: extracted.confidence,
             should_redact: extracted.redact,
         }
@@ -57,6 +64,18 @@ impl EntityExtractor {
             .collect()
     }
 
+    /// Extract entities from a detected tool's output
+    ///
+    /// The tool's hinted entity types are matched first; see
+    /// [`PatternRegistry::extract_entities_for_tool`].
+    pub fn extract_for_tool(&self, text: &str, tool: Option<&CompiledToolMatcher>) -> Vec<Entity> {
+        self.registry
+            .extract_entities_for_tool(text, tool)
+            .into_iter()
+            .map(Entity::from)
+            .collect()
+    }
+
     /// Extract entities by specific type
     ///
     /// Example types: "ip_address", "cve", "credential_password"
@@ -148,6 +167,7 @@ mod tests {
                 score_threshold_percentile: 0.8,
                 technical_patterns: vec![],
                 max_technical_score: 10.0,
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -156,6 +176,7 @@ mod tests {
                 cluster_patterns: vec![],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         let registry =
@@ -4,17 +4,147 @@
 
 use crate::entities::Entity;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Port states nmap and similar tools print after the port
+const PORT_STATES: &[&str] = &[
+    "open",
+    "closed",
+    "filtered",
+    "unfiltered",
+    "open|filtered",
+    "closed|filtered",
+];
+
+/// Observations less confident than this don't overturn a more confident
+/// earlier one
+pub const WEAK_EVIDENCE: f32 = 0.5;
+
+/// A value seen over a run of captures that agreed on it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    pub value: String,
+    /// Highest confidence among the captures that saw it
+    pub confidence: f32,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+/// Every value one fact about a host has been seen with, oldest first
+///
+/// Captures disagree: a port is open in one scan and closed in the next, a
+/// service is upgraded between visits. Rather than let the last capture
+/// win, each change is kept so the disagreement can be shown and the value
+/// that holds chosen by time and confidence.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct History(Vec<Observation>);
+
+impl History {
+    /// Record `value` as seen at `timestamp`
+    pub fn observe(&mut self, value: &str, confidence: f32, timestamp: i64) {
+        let at = self.0.partition_point(|o| o.first_seen <= timestamp);
+        if let Some(previous) = at.checked_sub(1).map(|i| &mut self.0[i]) {
+            if previous.value == value {
+                previous.confidence = previous.confidence.max(confidence);
+                previous.last_seen = previous.last_seen.max(timestamp);
+                return;
+            }
+        }
+        self.0.insert(
+            at,
+            Observation {
+                value: value.to_string(),
+                confidence,
+                first_seen: timestamp,
+                last_seen: timestamp,
+            },
+        );
+    }
+
+    pub fn observations(&self) -> &[Observation] {
+        &self.0
+    }
+
+    /// The value that holds: the latest one, unless it is weak evidence
+    /// against a more confident value before it
+    pub fn resolved(&self) -> Option<&Observation> {
+        self.0.iter().fold(None, |current, next| match current {
+            Some(current)
+                if next.confidence < WEAK_EVIDENCE && next.confidence < current.confidence =>
+            {
+                Some(current)
+            }
+            _ => Some(next),
+        })
+    }
+
+    /// Whether captures disagreed about the value
+    pub fn is_conflicted(&self) -> bool {
+        self.0.iter().any(|o| o.value != self.0[0].value)
+    }
+}
+
+/// A fact about a host that captures disagreed on
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict<'a> {
+    /// What the fact is about, e.g. `port 22` or `Apache`
+    pub subject: String,
+    /// state, service, version, os, mac or vendor
+    pub kind: &'static str,
+    /// The value that holds
+    pub resolved: &'a Observation,
+    pub history: &'a [Observation],
+}
+
+/// Who routes a public address, from a local ASN database
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AsnInfo {
+    pub asn: u32,
+    /// Name the AS is registered under, e.g. `CLOUDFLARENET`
+    pub org: String,
+    /// ISO 3166 country code
+    pub country: String,
+}
+
+impl std::fmt::Display for AsnInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AS{} {}", self.asn, self.org)?;
+        if !self.country.is_empty() {
+            write!(f, " ({})", self.country)?;
+        }
+        Ok(())
+    }
+}
 
 /// Information about a discovered host
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HostInfo {
     /// Host identifier (IP or hostname)
     pub identifier: String,
-    /// Open ports discovered
-    pub ports: HashSet<u16>,
-    /// Services running on the host
-    pub services: HashMap<u16, String>,
+    /// State of each port seen: open, closed, filtered...
+    pub ports: BTreeMap<u16, History>,
+    /// Service and banner seen on each port, e.g. `ssh OpenSSH 8.2p1`
+    pub services: BTreeMap<u16, History>,
+    /// Versions seen of each product, e.g. `Apache` → `2.4.41`
+    pub products: BTreeMap<String, History>,
+    /// Operating system scans identified or guessed
+    #[serde(default)]
+    pub os: History,
+    #[serde(default)]
+    pub mac: History,
+    /// Network card vendor of the MAC address
+    #[serde(default)]
+    pub vendor: History,
+    /// Raw service banners, e.g. `SSH-2.0-OpenSSH_8.2p1`
+    #[serde(default)]
+    pub banners: BTreeSet<String>,
+    /// Names or addresses DNS resolved it to or from, with when it was looked up
+    #[serde(default)]
+    pub aliases: BTreeMap<String, i64>,
+    /// Network a public address belongs to
+    #[serde(default)]
+    pub asn: Option<AsnInfo>,
     /// Vulnerabilities found
     pub vulnerabilities: HashSet<String>,
     /// Credentials discovered
@@ -32,8 +162,15 @@ impl HostInfo {
     pub fn new(identifier: String, timestamp: i64) -> Self {
         Self {
             identifier,
-            ports: HashSet::new(),
-            services: HashMap::new(),
+            ports: BTreeMap::new(),
+            services: BTreeMap::new(),
+            products: BTreeMap::new(),
+            os: History::default(),
+            mac: History::default(),
+            vendor: History::default(),
+            banners: BTreeSet::new(),
+            aliases: BTreeMap::new(),
+            asn: None,
             vulnerabilities: HashSet::new(),
             credentials: Vec::new(),
             paths: HashSet::new(),
@@ -47,17 +184,93 @@ impl HostInfo {
         if timestamp > self.last_seen {
             self.last_seen = timestamp;
         }
+        if timestamp < self.first_seen {
+            self.first_seen = timestamp;
+        }
     }
 
-    /// Add a port
-    pub fn add_port(&mut self, port: u16) {
-        self.ports.insert(port);
+    /// Record a port's state
+    pub fn observe_port(&mut self, port: u16, state: &str, confidence: f32, timestamp: i64) {
+        self.ports
+            .entry(port)
+            .or_default()
+            .observe(state, confidence, timestamp);
     }
 
-    /// Add a service
-    pub fn add_service(&mut self, port: u16, service: String) {
-        self.ports.insert(port);
-        self.services.insert(port, service);
+    /// Record the service on a port
+    pub fn observe_service(&mut self, port: u16, service: &str, confidence: f32, timestamp: i64) {
+        self.services
+            .entry(port)
+            .or_default()
+            .observe(service, confidence, timestamp);
+    }
+
+    /// Record a product version
+    pub fn observe_product(
+        &mut self,
+        product: &str,
+        version: &str,
+        confidence: f32,
+        timestamp: i64,
+    ) {
+        self.products
+            .entry(product.to_string())
+            .or_default()
+            .observe(version, confidence, timestamp);
+    }
+
+    /// Ports whose resolved state is open, in order
+    pub fn open_ports(&self) -> Vec<u16> {
+        self.ports
+            .iter()
+            .filter(|(_, history)| history.resolved().is_some_and(|o| o.value == "open"))
+            .map(|(port, _)| *port)
+            .collect()
+    }
+
+    /// The resolved service on a port
+    pub fn service(&self, port: u16) -> Option<&str> {
+        self.services
+            .get(&port)
+            .and_then(History::resolved)
+            .map(|o| o.value.as_str())
+    }
+
+    /// Facts captures disagreed on, ports first
+    pub fn conflicts(&self) -> Vec<Conflict<'_>> {
+        let ports = self
+            .ports
+            .iter()
+            .map(|(port, history)| (format!("port {}", port), "state", history));
+        let services = self
+            .services
+            .iter()
+            .map(|(port, history)| (format!("port {}", port), "service", history));
+        let products = self
+            .products
+            .iter()
+            .map(|(product, history)| (product.clone(), "version", history));
+        let fingerprint = [
+            ("os", &self.os),
+            ("mac", &self.mac),
+            ("vendor", &self.vendor),
+        ]
+        .into_iter()
+        .map(|(kind, history)| ("host".to_string(), kind, history));
+        ports
+            .chain(services)
+            .chain(products)
+            .chain(fingerprint)
+            .filter(|(_, _, history)| history.is_conflicted())
+            .filter_map(|(subject, kind, history)| {
+                Some(Conflict {
+                    subject,
+                    kind,
+                    resolved: history.resolved()?,
+                    history: history.observations(),
+                })
+            })
+            .collect()
     }
 
     /// Add a vulnerability
@@ -81,8 +294,9 @@ impl HostInfo {
 pub struct ServiceInfo {
     /// Service name
     pub name: String,
-    /// Hosts running this service
-    pub hosts: HashSet<String>,
+    /// Hosts running this service, weighted by the highest confidence of
+    /// the evidence linking them
+    pub hosts: HashMap<String, f32>,
     /// Versions seen
     pub versions: HashSet<String>,
     /// Vulnerabilities associated with this service
@@ -94,15 +308,16 @@ impl ServiceInfo {
     pub fn new(name: String) -> Self {
         Self {
             name,
-            hosts: HashSet::new(),
+            hosts: HashMap::new(),
             versions: HashSet::new(),
             vulnerabilities: HashSet::new(),
         }
     }
 
     /// Add a host running this service
-    pub fn add_host(&mut self, host: String) {
-        self.hosts.insert(host);
+    pub fn add_host(&mut self, host: String, confidence: f32) {
+        let weight = self.hosts.entry(host).or_insert(confidence);
+        *weight = weight.max(confidence);
     }
 
     /// Add a version
@@ -138,7 +353,10 @@ impl CorrelationGraph {
 
     /// Process entities and update graph
     ///
-    /// Correlates entities extracted from the same context
+    /// Correlates entities extracted from the same context, seen at
+    /// `timestamp`. Port states, services and versions are recorded as
+    /// observations weighted by each entity's confidence, so captures
+    /// processed out of order still resolve to the right value.
     pub fn process_entities(&mut self, entities: &[Entity], timestamp: i64) {
         // Extract hosts (IPs and hostnames)
         let hosts: Vec<&Entity> = entities
@@ -174,6 +392,37 @@ impl CorrelationGraph {
             .filter(|e| e.entity_type == "file_path_unix" || e.entity_type == "file_path_windows")
             .collect();
 
+        // Fingerprints only tell one machine apart from another when the
+        // entities are about one address
+        let addresses: HashSet<&str> = hosts
+            .iter()
+            .filter(|e| e.entity_type == "ip_address")
+            .map(|e| e.value.as_str())
+            .collect();
+        let fingerprints: Vec<&Entity> = if addresses.len() <= 1 {
+            entities
+                .iter()
+                .filter(|e| {
+                    matches!(
+                        e.entity_type.as_str(),
+                        "os_guess" | "mac_address" | "mac_vendor" | "banner"
+                    )
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        // Of the OS lines in one capture, the most confident and then the
+        // most specific stands for it
+        let os = fingerprints
+            .iter()
+            .filter(|e| e.entity_type == "os_guess")
+            .max_by(|a, b| {
+                a.confidence
+                    .total_cmp(&b.confidence)
+                    .then(a.value.len().cmp(&b.value.len()))
+            });
+
         // Process each host
         for host_entity in &hosts {
             let host_id = &host_entity.value;
@@ -184,10 +433,24 @@ impl CorrelationGraph {
 
             host_info.update_timestamp(timestamp);
 
-            // Add ports
+            // Add ports; a port mentioned without a state is only weak
+            // evidence that it's open
             for port_entity in &ports {
                 if let Some(port) = Self::parse_port(&port_entity.value) {
-                    host_info.add_port(port);
+                    let (state, service) = Self::parse_port_line(port_entity);
+                    let confidence = match state {
+                        Some(_) => port_entity.confidence,
+                        None => port_entity.confidence * 0.5,
+                    };
+                    host_info.observe_port(port, state.unwrap_or("open"), confidence, timestamp);
+                    if let Some(service) = service {
+                        host_info.observe_service(
+                            port,
+                            &service,
+                            port_entity.confidence,
+                            timestamp,
+                        );
+                    }
                 }
             }
 
@@ -195,20 +458,48 @@ impl CorrelationGraph {
             for service_entity in &services {
                 if let Some((service_name, version)) = Self::parse_service(&service_entity.value) {
                     // Add to host
-                    if let Some(first_port) = host_info.ports.iter().next().copied() {
-                        host_info.add_service(first_port, service_name.clone());
-                    }
+                    host_info.observe_product(
+                        &service_name,
+                        &version,
+                        service_entity.confidence,
+                        timestamp,
+                    );
 
                     // Add to services graph
                     let service_info = self
                         .services
                         .entry(service_name.clone())
                         .or_insert_with(|| ServiceInfo::new(service_name));
-                    service_info.add_host(host_id.clone());
+                    service_info.add_host(host_id.clone(), service_entity.confidence);
                     service_info.add_version(version);
                 }
             }
 
+            // Add fingerprints
+            if let Some(os) = os {
+                host_info
+                    .os
+                    .observe(os.value.trim(), os.confidence, timestamp);
+            }
+            for entity in &fingerprints {
+                match entity.entity_type.as_str() {
+                    "mac_address" => host_info.mac.observe(
+                        &entity.value.to_ascii_uppercase(),
+                        entity.confidence,
+                        timestamp,
+                    ),
+                    "mac_vendor" => {
+                        host_info
+                            .vendor
+                            .observe(&entity.value, entity.confidence, timestamp)
+                    }
+                    "banner" => {
+                        host_info.banners.insert(entity.value.trim().to_string());
+                    }
+                    _ => {}
+                }
+            }
+
             // Add vulnerabilities
             for vuln_entity in &vulnerabilities {
                 let vuln_id = &vuln_entity.value;
@@ -222,7 +513,7 @@ impl CorrelationGraph {
 
                 // Add to services if applicable
                 for service_info in self.services.values_mut() {
-                    if service_info.hosts.contains(host_id) {
+                    if service_info.hosts.contains_key(host_id) {
                         service_info.add_vulnerability(vuln_id.clone());
                     }
                 }
@@ -240,6 +531,26 @@ impl CorrelationGraph {
         }
     }
 
+    /// Record that DNS resolved `name` to `answer` at `resolved_at`
+    ///
+    /// Each is added as an alias of the other where it's a host already in
+    /// the graph; a lookup alone doesn't add a host.
+    pub fn link_alias(&mut self, name: &str, answer: &str, resolved_at: i64) {
+        for (host, alias) in [(name, answer), (answer, name)] {
+            if let Some(info) = self.hosts.get_mut(host) {
+                let at = info.aliases.entry(alias.to_string()).or_insert(resolved_at);
+                *at = (*at).max(resolved_at);
+            }
+        }
+    }
+
+    /// Record the network `host` belongs to, if it's in the graph
+    pub fn annotate_asn(&mut self, host: &str, asn: AsnInfo) {
+        if let Some(info) = self.hosts.get_mut(host) {
+            info.asn = Some(asn);
+        }
+    }
+
     /// Get host information
     pub fn get_host(&self, identifier: &str) -> Option<&HostInfo> {
         self.hosts.get(identifier)
@@ -286,8 +597,9 @@ impl CorrelationGraph {
             host_count: self.hosts.len(),
             service_count: self.services.len(),
             vulnerability_count: self.vulnerabilities.len(),
-            total_ports: self.hosts.values().map(|h| h.ports.len()).sum(),
+            total_ports: self.hosts.values().map(|h| h.open_ports().len()).sum(),
             total_credentials: self.hosts.values().map(|h| h.credentials.len()).sum(),
+            conflicts: self.hosts.values().map(|h| h.conflicts().len()).sum(),
         }
     }
 
@@ -296,6 +608,34 @@ impl CorrelationGraph {
         value.split('/').next()?.parse().ok()
     }
 
+    /// State and service on a port entity's line, e.g. `open` and
+    /// `ssh OpenSSH 8.2p1` from `22/tcp open  ssh OpenSSH 8.2p1`
+    ///
+    /// The entity's context may cut the line short; its last word is then
+    /// dropped rather than recorded half-read.
+    fn parse_port_line(entity: &Entity) -> (Option<&'static str>, Option<String>) {
+        let Some(at) = entity.context.find(&entity.value) else {
+            return (None, None);
+        };
+        let rest = &entity.context[at + entity.value.len()..];
+        let (line, complete) = match rest.find('\n') {
+            Some(end) => (&rest[..end], true),
+            None => (rest, false),
+        };
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if !complete && words.len() > 2 {
+            words.pop();
+        }
+        let Some(state) = words
+            .first()
+            .and_then(|word| PORT_STATES.iter().find(|s| *s == word))
+        else {
+            return (None, None);
+        };
+        let service = (words.len() > 1).then(|| words[1..].join(" "));
+        (Some(state), service)
+    }
+
     /// Parse service from entity value (e.g., "Apache/2.4.41" -> Some(("Apache", "2.4.41")))
     fn parse_service(value: &str) -> Option<(String, String)> {
         let parts: Vec<&str> = value.split('/').collect();
@@ -321,6 +661,8 @@ pub struct GraphStats {
     pub vulnerability_count: usize,
     pub total_ports: usize,
     pub total_credentials: usize,
+    /// Facts about hosts that captures disagreed on
+    pub conflicts: usize,
 }
 
 #[cfg(test)]
@@ -332,6 +674,8 @@ mod tests {
             entity_type: entity_type.to_string(),
             value: value.to_string(),
             context: format!("Context for {}", value),
+            start: 0,
+            end: 0,
             confidence: 0.9,
             should_redact: false,
         }
@@ -348,15 +692,87 @@ mod tests {
     #[test]
     fn test_host_update() {
         let mut host = HostInfo::new("192.168.1.1".to_string(), 1000);
-        host.add_port(22);
-        host.add_port(80);
-        host.add_service(22, "ssh".to_string());
+        host.observe_port(22, "open", 0.9, 1000);
+        host.observe_port(80, "open", 0.9, 1000);
+        host.observe_service(22, "ssh", 0.9, 1000);
         host.add_vulnerability("CVE-2021-44228".to_string());
 
-        assert_eq!(host.ports.len(), 2);
-        assert!(host.ports.contains(&22));
-        assert!(host.services.contains_key(&22));
+        assert_eq!(host.open_ports(), vec![22, 80]);
+        assert_eq!(host.service(22), Some("ssh"));
         assert_eq!(host.vulnerabilities.len(), 1);
+        assert!(host.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_history_resolution() {
+        let mut history = History::default();
+        history.observe("open", 0.9, 1000);
+        history.observe("open", 0.8, 1500);
+        assert_eq!(history.observations().len(), 1);
+        assert_eq!(history.observations()[0].last_seen, 1500);
+        assert!(!history.is_conflicted());
+
+        // A later confident observation wins
+        history.observe("closed", 0.9, 2000);
+        assert_eq!(history.resolved().unwrap().value, "closed");
+        assert!(history.is_conflicted());
+
+        // A weak one doesn't overturn it
+        history.observe("open", 0.3, 3000);
+        assert_eq!(history.resolved().unwrap().value, "closed");
+        assert_eq!(history.observations().len(), 3);
+
+        // Captures seen out of order land in time order
+        history.observe("filtered", 0.9, 1200);
+        let values: Vec<_> = history
+            .observations()
+            .iter()
+            .map(|o| o.value.as_str())
+            .collect();
+        assert_eq!(values, ["open", "filtered", "closed", "open"]);
+    }
+
+    #[test]
+    fn test_conflicting_captures() {
+        let mut graph = CorrelationGraph::new();
+        let port = |line: &str| Entity {
+            context: line.to_string(),
+            ..create_test_entity("port", "80/tcp")
+        };
+        let first = vec![
+            create_test_entity("ip_address", "10.0.0.5"),
+            port("80/tcp open  http    Apache httpd 2.4.41\n"),
+            create_test_entity("service_version", "Apache/2.4.41"),
+        ];
+        let second = vec![
+            create_test_entity("ip_address", "10.0.0.5"),
+            port("80/tcp closed http\n"),
+            create_test_entity("service_version", "Apache/2.4.49"),
+        ];
+        graph.process_entities(&first, 1000);
+        graph.process_entities(&second, 2000);
+
+        let host = graph.get_host("10.0.0.5").unwrap();
+        assert!(host.open_ports().is_empty());
+        assert_eq!(host.service(80), Some("http"));
+        let conflicts = host.conflicts();
+        let kinds: Vec<_> = conflicts
+            .iter()
+            .map(|c| (c.subject.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("port 80", "state"),
+                ("port 80", "service"),
+                ("Apache", "version")
+            ]
+        );
+        assert_eq!(conflicts[0].history[0].value, "open");
+        assert_eq!(conflicts[0].resolved.value, "closed");
+        assert_eq!(conflicts[2].resolved.value, "2.4.49");
+        assert_eq!(graph.stats().conflicts, 3);
+        assert_eq!(graph.get_service("Apache").unwrap().hosts["10.0.0.5"], 0.9);
     }
 
     #[test]
@@ -371,8 +787,7 @@ mod tests {
         graph.process_entities(&entities, 1000);
 
         let host = graph.get_host("192.168.1.1").unwrap();
-        assert_eq!(host.ports.len(), 1);
-        assert!(host.ports.contains(&22));
+        assert_eq!(host.open_ports(), vec![22]);
         assert_eq!(host.vulnerabilities.len(), 1);
     }
 
@@ -388,7 +803,7 @@ mod tests {
         graph.process_entities(&entities, 1000);
 
         let service = graph.get_service("Apache").unwrap();
-        assert!(service.hosts.contains("192.168.1.1"));
+        assert!(service.hosts.contains_key("192.168.1.1"));
         assert!(service.versions.contains("2.4.41"));
     }
 
@@ -411,6 +826,42 @@ mod tests {
         assert_eq!(affected.len(), 2);
     }
 
+    #[test]
+    fn test_fingerprints() {
+        let mut graph = CorrelationGraph::new();
+        graph.process_entities(
+            &[
+                create_test_entity("ip_address", "10.0.0.5"),
+                create_test_entity("os_guess", "Linux"),
+                create_test_entity("os_guess", "Linux 4.15 - 5.6"),
+                create_test_entity("mac_address", "08:00:27:ab:cd:ef"),
+                create_test_entity("mac_vendor", "Oracle VirtualBox virtual NIC"),
+                create_test_entity("banner", "SSH-2.0-OpenSSH_8.2p1"),
+            ],
+            1000,
+        );
+        let host = graph.get_host("10.0.0.5").unwrap();
+        assert_eq!(host.os.resolved().unwrap().value, "Linux 4.15 - 5.6");
+        assert!(!host.os.is_conflicted());
+        assert_eq!(host.mac.resolved().unwrap().value, "08:00:27:AB:CD:EF");
+        assert_eq!(
+            host.vendor.resolved().unwrap().value,
+            "Oracle VirtualBox virtual NIC"
+        );
+        assert!(host.banners.contains("SSH-2.0-OpenSSH_8.2p1"));
+
+        // A listing of several machines says nothing about any one of them
+        graph.process_entities(
+            &[
+                create_test_entity("ip_address", "10.0.0.6"),
+                create_test_entity("ip_address", "10.0.0.7"),
+                create_test_entity("mac_address", "08:00:27:00:00:01"),
+            ],
+            2000,
+        );
+        assert!(graph.get_host("10.0.0.6").unwrap().mac.resolved().is_none());
+    }
+
     #[test]
     fn test_credential_tracking() {
         let mut graph = CorrelationGraph::new();
@@ -473,4 +924,22 @@ mod tests {
         assert_eq!(host.first_seen, 1000);
         assert_eq!(host.last_seen, 2000);
     }
+
+    #[test]
+    fn test_link_alias() {
+        let mut graph = CorrelationGraph::new();
+        graph.process_entities(&[create_test_entity("ip_address", "10.0.0.5")], 1000);
+        graph.process_entities(&[create_test_entity("hostname", "dc01.corp.local")], 1000);
+
+        graph.link_alias("dc01.corp.local", "10.0.0.5", 2000);
+        graph.link_alias("10.0.0.5", "dc01.corp.local", 1500);
+        graph.link_alias("www.corp.local", "10.0.0.9", 2000);
+
+        let ip = graph.get_host("10.0.0.5").unwrap();
+        assert_eq!(ip.aliases.get("dc01.corp.local"), Some(&2000));
+        let name = graph.get_host("dc01.corp.local").unwrap();
+        assert_eq!(name.aliases.get("10.0.0.5"), Some(&2000));
+        assert!(graph.get_host("www.corp.local").is_none());
+        assert!(graph.get_host("10.0.0.9").is_none());
+    }
 }
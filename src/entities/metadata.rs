@@ -202,10 +202,7 @@ impl MetadataEnricher {
             .map(|host| {
                 let mut map = HashMap::new();
                 map.insert("identifier".to_string(), serde_json::json!(host.identifier));
-                map.insert(
-                    "ports".to_string(),
-                    serde_json::json!(host.ports.iter().collect::<Vec<_>>()),
-                );
+                map.insert("ports".to_string(), serde_json::json!(host.open_ports()));
                 map.insert(
                     "vulnerabilities".to_string(),
                     serde_json::json!(host.vulnerabilities.iter().collect::<Vec<_>>()),
@@ -233,6 +230,8 @@ mod tests {
             entity_type: entity_type.to_string(),
             value: value.to_string(),
             context: format!("Context for {}", value),
+            start: 0,
+            end: 0,
             confidence: 0.9,
             should_redact,
         }
@@ -13,5 +13,5 @@ mod graph;
 mod metadata;
 
 pub use extractor::{Entity, EntityExtractor};
-pub use graph::{CorrelationGraph, HostInfo, ServiceInfo};
+pub use graph::{AsnInfo, Conflict, CorrelationGraph, History, HostInfo, Observation, ServiceInfo};
 pub use metadata::{CaptureMetadata, ChunkMetadata, MetadataEnricher};
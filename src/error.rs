@@ -62,10 +62,22 @@ pub enum YinxError {
     #[error("Daemon is not running")]
     DaemonNotRunning,
 
+    /// IPC frame over the size limit
+    #[error("Message too large: {size} bytes (max: {max})")]
+    MessageTooLarge { size: usize, max: usize },
+
     /// Daemon already running
     #[error("Daemon is already running (PID: {pid})")]
     DaemonAlreadyRunning { pid: u32 },
 
+    /// Sync errors
+    #[error("Sync error: {0}")]
+    Sync(String),
+
+    /// Vault errors
+    #[error("Vault error: {0}")]
+    Vault(String),
+
     /// Generic errors
     #[error(transparent)]
     Other(#[from] anyhow::Error),
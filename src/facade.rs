@@ -0,0 +1,236 @@
+//! Library facade for embedding yinx
+//!
+//! [`Yinx`] wraps a data directory with the same storage, pattern and
+//! pipeline setup the daemon uses, so a C2 wrapper or a custom TUI can feed
+//! captures in and read results back without going through the CLI or the
+//! daemon socket. Captures ingested here are also added to the keyword
+//! index, so they are searchable straight away.
+
+use crate::config::{Config, ReportConfig};
+use crate::daemon::{
+    expand_tilde, load_extensions, open_storage, process_capture, CaptureEvent, PipelineExtensions,
+};
+use crate::embedding::KeywordIndex;
+use crate::error::{Result, YinxError};
+use crate::filtering::FilterPipeline;
+use crate::patterns::PatternRegistry;
+use crate::report::{build_report, Report};
+use crate::retrieval::sync_keyword_index;
+use crate::session::{Session, SessionManager};
+use crate::storage::{HostGraph, SessionEntityRecord, StorageManager, TranscriptFilter};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Keyword search result
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub chunk_id: i64,
+    pub capture_id: i64,
+    pub session_id: Option<String>,
+    pub command: Option<String>,
+    /// User who ran the command
+    pub username: Option<String>,
+    /// BM25 relevance score
+    pub score: f32,
+    /// Representative text of the matching chunk
+    pub text: String,
+}
+
+/// An open yinx data directory
+pub struct Yinx {
+    storage: StorageManager,
+    patterns: Arc<PatternRegistry>,
+    extensions: PipelineExtensions,
+    filter_pipeline: FilterPipeline,
+    keywords: Mutex<KeywordIndex>,
+}
+
+impl Yinx {
+    /// Open a data directory with default settings and the bundled patterns
+    ///
+    /// No hooks or rules run, and nothing is read from the user's config.
+    pub fn open(data_dir: impl AsRef<Path>) -> Result<Self> {
+        Self::with_patterns(data_dir.as_ref(), PatternRegistry::bundled()?)
+    }
+
+    /// Like [`Yinx::open`], with other patterns
+    pub(crate) fn with_patterns(data_dir: &Path, patterns: PatternRegistry) -> Result<Self> {
+        let mut config = Config::default();
+        config.storage.data_dir = data_dir.to_path_buf();
+        Self::build(
+            open_storage(&config)?,
+            patterns,
+            PipelineExtensions::default(),
+        )
+    }
+
+    /// Open the data directory of a loaded config, with its patterns, hooks and rules
+    pub fn with_config(config: &Config) -> Result<Self> {
+        let patterns = PatternRegistry::from_config_files(
+            &expand_tilde(&config.patterns.entities_file),
+            &expand_tilde(&config.patterns.tools_file),
+            &expand_tilde(&config.patterns.filters_file),
+        )?
+        .with_aliases(&config.tools.aliases);
+        Self::build(open_storage(config)?, patterns, load_extensions(config)?)
+    }
+
+    fn build(
+        storage: StorageManager,
+        patterns: PatternRegistry,
+        extensions: PipelineExtensions,
+    ) -> Result<Self> {
+        let patterns = Arc::new(patterns);
+        let filter_pipeline =
+            FilterPipeline::with_state_dir(patterns.clone(), storage.sessions_dir());
+        let keywords = storage.open_keyword_index()?;
+
+        Ok(Self {
+            storage,
+            patterns,
+            extensions,
+            filter_pipeline,
+            keywords: Mutex::new(keywords),
+        })
+    }
+
+    /// Underlying storage, for anything the facade doesn't cover
+    pub fn storage(&self) -> &StorageManager {
+        &self.storage
+    }
+
+    /// Session with this name, created if it doesn't exist yet
+    pub fn start_session(&self, name: &str) -> Result<Session> {
+        let mut manager = SessionManager::new(self.storage.base_path().to_path_buf());
+        let session = match manager.find_by_name(name)? {
+            Some(session) => session,
+            None => manager.create_session(Some(name.to_string()))?.clone(),
+        };
+        self.storage.database.ensure_session(
+            &session.id.to_string(),
+            &session.name,
+            session.started_at.timestamp(),
+            &format!("{:?}", session.status).to_lowercase(),
+        )?;
+        Ok(session)
+    }
+
+    /// Run a capture through the full pipeline and index its chunks, along
+    /// with any others not indexed yet
+    ///
+    /// Returns the capture id, or None when the capture was folded into an
+    /// identical earlier one.
+    pub async fn ingest(&self, event: CaptureEvent) -> Result<Option<i64>> {
+        let Some(capture_id) = process_capture(
+            &event,
+            &self.storage,
+            &self.patterns,
+            &self.extensions,
+            &self.filter_pipeline,
+        )
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut keywords = self.keywords.lock().expect("keyword index lock poisoned");
+        sync_keyword_index(&self.storage.database, &mut keywords)?;
+
+        Ok(Some(capture_id))
+    }
+
+    /// Keyword search over every indexed chunk, best match first
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let keywords = self.keywords.lock().expect("keyword index lock poisoned");
+        search_index(&self.storage, &keywords, query, limit)
+    }
+
+    /// Hosts seen in capture output, sorted; all sessions when `session_id`
+    /// is None, and only captures taken by `as_of` when set
+    pub fn hosts(&self, session_id: Option<&str>, as_of: Option<i64>) -> Result<Vec<String>> {
+        Ok(self
+            .storage
+            .host_graph(session_id, as_of)?
+            .nodes
+            .into_iter()
+            .map(|node| node.id)
+            .collect())
+    }
+
+    /// Hosts and the captures they share; all sessions when `session_id` is
+    /// None, and only captures taken by `as_of` when set
+    pub fn host_graph(&self, session_id: Option<&str>, as_of: Option<i64>) -> Result<HostGraph> {
+        self.storage.host_graph(session_id, as_of)
+    }
+
+    /// Unique entities of a session, most frequent first
+    pub fn entities(
+        &self,
+        session_id: &str,
+        entity_type: Option<&str>,
+    ) -> Result<Vec<SessionEntityRecord>> {
+        self.storage
+            .database
+            .get_session_entities(session_id, entity_type)
+    }
+
+    /// Terminal transcript of a session
+    pub fn transcript(&self, session_id: &str) -> Result<String> {
+        self.storage
+            .transcript(session_id, &TranscriptFilter::default())
+    }
+
+    /// Report on a session (ID or name) with the sections `config` asks for
+    pub fn report(
+        &self,
+        session_id: &str,
+        config: &ReportConfig,
+        include_evidence: bool,
+    ) -> Result<Report> {
+        let session =
+            SessionManager::new(self.storage.base_path().to_path_buf()).resolve(session_id)?;
+        build_report(&self.storage, &session, config, include_evidence)
+    }
+}
+
+/// Run a keyword query and resolve the hits to their chunks and captures
+pub(crate) fn search_index(
+    storage: &StorageManager,
+    index: &KeywordIndex,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchHit>> {
+    let hits = index
+        .search(query, limit)
+        .map_err(|e| YinxError::Other(e.into()))?;
+    let ids: Vec<i64> = hits.iter().map(|h| h.id as i64).collect();
+    let mut chunks = storage.database.get_chunks(&ids)?;
+
+    let mut results = Vec::new();
+    for hit in hits {
+        let Some(pos) = chunks.iter().position(|c| c.id == hit.id as i64) else {
+            continue;
+        };
+        let chunk = chunks.swap_remove(pos);
+        let capture = storage.database.get_capture(chunk.capture_id)?;
+        results.push(SearchHit {
+            chunk_id: chunk.id,
+            capture_id: chunk.capture_id,
+            session_id: capture.as_ref().map(|c| c.session_id.clone()),
+            username: capture.as_ref().and_then(|c| c.username.clone()),
+            command: capture.and_then(|c| c.command),
+            score: hit.score,
+            text: chunk.representative_text,
+        });
+    }
+    Ok(results)
+}
+
+impl Drop for Yinx {
+    fn drop(&mut self) {
+        if let Err(e) = self.filter_pipeline.persist_state() {
+            tracing::warn!("Failed to persist filter state: {}", e);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// Adaptive Tier 2 threshold
+// Tracks how much output each tool usually sends into Tier 2 and picks a
+// percentile that keeps roughly a fixed number of lines per capture
+
+use std::collections::HashMap;
+
+use crate::patterns::AutoThresholdConfig;
+
+/// Weight of the newest capture in the running mean
+const SMOOTHING: f32 = 0.2;
+
+/// Per-tool running mean of Tier 2 input volume
+#[derive(Debug, Default)]
+pub struct VolumeTracker {
+    means: HashMap<String, f32>,
+}
+
+impl VolumeTracker {
+    /// Record the Tier 2 input size of one capture
+    pub fn observe(&mut self, tool: &str, lines: usize) {
+        let lines = lines as f32;
+        self.means
+            .entry(tool.to_string())
+            .and_modify(|mean| *mean += SMOOTHING * (lines - *mean))
+            .or_insert(lines);
+    }
+
+    /// Start a tool's mean from a historical value
+    pub fn seed(&mut self, tool: &str, mean: f32) {
+        self.means.insert(tool.to_string(), mean);
+    }
+
+    /// Running mean for a tool, if it has been seen
+    pub fn mean(&self, tool: &str) -> Option<f32> {
+        self.means.get(tool).copied()
+    }
+}
+
+/// Percentile to use for a tool with the given mean volume
+///
+/// Falls back to `base` when auto mode is off or the tool is unknown.
+pub fn percentile_for(config: &AutoThresholdConfig, mean: Option<f32>, base: f32) -> f32 {
+    match mean {
+        Some(mean) if config.enabled && mean > 0.0 => (1.0 - config.target_lines as f32 / mean)
+            .clamp(config.min_percentile, config.max_percentile),
+        _ => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AutoThresholdConfig {
+        AutoThresholdConfig {
+            enabled: true,
+            target_lines: 100,
+            min_percentile: 0.5,
+            max_percentile: 0.98,
+        }
+    }
+
+    #[test]
+    fn test_percentile_tracks_volume() {
+        // 1000 lines → keep ~100 → 90th percentile
+        assert!((percentile_for(&config(), Some(1000.0), 0.8) - 0.9).abs() < 1e-6);
+        // Quiet tools are clamped to the minimum
+        assert_eq!(percentile_for(&config(), Some(50.0), 0.8), 0.5);
+        // Very noisy tools are clamped to the maximum
+        assert_eq!(percentile_for(&config(), Some(1_000_000.0), 0.8), 0.98);
+        // Unknown tool or disabled mode uses the configured percentile
+        assert_eq!(percentile_for(&config(), None, 0.8), 0.8);
+        let disabled = AutoThresholdConfig::default();
+        assert_eq!(percentile_for(&disabled, Some(1000.0), 0.8), 0.8);
+    }
+
+    #[test]
+    fn test_volume_tracker_smooths() {
+        let mut tracker = VolumeTracker::default();
+        tracker.observe("nmap", 100);
+        assert_eq!(tracker.mean("nmap"), Some(100.0));
+
+        tracker.observe("nmap", 600);
+        assert_eq!(tracker.mean("nmap"), Some(200.0));
+        assert_eq!(tracker.mean("gobuster"), None);
+    }
+}
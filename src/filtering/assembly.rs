@@ -0,0 +1,241 @@
+//! Chunk assembly
+//!
+//! Tier 3 leaves one representative line per cluster, and a single line
+//! like `22/tcp open ssh` embeds poorly on its own. With a token budget set,
+//! clusters are put back in output order and adjacent representatives are
+//! merged into chunks of up to that many tokens, so a chunk reads like a
+//! short passage of the output. Lines from different output sections (an
+//! nmap port table and its script results, say) stay in separate chunks
+//! unless told otherwise, and lines about different hosts of a multi-host
+//! scan always do.
+
+use super::Cluster;
+use crate::retrieval::{CharEstimate, TokenCounter};
+use std::collections::{BTreeSet, HashMap};
+
+/// How cluster representatives become stored chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkPolicy {
+    /// Token budget of a merged chunk; 0 keeps one chunk per cluster
+    pub max_tokens: usize,
+    /// Only merge clusters tagged with the same sections
+    pub keep_sections: bool,
+}
+
+impl Default for ChunkPolicy {
+    fn default() -> Self {
+        Self {
+            max_tokens: 0,
+            keep_sections: true,
+        }
+    }
+}
+
+impl ChunkPolicy {
+    /// Merge adjacent clusters of `output` into chunks within the budget
+    ///
+    /// A cluster over the budget by itself stays whole. Merged chunks hold
+    /// the representatives one per line, the sum of the cluster sizes, and
+    /// `merged` (clusters in the chunk), `sections`, `host` and `truncated`
+    /// in their metadata.
+    pub fn assemble(&self, clusters: Vec<Cluster>, output: &str) -> Vec<Cluster> {
+        if self.max_tokens == 0 || clusters.len() < 2 {
+            return clusters;
+        }
+        let tokens = CharEstimate {
+            chars_per_token: 4.0,
+        };
+
+        let mut first_seen: HashMap<&str, usize> = HashMap::new();
+        for (i, line) in output.lines().enumerate() {
+            first_seen.entry(line).or_insert(i);
+        }
+        let mut ordered: Vec<(usize, Cluster)> = clusters
+            .into_iter()
+            .map(|cluster| {
+                let position = cluster
+                    .members
+                    .iter()
+                    .filter_map(|line| first_seen.get(line.as_str()))
+                    .min()
+                    .copied()
+                    .unwrap_or(usize::MAX);
+                (position, cluster)
+            })
+            .collect();
+        ordered.sort_by_key(|(position, _)| *position);
+
+        let mut chunks = Vec::new();
+        let mut group: Vec<Cluster> = Vec::new();
+        let mut group_tokens = 0;
+        for (_, cluster) in ordered {
+            let cluster_tokens = tokens.count(&cluster.representative);
+            let fits = group_tokens + cluster_tokens <= self.max_tokens;
+            let same_sections = !self.keep_sections
+                || group
+                    .first()
+                    .is_none_or(|first| sections(first) == sections(&cluster));
+            let same_host = group
+                .first()
+                .is_none_or(|first| host(first) == host(&cluster));
+            if !group.is_empty() && (!fits || !same_sections || !same_host) {
+                chunks.push(merge(std::mem::take(&mut group)));
+                group_tokens = 0;
+            }
+            group_tokens += cluster_tokens;
+            group.push(cluster);
+        }
+        if !group.is_empty() {
+            chunks.push(merge(group));
+        }
+        chunks
+    }
+}
+
+fn sections(cluster: &Cluster) -> BTreeSet<&str> {
+    cluster
+        .metadata
+        .get("sections")
+        .and_then(|s| s.as_array())
+        .map(|s| s.iter().filter_map(|s| s.as_str()).collect())
+        .unwrap_or_default()
+}
+
+fn host(cluster: &Cluster) -> Option<&str> {
+    cluster.metadata.get("host").and_then(|h| h.as_str())
+}
+
+fn merge(mut group: Vec<Cluster>) -> Cluster {
+    if group.len() == 1 {
+        return group.remove(0);
+    }
+    let all_sections: BTreeSet<String> = group
+        .iter()
+        .flat_map(|c| sections(c).into_iter().map(str::to_string))
+        .collect();
+    let truncated = group
+        .iter()
+        .any(|c| c.metadata.get("truncated").is_some_and(|t| t == true));
+
+    let mut metadata = serde_json::json!({ "merged": group.len() });
+    if !all_sections.is_empty() {
+        metadata["sections"] = serde_json::json!(all_sections);
+    }
+    if let Some(host) = host(&group[0]) {
+        metadata["host"] = serde_json::json!(host);
+    }
+    if truncated {
+        metadata["truncated"] = serde_json::json!(true);
+    }
+    Cluster {
+        pattern: group
+            .iter()
+            .map(|c| c.pattern.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        representative: group
+            .iter()
+            .map(|c| c.representative.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        size: group.iter().map(|c| c.size).sum(),
+        members: group.into_iter().flat_map(|c| c.members).collect(),
+        metadata,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(line: &str, sections: &[&str]) -> Cluster {
+        let mut metadata = serde_json::json!({ "singleton": true });
+        if !sections.is_empty() {
+            metadata["sections"] = serde_json::json!(sections);
+        }
+        Cluster {
+            pattern: line.to_string(),
+            representative: line.to_string(),
+            members: vec![line.to_string()],
+            size: 1,
+            metadata,
+        }
+    }
+
+    const OUTPUT: &str = "Nmap scan report for 10.0.0.5\n\
+                          22/tcp open ssh\n\
+                          80/tcp open http\n\
+                          443/tcp open https\n";
+
+    #[test]
+    fn test_merges_adjacent_clusters_in_output_order() {
+        // Tier 3 hands clusters over in no particular order
+        let clusters = vec![
+            cluster("80/tcp open http", &["ports"]),
+            cluster("Nmap scan report for 10.0.0.5", &[]),
+            cluster("443/tcp open https", &["ports"]),
+            cluster("22/tcp open ssh", &["ports"]),
+        ];
+        let policy = ChunkPolicy {
+            max_tokens: 64,
+            keep_sections: true,
+        };
+        let chunks = policy.assemble(clusters.clone(), OUTPUT);
+        let texts: Vec<_> = chunks.iter().map(|c| c.representative.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Nmap scan report for 10.0.0.5",
+                "22/tcp open ssh\n80/tcp open http\n443/tcp open https"
+            ]
+        );
+        assert_eq!(chunks[1].size, 3);
+        assert_eq!(chunks[1].metadata["merged"], 3);
+        assert_eq!(chunks[1].metadata["sections"], serde_json::json!(["ports"]));
+
+        // Across sections when allowed
+        let everything = ChunkPolicy {
+            keep_sections: false,
+            ..policy
+        }
+        .assemble(clusters.clone(), OUTPUT);
+        assert_eq!(everything.len(), 1);
+        assert_eq!(everything[0].members.len(), 4);
+
+        // The budget splits chunks; each port line is about 4 tokens
+        let small = ChunkPolicy {
+            max_tokens: 9,
+            keep_sections: true,
+        }
+        .assemble(clusters.clone(), OUTPUT);
+        assert_eq!(small.len(), 3);
+
+        // Off keeps clusters as they came
+        let off = ChunkPolicy::default().assemble(clusters.clone(), OUTPUT);
+        assert_eq!(off.len(), 4);
+        assert_eq!(off[0].representative, "80/tcp open http");
+    }
+
+    #[test]
+    fn test_keeps_hosts_apart() {
+        let on = |host: &str, line: &str| {
+            let mut cluster = cluster(line, &[]);
+            cluster.metadata["host"] = serde_json::json!(host);
+            cluster
+        };
+        let clusters = vec![
+            on("10.0.0.5", "22/tcp open ssh"),
+            on("10.0.0.5", "80/tcp open http"),
+            on("10.0.0.6", "443/tcp open https"),
+        ];
+        let policy = ChunkPolicy {
+            max_tokens: 64,
+            keep_sections: false,
+        };
+        let chunks = policy.assemble(clusters, OUTPUT);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].metadata["host"], "10.0.0.5");
+        assert_eq!(chunks[0].metadata["merged"], 2);
+        assert_eq!(chunks[1].metadata["host"], "10.0.0.6");
+    }
+}
@@ -0,0 +1,106 @@
+// Session line history for Tier 2 change detection
+// Remembers which lines earlier captures in a session produced, so a port
+// that just opened or a newly found path stands out from output seen before
+
+use super::utils::stable_hash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Most distinct lines remembered per session; later lines always count as new
+const MAX_HISTORY_LINES: usize = 100_000;
+
+/// Lines seen in previous captures of one session
+#[derive(Debug, Default)]
+pub struct ChangeHistory {
+    seen: HashSet<u64>,
+}
+
+impl ChangeHistory {
+    /// Whether a line has not appeared in an earlier capture
+    pub fn is_new(&self, line: &str) -> bool {
+        !self.seen.contains(&Self::hash_line(line))
+    }
+
+    /// Remember the lines of a capture once it has been scored
+    pub fn record<'a>(&mut self, lines: impl Iterator<Item = &'a str>) {
+        for line in lines {
+            if self.seen.len() >= MAX_HISTORY_LINES {
+                break;
+            }
+            self.seen.insert(Self::hash_line(line));
+        }
+    }
+
+    /// Number of distinct lines remembered
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Whether nothing has been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Snapshot the history so it can survive a daemon restart
+    pub fn snapshot(&self) -> ChangeHistoryState {
+        ChangeHistoryState {
+            lines: self.seen.iter().copied().collect(),
+        }
+    }
+
+    /// Restore from a snapshot, replacing current history
+    pub fn restore(&mut self, state: ChangeHistoryState) {
+        self.seen = state.lines.into_iter().collect();
+    }
+
+    /// Whitespace at the edges is layout, not content; the hash is stable
+    /// across processes so a restored history still matches
+    fn hash_line(line: &str) -> u64 {
+        stable_hash(&[line.trim()])
+    }
+}
+
+/// Serializable change history (hashes of seen lines)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeHistoryState {
+    pub lines: Vec<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_tracks_lines() {
+        let mut history = ChangeHistory::default();
+        assert!(history.is_new("22/tcp open ssh"));
+
+        history.record(["22/tcp open ssh", "80/tcp open http"].into_iter());
+        assert!(!history.is_new("22/tcp open ssh"));
+        assert!(!history.is_new("  22/tcp open ssh  "));
+        assert!(history.is_new("443/tcp open https"));
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn test_history_snapshot_roundtrip() {
+        let mut history = ChangeHistory::default();
+        history.record(["/admin (Status: 301)"].into_iter());
+
+        let mut restored = ChangeHistory::default();
+        restored.restore(history.snapshot());
+        assert!(!restored.is_new("/admin (Status: 301)"));
+    }
+
+    #[test]
+    fn test_history_hash_is_stable() {
+        // History persisted by an earlier daemon must still match in this one
+        assert_eq!(ChangeHistory::hash_line(" repeat "), 17281042442151225043);
+
+        let mut restored = ChangeHistory::default();
+        restored.restore(ChangeHistoryState {
+            lines: vec![17281042442151225043],
+        });
+        assert!(!restored.is_new("repeat"));
+    }
+}
@@ -3,21 +3,33 @@
 // Tier 1: Hash-based deduplication (100K → 10K lines, 90% reduction)
 // Tier 2: Statistical scoring (10K → 2K lines, 80% reduction)
 // Tier 3: Semantic clustering (2K → 100 clusters, 95% reduction)
+//
+// Boilerplate lines (banners, copyright, usage text) are dropped before
+// Tier 1, so they never reach the keyword index or embeddings.
 
+mod adaptive;
+mod assembly;
+mod history;
 mod tier1;
 mod tier2;
 mod tier3;
 mod types;
 mod utils;
 
-pub use tier1::{Tier1Filter, Tier1Stats};
+pub use adaptive::VolumeTracker;
+pub use assembly::ChunkPolicy;
+pub use history::{ChangeHistory, ChangeHistoryState};
+pub use tier1::{Tier1Filter, Tier1State, Tier1Stats};
 pub use tier2::Tier2Filter;
 pub use tier3::{RepresentativeStrategy, Tier3Filter};
-pub use types::{Cluster, FilterDecision, FilterStats, ScoreComponents, ScoredLine};
+pub use types::{Cluster, FilterDecision, FilterStats, FilterTrace, ScoreComponents, ScoredLine};
 
-use crate::error::Result;
-use crate::patterns::PatternRegistry;
+use crate::error::{Result, YinxError};
+use crate::patterns::{HostSection, PatternRegistry};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
@@ -29,8 +41,23 @@ pub struct FilterPipeline {
     /// Session-scoped Tier1 filters (stateful deduplication)
     /// Key: session_id, Value: Tier1Filter wrapped in Mutex for interior mutability
     tier1_filters: Arc<Mutex<HashMap<String, Arc<Mutex<Tier1Filter>>>>>,
+
+    /// Session-scoped line history for Tier2 change detection
+    histories: Arc<Mutex<HashMap<String, Arc<Mutex<ChangeHistory>>>>>,
+
+    /// Directory holding per-session filter state (`<dir>/<session_id>/filter_state.json`)
+    state_dir: Option<PathBuf>,
+
+    /// Per-tool output volume for the adaptive Tier 2 threshold
+    volumes: Arc<Mutex<VolumeTracker>>,
 }
 
+/// File name used for persisted Tier1 state inside a session directory
+const FILTER_STATE_FILE: &str = "filter_state.json";
+
+/// File name used for persisted Tier2 change history inside a session directory
+const CHANGE_HISTORY_FILE: &str = "change_history.json";
+
 impl FilterPipeline {
     /// Create new filter pipeline
     ///
@@ -40,6 +67,21 @@ impl FilterPipeline {
         Self {
             patterns,
             tier1_filters: Arc::new(Mutex::new(HashMap::new())),
+            histories: Arc::new(Mutex::new(HashMap::new())),
+            state_dir: None,
+            volumes: Arc::new(Mutex::new(VolumeTracker::default())),
+        }
+    }
+
+    /// Create filter pipeline that restores and persists Tier1 state
+    ///
+    /// # Arguments
+    /// * `patterns` - Pattern registry with tier configurations
+    /// * `state_dir` - Sessions directory; state is kept per session inside it
+    pub fn with_state_dir(patterns: Arc<PatternRegistry>, state_dir: PathBuf) -> Self {
+        Self {
+            state_dir: Some(state_dir),
+            ..Self::new(patterns)
         }
     }
 
@@ -55,45 +97,224 @@ impl FilterPipeline {
         &self,
         session_id: &str,
         output: &str,
+    ) -> Result<(Vec<Cluster>, FilterStats)> {
+        self.process_tool_capture(session_id, None, output)
+    }
+
+    /// Process capture output from a known tool
+    ///
+    /// The tool picks the volume history used when `tier2.auto_threshold`
+    /// is enabled; otherwise this is the same as `process_capture`.
+    pub fn process_tool_capture(
+        &self,
+        session_id: &str,
+        tool: Option<&str>,
+        output: &str,
+    ) -> Result<(Vec<Cluster>, FilterStats)> {
+        self.process_host_sections(session_id, tool, output, &[])
+    }
+
+    /// Process multi-host output split into per-host sections
+    ///
+    /// Tier 1 counts repeats within each host, so a line every host shares
+    /// isn't dropped after the first few, and Tier 3 clusters each host's
+    /// lines apart, recording the host in the cluster's `host` metadata.
+    /// Tier 2 scores the output as a whole. Without sections this is
+    /// `process_tool_capture`.
+    pub fn process_host_sections(
+        &self,
+        session_id: &str,
+        tool: Option<&str>,
+        output: &str,
+        sections: &[HostSection],
     ) -> Result<(Vec<Cluster>, FilterStats)> {
         let start = Instant::now();
 
-        // Split output into lines
-        let lines: Vec<String> = output.lines().map(|s| s.to_string()).collect();
-        let input_count = lines.len();
+        // Split output into lines, leaving out boilerplate, with each line's host
+        let mut input_count = 0;
+        let mut hosts = Vec::new();
+        let mut lines = Vec::new();
+        let mut section = sections.iter().peekable();
+        for (i, line) in output.lines().enumerate() {
+            input_count += 1;
+            while section.peek().is_some_and(|s| s.lines.end <= i) {
+                section.next();
+            }
+            if self.patterns.is_boilerplate(line, tool) {
+                continue;
+            }
+            let host = section
+                .peek()
+                .filter(|s| s.lines.contains(&i))
+                .and_then(|s| s.host.as_deref());
+            hosts.push(host);
+            lines.push(line.to_string());
+        }
+        let boilerplate_count = input_count - lines.len();
 
         // Tier 1: Hash-based deduplication (stateful per session)
         let tier1_filter = self.get_or_create_tier1_filter(session_id);
-        let tier1_output = {
+        let (tier1_hosts, tier1_output): (Vec<_>, Vec<_>) = {
             let mut filter = tier1_filter.lock().unwrap();
-            filter.filter_lines(lines.into_iter())
+            hosts
+                .into_iter()
+                .zip(lines)
+                .filter(|(host, line)| {
+                    filter.process_scoped_line(*host, line) == FilterDecision::Keep
+                })
+                .unzip()
         };
         let tier1_count = tier1_output.len();
 
-        // Tier 2: Statistical scoring (stateless)
-        let tier2_filter = Tier2Filter::new(self.patterns.clone());
-        let tier2_output = tier2_filter.filter_lines(tier1_output);
-        let tier2_count = tier2_output.len();
-
-        // Extract lines from scored results
-        let tier2_lines: Vec<String> = tier2_output.into_iter().map(|s| s.line).collect();
+        // Tier 2: Statistical scoring (change detection against session history)
+        let (tier2_output, threshold, percentile) =
+            self.score_tier2(session_id, tool, tier1_output);
+        let (score_median, score_p90) = score_summary(&tier2_output);
+
+        // Lines that passed, grouped by host in output order
+        let mut by_host: Vec<(Option<&str>, Vec<String>)> = Vec::new();
+        for (host, scored) in tier1_hosts.into_iter().zip(tier2_output) {
+            if scored.score < threshold {
+                continue;
+            }
+            match by_host.iter_mut().find(|(h, _)| *h == host) {
+                Some((_, lines)) => lines.push(scored.line),
+                None => by_host.push((host, vec![scored.line])),
+            }
+        }
+        let tier2_count = by_host.iter().map(|(_, lines)| lines.len()).sum();
 
-        // Tier 3: Semantic clustering (stateless)
+        // Tier 3: Semantic clustering (stateless), within each host
         let tier3_filter = Tier3Filter::new(self.patterns.clone());
-        let clusters = tier3_filter.cluster_lines(tier2_lines);
+        let mut clusters = Vec::new();
+        for (host, lines) in by_host {
+            let mut host_clusters = tier3_filter.cluster_lines(lines);
+            if let Some(host) = host {
+                for cluster in &mut host_clusters {
+                    if let Some(metadata) = cluster.metadata.as_object_mut() {
+                        metadata.insert("host".to_string(), serde_json::json!(host));
+                    }
+                }
+            }
+            clusters.extend(host_clusters);
+        }
         let cluster_count = clusters.len();
 
         let stats = FilterStats {
             input_lines: input_count,
+            boilerplate_lines: boilerplate_count,
             tier1_output: tier1_count,
             tier2_output: tier2_count,
             tier3_clusters: cluster_count,
+            tier2_percentile: percentile,
+            tier2_threshold: threshold,
+            score_median,
+            score_p90,
             processing_time_ms: start.elapsed().as_millis() as u64,
         };
 
         Ok((clusters, stats))
     }
 
+    /// Process capture output and report what happened to each line
+    ///
+    /// Updates Tier1 state exactly like `process_capture`; used by `yinx watch`
+    /// to preview filtering while tuning thresholds.
+    pub fn trace_capture(
+        &self,
+        session_id: &str,
+        tool: Option<&str>,
+        output: &str,
+    ) -> Result<FilterTrace> {
+        let start = Instant::now();
+        let mut trace = FilterTrace::default();
+        trace.stats.input_lines = output.lines().count();
+
+        let tier1_output: Vec<String> = {
+            let tier1_filter = self.get_or_create_tier1_filter(session_id);
+            let mut filter = tier1_filter.lock().unwrap();
+            let mut kept = Vec::new();
+            for line in output.lines() {
+                if self.patterns.is_boilerplate(line, tool) {
+                    trace.boilerplate.push(line.to_string());
+                    continue;
+                }
+                match filter.process_line(line) {
+                    FilterDecision::Keep => kept.push(line.to_string()),
+                    FilterDecision::Discard => trace.tier1_dropped.push(line.to_string()),
+                }
+            }
+            kept
+        };
+        trace.stats.boilerplate_lines = trace.boilerplate.len();
+        trace.stats.tier1_output = tier1_output.len();
+
+        let (scored, threshold, percentile) = self.score_tier2(session_id, tool, tier1_output);
+        (trace.stats.score_median, trace.stats.score_p90) = score_summary(&scored);
+        trace.stats.tier2_percentile = percentile;
+        trace.stats.tier2_threshold = threshold;
+        trace.tier2_scored = scored;
+        trace.tier2_threshold = threshold;
+
+        let tier2_lines: Vec<String> = trace.tier2_passed().map(|s| s.line.clone()).collect();
+        trace.stats.tier2_output = tier2_lines.len();
+
+        trace.clusters = Tier3Filter::new(self.patterns.clone()).cluster_lines(tier2_lines);
+        trace.stats.tier3_clusters = trace.clusters.len();
+        trace.stats.processing_time_ms = start.elapsed().as_millis() as u64;
+
+        Ok(trace)
+    }
+
+    /// Score Tier 1 survivors, then add them to the session's change history
+    ///
+    /// # Returns
+    /// All scored lines, the pass threshold, and the percentile it came from
+    fn score_tier2(
+        &self,
+        session_id: &str,
+        tool: Option<&str>,
+        lines: Vec<String>,
+    ) -> (Vec<ScoredLine>, f32, f32) {
+        let percentile = self.tier2_percentile(tool, lines.len());
+        let tier2_filter = Tier2Filter::with_percentile(self.patterns.clone(), percentile);
+
+        let history = self.get_or_create_history(session_id);
+        let mut history = history.lock().unwrap();
+        let (scored, threshold) = tier2_filter.score_lines_with_history(lines, Some(&history));
+        history.record(scored.iter().map(|s| s.line.as_str()));
+
+        (scored, threshold, percentile)
+    }
+
+    /// Tier 2 percentile for a capture, recording its volume for next time
+    fn tier2_percentile(&self, tool: Option<&str>, lines: usize) -> f32 {
+        let config = &self.patterns.tier2_config;
+        if !config.auto_threshold.enabled {
+            return config.score_threshold_percentile;
+        }
+
+        let tool = tool.unwrap_or("");
+        let mut volumes = self.volumes.lock().unwrap();
+        volumes.observe(tool, lines);
+        adaptive::percentile_for(
+            &config.auto_threshold,
+            volumes.mean(tool),
+            config.score_threshold_percentile,
+        )
+    }
+
+    /// Start per-tool volume history from stored statistics
+    ///
+    /// # Arguments
+    /// * `means` - Mean Tier 2 input lines per tool (`""` for unknown tools)
+    pub fn seed_volumes(&self, means: &[(String, f32)]) {
+        let mut volumes = self.volumes.lock().unwrap();
+        for (tool, mean) in means {
+            volumes.seed(tool, *mean);
+        }
+    }
+
     /// Get or create Tier1 filter for session
     fn get_or_create_tier1_filter(&self, session_id: &str) -> Arc<Mutex<Tier1Filter>> {
         let mut filters = self.tier1_filters.lock().unwrap();
@@ -102,12 +323,117 @@ impl FilterPipeline {
             .entry(session_id.to_string())
             .or_insert_with(|| {
                 let max_occurrences = self.patterns.tier1_config.max_occurrences;
-                let filter = Tier1Filter::new(self.patterns.clone(), max_occurrences);
+                let mut filter = Tier1Filter::new(self.patterns.clone(), max_occurrences);
+                if let Some(state) = self.load_state(session_id, FILTER_STATE_FILE) {
+                    filter.restore(state);
+                }
                 Arc::new(Mutex::new(filter))
             })
             .clone()
     }
 
+    /// Get or create Tier2 change history for session
+    fn get_or_create_history(&self, session_id: &str) -> Arc<Mutex<ChangeHistory>> {
+        let mut histories = self.histories.lock().unwrap();
+
+        histories
+            .entry(session_id.to_string())
+            .or_insert_with(|| {
+                let mut history = ChangeHistory::default();
+                if let Some(state) = self.load_state(session_id, CHANGE_HISTORY_FILE) {
+                    history.restore(state);
+                }
+                Arc::new(Mutex::new(history))
+            })
+            .clone()
+    }
+
+    /// Load a persisted state file for a session, if any
+    fn load_state<T: DeserializeOwned>(&self, session_id: &str, file: &str) -> Option<T> {
+        let path = self.state_path(session_id, file)?;
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                tracing::warn!("Ignoring corrupt filter state {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn state_path(&self, session_id: &str, file: &str) -> Option<PathBuf> {
+        // Session IDs come from the shell hook; refuse anything that could escape the directory
+        if session_id.is_empty() || session_id.contains(['/', '\\']) || session_id.starts_with('.')
+        {
+            return None;
+        }
+        self.state_dir
+            .as_deref()
+            .map(|dir: &Path| dir.join(session_id).join(file))
+    }
+
+    /// Write Tier1 state for every tracked session to disk
+    ///
+    /// # Returns
+    /// Number of sessions persisted (0 if no state directory is configured)
+    pub fn persist_state(&self) -> Result<usize> {
+        let filters: Vec<(String, Arc<Mutex<Tier1Filter>>)> = {
+            let filters = self.tier1_filters.lock().unwrap();
+            filters
+                .iter()
+                .map(|(id, f)| (id.clone(), f.clone()))
+                .collect()
+        };
+        let histories: Vec<(String, Arc<Mutex<ChangeHistory>>)> = {
+            let histories = self.histories.lock().unwrap();
+            histories
+                .iter()
+                .map(|(id, h)| (id.clone(), h.clone()))
+                .collect()
+        };
+
+        let mut persisted = 0;
+        for (session_id, filter) in filters {
+            let state = filter.lock().unwrap().snapshot();
+            if self.write_state(&session_id, FILTER_STATE_FILE, &state)? {
+                persisted += 1;
+            }
+        }
+        for (session_id, history) in histories {
+            let state = history.lock().unwrap().snapshot();
+            self.write_state(&session_id, CHANGE_HISTORY_FILE, &state)?;
+        }
+
+        Ok(persisted)
+    }
+
+    /// Write one state file for a session; false if there is nowhere to put it
+    fn write_state<T: Serialize>(&self, session_id: &str, file: &str, state: &T) -> Result<bool> {
+        let Some(path) = self.state_path(session_id, file) else {
+            return Ok(false);
+        };
+        let content = serde_json::to_string(state).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to serialize filter state".to_string(),
+        })?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!(
+                    "Failed to create filter state directory: {}",
+                    parent.display()
+                ),
+            })?;
+        }
+        std::fs::write(&path, content).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write filter state: {}", path.display()),
+        })?;
+
+        Ok(true)
+    }
+
     /// Clear session filter state (called when session ends)
     ///
     /// # Arguments
@@ -115,6 +441,7 @@ impl FilterPipeline {
     pub fn clear_session(&self, session_id: &str) {
         let mut filters = self.tier1_filters.lock().unwrap();
         filters.remove(session_id);
+        self.histories.lock().unwrap().remove(session_id);
     }
 
     /// Get number of active sessions being tracked
@@ -124,6 +451,15 @@ impl FilterPipeline {
     }
 }
 
+/// Median and 90th percentile of Tier 2 scores
+fn score_summary(scored: &[ScoredLine]) -> (f32, f32) {
+    let scores: Vec<f32> = scored.iter().map(|s| s.score).collect();
+    (
+        utils::percentile(&scores, 0.5),
+        utils::percentile(&scores, 0.9),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +494,7 @@ mod tests {
                     pattern: r"CVE-\d{4}-\d{4,}".to_string(),
                     weight: 2.0,
                 }],
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -171,6 +508,7 @@ mod tests {
                 }],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         Arc::new(
@@ -213,6 +551,36 @@ mod tests {
         assert_eq!(stats2.tier1_output, 1);
     }
 
+    #[test]
+    fn test_pipeline_host_sections() {
+        let output = "Nmap scan report for 10.0.0.5\n\
+                      22/tcp open ssh\n22/tcp open ssh\n22/tcp open ssh\n22/tcp open ssh\n\
+                      Nmap scan report for 10.0.0.6\n\
+                      22/tcp open ssh\n22/tcp open ssh\n22/tcp open ssh\n22/tcp open ssh\n";
+        let section = |host: &str, lines: std::ops::Range<usize>| HostSection {
+            host: Some(host.to_string()),
+            lines,
+            bytes: 0..0,
+        };
+        let sections = [section("10.0.0.5", 0..5), section("10.0.0.6", 5..10)];
+
+        // Whole, the second host's port lines are repeats of the first's
+        let pipeline = FilterPipeline::new(create_test_patterns());
+        let (_, stats) = pipeline.process_capture("session1", output).unwrap();
+        assert_eq!(stats.tier1_output, 5);
+
+        let pipeline = FilterPipeline::new(create_test_patterns());
+        let (clusters, stats) = pipeline
+            .process_host_sections("session1", None, output, &sections)
+            .unwrap();
+        assert_eq!(stats.tier1_output, 8);
+        assert!(!clusters.is_empty());
+        for cluster in &clusters {
+            let host = cluster.metadata["host"].as_str().unwrap();
+            assert!(host == "10.0.0.5" || host == "10.0.0.6");
+        }
+    }
+
     #[test]
     fn test_pipeline_session_isolation() {
         let patterns = create_test_patterns();
@@ -246,6 +614,94 @@ mod tests {
         assert_eq!(pipeline.active_sessions(), 0);
     }
 
+    #[test]
+    fn test_pipeline_state_survives_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let output = "Repeated line\nRepeated line\nRepeated line\n";
+
+        let pipeline =
+            FilterPipeline::with_state_dir(create_test_patterns(), temp_dir.path().to_path_buf());
+        pipeline.process_capture("session1", output).unwrap();
+        assert_eq!(pipeline.persist_state().unwrap(), 1);
+
+        let restarted =
+            FilterPipeline::with_state_dir(create_test_patterns(), temp_dir.path().to_path_buf());
+        let (_, stats) = restarted.process_capture("session1", output).unwrap();
+        assert_eq!(stats.tier1_output, 0);
+    }
+
+    #[test]
+    fn test_trace_matches_process() {
+        let output =
+            "Repeated line\nRepeated line\nRepeated line\nRepeated line\nCVE-2021-44228 found\n";
+
+        let (_, stats) = FilterPipeline::new(create_test_patterns())
+            .process_capture("s", output)
+            .unwrap();
+        let trace = FilterPipeline::new(create_test_patterns())
+            .trace_capture("s", None, output)
+            .unwrap();
+
+        assert_eq!(trace.stats.tier1_output, stats.tier1_output);
+        assert_eq!(trace.stats.tier2_output, stats.tier2_output);
+        assert_eq!(trace.stats.tier3_clusters, stats.tier3_clusters);
+        assert_eq!(trace.tier1_dropped, vec!["Repeated line"]);
+        assert_eq!(
+            trace.tier2_scored.len(),
+            trace.stats.tier1_output,
+            "every tier 1 survivor is scored"
+        );
+    }
+
+    #[test]
+    fn test_boilerplate_skips_tiers() {
+        let output = "Starting Nmap 7.94 ( https://nmap.org ) at 2024-01-01 10:00 UTC\n\
+                      22/tcp open ssh\n\
+                      Service detection performed. Please report any incorrect results.\n";
+        let pipeline = FilterPipeline::new(create_test_patterns());
+        let (clusters, stats) = pipeline
+            .process_tool_capture("s", Some("nmap"), output)
+            .unwrap();
+        assert_eq!(stats.input_lines, 3);
+        assert_eq!(stats.boilerplate_lines, 2);
+        assert_eq!(stats.tier1_output, 1);
+        assert!(clusters
+            .iter()
+            .all(|c| c.representative == "22/tcp open ssh"));
+
+        let trace = FilterPipeline::new(create_test_patterns())
+            .trace_capture("s", Some("nmap"), output)
+            .unwrap();
+        assert_eq!(trace.boilerplate.len(), 2);
+        assert_eq!(trace.stats.boilerplate_lines, stats.boilerplate_lines);
+    }
+
+    #[test]
+    fn test_change_history_across_captures() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let pipeline =
+            FilterPipeline::with_state_dir(create_test_patterns(), temp_dir.path().to_path_buf());
+        pipeline.process_capture("s", "22/tcp open ssh\n").unwrap();
+        pipeline.persist_state().unwrap();
+
+        // A restarted daemon still knows port 22 was already open
+        let restarted =
+            FilterPipeline::with_state_dir(create_test_patterns(), temp_dir.path().to_path_buf());
+        let trace = restarted
+            .trace_capture("s", None, "22/tcp open ssh\n80/tcp open http\n")
+            .unwrap();
+        let change = |line: &str| {
+            trace
+                .tier2_scored
+                .iter()
+                .find(|s| s.line == line)
+                .unwrap()
+                .components
+                .change
+        };
+        assert!(change("80/tcp open http") > change("22/tcp open ssh"));
+    }
+
     #[test]
     fn test_pipeline_empty_output() {
         let patterns = create_test_patterns();
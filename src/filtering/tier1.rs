@@ -1,10 +1,11 @@
 // Tier 1: Hash-based deduplication filter
 // Normalizes content using patterns and tracks occurrence counts
 use ahash::{HashMap, HashMapExt};
-use std::hash::{Hash, Hasher};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use crate::filtering::types::FilterDecision;
+use crate::filtering::utils::stable_hash;
 use crate::patterns::PatternRegistry;
 
 /// Hash-based deduplication filter
@@ -45,11 +46,20 @@ impl Tier1Filter {
     /// # Returns
     /// FilterDecision::Keep if line should be kept, Discard otherwise
     pub fn process_line(&mut self, line: &str) -> FilterDecision {
+        self.process_scoped_line(None, line)
+    }
+
+    /// Process a line, counting its occurrences apart from the same line
+    /// in other scopes
+    ///
+    /// The scope is a host's section of multi-host output, so a port line
+    /// every host shares is kept for each of them.
+    pub fn process_scoped_line(&mut self, scope: Option<&str>, line: &str) -> FilterDecision {
         // Normalize using patterns from config (replace IPs, timestamps, etc.)
         let normalized = self.patterns.normalize_tier1(line);
 
-        // Hash normalized pattern using fast non-crypto hash
-        let hash = self.hash_pattern(&normalized);
+        // Hash normalized pattern
+        let hash = self.hash_pattern(scope, &normalized);
 
         // Update occurrence count
         let count = self.pattern_counts.entry(hash).or_insert(0);
@@ -76,12 +86,13 @@ impl Tier1Filter {
             .collect()
     }
 
-    /// Hash a normalized pattern using fast non-crypto hash (AHash)
-    fn hash_pattern(&self, pattern: &str) -> u64 {
-        use ahash::AHasher;
-        let mut hasher = AHasher::default();
-        pattern.hash(&mut hasher);
-        hasher.finish()
+    /// Hash a normalized pattern, stable across processes so persisted
+    /// counts still match after a restart
+    fn hash_pattern(&self, scope: Option<&str>, pattern: &str) -> u64 {
+        match scope {
+            Some(scope) => stable_hash(&[scope, pattern]),
+            None => stable_hash(&[pattern]),
+        }
     }
 
     /// Clear state (called when session ends)
@@ -89,6 +100,22 @@ impl Tier1Filter {
         self.pattern_counts.clear();
     }
 
+    /// Snapshot occurrence counts so they can survive a daemon restart
+    pub fn snapshot(&self) -> Tier1State {
+        Tier1State {
+            pattern_counts: self
+                .pattern_counts
+                .iter()
+                .map(|(hash, count)| (*hash, *count))
+                .collect(),
+        }
+    }
+
+    /// Restore occurrence counts from a snapshot, replacing current state
+    pub fn restore(&mut self, state: Tier1State) {
+        self.pattern_counts = state.pattern_counts.into_iter().collect();
+    }
+
     /// Get statistics about current filter state
     pub fn stats(&self) -> Tier1Stats {
         Tier1Stats {
@@ -107,6 +134,12 @@ pub struct Tier1Stats {
     pub total_occurrences: u32,
 }
 
+/// Serializable Tier 1 state (pattern hash -> occurrence count)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tier1State {
+    pub pattern_counts: Vec<(u64, u32)>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,6 +176,7 @@ mod tests {
                 score_threshold_percentile: 0.8,
                 max_technical_score: 10.0,
                 technical_patterns: vec![],
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -151,6 +185,7 @@ mod tests {
                 cluster_patterns: vec![],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         Arc::new(
@@ -232,4 +267,59 @@ mod tests {
         let filtered = filter.filter_lines(lines.into_iter());
         assert_eq!(filtered.len(), 3); // line1 (2x) + line2 (1x)
     }
+
+    #[test]
+    fn test_tier1_scoped_lines() {
+        let patterns = create_test_patterns();
+        let mut filter = Tier1Filter::new(patterns, 1);
+
+        assert_eq!(filter.process_line("22/tcp open ssh"), FilterDecision::Keep);
+        assert_eq!(
+            filter.process_scoped_line(Some("10.0.0.5"), "22/tcp open ssh"),
+            FilterDecision::Keep
+        );
+        assert_eq!(
+            filter.process_scoped_line(Some("10.0.0.6"), "22/tcp open ssh"),
+            FilterDecision::Keep
+        );
+        assert_eq!(
+            filter.process_scoped_line(Some("10.0.0.5"), "22/tcp open ssh"),
+            FilterDecision::Discard
+        );
+        assert_eq!(
+            filter.process_line("22/tcp open ssh"),
+            FilterDecision::Discard
+        );
+    }
+
+    #[test]
+    fn test_tier1_snapshot_restore() {
+        let patterns = create_test_patterns();
+        let mut filter = Tier1Filter::new(patterns.clone(), 2);
+        filter.process_line("repeat");
+        filter.process_line("repeat");
+
+        let mut restored = Tier1Filter::new(patterns, 2);
+        restored.restore(filter.snapshot());
+
+        // Count carries over, so a third occurrence is discarded
+        assert_eq!(restored.process_line("repeat"), FilterDecision::Discard);
+    }
+
+    #[test]
+    fn test_tier1_hash_is_stable() {
+        // Counts persisted by an earlier daemon must still match in this one
+        let filter = Tier1Filter::new(create_test_patterns(), 2);
+        assert_eq!(filter.hash_pattern(None, "repeat"), 17281042442151225043);
+        assert_eq!(
+            filter.hash_pattern(Some("10.0.0.1"), "repeat"),
+            6451061468767986345
+        );
+
+        let mut restored = Tier1Filter::new(create_test_patterns(), 2);
+        restored.restore(Tier1State {
+            pattern_counts: vec![(17281042442151225043, 2)],
+        });
+        assert_eq!(restored.process_line("repeat"), FilterDecision::Discard);
+    }
 }
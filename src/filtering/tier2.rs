@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::filtering::history::ChangeHistory;
 use crate::filtering::types::{ScoreComponents, ScoredLine};
 use crate::filtering::utils;
 use crate::patterns::PatternRegistry;
@@ -11,12 +12,26 @@ use crate::patterns::PatternRegistry;
 /// Analyzes line characteristics to assign importance scores
 pub struct Tier2Filter {
     patterns: Arc<PatternRegistry>,
+    /// Percentile a line must reach to pass
+    percentile: f32,
 }
 
 impl Tier2Filter {
     /// Create Tier 2 filter from pattern registry
     pub fn new(patterns: Arc<PatternRegistry>) -> Self {
-        Self { patterns }
+        let percentile = patterns.tier2_config.score_threshold_percentile;
+        Self {
+            patterns,
+            percentile,
+        }
+    }
+
+    /// Create Tier 2 filter with a percentile overriding the configured one
+    pub fn with_percentile(patterns: Arc<PatternRegistry>, percentile: f32) -> Self {
+        Self {
+            patterns,
+            percentile,
+        }
     }
 
     /// Filter lines by statistical scoring (two-pass algorithm)
@@ -30,8 +45,30 @@ impl Tier2Filter {
     /// # Returns
     /// Vector of scored lines above the threshold
     pub fn filter_lines(&self, lines: Vec<String>) -> Vec<ScoredLine> {
+        let (mut scored_lines, threshold) = self.score_lines(lines);
+        scored_lines.retain(|s| s.score >= threshold);
+        scored_lines
+    }
+
+    /// Score every line without filtering
+    ///
+    /// # Returns
+    /// All scored lines in input order, and the score a line needs to pass
+    pub fn score_lines(&self, lines: Vec<String>) -> (Vec<ScoredLine>, f32) {
+        self.score_lines_with_history(lines, None)
+    }
+
+    /// Score every line, counting lines absent from earlier captures as changes
+    ///
+    /// With a history, the change component is split evenly between difference
+    /// from the previous line and whether the line is new to the session.
+    pub fn score_lines_with_history(
+        &self,
+        lines: Vec<String>,
+        history: Option<&ChangeHistory>,
+    ) -> (Vec<ScoredLine>, f32) {
         if lines.is_empty() {
-            return Vec::new();
+            return (Vec::new(), 0.0);
         }
 
         // Extract config values
@@ -41,7 +78,6 @@ impl Tier2Filter {
         let technical_weight = config.technical_weight;
         let change_weight = config.change_weight;
         let max_technical_score = config.max_technical_score;
-        let threshold_percentile = config.score_threshold_percentile;
 
         // Pass 1: Build frequency map
         let mut line_frequencies: HashMap<&str, u32> = HashMap::new();
@@ -51,7 +87,7 @@ impl Tier2Filter {
         let total_lines = lines.len() as f32;
 
         // Pass 2: Score each line
-        let mut scored_lines: Vec<ScoredLine> = lines
+        let scored_lines: Vec<ScoredLine> = lines
             .iter()
             .enumerate()
             .map(|(i, line)| {
@@ -72,9 +108,16 @@ impl Tier2Filter {
                     .calculate_technical_score(line, max_technical_score)
                     * technical_weight;
 
-                let change = match prev_line {
-                    Some(prev) => utils::change_score(line, prev) * change_weight,
-                    None => change_weight, // First line gets max change score
+                let adjacent = match prev_line {
+                    Some(prev) => utils::change_score(line, prev),
+                    None => 1.0, // First line gets max change score
+                };
+                let change = match history {
+                    Some(history) => {
+                        let novelty = if history.is_new(line) { 1.0 } else { 0.0 };
+                        (adjacent + novelty) / 2.0 * change_weight
+                    }
+                    None => adjacent * change_weight,
                 };
 
                 let components = ScoreComponents {
@@ -94,11 +137,9 @@ impl Tier2Filter {
 
         // Calculate threshold percentile
         let scores: Vec<f32> = scored_lines.iter().map(|s| s.score).collect();
-        let threshold = utils::percentile(&scores, threshold_percentile);
+        let threshold = utils::percentile(&scores, self.percentile);
 
-        // Filter by threshold
-        scored_lines.retain(|s| s.score >= threshold);
-        scored_lines
+        (scored_lines, threshold)
     }
 }
 
@@ -138,6 +179,7 @@ mod tests {
                         weight: 1.0,
                     },
                 ],
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -146,6 +188,7 @@ mod tests {
                 cluster_patterns: vec![],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         Arc::new(
@@ -230,6 +273,18 @@ mod tests {
         assert!(!scored.is_empty()); // Minimum one passes
     }
 
+    #[test]
+    fn test_tier2_history_favors_new_lines() {
+        let filter = Tier2Filter::new(create_test_patterns());
+        let mut history = ChangeHistory::default();
+        history.record(["22/tcp open ssh"].into_iter());
+
+        let lines = vec!["22/tcp open ssh".to_string(), "80/tcp open ssh".to_string()];
+        let (scored, _) = filter.score_lines_with_history(lines, Some(&history));
+
+        assert!(scored[1].components.change > scored[0].components.change);
+    }
+
     #[test]
     fn test_tier2_empty_input() {
         let patterns = create_test_patterns();
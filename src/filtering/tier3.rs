@@ -173,6 +173,7 @@ mod tests {
                 score_threshold_percentile: 0.8,
                 max_technical_score: 10.0,
                 technical_patterns: vec![],
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -186,6 +187,7 @@ mod tests {
                 }],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         Arc::new(
@@ -260,6 +262,7 @@ mod tests {
                 score_threshold_percentile: 0.8,
                 max_technical_score: 10.0,
                 technical_patterns: vec![],
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 3, // Increase min size
@@ -268,6 +271,7 @@ mod tests {
                 cluster_patterns: vec![],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         let patterns = Arc::new(
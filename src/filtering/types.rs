@@ -65,12 +65,48 @@ pub struct Cluster {
 pub struct FilterStats {
     /// Number of input lines
     pub input_lines: usize,
+    /// Number of input lines dropped as boilerplate
+    pub boilerplate_lines: usize,
     /// Number of lines after Tier 1 deduplication
     pub tier1_output: usize,
     /// Number of lines after Tier 2 scoring
     pub tier2_output: usize,
     /// Number of clusters from Tier 3
     pub tier3_clusters: usize,
+    /// Percentile Tier 2 filtered at (configured or adapted to the tool)
+    pub tier2_percentile: f32,
+    /// Score a line needed to pass Tier 2
+    pub tier2_threshold: f32,
+    /// Median Tier 2 score
+    pub score_median: f32,
+    /// 90th percentile Tier 2 score
+    pub score_p90: f32,
     /// Total processing time in milliseconds
     pub processing_time_ms: u64,
 }
+
+/// Line-by-line account of one capture passing through the tiers
+#[derive(Debug, Clone, Default)]
+pub struct FilterTrace {
+    /// Lines dropped as boilerplate before Tier 1
+    pub boilerplate: Vec<String>,
+    /// Lines dropped by Tier 1 as repeats
+    pub tier1_dropped: Vec<String>,
+    /// Every Tier 1 survivor with its Tier 2 score, in input order
+    pub tier2_scored: Vec<ScoredLine>,
+    /// Score a line needed to pass Tier 2
+    pub tier2_threshold: f32,
+    /// Tier 3 clusters built from the Tier 2 survivors
+    pub clusters: Vec<Cluster>,
+    /// Counts matching what `process_capture` reports
+    pub stats: FilterStats,
+}
+
+impl FilterTrace {
+    /// Tier 2 lines that met the threshold
+    pub fn tier2_passed(&self) -> impl Iterator<Item = &ScoredLine> {
+        self.tier2_scored
+            .iter()
+            .filter(move |s| s.score >= self.tier2_threshold)
+    }
+}
@@ -63,6 +63,22 @@ pub fn percentile(scores: &[f32], p: f32) -> f32 {
     sorted[index]
 }
 
+/// 64-bit hash of a sequence of strings that is the same in every process
+///
+/// For hashes persisted across daemon restarts; `AHasher` is seeded per
+/// process. 0xff can't occur in UTF-8, so it separates the parts.
+pub fn stable_hash(parts: &[&str]) -> u64 {
+    let mut hasher = blake3::Hasher::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update(&[0xff]);
+    }
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest.as_bytes()[..8]);
+    u64::from_le_bytes(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
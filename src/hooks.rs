@@ -0,0 +1,151 @@
+//! External pipeline hooks
+//!
+//! Hooks are executables listed under `[[hooks]]` in the config. Each one is
+//! started once per capture at its stage, given a JSON payload on stdin, and
+//! may answer with a JSON object on stdout. A hook that fails, times out or
+//! prints something other than an object is logged and skipped; it never
+//! stops a capture from being stored.
+
+use crate::config::{parse_duration, HookConfig, HookStage};
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Used when a hook's timeout can't be parsed (validation normally rejects it)
+const FALLBACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Object returned by one hook
+#[derive(Debug, Clone)]
+pub struct HookAnnotation {
+    pub hook: String,
+    pub data: serde_json::Map<String, Value>,
+}
+
+/// Runs the configured hooks for a pipeline stage
+#[derive(Debug, Clone, Default)]
+pub struct HookRunner {
+    hooks: Vec<HookConfig>,
+}
+
+impl HookRunner {
+    pub fn new(hooks: Vec<HookConfig>) -> Self {
+        Self { hooks }
+    }
+
+    /// Whether any hook is registered for a stage
+    pub fn has_stage(&self, stage: HookStage) -> bool {
+        self.hooks.iter().any(|h| h.stage == stage)
+    }
+
+    /// Run every hook for `stage` in config order
+    ///
+    /// The payload gets a `"stage"` field added before it is sent.
+    pub async fn run(&self, stage: HookStage, mut payload: Value) -> Vec<HookAnnotation> {
+        if let Some(object) = payload.as_object_mut() {
+            object.insert("stage".to_string(), Value::from(stage.as_str()));
+        }
+        let input = payload.to_string();
+
+        let mut annotations = Vec::new();
+        for hook in self.hooks.iter().filter(|h| h.stage == stage) {
+            match run_hook(hook, &input).await {
+                Ok(Some(data)) => annotations.push(HookAnnotation {
+                    hook: hook.name.clone(),
+                    data,
+                }),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Hook '{}' failed: {}", hook.name, e),
+            }
+        }
+        annotations
+    }
+}
+
+/// Run one hook; `Ok(None)` when it printed nothing
+async fn run_hook(
+    hook: &HookConfig,
+    input: &str,
+) -> std::result::Result<Option<serde_json::Map<String, Value>>, String> {
+    let timeout = parse_duration(&hook.timeout).unwrap_or(FALLBACK_TIMEOUT);
+    let mut child = Command::new(&hook.command)
+        .args(&hook.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to start {}: {}", hook.command.display(), e))?;
+
+    // Write stdin concurrently so a hook that streams output can't deadlock us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_string();
+    let writer = tokio::spawn(async move {
+        // A hook may exit without reading its input; that is not an error
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| format!("timed out after {:?}", timeout))?
+        .map_err(|e| e.to_string())?;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        return Err(format!("exited with {}", output.status));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(None);
+    }
+    match serde_json::from_str(stdout.trim()) {
+        Ok(Value::Object(data)) => Ok(Some(data)),
+        Ok(_) => Err("output is not a JSON object".to_string()),
+        Err(e) => Err(format!("invalid JSON output: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn shell_hook(name: &str, script: &str, timeout: &str) -> HookConfig {
+        HookConfig {
+            name: name.to_string(),
+            stage: HookStage::PostCapture,
+            command: PathBuf::from("/bin/sh"),
+            args: vec!["-c".to_string(), script.to_string()],
+            timeout: timeout.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_annotate_and_skip_failures() {
+        let runner = HookRunner::new(vec![
+            // Echo the stage and command back from the payload
+            shell_hook(
+                "echo",
+                r#"read -r line; printf '{"seen":%s}' "$line""#,
+                "5s",
+            ),
+            shell_hook("broken", "echo not-json", "5s"),
+            shell_hook("slow", "sleep 5", "100ms"),
+            shell_hook("silent", "cat > /dev/null", "5s"),
+        ]);
+        assert!(!runner.has_stage(HookStage::PostExtraction));
+
+        let annotations = runner
+            .run(
+                HookStage::PostCapture,
+                serde_json::json!({ "command": "nmap 10.0.0.1" }),
+            )
+            .await;
+
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].hook, "echo");
+        assert_eq!(annotations[0].data["seen"]["stage"], "post_capture");
+        assert_eq!(annotations[0].data["seen"]["command"], "nmap 10.0.0.1");
+    }
+}
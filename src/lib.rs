@@ -3,17 +3,79 @@
 //! A background CLI daemon that captures terminal activity during penetration testing,
 //! intelligently filters noise, semantically indexes findings, and provides instant
 //! retrieval with optional AI assistance.
+//!
+//! Other tools can embed yinx through the [`Yinx`] facade instead of
+//! shelling out to the CLI:
+//!
+//! ```
+//! use yinx::{CaptureEvent, Yinx};
+//!
+//! # fn main() -> yinx::Result<()> {
+//! let dir = tempfile::tempdir().unwrap();
+//! let yinx = Yinx::open(dir.path())?;
+//! let session = yinx.start_session("htb-lame")?;
+//!
+//! let runtime = tokio::runtime::Runtime::new().unwrap();
+//! let capture_id = runtime.block_on(yinx.ingest(CaptureEvent {
+//!     session_id: session.id.to_string(),
+//!     timestamp: 1_700_000_000,
+//!     command: "nmap -sV 10.10.10.3".to_string(),
+//!     output: "Nmap scan report for 10.10.10.3\n21/tcp open ftp vsftpd 2.3.4\n22/tcp open ssh OpenSSH 4.7p1\n".to_string(),
+//!     exit_code: 0,
+//!     cwd: "/tmp".to_string(),
+//!     username: None,
+//!     source: None,
+//!     tool: None,
+//!     context: None,
+//! }))?;
+//!
+//! assert_eq!(yinx.hosts(Some(&session.id.to_string()), None)?, vec!["10.10.10.3"]);
+//! let hits = yinx.search("scan report", 5)?;
+//! assert_eq!(Some(hits[0].capture_id), capture_id);
+//! # Ok(())
+//! # }
+//! ```
 
+pub mod activity;
+pub mod agent;
+pub mod ask;
+pub mod bench;
+pub mod bloodhound;
+pub mod board;
 pub mod cli;
+pub mod clipboard;
 pub mod config;
+pub mod cracked;
 pub mod daemon;
 pub mod embedding;
+pub mod enrichment;
 pub mod entities;
 pub mod error;
+mod facade;
 pub mod filtering;
+pub mod hooks;
+pub mod manual;
+pub mod network;
 pub mod patterns;
+pub mod proof;
+#[cfg(feature = "yinx-py")]
+mod python;
+pub mod refilter;
+pub mod replay;
+pub mod report;
 pub mod retrieval;
+pub mod rules;
 pub mod session;
 pub mod storage;
+pub mod summary;
+pub mod sync;
+pub mod tickets;
+pub mod triage;
+pub mod tune;
+pub mod vault;
+pub mod verify;
+pub mod web;
 
+pub use daemon::CaptureEvent;
 pub use error::{Result, YinxError};
+pub use facade::{SearchHit, Yinx};
@@ -1,233 +1,4472 @@
-use yinx::cli::{Cli, Commands, ConfigAction, InternalAction};
-use yinx::config::Config;
-use yinx::daemon::{Daemon, IpcClient, IpcMessage, ProcessManager};
+use yinx::board::HostStage;
+use yinx::cli::export::{render_table, ExportFormat, SessionExportFormat};
+use yinx::cli::output::{
+    print_json, print_json_line, ArchiveExportOutput, ArchiveImportOutput, ArchiveOutput,
+    AttachmentOutput, BenchOutput, CrackImportOutput, DaemonOutput, EntityOutput,
+    FilterStatsOutput, FindingOutput, GraphExportOutput, GrepOutput, HostOutput, HostStageOutput,
+    ImportDiffOutput, ImportOutput, LatencyOutput, ManualCaptureOutput, NetworkOutput,
+    NotesExportOutput, ProofOutput, PurgeOutput, QueryExplainOutput, QueryResultOutput,
+    RefilterOutput, ReplayCaptureOutput, ReplayOutput, ReportOutput, ScrubOutput, ServiceOutput,
+    SessionActionOutput, SessionOutput, StatusOutput, StorageOutput, SyncOutput, TicketOutput,
+    TuneOutput, VerifyOutput, WatchOutput,
+};
+use yinx::cli::{
+    BoardAction, Cli, Commands, ConfigAction, DaemonAction, EntitiesAction, FiltersAction,
+    FindingsAction, HostsAction, ImportAction, InternalAction, PatternsAction, SessionsAction,
+};
+use yinx::config::{Config, SearchEffort};
+use yinx::daemon::{
+    check_alarms, CommandExclusions, Daemon, IpcClient, IpcErrorCode, IpcMessage, IpcResponse,
+    ModelStatus, OutputHandoff, PipelineHealth, ProcessManager, ServiceManager, ServiceSpec,
+};
+use yinx::enrichment::AsnFilter;
 use yinx::error::{Result, YinxError};
+use yinx::patterns::PatternStats;
 use yinx::session::SessionManager;
+use yinx::storage::latency::LatencyOptions;
+use yinx::storage::{
+    CaptureSelection, EmbeddingBacklog, EntitySearch, GrepOptions, ScrubPattern, StorageManager,
+    StorageStats, TranscriptFilter,
+};
+use yinx::sync::archive::{Encryption, ImportOptions};
+use yinx::sync::ConflictStrategy;
+use yinx::vault::Vault;
+
+/// Data directory opened by `yinx open`, used in place of the configured one
+static OPENED_STORE: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
 
 fn main() -> Result<()> {
     // Initialize logging
     init_logging();
 
     // Parse CLI arguments
-    let cli = Cli::parse_args();
+    let result = run(Cli::parse_args());
+    if let Err(e) = yinx::storage::latency::flush() {
+        tracing::debug!("Failed to save latency samples: {}", e);
+    }
+    result
+}
+
+fn run(cli: Cli) -> Result<()> {
+    if cli.json && !cli.command.supports_json() {
+        return Err(YinxError::InvalidConfigValue {
+            path: "--json".to_string(),
+            message: "this command has no JSON output".to_string(),
+        });
+    }
 
     // Handle commands
     match cli.command {
-        Commands::Start { session, profile } => {
-            cmd_start(cli.config, session, profile)?;
+        Commands::Start {
+            session,
+            profile,
+            deadline,
+            supervise,
+        } => {
+            cmd_start(cli.config, session, profile, deadline, supervise)?;
         }
         Commands::Stop => {
             cmd_stop()?;
         }
         Commands::Status => {
-            cmd_status(cli.config)?;
+            cmd_status(cli.config, cli.json)?;
+        }
+        Commands::Daemon { action } => {
+            cmd_daemon(cli.config, action, cli.json)?;
         }
         Commands::Query {
             query,
             limit,
             tool,
-            json,
+            target,
+            host,
+            asn,
+            country,
+            org,
+            context,
+            terminal,
+            as_of,
+            export,
+            output,
+            columns,
+            explain,
+            accurate,
+            fast,
+        } => {
+            let effort = if accurate {
+                SearchEffort::Accurate
+            } else if fast {
+                SearchEffort::Fast
+            } else {
+                SearchEffort::Balanced
+            };
+            let export = export.map(|format| QueryExport {
+                format,
+                output,
+                columns,
+            });
+            let network = AsnFilter {
+                asn: asn
+                    .map(|asn| {
+                        AsnFilter::parse_asn(&asn).ok_or_else(|| YinxError::InvalidConfigValue {
+                            path: "--asn".to_string(),
+                            message: format!("expected an AS number like AS13335, got '{}'", asn),
+                        })
+                    })
+                    .transpose()?,
+                country,
+                org,
+            };
+            let filter = QueryFilter {
+                tool,
+                target,
+                host,
+                network,
+                context,
+                terminal,
+                as_of: as_of
+                    .map(|as_of| parse_time_bound("--as-of", &as_of))
+                    .transpose()?,
+            };
+            let search = QuerySearch { limit, effort };
+            cmd_query(
+                cli.config, &query, search, filter, export, explain, cli.json,
+            )?;
+        }
+        Commands::Grep {
+            pattern,
+            session,
+            tool,
+            ignore_case,
+            jobs,
+            max_memory,
+        } => {
+            let memory_limit = yinx::config::parse_size(&max_memory).ok_or_else(|| {
+                YinxError::InvalidConfigValue {
+                    path: "--max-memory".to_string(),
+                    message: format!("invalid size '{}' (expected e.g. 64MB, 1GB)", max_memory),
+                }
+            })?;
+            let options = GrepOptions {
+                session_id: None,
+                tool,
+                jobs,
+                memory_limit: memory_limit as usize,
+            };
+            cmd_grep(
+                cli.config,
+                &pattern,
+                ignore_case,
+                session,
+                options,
+                cli.json,
+            )?;
+        }
+        Commands::Watch {
+            session,
+            max_lines,
+            interval,
         } => {
-            cmd_query(&query, limit, tool, json)?;
+            cmd_watch(cli.config, session, max_lines, interval, cli.json)?;
         }
         Commands::Ask {
             question,
             offline,
             context_size,
+            session,
         } => {
-            cmd_ask(&question, offline, context_size)?;
+            cmd_ask(cli.config, &question, offline, context_size, session)?;
         }
         Commands::Report {
             output,
             format,
             session,
             include_evidence,
+            sign,
         } => {
-            cmd_report(output, &format, session, include_evidence)?;
+            cmd_report(
+                cli.config,
+                output,
+                &format,
+                session,
+                include_evidence,
+                sign,
+                cli.json,
+            )?;
+        }
+        Commands::Export {
+            output,
+            session,
+            format: Some(format),
+            ..
+        } => {
+            cmd_export_as(cli.config, &output, session, format, cli.json)?;
         }
         Commands::Export {
             output,
             session,
             include_indexes,
+            format: None,
+            encrypt,
+            recipients,
+            hosts,
+            tools,
+            tags,
+            since,
+            until,
+        } => {
+            let selection = CaptureSelection {
+                hosts,
+                tools,
+                tags,
+                since: since
+                    .map(|since| parse_time_bound("--since", &since))
+                    .transpose()?,
+                until: until
+                    .map(|until| parse_time_bound("--until", &until))
+                    .transpose()?,
+            };
+            let encryption = if !recipients.is_empty() {
+                Encryption::Recipients(recipients)
+            } else if encrypt {
+                Encryption::Passphrase
+            } else {
+                Encryption::None
+            };
+            cmd_export(
+                cli.config,
+                &output,
+                session,
+                include_indexes,
+                &selection,
+                &encryption,
+                cli.json,
+            )?;
+        }
+        Commands::Sync { peer, remote_bin } => {
+            cmd_sync(cli.config, &peer, &remote_bin, cli.json)?;
+        }
+        Commands::Serve { web } => {
+            cmd_serve(cli.config, web)?;
+        }
+        Commands::Agent {
+            upstream,
+            token_file,
+            session,
+            spool,
+            batch_size,
+        } => {
+            cmd_agent(
+                cli.config, &upstream, token_file, session, spool, batch_size,
+            )?;
+        }
+        Commands::Scrub {
+            value,
+            regex,
+            dry_run,
+        } => {
+            cmd_scrub(cli.config, &value, regex, dry_run, cli.json)?;
+        }
+        Commands::Refilter { session, dry_run } => {
+            cmd_refilter(cli.config, session, dry_run, cli.json)?;
+        }
+        Commands::Tune {
+            session,
+            profile_a,
+            profile_b,
+            lines,
+        } => {
+            cmd_tune(cli.config, session, profile_a, profile_b, lines, cli.json)?;
+        }
+        Commands::Verify { repair } => {
+            cmd_verify(cli.config, repair, cli.json)?;
+        }
+        Commands::Sessions { action } => {
+            cmd_sessions(cli.config, action, cli.json)?;
+        }
+        Commands::Filters { action } => {
+            cmd_filters(cli.config, action, cli.json)?;
+        }
+        Commands::Patterns {
+            action: PatternsAction::Validate { strict },
+        } => {
+            cmd_patterns_validate(cli.config, strict, cli.json)?;
+        }
+        Commands::Patterns {
+            action: PatternsAction::Stats { limit },
+        } => {
+            cmd_patterns_stats(cli.config, limit, cli.json)?;
+        }
+        Commands::Findings {
+            action:
+                Some(FindingsAction::Push {
+                    target,
+                    ids,
+                    session,
+                }),
+            ..
+        } => {
+            cmd_findings_push(cli.config, target, &ids, session, cli.json)?;
+        }
+        Commands::Findings {
+            session,
+            action: None,
+        } => {
+            cmd_findings(cli.config, session, cli.json)?;
+        }
+        Commands::Entities { action } => {
+            cmd_entities(cli.config, action, cli.json)?;
+        }
+        Commands::Transcript {
+            session,
+            tool,
+            host,
+            terminal,
+            per_host,
+        } => {
+            let filter = TranscriptFilter {
+                tool,
+                host,
+                terminal,
+            };
+            cmd_transcript(cli.config, &session, &filter, per_host)?;
+        }
+        Commands::Board { action, session } => {
+            cmd_board(cli.config, action, session, cli.json)?;
+        }
+        Commands::Hosts {
+            action,
+            prefix,
+            session,
+        } => {
+            cmd_hosts(cli.config, action, prefix, session, cli.json)?;
+        }
+        Commands::Proofs { session } => {
+            cmd_proofs(cli.config, session, cli.json)?;
+        }
+        Commands::Summary {
+            since,
+            session,
+            markdown,
+        } => {
+            cmd_summary(cli.config, &since, session, markdown, cli.json)?;
+        }
+        Commands::Clip {
+            note,
+            stdin,
+            session,
+        } => {
+            cmd_clip(cli.config, note, stdin, session, cli.json)?;
+        }
+        Commands::Add {
+            source,
+            tool,
+            timestamp,
+            session,
+        } => {
+            cmd_add(cli.config, &source, tool, timestamp, session, cli.json)?;
+        }
+        Commands::Attach {
+            file,
+            capture,
+            finding,
+            session,
         } => {
-            cmd_export(&output, session, include_indexes)?;
+            cmd_attach(cli.config, &file, capture, finding, session, cli.json)?;
+        }
+        Commands::Import { action } => {
+            cmd_import(cli.config, action, cli.json)?;
+        }
+        Commands::Replay {
+            dir,
+            session,
+            interval,
+            start,
+            realtime,
+        } => {
+            cmd_replay(
+                cli.config, &dir, session, &interval, start, realtime, cli.json,
+            )?;
+        }
+        Commands::Bench { captures, lines } => {
+            cmd_bench(cli.config, captures, lines, cli.json)?;
         }
         Commands::Config { action } => {
             cmd_config(cli.config, action)?;
         }
+        Commands::Open {
+            path,
+            command,
+            read_only: _,
+        } => {
+            cmd_open(cli.config, &path, &command, cli.json)?;
+        }
         Commands::Internal { action } => {
-            cmd_internal(action)?;
+            cmd_internal(cli.config, action)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_open(
+    config_path: Option<std::path::PathBuf>,
+    path: &std::path::Path,
+    command: &[String],
+    json: bool,
+) -> Result<()> {
+    use std::io::Write;
+    use yinx::storage::readonly;
+
+    // An unpacked archive lives in a temporary directory until we exit
+    let mut unpacked = None;
+    let data_dir = if path.is_dir() {
+        readonly::find_data_dir(path)?
+    } else {
+        let dir = tempfile::tempdir().map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to create a directory to unpack into".to_string(),
+        })?;
+        readonly::unpack_archive(path, dir.path())?;
+        let data_dir = readonly::find_data_dir(dir.path())?;
+        unpacked = Some(dir);
+        data_dir
+    };
+    readonly::set_read_only();
+    // Fail now rather than on the first command if the schema doesn't match
+    StorageManager::new(data_dir.clone())?;
+    let _ = OPENED_STORE.set(data_dir);
+
+    if !command.is_empty() {
+        return run_opened(config_path, command, json, false);
+    }
+
+    println!(
+        "Opened {} read-only. Type yinx commands (query, report, entities, ...) or `exit`.",
+        path.display()
+    );
+    let stdin = std::io::stdin();
+    loop {
+        print!("yinx (read-only)> ");
+        std::io::stdout().flush().ok();
+        let mut line = String::new();
+        let read = stdin.read_line(&mut line).map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to read command".to_string(),
+        })?;
+        if read == 0 {
+            println!();
+            break;
+        }
+        let words = match split_command_line(&line) {
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some(_) => {}
+        }
+        if let Err(e) = run_opened(config_path.clone(), &words, json, true) {
+            eprintln!("Error: {}", e);
+        }
+    }
+    drop(unpacked);
+    Ok(())
+}
+
+/// Run one command line against the opened store, refusing anything that writes
+fn run_opened(
+    config_path: Option<std::path::PathBuf>,
+    words: &[String],
+    json: bool,
+    interactive: bool,
+) -> Result<()> {
+    use clap::Parser;
+
+    let args = std::iter::once("yinx".to_string()).chain(words.iter().cloned());
+    let mut cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(e) if interactive => {
+            e.print().ok();
+            return Ok(());
+        }
+        Err(e) => e.exit(),
+    };
+    if !cli.command.is_read_only() {
+        return Err(YinxError::Other(anyhow::anyhow!(
+            "`{}` changes the store or needs the daemon; it isn't available read-only",
+            words.join(" ")
+        )));
+    }
+    cli.config = cli.config.or(config_path);
+    cli.json |= json;
+    run(cli)
+}
+
+/// Split a prompt line into words, honouring quotes and backslash escapes
+fn split_command_line(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(YinxError::Other(anyhow::anyhow!("Unterminated quote")));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn init_logging() {
+    use tracing_subscriber::{fmt, EnvFilter};
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("yinx=info"));
+
+    // stdout is reserved for command output (and the sync protocol)
+    fmt()
+        .with_env_filter(filter)
+        .with_target(false)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+fn cmd_start(
+    config_path: Option<std::path::PathBuf>,
+    session: Option<String>,
+    profile: Option<String>,
+    deadline: Option<String>,
+    supervise: bool,
+) -> Result<()> {
+    tracing::info!("Starting yinx daemon...");
+
+    // Load configuration
+    let mut config = load_config(config_path, profile)?;
+    config.daemon.supervisor.enabled |= supervise;
+
+    tracing::info!("Configuration loaded successfully");
+
+    // Initialize session manager
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let mut session_manager = SessionManager::new(data_dir.clone());
+
+    // Parse the deadline before creating anything so a typo doesn't leave a session behind
+    let now = chrono::Utc::now();
+    let deadline = deadline
+        .map(|d| yinx::session::parse_deadline(&d, now))
+        .transpose()?;
+
+    // Create new session
+    session_manager.create_session(session)?;
+    if let Some(deadline) = deadline {
+        let session = session_manager
+            .current_session_mut()
+            .expect("session was just created");
+        session.set_deadline(deadline);
+        session.save(&data_dir)?;
+    }
+    let session = session_manager
+        .current_session()
+        .expect("session was just created");
+
+    println!("✓ Starting yinx daemon...");
+    println!("  Session: {} ({})", session.name, session.id);
+    println!(
+        "  Started: {}",
+        session.started_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    if let Some(deadline) = deadline {
+        println!(
+            "  Deadline: {} ({} left)",
+            deadline
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            format_gap((deadline - now).num_seconds().max(0))
+        );
+    }
+
+    // Start daemon (this will fork - parent exits, child continues)
+    let mut daemon = Daemon::new(config)?.with_session(session.id);
+    warn_pattern_fallback(&daemon);
+    daemon.start_daemon()?;
+
+    // This line is never reached in parent (parent exits in daemon.start())
+    // Child process continues as daemon
+
+    Ok(())
+}
+
+/// Run the daemon in this process until it is stopped
+///
+/// This is what `yinx start` launches on Windows and what the supervisor
+/// restarts; a service wrapper can run it directly too.
+/// Say so before daemonizing when capture will run on the bundled patterns
+fn warn_pattern_fallback(daemon: &Daemon) {
+    if let Some(reason) = daemon.pattern_fallback() {
+        eprintln!("⚠ Pattern files not loaded: {}", reason);
+        eprintln!("  Capturing with the bundled patterns; run 'yinx config init' to install them");
+    }
+}
+
+fn cmd_run_daemon(
+    config_path: Option<std::path::PathBuf>,
+    session_id: Option<String>,
+    crash_dir: Option<std::path::PathBuf>,
+) -> Result<()> {
+    if let Some(crash_dir) = crash_dir {
+        yinx::daemon::install_crash_handler(crash_dir)?;
+    }
+    yinx::daemon::install_panic_hook();
+    let config = load_config(config_path, None)?;
+    let mut daemon = Daemon::new(config)?;
+    if let Some(session_id) = parse_session_id(session_id)? {
+        daemon = daemon.with_session(session_id);
+    }
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to create tokio runtime".to_string(),
+    })?;
+    runtime.block_on(daemon.run_foreground())
+}
+
+/// Supervise the daemon in this process until it stops or crash-loops
+fn cmd_supervise(
+    config_path: Option<std::path::PathBuf>,
+    session_id: Option<String>,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    yinx::daemon::supervise(&config, parse_session_id(session_id)?)
+}
+
+fn parse_session_id(session_id: Option<String>) -> Result<Option<uuid::Uuid>> {
+    session_id
+        .map(|id| {
+            uuid::Uuid::parse_str(&id)
+                .map_err(|e| YinxError::Session(format!("Invalid session ID {}: {}", id, e)))
+        })
+        .transpose()
+}
+
+fn cmd_stop() -> Result<()> {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    // Load config to get daemon settings
+    let config = load_config(None, None)?;
+    let pid_file = expand_path(&config.daemon.pid_file)?;
+
+    // Check daemon status
+    let pm = ProcessManager::new(pid_file);
+    if !pm.is_running() {
+        println!("Daemon is not running");
+        return Ok(());
+    }
+
+    // Ask over IPC first; that works the same everywhere. Fall back to
+    // SIGTERM (taskkill on Windows) if the daemon doesn't answer.
+    let socket_path = expand_path(&config.daemon.socket_path)?;
+    let stopped = tokio::runtime::Runtime::new()
+        .ok()
+        .and_then(|runtime| {
+            runtime
+                .block_on(
+                    IpcClient::new(socket_path)
+                        .with_encoding(config.daemon.ipc_encoding)
+                        .send(&IpcMessage::Stop),
+                )
+                .ok()
+        })
+        .is_some_and(|response| response.success);
+    if stopped {
+        println!("Asked daemon to shut down...");
+    } else {
+        println!("Sending SIGTERM to daemon...");
+        pm.terminate()?;
+    }
+
+    // Wait for daemon to stop gracefully
+    for i in 0..5 {
+        sleep(Duration::from_millis(500));
+        if !pm.is_running() {
+            println!("✓ Daemon stopped gracefully");
+            return Ok(());
+        }
+        if i < 4 {
+            print!(".");
+            use std::io::Write;
+            std::io::stdout().flush().ok();
+        }
+    }
+
+    // If still running, force kill with SIGKILL
+    if pm.is_running() {
+        println!("\nDaemon not responding, sending SIGKILL...");
+        pm.force_kill()?;
+        sleep(Duration::from_millis(500));
+
+        if !pm.is_running() {
+            println!("✓ Daemon force killed");
+        } else {
+            println!("⚠ Warning: Daemon may still be running (PID file stale)");
         }
     }
 
     Ok(())
 }
 
-fn init_logging() {
-    use tracing_subscriber::{fmt, EnvFilter};
+fn cmd_daemon(
+    config_path: Option<std::path::PathBuf>,
+    action: DaemonAction,
+    json: bool,
+) -> Result<()> {
+    let manager = ServiceManager::native().ok_or_else(|| {
+        YinxError::Daemon(
+            "No supported service manager on this platform; run `yinx _internal run-daemon` \
+             from your init system or a service wrapper instead"
+                .to_string(),
+        )
+    })?;
+
+    let output = match action {
+        DaemonAction::InstallService { no_load, force } => {
+            let config = load_config(config_path.clone(), None)?;
+            // The service may start from another directory
+            let config_path = config_path.map(|path| std::fs::canonicalize(&path).unwrap_or(path));
+            let spec = ServiceSpec::current(&config, config_path)?;
+            let path = manager.install(&spec, !no_load, force)?;
+            ServiceOutput {
+                manager: manager.name().to_string(),
+                path: Some(path.display().to_string()),
+                installed: true,
+                loaded: !no_load,
+            }
+        }
+        DaemonAction::UninstallService => ServiceOutput {
+            manager: manager.name().to_string(),
+            path: manager.uninstall()?.map(|path| path.display().to_string()),
+            installed: false,
+            loaded: false,
+        },
+    };
+
+    if json {
+        return print_json(&output);
+    }
+    match (&output.path, output.installed) {
+        (Some(path), true) => {
+            println!("✓ Installed {} service: {}", output.manager, path);
+            if output.loaded {
+                println!("  The daemon now starts at login");
+            } else {
+                println!("  Written only (--no-load); it is not loaded yet");
+            }
+        }
+        (Some(path), false) => println!("✓ Removed {} service: {}", output.manager, path),
+        (None, _) => println!("No {} service installed", output.manager),
+    }
+    Ok(())
+}
+
+/// What a running daemon says about itself
+#[derive(Default, serde::Deserialize)]
+struct DaemonReport {
+    models: Option<ModelStatus>,
+    health: Option<PipelineHealth>,
+    /// Set when the daemon runs on the bundled patterns
+    pattern_fallback: Option<String>,
+    #[serde(default)]
+    patterns: Vec<PatternStats>,
+}
+
+/// The running daemon's report, None if it can't be reached
+fn daemon_report(config: &Config) -> Option<DaemonReport> {
+    let socket_path = expand_path(&config.daemon.socket_path).ok()?;
+    let runtime = tokio::runtime::Runtime::new().ok()?;
+    let response = runtime
+        .block_on(
+            IpcClient::new(socket_path)
+                .with_encoding(config.daemon.ipc_encoding)
+                .send(&IpcMessage::Status),
+        )
+        .ok()?;
+    serde_json::from_value(response.data?).ok()
+}
+
+/// Chunks waiting for embeddings, when anything is expected to embed them
+///
+/// A store nothing was ever embedded into has no embedder running rather
+/// than a stalled one, so it has no backlog to warn about.
+fn embedding_backlog(
+    config: &Config,
+    storage: &StorageManager,
+) -> Result<Option<EmbeddingBacklog>> {
+    if !config.retrieval.mode.uses_vector() || storage.database.count_embeddings()? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(storage.database.embedding_backlog()?))
+}
+
+/// Failed captures `yinx status` lists
+const RECENT_FAILURES: usize = 5;
+
+fn cmd_status(config_path: Option<std::path::PathBuf>, json: bool) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let pid_file = expand_path(&config.daemon.pid_file)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+
+    if json {
+        let pm = ProcessManager::new(pid_file);
+        let running = pm.is_running();
+        // Asking the daemon first has it save its latency samples
+        let report = if running {
+            daemon_report(&config).unwrap_or_default()
+        } else {
+            DaemonReport::default()
+        };
+        let latency = yinx::storage::latency::summarize(&data_dir.join("logs"))?;
+        let sessions = session_manager.list_sessions()?;
+        let mut backlog = None;
+        let mut failures = Vec::new();
+        let (storage, board, terminals) = if data_dir.exists() {
+            let storage = StorageManager::new(data_dir)?;
+            let (board, terminals) = match session_manager.find_active()? {
+                Some(active) => {
+                    failures = storage.recent_failures(&active.id.to_string(), RECENT_FAILURES)?;
+                    (
+                        board_summary(&storage, &active)?,
+                        storage
+                            .database
+                            .terminal_counts(&active.id.to_string())?
+                            .into_iter()
+                            .collect(),
+                    )
+                }
+                None => Default::default(),
+            };
+            backlog = embedding_backlog(&config, &storage)?;
+            (
+                Some(StorageOutput::from(&storage.stats()?)),
+                board,
+                terminals,
+            )
+        } else {
+            (None, Default::default(), Default::default())
+        };
+
+        let alarms = check_alarms(
+            &config.daemon.alarms,
+            report.health.as_ref(),
+            backlog.as_ref(),
+            chrono::Utc::now().timestamp(),
+        );
+        return print_json(&StatusOutput {
+            daemon: DaemonOutput {
+                running,
+                pid: if running { pm.read_pid().ok() } else { None },
+                models: report.models,
+                health: report.health,
+                pattern_fallback: report.pattern_fallback,
+            },
+            embedding_backlog: backlog,
+            alarms,
+            failures,
+            sessions_total: sessions.len(),
+            interrupted: sessions
+                .iter()
+                .filter(|s| s.status == yinx::session::SessionStatus::Interrupted)
+                .map(SessionOutput::from)
+                .collect(),
+            recent: sessions.iter().take(5).map(SessionOutput::from).collect(),
+            storage,
+            board,
+            terminals,
+            latency: latency.iter().map(LatencyOutput::from).collect(),
+        });
+    }
+
+    // Check daemon status
+    let pm = ProcessManager::new(pid_file);
+    let daemon_status = if pm.is_running() {
+        if let Ok(pid) = pm.read_pid() {
+            format!("Running (PID: {})", pid)
+        } else {
+            "Running".to_string()
+        }
+    } else {
+        "Stopped".to_string()
+    };
+
+    println!("Yinx Status");
+    println!("===========");
+    println!("\nDaemon: {}", daemon_status);
+    let report = pm
+        .is_running()
+        .then(|| daemon_report(&config))
+        .flatten()
+        .unwrap_or_default();
+    if let Some(models) = &report.models {
+        println!("  Embedding model: {}", models.embedding);
+        if let Some(reranker) = &models.reranker {
+            println!("  Reranker: {}", reranker);
+        }
+    }
+    if let Some(reason) = &report.pattern_fallback {
+        println!("  ⚠ Patterns: bundled defaults ({})", reason);
+        println!("    Run 'yinx config init' to install pattern files, then restart the daemon");
+    }
+    if let Some(health) = &report.health {
+        println!(
+            "  Pipeline: {} flushes (last {}ms, slowest {}ms), {} dropped, {} IPC errors, {} throttled",
+            health.flushes,
+            health.last_flush_ms,
+            health.slowest_flush_ms,
+            health.dropped_captures,
+            health.ipc_errors,
+            health.throttled_requests
+        );
+    }
+    let backlog = if data_dir.exists() {
+        embedding_backlog(&config, &StorageManager::new(data_dir.clone())?)?
+    } else {
+        None
+    };
+    let alarms = check_alarms(
+        &config.daemon.alarms,
+        report.health.as_ref(),
+        backlog.as_ref(),
+        chrono::Utc::now().timestamp(),
+    );
+    if !alarms.is_empty() {
+        println!("\n⚠ Pipeline alarms (daemon.alarms):");
+        for alarm in &alarms {
+            println!("  {}", alarm);
+        }
+    }
+
+    // List sessions
+    let sessions = session_manager.list_sessions()?;
+    println!("\nSessions: {} total", sessions.len());
+
+    let interrupted: Vec<_> = sessions
+        .iter()
+        .filter(|s| s.status == yinx::session::SessionStatus::Interrupted)
+        .collect();
+    if !interrupted.is_empty() {
+        println!("\n⚠ Interrupted sessions (daemon stopped unexpectedly):");
+        for session in &interrupted {
+            let gap = session
+                .interruption_gap()
+                .map(format_gap)
+                .unwrap_or_else(|| "unknown".to_string());
+            println!("  {} ({}) - capture gap {}", session.name, session.id, gap);
+        }
+        println!(
+            "  Run 'yinx sessions resume <id>' to continue or 'yinx sessions close <id>' to finish"
+        );
+    }
+
+    if let Some(active) = session_manager.find_active()? {
+        if data_dir.exists() {
+            let storage = StorageManager::new(data_dir.clone())?;
+            let board = board_summary(&storage, &active)?;
+            if !board.is_empty() {
+                let counts: Vec<String> = HostStage::ALL
+                    .iter()
+                    .filter_map(|stage| {
+                        board
+                            .get(stage.as_str())
+                            .map(|n| format!("{} {}", n, stage.label().to_lowercase()))
+                    })
+                    .collect();
+                println!("\nBoard ({}): {}", active.name, counts.join(", "));
+            }
+            let terminals = storage.database.terminal_counts(&active.id.to_string())?;
+            if !terminals.is_empty() {
+                println!("\nTerminals ({}):", active.name);
+                for (terminal, captures) in terminals {
+                    println!("  {:<24} {} captures", terminal, captures);
+                }
+            }
+            let failures = storage.recent_failures(&active.id.to_string(), RECENT_FAILURES)?;
+            if !failures.is_empty() {
+                println!("\nRecent failures ({}):", active.name);
+                for failure in &failures {
+                    println!(
+                        "  #{} {} (exit {})",
+                        failure.capture_id,
+                        failure.command.as_deref().unwrap_or(""),
+                        failure.exit_code.unwrap_or_default()
+                    );
+                    println!("    {}", failure.triage.hint);
+                    if let Some(explanation) = &failure.triage.explanation {
+                        println!("    {}", explanation);
+                    }
+                }
+            }
+        }
+    }
+
+    let latency = yinx::storage::latency::summarize(&data_dir.join("logs"))?;
+    if !latency.is_empty() {
+        println!("\nLatency (recent calls):");
+        for op in &latency {
+            println!(
+                "  {:<28} p50 {:>8.1}ms  p95 {:>8.1}ms  ({} calls)",
+                op.op,
+                op.p50.as_secs_f64() * 1000.0,
+                op.p95.as_secs_f64() * 1000.0,
+                op.samples
+            );
+        }
+        if config.storage.slow_query_threshold().is_some() {
+            println!(
+                "  Slow calls: {}",
+                data_dir.join("logs").join("slow.log").display()
+            );
+        }
+    }
+
+    let now = chrono::Utc::now();
+    for session in sessions.iter().filter(|s| {
+        matches!(
+            s.status,
+            yinx::session::SessionStatus::Active | yinx::session::SessionStatus::Paused
+        )
+    }) {
+        if let Some(remaining) = session.time_remaining(now) {
+            let seconds = remaining.num_seconds();
+            if seconds > 0 {
+                println!(
+                    "\nExam clock ({}): {} left, {:.1}h in",
+                    session.name,
+                    format_gap(seconds),
+                    session.hours_into(now)
+                );
+            } else {
+                println!(
+                    "\n⚠ Exam clock ({}): deadline passed {} ago",
+                    session.name,
+                    format_gap(-seconds)
+                );
+            }
+        }
+    }
+
+    if !sessions.is_empty() {
+        println!("\nRecent sessions:");
+        for session in sessions.iter().take(5) {
+            println!(
+                "  {} - {} ({})",
+                session.name,
+                session.status_str(),
+                session.started_at.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// `yinx query` result filters
+struct QueryFilter {
+    tool: Option<String>,
+    /// Host the capture's command was run against
+    target: Option<String>,
+    /// Host the chunk's evidence is about
+    host: Option<String>,
+    /// Network a public address the evidence is about belongs to
+    network: AsnFilter,
+    /// KEY=VALUE pairs the capture context must match
+    context: Vec<String>,
+    /// Terminal, hostname or host:terminal the command was typed in
+    terminal: Option<String>,
+    /// Latest capture time to include
+    as_of: Option<i64>,
+}
+
+/// How much `yinx query` returns and how hard it looks
+struct QuerySearch {
+    limit: usize,
+    /// Vector search effort from --fast/--accurate
+    effort: SearchEffort,
+}
+
+/// `yinx query --export` settings
+struct QueryExport {
+    format: ExportFormat,
+    output: Option<std::path::PathBuf>,
+    columns: Vec<String>,
+}
+
+/// Keyword search over captured chunks
+///
+/// Semantic search and reranking need the embedding models and are not
+/// wired into the CLI yet.
+fn cmd_query(
+    config_path: Option<std::path::PathBuf>,
+    query: &str,
+    search: QuerySearch,
+    filter: QueryFilter,
+    export: Option<QueryExport>,
+    explain: bool,
+    json: bool,
+) -> Result<()> {
+    let QuerySearch { limit, effort } = search;
+    use yinx::embedding::{KeywordIndex, KeywordIndexError, KeywordSearchResult};
+    use yinx::retrieval::{
+        explain_fusion, is_cold, DropReason, Dropped, FusionConfig, RebuildProgress,
+    };
+
+    // Check columns before doing any work
+    let columns: Vec<String> = match &export {
+        Some(export) if !export.columns.is_empty() => export.columns.clone(),
+        _ => QueryResultOutput::DEFAULT_COLUMNS
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+    };
+    if let Some(unknown) = columns
+        .iter()
+        .find(|c| !QueryResultOutput::COLUMNS.contains(&c.as_str()))
+    {
+        return Err(YinxError::InvalidConfigValue {
+            path: "--columns".to_string(),
+            message: format!(
+                "unknown column '{}' (expected one of: {})",
+                unknown,
+                QueryResultOutput::COLUMNS.join(", ")
+            ),
+        });
+    }
+
+    let context_filters = filter
+        .context
+        .iter()
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key, value)),
+            _ => Err(YinxError::InvalidConfigValue {
+                path: "--context".to_string(),
+                message: format!("expected KEY=VALUE, got '{}'", pair),
+            }),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let config = load_config(config_path.clone(), None)?;
+    if !config.retrieval.mode.uses_keyword() {
+        return Err(YinxError::InvalidConfigValue {
+            path: "retrieval.mode".to_string(),
+            message: format!(
+                "'{}' disables the keyword index, which is all `yinx query` searches",
+                config.retrieval.mode.as_str()
+            ),
+        });
+    }
+    let storage = StorageManager::new(expand_path(&config.storage.data_dir)?)?;
+
+    // An empty index with chunks in the database was lost or never built,
+    // and one built with other tokenizers is out of date: rebuild it in the
+    // background and search SQLite's full-text index until it's back
+    let progress_path = storage.machine_zone().join("keywords.rebuild");
+    let mut rebuild = RebuildProgress::read(&progress_path);
+    let index = match rebuild {
+        Some(_) => None,
+        None => {
+            // Searching only: the daemon may hold the writer
+            let index = match KeywordIndex::open_read_only(storage.machine_zone().join("keywords"))
+            {
+                Ok(index) => Some(index),
+                Err(KeywordIndexError::IndexNotFound(_)) => None,
+                Err(e) => return Err(YinxError::Other(e.into())),
+            };
+            // Searching by host needs an index built with the host field
+            let retokenize = index.as_ref().is_some_and(|index| {
+                index.tokenizers() != &config.indexing.tokenizers
+                    || (filter.host.is_some() && !index.scopes_hosts())
+            });
+            let len = index.as_ref().map_or(0, |index| index.len());
+            if retokenize || is_cold(len, &storage.database)? {
+                drop(index);
+                let progress = RebuildProgress {
+                    indexed: 0,
+                    total: storage.database.count_chunks()?,
+                };
+                progress.write(&progress_path).map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to write {}", progress_path.display()),
+                })?;
+                if let Err(e) = spawn_reindex(config_path.as_deref()) {
+                    let _ = std::fs::remove_file(&progress_path);
+                    return Err(e);
+                }
+                rebuild = Some(progress);
+                None
+            } else {
+                index
+            }
+        }
+    };
+
+    // Over-fetch when filtering so the limit still means something
+    let filtered = filter.tool.is_some()
+        || filter.target.is_some()
+        || filter.host.is_some()
+        || !filter.network.is_empty()
+        || filter.terminal.is_some()
+        || filter.as_of.is_some()
+        || !context_filters.is_empty();
+    let fetch = if filtered { limit * 4 } else { limit };
+    let mut hits = match &index {
+        Some(index) => index
+            .search_host(query, fetch, filter.host.as_deref())
+            .map_err(|e| YinxError::Other(e.into()))?,
+        None => storage
+            .database
+            .search_chunk_texts(query, fetch)?
+            .into_iter()
+            .map(|(id, score)| KeywordSearchResult {
+                id: id as u64,
+                score,
+                snippet: String::new(),
+            })
+            .collect(),
+    };
+
+    // A capture whose digest matches stands in with its first chunk
+    match storage.open_summary_index() {
+        Ok(summaries) => {
+            let summary_hits = summaries
+                .search(query, fetch)
+                .map_err(|e| YinxError::Other(e.into()))?;
+            for hit in summary_hits {
+                let Some(first) = storage
+                    .database
+                    .get_capture_chunks(hit.id as i64)?
+                    .first()
+                    .map(|c| c.id as u64)
+                else {
+                    continue;
+                };
+                match hits.iter_mut().find(|h| h.id == first) {
+                    Some(existing) => existing.score = existing.score.max(hit.score),
+                    None => hits.push(KeywordSearchResult { id: first, ..hit }),
+                }
+            }
+            hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        Err(e) => tracing::warn!("Capture digests not searched: {}", e),
+    }
+    let ids: Vec<i64> = hits.iter().map(|h| h.id as i64).collect();
+    let chunks: std::collections::HashMap<i64, _> = storage
+        .database
+        .get_chunks(&ids)?
+        .into_iter()
+        .map(|c| (c.id, c))
+        .collect();
+
+    // Only the keyword channel is searched here, so its rank is all that
+    // feeds the fused score
+    let mut explanations = if explain {
+        let fusion =
+            FusionConfig::from_config(&config.retrieval).map_err(|e| YinxError::Other(e.into()))?;
+        let keyword: Vec<(i64, f32)> = hits.iter().map(|h| (h.id as i64, h.score)).collect();
+        explain_fusion(&[], &keyword, &fusion)
+    } else {
+        std::collections::HashMap::new()
+    };
+    let mut dropped = Vec::new();
+    let networks = if filter.network.is_empty() {
+        std::collections::HashMap::new()
+    } else {
+        storage.asn_annotations()?
+    };
+
+    let mut results = Vec::new();
+    for hit in hits {
+        let chunk_id = hit.id as i64;
+        if results.len() == limit {
+            dropped.push(Dropped {
+                chunk_id,
+                reason: DropReason::Limit,
+            });
+            continue;
+        }
+        let Some(chunk) = chunks.get(&chunk_id) else {
+            dropped.push(Dropped {
+                chunk_id,
+                reason: DropReason::Missing,
+            });
+            continue;
+        };
+        // The keyword index filtered by host already; the full-text
+        // fallback and capture digests didn't
+        if let Some(host) = &filter.host {
+            if !chunk.host().is_some_and(|h| h.eq_ignore_ascii_case(host)) {
+                dropped.push(Dropped {
+                    chunk_id,
+                    reason: DropReason::Host,
+                });
+                continue;
+            }
+        }
+        let capture = storage.database.get_capture(chunk.capture_id)?;
+        if let Some(as_of) = filter.as_of {
+            if capture.as_ref().is_none_or(|c| c.timestamp > as_of) {
+                dropped.push(Dropped {
+                    chunk_id,
+                    reason: DropReason::AsOf,
+                });
+                continue;
+            }
+        }
+        let capture_tool = capture.as_ref().and_then(|c| c.tool.clone());
+        if filter.tool.is_some() && capture_tool != filter.tool {
+            dropped.push(Dropped {
+                chunk_id,
+                reason: DropReason::Tool,
+            });
+            continue;
+        }
+        let arguments = capture
+            .as_ref()
+            .map(|c| c.arguments.clone())
+            .unwrap_or_default();
+        if let Some(target) = &filter.target {
+            if !arguments.targets(target) {
+                dropped.push(Dropped {
+                    chunk_id,
+                    reason: DropReason::Target,
+                });
+                continue;
+            }
+        }
+        if !context_filters.is_empty() || filter.terminal.is_some() {
+            let context = storage
+                .database
+                .get_capture_context(chunk.capture_id)?
+                .unwrap_or_default();
+            if !context_filters
+                .iter()
+                .all(|(key, value)| context.matches(key, value))
+                || filter
+                    .terminal
+                    .as_ref()
+                    .is_some_and(|terminal| !context.on_terminal(terminal))
+            {
+                dropped.push(Dropped {
+                    chunk_id,
+                    reason: DropReason::Context,
+                });
+                continue;
+            }
+        }
+        // A chunk of a multi-host scan is about its own host alone
+        let hosts = match chunk.host() {
+            Some(host) => vec![host],
+            None => {
+                let mut hosts: Vec<String> = storage
+                    .database
+                    .get_entities_for_capture(chunk.capture_id)?
+                    .into_iter()
+                    .filter(|e| matches!(e.entity_type.as_str(), "ip_address" | "hostname"))
+                    .map(|e| e.value)
+                    .collect();
+                // The command's target counts even when the output never names it
+                if let Some(target) = arguments.target.filter(|t| !t.contains(['/', ','])) {
+                    if !hosts.contains(&target) {
+                        hosts.insert(0, target);
+                    }
+                }
+                hosts
+            }
+        };
+        if !filter.network.is_empty()
+            && !hosts.iter().any(|host| {
+                networks
+                    .get(host)
+                    .is_some_and(|asn| filter.network.matches(asn))
+            })
+        {
+            dropped.push(Dropped {
+                chunk_id,
+                reason: DropReason::Network,
+            });
+            continue;
+        }
+
+        results.push(QueryResultOutput {
+            chunk_id: chunk.id,
+            score: hit.score,
+            capture_id: chunk.capture_id,
+            session_id: capture.as_ref().map(|c| c.session_id.clone()),
+            timestamp: capture.as_ref().map(|c| c.timestamp),
+            tool: capture_tool,
+            command: capture.as_ref().and_then(|c| c.command.clone()),
+            hosts,
+            sections: chunk.sections(),
+            text: chunk.representative_text.clone(),
+            summary: None,
+            explanation: explanations.remove(&chunk_id),
+        });
+    }
+    let capture_ids: Vec<i64> = results.iter().map(|r| r.capture_id).collect();
+    let mut summaries = storage.get_summaries(&capture_ids)?;
+    for result in &mut results {
+        result.summary = summaries.remove(&result.capture_id);
+    }
+
+    if let Some(export) = export {
+        let headers: Vec<&str> = columns.iter().map(String::as_str).collect();
+        let rows: Vec<Vec<String>> = results
+            .iter()
+            .map(|r| {
+                headers
+                    .iter()
+                    .map(|c| r.column(c).unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        let table = render_table(export.format, &headers, &rows);
+        return match export.output {
+            Some(path) => {
+                std::fs::write(&path, table).map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to write export: {}", path.display()),
+                })?;
+                eprintln!("✓ Exported {} results to {}", results.len(), path.display());
+                Ok(())
+            }
+            None => {
+                print!("{}", table);
+                Ok(())
+            }
+        };
+    }
+
+    if json && explain {
+        return print_json(&QueryExplainOutput {
+            results,
+            dropped,
+            rebuild,
+            effort,
+            ef_search: config.retrieval.ef_search(effort),
+        });
+    }
+    if let Some(progress) = rebuild {
+        eprintln!(
+            "⟳ Rebuilding the keyword index in the background ({}/{} chunks, {:.0}%); \
+             results come from the database's full-text index until it's done",
+            progress.indexed,
+            progress.total,
+            progress.percent()
+        );
+    }
+    if json {
+        return print_json(&results);
+    }
+    if results.is_empty() {
+        println!("No results for '{}'", query);
+    }
+    for result in &results {
+        println!(
+            "[{:.2}] capture {} {}",
+            result.score,
+            result.capture_id,
+            result.command.as_deref().unwrap_or("")
+        );
+        match &result.summary {
+            Some(summary) => println!("    {}", summary),
+            None => {
+                for line in result.text.lines().take(3) {
+                    println!("    {}", line);
+                }
+            }
+        }
+        if let Some(explanation) = &result.explanation {
+            let channel = |hit: Option<yinx::retrieval::ChannelHit>| match hit {
+                Some(hit) => format!(
+                    "#{} (score {:.3}, rrf +{:.5})",
+                    hit.rank, hit.score, hit.contribution
+                ),
+                None => "-".to_string(),
+            };
+            println!(
+                "    ↳ vector {} | keyword {} | fused {:.5} (#{}) | rerank {}",
+                channel(explanation.semantic),
+                channel(explanation.keyword),
+                explanation.fused_score,
+                explanation.fused_rank,
+                match explanation.rerank {
+                    Some(delta) => format!(
+                        "{:+} ({:.3} → {:.3})",
+                        delta.rank_change(),
+                        delta.score_before,
+                        delta.score_after
+                    ),
+                    None => "off".to_string(),
+                }
+            );
+        }
+    }
+    if explain {
+        println!();
+        let retrieval = &config.retrieval;
+        println!(
+            "Fusion: {}, keyword k={} weight {}; only the keyword index is searched",
+            retrieval.fusion_strategy.as_str(),
+            retrieval.keyword_rrf_k.unwrap_or(retrieval.rrf_k),
+            retrieval.keyword_weight
+        );
+        println!(
+            "Vector search effort: {} (HNSW ef_search {})",
+            effort.as_str(),
+            retrieval.ef_search(effort)
+        );
+        if !dropped.is_empty() {
+            println!("Dropped {} candidate(s):", dropped.len());
+            for candidate in &dropped {
+                println!(
+                    "  chunk {} - {}",
+                    candidate.chunk_id,
+                    candidate.reason.as_str()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Start `yinx _internal reindex` detached, so it outlives this command
+fn spawn_reindex(config_path: Option<&std::path::Path>) -> Result<()> {
+    let exe = std::env::current_exe().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to locate the yinx executable".to_string(),
+    })?;
+    let mut command = std::process::Command::new(exe);
+    if let Some(config_path) = config_path {
+        command.arg("--config").arg(config_path);
+    }
+    command
+        .args(["_internal", "reindex"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Out of the terminal's process group, so Ctrl-C on the shell
+        // doesn't stop it
+        command.process_group(0);
+    }
+    command.spawn().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to start the keyword index rebuild".to_string(),
+    })?;
+    Ok(())
+}
+
+fn cmd_watch(
+    config_path: Option<std::path::PathBuf>,
+    session: Option<String>,
+    max_lines: usize,
+    interval_ms: u64,
+    json: bool,
+) -> Result<()> {
+    use yinx::entities::EntityExtractor;
+    use yinx::filtering::FilterPipeline;
+    use yinx::patterns::PatternRegistry;
+
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_id = match session {
+        Some(session) => Some(
+            SessionManager::new(data_dir.clone())
+                .resolve(&session)?
+                .id
+                .to_string(),
+        ),
+        None => None,
+    };
+    let storage = StorageManager::new(data_dir)?;
+
+    let pattern_files = [
+        expand_path(&config.patterns.entities_file)?,
+        expand_path(&config.patterns.tools_file)?,
+        expand_path(&config.patterns.filters_file)?,
+    ];
+    let modified = || -> Vec<Option<std::time::SystemTime>> {
+        pattern_files
+            .iter()
+            .map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+            .collect()
+    };
+    let load = || {
+        PatternRegistry::from_config_files(&pattern_files[0], &pattern_files[1], &pattern_files[2])
+    };
+
+    let mut patterns = load()?;
+    let mut pipeline = FilterPipeline::new(std::sync::Arc::new(patterns.clone()));
+    pipeline.seed_volumes(&storage.database.tier2_volume_means()?);
+    let mut loaded_at = modified();
+    let mut last_id = storage.database.max_capture_id()?;
+
+    if !json {
+        println!("Watching for new captures (Ctrl-C to stop)");
+        println!("  Pattern files are reloaded when they change; repeats are counted from now");
+    }
+
+    loop {
+        // Pick up edits to the pattern files between captures
+        if modified() != loaded_at {
+            loaded_at = modified();
+            match load() {
+                Ok(reloaded) => {
+                    patterns = reloaded;
+                    pipeline = FilterPipeline::new(std::sync::Arc::new(patterns.clone()));
+                    pipeline.seed_volumes(&storage.database.tier2_volume_means()?);
+                    if !json {
+                        println!("\n↻ Pattern files changed, reloaded");
+                    }
+                }
+                Err(e) => eprintln!("⚠ Keeping previous patterns: {}", e),
+            }
+        }
+
+        for capture in storage.database.get_captures_after(last_id)? {
+            last_id = capture.id;
+            if session_id
+                .as_ref()
+                .is_some_and(|id| *id != capture.session_id)
+            {
+                continue;
+            }
+
+            let output = storage.blob_store.read(&capture.output_hash)?;
+            let output = String::from_utf8_lossy(&output);
+            let trace =
+                pipeline.trace_capture(&capture.session_id, capture.tool.as_deref(), &output)?;
+            let tool = capture.tool.as_deref().and_then(|name| patterns.tool(name));
+            let entities = EntityExtractor::new(patterns.clone()).extract_for_tool(&output, tool);
+
+            let watched = WatchOutput::new(&capture, &trace, &entities);
+            if json {
+                print_json_line(&watched)?;
+            } else {
+                print_watched(&watched, max_lines);
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(interval_ms));
+    }
+}
+
+fn print_watched(watched: &WatchOutput, max_lines: usize) {
+    let more = |total: usize| {
+        if total > max_lines {
+            println!("      … {} more", total - max_lines);
+        }
+    };
+
+    println!(
+        "\n── capture {} [{}] {}",
+        watched.capture_id,
+        watched.tool.as_deref().unwrap_or("-"),
+        watched.command.as_deref().unwrap_or("")
+    );
+    println!(
+        "  {} lines → tier1 {} → tier2 {} (threshold {:.2}) → {} clusters",
+        watched.input_lines,
+        watched.tier1_output,
+        watched.tier2_output,
+        watched.tier2_threshold,
+        watched.clusters.len()
+    );
+
+    if !watched.boilerplate.is_empty() {
+        println!("  boilerplate {} line(s):", watched.boilerplate.len());
+        for line in watched.boilerplate.iter().take(max_lines) {
+            println!("      {}", line);
+        }
+        more(watched.boilerplate.len());
+    }
+
+    if !watched.tier1_dropped.is_empty() {
+        println!("  tier1 dropped {} repeat(s):", watched.tier1_dropped.len());
+        for line in watched.tier1_dropped.iter().take(max_lines) {
+            println!("      {}", line);
+        }
+        more(watched.tier1_dropped.len());
+    }
+
+    if !watched.tier2.is_empty() {
+        println!("  tier2:");
+        for line in watched.tier2.iter().take(max_lines) {
+            let mark = if line.passed { "✓" } else { "✗" };
+            println!("    {} {:5.2}  {}", mark, line.score, line.line);
+        }
+        more(watched.tier2.len());
+    }
+
+    if !watched.clusters.is_empty() {
+        println!("  tier3:");
+        for cluster in watched.clusters.iter().take(max_lines) {
+            println!("    ×{:<4} {}", cluster.size, cluster.representative);
+        }
+        more(watched.clusters.len());
+    }
+
+    if !watched.entities.is_empty() {
+        println!("  entities:");
+        for entity in watched.entities.iter().take(max_lines) {
+            println!(
+                "    {:<20} {} ({:.2})",
+                entity.entity_type,
+                entity.value.as_deref().unwrap_or("[sensitive]"),
+                entity.confidence
+            );
+        }
+        more(watched.entities.len());
+    }
+}
+
+fn cmd_findings(
+    config_path: Option<std::path::PathBuf>,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+
+    let session_id = match session {
+        Some(session) => Some(
+            SessionManager::new(data_dir.clone())
+                .resolve(&session)?
+                .id
+                .to_string(),
+        ),
+        None => None,
+    };
+    let findings = StorageManager::new(data_dir)?
+        .database
+        .list_findings(session_id.as_deref())?;
+
+    if json {
+        let findings: Vec<_> = findings.iter().map(FindingOutput::from).collect();
+        return print_json(&findings);
+    }
+    if findings.is_empty() {
+        println!("No findings yet");
+        return Ok(());
+    }
+
+    for finding in &findings {
+        let opened = chrono::DateTime::from_timestamp(finding.created_at, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "#{:<5} {:<9} {}  ({}{})",
+            finding.id,
+            finding.severity,
+            finding.title,
+            opened,
+            finding
+                .capture_id
+                .map(|id| format!(", capture {}", id))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+fn cmd_entities(
+    config_path: Option<std::path::PathBuf>,
+    action: EntitiesAction,
+    json: bool,
+) -> Result<()> {
+    let (entity_type, session, since, export) = match &action {
+        EntitiesAction::List {
+            entity_type,
+            session,
+            since,
+            export,
+            ..
+        }
+        | EntitiesAction::Top {
+            entity_type,
+            session,
+            since,
+            export,
+            ..
+        }
+        | EntitiesAction::Grep {
+            entity_type,
+            session,
+            since,
+            export,
+            ..
+        } => (entity_type.clone(), session.clone(), since.clone(), *export),
+    };
+    let since = match since {
+        Some(since) => {
+            let interval = yinx::config::parse_duration(&since).ok_or_else(|| {
+                YinxError::InvalidConfigValue {
+                    path: "--since".to_string(),
+                    message: format!("invalid duration '{}' (expected e.g. 24h, 90m, 2d)", since),
+                }
+            })?;
+            Some(chrono::Utc::now().timestamp() - interval.as_secs() as i64)
+        }
+        None => None,
+    };
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_id = match session {
+        Some(session) => Some(
+            SessionManager::new(data_dir.clone())
+                .resolve(&session)?
+                .id
+                .to_string(),
+        ),
+        None => None,
+    };
+    let search = EntitySearch {
+        session_id,
+        entity_type,
+        since,
+    };
+    let database = StorageManager::new(data_dir)?.database;
+
+    let entities = match action {
+        EntitiesAction::List { limit, .. } => {
+            let mut entities = database.search_entities(&search)?;
+            entities.truncate(limit);
+            entities
+        }
+        EntitiesAction::Top { limit, .. } => database.top_entities(&search, limit)?,
+        EntitiesAction::Grep { pattern, .. } => {
+            let regex = regex::Regex::new(&pattern).map_err(|e| YinxError::InvalidConfigValue {
+                path: "pattern".to_string(),
+                message: format!("invalid regex '{}': {}", pattern, e),
+            })?;
+            let mut entities = database.search_entities(&search)?;
+            entities.retain(|entity| regex.is_match(&entity.value));
+            entities
+        }
+    };
+
+    if let Some(format) = export {
+        let headers = [
+            "type",
+            "value",
+            "occurrences",
+            "captures",
+            "sessions",
+            "first_seen",
+            "last_seen",
+        ];
+        let timestamp = |t: i64| {
+            chrono::DateTime::from_timestamp(t, 0)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default()
+        };
+        let rows: Vec<Vec<String>> = entities
+            .iter()
+            .map(|e| {
+                vec![
+                    e.entity_type.clone(),
+                    e.value.clone(),
+                    e.occurrences.to_string(),
+                    e.captures.to_string(),
+                    e.sessions.to_string(),
+                    timestamp(e.first_seen),
+                    timestamp(e.last_seen),
+                ]
+            })
+            .collect();
+        print!("{}", render_table(format, &headers, &rows));
+        return Ok(());
+    }
+    if json {
+        let entities: Vec<_> = entities.iter().map(EntityOutput::from).collect();
+        return print_json(&entities);
+    }
+    if entities.is_empty() {
+        println!("No matching entities");
+        return Ok(());
+    }
+
+    for entity in &entities {
+        let last_seen = chrono::DateTime::from_timestamp(entity.last_seen, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{:>5}×  {:<16} {}  ({} captures, {} sessions, last {})",
+            entity.occurrences,
+            entity.entity_type,
+            entity.value,
+            entity.captures,
+            entity.sessions,
+            last_seen
+        );
+    }
+    Ok(())
+}
+
+fn cmd_findings_push(
+    config_path: Option<std::path::PathBuf>,
+    target: yinx::tickets::TicketTarget,
+    ids: &[i64],
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    use yinx::patterns::PatternRegistry;
+
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let storage = StorageManager::new(data_dir.clone())?;
+
+    let findings = if ids.is_empty() {
+        let session_manager = SessionManager::new(data_dir);
+        let session = match session {
+            Some(session) => session_manager.resolve(&session)?,
+            None => session_manager.find_active()?.ok_or_else(|| {
+                YinxError::Session("No active session; pass --session or finding IDs".to_string())
+            })?,
+        };
+        let mut findings = storage
+            .database
+            .list_findings(Some(&session.id.to_string()))?;
+        findings.reverse();
+        findings
+    } else {
+        ids.iter()
+            .map(|&id| {
+                storage
+                    .database
+                    .get_finding(id)?
+                    .ok_or_else(|| YinxError::Session(format!("Finding {} not found", id)))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    // Excerpts are redacted before they leave; never skip that for want of pattern files
+    let patterns = PatternRegistry::from_config_files(
+        &expand_path(&config.patterns.entities_file)?,
+        &expand_path(&config.patterns.tools_file)?,
+        &expand_path(&config.patterns.filters_file)?,
+    )
+    .or_else(|e| {
+        tracing::warn!("Using the bundled patterns: {}", e);
+        PatternRegistry::bundled()
+    })?;
+
+    let mut pushed = Vec::new();
+    for finding in &findings {
+        let result =
+            yinx::tickets::push_finding(&storage, &patterns, &config.tickets, target, finding)?;
+        if json {
+            pushed.push(TicketOutput {
+                finding_id: result.finding_id,
+                target: target.as_str().to_string(),
+                action: if result.created { "created" } else { "updated" }.to_string(),
+                remote_id: result.remote_id,
+                url: result.url,
+            });
+            continue;
+        }
+        println!(
+            "✓ #{} {} {} {}{}",
+            finding.id,
+            if result.created { "→" } else { "↻" },
+            target.as_str(),
+            result.remote_id,
+            result
+                .url
+                .map(|url| format!(" ({})", url))
+                .unwrap_or_default()
+        );
+    }
+    if json {
+        return print_json(&pushed);
+    }
+    if findings.is_empty() {
+        println!("No findings to push");
+    }
+    Ok(())
+}
+
+fn cmd_grep(
+    config_path: Option<std::path::PathBuf>,
+    pattern: &str,
+    ignore_case: bool,
+    session: Option<String>,
+    mut options: GrepOptions,
+    json: bool,
+) -> Result<()> {
+    let regex = regex::bytes::RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| YinxError::InvalidConfigValue {
+            path: "pattern".to_string(),
+            message: format!("invalid regex '{}': {}", pattern, e),
+        })?;
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    if let Some(session) = session {
+        options.session_id = Some(
+            SessionManager::new(data_dir.clone())
+                .resolve(&session)?
+                .id
+                .to_string(),
+        );
+    }
+    let storage = StorageManager::new(data_dir)?;
+
+    let stats = storage.grep(&regex, &options, |hit| {
+        let hit = GrepOutput::from(hit);
+        if json {
+            return print_json_line(&hit);
+        }
+        println!(
+            "── capture {} [{}] {}",
+            hit.capture_id,
+            hit.tool.as_deref().unwrap_or("-"),
+            hit.command.as_deref().unwrap_or("")
+        );
+        for line in &hit.lines {
+            println!("{:>6}: {}", line.number, line.text);
+        }
+        if hit.dropped > 0 {
+            println!("      … {} more (over --max-memory)", hit.dropped);
+        }
+        Ok(())
+    })?;
+
+    if !json {
+        eprintln!(
+            "{} match(es) in {} of {} capture(s)",
+            stats.matches, stats.captures_matched, stats.captures_scanned
+        );
+        if stats.missing_blobs > 0 {
+            eprintln!("⚠ {} capture(s) had no stored output", stats.missing_blobs);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_transcript(
+    config_path: Option<std::path::PathBuf>,
+    session: &str,
+    filter: &TranscriptFilter,
+    per_host: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session = SessionManager::new(data_dir.clone()).resolve(session)?;
+    let storage = StorageManager::new(data_dir)?;
+    let session_id = session.id.to_string();
+
+    if per_host {
+        let written = storage.write_host_transcripts(&session_id, &session.name, filter)?;
+        if written.is_empty() {
+            println!("No captures involving a host in {}", session.name);
+        }
+        for (host, path) in written {
+            println!("✓ {} → {}", host, path.display());
+        }
+        return Ok(());
+    }
+
+    print!("{}", storage.transcript(&session_id, filter)?);
+    Ok(())
+}
+
+fn cmd_board(
+    config_path: Option<std::path::PathBuf>,
+    action: Option<BoardAction>,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+    let session = match session {
+        Some(session) => session_manager.resolve(&session)?,
+        None => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let session_id = session.id.to_string();
+    let storage = StorageManager::new(data_dir)?;
+
+    if let Some(BoardAction::Set { host, stage }) = action {
+        storage.database.ensure_session(
+            &session_id,
+            &session.name,
+            session.started_at.timestamp(),
+            &session.status_str().to_lowercase(),
+        )?;
+        storage.database.set_host_stage(&session_id, &host, stage)?;
+        if !json {
+            println!("✓ {} moved to {}", host, stage.label());
+            return Ok(());
+        }
+    }
+
+    let board = storage.database.list_host_stages(&session_id)?;
+    if json {
+        let board: Vec<_> = board.iter().map(HostStageOutput::from).collect();
+        return print_json(&board);
+    }
+    if board.is_empty() {
+        println!("No hosts on the board for {} yet", session.name);
+        return Ok(());
+    }
+
+    const WIDTH: usize = 18;
+    let columns: Vec<Vec<String>> = HostStage::ALL
+        .iter()
+        .map(|stage| {
+            board
+                .iter()
+                .filter(|h| h.stage == *stage)
+                .map(|h| format!("{}{}", h.host, if h.manual { "*" } else { "" }))
+                .collect()
+        })
+        .collect();
+
+    let header: Vec<String> = HostStage::ALL
+        .iter()
+        .map(|stage| format!("{:<WIDTH$}", stage.label()))
+        .collect();
+    println!("{}", header.join(" ").trim_end());
+    let rule: Vec<String> = HostStage::ALL
+        .iter()
+        .map(|stage| format!("{:<WIDTH$}", "-".repeat(stage.label().len())))
+        .collect();
+    println!("{}", rule.join(" ").trim_end());
+
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    for row in 0..rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| format!("{:<WIDTH$}", column.get(row).map_or("", String::as_str)))
+            .collect();
+        println!("{}", cells.join(" ").trim_end());
+    }
+    if board.iter().any(|h| h.manual) {
+        println!("\n* set by hand");
+    }
+    Ok(())
+}
+
+fn cmd_hosts(
+    config_path: Option<std::path::PathBuf>,
+    action: Option<HostsAction>,
+    prefix: u8,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_id = session
+        .map(|s| SessionManager::new(data_dir.clone()).resolve(&s))
+        .transpose()?
+        .map(|s| s.id.to_string());
+    let storage = StorageManager::new(data_dir)?;
+    let Some(HostsAction::Show { host }) = action else {
+        let graph = storage.host_graph(session_id.as_deref(), None)?;
+        return print_network(&NetworkOutput::new(&graph, prefix), json);
+    };
+
+    let graph = storage.correlation_graph(session_id.as_deref(), None)?;
+    let info = graph
+        .get_host(&host)
+        .or_else(|| graph.get_host(&host.to_ascii_lowercase()))
+        .ok_or_else(|| YinxError::Other(anyhow::anyhow!("No captures mention {}", host)))?;
+    let output = HostOutput::from(info);
+    if json {
+        return print_json(&output);
+    }
+
+    let time = |t: i64| {
+        chrono::DateTime::from_timestamp(t, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    println!(
+        "{}  (first seen {}, last seen {})",
+        output.host,
+        time(output.first_seen),
+        time(output.last_seen)
+    );
+    if let Some(os) = &output.os {
+        println!("OS: {}", os);
+    }
+    if let Some(mac) = &output.mac {
+        match &output.vendor {
+            Some(vendor) => println!("MAC: {} ({})", mac, vendor),
+            None => println!("MAC: {}", mac),
+        }
+    }
+    if let Some(asn) = &output.asn {
+        println!("Network: {}", asn);
+    }
+    for alias in &output.aliases {
+        println!(
+            "DNS: {}  (resolved {})",
+            alias.name,
+            time(alias.resolved_at)
+        );
+    }
+    if !output.ports.is_empty() {
+        println!("\n{:<7} {:<16} SERVICE", "PORT", "STATE");
+        for port in &output.ports {
+            println!(
+                "{:<7} {:<16} {}{}",
+                port.port,
+                port.state,
+                port.service.as_deref().unwrap_or("-"),
+                if port.conflicted { "  !" } else { "" }
+            );
+        }
+    }
+    if !output.versions.is_empty() {
+        println!();
+        for (product, version) in &output.versions {
+            println!("{} {}", product, version);
+        }
+    }
+    if !output.banners.is_empty() {
+        println!("\nBanners:");
+        for banner in &output.banners {
+            println!("  {}", banner);
+        }
+    }
+    if !output.vulnerabilities.is_empty() {
+        println!("\nVulnerabilities: {}", output.vulnerabilities.join(", "));
+    }
+    if !output.conflicts.is_empty() {
+        println!("\n! Captures disagreed:");
+        for conflict in &output.conflicts {
+            println!(
+                "  {} {} → {}",
+                conflict.subject, conflict.kind, conflict.resolved
+            );
+            for observation in &conflict.history {
+                println!(
+                    "    {:<24} {} – {} (confidence {:.2})",
+                    observation.value,
+                    time(observation.first_seen),
+                    time(observation.last_seen),
+                    observation.confidence
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draw hosts as a tree under their subnets, with what's left to scan
+fn print_network(network: &NetworkOutput, json: bool) -> Result<()> {
+    if json {
+        return print_json(network);
+    }
+    if network.subnets.is_empty() {
+        println!("No hosts captured yet");
+        return Ok(());
+    }
+    let width = network
+        .subnets
+        .iter()
+        .flat_map(|s| &s.hosts)
+        .map(|h| h.host.len())
+        .max()
+        .unwrap_or(0);
+    let mut unscanned = Vec::new();
+    for subnet in &network.subnets {
+        println!(
+            "{}  {} host(s) · {} scanned · {} responsive",
+            subnet.subnet.as_deref().unwrap_or("Named hosts"),
+            subnet.hosts.len(),
+            subnet.scanned,
+            subnet.responsive
+        );
+        for (i, host) in subnet.hosts.iter().enumerate() {
+            let branch = if i + 1 == subnet.hosts.len() {
+                "└─"
+            } else {
+                "├─"
+            };
+            let status = if !host.open_ports.is_empty() {
+                let ports: Vec<String> = host.open_ports.iter().map(u16::to_string).collect();
+                format!("open {}", ports.join(","))
+            } else if host.scanned {
+                "scanned, nothing open".to_string()
+            } else {
+                unscanned.push(host.host.as_str());
+                "not scanned".to_string()
+            };
+            match &host.asn {
+                Some(asn) => println!("{} {:<width$}  {}  [{}]", branch, host.host, status, asn),
+                None => println!("{} {:<width$}  {}", branch, host.host, status),
+            }
+        }
+    }
+    if !unscanned.is_empty() {
+        println!("\nNot scanned yet: {}", unscanned.join(", "));
+    }
+    Ok(())
+}
+
+fn cmd_proofs(
+    config_path: Option<std::path::PathBuf>,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+
+    let session = match session {
+        Some(session) => Some(SessionManager::new(data_dir.clone()).resolve(&session)?),
+        None => None,
+    };
+    let session_id = session.as_ref().map(|s| s.id.to_string());
+    let proofs = StorageManager::new(data_dir)?
+        .database
+        .list_proofs(session_id.as_deref())?;
+
+    if json {
+        let proofs: Vec<_> = proofs.iter().map(ProofOutput::from).collect();
+        return print_json(&proofs);
+    }
+    if proofs.is_empty() {
+        println!("No proofs found yet");
+        return Ok(());
+    }
+
+    println!("Exam checklist");
+    println!("==============");
+    for proof in &proofs {
+        let found = chrono::DateTime::from_timestamp(proof.created_at, 0);
+        // With a single session, show how far into the exam each proof came
+        let when = match (&session, found) {
+            (Some(session), Some(at)) => format!("+{:.1}h", session.hours_into(at)),
+            (None, Some(at)) => at.format("%Y-%m-%d %H:%M").to_string(),
+            _ => String::new(),
+        };
+        println!(
+            "  [x] {:<16} {:<10} {}  ({}{})",
+            proof.host.as_deref().unwrap_or("unknown host"),
+            proof.file,
+            proof.value,
+            when,
+            proof
+                .capture_id
+                .map(|id| format!(", capture {}", id))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+fn cmd_replay(
+    config_path: Option<std::path::PathBuf>,
+    dir: &std::path::Path,
+    session: Option<String>,
+    interval: &str,
+    start: Option<String>,
+    realtime: bool,
+    json: bool,
+) -> Result<()> {
+    use yinx::replay::load_fixtures;
+
+    let interval =
+        yinx::config::parse_duration(interval).ok_or_else(|| YinxError::InvalidConfigValue {
+            path: "--interval".to_string(),
+            message: format!("invalid duration '{}' (expected e.g. 30s, 5m)", interval),
+        })?;
+    let start = match start {
+        Some(start) => yinx::manual::parse_timestamp(&start)?,
+        None => chrono::Utc::now().timestamp(),
+    };
+    let fixtures = load_fixtures(dir)?;
+    if fixtures.is_empty() {
+        return Err(YinxError::Other(anyhow::anyhow!(
+            "No output files to replay in {}",
+            dir.display()
+        )));
+    }
+
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let name = session.unwrap_or_else(|| {
+        let dir_name = std::path::absolute(dir)
+            .ok()
+            .and_then(|dir| dir.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "fixtures".to_string());
+        format!("replay-{}", dir_name)
+    });
+    let created = SessionManager::new(data_dir.clone())
+        .find_by_name(&name)?
+        .is_none();
+
+    let yinx = yinx::Yinx::with_config(&config)?;
+    let session = yinx.start_session(&name)?;
+    let session_id = session.id.to_string();
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to start async runtime".to_string(),
+    })?;
+
+    if !json {
+        println!(
+            "Replaying {} file(s) from {} into {}",
+            fixtures.len(),
+            dir.display(),
+            session.name
+        );
+    }
+    let mut captures = Vec::with_capacity(fixtures.len());
+    for (i, fixture) in fixtures.iter().enumerate() {
+        if realtime && i > 0 {
+            std::thread::sleep(interval);
+        }
+        let timestamp = start + (interval.as_secs() * i as u64) as i64;
+        let capture_id = runtime.block_on(yinx.ingest(fixture.event(&session_id, timestamp)))?;
+        let file = fixture
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !json {
+            match capture_id {
+                Some(id) => println!("  ✓ {:<24} capture {} ({})", file, id, fixture.command),
+                None => println!("  = {:<24} repeat of the previous capture", file),
+            }
+        }
+        captures.push(ReplayCaptureOutput {
+            file,
+            command: fixture.command.clone(),
+            capture_id,
+            timestamp,
+        });
+    }
+
+    // A session made for the replay shouldn't look like a live engagement
+    if created {
+        let mut session = session;
+        session.stop();
+        session.save(&data_dir)?;
+        yinx.storage().database.set_session_status(
+            &session_id,
+            "stopped",
+            session.stopped_at.map(|t| t.timestamp()),
+        )?;
+    }
+
+    if json {
+        return print_json(&ReplayOutput {
+            session_id,
+            captures,
+        });
+    }
+    println!(
+        "\nDone. Try `yinx query <text>` or `yinx report --session {}`",
+        name
+    );
+    Ok(())
+}
+
+fn cmd_bench(
+    config_path: Option<std::path::PathBuf>,
+    captures: usize,
+    lines: usize,
+    json: bool,
+) -> Result<()> {
+    use yinx::bench::BenchOptions;
+    use yinx::patterns::PatternRegistry;
+
+    let config = load_config(config_path, None)?;
+    // The configured patterns are what the daemon will run
+    let patterns = PatternRegistry::from_config_files(
+        &expand_path(&config.patterns.entities_file)?,
+        &expand_path(&config.patterns.tools_file)?,
+        &expand_path(&config.patterns.filters_file)?,
+    )
+    .or_else(|e| {
+        tracing::warn!("Using the bundled patterns: {}", e);
+        PatternRegistry::bundled()
+    })?;
+    let options = BenchOptions {
+        captures: captures.max(1),
+        lines: lines.max(1),
+        ..BenchOptions::default()
+    };
+
+    if !json {
+        println!(
+            "Benchmarking {} synthetic captures of {} lines...",
+            options.captures, options.lines
+        );
+    }
+    let report = yinx::bench::run(options, patterns)?;
+    if json {
+        return print_json(&BenchOutput::from(&report));
+    }
+
+    println!(
+        "\n  {:<16} {:>8} {:>10} {:>10} {:>10}",
+        "Stage", "Runs", "p50", "p95", "MB/s"
+    );
+    for stage in &report.stages {
+        let mb_per_sec = if stage.bytes > 0 {
+            format!("{:.1}", stage.mb_per_sec())
+        } else {
+            "-".to_string()
+        };
+        println!(
+            "  {:<16} {:>8} {:>8.2}ms {:>8.2}ms {:>10}",
+            stage.name,
+            stage.iterations,
+            stage.p50.as_secs_f64() * 1000.0,
+            stage.p95.as_secs_f64() * 1000.0,
+            mb_per_sec
+        );
+    }
+    println!(
+        "\nExpected throughput: ~{:.0} captures/sec (before embeddings)",
+        report.captures_per_sec()
+    );
+    Ok(())
+}
+
+fn cmd_summary(
+    config_path: Option<std::path::PathBuf>,
+    since: &str,
+    session: Option<String>,
+    markdown: bool,
+    json: bool,
+) -> Result<()> {
+    let interval =
+        yinx::config::parse_duration(since).ok_or_else(|| YinxError::InvalidConfigValue {
+            path: "--since".to_string(),
+            message: format!("invalid duration '{}' (expected e.g. 24h, 90m, 2d)", since),
+        })?;
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+
+    let session_id = match session {
+        Some(session) => Some(
+            SessionManager::new(data_dir.clone())
+                .resolve(&session)?
+                .id
+                .to_string(),
+        ),
+        None => None,
+    };
+    let until = chrono::Utc::now().timestamp();
+    let summary = yinx::activity::summarize_activity(
+        &StorageManager::new(data_dir)?,
+        session_id.as_deref(),
+        until - interval.as_secs() as i64,
+        until,
+    )?;
+
+    if json {
+        return print_json(&summary);
+    }
+    if markdown {
+        print!("{}", summary.to_markdown());
+        return Ok(());
+    }
+    if summary.is_empty() {
+        println!("No activity in the last {}", since);
+        return Ok(());
+    }
+
+    println!("Activity in the last {}", since);
+    println!("=================={}", "=".repeat(since.len()));
+    let tools: Vec<String> = summary
+        .tools
+        .iter()
+        .map(|(tool, n)| format!("{} {}", n, tool))
+        .collect();
+    println!(
+        "{} commands captured{}",
+        summary.captures,
+        if tools.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", tools.join(", "))
+        }
+    );
+    if !summary.new_hosts.is_empty() {
+        let hosts: Vec<&str> = summary.new_hosts.iter().map(|h| h.value.as_str()).collect();
+        println!("\nNew hosts: {}", hosts.join(", "));
+    }
+    if !summary.new_services.is_empty() {
+        println!("\nNew services:");
+        for service in &summary.new_services {
+            match &service.host {
+                Some(host) => println!("  {:<16} {}", host, service.value),
+                None => println!("  {:<16} {}", "", service.value),
+            }
+        }
+    }
+    if !summary.findings.is_empty() {
+        println!("\nFindings:");
+        for finding in &summary.findings {
+            println!("  [{}] {}", finding.severity, finding.title);
+        }
+    }
+    if !summary.credentials.is_empty() || !summary.secrets.is_empty() {
+        println!("\nCredentials:");
+        for credential in &summary.credentials {
+            println!(
+                "  {} ({}, cracked with {})",
+                credential.username.as_deref().unwrap_or("unknown user"),
+                credential.hash_type,
+                credential.source
+            );
+        }
+        for (kind, n) in &summary.secrets {
+            println!("  {} × {} seen in output", n, kind);
+        }
+    }
+    if !summary.proofs.is_empty() {
+        println!("\nProofs:");
+        for proof in &summary.proofs {
+            println!(
+                "  {} on {}",
+                proof.file,
+                proof.host.as_deref().unwrap_or("unknown host")
+            );
+        }
+    }
+    if !summary.notable.is_empty() {
+        println!("\nNotable commands:");
+        for notable in &summary.notable {
+            println!("  {}  ({})", notable.command, notable.reason);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_attach(
+    config_path: Option<std::path::PathBuf>,
+    file: &std::path::Path,
+    capture: Option<i64>,
+    finding: Option<i64>,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+    let storage = StorageManager::new(data_dir)?;
+
+    // Sessions implied by the capture and finding, which must agree
+    let mut implied = Vec::new();
+    if let Some(id) = capture {
+        let capture = storage
+            .database
+            .get_capture(id)?
+            .ok_or_else(|| YinxError::Session(format!("Capture {} not found", id)))?;
+        implied.push(capture.session_id);
+    }
+    if let Some(id) = finding {
+        let finding = storage
+            .database
+            .get_finding(id)?
+            .ok_or_else(|| YinxError::Session(format!("Finding {} not found", id)))?;
+        implied.push(finding.session_id);
+    }
+
+    let session = match (session, implied.first()) {
+        (Some(session), _) => session_manager.resolve(&session)?,
+        (None, Some(id)) => session_manager.resolve(id)?,
+        (None, None) => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let session_id = session.id.to_string();
+    if let Some(other) = implied.iter().find(|id| **id != session_id) {
+        return Err(YinxError::Session(format!(
+            "Capture/finding belongs to session {}, not {}",
+            other, session.name
+        )));
+    }
+
+    storage.database.ensure_session(
+        &session_id,
+        &session.name,
+        session.started_at.timestamp(),
+        &session.status_str().to_lowercase(),
+    )?;
+    let attachment = storage.attach(
+        file,
+        yinx::storage::AttachTarget {
+            session_id: &session_id,
+            session_name: &session.name,
+            capture_id: capture,
+            finding_id: finding,
+        },
+    )?;
+
+    if json {
+        return print_json(&AttachmentOutput::from(&attachment));
+    }
+    println!("✓ Attached {} to {}", attachment.filename, session.name);
+    println!(
+        "  {}",
+        storage
+            .session_report_dir(&session.name)
+            .join(attachment.report_path())
+            .display()
+    );
+    println!("  blake3 {}", attachment.hash);
+    Ok(())
+}
+
+fn cmd_import(
+    config_path: Option<std::path::PathBuf>,
+    action: ImportAction,
+    json: bool,
+) -> Result<()> {
+    use yinx::network::{read_pcap, read_zeek, NetworkEvidence};
+
+    let (source, paths, session) = match action {
+        ImportAction::Archive {
+            file,
+            identity,
+            strategy,
+            dry_run,
+        } => {
+            let options = ImportOptions {
+                identity: identity.as_deref(),
+                strategy,
+                dry_run,
+            };
+            return cmd_import_archive(config_path, &file, &options, json);
+        }
+        ImportAction::Pcap { files, session } => ("pcap", files, session),
+        ImportAction::Zeek { paths, session } => ("zeek", paths, session),
+        ImportAction::Hashcat { files, session } => {
+            return cmd_import_cracked(config_path, "hashcat", files, session, json)
+        }
+        ImportAction::John { files, session } => {
+            return cmd_import_cracked(config_path, "john", files, session, json)
+        }
+    };
+    let open = |path: &std::path::Path| {
+        std::fs::File::open(path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to open {:?}", path),
+        })
+    };
+
+    let mut evidence = NetworkEvidence::default();
+    let mut records = 0;
+    for path in &paths {
+        if source == "pcap" {
+            records += read_pcap(std::io::BufReader::new(open(path)?), &mut evidence)?;
+            continue;
+        }
+        let files = if path.is_dir() {
+            let mut logs: Vec<_> = std::fs::read_dir(path)
+                .map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to read directory {:?}", path),
+                })?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| {
+                    p.extension()
+                        .is_some_and(|ext| ext == "log" || ext == "json")
+                })
+                .collect();
+            logs.sort();
+            logs
+        } else {
+            vec![path.clone()]
+        };
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            records += read_zeek(std::io::BufReader::new(open(&file)?), &name, &mut evidence)?;
+        }
+    }
+
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir);
+    let session = match session {
+        Some(session) => session_manager.resolve(&session)?,
+        None => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let session_id = session.id.to_string();
+
+    let yinx = yinx::Yinx::with_config(&config)?;
+    yinx.storage().database.ensure_session(
+        &session_id,
+        &session.name,
+        session.started_at.timestamp(),
+        &session.status_str().to_lowercase(),
+    )?;
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to start async runtime".to_string(),
+    })?;
+    // Every conversation's capture names the first path as its command
+    let stats = runtime.block_on(yinx::network::ingest(
+        &yinx,
+        &session_id,
+        source,
+        &paths[0],
+        &evidence,
+    ))?;
+
+    if json {
+        return print_json(&ImportOutput {
+            session_id,
+            source: source.to_string(),
+            records,
+            conversations: stats.conversations,
+            hosts: stats.hosts,
+            credentials: stats.credentials,
+        });
+    }
+    println!(
+        "✓ Imported {} conversation(s) between {} host(s) into {}",
+        stats.conversations, stats.hosts, session.name
+    );
+    println!(
+        "  Read {} {}",
+        records,
+        if source == "pcap" {
+            "packet(s)"
+        } else {
+            "log record(s)"
+        }
+    );
+    if stats.credentials > 0 {
+        println!("  ⚠ {} credential(s) seen in clear text", stats.credentials);
+    }
+    Ok(())
+}
+
+fn cmd_clip(
+    config_path: Option<std::path::PathBuf>,
+    note: Option<String>,
+    stdin: bool,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    use std::io::{IsTerminal, Read};
+    use yinx::clipboard::{clip_event, read_clipboard};
+
+    let text = if stdin || !std::io::stdin().is_terminal() {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| YinxError::Io {
+                source: e,
+                context: "Failed to read stdin".to_string(),
+            })?;
+        text
+    } else {
+        read_clipboard()?
+    };
+    if text.trim().is_empty() {
+        return Err(YinxError::Other(anyhow::anyhow!(
+            "Nothing to capture: the text is empty"
+        )));
+    }
+
+    let bytes = text.len();
+    let (session, capture_id) = ingest_manual(config_path, session, |session_id| {
+        let cwd = std::env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        clip_event(session_id, text, note.as_deref(), &cwd)
+    })?;
+    print_manual_capture(&session, capture_id, bytes, json)
+}
+
+fn cmd_add(
+    config_path: Option<std::path::PathBuf>,
+    source: &str,
+    tool: Option<String>,
+    timestamp: Option<String>,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    use yinx::manual::{add_event, parse_timestamp, AddSource};
+
+    let timestamp = timestamp.as_deref().map(parse_timestamp).transpose()?;
+    let source = AddSource::parse(source);
+    let text = source.read()?;
+    if text.trim().is_empty() {
+        return Err(YinxError::Other(anyhow::anyhow!(
+            "Nothing to capture: the text is empty"
+        )));
+    }
+
+    let bytes = text.len();
+    let (session, capture_id) = ingest_manual(config_path, session, |session_id| {
+        add_event(session_id, &source, text, tool.as_deref(), timestamp)
+    })?;
+    print_manual_capture(&session, capture_id, bytes, json)
+}
+
+/// Run one hand-made capture through the pipeline into a session (the
+/// active one by default)
+fn ingest_manual(
+    config_path: Option<std::path::PathBuf>,
+    session: Option<String>,
+    event: impl FnOnce(&str) -> yinx::CaptureEvent,
+) -> Result<(yinx::session::Session, Option<i64>)> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir);
+    let session = match session {
+        Some(session) => session_manager.resolve(&session)?,
+        None => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let session_id = session.id.to_string();
+
+    let yinx = yinx::Yinx::with_config(&config)?;
+    yinx.storage().database.ensure_session(
+        &session_id,
+        &session.name,
+        session.started_at.timestamp(),
+        &session.status_str().to_lowercase(),
+    )?;
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to start async runtime".to_string(),
+    })?;
+    let capture_id = runtime.block_on(yinx.ingest(event(&session_id)))?;
+    Ok((session, capture_id))
+}
+
+fn print_manual_capture(
+    session: &yinx::session::Session,
+    capture_id: Option<i64>,
+    bytes: usize,
+    json: bool,
+) -> Result<()> {
+    if json {
+        return print_json(&ManualCaptureOutput {
+            session_id: session.id.to_string(),
+            capture_id,
+            bytes,
+        });
+    }
+    match capture_id {
+        Some(id) => println!(
+            "✓ Stored {} bytes as capture {} in {}",
+            bytes, id, session.name
+        ),
+        None => println!("Same text was captured moments ago; counted as a repeat"),
+    }
+    Ok(())
+}
+
+fn cmd_import_cracked(
+    config_path: Option<std::path::PathBuf>,
+    source: &str,
+    files: Vec<std::path::PathBuf>,
+    session: Option<String>,
+    json: bool,
+) -> Result<()> {
+    use yinx::cracked::{match_cracked, record_cracked};
+
+    let config = load_config(config_path, None)?;
+    if !config.vault.enabled {
+        return Err(YinxError::Vault(
+            "Cracked passwords are stored sealed; enable the vault (vault.enabled) first"
+                .to_string(),
+        ));
+    }
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_id = match session {
+        Some(session) => Some(
+            SessionManager::new(data_dir.clone())
+                .resolve(&session)?
+                .id
+                .to_string(),
+        ),
+        None => None,
+    };
+
+    let mut output = String::new();
+    for file in &files {
+        output.push_str(&std::fs::read_to_string(file).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read {:?}", file),
+        })?);
+        output.push('\n');
+    }
+
+    let storage = StorageManager::new(data_dir)?.with_vault(Vault::open_or_create(&expand_path(
+        &config.vault.key_file,
+    )?)?);
+    let result = match_cracked(&storage, &output, session_id.as_deref())?;
+    let stats = record_cracked(&storage, &result.matched, source)?;
+
+    if json {
+        return print_json(&CrackImportOutput {
+            source: source.to_string(),
+            lines: result.lines,
+            matched: result.matched.len(),
+            unmatched: result.unmatched,
+            credentials: stats.credentials,
+            findings: stats.findings,
+            escalated: stats.escalated,
+        });
+    }
+    println!(
+        "✓ Matched {} of {} cracked hash(es) to captured evidence",
+        result.lines - result.unmatched,
+        result.lines
+    );
+    println!(
+        "  {} new credential(s) sealed in the vault",
+        stats.credentials
+    );
+    if stats.findings > 0 || stats.escalated > 0 {
+        println!(
+            "  {} finding(s) opened, {} raised to high",
+            stats.findings, stats.escalated
+        );
+    }
+    Ok(())
+}
+
+fn cmd_filters(
+    config_path: Option<std::path::PathBuf>,
+    action: FiltersAction,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+
+    match action {
+        FiltersAction::Stats { session } => {
+            let session_id = match session {
+                Some(session) => Some(
+                    SessionManager::new(data_dir.clone())
+                        .resolve(&session)?
+                        .id
+                        .to_string(),
+                ),
+                None => None,
+            };
+            let stats = StorageManager::new(data_dir)?
+                .database
+                .filter_stats_by_tool(session_id.as_deref())?;
+
+            if json {
+                let stats: Vec<_> = stats.iter().map(FilterStatsOutput::from).collect();
+                return print_json(&stats);
+            }
+            if stats.is_empty() {
+                println!("No filter statistics recorded yet");
+                return Ok(());
+            }
+
+            println!(
+                "{:<16} {:>8} {:>10} {:>9} {:>9} {:>9} {:>9} {:>6} {:>9}",
+                "Tool",
+                "Captures",
+                "Lines",
+                "Tier1",
+                "Tier2",
+                "Clusters",
+                "Reduced",
+                "Pctl",
+                "Median/P90"
+            );
+            for tool in &stats {
+                println!(
+                    "{:<16} {:>8} {:>10} {:>9} {:>9} {:>9} {:>8.1}% {:>6.2} {:>4.2}/{:.2}",
+                    if tool.tool.is_empty() {
+                        "(unknown)"
+                    } else {
+                        &tool.tool
+                    },
+                    tool.captures,
+                    tool.input_lines,
+                    tool.tier1_output,
+                    tool.tier2_output,
+                    tool.tier3_clusters,
+                    tool.reduction() * 100.0,
+                    tool.avg_percentile,
+                    tool.avg_score_median,
+                    tool.avg_score_p90
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_patterns_validate(
+    config_path: Option<std::path::PathBuf>,
+    strict: bool,
+    json: bool,
+) -> Result<()> {
+    use yinx::patterns::{lint_pattern_files, PatternFiles, Severity};
+
+    let config = load_config(config_path, None)?;
+    let files = PatternFiles {
+        entities: expand_path(&config.patterns.entities_file)?,
+        tools: expand_path(&config.patterns.tools_file)?,
+        filters: expand_path(&config.patterns.filters_file)?,
+        rules: expand_path(&config.patterns.rules_file)?,
+    };
+    let issues = lint_pattern_files(&files);
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+
+    if json {
+        print_json(&issues)?;
+    } else {
+        for issue in &issues {
+            let at = match (issue.line, issue.column) {
+                (Some(line), Some(column)) => format!(":{}:{}", line, column),
+                (Some(line), None) => format!(":{}", line),
+                _ => String::new(),
+            };
+            println!(
+                "{}{}: {}: {}",
+                issue.file.display(),
+                at,
+                issue.severity.as_str(),
+                issue.message
+            );
+        }
+        if issues.is_empty() {
+            println!("✓ Pattern files are valid");
+        } else {
+            println!("{} error(s), {} warning(s)", errors, warnings);
+        }
+    }
+
+    if errors > 0 || (strict && warnings > 0) {
+        return Err(YinxError::Config(format!(
+            "Pattern files have {} error(s) and {} warning(s)",
+            errors, warnings
+        )));
+    }
+    Ok(())
+}
+
+fn cmd_patterns_stats(
+    config_path: Option<std::path::PathBuf>,
+    limit: usize,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    if !ProcessManager::new(expand_path(&config.daemon.pid_file)?).is_running() {
+        return Err(YinxError::Daemon(
+            "Daemon is not running; pattern stats are kept by the running daemon".to_string(),
+        ));
+    }
+    let report = daemon_report(&config)
+        .ok_or_else(|| YinxError::Daemon("Daemon did not answer".to_string()))?;
+    let mut stats = report.patterns;
+    stats.sort_by(|a, b| b.time_us.cmp(&a.time_us).then(b.matches.cmp(&a.matches)));
+    let total_us: u64 = stats.iter().map(|pattern| pattern.time_us).sum();
+    stats.truncate(limit);
+
+    if json {
+        return print_json(&stats);
+    }
+    if stats.iter().all(|pattern| pattern.scans == 0) {
+        println!("No entity patterns have run since the daemon started");
+        return Ok(());
+    }
+
+    println!(
+        "{:<24} {:>8} {:>10} {:>12} {:>10} {:>7} {:>10}",
+        "Type", "Scans", "Matches", "Matches/scan", "Time", "Share", "MiB/s"
+    );
+    for pattern in &stats {
+        println!(
+            "{:<24} {:>8} {:>10} {:>12.1} {:>8.1}ms {:>6.1}% {:>10}",
+            pattern.type_name,
+            pattern.scans,
+            pattern.matches,
+            pattern.matches_per_scan(),
+            pattern.time_us as f64 / 1000.0,
+            pattern.time_us as f64 * 100.0 / total_us.max(1) as f64,
+            pattern
+                .throughput()
+                .map_or("-".to_string(), |mibs| format!("{:.1}", mibs))
+        );
+    }
+    Ok(())
+}
+
+fn cmd_ask(
+    config_path: Option<std::path::PathBuf>,
+    question: &str,
+    offline: bool,
+    context_size: usize,
+    session: Option<String>,
+) -> Result<()> {
+    use yinx::ask::{ask, AskOptions};
+    use yinx::embedding::{EmbeddingProvider, FastEmbedProvider};
+    use yinx::patterns::PatternRegistry;
+
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+    let session = match session {
+        Some(session) => session_manager.resolve(&session)?,
+        None => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let storage = StorageManager::new(data_dir)?;
+
+    // Secrets are screened out of the request with these; never skip that
+    // for want of pattern files
+    let patterns = PatternRegistry::from_config_files(
+        &expand_path(&config.patterns.entities_file)?,
+        &expand_path(&config.patterns.tools_file)?,
+        &expand_path(&config.patterns.filters_file)?,
+    )
+    .or_else(|e| {
+        tracing::warn!("Using the bundled patterns: {}", e);
+        PatternRegistry::bundled()
+    })?;
+
+    // The cache is keyed by question embedding; only worth a model load
+    // when an answer would otherwise be generated
+    let online = config.llm.enabled && !offline;
+    let embedder = if online && config.llm.answer_cache {
+        match FastEmbedProvider::new(&config.embedding.model) {
+            Ok(embedder) => Some(embedder),
+            Err(e) => {
+                tracing::warn!("Answer cache unavailable: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let answer = ask(
+        &storage,
+        &config,
+        &patterns,
+        embedder.as_ref().map(|e| e as &dyn EmbeddingProvider),
+        &session.id.to_string(),
+        question,
+        AskOptions {
+            context_size,
+            offline,
+        },
+    )?;
+
+    if let Some(cached) = &answer.cached {
+        println!(
+            "(cached answer to \"{}\", {:.0}% similar; served {} time(s))\n",
+            cached.question,
+            cached.similarity * 100.0,
+            cached.hits
+        );
+    }
+    for finding in &answer.injections {
+        println!(
+            "Warning: chunk {} line {} looks like an instruction to the model ({}){}",
+            finding.chunk_id,
+            finding.line,
+            finding.rule,
+            if finding.stripped { ", removed" } else { "" }
+        );
+    }
+    match &answer.text {
+        Some(text) => println!("{}", text),
+        None if answer.context.chunks.is_empty() => {
+            println!(
+                "No captured output in {} matches the question",
+                session.name
+            );
+        }
+        None => {
+            if !online {
+                println!("LLM disabled; the captured output that matches the question:\n");
+            }
+            println!("{}", answer.context.render());
+        }
+    }
+
+    if answer.cached.is_none() && !answer.context.chunks.is_empty() {
+        let captures: std::collections::BTreeSet<i64> =
+            answer.context.chunks.iter().map(|c| c.capture_id).collect();
+        println!(
+            "\nContext: {} chunk(s), {}/{} tokens, from capture(s) {}",
+            answer.context.chunks.len(),
+            answer.context.tokens,
+            answer.context.budget,
+            captures
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn cmd_report(
+    config_path: Option<std::path::PathBuf>,
+    output: Option<std::path::PathBuf>,
+    format: &str,
+    session: Option<String>,
+    include_evidence: bool,
+    sign: Option<std::path::PathBuf>,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+    let session = match session {
+        Some(session) => session_manager.resolve(&session)?,
+        None => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let storage = StorageManager::new(data_dir)?;
+    let report = yinx::report::build_report(&storage, &session, &config.report, include_evidence)?;
+
+    let (contents, extension) = match format {
+        "json" => (
+            serde_json::to_string_pretty(&report).map_err(|e| YinxError::Json {
+                source: e,
+                context: "Failed to serialize report".to_string(),
+            })?,
+            "json",
+        ),
+        _ => (report.to_markdown(), "md"),
+    };
+    let output = match output {
+        Some(output) => output,
+        // An opened store's report directory isn't ours to write to
+        None if yinx::storage::readonly::is_read_only() => {
+            std::path::PathBuf::from(format!("{}-report.{}", session.name, extension))
+        }
+        None => storage
+            .ensure_session_report_dir(&session.name)?
+            .join(format!("report.{}", extension)),
+    };
+    std::fs::write(&output, contents).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to write report: {}", output.display()),
+    })?;
+
+    let mut manifest = None;
+    let mut signature = None;
+    let mut tampered = Vec::new();
+    if include_evidence {
+        let evidence = storage.evidence_manifest(&report.session_id, &session.name)?;
+        tampered = evidence.tampered().iter().map(|f| f.path.clone()).collect();
+        let path = storage.write_evidence_manifest(&evidence)?;
+        if let Some(key) = &sign {
+            signature = Some(yinx::storage::custody::sign_manifest(&path, key)?);
+        }
+        manifest = Some(path);
+    }
+
+    let findings = report.findings.as_ref().map_or(0, Vec::len);
+    if json {
+        return print_json(&ReportOutput {
+            session_id: report.session_id,
+            format: format.to_string(),
+            path: output.display().to_string(),
+            sections: report
+                .sections
+                .iter()
+                .map(|s| s.title().to_string())
+                .collect(),
+            findings,
+            manifest: manifest.map(|p| p.display().to_string()),
+            signature: signature.map(|p| p.display().to_string()),
+            tampered,
+        });
+    }
+    println!(
+        "✓ Wrote {} report for {} ({} section(s), {} finding(s)) to {}",
+        format,
+        session.name,
+        report.sections.len(),
+        findings,
+        output.display()
+    );
+    if let Some(manifest) = &manifest {
+        println!("✓ Wrote evidence manifest to {}", manifest.display());
+    }
+    if let Some(signature) = &signature {
+        println!("✓ Signed it: {}", signature.display());
+    }
+    for path in &tampered {
+        eprintln!(
+            "⚠ {} is missing or no longer matches the hash recorded when it was attached",
+            path
+        );
+    }
+    Ok(())
+}
+
+fn cmd_export(
+    config_path: Option<std::path::PathBuf>,
+    output: &std::path::Path,
+    session: Option<String>,
+    include_indexes: bool,
+    selection: &CaptureSelection,
+    encryption: &Encryption,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+    let session = match session {
+        Some(session) => session_manager.resolve(&session)?,
+        None => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let session_id = session.id.to_string();
+    if include_indexes {
+        tracing::warn!("Indexes are rebuilt from chunks on import; --include-indexes is ignored");
+    }
+
+    let storage = StorageManager::new(data_dir)?;
+    let ids = storage.select_captures(&session_id, selection)?;
+    if ids.is_empty() {
+        let what = if selection.is_everything() {
+            "no captures"
+        } else {
+            "no captures matching the selection"
+        };
+        return Err(YinxError::Session(format!(
+            "Session {} has {} to export",
+            session.name, what
+        )));
+    }
+    let stats = yinx::sync::archive::export_archive(&storage, &ids, output, encryption)?;
+
+    if json {
+        return print_json(&ArchiveExportOutput {
+            session_id,
+            path: output.display().to_string(),
+            captures: stats.captures,
+            blobs: stats.blobs,
+            bytes: stats.bytes,
+            encrypted: *encryption != Encryption::None,
+        });
+    }
+    println!(
+        "✓ Exported {} capture(s) and {} blob(s) from {} to {} ({})",
+        stats.captures,
+        stats.blobs,
+        session.name,
+        output.display(),
+        StorageStats::format_size(stats.bytes)
+    );
+    match encryption {
+        Encryption::None => {}
+        Encryption::Passphrase => println!("  Encrypted with a passphrase"),
+        Encryption::Recipients(recipients) => {
+            println!("  Encrypted to {} recipient(s)", recipients.len())
+        }
+    }
+    Ok(())
+}
+
+/// A `--since`/`--until` value: a point in time, or an interval before now
+fn parse_time_bound(flag: &str, value: &str) -> Result<i64> {
+    yinx::manual::parse_timestamp(value).or_else(|_| {
+        yinx::config::parse_duration(value)
+            .map(|ago| chrono::Utc::now().timestamp() - ago.as_secs() as i64)
+            .ok_or_else(|| YinxError::InvalidConfigValue {
+                path: flag.to_string(),
+                message: format!(
+                    "'{}' is neither a time (e.g. 2025-07-01 14:30) nor an interval (e.g. 24h)",
+                    value
+                ),
+            })
+    })
+}
+
+fn cmd_import_archive(
+    config_path: Option<std::path::PathBuf>,
+    file: &std::path::Path,
+    options: &ImportOptions,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let storage = StorageManager::new(expand_path(&config.storage.data_dir)?)?;
+    let stats = yinx::sync::archive::import_archive(&storage, file, options)?;
+    let diff = &stats.diff;
+
+    if json {
+        return print_json(&ArchiveImportOutput {
+            path: file.display().to_string(),
+            sessions: stats.sessions,
+            captures: stats.captures,
+            imported: stats.imported,
+            updated: stats.updated,
+            blobs: stats.blobs,
+            encrypted: stats.encrypted,
+            strategy: options.strategy.as_str().to_string(),
+            dry_run: options.dry_run,
+            diff: ImportDiffOutput {
+                new_sessions: diff.new_sessions,
+                existing_sessions: diff.existing_sessions,
+                new_captures: diff.new_captures,
+                existing_captures: diff.existing_captures,
+                new_entities: diff.new_entities,
+                new_blobs: diff.new_blobs,
+                existing_blobs: diff.existing_blobs,
+            },
+        });
+    }
+
+    println!("{}", file.display());
+    println!(
+        "  Sessions: {} new, {} already here",
+        diff.new_sessions, diff.existing_sessions
+    );
+    println!(
+        "  Captures: {} new, {} already here ({} entities they lack)",
+        diff.new_captures, diff.existing_captures, diff.new_entities
+    );
+    println!(
+        "  Blobs:    {} new, {} already here",
+        diff.new_blobs, diff.existing_blobs
+    );
+    if options.dry_run {
+        println!(
+            "Dry run: nothing imported (strategy {})",
+            options.strategy.as_str()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "✓ Imported {} of {} capture(s) ({} session(s), {} blob(s))",
+        stats.imported, stats.captures, stats.sessions, stats.blobs
+    );
+    match options.strategy {
+        ConflictStrategy::Overwrite if stats.updated > 0 => {
+            println!("  Overwrote {} local capture(s)", stats.updated)
+        }
+        ConflictStrategy::Merge if stats.updated > 0 => {
+            println!("  Merged entities into {} local capture(s)", stats.updated)
+        }
+        _ if stats.imported + stats.updated < stats.captures => println!(
+            "  {} capture(s) were already in this store and were skipped",
+            stats.captures - stats.imported - stats.updated
+        ),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn cmd_export_as(
+    config_path: Option<std::path::PathBuf>,
+    output: &std::path::Path,
+    session: Option<String>,
+    format: SessionExportFormat,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+    let session = match session {
+        Some(session) => session_manager.resolve(&session)?,
+        None => session_manager
+            .find_active()?
+            .ok_or_else(|| YinxError::Session("No active session; pass --session".to_string()))?,
+    };
+    let session_id = session.id.to_string();
+    let storage = StorageManager::new(data_dir)?;
+    let write = |contents: String| {
+        std::fs::write(output, contents).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write {:?}", output),
+        })
+    };
+
+    if format == SessionExportFormat::BloodHound {
+        let graph = yinx::bloodhound::export_session(&storage, &session_id)?;
+        write(
+            serde_json::to_string_pretty(&graph).map_err(|e| YinxError::Json {
+                source: e,
+                context: "Failed to serialize BloodHound graph".to_string(),
+            })?,
+        )?;
+
+        let (nodes, edges) = (graph.graph.nodes.len(), graph.graph.edges.len());
+        if json {
+            return print_json(&GraphExportOutput {
+                session_id,
+                path: output.display().to_string(),
+                nodes,
+                edges,
+            });
+        }
+        if nodes == 0 {
+            println!("No Active Directory entities captured in {}", session.name);
+        }
+        println!(
+            "✓ Wrote {} node(s) and {} edge(s) to {}",
+            nodes,
+            edges,
+            output.display()
+        );
+        return Ok(());
+    }
+
+    let notes = storage.session_notes(&session_id, &session.name)?;
+    write(match format {
+        SessionExportFormat::Opml => notes.to_opml(),
+        _ => notes.to_cherrytree(),
+    })?;
+    let hosts = notes
+        .children
+        .iter()
+        .filter(|node| node.title != yinx::storage::notes::OTHER_FINDINGS)
+        .count();
+    if json {
+        return print_json(&NotesExportOutput {
+            session_id,
+            format: format.as_str().to_string(),
+            path: output.display().to_string(),
+            hosts,
+            notes: notes.descendants(),
+        });
+    }
+    println!(
+        "✓ Wrote {} note(s) covering {} host(s) to {}",
+        notes.descendants(),
+        hosts,
+        output.display()
+    );
+    Ok(())
+}
+
+fn cmd_sessions(
+    config_path: Option<std::path::PathBuf>,
+    action: SessionsAction,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_manager = SessionManager::new(data_dir.clone());
+
+    match action {
+        SessionsAction::List => {
+            let sessions = session_manager.list_sessions()?;
+            if json {
+                let sessions: Vec<_> = sessions.iter().map(SessionOutput::from).collect();
+                return print_json(&sessions);
+            }
+            if sessions.is_empty() {
+                println!("No sessions found");
+                return Ok(());
+            }
+
+            for session in &sessions {
+                println!(
+                    "  {} - {} ({}) [{}]",
+                    session.name,
+                    session.status_str(),
+                    session.started_at.format("%Y-%m-%d %H:%M:%S"),
+                    session.id
+                );
+            }
+        }
+        SessionsAction::Archive { session } => {
+            let mut session = session_manager.resolve(&session)?;
+            if session.is_archived() {
+                if json {
+                    return print_session_action("unchanged", &session, None);
+                }
+                println!("Session {} is already archived", session.name);
+                return Ok(());
+            }
+            if session.status == yinx::session::SessionStatus::Active {
+                return Err(YinxError::Session(format!(
+                    "Session {} is still active; stop it before archiving",
+                    session.name
+                )));
+            }
+
+            let storage = StorageManager::new(data_dir.clone())?;
+            let stats = storage.archive_session(&session.id.to_string())?;
+
+            session.archive();
+            session.save(&data_dir)?;
+
+            if json {
+                return print_session_action("archived", &session, Some(&stats));
+            }
+
+            println!("✓ Archived session {}", session.name);
+            println!(
+                "  Blobs recompressed: {} ({} saved)",
+                stats.blobs_recompressed,
+                StorageStats::format_size(stats.bytes_saved())
+            );
+            println!("  Chunks removed from index: {}", stats.chunks_indexed);
+            println!("  Embeddings dropped: {}", stats.embeddings_dropped);
+        }
+        SessionsAction::Resume { session } => {
+            let mut session = session_manager.resolve(&session)?;
+            if session.status != yinx::session::SessionStatus::Interrupted {
+                if json {
+                    return print_session_action("unchanged", &session, None);
+                }
+                println!("Session {} is not interrupted", session.name);
+                return Ok(());
+            }
+
+            let pm = ProcessManager::new(expand_path(&config.daemon.pid_file)?);
+            if pm.is_running() {
+                return Err(YinxError::DaemonAlreadyRunning {
+                    pid: pm.read_pid().unwrap_or_default() as u32,
+                });
+            }
+
+            session.resume();
+            session.metadata.insert(
+                "resumed_at".to_string(),
+                serde_json::Value::String(chrono::Utc::now().to_rfc3339()),
+            );
+            session.save(&data_dir)?;
+
+            if json {
+                print_session_action("resumed", &session, None)?;
+            } else {
+                println!("✓ Resuming session {} ({})", session.name, session.id);
+            }
+            let mut daemon = Daemon::new(config)?.with_session(session.id);
+            warn_pattern_fallback(&daemon);
+            daemon.start_daemon()?;
+        }
+        SessionsAction::Close { session } => {
+            let mut session = session_manager.resolve(&session)?;
+            if session.status != yinx::session::SessionStatus::Interrupted {
+                if json {
+                    return print_session_action("unchanged", &session, None);
+                }
+                println!("Session {} is not interrupted", session.name);
+                return Ok(());
+            }
+
+            session.close_interrupted();
+            session.save(&data_dir)?;
+            let storage = StorageManager::new(data_dir)?;
+            storage.database.set_session_status(
+                &session.id.to_string(),
+                "stopped",
+                session.stopped_at.map(|t| t.timestamp()),
+            )?;
+
+            if json {
+                return print_session_action("closed", &session, None);
+            }
+            println!("✓ Closed session {}", session.name);
+        }
+        SessionsAction::Unarchive { session } => {
+            let mut session = session_manager.resolve(&session)?;
+            if !session.is_archived() {
+                if json {
+                    return print_session_action("unchanged", &session, None);
+                }
+                println!("Session {} is not archived", session.name);
+                return Ok(());
+            }
+
+            let storage = StorageManager::new(data_dir.clone())?;
+            let stats = storage.unarchive_session(&session.id.to_string())?;
+
+            session.unarchive();
+            session.save(&data_dir)?;
+
+            if json {
+                return print_session_action("unarchived", &session, Some(&stats));
+            }
+
+            println!("✓ Unarchived session {}", session.name);
+            println!("  Chunks restored to index: {}", stats.chunks_indexed);
+            println!(
+                "  Embeddings not restored; the chunks are back in the embedding backlog (see yinx status)"
+            );
+        }
+        SessionsAction::Delete { session, purge } => {
+            let session = session_manager.resolve(&session)?;
+            if matches!(
+                session.status,
+                yinx::session::SessionStatus::Active | yinx::session::SessionStatus::Paused
+            ) {
+                return Err(YinxError::Session(format!(
+                    "Session {} is still running; stop it before deleting",
+                    session.name
+                )));
+            }
+
+            // The database goes first so a failed run can simply be repeated
+            let storage = StorageManager::new(data_dir.clone())?;
+            let stats = storage.purge_session(&session.id.to_string(), !purge)?;
+            if purge {
+                session_manager.delete_session(&session.id)?;
+            }
+
+            if json {
+                return print_json(&SessionActionOutput {
+                    action: if purge { "deleted" } else { "would_delete" },
+                    session: SessionOutput::from(&session),
+                    archive: None,
+                    purge: Some(PurgeOutput::from(&stats)),
+                });
+            }
+
+            if purge {
+                println!("✓ Deleted session {}", session.name);
+            } else {
+                println!("Deleting session {} would remove:", session.name);
+            }
+            println!("  Captures: {}", stats.captures);
+            println!("  Chunks: {}", stats.chunks);
+            println!("  Embeddings: {}", stats.embeddings);
+            println!("  Entities: {}", stats.entities);
+            println!("  Findings: {}", stats.findings);
+            println!(
+                "  Blobs: {} ({} freed), {} shared with other sessions kept",
+                stats.blobs_deleted,
+                StorageStats::format_size(stats.bytes_freed),
+                stats.blobs_shared
+            );
+            println!("  DNS and ASN lookups: {}", stats.lookups);
+            let reports = storage.session_report_dir(&session.name);
+            if reports.exists() {
+                println!("  Reports and evidence kept in {}", reports.display());
+            }
+            if !purge {
+                println!();
+                println!("Nothing was deleted; run again with --purge to delete it");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_session_action(
+    action: &'static str,
+    session: &yinx::session::Session,
+    archive: Option<&yinx::storage::ArchiveStats>,
+) -> Result<()> {
+    print_json(&SessionActionOutput {
+        action,
+        session: SessionOutput::from(session),
+        archive: archive.map(ArchiveOutput::from),
+        purge: None,
+    })
+}
+
+fn cmd_sync(
+    config_path: Option<std::path::PathBuf>,
+    peer: &str,
+    remote_bin: &str,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
+    let storage = StorageManager::new(expand_path(&config.storage.data_dir)?)?;
+
+    if json {
+        let stats = yinx::sync::sync_with_peer(&storage, peer, remote_bin)?;
+        return print_json(&SyncOutput::new(peer, &stats));
+    }
+
+    println!("Syncing with {}...", peer);
+    let stats = yinx::sync::sync_with_peer(&storage, peer, remote_bin)?;
+
+    println!("✓ Sync complete");
+    println!(
+        "  Received: {} capture(s), {} blob(s)",
+        stats.received, stats.blobs_received
+    );
+    println!(
+        "  Sent: {} capture(s), {} blob(s)",
+        stats.sent, stats.blobs_sent
+    );
+
+    Ok(())
+}
+
+fn cmd_serve(config_path: Option<std::path::PathBuf>, web: Option<String>) -> Result<()> {
+    let Some(addr) = web else {
+        return Err(YinxError::Config(
+            "Nothing to serve; pass --web [ADDR] to start the web UI".to_string(),
+        ));
+    };
+    let addr: std::net::SocketAddr = addr.parse().map_err(|_| YinxError::InvalidConfigValue {
+        path: "--web".to_string(),
+        message: format!("'{}' is not a valid address (expected IP:PORT)", addr),
+    })?;
+
+    let config = load_config(config_path, None)?;
+    let state = yinx::web::WebState::new(expand_path(&config.storage.data_dir)?)?;
 
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("yinx=info"));
+    println!("✓ Web UI listening on http://{}", addr);
+    println!("  Press Ctrl-C to stop");
 
-    fmt().with_env_filter(filter).with_target(false).init();
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to start async runtime".to_string(),
+    })?;
+    runtime.block_on(yinx::web::serve(addr, state))
 }
 
-fn cmd_start(
+fn cmd_agent(
     config_path: Option<std::path::PathBuf>,
+    upstream_arg: &str,
+    token_file: Option<std::path::PathBuf>,
     session: Option<String>,
-    profile: Option<String>,
+    spool: Option<std::path::PathBuf>,
+    batch_size: usize,
 ) -> Result<()> {
-    tracing::info!("Starting yinx daemon...");
+    let upstream: yinx::agent::Upstream = upstream_arg.parse()?;
+    let token = match token_file {
+        Some(path) => Some(
+            std::fs::read_to_string(&path)
+                .map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to read token file: {:?}", path),
+                })?
+                .trim()
+                .to_string(),
+        ),
+        None => std::env::var("YINX_AGENT_TOKEN").ok(),
+    };
 
-    // Load configuration
-    let config = load_config(config_path, profile)?;
+    let config = load_config(config_path, None)?;
+    let spool_dir = match spool {
+        Some(dir) => dir,
+        None => expand_path(&config.storage.data_dir)?.join("agent"),
+    };
+    let options = yinx::agent::AgentOptions {
+        upstream,
+        token,
+        socket_path: expand_path(&config.daemon.socket_path)?,
+        spool_dir,
+        session,
+        batch_size: batch_size.max(1),
+        retry_interval: std::time::Duration::from_secs(5),
+        user: std::env::var("USER").ok(),
+    };
 
-    tracing::info!("Configuration loaded successfully");
+    println!("✓ Agent spooling to {}", options.spool_dir.display());
+    println!("  Forwarding to {}", upstream_arg);
+    println!("  Press Ctrl-C to stop");
 
-    // Initialize session manager
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to start async runtime".to_string(),
+    })?;
+    runtime.block_on(yinx::agent::run(options))
+}
+
+fn cmd_scrub(
+    config_path: Option<std::path::PathBuf>,
+    value: &str,
+    regex: bool,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let config = load_config(config_path, None)?;
     let data_dir = expand_path(&config.storage.data_dir)?;
-    let mut session_manager = SessionManager::new(data_dir);
 
-    // Create new session
-    let session = session_manager.create_session(session)?;
+    // Blob hashes change underneath the daemon, so it must not be writing
+    let pm = ProcessManager::new(expand_path(&config.daemon.pid_file)?);
+    if !dry_run && pm.is_running() {
+        return Err(YinxError::DaemonAlreadyRunning {
+            pid: pm.read_pid().unwrap_or_default() as u32,
+        });
+    }
 
-    println!("✓ Starting yinx daemon...");
-    println!("  Session: {} ({})", session.name, session.id);
-    println!(
-        "  Started: {}",
-        session.started_at.format("%Y-%m-%d %H:%M:%S")
-    );
+    let pattern = if regex {
+        ScrubPattern::regex(value)?
+    } else {
+        ScrubPattern::literal(value)?
+    };
 
-    // Start daemon (this will fork - parent exits, child continues)
-    let mut daemon = Daemon::new(config)?;
-    daemon.start_daemon()?;
+    let mut storage = StorageManager::new(data_dir)?;
+    if config.vault.enabled {
+        storage = storage.with_vault(Vault::open_or_create(&expand_path(
+            &config.vault.key_file,
+        )?)?);
+    }
+    let report = storage.scrub(&pattern, dry_run)?;
 
-    // This line is never reached in parent (parent exits in daemon.start())
-    // Child process continues as daemon
+    if json {
+        return print_json(&ScrubOutput::new(&report, dry_run));
+    }
+
+    if report.is_empty() {
+        println!("Value not found in captured history");
+    } else {
+        println!(
+            "Found {} occurrence(s) in {} record(s):",
+            report.total_occurrences(),
+            report.hits.len()
+        );
+        for hit in &report.hits {
+            let capture = hit
+                .capture_id
+                .map(|id| format!(" capture {}", id))
+                .unwrap_or_default();
+            println!(
+                "  session {}{} - {} ({}x)",
+                hit.session_id,
+                capture,
+                hit.location.as_str(),
+                hit.occurrences
+            );
+        }
+        if report.slow_log_entries > 0 {
+            println!("  slow query log - {} entries", report.slow_log_entries);
+        }
+
+        let verb = if dry_run { "Would rewrite" } else { "Rewrote" };
+        println!();
+        println!("{} {} blob(s)", verb, report.blobs_rewritten);
+        println!("  Entities removed: {}", report.entities_removed);
+        println!("  Cached answers dropped: {}", report.answers_dropped);
+        if !dry_run {
+            println!("  Chunks reindexed: {}", report.chunks_reindexed);
+            println!("  Embeddings dropped: {}", report.embeddings_dropped);
+        }
+        if report.positions_dropped > 0 {
+            println!(
+                "  Entity positions dropped for {} capture(s); run 'yinx refilter' to re-derive them",
+                report.positions_dropped
+            );
+        }
+    }
+
+    if report.sealed_unchecked > 0 {
+        println!(
+            "⚠ {} sealed command original(s) not checked (vault disabled)",
+            report.sealed_unchecked
+        );
+    }
+    if dry_run {
+        println!("Dry run: nothing was changed");
+    }
 
     Ok(())
 }
 
-fn cmd_stop() -> Result<()> {
-    use std::thread::sleep;
-    use std::time::Duration;
-
-    // Load config to get daemon settings
-    let config = load_config(None, None)?;
-    let pid_file = expand_path(&config.daemon.pid_file)?;
+fn cmd_verify(config_path: Option<std::path::PathBuf>, repair: bool, json: bool) -> Result<()> {
+    let config = load_config(config_path, None)?;
 
-    // Check daemon status
-    let pm = ProcessManager::new(pid_file);
-    if !pm.is_running() {
-        println!("Daemon is not running");
-        return Ok(());
+    // Repairs rewrite rows and the keyword index the daemon is writing to
+    let pm = ProcessManager::new(expand_path(&config.daemon.pid_file)?);
+    if repair && pm.is_running() {
+        return Err(YinxError::DaemonAlreadyRunning {
+            pid: pm.read_pid().unwrap_or_default() as u32,
+        });
     }
 
-    // Try graceful shutdown first (SIGTERM)
-    println!("Sending SIGTERM to daemon...");
-    pm.signal(nix::sys::signal::Signal::SIGTERM)?;
+    let storage = StorageManager::new(expand_path(&config.storage.data_dir)?)?;
+    let report = yinx::verify::verify(&storage, repair)?;
 
-    // Wait for daemon to stop gracefully
-    for i in 0..5 {
-        sleep(Duration::from_millis(500));
-        if !pm.is_running() {
-            println!("✓ Daemon stopped gracefully");
-            return Ok(());
+    if json {
+        print_json(&VerifyOutput::new(&report, repair))?;
+    } else {
+        println!(
+            "Checked {} session(s), {} capture(s), {} chunk(s), {} blob(s)",
+            report.sessions, report.captures, report.chunks, report.blobs
+        );
+        for issue in &report.issues {
+            println!(
+                "  {} {} [{}]: {}",
+                if issue.repaired { "✓" } else { "✗" },
+                issue.check.as_str(),
+                issue.subject,
+                issue.detail
+            );
         }
-        if i < 4 {
-            print!(".");
-            use std::io::Write;
-            std::io::stdout().flush().ok();
+        if report.embeddings_queued > 0 {
+            println!(
+                "  Chunks waiting for an embedding: {}",
+                report.embeddings_queued
+            );
         }
-    }
-
-    // If still running, force kill with SIGKILL
-    if pm.is_running() {
-        println!("\nDaemon not responding, sending SIGKILL...");
-        pm.signal(nix::sys::signal::Signal::SIGKILL)?;
-        sleep(Duration::from_millis(500));
-
-        if !pm.is_running() {
-            println!("✓ Daemon force killed");
+        if report.index_pending > 0 {
+            println!(
+                "  Chunks not in the keyword index yet: {} (the daemon catches up, or run with --repair)",
+                report.index_pending
+            );
+        }
+        if report.issues.is_empty() {
+            println!("✓ Everything is consistent");
+        } else if repair {
+            println!(
+                "Repaired {} of {} issue(s)",
+                report.issues.len() - report.unrepaired(),
+                report.issues.len()
+            );
         } else {
-            println!("⚠ Warning: Daemon may still be running (PID file stale)");
+            println!("Run again with --repair to fix what can be fixed");
         }
     }
 
+    let unrepaired = report.unrepaired();
+    if unrepaired > 0 {
+        return Err(YinxError::Other(anyhow::anyhow!(
+            "{} inconsistenc{} left",
+            unrepaired,
+            if unrepaired == 1 { "y" } else { "ies" }
+        )));
+    }
     Ok(())
 }
 
-fn cmd_status(config_path: Option<std::path::PathBuf>) -> Result<()> {
+fn cmd_refilter(
+    config_path: Option<std::path::PathBuf>,
+    session: Option<String>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    use yinx::patterns::PatternRegistry;
+
     let config = load_config(config_path, None)?;
-    let pid_file = expand_path(&config.daemon.pid_file)?;
     let data_dir = expand_path(&config.storage.data_dir)?;
-    let session_manager = SessionManager::new(data_dir);
 
-    // Check daemon status
-    let pm = ProcessManager::new(pid_file);
-    let daemon_status = if pm.is_running() {
-        if let Ok(pid) = pm.read_pid() {
-            format!("Running (PID: {})", pid)
-        } else {
-            "Running".to_string()
-        }
-    } else {
-        "Stopped".to_string()
+    // Chunk ids change underneath the daemon's keyword index writer
+    let pm = ProcessManager::new(expand_path(&config.daemon.pid_file)?);
+    if !dry_run && pm.is_running() {
+        return Err(YinxError::DaemonAlreadyRunning {
+            pid: pm.read_pid().unwrap_or_default() as u32,
+        });
+    }
+
+    let session_id = match session {
+        Some(session) => Some(
+            SessionManager::new(data_dir.clone())
+                .resolve(&session)?
+                .id
+                .to_string(),
+        ),
+        None => None,
     };
+    let patterns = PatternRegistry::from_config_files(
+        &expand_path(&config.patterns.entities_file)?,
+        &expand_path(&config.patterns.tools_file)?,
+        &expand_path(&config.patterns.filters_file)?,
+    )?;
 
-    println!("Yinx Status");
-    println!("===========");
-    println!("\nDaemon: {}", daemon_status);
+    let storage = StorageManager::new(data_dir)?;
+    let report = yinx::refilter::refilter(&storage, patterns, session_id.as_deref(), dry_run)?;
 
-    // List sessions
-    let sessions = session_manager.list_sessions()?;
-    println!("\nSessions: {} total", sessions.len());
+    if json {
+        return print_json(&RefilterOutput::new(&report, dry_run));
+    }
 
-    if !sessions.is_empty() {
-        println!("\nRecent sessions:");
-        for session in sessions.iter().take(5) {
-            println!(
-                "  {} - {} ({})",
-                session.name,
-                session.status_str(),
-                session.started_at.format("%Y-%m-%d %H:%M:%S")
-            );
+    println!(
+        "Re-filtered {} capture(s), {} changed",
+        report.captures,
+        report.changed.len()
+    );
+    for change in &report.changed {
+        let mut parts = Vec::new();
+        if change.chunks_changed {
+            parts.push(format!(
+                "chunks {} → {}",
+                change.chunks_before, change.chunks_after
+            ));
+        }
+        if !change.entities_added.is_empty() {
+            parts.push(format!("+{} entities", change.entities_added.len()));
+        }
+        if !change.entities_removed.is_empty() {
+            parts.push(format!("-{} entities", change.entities_removed.len()));
+        }
+        println!(
+            "  capture {} [{}] {}",
+            change.capture_id,
+            change.tool.as_deref().unwrap_or("unknown"),
+            parts.join(", ")
+        );
+        for (entity_type, value) in &change.entities_added {
+            println!("    + {}: {}", entity_type, value);
+        }
+        for (entity_type, value) in &change.entities_removed {
+            println!("    - {}: {}", entity_type, value);
         }
     }
 
-    Ok(())
-}
+    if !report.changed.is_empty() {
+        println!();
+        println!(
+            "  Chunks: {} → {}",
+            report.chunks_before(),
+            report.chunks_after()
+        );
+        println!(
+            "  Entities: +{} -{}",
+            report.entities_added(),
+            report.entities_removed()
+        );
+    }
+    if report.missing_blobs > 0 {
+        println!(
+            "⚠ {} capture(s) skipped, blob missing",
+            report.missing_blobs
+        );
+    }
+    if report.archived_skipped > 0 {
+        println!(
+            "  {} archived session(s) skipped; unarchive them first",
+            report.archived_skipped
+        );
+    }
+    if dry_run {
+        println!("Dry run: nothing was changed");
+    } else if report.chunks_after() > 0 {
+        println!("Embeddings for the new chunks are regenerated by the embedding backlog");
+    }
 
-fn cmd_query(_query: &str, _limit: usize, _tool: Option<String>, _json: bool) -> Result<()> {
-    println!("Query functionality will be available in Phase 6-7");
     Ok(())
 }
 
-fn cmd_ask(_question: &str, _offline: bool, _context_size: usize) -> Result<()> {
-    println!("Ask functionality will be available in Phase 8");
+fn cmd_tune(
+    config_path: Option<std::path::PathBuf>,
+    session: String,
+    profile_a: String,
+    profile_b: String,
+    lines: usize,
+    json: bool,
+) -> Result<()> {
+    use yinx::filtering::ChunkPolicy;
+    use yinx::patterns::PatternRegistry;
+    use yinx::tune::{TuneProfile, TuneStats};
+
+    let load = |name: &str| -> Result<(Config, TuneProfile)> {
+        let config = load_config(config_path.clone(), Some(name.to_string()))?;
+        if name != "default" && !config.profiles.contains_key(name) {
+            return Err(YinxError::Config(format!("Unknown profile '{}'", name)));
+        }
+        let patterns = PatternRegistry::from_config_files(
+            &expand_path(&config.patterns.entities_file)?,
+            &expand_path(&config.patterns.tools_file)?,
+            &expand_path(&config.patterns.filters_file)?,
+        )?;
+        let profile = TuneProfile {
+            name: name.to_string(),
+            patterns,
+            chunk_policy: ChunkPolicy {
+                max_tokens: config.indexing.chunk_max_tokens,
+                keep_sections: config.indexing.chunk_keep_sections,
+            },
+        };
+        Ok((config, profile))
+    };
+    let (config, a) = load(&profile_a)?;
+    let (_, b) = load(&profile_b)?;
+
+    let data_dir = expand_path(&config.storage.data_dir)?;
+    let session_id = SessionManager::new(data_dir.clone())
+        .resolve(&session)?
+        .id
+        .to_string();
+    let storage = StorageManager::new(data_dir)?;
+    let report = yinx::tune::tune(&storage, &session_id, a, b)?;
+
+    if json {
+        return print_json(&TuneOutput::new(&report, &session_id));
+    }
+
+    let width = report.profile_a.len().max(report.profile_b.len()).max(7);
+    let row = |name: &str, stats: &TuneStats| {
+        println!(
+            "  {:<width$}  {:>8} in  {:>8} kept  {:>6} chunks  {:>5.1}% reduction",
+            name,
+            stats.input_lines,
+            stats.kept_lines,
+            stats.chunks,
+            stats.reduction() * 100.0,
+            width = width
+        );
+    };
+    println!(
+        "Replayed {} capture(s) of session {}",
+        report.captures.len(),
+        session
+    );
+    row(&report.profile_a, &report.a);
+    row(&report.profile_b, &report.b);
+
+    let shown = |kept: &[String]| {
+        if lines == 0 {
+            kept.len()
+        } else {
+            kept.len().min(lines)
+        }
+    };
+    let mut differing = 0;
+    for capture in report.differing() {
+        differing += 1;
+        println!();
+        println!(
+            "capture {} [{}] {}",
+            capture.capture_id,
+            capture.tool.as_deref().unwrap_or("unknown"),
+            capture.command.as_deref().unwrap_or("")
+        );
+        row(&report.profile_a, &capture.a);
+        row(&report.profile_b, &capture.b);
+        for (sign, kept) in [("-", &capture.only_a), ("+", &capture.only_b)] {
+            for line in &kept[..shown(kept)] {
+                println!("    {} {}", sign, line);
+            }
+            if shown(kept) < kept.len() {
+                println!("    {} … {} more", sign, kept.len() - shown(kept));
+            }
+        }
+    }
+
+    println!();
+    if differing == 0 {
+        println!("Both profiles keep the same lines");
+    } else {
+        println!(
+            "{} capture(s) differ; - only {} keeps, + only {} keeps",
+            differing, report.profile_a, report.profile_b
+        );
+    }
+    if report.missing_blobs > 0 {
+        println!(
+            "⚠ {} capture(s) skipped, blob missing",
+            report.missing_blobs
+        );
+    }
+
     Ok(())
 }
 
-fn cmd_report(
-    _output: Option<std::path::PathBuf>,
-    _format: &str,
-    _session: Option<String>,
-    _include_evidence: bool,
-) -> Result<()> {
-    println!("Report generation will be available in Phase 9");
-    Ok(())
+/// How long a shell hook stays quiet about a problem it already reported
+const HOOK_WARNING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Tell the shell why the daemon turned a capture or intent away
+fn warn_refused(data_dir: &std::path::Path, session_id: &str, response: &IpcResponse) {
+    let code = response.code.unwrap_or(IpcErrorCode::Unknown);
+    let reason = response.message.as_deref().unwrap_or("no reason given");
+    let message = match code {
+        IpcErrorCode::SessionUnknown => format!(
+            "no session {}: captures from this shell aren't stored; set YINX_SESSION_ID to the id 'yinx start' printed",
+            session_id
+        ),
+        IpcErrorCode::Paused => format!(
+            "session {} is paused: captures from this shell aren't stored",
+            session_id
+        ),
+        IpcErrorCode::PayloadTooLarge => format!("output too large to capture ({})", reason),
+        code if code.is_retryable() => format!("daemon busy, capture dropped ({})", reason),
+        _ => format!("capture failed: {}", reason),
+    };
+    hook_warning(data_dir, code.as_str(), session_id, &message);
 }
 
-fn cmd_export(
-    _output: &std::path::Path,
-    _session: Option<String>,
-    _include_indexes: bool,
-) -> Result<()> {
-    println!("Export functionality will be available in Phase 9");
-    Ok(())
+/// Print a warning from a shell hook, unless the same problem was reported
+/// for this session within `HOOK_WARNING_INTERVAL`
+///
+/// Every prompt runs the hook, so a session that's gone would otherwise be
+/// reported after each command.
+fn hook_warning(data_dir: &std::path::Path, kind: &str, session_id: &str, message: &str) {
+    let session: String = session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let marker = data_dir
+        .join("hook-warnings")
+        .join(format!("{}-{}", kind, session));
+    let recent = std::fs::metadata(&marker)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < HOOK_WARNING_INTERVAL);
+    if recent {
+        return;
+    }
+    if let Some(dir) = marker.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = std::fs::write(&marker, message);
+    eprintln!("yinx: {}", message);
 }
 
-fn cmd_internal(action: InternalAction) -> Result<()> {
+fn cmd_internal(config_path: Option<std::path::PathBuf>, action: InternalAction) -> Result<()> {
     match action {
+        InternalAction::RunDaemon {
+            session_id,
+            crash_dir,
+        } => cmd_run_daemon(config_path, session_id, crash_dir),
+        InternalAction::Supervise { session_id } => cmd_supervise(config_path, session_id),
         InternalAction::Capture {
             session_id,
             timestamp,
@@ -235,23 +4474,63 @@ fn cmd_internal(action: InternalAction) -> Result<()> {
             output_file,
             exit_code,
             cwd,
+            tty,
+            dry_run,
         } => {
-            // Read output from file
-            let output = std::fs::read_to_string(&output_file).unwrap_or_default();
-
             // Load config to get socket path
             let config = load_config(None, None)?;
             let socket_path = expand_path(&config.daemon.socket_path)?;
 
+            // Excluded commands never leave the shell
+            let exclusions = CommandExclusions::new(&config.capture.exclude_commands)?;
+            if exclusions.is_excluded(&command) {
+                if dry_run {
+                    return print_json(&IpcResponse::skipped(
+                        "Command excluded by capture.exclude_commands",
+                    ));
+                }
+                let _ = std::fs::remove_file(&output_file);
+                return Ok(());
+            }
+
+            // Big output stays in its file for the daemon to read; the
+            // rest is read here and sent inline
+            let threshold =
+                yinx::config::parse_size(&config.capture.handoff_threshold).unwrap_or(0);
+            let size = std::fs::metadata(&output_file)
+                .map(|m| m.len())
+                .unwrap_or(0);
+            let handoff = if threshold > 0 && size >= threshold {
+                Some(OutputHandoff::for_file(&output_file)?)
+            } else {
+                None
+            };
+            let output = match handoff {
+                Some(_) => String::new(),
+                None => std::fs::read_to_string(&output_file).unwrap_or_default(),
+            };
+
+            // The hook inherits the shell's environment, so context is read here
+            let context = yinx::daemon::CaptureContext::collect(
+                std::path::Path::new(&cwd),
+                tty.as_deref(),
+                &config.capture,
+            )
+            .map(Box::new);
+
             // Create IPC client and send capture message
-            let client = IpcClient::new(socket_path);
-            let message = IpcMessage::Capture {
-                session_id,
+            let client = IpcClient::new(socket_path).with_encoding(config.daemon.ipc_encoding);
+            let data_dir = expand_path(&config.storage.data_dir)?;
+            let mut message = IpcMessage::Capture {
+                session_id: session_id.clone(),
                 timestamp,
                 command,
                 output,
                 exit_code,
                 cwd,
+                context,
+                dry_run,
+                handoff,
             };
 
             // Send message (this is async so we need tokio runtime)
@@ -259,21 +4538,128 @@ fn cmd_internal(action: InternalAction) -> Result<()> {
                 source: e,
                 context: "Failed to create tokio runtime".to_string(),
             })?;
-            rt.block_on(async {
-                match client.send(&message).await {
-                    Ok(_response) => {
-                        // Success - cleanup output file
-                        let _ = std::fs::remove_file(&output_file);
-                        Ok(())
+            if dry_run {
+                let response = rt.block_on(client.send_with_backoff(&message))?;
+                if !response.success {
+                    return Err(YinxError::Daemon(response.message.unwrap_or_default()));
+                }
+                return print_json(&response);
+            }
+            let result = rt.block_on(async {
+                let response = client.send_with_backoff(&message).await?;
+                // Too big to go inline: let the daemon read it from the file
+                if response.code == Some(IpcErrorCode::PayloadTooLarge) {
+                    if let IpcMessage::Capture {
+                        output,
+                        handoff: handoff @ None,
+                        ..
+                    } = &mut message
+                    {
+                        *handoff = Some(OutputHandoff::for_file(&output_file)?);
+                        output.clear();
+                        return client.send_with_backoff(&message).await;
                     }
-                    Err(e) => {
-                        tracing::debug!("Failed to send capture: {}", e);
-                        // Still cleanup output file even on error
-                        let _ = std::fs::remove_file(&output_file);
-                        Err(e)
+                }
+                Ok(response)
+            });
+            // The output file goes whatever happened to the capture
+            let _ = std::fs::remove_file(&output_file);
+
+            // The shell shows what's printed here, so it's one line at most
+            match result {
+                Ok(response) if response.success => {
+                    if response.skipped {
+                        tracing::debug!("Daemon skipped capture: {:?}", response.message);
                     }
                 }
-            })
+                Ok(response) => warn_refused(&data_dir, &session_id, &response),
+                Err(e) => {
+                    tracing::debug!("Failed to send capture: {}", e);
+                    hook_warning(
+                        &data_dir,
+                        "UNREACHABLE",
+                        &session_id,
+                        &format!("capture not sent: {}", e),
+                    );
+                }
+            }
+            Ok(())
+        }
+        InternalAction::Intent {
+            session_id,
+            timestamp,
+            command,
+        } => {
+            let config = load_config(None, None)?;
+            let exclusions = CommandExclusions::new(&config.capture.exclude_commands)?;
+            if !config.capture.register_targets || exclusions.is_excluded(&command) {
+                return Ok(());
+            }
+
+            let client = IpcClient::new(expand_path(&config.daemon.socket_path)?)
+                .with_encoding(config.daemon.ipc_encoding);
+            let message = IpcMessage::Intent {
+                session_id: session_id.clone(),
+                timestamp,
+                command,
+            };
+            let rt = tokio::runtime::Runtime::new().map_err(|e| YinxError::Io {
+                source: e,
+                context: "Failed to create tokio runtime".to_string(),
+            })?;
+            // The capture that follows reports a daemon that can't be reached
+            let response = match rt.block_on(client.send(&message)) {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::debug!("Failed to send intent: {}", e);
+                    return Ok(());
+                }
+            };
+            if !response.success {
+                tracing::debug!("Daemon rejected intent: {:?}", response.message);
+                if matches!(
+                    response.code,
+                    Some(IpcErrorCode::SessionUnknown | IpcErrorCode::Paused)
+                ) {
+                    let data_dir = expand_path(&config.storage.data_dir)?;
+                    warn_refused(&data_dir, &session_id, &response);
+                }
+            }
+            Ok(())
+        }
+        InternalAction::SyncServe => {
+            let config = load_config(None, None)?;
+            let storage = StorageManager::new(expand_path(&config.storage.data_dir)?)?;
+            let imported = yinx::sync::serve(
+                &storage,
+                &mut std::io::stdin().lock(),
+                &mut std::io::stdout().lock(),
+            )?;
+            tracing::info!("Imported {} capture(s) from peer", imported);
+            Ok(())
+        }
+        InternalAction::Reindex => {
+            use yinx::retrieval::{rebuild_keyword_index, IndexRebuild};
+
+            let config = load_config(config_path, None)?;
+            let storage = StorageManager::new(expand_path(&config.storage.data_dir)?)?;
+            let zone = storage.machine_zone();
+            let rebuild = IndexRebuild::with_progress_file(zone.join("keywords.rebuild"));
+            rebuild.start();
+            let indexed = rebuild_keyword_index(
+                &storage.database,
+                zone.join("keywords"),
+                &config.indexing.tokenizers,
+                &rebuild,
+            )?;
+            tracing::info!("Rebuilt the keyword index: {} chunks", indexed);
+            Ok(())
+        }
+        InternalAction::ExcludePattern => {
+            let config = load_config(None, None)?;
+            let exclusions = CommandExclusions::new(&config.capture.exclude_commands)?;
+            println!("{}", exclusions.combined_pattern());
+            Ok(())
         }
     }
 }
@@ -295,8 +4681,15 @@ fn cmd_config(config_path: Option<std::path::PathBuf>, action: ConfigAction) ->
             println!("{}", json);
         }
         ConfigAction::Set { key, value } => {
-            println!("Setting {key} = {value}");
-            println!("Note: Config modification not yet implemented");
+            let path = config_path.unwrap_or_else(|| Config::default_path().unwrap());
+            if !path.exists() {
+                return Err(YinxError::ConfigNotFound { path });
+            }
+            Config::set_value(&path, &key, &value)?;
+            println!("✓ Set {} = {}", key, value);
+            if key.starts_with("tools.") {
+                println!("  Restart the daemon to detect tools with it: yinx stop && yinx start");
+            }
         }
         ConfigAction::Get { key } => {
             println!("Getting value for: {key}");
@@ -339,6 +4732,7 @@ fn cmd_config(config_path: Option<std::path::PathBuf>, action: ConfigAction) ->
             println!("  - entities.toml: Entity extraction patterns");
             println!("  - tools.toml: Tool detection patterns");
             println!("  - filters.toml: Filtering configuration");
+            println!("  - rules.toml: Automatic tagging and findings");
         }
         ConfigAction::SetProfile { profile } => {
             println!("Setting active profile to: {profile}");
@@ -356,14 +4750,33 @@ fn load_config(config_path: Option<std::path::PathBuf>, profile: Option<String>)
         tracing::warn!(
             "Config file not found, using defaults. Run 'yinx config init' to create one."
         );
-        return Ok(Config::default());
+        return with_latency_log(with_opened_store(Config::default()));
     }
 
-    if let Some(profile) = profile {
-        Config::load_with_profile(&path, &profile)
+    let config = if let Some(profile) = profile {
+        Config::load_with_profile(&path, &profile)?
     } else {
-        Config::load(&path)
+        Config::load(&path)?
+    };
+    yinx::storage::database::configure(config.storage.sqlite.options());
+    with_latency_log(with_opened_store(config))
+}
+
+/// Record storage and search latency under the config's data directory
+fn with_latency_log(config: Config) -> Result<Config> {
+    yinx::storage::latency::configure(LatencyOptions {
+        log_dir: expand_path(&config.storage.data_dir)?.join("logs"),
+        slow_threshold: config.storage.slow_query_threshold(),
+    });
+    Ok(config)
+}
+
+/// Point the config at the store `yinx open` opened, if any
+fn with_opened_store(mut config: Config) -> Config {
+    if let Some(data_dir) = OPENED_STORE.get() {
+        config.storage.data_dir = data_dir.clone();
     }
+    config
 }
 
 fn copy_pattern_templates(config_dir: &std::path::Path, force: bool) -> Result<()> {
@@ -374,6 +4787,7 @@ fn copy_pattern_templates(config_dir: &std::path::Path, force: bool) -> Result<(
     let entities_path = config_dir.join("entities.toml");
     let tools_path = config_dir.join("tools.toml");
     let filters_path = config_dir.join("filters.toml");
+    let rules_path = config_dir.join("rules.toml");
 
     // Try to copy from config-templates/ if available
     if let Some(root) = repo_root {
@@ -390,6 +4804,9 @@ fn copy_pattern_templates(config_dir: &std::path::Path, force: bool) -> Result<(
             if force || !filters_path.exists() {
                 std::fs::copy(template_dir.join("filters.toml"), &filters_path).ok();
             }
+            if force || !rules_path.exists() {
+                std::fs::copy(template_dir.join("rules.toml"), &rules_path).ok();
+            }
             return Ok(());
         }
     }
@@ -419,6 +4836,14 @@ fn copy_pattern_templates(config_dir: &std::path::Path, force: bool) -> Result<(
         })?;
     }
 
+    if force || !rules_path.exists() {
+        let rules_content = include_str!("../config-templates/rules.toml");
+        std::fs::write(&rules_path, rules_content).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write rules.toml: {:?}", rules_path),
+        })?;
+    }
+
     Ok(())
 }
 
@@ -437,6 +4862,31 @@ fn expand_path(path: &std::path::Path) -> Result<std::path::PathBuf> {
     }
 }
 
+/// Format a gap in seconds as e.g. "2h 13m"
+/// Hosts per board column for a session
+fn board_summary(
+    storage: &StorageManager,
+    session: &yinx::session::Session,
+) -> Result<std::collections::BTreeMap<String, usize>> {
+    let mut counts = std::collections::BTreeMap::new();
+    for host in storage.database.list_host_stages(&session.id.to_string())? {
+        *counts.entry(host.stage.as_str().to_string()).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+fn format_gap(seconds: i64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
 // Extension trait for SessionStatus
 trait SessionStatusExt {
     fn status_str(&self) -> &str;
@@ -448,6 +4898,8 @@ impl SessionStatusExt for yinx::session::Session {
             yinx::session::SessionStatus::Active => "Active",
             yinx::session::SessionStatus::Paused => "Paused",
             yinx::session::SessionStatus::Stopped => "Stopped",
+            yinx::session::SessionStatus::Archived => "Archived",
+            yinx::session::SessionStatus::Interrupted => "Interrupted",
         }
     }
 }
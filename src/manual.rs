@@ -0,0 +1,206 @@
+//! Manual captures
+//!
+//! `yinx add` runs text that was never captured live through the full
+//! filter/extract/index pipeline: saved tool output, old notes, or a page
+//! fetched over HTTP. `--tool` applies a tool's entity hints and section
+//! tagging when the command line can't name it, and `--timestamp` backdates
+//! notes so they land in the right place in the session.
+
+use crate::daemon::CaptureEvent;
+use crate::error::{Result, YinxError};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Tag and source recorded on captures added by hand
+pub const MANUAL_SOURCE: &str = "manual";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where `yinx add` reads its text from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddSource {
+    Stdin,
+    Url(String),
+    File(PathBuf),
+}
+
+impl AddSource {
+    /// `-` for stdin, an http(s) URL, or a file path
+    pub fn parse(arg: &str) -> Self {
+        if arg == "-" {
+            Self::Stdin
+        } else if arg.starts_with("http://") || arg.starts_with("https://") {
+            Self::Url(arg.to_string())
+        } else {
+            Self::File(PathBuf::from(arg))
+        }
+    }
+
+    /// Read the text; bytes that aren't UTF-8 are replaced
+    pub fn read(&self) -> Result<String> {
+        match self {
+            Self::Stdin => {
+                let mut bytes = Vec::new();
+                std::io::Read::read_to_end(&mut std::io::stdin(), &mut bytes).map_err(|e| {
+                    YinxError::Io {
+                        source: e,
+                        context: "Failed to read stdin".to_string(),
+                    }
+                })?;
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Self::Url(url) => ureq::get(url)
+                .timeout(FETCH_TIMEOUT)
+                .call()
+                .map_err(|e| YinxError::Other(anyhow::anyhow!("Failed to fetch {}: {}", url, e)))?
+                .into_string()
+                .map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to read response from {}", url),
+                }),
+            Self::File(path) => {
+                let bytes = std::fs::read(path).map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to read {}", path.display()),
+                })?;
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            }
+        }
+    }
+
+    /// Command line recorded for the capture
+    fn command(&self) -> String {
+        match self {
+            Self::Stdin => "yinx add -".to_string(),
+            Self::Url(url) => format!("yinx add {}", url),
+            Self::File(path) => format!("yinx add {}", path.display()),
+        }
+    }
+}
+
+/// Capture event for text added by hand
+///
+/// Without a timestamp the capture is dated now; a file's working directory
+/// is the one it was read from.
+pub fn add_event(
+    session_id: &str,
+    source: &AddSource,
+    text: String,
+    tool: Option<&str>,
+    timestamp: Option<i64>,
+) -> CaptureEvent {
+    let cwd = match source {
+        AddSource::File(path) => std::path::absolute(path)
+            .ok()
+            .and_then(|path| path.parent().map(|dir| dir.display().to_string())),
+        _ => None,
+    }
+    .or_else(|| {
+        std::env::current_dir()
+            .ok()
+            .map(|dir| dir.display().to_string())
+    })
+    .unwrap_or_default();
+    CaptureEvent {
+        session_id: session_id.to_string(),
+        timestamp: timestamp.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+        command: source.command(),
+        output: text,
+        exit_code: 0,
+        cwd,
+        username: None,
+        source: Some(MANUAL_SOURCE.to_string()),
+        tool: tool.map(str::to_string),
+        context: None,
+    }
+}
+
+/// Parse a `--timestamp` value
+///
+/// Accepts Unix seconds, RFC 3339, or a local date and time such as
+/// `2025-07-01 14:30` or `2025-07-01`.
+pub fn parse_timestamp(value: &str) -> Result<i64> {
+    use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.timestamp());
+    }
+    let naive = [
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%d %H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    });
+    naive
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(|t| t.timestamp())
+        .ok_or_else(|| YinxError::InvalidConfigValue {
+            path: "--timestamp".to_string(),
+            message: format!(
+                "'{}' is not a time (expected e.g. 2025-07-01 14:30, RFC 3339 or Unix seconds)",
+                value
+            ),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Yinx;
+
+    #[tokio::test]
+    async fn test_add_backfilled_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let yinx = Yinx::open(dir.path()).unwrap();
+        let session = yinx.start_session("backfill").unwrap();
+        let session_id = session.id.to_string();
+
+        let notes = dir.path().join("scan.txt");
+        std::fs::write(&notes, "PORT   STATE SERVICE\n22/tcp open  ssh\n").unwrap();
+        let source = AddSource::parse(notes.to_str().unwrap());
+        assert_eq!(source, AddSource::File(notes.clone()));
+        assert_eq!(AddSource::parse("-"), AddSource::Stdin);
+        assert!(matches!(
+            AddSource::parse("https://10.10.10.5/robots.txt"),
+            AddSource::Url(_)
+        ));
+
+        let timestamp = parse_timestamp("2024-03-01T10:00:00Z").unwrap();
+        assert_eq!(timestamp, 1_709_287_200);
+        assert_eq!(parse_timestamp("1709287200").unwrap(), timestamp);
+        assert!(parse_timestamp("last tuesday").is_err());
+
+        let text = source.read().unwrap();
+        let event = add_event(&session_id, &source, text, Some("nmap"), Some(timestamp));
+        assert_eq!(event.cwd, dir.path().display().to_string());
+        let capture_id = yinx.ingest(event).await.unwrap().unwrap();
+
+        let capture = yinx
+            .storage()
+            .database
+            .get_capture(capture_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(capture.tool.as_deref(), Some("nmap"));
+        assert_eq!(capture.timestamp, timestamp);
+        assert!(capture.arguments.is_empty());
+        assert_eq!(
+            yinx.storage()
+                .database
+                .get_capture_tags(capture_id)
+                .unwrap(),
+            vec![MANUAL_SOURCE.to_string()]
+        );
+    }
+}
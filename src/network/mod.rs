@@ -0,0 +1,299 @@
+//! Network evidence import (PCAP and Zeek logs)
+//!
+//! Both formats are reduced to conversations between a client and a server:
+//! the services used, HTTP and TLS virtual hosts, and credentials that
+//! crossed the wire in clear text. Each conversation is ingested as one
+//! capture, so its hosts land in the same entity tables, host graph and
+//! status board as terminal evidence.
+
+mod pcap;
+mod zeek;
+
+pub use pcap::read_pcap;
+pub use zeek::read_zeek;
+
+use crate::daemon::CaptureEvent;
+use crate::error::Result;
+use crate::Yinx;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Entity type recorded for credentials seen in clear text
+pub const CLEARTEXT_CREDENTIAL: &str = "cleartext_credential";
+
+/// Login seen in clear text
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CleartextCredential {
+    pub protocol: String,
+    pub username: String,
+    pub password: Option<String>,
+}
+
+/// Traffic from one client to one server
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    pub client: String,
+    pub server: String,
+    pub connections: usize,
+    /// Ports by transport (`tcp`, `udp`), with the protocol seen on them
+    pub services: BTreeMap<(u16, &'static str), Option<String>>,
+    /// HTTP `Host` headers and TLS server names
+    pub virtual_hosts: BTreeSet<String>,
+    pub credentials: BTreeSet<CleartextCredential>,
+}
+
+impl Conversation {
+    /// Note a port; the first protocol identified on it sticks
+    pub fn add_service(&mut self, port: u16, transport: &'static str, name: Option<&str>) {
+        let entry = self.services.entry((port, transport)).or_default();
+        if entry.is_none() {
+            *entry = name.map(str::to_string);
+        }
+    }
+
+    /// Capture text, laid out like tool output so the usual extractors apply
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{} -> {} ({} connection{})\n",
+            self.client,
+            self.server,
+            self.connections,
+            if self.connections == 1 { "" } else { "s" }
+        );
+        for ((port, transport), name) in &self.services {
+            out.push_str(&format!("{}/{}", port, transport));
+            if let Some(name) = name
+                .as_deref()
+                .or_else(|| well_known_service(*port, transport))
+            {
+                out.push_str(&format!(" {}", name));
+            }
+            out.push('\n');
+        }
+        for host in &self.virtual_hosts {
+            out.push_str(&format!("virtual host: {}\n", host));
+        }
+        for credential in &self.credentials {
+            out.push_str(&format!(
+                "cleartext {} login: user={} password={}\n",
+                credential.protocol,
+                credential.username,
+                credential.password.as_deref().unwrap_or("-")
+            ));
+        }
+        out
+    }
+
+    /// Entities the output patterns wouldn't find on their own
+    fn entities(&self) -> Vec<(String, String, String, f32)> {
+        let mut entities = Vec::new();
+        for host in [&self.client, &self.server] {
+            if host.contains(':') {
+                entities.push((
+                    "ip_address_v6".to_string(),
+                    host.clone(),
+                    String::new(),
+                    1.0,
+                ));
+            }
+        }
+        for host in self.virtual_hosts.iter().filter(|h| !h.contains('.')) {
+            entities.push(("hostname".to_string(), host.clone(), String::new(), 0.9));
+        }
+        for credential in &self.credentials {
+            entities.push((
+                CLEARTEXT_CREDENTIAL.to_string(),
+                format!(
+                    "{}://{}@{}",
+                    credential.protocol, credential.username, self.server
+                ),
+                format!("{} -> {}", self.client, self.server),
+                1.0,
+            ));
+        }
+        entities
+    }
+}
+
+/// Conversations collected from one or more files
+#[derive(Debug, Default)]
+pub struct NetworkEvidence {
+    conversations: BTreeMap<(String, String), Conversation>,
+}
+
+impl NetworkEvidence {
+    /// The conversation from `client` to `server`, created if new
+    pub fn conversation(&mut self, client: &str, server: &str) -> &mut Conversation {
+        self.conversations
+            .entry((client.to_string(), server.to_string()))
+            .or_insert_with(|| Conversation {
+                client: client.to_string(),
+                server: server.to_string(),
+                ..Default::default()
+            })
+    }
+
+    pub fn conversations(&self) -> impl Iterator<Item = &Conversation> {
+        self.conversations.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conversations.is_empty()
+    }
+}
+
+/// What an import added
+#[derive(Debug, Default)]
+pub struct ImportStats {
+    pub conversations: usize,
+    pub hosts: usize,
+    pub credentials: usize,
+}
+
+/// Ingest every conversation into a session, tagged with `source` (`pcap`, `zeek`)
+pub async fn ingest(
+    yinx: &Yinx,
+    session_id: &str,
+    source: &str,
+    path: &Path,
+    evidence: &NetworkEvidence,
+) -> Result<ImportStats> {
+    let mut stats = ImportStats::default();
+    let mut hosts = BTreeSet::new();
+    let timestamp = chrono::Utc::now().timestamp();
+
+    for conversation in evidence.conversations() {
+        let event = CaptureEvent {
+            session_id: session_id.to_string(),
+            timestamp,
+            command: format!("yinx import {} {}", source, path.display()),
+            output: conversation.render(),
+            exit_code: 0,
+            cwd: path
+                .parent()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            username: None,
+            source: Some(source.to_string()),
+            tool: None,
+            context: None,
+        };
+        let Some(capture_id) = yinx.ingest(event).await? else {
+            continue;
+        };
+        let entities = conversation.entities();
+        if !entities.is_empty() {
+            yinx.storage()
+                .database
+                .insert_entities(capture_id, &entities)?;
+        }
+
+        stats.conversations += 1;
+        stats.credentials += conversation.credentials.len();
+        hosts.insert(&conversation.client);
+        hosts.insert(&conversation.server);
+    }
+    stats.hosts = hosts.len();
+    Ok(stats)
+}
+
+/// Protocol usually found on a port, for ports nothing identified
+fn well_known_service(port: u16, transport: &str) -> Option<&'static str> {
+    let name = match (port, transport) {
+        (21, "tcp") => "ftp",
+        (22, "tcp") => "ssh",
+        (23, "tcp") => "telnet",
+        (25 | 587, "tcp") => "smtp",
+        (53, _) => "dns",
+        (67 | 68, "udp") => "dhcp",
+        (80 | 8080, "tcp") => "http",
+        (88, _) => "kerberos",
+        (110, "tcp") => "pop3",
+        (123, "udp") => "ntp",
+        (135, "tcp") => "msrpc",
+        (137, "udp") => "netbios-ns",
+        (139, "tcp") => "netbios-ssn",
+        (143, "tcp") => "imap",
+        (161, "udp") => "snmp",
+        (389, _) => "ldap",
+        (443 | 8443, "tcp") => "https",
+        (445, "tcp") => "smb",
+        (636, "tcp") => "ldaps",
+        (1433, "tcp") => "mssql",
+        (3306, "tcp") => "mysql",
+        (3389, "tcp") => "rdp",
+        (5432, "tcp") => "postgresql",
+        (5985 | 5986, "tcp") => "winrm",
+        (6379, "tcp") => "redis",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Decode standard base64 (HTTP Basic auth, SMTP AUTH PLAIN)
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in input.trim().trim_end_matches('=').bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_render() {
+        let mut evidence = NetworkEvidence::default();
+        let conversation = evidence.conversation("10.0.0.5", "10.0.0.9");
+        conversation.connections = 2;
+        conversation.add_service(8000, "tcp", None);
+        conversation.add_service(8000, "tcp", Some("http"));
+        conversation.add_service(8000, "tcp", None);
+        conversation.add_service(21, "tcp", None);
+        conversation.virtual_hosts.insert("intranet".to_string());
+        conversation.credentials.insert(CleartextCredential {
+            protocol: "ftp".to_string(),
+            username: "anonymous".to_string(),
+            password: Some("guest".to_string()),
+        });
+
+        assert_eq!(
+            conversation.render(),
+            "10.0.0.5 -> 10.0.0.9 (2 connections)\n\
+             21/tcp ftp\n\
+             8000/tcp http\n\
+             virtual host: intranet\n\
+             cleartext ftp login: user=anonymous password=guest\n"
+        );
+        let entities = conversation.entities();
+        assert_eq!(entities[0].1, "intranet");
+        assert_eq!(entities[1].1, "ftp://anonymous@10.0.0.9");
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("YWRtaW46czNjcmV0").unwrap(), b"admin:s3cret");
+        assert_eq!(
+            decode_base64("AGJvYgBodW50ZXIy").unwrap(),
+            b"\0bob\0hunter2"
+        );
+        assert!(decode_base64("not base64!").is_none());
+    }
+}
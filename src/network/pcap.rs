@@ -0,0 +1,598 @@
+//! PCAP and PCAPNG reader
+//!
+//! Decodes Ethernet, Linux cooked, loopback and raw IP frames down to TCP
+//! and UDP, and looks at client payloads for HTTP requests and the line
+//! based logins of FTP, POP3, IMAP and SMTP. There is no stream
+//! reassembly; a header split across segments is missed.
+
+use super::{decode_base64, well_known_service, CleartextCredential, NetworkEvidence};
+use crate::error::{Result, YinxError};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER: u32 = 0x1A2B_3C4D;
+/// Refuse records larger than this rather than allocating for a corrupt length
+const MAX_RECORD: usize = 16 * 1024 * 1024;
+
+// Link-layer header types
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_RAW_ALT: u32 = 12;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+/// Endpoint pair of a flow, as (address, port)
+type Endpoint = (IpAddr, u16);
+type FlowKey = (Endpoint, Endpoint, &'static str);
+
+/// Read a capture file into `evidence`; returns the number of packets seen
+pub fn read_pcap(mut reader: impl Read, evidence: &mut NetworkEvidence) -> Result<usize> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic).map_err(read_error)?;
+    let mut decoder = Decoder::default();
+
+    let packets = if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER {
+        read_pcapng(reader, &mut decoder)?
+    } else {
+        let big_endian = match u32::from_le_bytes(magic) {
+            0xA1B2_C3D4 | 0xA1B2_3C4D => false,
+            0xD4C3_B2A1 | 0x4D3C_B2A1 => true,
+            _ => return Err(format_error("not a pcap or pcapng file")),
+        };
+        read_classic(reader, big_endian, &mut decoder)?
+    };
+
+    decoder.finish(evidence);
+    Ok(packets)
+}
+
+fn read_classic(mut reader: impl Read, big_endian: bool, decoder: &mut Decoder) -> Result<usize> {
+    let mut header = [0u8; 20];
+    reader.read_exact(&mut header).map_err(read_error)?;
+    let link_type = read_u32(&header[16..], big_endian) & 0x0FFF_FFFF;
+
+    let mut packets = 0;
+    let mut record = [0u8; 16];
+    let mut data = Vec::new();
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(read_error(e)),
+        }
+        let captured = read_u32(&record[8..], big_endian) as usize;
+        read_block(&mut reader, &mut data, captured)?;
+        decoder.frame(link_type, &data);
+        packets += 1;
+    }
+    Ok(packets)
+}
+
+fn read_pcapng(mut reader: impl Read, decoder: &mut Decoder) -> Result<usize> {
+    let mut big_endian = false;
+    let mut link_types: Vec<u32> = Vec::new();
+    let mut packets = 0;
+    let mut block_type = PCAPNG_SECTION_HEADER;
+    let mut body = Vec::new();
+
+    loop {
+        let mut length = [0u8; 4];
+        reader.read_exact(&mut length).map_err(read_error)?;
+        if block_type == PCAPNG_SECTION_HEADER {
+            // The byte-order magic that follows decides how to read everything else
+            let mut order = [0u8; 4];
+            reader.read_exact(&mut order).map_err(read_error)?;
+            big_endian = match u32::from_le_bytes(order) {
+                PCAPNG_BYTE_ORDER => false,
+                _ if u32::from_be_bytes(order) == PCAPNG_BYTE_ORDER => true,
+                _ => return Err(format_error("bad pcapng byte-order magic")),
+            };
+            let total = read_u32(&length, big_endian) as usize;
+            read_block(&mut reader, &mut body, total.saturating_sub(12))?;
+            link_types.clear();
+        } else {
+            let total = read_u32(&length, big_endian) as usize;
+            read_block(&mut reader, &mut body, total.saturating_sub(8))?;
+            match block_type {
+                // Interface description
+                1 if body.len() >= 2 => {
+                    let link_type = if big_endian {
+                        u16::from_be_bytes([body[0], body[1]])
+                    } else {
+                        u16::from_le_bytes([body[0], body[1]])
+                    };
+                    link_types.push(link_type as u32);
+                }
+                // Enhanced packet
+                6 if body.len() >= 20 => {
+                    let interface = read_u32(&body, big_endian) as usize;
+                    let captured = read_u32(&body[12..], big_endian) as usize;
+                    let data = &body[20..(20 + captured).min(body.len())];
+                    if let Some(&link_type) = link_types.get(interface) {
+                        decoder.frame(link_type, data);
+                    }
+                    packets += 1;
+                }
+                // Simple packet (always interface 0)
+                3 if body.len() >= 4 => {
+                    if let Some(&link_type) = link_types.first() {
+                        decoder.frame(link_type, &body[4..]);
+                    }
+                    packets += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let mut next = [0u8; 4];
+        match reader.read_exact(&mut next) {
+            Ok(()) => block_type = read_u32(&next, big_endian),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(read_error(e)),
+        }
+    }
+    Ok(packets)
+}
+
+fn read_block(reader: &mut impl Read, buf: &mut Vec<u8>, len: usize) -> Result<()> {
+    if len > MAX_RECORD {
+        return Err(format_error("record length out of range"));
+    }
+    buf.resize(len, 0);
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(()),
+        // A capture cut off mid-record still has everything before it
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            buf.clear();
+            Ok(())
+        }
+        Err(e) => Err(read_error(e)),
+    }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+fn read_error(e: std::io::Error) -> YinxError {
+    YinxError::Io {
+        source: e,
+        context: "Failed to read capture file".to_string(),
+    }
+}
+
+fn format_error(message: &str) -> YinxError {
+    YinxError::Other(anyhow::anyhow!("Unsupported capture file: {}", message))
+}
+
+/// A decoded flow as seen so far
+struct Flow {
+    client: Endpoint,
+    server: Endpoint,
+    protocol: Option<&'static str>,
+    /// The server sent a SYN-ACK or data
+    answered: bool,
+    /// Something other than a bare SYN or reset went by
+    progressed: bool,
+    /// USER sent on a line-based login, waiting for its PASS
+    pending_user: Option<String>,
+}
+
+/// Packet decoding and per-flow state
+#[derive(Default)]
+struct Decoder {
+    flows: HashMap<FlowKey, Flow>,
+    /// Flows whose client was learned from a SYN
+    opened: HashSet<FlowKey>,
+    http_hosts: Vec<(FlowKey, String)>,
+    credentials: Vec<(FlowKey, CleartextCredential)>,
+}
+
+impl Decoder {
+    fn frame(&mut self, link_type: u32, frame: &[u8]) {
+        let packet = match link_type {
+            LINKTYPE_ETHERNET => ethernet_payload(frame),
+            LINKTYPE_RAW | LINKTYPE_RAW_ALT => Some(frame),
+            LINKTYPE_NULL | LINKTYPE_LOOP => frame.get(4..),
+            LINKTYPE_LINUX_SLL => frame.get(16..),
+            LINKTYPE_LINUX_SLL2 => frame.get(20..),
+            _ => None,
+        };
+        if let Some(packet) = packet {
+            self.ip(packet);
+        }
+    }
+
+    fn ip(&mut self, packet: &[u8]) {
+        let Some(&first) = packet.first() else {
+            return;
+        };
+        let (src, dst, protocol, payload): (IpAddr, IpAddr, u8, &[u8]) = match first >> 4 {
+            4 if packet.len() >= 20 => {
+                let header_len = ((first & 0x0F) as usize) * 4;
+                let total = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+                let fragment_offset = u16::from_be_bytes([packet[6], packet[7]]) & 0x1FFF;
+                if fragment_offset != 0 || header_len < 20 {
+                    return;
+                }
+                let end = total.clamp(header_len, packet.len());
+                let Some(payload) = packet.get(header_len..end) else {
+                    return;
+                };
+                let src = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+                let dst = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+                (src.into(), dst.into(), packet[9], payload)
+            }
+            6 if packet.len() >= 40 => {
+                let src: [u8; 16] = packet[8..24].try_into().unwrap();
+                let dst: [u8; 16] = packet[24..40].try_into().unwrap();
+                (
+                    Ipv6Addr::from(src).into(),
+                    Ipv6Addr::from(dst).into(),
+                    packet[6],
+                    &packet[40..],
+                )
+            }
+            _ => return,
+        };
+
+        match protocol {
+            6 if payload.len() >= 20 => {
+                let src_port = u16::from_be_bytes([payload[0], payload[1]]);
+                let dst_port = u16::from_be_bytes([payload[2], payload[3]]);
+                let offset = ((payload[12] >> 4) as usize) * 4;
+                let flags = payload[13];
+                let data = payload.get(offset..).unwrap_or_default();
+                self.segment((src, src_port), (dst, dst_port), "tcp", flags, data);
+            }
+            17 if payload.len() >= 8 => {
+                let src_port = u16::from_be_bytes([payload[0], payload[1]]);
+                let dst_port = u16::from_be_bytes([payload[2], payload[3]]);
+                self.segment((src, src_port), (dst, dst_port), "udp", 0, &payload[8..]);
+            }
+            _ => {}
+        }
+    }
+
+    fn segment(
+        &mut self,
+        src: Endpoint,
+        dst: Endpoint,
+        transport: &'static str,
+        flags: u8,
+        data: &[u8],
+    ) {
+        let key = flow_key(src, dst, transport);
+        let syn = flags & (TCP_SYN | TCP_ACK) == TCP_SYN;
+        if syn && self.opened.insert(key) {
+            // A SYN settles who the client is, even if later packets came first
+            self.flows.insert(key, new_flow(src, dst, None));
+        }
+        let flow = self
+            .flows
+            .entry(key)
+            .or_insert_with(|| guess_flow(src, dst, transport));
+        let from_client = src == flow.client;
+        if !from_client && (flags & TCP_SYN != 0 || !data.is_empty()) {
+            flow.answered = true;
+        }
+        if flags & (TCP_SYN | TCP_RST) == 0 {
+            flow.progressed = true;
+        }
+        if data.is_empty() || !from_client {
+            return;
+        }
+
+        let text = String::from_utf8_lossy(&data[..data.len().min(4096)]);
+        if let Some(host) = http_request_host(&text) {
+            flow.protocol = Some("http");
+            if let Some(credential) = basic_auth(&text) {
+                self.credentials.push((key, credential));
+            }
+            if let Some(host) = host {
+                self.http_hosts.push((key, host));
+            }
+            return;
+        }
+        if let Some(credential) = line_login(flow, &text) {
+            self.credentials.push((key, credential));
+        }
+    }
+
+    /// Fold the flows into conversations
+    ///
+    /// Probes nothing answered (closed or filtered ports in a scan) are left out.
+    fn finish(self, evidence: &mut NetworkEvidence) {
+        for (key, flow) in &self.flows {
+            if !flow.answered && !flow.progressed {
+                continue;
+            }
+            let conversation =
+                evidence.conversation(&flow.client.0.to_string(), &flow.server.0.to_string());
+            conversation.connections += 1;
+            conversation.add_service(flow.server.1, key.2, flow.protocol);
+        }
+        for (key, host) in self.http_hosts {
+            let flow = &self.flows[&key];
+            evidence
+                .conversation(&flow.client.0.to_string(), &flow.server.0.to_string())
+                .virtual_hosts
+                .insert(host);
+        }
+        for (key, credential) in self.credentials {
+            let flow = &self.flows[&key];
+            evidence
+                .conversation(&flow.client.0.to_string(), &flow.server.0.to_string())
+                .credentials
+                .insert(credential);
+        }
+    }
+}
+
+/// Direction-independent key for a flow
+fn flow_key(a: Endpoint, b: Endpoint, transport: &'static str) -> FlowKey {
+    if a <= b {
+        (a, b, transport)
+    } else {
+        (b, a, transport)
+    }
+}
+
+fn new_flow(client: Endpoint, server: Endpoint, protocol: Option<&'static str>) -> Flow {
+    Flow {
+        client,
+        server,
+        protocol,
+        answered: false,
+        progressed: false,
+        pending_user: None,
+    }
+}
+
+/// Pick the server side of a flow whose start wasn't captured
+fn guess_flow(src: Endpoint, dst: Endpoint, transport: &str) -> Flow {
+    let known = |port| well_known_service(port, transport).is_some();
+    let src_is_server = match (known(src.1), known(dst.1)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => src.1 < dst.1,
+    };
+    if src_is_server {
+        new_flow(dst, src, None)
+    } else {
+        new_flow(src, dst, None)
+    }
+}
+
+fn ethernet_payload(frame: &[u8]) -> Option<&[u8]> {
+    let mut offset = 12;
+    loop {
+        let ether_type = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+        match ether_type {
+            // 802.1Q / 802.1ad VLAN tags
+            0x8100 | 0x88A8 => offset += 4,
+            0x0800 | 0x86DD => return frame.get(offset + 2..),
+            _ => return None,
+        }
+    }
+}
+
+/// For an HTTP request, its Host header (if any)
+fn http_request_host(text: &str) -> Option<Option<String>> {
+    const METHODS: [&str; 8] = [
+        "GET ", "POST ", "HEAD ", "PUT ", "DELETE ", "OPTIONS ", "PATCH ", "CONNECT ",
+    ];
+    let request_line = text.lines().next()?;
+    if !METHODS.iter().any(|m| request_line.starts_with(m)) || !request_line.contains(" HTTP/") {
+        return None;
+    }
+    Some(header(text, "host").map(|host| {
+        // Drop the port; the conversation already records it
+        match host.rsplit_once(':') {
+            Some((name, port)) if port.parse::<u16>().is_ok() && !name.ends_with(']') => {
+                name.to_string()
+            }
+            _ => host.to_string(),
+        }
+    }))
+}
+
+fn header<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+}
+
+fn basic_auth(text: &str) -> Option<CleartextCredential> {
+    let value = header(text, "authorization")?;
+    let (scheme, encoded) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(decode_base64(encoded)?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some(CleartextCredential {
+        protocol: "http".to_string(),
+        username: username.to_string(),
+        password: Some(password.to_string()),
+    })
+}
+
+/// FTP/POP3 `USER`+`PASS`, IMAP `LOGIN` and SMTP `AUTH PLAIN`
+fn line_login(flow: &mut Flow, text: &str) -> Option<CleartextCredential> {
+    let line = text.lines().next()?.trim();
+    let (verb, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let credential = |protocol: &str, username: &str, password: Option<&str>| CleartextCredential {
+        protocol: protocol.to_string(),
+        username: username.to_string(),
+        password: password.map(str::to_string),
+    };
+    let line_protocol = || match flow.server.1 {
+        110 => "pop3",
+        _ => "ftp",
+    };
+
+    match verb.to_ascii_uppercase().as_str() {
+        "USER" if !rest.is_empty() => {
+            flow.protocol.get_or_insert(line_protocol());
+            flow.pending_user = Some(rest.to_string());
+            None
+        }
+        "PASS" => {
+            let username = flow.pending_user.take()?;
+            let protocol = line_protocol();
+            flow.protocol.get_or_insert(protocol);
+            Some(credential(protocol, &username, Some(rest)))
+        }
+        "AUTH" => {
+            let encoded = rest
+                .strip_prefix("PLAIN ")
+                .or_else(|| rest.strip_prefix("plain "))?;
+            let decoded = String::from_utf8(decode_base64(encoded)?).ok()?;
+            let mut parts = decoded.split('\0').skip(1);
+            let username = parts.next()?;
+            flow.protocol.get_or_insert("smtp");
+            Some(credential("smtp", username, parts.next()))
+        }
+        _ => {
+            // IMAP: "<tag> LOGIN <user> <password>"
+            let (command, args) = rest.split_once(' ')?;
+            if !command.eq_ignore_ascii_case("LOGIN") {
+                return None;
+            }
+            let mut args = args.split_whitespace().map(|a| a.trim_matches('"'));
+            let username = args.next()?;
+            flow.protocol.get_or_insert("imap");
+            Some(credential("imap", username, args.next()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ethernet + IPv4 + TCP frame
+    fn tcp_frame(src: [u8; 4], dst: [u8; 4], ports: (u16, u16), flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        let total = (20 + 20 + data.len()) as u16;
+        frame.extend_from_slice(&[
+            0x45,
+            0,
+            (total >> 8) as u8,
+            total as u8,
+            0,
+            0,
+            0,
+            0,
+            64,
+            6,
+            0,
+            0,
+        ]);
+        frame.extend_from_slice(&src);
+        frame.extend_from_slice(&dst);
+        frame.extend_from_slice(&ports.0.to_be_bytes());
+        frame.extend_from_slice(&ports.1.to_be_bytes());
+        frame.extend_from_slice(&[0; 8]);
+        frame.extend_from_slice(&[0x50, flags, 0, 0, 0, 0, 0, 0]);
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    fn classic_pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+        file.extend_from_slice(&[2, 0, 4, 0]);
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&65535u32.to_le_bytes());
+        file.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+        for frame in frames {
+            file.extend_from_slice(&[0; 8]);
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(frame);
+        }
+        file
+    }
+
+    #[test]
+    fn test_read_classic_pcap() {
+        let client = [10, 0, 0, 5];
+        let server = [10, 0, 0, 9];
+        let frames = vec![
+            tcp_frame(client, server, (40000, 21), TCP_SYN, b""),
+            // Closed port during a scan
+            tcp_frame(client, server, (40001, 23), TCP_SYN, b""),
+            tcp_frame(server, client, (23, 40001), TCP_RST | TCP_ACK, b""),
+            tcp_frame(server, client, (21, 40000), TCP_SYN | TCP_ACK, b""),
+            tcp_frame(server, client, (21, 40000), TCP_ACK, b"220 vsFTPd 2.3.4\r\n"),
+            tcp_frame(client, server, (40000, 21), TCP_ACK, b"USER anonymous\r\n"),
+            tcp_frame(client, server, (40000, 21), TCP_ACK, b"PASS guest@\r\n"),
+            tcp_frame(
+                client,
+                server,
+                (40002, 8000),
+                TCP_ACK,
+                b"GET /admin HTTP/1.1\r\nHost: intranet:8000\r\nAuthorization: Basic YWRtaW46czNjcmV0\r\n\r\n",
+            ),
+        ];
+        let mut evidence = NetworkEvidence::default();
+        let packets = read_pcap(&classic_pcap(&frames)[..], &mut evidence).unwrap();
+        assert_eq!(packets, 8);
+
+        let conversations: Vec<_> = evidence.conversations().collect();
+        assert_eq!(conversations.len(), 1);
+        let conversation = conversations[0];
+        assert_eq!(conversation.client, "10.0.0.5");
+        assert_eq!(conversation.server, "10.0.0.9");
+        assert_eq!(conversation.connections, 2);
+        assert_eq!(
+            conversation.services.get(&(8000, "tcp")),
+            Some(&Some("http".to_string()))
+        );
+        assert!(conversation.virtual_hosts.contains("intranet"));
+        let logins: Vec<_> = conversation
+            .credentials
+            .iter()
+            .map(|c| {
+                (
+                    c.protocol.as_str(),
+                    c.username.as_str(),
+                    c.password.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            logins,
+            vec![
+                ("ftp", "anonymous", Some("guest@")),
+                ("http", "admin", Some("s3cret"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        let mut evidence = NetworkEvidence::default();
+        assert!(read_pcap(&b"#separator \\x09\n"[..], &mut evidence).is_err());
+    }
+}
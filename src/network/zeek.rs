@@ -0,0 +1,226 @@
+//! Zeek log reader
+//!
+//! Reads `conn`, `http`, `ftp` and `ssl` logs, in Zeek's tab-separated
+//! format or as JSON lines. Other logs are skipped.
+
+use super::{CleartextCredential, NetworkEvidence};
+use crate::error::{Result, YinxError};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+/// Read one Zeek log into `evidence`; returns the number of records used
+///
+/// `name` is the file name, used to tell the log type of JSON logs.
+pub fn read_zeek(
+    reader: impl BufRead,
+    name: &str,
+    evidence: &mut NetworkEvidence,
+) -> Result<usize> {
+    let mut path = name.split('.').next().unwrap_or_default().to_string();
+    let mut fields: Vec<String> = Vec::new();
+    let mut separator = "\t".to_string();
+    let mut unset = "-".to_string();
+    let mut empty = "(empty)".to_string();
+    let mut records = 0;
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read Zeek log {}", name),
+        })?;
+        if line.is_empty() {
+            continue;
+        }
+
+        let record: HashMap<String, String> = if let Some(directive) = line.strip_prefix('#') {
+            let (key, value) = directive.split_once([' ', '\t']).unwrap_or((directive, ""));
+            match key {
+                "separator" => separator = unescape(value),
+                "unset_field" => unset = value.trim().to_string(),
+                "empty_field" => empty = value.trim().to_string(),
+                "path" => path = value.trim().to_string(),
+                "fields" => {
+                    fields = value
+                        .split(separator.as_str())
+                        .map(str::to_string)
+                        .collect();
+                }
+                _ => {}
+            }
+            continue;
+        } else if line.starts_with('{') {
+            let Ok(Value::Object(object)) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            object
+                .iter()
+                .filter_map(|(key, value)| {
+                    let value = match value {
+                        Value::String(s) => s.clone(),
+                        Value::Number(n) => n.to_string(),
+                        Value::Bool(b) => b.to_string(),
+                        _ => return None,
+                    };
+                    Some((key.clone(), value))
+                })
+                .collect()
+        } else {
+            fields
+                .iter()
+                .zip(line.split(separator.as_str()))
+                .filter(|(_, value)| *value != unset && *value != empty)
+                .map(|(key, value)| (key.clone(), value.to_string()))
+                .collect()
+        };
+
+        if apply(&path, &record, evidence) {
+            records += 1;
+        }
+    }
+    Ok(records)
+}
+
+/// Decode `\x09`-style escapes in header values
+fn unescape(value: &str) -> String {
+    let value = value.trim_start_matches(' ');
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(i) = rest.find("\\x") {
+        out.push_str(&rest[..i]);
+        match rest
+            .get(i + 2..i + 4)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                out.push_str("\\x");
+                rest = &rest[i + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Fold one record into the evidence; false for logs or records that say nothing useful
+fn apply(path: &str, record: &HashMap<String, String>, evidence: &mut NetworkEvidence) -> bool {
+    let (Some(client), Some(server)) = (record.get("id.orig_h"), record.get("id.resp_h")) else {
+        return false;
+    };
+    let port = record
+        .get("id.resp_p")
+        .and_then(|p| p.parse::<u16>().ok())
+        .unwrap_or_default();
+    let value = |key: &str| record.get(key).map(String::as_str).filter(|v| *v != "-");
+
+    match path {
+        "conn" => {
+            let transport = match value("proto") {
+                Some("udp") => "udp",
+                Some("tcp") => "tcp",
+                _ => return false,
+            };
+            // Zeek lists every analyzer that attached, e.g. "ssl,http"
+            let service = value("service").and_then(|s| s.split(',').next_back());
+            let conversation = evidence.conversation(client, server);
+            conversation.connections += 1;
+            conversation.add_service(port, transport, service);
+        }
+        "http" => {
+            let conversation = evidence.conversation(client, server);
+            conversation.add_service(port, "tcp", Some("http"));
+            if let Some(host) = value("host") {
+                let host = host.rsplit_once(':').map_or(host, |(name, _)| name);
+                conversation.virtual_hosts.insert(host.to_string());
+            }
+            if let Some(username) = value("username") {
+                conversation.credentials.insert(CleartextCredential {
+                    protocol: "http".to_string(),
+                    username: username.to_string(),
+                    password: value("password").map(str::to_string),
+                });
+            }
+        }
+        "ftp" => {
+            let conversation = evidence.conversation(client, server);
+            conversation.add_service(port, "tcp", Some("ftp"));
+            if let Some(username) = value("user").filter(|u| *u != "<unknown>") {
+                conversation.credentials.insert(CleartextCredential {
+                    protocol: "ftp".to_string(),
+                    username: username.to_string(),
+                    password: value("password").map(str::to_string),
+                });
+            }
+        }
+        "ssl" => {
+            let conversation = evidence.conversation(client, server);
+            conversation.add_service(port, "tcp", Some("ssl"));
+            if let Some(name) = value("server_name") {
+                conversation.virtual_hosts.insert(name.to_string());
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_tsv_logs() {
+        let conn = "#separator \\x09\n\
+#set_separator\t,\n\
+#empty_field\t(empty)\n\
+#unset_field\t-\n\
+#path\tconn\n\
+#fields\tts\tuid\tid.orig_h\tid.orig_p\tid.resp_h\tid.resp_p\tproto\tservice\n\
+1700000000.1\tC1\t10.0.0.5\t40000\t10.0.0.9\t21\ttcp\tftp\n\
+1700000000.2\tC2\t10.0.0.5\t40001\t10.0.0.9\t443\ttcp\tssl,http\n\
+1700000000.3\tC3\t10.0.0.5\t0\t10.0.0.9\t8\ticmp\t-\n";
+        let ftp = "#separator \\x09\n\
+#path\tftp\n\
+#fields\tts\tuid\tid.orig_h\tid.orig_p\tid.resp_h\tid.resp_p\tuser\tpassword\tcommand\n\
+1700000000.1\tC1\t10.0.0.5\t40000\t10.0.0.9\t21\tanonymous\tguest@\tRETR\n";
+
+        let mut evidence = NetworkEvidence::default();
+        assert_eq!(
+            read_zeek(conn.as_bytes(), "conn.log", &mut evidence).unwrap(),
+            2
+        );
+        assert_eq!(
+            read_zeek(ftp.as_bytes(), "ftp.log", &mut evidence).unwrap(),
+            1
+        );
+
+        let conversation = evidence.conversations().next().unwrap();
+        assert_eq!(conversation.connections, 2);
+        assert_eq!(
+            conversation.services.get(&(443, "tcp")),
+            Some(&Some("http".to_string()))
+        );
+        let credential = conversation.credentials.iter().next().unwrap();
+        assert_eq!(credential.username, "anonymous");
+        assert_eq!(credential.password.as_deref(), Some("guest@"));
+    }
+
+    #[test]
+    fn test_read_json_log() {
+        let http = r#"{"ts":1700000000.1,"id.orig_h":"10.0.0.5","id.orig_p":40002,"id.resp_h":"10.0.0.9","id.resp_p":8000,"host":"intranet:8000","uri":"/admin","username":"admin","password":"s3cret"}"#;
+        let mut evidence = NetworkEvidence::default();
+        assert_eq!(
+            read_zeek(http.as_bytes(), "http.00:00:00-01:00:00.log", &mut evidence).unwrap(),
+            1
+        );
+
+        let conversation = evidence.conversations().next().unwrap();
+        assert!(conversation.virtual_hosts.contains("intranet"));
+        assert_eq!(conversation.credentials.len(), 1);
+        assert!(conversation.services.contains_key(&(8000, "tcp")));
+    }
+}
@@ -0,0 +1,332 @@
+//! Tool arguments parsed from command lines
+//!
+//! Which flags carry the target, ports, wordlist, URL or service is declared
+//! per tool in tools.toml (`[[tool.arguments]]`), so a scan can be found by
+//! the host it was run against even when its output never names it.
+
+use crate::error::{Result, YinxError};
+use serde::{Deserialize, Serialize};
+
+/// Fields an argument can be stored as
+const FIELDS: &[&str] = &["target", "ports", "wordlist", "url", "service"];
+
+/// Tokens that end the tool's own command line
+const COMMAND_BREAKS: &[&str] = &["|", "||", "&&", ";", ">", ">>", "2>", "2>&1", "&"];
+
+/// Where a tool takes one of its arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArgumentConfig {
+    /// Capture field it fills: target, ports, wordlist, url or service
+    pub field: String,
+    /// Flags whose value is the argument, e.g. `-p` or `--url`
+    #[serde(default)]
+    pub flags: Vec<String>,
+    /// Index among the arguments that are neither flags nor flag values
+    #[serde(default)]
+    pub positional: Option<usize>,
+}
+
+impl ArgumentConfig {
+    pub(super) fn validate(&self, tool: &str) -> Result<()> {
+        if !FIELDS.contains(&self.field.as_str()) {
+            return Err(YinxError::Config(format!(
+                "Unknown argument field '{}' for tool '{}' (expected one of: {})",
+                self.field,
+                tool,
+                FIELDS.join(", ")
+            )));
+        }
+        if self.flags.is_empty() && self.positional.is_none() {
+            return Err(YinxError::Config(format!(
+                "Argument '{}' for tool '{}' needs flags or a position",
+                self.field, tool
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Structured arguments of a captured command
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolArguments {
+    /// Host, address, range or CIDR the tool was pointed at
+    pub target: Option<String>,
+    pub ports: Option<String>,
+    pub wordlist: Option<String>,
+    pub url: Option<String>,
+    pub service: Option<String>,
+}
+
+impl ToolArguments {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn field_mut(&mut self, field: &str) -> Option<&mut Option<String>> {
+        match field {
+            "target" => Some(&mut self.target),
+            "ports" => Some(&mut self.ports),
+            "wordlist" => Some(&mut self.wordlist),
+            "url" => Some(&mut self.url),
+            "service" => Some(&mut self.service),
+            _ => None,
+        }
+    }
+
+    /// Whether the target covers `host`: the same host, or a CIDR containing it
+    pub fn targets(&self, host: &str) -> bool {
+        let Some(target) = self.target.as_deref() else {
+            return false;
+        };
+        target
+            .split(',')
+            .any(|t| t.eq_ignore_ascii_case(host) || cidr_contains(t, host))
+    }
+
+    /// The target when it names exactly one host
+    ///
+    /// CIDR ranges, comma lists, wildcards and octet ranges like
+    /// `10.0.0.1-20` name more than one.
+    pub fn single_host(&self) -> Option<&str> {
+        let target = self.target.as_deref().filter(|t| !t.is_empty())?;
+        let octet_range = target.contains('-')
+            && target
+                .bytes()
+                .all(|b| b.is_ascii_digit() || b == b'.' || b == b'-');
+        (!octet_range && !target.contains(['/', ',', '*', ' '])).then_some(target)
+    }
+
+    /// Fill the target, service and ports from URLs, e.g. `ssh://10.0.0.5:2222`
+    fn fill_from_urls(&mut self) {
+        if let Some(target) = self.target.clone() {
+            if let Some((scheme, host, port)) = split_url(&target) {
+                self.target = Some(host.to_string());
+                if !matches!(scheme, "http" | "https") {
+                    self.service.get_or_insert_with(|| scheme.to_string());
+                }
+                if let Some(port) = port {
+                    self.ports.get_or_insert_with(|| port.to_string());
+                }
+            }
+        }
+        if self.target.is_none() {
+            if let Some((_, host, _)) = self.url.as_deref().and_then(split_url) {
+                self.target = Some(host.to_string());
+            }
+        }
+    }
+}
+
+/// Parse a command line against a tool's argument specs
+///
+/// Arguments before the tool's own name (sudo, proxychains, an
+/// interpreter) are skipped, as is anything after a pipe, redirect or
+/// command separator.
+pub fn parse_arguments(
+    tool: &str,
+    specs: &[ArgumentConfig],
+    value_flags: &[String],
+    command: &str,
+) -> ToolArguments {
+    let mut args = ToolArguments::default();
+    if specs.is_empty() {
+        return args;
+    }
+
+    let tokens = split_command(command);
+    let start = tokens
+        .iter()
+        .position(|t| {
+            let name = t.rsplit('/').next().unwrap_or(t);
+            name == tool || name.starts_with(&format!("{}.", tool))
+        })
+        .map_or(1, |i| i + 1);
+    let tokens: Vec<&str> = tokens
+        .iter()
+        .skip(start)
+        .map(String::as_str)
+        .take_while(|t| !COMMAND_BREAKS.contains(t))
+        .collect();
+
+    let takes_value = |flag: &str| {
+        value_flags.iter().any(|f| f == flag)
+            || specs.iter().any(|s| s.flags.iter().any(|f| f == flag))
+    };
+    let mut positionals = Vec::new();
+    let mut flagged: Vec<(&str, &str)> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        if let Some((flag, value)) = token.split_once('=').filter(|_| token.starts_with("--")) {
+            flagged.push((flag, value));
+        } else if token.starts_with('-') && token.len() > 1 {
+            if takes_value(token) {
+                if let Some(value) = tokens.get(i + 1) {
+                    flagged.push((token, value));
+                    i += 1;
+                }
+            } else if let Some(spec_flag) = attached_flag(specs, token) {
+                // Short flag with its value attached, e.g. `-p22,80`
+                flagged.push((spec_flag, &token[spec_flag.len()..]));
+            }
+        } else {
+            positionals.push(token);
+        }
+        i += 1;
+    }
+
+    for spec in specs {
+        let value = spec
+            .flags
+            .iter()
+            .find_map(|flag| flagged.iter().find(|(f, _)| f == flag).map(|(_, v)| *v))
+            .or_else(|| spec.positional.and_then(|n| positionals.get(n).copied()));
+        if let (Some(value), Some(slot)) = (value, args.field_mut(&spec.field)) {
+            if slot.is_none() && !value.is_empty() {
+                *slot = Some(value.to_string());
+            }
+        }
+    }
+    args.fill_from_urls();
+    args
+}
+
+/// Spec flag a token starts with, for short flags written as `-p80`
+fn attached_flag<'a>(specs: &'a [ArgumentConfig], token: &str) -> Option<&'a str> {
+    specs
+        .iter()
+        .flat_map(|s| s.flags.iter())
+        .map(String::as_str)
+        .find(|flag| flag.len() == 2 && !flag.starts_with("--") && token.starts_with(flag))
+}
+
+/// Split a command line into words, honouring quotes and backslashes
+pub(super) fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                    in_word = true;
+                }
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Scheme, host and port of a URL
+fn split_url(url: &str) -> Option<(&str, &str, Option<&str>)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority.rsplit('@').next()?;
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) && !port.is_empty() => {
+            (host, Some(port))
+        }
+        _ => (authority, None),
+    };
+    (!host.is_empty()).then_some((scheme, host, port))
+}
+
+/// Whether an IPv4 CIDR like `10.0.0.0/24` contains `host`
+fn cidr_contains(cidr: &str, host: &str) -> bool {
+    let Some((network, bits)) = cidr.split_once('/') else {
+        return false;
+    };
+    let (Ok(network), Ok(host), Ok(bits)) = (
+        network.parse::<std::net::Ipv4Addr>(),
+        host.parse::<std::net::Ipv4Addr>(),
+        bits.parse::<u32>(),
+    ) else {
+        return false;
+    };
+    if bits > 32 {
+        return false;
+    }
+    let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+    u32::from(network) & mask == u32::from(host) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::PatternRegistry;
+
+    fn parse(command: &str) -> ToolArguments {
+        let registry = PatternRegistry::bundled().unwrap();
+        registry
+            .detect_tool(command)
+            .map(|tool| tool.parse_arguments(command))
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_parse_tool_arguments() {
+        let nmap = parse("sudo nmap -sV -p 22,80,445 -oN scan.txt 10.10.10.5");
+        assert_eq!(nmap.target.as_deref(), Some("10.10.10.5"));
+        assert_eq!(nmap.ports.as_deref(), Some("22,80,445"));
+        assert!(nmap.targets("10.10.10.5"));
+
+        let sweep = parse("nmap -p- --min-rate 5000 10.10.10.0/24 | tee out");
+        assert_eq!(sweep.ports.as_deref(), Some("-"));
+        assert!(sweep.targets("10.10.10.5"));
+        assert!(!sweep.targets("10.10.11.5"));
+        assert_eq!(nmap.single_host(), Some("10.10.10.5"));
+        assert_eq!(sweep.single_host(), None);
+        assert_eq!(parse("nmap 10.10.10.1-20").single_host(), None);
+        assert_eq!(
+            parse("nmap dc-01.corp.local").single_host(),
+            Some("dc-01.corp.local")
+        );
+
+        let gobuster = parse(
+            "gobuster dir -u http://10.10.10.5:8080/admin -w '/usr/share/wordlists/dirb/common.txt'",
+        );
+        assert_eq!(
+            gobuster.url.as_deref(),
+            Some("http://10.10.10.5:8080/admin")
+        );
+        assert_eq!(
+            gobuster.wordlist.as_deref(),
+            Some("/usr/share/wordlists/dirb/common.txt")
+        );
+        assert_eq!(gobuster.target.as_deref(), Some("10.10.10.5"));
+
+        let hydra = parse("hydra -l admin -P rockyou.txt 10.10.10.5 ssh");
+        assert_eq!(hydra.target.as_deref(), Some("10.10.10.5"));
+        assert_eq!(hydra.service.as_deref(), Some("ssh"));
+        assert_eq!(hydra.wordlist.as_deref(), Some("rockyou.txt"));
+
+        let hydra_url = parse("hydra -L users.txt -P pass.txt ftp://10.10.10.7:2121");
+        assert_eq!(hydra_url.target.as_deref(), Some("10.10.10.7"));
+        assert_eq!(hydra_url.service.as_deref(), Some("ftp"));
+        assert_eq!(hydra_url.ports.as_deref(), Some("2121"));
+
+        assert!(parse("ls -la").is_empty());
+    }
+}
@@ -0,0 +1,576 @@
+//! Pattern file linting for `yinx patterns validate`
+//!
+//! Checks the pattern files the daemon would load without loading them:
+//! TOML errors with their line and column, regexes that don't compile or
+//! compile too large, nested repetition prone to catastrophic
+//! backtracking, duplicate names, and tool command patterns that claim
+//! another tool's commands first.
+
+use super::{EntitiesConfig, FiltersConfig, ToolsConfig};
+use crate::rules::RulesConfig;
+use regex::RegexBuilder;
+use regex_syntax::hir::{Hir, HirKind};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Compiled size past which a pattern is reported as too complex
+///
+/// The bundled patterns stay well under it; `\w` and `\d` are Unicode-aware
+/// and large bounded repeats of them are what usually cross it.
+pub const SIZE_LIMIT: usize = 512 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The daemon would refuse the file
+    Error,
+    /// Loads, but probably not as intended
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// One problem found in a pattern file
+#[derive(Debug, Clone, Serialize)]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub file: PathBuf,
+    /// 1-based position, when the problem can be placed
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// The files `yinx patterns validate` checks
+#[derive(Debug, Clone)]
+pub struct PatternFiles {
+    pub entities: PathBuf,
+    pub tools: PathBuf,
+    pub filters: PathBuf,
+    /// Optional; a missing rules file means no rules
+    pub rules: PathBuf,
+}
+
+/// Lint every pattern file, errors and warnings in file order
+pub fn lint_pattern_files(files: &PatternFiles) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut run = |result: std::result::Result<FileLint, LintIssue>| match result {
+        Ok(lint) => issues.extend(lint.issues),
+        Err(issue) => issues.push(issue),
+    };
+    run(FileLint::load(&files.entities, lint_entities));
+    run(FileLint::load(&files.tools, lint_tools));
+    run(FileLint::load(&files.filters, lint_filters));
+    if files.rules.exists() {
+        run(FileLint::load(&files.rules, lint_rules));
+    }
+    issues
+}
+
+fn lint_entities(lint: &mut FileLint, config: &EntitiesConfig) {
+    for entity in &config.entity {
+        let what = format!("entity '{}'", entity.type_name);
+        lint.check_regex(&what, &entity.pattern, &entity.type_name);
+    }
+    lint.check_duplicates(
+        "entity type",
+        config.entity.iter().map(|e| e.type_name.as_str()),
+        "; every definition extracts",
+    );
+}
+
+fn lint_tools(lint: &mut FileLint, config: &ToolsConfig) {
+    for tool in &config.tool {
+        for pattern in &tool.command_patterns {
+            let what = format!("tool '{}' command pattern", tool.name);
+            lint.check_regex(&what, pattern, &tool.name);
+        }
+        for output in &tool.output_patterns {
+            let what = format!("tool '{}' section '{}'", tool.name, output.section);
+            lint.check_regex(&what, &output.pattern, &output.section);
+        }
+        if let Some(pattern) = &tool.host_pattern {
+            let what = format!("tool '{}' host pattern", tool.name);
+            lint.check_regex(&what, pattern, &tool.name);
+            if regex::Regex::new(pattern).is_ok_and(|re| re.captures_len() < 2) {
+                let at = lint.locate_pattern(pattern, &tool.name);
+                lint.error(at, format!("{}: needs a group capturing the host", what));
+            }
+        }
+    }
+    lint.check_duplicates(
+        "tool",
+        config.tool.iter().map(|t| t.name.as_str()),
+        "; commands are detected as the first definition",
+    );
+
+    // Detection takes the first tool with a matching command pattern, so
+    // an earlier tool whose pattern matches a later tool's own command
+    // line takes its captures
+    let compiled: Vec<Vec<regex::Regex>> = config
+        .tool
+        .iter()
+        .map(|tool| {
+            tool.command_patterns
+                .iter()
+                .filter_map(|p| regex::Regex::new(p).ok())
+                .collect()
+        })
+        .collect();
+    for (later, tool) in config.tool.iter().enumerate() {
+        for probe in command_probes(&tool.name, &tool.command_patterns) {
+            let shadowing = config.tool[..later]
+                .iter()
+                .zip(&compiled)
+                .filter(|(earlier, _)| earlier.name != tool.name)
+                .find_map(|(earlier, patterns)| {
+                    patterns
+                        .iter()
+                        .find(|re| re.is_match(&probe))
+                        .map(|re| (earlier, re))
+                });
+            if let Some((earlier, re)) = shadowing {
+                let at = lint.locate_nth(&quoted(&tool.name), 0);
+                lint.warn(
+                    at,
+                    format!(
+                        "`{}` is detected as '{}' (its pattern '{}' comes first), not '{}'",
+                        probe,
+                        earlier.name,
+                        re.as_str(),
+                        tool.name
+                    ),
+                );
+                break;
+            }
+        }
+    }
+}
+
+fn lint_filters(lint: &mut FileLint, config: &FiltersConfig) {
+    let lists: [(&str, Vec<(&str, &str)>); 4] = [
+        (
+            "tier1 pattern",
+            config
+                .tier1
+                .normalization_patterns
+                .iter()
+                .map(|p| (p.name.as_str(), p.pattern.as_str()))
+                .collect(),
+        ),
+        (
+            "tier2 pattern",
+            config
+                .tier2
+                .technical_patterns
+                .iter()
+                .map(|p| (p.name.as_str(), p.pattern.as_str()))
+                .collect(),
+        ),
+        (
+            "tier3 pattern",
+            config
+                .tier3
+                .cluster_patterns
+                .iter()
+                .map(|p| (p.name.as_str(), p.pattern.as_str()))
+                .collect(),
+        ),
+        (
+            "boilerplate pattern",
+            config
+                .boilerplate
+                .patterns
+                .iter()
+                .map(|p| (p.name.as_str(), p.pattern.as_str()))
+                .collect(),
+        ),
+    ];
+    for (kind, patterns) in lists {
+        for (name, pattern) in &patterns {
+            lint.check_regex(&format!("{} '{}'", kind, name), pattern, name);
+        }
+        lint.check_duplicates(kind, patterns.iter().map(|(name, _)| *name), "");
+    }
+}
+
+fn lint_rules(lint: &mut FileLint, config: &RulesConfig) {
+    for rule in &config.rule {
+        let when = &rule.when;
+        for (field, pattern) in [
+            ("command", &when.command),
+            ("output", &when.output),
+            ("entity_value", &when.entity_value),
+        ] {
+            if let Some(pattern) = pattern {
+                let what = format!("rule '{}' {}", rule.name, field);
+                lint.check_regex(&what, pattern, &rule.name);
+            }
+        }
+    }
+    lint.check_duplicates("rule", config.rule.iter().map(|r| r.name.as_str()), "");
+}
+
+/// Command lines a tool's own patterns say are its: the tool name, and
+/// each plain-text command pattern (`^gobuster dir`, `sqlmap.py`, `-sV`)
+/// as typed after it
+fn command_probes(name: &str, patterns: &[String]) -> Vec<String> {
+    let mut probes = vec![name.to_string()];
+    for pattern in patterns {
+        let anchored = pattern.starts_with('^');
+        let text = pattern.trim_start_matches('^').trim_end_matches("\\b");
+        if text.is_empty()
+            || text.contains(['\\', '[', ']', '(', ')', '{', '}', '*', '+', '?', '|', '$'])
+        {
+            continue;
+        }
+        let probe = if anchored || text.starts_with(name) {
+            text.to_string()
+        } else {
+            format!("{} {}", name, text)
+        };
+        if !probes.contains(&probe) {
+            probes.push(probe);
+        }
+    }
+    probes
+}
+
+fn quoted(name: &str) -> String {
+    format!("\"{}\"", name)
+}
+
+/// Issues found in one file, with its text for placing them
+struct FileLint {
+    path: PathBuf,
+    content: String,
+    issues: Vec<LintIssue>,
+}
+
+impl FileLint {
+    /// Read and parse `path`, then run `lint` over it
+    ///
+    /// A file that can't be read or parsed is a single error, placed at
+    /// the TOML error when there is one.
+    fn load<T: DeserializeOwned>(
+        path: &Path,
+        lint: impl FnOnce(&mut Self, &T),
+    ) -> std::result::Result<Self, LintIssue> {
+        let failed = |line, column, message| LintIssue {
+            severity: Severity::Error,
+            file: path.to_path_buf(),
+            line,
+            column,
+            message,
+        };
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| failed(None, None, format!("can't read file: {}", e)))?;
+        let config: T = toml::from_str(&content).map_err(|e| {
+            let at = e.span().map(|span| position(&content, span.start));
+            failed(
+                at.map(|(line, _)| line),
+                at.map(|(_, column)| column),
+                e.message().trim().to_string(),
+            )
+        })?;
+        let mut file = Self {
+            path: path.to_path_buf(),
+            content,
+            issues: Vec::new(),
+        };
+        lint(&mut file, &config);
+        Ok(file)
+    }
+
+    fn warn(&mut self, at: Option<(usize, usize)>, message: String) {
+        self.push(Severity::Warning, at, message);
+    }
+
+    fn error(&mut self, at: Option<(usize, usize)>, message: String) {
+        self.push(Severity::Error, at, message);
+    }
+
+    fn push(&mut self, severity: Severity, at: Option<(usize, usize)>, message: String) {
+        self.issues.push(LintIssue {
+            severity,
+            file: self.path.clone(),
+            line: at.map(|(line, _)| line),
+            column: at.map(|(_, column)| column),
+            message,
+        });
+    }
+
+    /// Warn about each name in `names` seen before, at its repeat
+    fn check_duplicates<'a>(
+        &mut self,
+        kind: &str,
+        names: impl IntoIterator<Item = &'a str>,
+        note: &str,
+    ) {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for name in names {
+            let count = seen.entry(name).or_default();
+            if *count > 0 {
+                let at = self.locate_nth(&quoted(name), *count);
+                self.warn(
+                    at,
+                    format!("{} '{}' is defined more than once{}", kind, name, note),
+                );
+            }
+            *count += 1;
+        }
+    }
+
+    /// Where the `n`th (0-based) occurrence of `needle` is
+    fn locate_nth(&self, needle: &str, n: usize) -> Option<(usize, usize)> {
+        let offset = self.content.match_indices(needle).nth(n)?.0;
+        Some(position(&self.content, offset))
+    }
+
+    /// Where a pattern is written, or else the entry it belongs to
+    fn locate_pattern(&self, pattern: &str, owner: &str) -> Option<(usize, usize)> {
+        self.locate_nth(pattern, 0)
+            .or_else(|| self.locate_nth(&quoted(owner), 0))
+    }
+
+    /// Compile `pattern`, reporting it if it fails, is too big, or nests
+    /// unbounded repetition
+    fn check_regex(&mut self, what: &str, pattern: &str, owner: &str) {
+        let at = self.locate_pattern(pattern, owner);
+        let hir = match regex_syntax::Parser::new().parse(pattern) {
+            Ok(hir) => hir,
+            Err(e) => {
+                let reason = e.to_string();
+                let reason = reason.lines().last().unwrap_or_default().trim();
+                self.error(at, format!("{}: invalid regex: {}", what, reason));
+                return;
+            }
+        };
+        if let Err(e) = RegexBuilder::new(pattern).build() {
+            self.error(at, format!("{}: invalid regex: {}", what, e));
+            return;
+        }
+        if RegexBuilder::new(pattern)
+            .size_limit(SIZE_LIMIT)
+            .build()
+            .is_err()
+        {
+            self.warn(
+                at,
+                format!(
+                    "{}: compiles to over {} KiB, which slows matching; Unicode classes \
+                     (\\w, \\d) in large bounded repeats are the usual cause, try (?-u)",
+                    what,
+                    SIZE_LIMIT / 1024
+                ),
+            );
+        }
+        if nested_repetition(&hir) {
+            self.warn(
+                at,
+                format!(
+                    "{}: nested unbounded repetition like (a+)+ is prone to catastrophic \
+                     backtracking",
+                    what
+                ),
+            );
+        }
+    }
+}
+
+/// 1-based line and column of a byte offset
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |newline| &before[newline + 1..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// Whether an unbounded repetition repeats something that is itself an
+/// unbounded repetition, give or take parts that can match nothing, so
+/// the same text can be split between the two in many ways
+fn nested_repetition(hir: &Hir) -> bool {
+    match hir.kind() {
+        HirKind::Repetition(rep) => {
+            if rep.max.is_none() && repeats_unbounded(&rep.sub) {
+                return true;
+            }
+            nested_repetition(&rep.sub)
+        }
+        HirKind::Capture(cap) => nested_repetition(&cap.sub),
+        HirKind::Concat(parts) | HirKind::Alternation(parts) => parts.iter().any(nested_repetition),
+        _ => false,
+    }
+}
+
+fn repeats_unbounded(hir: &Hir) -> bool {
+    let is_unbounded = |hir: &Hir| matches!(strip_captures(hir).kind(), HirKind::Repetition(rep) if rep.max.is_none());
+    let hir = strip_captures(hir);
+    match hir.kind() {
+        HirKind::Concat(parts) => parts.iter().enumerate().any(|(i, part)| {
+            is_unbounded(part)
+                && parts
+                    .iter()
+                    .enumerate()
+                    .all(|(j, other)| i == j || other.properties().minimum_len() == Some(0))
+        }),
+        HirKind::Alternation(branches) => branches.iter().any(is_unbounded),
+        _ => is_unbounded(hir),
+    }
+}
+
+fn strip_captures(mut hir: &Hir) -> &Hir {
+    while let HirKind::Capture(cap) = hir.kind() {
+        hir = &cap.sub;
+    }
+    hir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn templates() -> PatternFiles {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("config-templates");
+        PatternFiles {
+            entities: dir.join("entities.toml"),
+            tools: dir.join("tools.toml"),
+            filters: dir.join("filters.toml"),
+            rules: dir.join("rules.toml"),
+        }
+    }
+
+    fn issues_in(files: &PatternFiles, file: &Path) -> Vec<LintIssue> {
+        lint_pattern_files(files)
+            .into_iter()
+            .filter(|issue| issue.file == file)
+            .collect()
+    }
+
+    #[test]
+    fn test_bundled_patterns_have_no_errors() {
+        let issues = lint_pattern_files(&templates());
+        let errors: Vec<_> = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(!issues
+            .iter()
+            .any(|issue| issue.message.contains("more than once")));
+    }
+
+    #[test]
+    fn test_lint_entities() {
+        let temp_dir = TempDir::new().unwrap();
+        let files = PatternFiles {
+            entities: temp_dir.path().join("entities.toml"),
+            rules: temp_dir.path().join("rules.toml"),
+            ..templates()
+        };
+
+        // A TOML error is placed where the parser stopped
+        std::fs::write(&files.entities, "[[entity]]\ntype = \"ip\"\npattern = \n").unwrap();
+        let issues = issues_in(&files, &files.entities);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!((issues[0].line, issues[0].column), (Some(3), Some(11)));
+
+        std::fs::write(
+            &files.entities,
+            "[[entity]]\n\
+             type = \"token\"\n\
+             pattern = '(\\w+)+!'\n\
+             confidence = 0.5\n\
+             context_window = 10\n\
+             [[entity]]\n\
+             type = \"port\"\n\
+             pattern = '(\\d+'\n\
+             confidence = 0.5\n\
+             context_window = 10\n\
+             [[entity]]\n\
+             type = \"token\"\n\
+             pattern = '[a-z]{8}'\n\
+             confidence = 0.5\n\
+             context_window = 10\n",
+        )
+        .unwrap();
+        let issues = issues_in(&files, &files.entities);
+        let messages: Vec<_> = issues
+            .iter()
+            .map(|i| (i.severity, i.line, i.message.as_str()))
+            .collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert_eq!(messages[0].0, Severity::Warning);
+        assert_eq!(messages[0].1, Some(3));
+        assert!(messages[0].2.contains("catastrophic backtracking"));
+        assert_eq!(messages[1].0, Severity::Error);
+        assert_eq!(messages[1].1, Some(8));
+        assert!(messages[1].2.contains("invalid regex"));
+        assert_eq!(messages[2].1, Some(12));
+        assert!(messages[2]
+            .2
+            .contains("entity type 'token' is defined more than once"));
+    }
+
+    #[test]
+    fn test_lint_tool_overlap() {
+        let temp_dir = TempDir::new().unwrap();
+        let files = PatternFiles {
+            tools: temp_dir.path().join("tools.toml"),
+            rules: temp_dir.path().join("rules.toml"),
+            ..templates()
+        };
+        std::fs::write(
+            &files.tools,
+            "[[tool]]\n\
+             name = \"ffuf\"\n\
+             command_patterns = ['^ffuf\\b', '-w']\n\
+             entity_hints = []\n\
+             output_patterns = []\n\
+             [[tool]]\n\
+             name = \"wfuzz\"\n\
+             command_patterns = ['^wfuzz\\b', '-w']\n\
+             entity_hints = []\n\
+             output_patterns = []\n\
+             [[tool]]\n\
+             name = \"gobuster\"\n\
+             command_patterns = ['^gobuster dir']\n\
+             entity_hints = []\n\
+             output_patterns = []\n",
+        )
+        .unwrap();
+        let issues = issues_in(&files, &files.tools);
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].line, Some(7), "{:?}", issues);
+        assert!(issues[0]
+            .message
+            .contains("`wfuzz -w` is detected as 'ffuf'"));
+    }
+
+    #[test]
+    fn test_nested_repetition() {
+        let nested = |pattern: &str| nested_repetition(&regex_syntax::parse(pattern).unwrap());
+        assert!(nested("(a+)+"));
+        assert!(nested("(?:\\s*\\w+)*$"));
+        assert!(nested("(x|y*)+"));
+        assert!(!nested("(ab+)+"));
+        assert!(!nested("\\d{1,3}(\\.\\d{1,3}){3}"));
+        assert!(!nested("[a-z]+@[a-z]+"));
+    }
+}
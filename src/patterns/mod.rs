@@ -5,12 +5,27 @@
 //! - Entity extraction patterns (IPs, ports, CVEs, credentials, etc.)
 //! - Tool detection patterns (nmap, gobuster, hydra, etc.)
 //! - Filter normalization patterns (for tier 1-3 filtering)
+//! - Boilerplate kept out of the indexes (banners, copyright, usage text)
+//! - Tool argument specs (target, ports, wordlist, ...)
+//! - Linting of the pattern files (`yinx patterns validate`)
+//! - Per-pattern match counts and timings (`yinx patterns stats`)
+
+mod arguments;
+mod lint;
+mod stats;
+
+pub use arguments::{parse_arguments, ArgumentConfig, ToolArguments};
+pub use lint::{lint_pattern_files, LintIssue, PatternFiles, Severity};
+pub use stats::{PatternCounters, PatternStats};
 
 use crate::error::{Result, YinxError};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 
 /// Entity pattern configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,7 +53,21 @@ pub struct ToolConfig {
     pub name: String,
     pub command_patterns: Vec<String>,
     pub entity_hints: Vec<String>,
+    /// Above this many output lines, only hinted (and redactable) entity
+    /// types are extracted
+    #[serde(default)]
+    pub hints_only_above: Option<usize>,
     pub output_patterns: Vec<OutputPatternConfig>,
+    /// Arguments stored with each capture of this tool
+    #[serde(default)]
+    pub arguments: Vec<ArgumentConfig>,
+    /// Flags that take a value, so it isn't read as a positional argument
+    #[serde(default)]
+    pub value_flags: Vec<String>,
+    /// Line opening each host's part of multi-host output; the host is the
+    /// `host` group, or the first group
+    #[serde(default)]
+    pub host_pattern: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +106,46 @@ pub struct FiltersConfig {
     pub tier1: Tier1Config,
     pub tier2: Tier2Config,
     pub tier3: Tier3Config,
+    #[serde(default)]
+    pub boilerplate: BoilerplateConfig,
+}
+
+/// Lines left out of keyword indexing and embedding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoilerplateConfig {
+    /// Use the boilerplate shipped with yinx as well as `patterns`
+    #[serde(default = "default_boilerplate_defaults")]
+    pub defaults: bool,
+    #[serde(default)]
+    pub patterns: Vec<BoilerplatePattern>,
+}
+
+fn default_boilerplate_defaults() -> bool {
+    true
+}
+
+impl Default for BoilerplateConfig {
+    fn default() -> Self {
+        Self {
+            defaults: default_boilerplate_defaults(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoilerplatePattern {
+    pub name: String,
+    pub pattern: String,
+    /// Only for captures of these tools; empty for every capture
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+/// The boilerplate file shipped with yinx
+#[derive(Debug, Deserialize)]
+struct BundledBoilerplate {
+    pattern: Vec<BoilerplatePattern>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -94,6 +163,32 @@ pub struct Tier2Config {
     pub score_threshold_percentile: f32,
     pub technical_patterns: Vec<TechnicalPattern>,
     pub max_technical_score: f32,
+    #[serde(default)]
+    pub auto_threshold: AutoThresholdConfig,
+}
+
+/// Adapts the tier 2 percentile to each tool's typical output volume
+///
+/// Noisy tools get a higher percentile so roughly `target_lines` survive
+/// tier 2; quiet ones keep more of their output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoThresholdConfig {
+    pub enabled: bool,
+    pub target_lines: usize,
+    pub min_percentile: f32,
+    pub max_percentile: f32,
+}
+
+impl Default for AutoThresholdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_lines: 200,
+            min_percentile: 0.5,
+            max_percentile: 0.98,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,6 +209,33 @@ pub struct CompiledEntityPattern {
     pub context_window: usize,
     pub redact: bool,
     pub description: String,
+    /// Shared by clones of the registry
+    pub counters: Arc<PatternCounters>,
+}
+
+impl CompiledEntityPattern {
+    /// Spans of every match in `text`, or of its `value` group when the
+    /// pattern has one, counted and timed
+    fn find_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let started = Instant::now();
+        let has_value = self.regex.capture_names().any(|n| n == Some("value"));
+        let spans: Vec<_> = if has_value {
+            self.regex
+                .captures_iter(text)
+                .filter_map(|caps| caps.name("value"))
+                .filter(|m| !m.is_empty())
+                .map(|m| (m.start(), m.end()))
+                .collect()
+        } else {
+            self.regex
+                .find_iter(text)
+                .map(|m| (m.start(), m.end()))
+                .collect()
+        };
+        self.counters
+            .record(text.len(), spans.len(), started.elapsed());
+        spans
+    }
 }
 
 /// Compiled tool matcher with pre-compiled regexes
@@ -122,9 +244,166 @@ pub struct CompiledToolMatcher {
     pub name: String,
     pub command_patterns: Vec<Regex>,
     pub entity_hints: Vec<String>,
+    pub hints_only_above: Option<usize>,
     pub output_patterns: Vec<(Regex, String)>,
+    pub arguments: Vec<ArgumentConfig>,
+    pub value_flags: Vec<String>,
+    pub host_pattern: Option<Regex>,
+}
+
+/// The part of a multi-host tool's output about one host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostSection {
+    /// None for the lines before the first host
+    pub host: Option<String>,
+    /// Lines of the output, counted from 0
+    pub lines: Range<usize>,
+    /// Byte offsets into the output
+    pub bytes: Range<usize>,
+}
+
+/// Host of the section holding byte `offset` of the output
+pub fn host_at(sections: &[HostSection], offset: usize) -> Option<&str> {
+    let after = sections.partition_point(|s| s.bytes.start <= offset);
+    sections[..after].last()?.host.as_deref()
+}
+
+impl CompiledToolMatcher {
+    /// Target, ports and other declared arguments of a command line
+    pub fn parse_arguments(&self, command: &str) -> ToolArguments {
+        parse_arguments(&self.name, &self.arguments, &self.value_flags, command)
+    }
+
+    /// Label each line of this tool's output with its section
+    ///
+    /// A line matching one of the output patterns opens that section, and
+    /// the lines after it stay in it until another pattern matches. Lines
+    /// before the first match have no section.
+    pub fn section_lines(&self, output: &str) -> Vec<Option<&str>> {
+        let mut current = None;
+        output
+            .lines()
+            .map(|line| {
+                if let Some((_, section)) = self
+                    .output_patterns
+                    .iter()
+                    .find(|(re, _)| re.is_match(line))
+                {
+                    current = Some(section.as_str());
+                }
+                current
+            })
+            .collect()
+    }
+
+    /// Split this tool's output into one section per host
+    ///
+    /// A line matching the host pattern opens that host's section, which
+    /// runs until the next one. Output naming fewer than two hosts isn't
+    /// split, and gives no sections.
+    pub fn host_sections(&self, output: &str) -> Vec<HostSection> {
+        let Some(pattern) = &self.host_pattern else {
+            return Vec::new();
+        };
+
+        let mut sections: Vec<HostSection> = Vec::new();
+        let mut offset = 0;
+        for (i, line) in output.split_inclusive('\n').enumerate() {
+            let host = pattern
+                .captures(line.trim_end_matches(['\n', '\r']))
+                .and_then(|c| c.name("host").or_else(|| c.get(1)))
+                .map(|m| m.as_str().to_string());
+            if host.is_some() || sections.is_empty() {
+                sections.push(HostSection {
+                    host,
+                    lines: i..i,
+                    bytes: offset..offset,
+                });
+            }
+            offset += line.len();
+            if let Some(section) = sections.last_mut() {
+                section.lines.end = i + 1;
+                section.bytes.end = offset;
+            }
+        }
+
+        if sections.iter().filter(|s| s.host.is_some()).count() < 2 {
+            return Vec::new();
+        }
+        sections
+    }
+}
+
+/// How a capture's tool was identified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionMethod {
+    /// Named by whatever sent the capture (an importer, `yinx add --tool`)
+    Named,
+    /// The command ran an alias or wrapper mapped in `[tools.aliases]`
+    Alias,
+    /// A command pattern matched the command line
+    Command,
+    /// Output patterns matched the output, for tools run through wrappers
+    /// and scripts the command patterns don't recognize
+    Output,
 }
 
+impl DetectionMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DetectionMethod::Named => "named",
+            DetectionMethod::Alias => "alias",
+            DetectionMethod::Command => "command",
+            DetectionMethod::Output => "output",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "named" => Some(DetectionMethod::Named),
+            "alias" => Some(DetectionMethod::Alias),
+            "command" => Some(DetectionMethod::Command),
+            "output" => Some(DetectionMethod::Output),
+            _ => None,
+        }
+    }
+}
+
+/// A tool identified for a capture, and how sure the match is
+#[derive(Debug, Clone, Copy)]
+pub struct ToolDetection<'a> {
+    pub tool: &'a CompiledToolMatcher,
+    pub method: DetectionMethod,
+    /// 1.0 for command matches; for output matches, the share of the
+    /// tool's output patterns found
+    pub confidence: f32,
+}
+
+/// Programs that run the command after them, skipped to find what an
+/// alias would be
+const LAUNCHERS: &[&str] = &[
+    "sudo",
+    "doas",
+    "env",
+    "time",
+    "nohup",
+    "proxychains",
+    "proxychains4",
+    "bash",
+    "sh",
+    "zsh",
+    "python",
+    "python3",
+];
+
+/// Lines of output searched for a tool's fingerprint
+const FINGERPRINT_LINES: usize = 500;
+
+/// Distinct output patterns a tool needs to match to be fingerprinted;
+/// one generic line like `Permission denied` isn't enough
+const FINGERPRINT_MIN_PATTERNS: usize = 2;
+
 /// Compiled normalization pattern
 #[derive(Debug, Clone)]
 pub struct CompiledNormalizationPattern {
@@ -142,6 +421,14 @@ pub struct CompiledTechnicalPattern {
     pub weight: f32,
 }
 
+/// Compiled boilerplate pattern
+#[derive(Debug, Clone)]
+pub struct CompiledBoilerplatePattern {
+    pub name: String,
+    pub regex: Regex,
+    pub tools: Vec<String>,
+}
+
 /// Pattern registry with all pre-compiled patterns
 #[derive(Clone)]
 pub struct PatternRegistry {
@@ -159,12 +446,16 @@ pub struct PatternRegistry {
     pub tier2_technical: Vec<CompiledTechnicalPattern>,
     /// Tier 3 cluster patterns
     pub tier3_cluster: Vec<CompiledNormalizationPattern>,
+    /// Boilerplate patterns, shipped and user-defined
+    pub boilerplate: Vec<CompiledBoilerplatePattern>,
     /// Tier 1 configuration
     pub tier1_config: Tier1Config,
     /// Tier 2 configuration
     pub tier2_config: Tier2Config,
     /// Tier 3 configuration
     pub tier3_config: Tier3Config,
+    /// Aliases and wrapper scripts that run a tool, to its name
+    pub aliases: HashMap<String, String>,
 }
 
 impl PatternRegistry {
@@ -198,6 +489,31 @@ impl PatternRegistry {
         Self::from_configs(entities_config, tools_config, filters_config)
     }
 
+    /// Load pattern registry from configuration files, or the bundled
+    /// patterns when they can't be read or don't parse
+    ///
+    /// The error that forced the fallback comes back with the registry, so
+    /// the caller can tell the user capture runs on defaults.
+    pub fn from_config_files_or_bundled(
+        entities_path: &Path,
+        tools_path: &Path,
+        filters_path: &Path,
+    ) -> Result<(Self, Option<YinxError>)> {
+        match Self::from_config_files(entities_path, tools_path, filters_path) {
+            Ok(registry) => Ok((registry, None)),
+            Err(e) => Ok((Self::bundled()?, Some(e))),
+        }
+    }
+
+    /// Build pattern registry from the pattern files shipped with yinx
+    pub fn bundled() -> Result<Self> {
+        Self::from_configs(
+            toml::from_str(include_str!("../../config-templates/entities.toml"))?,
+            toml::from_str(include_str!("../../config-templates/tools.toml"))?,
+            toml::from_str(include_str!("../../config-templates/filters.toml"))?,
+        )
+    }
+
     /// Build pattern registry from parsed configurations
     pub fn from_configs(
         entities_config: EntitiesConfig,
@@ -223,6 +539,7 @@ impl PatternRegistry {
                 context_window: entity_cfg.context_window,
                 redact: entity_cfg.redact,
                 description: entity_cfg.description.clone(),
+                counters: Arc::default(),
             });
 
             entities_by_type.insert(entity_cfg.type_name.clone(), idx);
@@ -261,11 +578,32 @@ impl PatternRegistry {
                 })
                 .collect::<Result<Vec<_>>>()?;
 
+            for argument in &tool_cfg.arguments {
+                argument.validate(&tool_cfg.name)?;
+            }
+
+            let host_pattern = tool_cfg
+                .host_pattern
+                .as_deref()
+                .map(|p| {
+                    Regex::new(p).map_err(|e| {
+                        YinxError::Config(format!(
+                            "Invalid host pattern for tool '{}': {}",
+                            tool_cfg.name, e
+                        ))
+                    })
+                })
+                .transpose()?;
+
             tools.push(CompiledToolMatcher {
                 name: tool_cfg.name.clone(),
                 command_patterns,
                 entity_hints: tool_cfg.entity_hints.clone(),
+                hints_only_above: tool_cfg.hints_only_above,
                 output_patterns,
+                arguments: tool_cfg.arguments.clone(),
+                value_flags: tool_cfg.value_flags.clone(),
+                host_pattern,
             });
 
             tools_by_name.insert(tool_cfg.name.clone(), idx);
@@ -341,6 +679,43 @@ impl PatternRegistry {
 
         tier3_cluster.sort_by_key(|p| p.priority);
 
+        // Compile boilerplate patterns, the shipped ones first
+        let mut boilerplate_patterns = Vec::new();
+        if filters_config.boilerplate.defaults {
+            let bundled: BundledBoilerplate =
+                toml::from_str(include_str!("../../config-templates/boilerplate.toml"))?;
+            boilerplate_patterns.extend(bundled.pattern);
+        }
+        boilerplate_patterns.extend(filters_config.boilerplate.patterns.iter().cloned());
+        let boilerplate: Vec<CompiledBoilerplatePattern> = boilerplate_patterns
+            .into_iter()
+            .map(|bp| {
+                Regex::new(&bp.pattern)
+                    .map(|r| CompiledBoilerplatePattern {
+                        name: bp.name.clone(),
+                        regex: r,
+                        tools: bp.tools.clone(),
+                    })
+                    .map_err(|e| {
+                        YinxError::Config(format!(
+                            "Invalid boilerplate pattern '{}': {}",
+                            bp.name, e
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let auto = &filters_config.tier2.auto_threshold;
+        if !(0.0..=1.0).contains(&auto.min_percentile)
+            || !(0.0..=1.0).contains(&auto.max_percentile)
+            || auto.min_percentile > auto.max_percentile
+        {
+            return Err(YinxError::Config(format!(
+                "Invalid tier2 auto_threshold bounds: min_percentile {} / max_percentile {}",
+                auto.min_percentile, auto.max_percentile
+            )));
+        }
+
         Ok(Self {
             entities,
             entities_by_type,
@@ -349,9 +724,11 @@ impl PatternRegistry {
             tier1_normalization,
             tier2_technical,
             tier3_cluster,
+            boilerplate,
             tier1_config: filters_config.tier1,
             tier2_config: filters_config.tier2,
             tier3_config: filters_config.tier3,
+            aliases: HashMap::new(),
         })
     }
 
@@ -362,24 +739,246 @@ impl PatternRegistry {
             .find(|tool| tool.command_patterns.iter().any(|p| p.is_match(command)))
     }
 
+    /// Match counts and timings of every entity pattern, in file order
+    pub fn pattern_stats(&self) -> Vec<PatternStats> {
+        self.entities
+            .iter()
+            .map(|pattern| pattern.counters.snapshot(&pattern.type_name))
+            .collect()
+    }
+
+    /// Map aliases and wrapper scripts to the tools they run
+    ///
+    /// Aliases naming a tool the registry doesn't have are skipped with a
+    /// warning.
+    pub fn with_aliases(mut self, aliases: &BTreeMap<String, String>) -> Self {
+        for (alias, tool) in aliases {
+            if self.tools_by_name.contains_key(tool) {
+                self.aliases.insert(alias.clone(), tool.clone());
+            } else {
+                tracing::warn!(
+                    "Ignoring tools.aliases.{}: no tool named '{}' in the tool patterns",
+                    alias,
+                    tool
+                );
+            }
+        }
+        self
+    }
+
+    /// The tool an alias or wrapper script in `command` runs
+    ///
+    /// The program is the first word past environment assignments and
+    /// launchers like sudo; it matches an alias by itself or by its file
+    /// name, so `./scan.sh` and `~/bin/scan.sh` both match `scan.sh`.
+    pub fn alias_for(&self, command: &str) -> Option<&CompiledToolMatcher> {
+        if self.aliases.is_empty() {
+            return None;
+        }
+        let words = arguments::split_command(command);
+        let program = words.iter().find(|word| {
+            let assignment = word
+                .split_once('=')
+                .is_some_and(|(name, _)| !name.contains('/'));
+            !assignment && !word.starts_with('-') && !LAUNCHERS.contains(&word.as_str())
+        })?;
+        let file_name = program.rsplit('/').next().unwrap_or(program);
+        let tool = self
+            .aliases
+            .get(program.as_str())
+            .or_else(|| self.aliases.get(file_name))?;
+        self.tool(tool)
+    }
+
+    /// Detect the tool from a mapped alias, the command line, or failing
+    /// those, from the output
+    pub fn identify_tool(&self, command: &str, output: &str) -> Option<ToolDetection<'_>> {
+        if let Some(tool) = self.alias_for(command) {
+            return Some(ToolDetection {
+                tool,
+                method: DetectionMethod::Alias,
+                confidence: 1.0,
+            });
+        }
+        if let Some(tool) = self.detect_tool(command) {
+            return Some(ToolDetection {
+                tool,
+                method: DetectionMethod::Command,
+                confidence: 1.0,
+            });
+        }
+        self.fingerprint_tool(output)
+    }
+
+    /// The tool whose output patterns the output matches best
+    ///
+    /// Only the first [`FINGERPRINT_LINES`] lines are searched, and a tool
+    /// needs [`FINGERPRINT_MIN_PATTERNS`] distinct patterns to match. The
+    /// most patterns matched wins, then the higher share of the tool's
+    /// patterns, then the earlier tool.
+    pub fn fingerprint_tool(&self, output: &str) -> Option<ToolDetection<'_>> {
+        let lines: Vec<&str> = output.lines().take(FINGERPRINT_LINES).collect();
+        let mut best: Option<(usize, ToolDetection)> = None;
+        for tool in &self.tools {
+            if tool.output_patterns.len() < FINGERPRINT_MIN_PATTERNS {
+                continue;
+            }
+            let matched = tool
+                .output_patterns
+                .iter()
+                .filter(|(re, _)| lines.iter().any(|line| re.is_match(line)))
+                .count();
+            if matched < FINGERPRINT_MIN_PATTERNS {
+                continue;
+            }
+            let confidence = matched as f32 / tool.output_patterns.len() as f32;
+            let better = best.as_ref().is_none_or(|(most, current)| {
+                matched > *most || (matched == *most && confidence > current.confidence)
+            });
+            if better {
+                best = Some((
+                    matched,
+                    ToolDetection {
+                        tool,
+                        method: DetectionMethod::Output,
+                        confidence,
+                    },
+                ));
+            }
+        }
+        best.map(|(_, detection)| detection)
+    }
+
+    /// Look up a tool matcher by name
+    pub fn tool(&self, name: &str) -> Option<&CompiledToolMatcher> {
+        self.tools_by_name.get(name).map(|&idx| &self.tools[idx])
+    }
+
     /// Extract all entities from text
     pub fn extract_entities(&self, text: &str) -> Vec<ExtractedEntity> {
         self.entities
             .iter()
-            .flat_map(|pattern| {
-                pattern.regex.find_iter(text).map(|m| ExtractedEntity {
-                    type_name: pattern.type_name.clone(),
-                    value: m.as_str().to_string(),
-                    start: m.start(),
-                    end: m.end(),
-                    context: Self::get_context(text, m.start(), m.end(), pattern.context_window),
-                    confidence: pattern.confidence,
-                    redact: pattern.redact,
-                })
+            .flat_map(|pattern| Self::find_entities(pattern, text))
+            .collect()
+    }
+
+    /// Extract entities from a detected tool's output, hinted types first
+    ///
+    /// Matches of the tool's `entity_hints` are returned first, and other
+    /// patterns may not claim text a hinted pattern already matched. When
+    /// the output is longer than `hints_only_above` lines, only hinted and
+    /// redactable patterns run at all.
+    pub fn extract_entities_for_tool(
+        &self,
+        text: &str,
+        tool: Option<&CompiledToolMatcher>,
+    ) -> Vec<ExtractedEntity> {
+        let Some(tool) = tool.filter(|t| !t.entity_hints.is_empty()) else {
+            return self.extract_entities(text);
+        };
+
+        let (hinted, rest): (Vec<_>, Vec<_>) = self
+            .entities
+            .iter()
+            .partition(|p| tool.entity_hints.contains(&p.type_name));
+        let hints_only = tool
+            .hints_only_above
+            .is_some_and(|max| text.lines().count() > max);
+
+        let mut entities: Vec<ExtractedEntity> = hinted
+            .iter()
+            .flat_map(|pattern| Self::find_entities(pattern, text))
+            .collect();
+
+        // Hinted spans sorted by start, with the furthest end seen so far
+        let mut claimed: Vec<(usize, usize)> = entities.iter().map(|e| (e.start, e.end)).collect();
+        claimed.sort_unstable();
+        let mut furthest = 0;
+        for span in &mut claimed {
+            furthest = furthest.max(span.1);
+            span.1 = furthest;
+        }
+        let overlaps_hint = |e: &ExtractedEntity| {
+            let before = claimed.partition_point(|&(start, _)| start < e.end);
+            before > 0 && claimed[before - 1].1 > e.start
+        };
+
+        for pattern in rest {
+            if hints_only && !pattern.redact {
+                continue;
+            }
+            entities.extend(
+                Self::find_entities(pattern, text)
+                    .into_iter()
+                    .filter(|e| !overlaps_hint(e)),
+            );
+        }
+
+        entities
+    }
+
+    fn find_entities(pattern: &CompiledEntityPattern, text: &str) -> Vec<ExtractedEntity> {
+        pattern
+            .find_spans(text)
+            .into_iter()
+            .map(|(start, end)| ExtractedEntity {
+                type_name: pattern.type_name.clone(),
+                value: text[start..end].to_string(),
+                start,
+                end,
+                context: Self::get_context(text, start, end, pattern.context_window),
+                confidence: pattern.confidence,
+                redact: pattern.redact,
             })
             .collect()
     }
 
+    /// Replace matches of `redact = true` entity patterns with `[REDACTED:<type>]`
+    ///
+    /// Patterns with a `secret` capture group only redact that group, so
+    /// `-p (?P<secret>\S+)` keeps the flag visible. Returns None when nothing
+    /// needed redacting.
+    pub fn redact_sensitive(&self, text: &str) -> Option<String> {
+        let mut spans: Vec<(usize, usize, &str)> = Vec::new();
+        for pattern in self.entities.iter().filter(|p| p.redact) {
+            let started = Instant::now();
+            let mut matches = 0;
+            for caps in pattern.regex.captures_iter(text) {
+                matches += 1;
+                let m = caps
+                    .name("secret")
+                    .or_else(|| caps.get(0))
+                    .filter(|m| !m.is_empty());
+                if let Some(m) = m {
+                    spans.push((m.start(), m.end(), &pattern.type_name));
+                }
+            }
+            pattern
+                .counters
+                .record(text.len(), matches, started.elapsed());
+        }
+        if spans.is_empty() {
+            return None;
+        }
+
+        // Earliest, then longest span wins; overlapping spans are absorbed
+        spans.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for (start, end, type_name) in spans {
+            if start < cursor {
+                continue;
+            }
+            redacted.push_str(&text[cursor..start]);
+            redacted.push_str(&format!("[REDACTED:{}]", type_name));
+            cursor = end;
+        }
+        redacted.push_str(&text[cursor..]);
+
+        Some(redacted)
+    }
+
     /// Get context around a match
     fn get_context(text: &str, start: usize, end: usize, window: usize) -> String {
         let context_start = start.saturating_sub(window);
@@ -410,6 +1009,14 @@ impl PatternRegistry {
         (weighted_sum / max_score).min(1.0)
     }
 
+    /// Whether a line of a capture of `tool` is boilerplate
+    pub fn is_boilerplate(&self, line: &str, tool: Option<&str>) -> bool {
+        self.boilerplate.iter().any(|p| {
+            (p.tools.is_empty() || tool.is_some_and(|t| p.tools.iter().any(|pt| pt == t)))
+                && p.regex.is_match(line)
+        })
+    }
+
     /// Apply tier 3 normalization
     pub fn normalize_tier3(&self, line: &str) -> String {
         let mut result = line.to_string();
@@ -439,6 +1046,73 @@ pub struct ExtractedEntity {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bundled_patterns() {
+        let registry = PatternRegistry::bundled().unwrap();
+        assert!(registry.detect_tool("nmap -sV 10.0.0.1").is_some());
+
+        // Missing pattern files fall back to the same patterns
+        let missing = Path::new("/nonexistent/yinx/patterns.toml");
+        let (fallback, error) =
+            PatternRegistry::from_config_files_or_bundled(missing, missing, missing).unwrap();
+        assert!(error.is_some());
+        assert_eq!(fallback.entities.len(), registry.entities.len());
+
+        let output = "SMB 10.0.0.5 445 DC01 (name:DC01) (domain:corp.local)\n\
+                      MSSQLSvc/sql01.corp.local:1433  svc_sql  CN=Users,DC=corp,DC=local\n\
+                      [+] CORP\\alice:Summer2024";
+        let found: Vec<(String, String)> = registry
+            .extract_entities(output)
+            .into_iter()
+            .filter(|e| e.type_name.starts_with("ad_") || e.type_name == "spn")
+            .map(|e| (e.type_name, e.value))
+            .collect();
+        for expected in [
+            ("ad_domain", "(domain:corp.local)"),
+            ("spn", "MSSQLSvc/sql01.corp.local:1433"),
+            ("ad_domain", "DC=corp,DC=local"),
+            ("ad_user", "CORP\\alice"),
+        ] {
+            assert!(
+                found.contains(&(expected.0.to_string(), expected.1.to_string())),
+                "{:?} not in {:?}",
+                expected,
+                found
+            );
+        }
+    }
+
+    #[test]
+    fn test_boilerplate() {
+        let registry = PatternRegistry::bundled().unwrap();
+        let banner = "Starting Nmap 7.94 ( https://nmap.org ) at 2024-01-01 10:00 UTC";
+        assert!(registry.is_boilerplate(banner, Some("nmap")));
+        assert!(!registry.is_boilerplate(banner, Some("gobuster")));
+        assert!(!registry.is_boilerplate(banner, None));
+        assert!(registry.is_boilerplate("Usage: smbclient [OPTIONS] service", None));
+        assert!(registry.is_boilerplate("===============================", Some("gobuster")));
+        assert!(registry.is_boilerplate("      \\ \\_\\   \\ \\_\\  \\ \\____/", Some("ffuf")));
+        assert!(!registry.is_boilerplate("22/tcp open ssh OpenSSH 8.2p1", Some("nmap")));
+
+        // User additions, with or without the shipped list
+        let mut filters: FiltersConfig =
+            toml::from_str(include_str!("../../config-templates/filters.toml")).unwrap();
+        filters.boilerplate.patterns.push(BoilerplatePattern {
+            name: "motd".to_string(),
+            pattern: "^Authorized use only".to_string(),
+            tools: vec![],
+        });
+        filters.boilerplate.defaults = false;
+        let registry = PatternRegistry::from_configs(
+            EntitiesConfig { entity: vec![] },
+            ToolsConfig { tool: vec![] },
+            filters,
+        )
+        .unwrap();
+        assert!(registry.is_boilerplate("Authorized use only. Activity is logged.", None));
+        assert!(!registry.is_boilerplate(banner, Some("nmap")));
+    }
+
     #[test]
     fn test_entity_pattern_compilation() {
         let config = EntitiesConfig {
@@ -466,6 +1140,7 @@ mod tests {
                 score_threshold_percentile: 0.8,
                 technical_patterns: vec![],
                 max_technical_score: 10.0,
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -474,6 +1149,7 @@ mod tests {
                 cluster_patterns: vec![],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         let registry = PatternRegistry::from_configs(config, tools_config, filters_config).unwrap();
@@ -507,6 +1183,7 @@ mod tests {
                 score_threshold_percentile: 0.8,
                 technical_patterns: vec![],
                 max_technical_score: 10.0,
+                auto_threshold: Default::default(),
             },
             tier3: Tier3Config {
                 cluster_min_size: 2,
@@ -515,6 +1192,7 @@ mod tests {
                 cluster_patterns: vec![],
                 preserve_metadata: vec![],
             },
+            boilerplate: Default::default(),
         };
 
         let registry = PatternRegistry::from_configs(config, tools_config, filters_config).unwrap();
@@ -525,5 +1203,342 @@ mod tests {
         assert_eq!(entities.len(), 2);
         assert_eq!(entities[0].value, "192.168.1.1");
         assert_eq!(entities[1].value, "10.0.0.1");
+
+        // Clones count into the same totals
+        registry.clone().extract_entities("nothing here");
+        let stats = registry.pattern_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].type_name, "ip_address");
+        assert_eq!(stats[0].scans, 2);
+        assert_eq!(stats[0].matches, 2);
+        assert_eq!(stats[0].bytes, (text.len() + "nothing here".len()) as u64);
+    }
+
+    #[test]
+    fn test_fingerprint_entities() {
+        let registry = PatternRegistry::bundled().unwrap();
+        let output = "22/tcp open  ssh\n\
+                      |_banner: SSH-2.0-OpenSSH_8.2p1 Ubuntu-4ubuntu0.5\n\
+                      MAC Address: 08:00:27:AB:CD:EF (Oracle VirtualBox virtual NIC)\n\
+                      OS details: Linux 4.15 - 5.6\n\
+                      Service Info: Host: web01; OS: Linux; CPE: cpe:/o:linux:linux_kernel\n\
+                      220 (vsFTPd 3.0.3)\n";
+        let entities = registry.extract_entities(output);
+        let values = |type_name: &str| -> Vec<&str> {
+            entities
+                .iter()
+                .filter(|e| e.type_name == type_name)
+                .map(|e| e.value.as_str())
+                .collect()
+        };
+        assert_eq!(values("os_guess"), ["Linux 4.15 - 5.6", "Linux"]);
+        assert_eq!(values("mac_vendor"), ["Oracle VirtualBox virtual NIC"]);
+        assert_eq!(
+            values("banner"),
+            [
+                "SSH-2.0-OpenSSH_8.2p1 Ubuntu-4ubuntu0.5",
+                "220 (vsFTPd 3.0.3)"
+            ]
+        );
+        assert_eq!(values("mac_address"), ["08:00:27:AB:CD:EF"]);
+    }
+
+    #[test]
+    fn test_extraction_prefers_tool_hints() {
+        let entity = |type_name: &str, pattern: &str, redact: bool| EntityConfig {
+            type_name: type_name.to_string(),
+            pattern: pattern.to_string(),
+            confidence: 0.9,
+            context_window: 10,
+            redact,
+            description: String::new(),
+        };
+        let config = EntitiesConfig {
+            entity: vec![
+                entity("number", r"\d+", false),
+                entity("port", r"\d+/tcp", false),
+                entity("password", r"pass=\S+", true),
+            ],
+        };
+        let tool = |hints_only_above| ToolConfig {
+            name: "nmap".to_string(),
+            command_patterns: vec!["^nmap".to_string()],
+            entity_hints: vec!["port".to_string()],
+            hints_only_above,
+            output_patterns: vec![],
+            arguments: vec![],
+            value_flags: vec![],
+            host_pattern: None,
+        };
+        let filters = || FiltersConfig {
+            tier1: Tier1Config {
+                max_occurrences: 3,
+                normalization_patterns: vec![],
+            },
+            tier2: Tier2Config {
+                entropy_weight: 0.3,
+                uniqueness_weight: 0.3,
+                technical_weight: 0.2,
+                change_weight: 0.2,
+                score_threshold_percentile: 0.8,
+                technical_patterns: vec![],
+                max_technical_score: 10.0,
+                auto_threshold: Default::default(),
+            },
+            tier3: Tier3Config {
+                cluster_min_size: 2,
+                max_cluster_size: 1000,
+                representative_strategy: "highest_entropy".to_string(),
+                cluster_patterns: vec![],
+                preserve_metadata: vec![],
+            },
+            boilerplate: Default::default(),
+        };
+        let text = "22/tcp open\nretries 3\npass=hunter2";
+
+        let registry = PatternRegistry::from_configs(
+            config.clone(),
+            ToolsConfig {
+                tool: vec![tool(None)],
+            },
+            filters(),
+        )
+        .unwrap();
+        let nmap = registry.detect_tool("nmap -sV 10.0.0.1");
+        let types: Vec<_> = registry
+            .extract_entities_for_tool(text, nmap)
+            .into_iter()
+            .map(|e| (e.type_name, e.value))
+            .collect();
+        // The hinted port comes first and keeps "22" from also being a number
+        assert_eq!(types[0], ("port".to_string(), "22/tcp".to_string()));
+        assert!(types.contains(&("number".to_string(), "3".to_string())));
+        assert!(!types.contains(&("number".to_string(), "22".to_string())));
+
+        let registry = PatternRegistry::from_configs(
+            config,
+            ToolsConfig {
+                tool: vec![tool(Some(1))],
+            },
+            filters(),
+        )
+        .unwrap();
+        let nmap = registry.detect_tool("nmap -sV 10.0.0.1");
+        let types: Vec<_> = registry
+            .extract_entities_for_tool(text, nmap)
+            .into_iter()
+            .map(|e| e.type_name)
+            .collect();
+        // Large output: unhinted patterns are skipped, secrets still found
+        assert_eq!(types, vec!["port", "password"]);
+    }
+
+    #[test]
+    fn test_fingerprint_tool() {
+        let registry = PatternRegistry::bundled().unwrap();
+        let output = "Starting Nmap 7.94
+\
+                      PORT   STATE SERVICE
+\
+                      22/tcp open  ssh
+\
+                      80/tcp open  http
+\
+                      Service Info: OS: Linux
+";
+
+        let by_command = registry.identify_tool("nmap -sV 10.0.0.5", output).unwrap();
+        assert_eq!(by_command.tool.name, "nmap");
+        assert_eq!(by_command.method, DetectionMethod::Command);
+        assert_eq!(by_command.confidence, 1.0);
+
+        // A wrapper script the command patterns don't know
+        let by_output = registry
+            .identify_tool("./scan.sh 10.0.0.5", output)
+            .unwrap();
+        assert_eq!(by_output.tool.name, "nmap");
+        assert_eq!(by_output.method, DetectionMethod::Output);
+        assert!(by_output.confidence > 0.5 && by_output.confidence < 1.0);
+
+        // One generic line is not a fingerprint
+        assert!(registry
+            .identify_tool("./login.sh", "Permission denied (publickey).\n")
+            .is_none());
+        assert_eq!(
+            DetectionMethod::parse("output"),
+            Some(DetectionMethod::Output)
+        );
+    }
+
+    #[test]
+    fn test_alias_detection() {
+        let aliases = BTreeMap::from([
+            ("nmapAll".to_string(), "nmap".to_string()),
+            ("scan.sh".to_string(), "nmap".to_string()),
+            ("fuzz".to_string(), "no-such-tool".to_string()),
+        ]);
+        let registry = PatternRegistry::bundled().unwrap().with_aliases(&aliases);
+        assert_eq!(registry.aliases.len(), 2);
+
+        let detection = registry.identify_tool("nmapAll 10.0.0.5", "").unwrap();
+        assert_eq!(detection.tool.name, "nmap");
+        assert_eq!(detection.method, DetectionMethod::Alias);
+        let nmap = |command| registry.alias_for(command).map(|t| t.name.as_str());
+        assert_eq!(nmap("sudo ./scan.sh 10.0.0.5"), Some("nmap"));
+        assert_eq!(nmap("TARGET=10.0.0.5 bash ~/bin/scan.sh"), Some("nmap"));
+        assert_eq!(nmap("cat scan.sh"), None);
+        assert_eq!(nmap("fuzz -u http://x"), None);
+    }
+
+    #[test]
+    fn test_section_lines() {
+        let tool = CompiledToolMatcher {
+            name: "nmap".to_string(),
+            command_patterns: vec![],
+            entity_hints: vec![],
+            hints_only_above: None,
+            arguments: vec![],
+            value_flags: vec![],
+            host_pattern: None,
+            output_patterns: vec![
+                (
+                    Regex::new(r"\d+/tcp\s+open").unwrap(),
+                    "open_port".to_string(),
+                ),
+                (
+                    Regex::new(r"^Host script results").unwrap(),
+                    "host_script_results".to_string(),
+                ),
+            ],
+        };
+
+        let output = "Starting Nmap\n22/tcp open ssh\n| ssh-hostkey: 2048\nHost script results:\n| smb2-time: ok";
+        assert_eq!(
+            tool.section_lines(output),
+            vec![
+                None,
+                Some("open_port"),
+                Some("open_port"),
+                Some("host_script_results"),
+                Some("host_script_results"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_host_sections() {
+        let tool = CompiledToolMatcher {
+            name: "nmap".to_string(),
+            command_patterns: vec![],
+            entity_hints: vec![],
+            hints_only_above: None,
+            output_patterns: vec![],
+            arguments: vec![],
+            value_flags: vec![],
+            host_pattern: Some(
+                Regex::new(r"^Nmap scan report for (?:\S+ \()?(?P<host>[^\s()]+)\)?\s*$").unwrap(),
+            ),
+        };
+
+        let output = "Starting Nmap\n\
+                      Nmap scan report for 10.0.0.5\n\
+                      22/tcp open ssh\n\
+                      Nmap scan report for web.lab (10.0.0.6)\r\n\
+                      22/tcp open ssh\n\
+                      80/tcp open http";
+        let sections = tool.host_sections(output);
+        let hosts: Vec<_> = sections.iter().map(|s| s.host.as_deref()).collect();
+        assert_eq!(hosts, [None, Some("10.0.0.5"), Some("10.0.0.6")]);
+        assert_eq!(sections[1].lines, 1..3);
+        assert_eq!(sections[2].lines, 3..6);
+        assert_eq!(sections[2].bytes.end, output.len());
+        assert_eq!(
+            &output[sections[1].bytes.clone()],
+            "Nmap scan report for 10.0.0.5\n22/tcp open ssh\n"
+        );
+
+        let offset = output.rfind("80/tcp").unwrap();
+        assert_eq!(host_at(&sections, offset), Some("10.0.0.6"));
+        assert_eq!(host_at(&sections, 0), None);
+
+        // A single host isn't split
+        assert!(tool
+            .host_sections("Nmap scan report for 10.0.0.5\n22/tcp open ssh")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_redact_sensitive() {
+        let entity = |type_name: &str, pattern: &str, redact: bool| EntityConfig {
+            type_name: type_name.to_string(),
+            pattern: pattern.to_string(),
+            confidence: 0.8,
+            context_window: 10,
+            redact,
+            description: String::new(),
+        };
+        let config = EntitiesConfig {
+            entity: vec![
+                entity("ip_address", r"\b\d{1,3}(\.\d{1,3}){3}\b", false),
+                entity(
+                    "credential_password",
+                    r"(?i)(password|passwd|pwd)\s*[:=]\s*\S+",
+                    true,
+                ),
+                entity(
+                    "credential_env_secret",
+                    r"\b[A-Z0-9_]*(SECRET|PASSWORD)[A-Z0-9_]*=(?P<secret>\S+)",
+                    true,
+                ),
+                entity(
+                    "credential_cli_password",
+                    r"\bsshpass\b[^|;&]*?\s-p\s*(?P<secret>[^\s-]\S*)",
+                    true,
+                ),
+            ],
+        };
+        let filters_config = FiltersConfig {
+            tier1: Tier1Config {
+                max_occurrences: 3,
+                normalization_patterns: vec![],
+            },
+            tier2: Tier2Config {
+                entropy_weight: 0.3,
+                uniqueness_weight: 0.3,
+                technical_weight: 0.2,
+                change_weight: 0.2,
+                score_threshold_percentile: 0.8,
+                technical_patterns: vec![],
+                max_technical_score: 10.0,
+                auto_threshold: Default::default(),
+            },
+            tier3: Tier3Config {
+                cluster_min_size: 2,
+                max_cluster_size: 1000,
+                representative_strategy: "highest_entropy".to_string(),
+                cluster_patterns: vec![],
+                preserve_metadata: vec![],
+            },
+            boilerplate: Default::default(),
+        };
+        let registry =
+            PatternRegistry::from_configs(config, ToolsConfig { tool: vec![] }, filters_config)
+                .unwrap();
+
+        assert_eq!(
+            registry
+                .redact_sensitive("sshpass -p P@ssw0rd ssh root@10.0.0.1")
+                .unwrap(),
+            "sshpass -p [REDACTED:credential_cli_password] ssh root@10.0.0.1"
+        );
+        // Overlapping matches collapse into the earliest, longest span
+        assert_eq!(
+            registry
+                .redact_sensitive("AWS_SECRET=abc123 DB_PASSWORD=hunter2 ./run.sh")
+                .unwrap(),
+            "AWS_SECRET=[REDACTED:credential_env_secret] \
+             DB_[REDACTED:credential_password] ./run.sh"
+        );
+        assert!(registry.redact_sensitive("nmap -p 22 10.0.0.1").is_none());
     }
 }
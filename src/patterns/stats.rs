@@ -0,0 +1,93 @@
+//! Per-pattern extraction metrics
+//!
+//! Every entity pattern counts the texts it scanned, the bytes in them, the
+//! matches it found and the time its regex took, for as long as the
+//! registry lives. The counters are shared by clones of the registry, so
+//! the daemon's pipeline, dry runs and target registration all add to the
+//! same numbers, which `yinx patterns stats` asks the daemon for to find
+//! slow patterns and patterns that match far more than they should.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Running totals for one pattern
+#[derive(Debug, Default)]
+pub struct PatternCounters {
+    scans: AtomicU64,
+    bytes: AtomicU64,
+    matches: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl PatternCounters {
+    /// Record one scan of `bytes` of text
+    pub fn record(&self, bytes: usize, matches: usize, elapsed: Duration) {
+        self.scans.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.matches.fetch_add(matches as u64, Ordering::Relaxed);
+        self.nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// The totals so far, for the pattern extracting `type_name`
+    pub fn snapshot(&self, type_name: &str) -> PatternStats {
+        PatternStats {
+            type_name: type_name.to_string(),
+            scans: self.scans.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            matches: self.matches.load(Ordering::Relaxed),
+            time_us: self.nanos.load(Ordering::Relaxed) / 1_000,
+        }
+    }
+}
+
+/// What one entity pattern has done since the daemon started
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternStats {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// Texts scanned, redaction passes included
+    pub scans: u64,
+    pub bytes: u64,
+    pub matches: u64,
+    /// Time spent matching, in microseconds
+    pub time_us: u64,
+}
+
+impl PatternStats {
+    pub fn matches_per_scan(&self) -> f64 {
+        if self.scans == 0 {
+            0.0
+        } else {
+            self.matches as f64 / self.scans as f64
+        }
+    }
+
+    /// Matching throughput in MiB/s, None before anything was timed
+    pub fn throughput(&self) -> Option<f64> {
+        (self.time_us > 0)
+            .then(|| self.bytes as f64 / (1024.0 * 1024.0) / (self.time_us as f64 / 1e6))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let counters = PatternCounters::default();
+        counters.record(1024 * 1024, 3, Duration::from_millis(500));
+        counters.record(1024 * 1024, 0, Duration::from_millis(500));
+
+        let stats = counters.snapshot("ip");
+        assert_eq!(stats.type_name, "ip");
+        assert_eq!(stats.scans, 2);
+        assert_eq!(stats.matches, 3);
+        assert_eq!(stats.time_us, 1_000_000);
+        assert_eq!(stats.matches_per_scan(), 1.5);
+        assert_eq!(stats.throughput(), Some(2.0));
+        assert_eq!(PatternStats::default().throughput(), None);
+    }
+}
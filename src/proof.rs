@@ -0,0 +1,184 @@
+//! Exam proof-file detection
+//!
+//! Spots captures that print a proof artifact: `cat proof.txt`, `type
+//! local.txt`, or any file reader whose output contains a bare flag hash.
+//! Which files, readers and flag format count is set under `[proof]` in the
+//! config. Detected proofs are tagged and collected into the exam checklist.
+
+use crate::config::ProofConfig;
+use crate::entities::Entity;
+use crate::error::{Result, YinxError};
+use regex::Regex;
+
+/// A proof printed by a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofArtifact {
+    /// Name of the file that was read
+    pub file: String,
+    /// Proof value (flag hash or the named file's first line)
+    pub value: String,
+    /// Host seen in the same capture, if any
+    pub host: Option<String>,
+}
+
+/// Compiled `[proof]` settings; the default detects nothing
+#[derive(Debug, Default)]
+pub struct ProofDetector {
+    files: Vec<String>,
+    readers: Vec<String>,
+    /// None when detection is disabled
+    flag: Option<Regex>,
+}
+
+impl ProofDetector {
+    pub fn new(config: &ProofConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        let flag = Regex::new(&config.flag_pattern)
+            .map_err(|e| YinxError::Config(format!("Invalid proof.flag_pattern: {}", e)))?;
+        Ok(Self {
+            files: config.files.clone(),
+            readers: config.readers.clone(),
+            flag: Some(flag),
+        })
+    }
+
+    /// Check a capture for a proof artifact
+    ///
+    /// Failed commands never count: their output is usually an error message.
+    pub fn detect(
+        &self,
+        command: &str,
+        output: &str,
+        exit_code: i32,
+        entities: &[Entity],
+    ) -> Option<ProofArtifact> {
+        let flag = self.flag.as_ref()?;
+        if exit_code != 0 {
+            return None;
+        }
+        let read = self.files_read(command)?;
+
+        let named = read
+            .iter()
+            .find(|file| self.files.iter().any(|f| f.eq_ignore_ascii_case(file)));
+        let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+        let (file, value) = match named {
+            // A named proof file counts whatever its format
+            Some(file) => (file.clone(), lines.next()?.to_string()),
+            None => (
+                read.first().cloned().unwrap_or_else(|| "stdin".to_string()),
+                lines.find(|line| flag.is_match(line))?.to_string(),
+            ),
+        };
+
+        Some(ProofArtifact {
+            file,
+            value,
+            host: capture_host(entities),
+        })
+    }
+
+    /// Base names of files passed to reader commands, or None if no reader ran
+    fn files_read(&self, command: &str) -> Option<Vec<String>> {
+        let mut reader_seen = false;
+        let mut files = Vec::new();
+        for segment in command.split(['|', ';', '&']) {
+            let mut words = segment.split_whitespace();
+            let Some(program) = words.next().map(base_name) else {
+                continue;
+            };
+            if !self.readers.iter().any(|r| r.eq_ignore_ascii_case(program)) {
+                continue;
+            }
+            reader_seen = true;
+            files.extend(
+                words
+                    .filter(|w| !w.starts_with('-'))
+                    .map(|w| base_name(w.trim_matches(['"', '\''])).to_string()),
+            );
+        }
+        reader_seen.then_some(files)
+    }
+}
+
+/// Last path component, for both `/` and `\` separators
+fn base_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// First non-loopback address or hostname among a capture's entities
+fn capture_host(entities: &[Entity]) -> Option<String> {
+    entities
+        .iter()
+        .find(|e| e.entity_type == "ip_address" && !e.value.starts_with("127."))
+        .or_else(|| entities.iter().find(|e| e.entity_type == "hostname"))
+        .map(|e| e.value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAG: &str = "4f1b2c3d4e5f60718293a4b5c6d7e8f9";
+
+    fn ip(value: &str) -> Entity {
+        Entity {
+            entity_type: "ip_address".to_string(),
+            value: value.to_string(),
+            context: String::new(),
+            start: 0,
+            end: 0,
+            confidence: 0.9,
+            should_redact: false,
+        }
+    }
+
+    #[test]
+    fn test_detects_proof_files() {
+        let detector = ProofDetector::new(&ProofConfig::default()).unwrap();
+        let entities = vec![ip("127.0.0.1"), ip("10.11.1.5")];
+
+        let proof = detector
+            .detect(
+                "cat /root/proof.txt && ip a",
+                &format!("{}\n1: lo: ...", FLAG),
+                0,
+                &entities,
+            )
+            .unwrap();
+        assert_eq!(proof.file, "proof.txt");
+        assert_eq!(proof.value, FLAG);
+        assert_eq!(proof.host.as_deref(), Some("10.11.1.5"));
+
+        // Windows reader, no host in the output
+        let proof = detector
+            .detect(r"type C:\Users\bob\Desktop\local.txt", FLAG, 0, &[])
+            .unwrap();
+        assert_eq!(proof.file, "local.txt");
+        assert!(proof.host.is_none());
+
+        // Any file holding a bare flag
+        let proof = detector.detect("cat user.txt", FLAG, 0, &[]).unwrap();
+        assert_eq!(proof.file, "user.txt");
+
+        assert!(detector
+            .detect("cat notes.txt", "todo: enum smb", 0, &[])
+            .is_none());
+        assert!(detector.detect("echo hi", FLAG, 0, &[]).is_none());
+        assert!(detector.detect("cat proof.txt", "", 0, &[]).is_none());
+        let missing = "cat: proof.txt: No such file or directory";
+        assert!(detector.detect("cat proof.txt", missing, 1, &[]).is_none());
+    }
+
+    #[test]
+    fn test_disabled_detector() {
+        let config = ProofConfig {
+            enabled: false,
+            ..ProofConfig::default()
+        };
+        let detector = ProofDetector::new(&config).unwrap();
+        assert!(detector.detect("cat proof.txt", FLAG, 0, &[]).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Python bindings (`yinx-py` feature)
+//!
+//! Wraps the [`Yinx`] facade for notebooks and analysis scripts. Build the
+//! extension with `maturin develop --release` (the `pyproject.toml` turns the
+//! feature on), then:
+//!
+//! ```python
+//! import yinx
+//! store = yinx.Yinx("~/.yinx/data")
+//! session = store.start_session("htb-lame")
+//! store.ingest(session, "nmap -sV 10.10.10.3", open("nmap.txt").read())
+//! store.search("vsftpd")
+//! store.graph(session)
+//! ```
+//!
+//! Records come back as plain dicts and lists, with the same fields as the
+//! CLI's `--json` output.
+
+use crate::config::{Config, ReportConfig};
+use crate::daemon::CaptureEvent;
+use crate::{Yinx, YinxError};
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::path::PathBuf;
+
+impl From<YinxError> for PyErr {
+    fn from(e: YinxError) -> Self {
+        PyRuntimeError::new_err(e.to_string())
+    }
+}
+
+/// Convert through JSON so Python gets ordinary dicts and lists
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+/// An open yinx data directory
+#[pyclass(name = "Yinx")]
+struct PyYinx {
+    inner: Yinx,
+    runtime: tokio::runtime::Runtime,
+}
+
+#[pymethods]
+impl PyYinx {
+    /// Open `data_dir` with the bundled patterns, or the store of a yinx
+    /// config file (the user's config when neither is given)
+    #[new]
+    #[pyo3(signature = (data_dir=None, config=None))]
+    fn new(data_dir: Option<PathBuf>, config: Option<PathBuf>) -> PyResult<Self> {
+        let inner = match (data_dir, config) {
+            (Some(data_dir), _) => Yinx::open(crate::daemon::expand_tilde(&data_dir))?,
+            (None, Some(path)) => Yinx::with_config(&Config::load(&path)?)?,
+            (None, None) => {
+                let path = Config::default_path()?;
+                let config = if path.exists() {
+                    Config::load(&path)?
+                } else {
+                    Config::default()
+                };
+                Yinx::with_config(&config)?
+            }
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(Self { inner, runtime })
+    }
+
+    /// Id of the session with this name, created if needed
+    fn start_session(&self, name: &str) -> PyResult<String> {
+        Ok(self.inner.start_session(name)?.id.to_string())
+    }
+
+    /// Run a command's output through the pipeline; returns the capture id
+    #[pyo3(signature = (session_id, command, output, exit_code=0, cwd="/", timestamp=None))]
+    fn ingest(
+        &self,
+        session_id: String,
+        command: String,
+        output: String,
+        exit_code: i32,
+        cwd: &str,
+        timestamp: Option<i64>,
+    ) -> PyResult<Option<i64>> {
+        let event = CaptureEvent {
+            session_id,
+            timestamp: timestamp.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            command,
+            output,
+            exit_code,
+            cwd: cwd.to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        };
+        Ok(self.runtime.block_on(self.inner.ingest(event))?)
+    }
+
+    /// Keyword search; list of dicts, best match first
+    #[pyo3(signature = (query, limit=10))]
+    fn search(&self, py: Python<'_>, query: &str, limit: usize) -> PyResult<PyObject> {
+        to_python(py, &self.inner.search(query, limit)?)
+    }
+
+    /// Unique entities of a session, optionally of one type
+    #[pyo3(signature = (session_id, entity_type=None))]
+    fn entities(
+        &self,
+        py: Python<'_>,
+        session_id: &str,
+        entity_type: Option<&str>,
+    ) -> PyResult<PyObject> {
+        to_python(py, &self.inner.entities(session_id, entity_type)?)
+    }
+
+    /// Hosts seen in capture output, as of a Unix time if given
+    #[pyo3(signature = (session_id=None, as_of=None))]
+    fn hosts(&self, session_id: Option<&str>, as_of: Option<i64>) -> PyResult<Vec<String>> {
+        Ok(self.inner.hosts(session_id, as_of)?)
+    }
+
+    /// Host graph as {"nodes": [...], "edges": [...]}, as of a Unix time if given
+    #[pyo3(signature = (session_id=None, as_of=None))]
+    fn graph(
+        &self,
+        py: Python<'_>,
+        session_id: Option<&str>,
+        as_of: Option<i64>,
+    ) -> PyResult<PyObject> {
+        to_python(py, &self.inner.host_graph(session_id, as_of)?)
+    }
+
+    /// Terminal transcript of a session
+    fn transcript(&self, session_id: &str) -> PyResult<String> {
+        Ok(self.inner.transcript(session_id)?)
+    }
+
+    /// Report on a session with the default sections, as a dict
+    #[pyo3(signature = (session_id, include_evidence=false))]
+    fn report(
+        &self,
+        py: Python<'_>,
+        session_id: &str,
+        include_evidence: bool,
+    ) -> PyResult<PyObject> {
+        let config = ReportConfig::default();
+        to_python(
+            py,
+            &self.inner.report(session_id, &config, include_evidence)?,
+        )
+    }
+}
+
+#[pymodule]
+fn yinx(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyYinx>()
+}
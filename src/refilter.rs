@@ -0,0 +1,379 @@
+//! Differential re-filtering
+//!
+//! After `filters.toml` or `entities.toml` changes, stored output can be run
+//! through the new patterns without recapturing anything. Each session's
+//! blobs are replayed in capture order through a fresh filter pipeline, so
+//! Tier 1 deduplication and the Tier 2 change history build up the same way
+//! they did live. Only captures whose chunks or entities come out different
+//! are rewritten.
+
+use crate::daemon::{capture_host, chunk_output, scope_to_host};
+use crate::entities::EntityExtractor;
+use crate::error::{Result, YinxError};
+use crate::filtering::FilterPipeline;
+use crate::patterns::{host_at, HostSection, PatternRegistry};
+use crate::storage::{metadata_host, CaptureRecord, EntityPosition, StorageManager};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// What re-filtering changed for one capture
+#[derive(Debug, Clone)]
+pub struct CaptureRefilter {
+    pub capture_id: i64,
+    pub session_id: String,
+    pub tool: Option<String>,
+    /// Whether the chunks were rebuilt; the counts are informational either way
+    pub chunks_changed: bool,
+    pub chunks_before: usize,
+    pub chunks_after: usize,
+    /// Entities (type, value) the new patterns extract that weren't stored
+    pub entities_added: Vec<(String, String)>,
+    /// Stored entities (type, value) the new patterns no longer extract
+    pub entities_removed: Vec<(String, String)>,
+}
+
+/// Outcome of re-filtering stored captures
+#[derive(Debug, Default)]
+pub struct RefilterReport {
+    /// Captures replayed through the pipeline
+    pub captures: usize,
+    /// Captures whose chunks or entities changed
+    pub changed: Vec<CaptureRefilter>,
+    /// Captures skipped because their blob is gone
+    pub missing_blobs: usize,
+    /// Archived sessions left alone
+    pub archived_skipped: usize,
+}
+
+impl RefilterReport {
+    /// Chunks of rebuilt captures before re-filtering
+    pub fn chunks_before(&self) -> usize {
+        self.rebuilt().map(|c| c.chunks_before).sum()
+    }
+
+    /// Chunks of rebuilt captures after re-filtering
+    pub fn chunks_after(&self) -> usize {
+        self.rebuilt().map(|c| c.chunks_after).sum()
+    }
+
+    fn rebuilt(&self) -> impl Iterator<Item = &CaptureRefilter> {
+        self.changed.iter().filter(|c| c.chunks_changed)
+    }
+
+    pub fn entities_added(&self) -> usize {
+        self.changed.iter().map(|c| c.entities_added.len()).sum()
+    }
+
+    pub fn entities_removed(&self) -> usize {
+        self.changed.iter().map(|c| c.entities_removed.len()).sum()
+    }
+}
+
+/// New chunks and entities computed for a capture
+struct Refiltered {
+    chunks: Vec<(String, i32, String)>,
+    entities: Vec<(String, String, String, f32)>,
+    positions: Vec<EntityPosition>,
+    sections: Vec<HostSection>,
+}
+
+/// Re-run filtering and entity extraction over one session's captures (all when None)
+///
+/// With `dry_run` the changes are computed and reported but nothing is written.
+pub fn refilter(
+    storage: &StorageManager,
+    patterns: PatternRegistry,
+    session_id: Option<&str>,
+    dry_run: bool,
+) -> Result<RefilterReport> {
+    let patterns = Arc::new(patterns);
+    let mut report = RefilterReport::default();
+
+    let sessions = match session_id {
+        Some(id) => vec![id.to_string()],
+        None => {
+            let conn = storage.database.read_conn()?;
+            let mut stmt =
+                conn.prepare("SELECT DISTINCT session_id FROM captures ORDER BY session_id")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<std::result::Result<Vec<String>, _>>()?
+        }
+    };
+
+    let pipeline = FilterPipeline::new(patterns.clone());
+    if patterns.tier2_config.auto_threshold.enabled {
+        pipeline.seed_volumes(&storage.database.tier2_volume_means()?);
+    }
+    let extractor = EntityExtractor::new(patterns.as_ref().clone());
+
+    let mut reindex_removed = Vec::new();
+    let mut reindex_added = Vec::new();
+    for session_id in &sessions {
+        if storage.database.is_session_archived(session_id)? {
+            tracing::info!("Skipping archived session {}", session_id);
+            report.archived_skipped += 1;
+            continue;
+        }
+
+        for capture in storage.database.get_session_captures(session_id)? {
+            if !storage.blob_store.exists(&capture.output_hash) {
+                tracing::warn!(
+                    "Blob {} missing while re-filtering capture {}",
+                    capture.output_hash,
+                    capture.id
+                );
+                report.missing_blobs += 1;
+                continue;
+            }
+            report.captures += 1;
+
+            let new = replay(storage, &patterns, &pipeline, &extractor, &capture)?;
+            // Entities stored before positions and hosts were recorded get them here
+            if !dry_run {
+                storage
+                    .database
+                    .set_entity_positions(capture.id, &new.positions)?;
+                storage
+                    .database
+                    .set_subcaptures(capture.id, &new.sections)?;
+            }
+            let Some(change) = compare(storage, &capture, &new)? else {
+                continue;
+            };
+
+            if !dry_run {
+                if change.chunks_changed {
+                    let (removed, added) = storage.database.replace_capture_chunks(
+                        capture.id,
+                        &capture.output_hash,
+                        &new.chunks,
+                    )?;
+                    reindex_removed.extend(removed.into_iter().map(|id| id as u64));
+                    reindex_added.extend(added.into_iter().zip(&new.chunks).map(
+                        |(id, (text, _, metadata))| {
+                            (id as u64, text.clone(), metadata_host(metadata))
+                        },
+                    ));
+                }
+                if !change.entities_added.is_empty() || !change.entities_removed.is_empty() {
+                    storage
+                        .database
+                        .replace_capture_entities(capture.id, &new.entities)?;
+                    storage
+                        .database
+                        .set_entity_positions(capture.id, &new.positions)?;
+                }
+            }
+            report.changed.push(change);
+        }
+    }
+
+    if !reindex_removed.is_empty() || !reindex_added.is_empty() {
+        let mut index = storage.open_keyword_index()?;
+        for id in &reindex_removed {
+            index.delete(*id).map_err(|e| YinxError::Other(e.into()))?;
+        }
+        index
+            .replace_hosted_batch(&reindex_added)
+            .map_err(|e| YinxError::Other(e.into()))?;
+        index.commit().map_err(|e| YinxError::Other(e.into()))?;
+    }
+
+    Ok(report)
+}
+
+/// Run a stored capture's output through the pipeline again
+fn replay(
+    storage: &StorageManager,
+    patterns: &PatternRegistry,
+    pipeline: &FilterPipeline,
+    extractor: &EntityExtractor,
+    capture: &CaptureRecord,
+) -> Result<Refiltered> {
+    // Truncated output was indexed without the marker line
+    let output = storage.indexed_output(capture)?;
+
+    let tool = capture.tool.as_deref().and_then(|name| patterns.tool(name));
+    let sections = tool.map(|t| t.host_sections(&output)).unwrap_or_default();
+    let extracted = extractor.extract_for_tool(&output, tool);
+    let host = sections
+        .is_empty()
+        .then(|| capture_host(&capture.arguments, &extracted))
+        .flatten();
+    let positions = extracted
+        .iter()
+        .map(|e| {
+            EntityPosition::in_text(&output, &e.entity_type, &e.value, e.start, e.end)
+                .with_host(host_at(&sections, e.start).or(host.as_deref()))
+        })
+        .collect();
+    let entities = extracted
+        .into_iter()
+        .map(|e| (e.entity_type, e.value, e.context, e.confidence))
+        .collect();
+
+    let (mut clusters, _) = chunk_output(
+        pipeline,
+        storage.chunk_policy(),
+        &capture.session_id,
+        capture.tool.as_deref(),
+        tool,
+        &output,
+        &sections,
+    )?;
+    if let Some(host) = &host {
+        scope_to_host(&mut clusters, host);
+    }
+    let chunks = clusters
+        .into_iter()
+        .map(|mut cluster| {
+            if capture.original_size.is_some() {
+                if let Some(metadata) = cluster.metadata.as_object_mut() {
+                    metadata.insert("truncated".to_string(), serde_json::json!(true));
+                }
+            }
+            let metadata =
+                serde_json::to_string(&cluster.metadata).unwrap_or_else(|_| "{}".to_string());
+            (cluster.representative, cluster.size as i32, metadata)
+        })
+        .collect();
+
+    Ok(Refiltered {
+        chunks,
+        entities,
+        positions,
+        sections,
+    })
+}
+
+/// Diff the replayed results against what's stored; None when nothing changed
+fn compare(
+    storage: &StorageManager,
+    capture: &CaptureRecord,
+    new: &Refiltered,
+) -> Result<Option<CaptureRefilter>> {
+    let old_chunks: Vec<(String, i32)> = storage
+        .database
+        .get_capture_chunks(capture.id)?
+        .into_iter()
+        .map(|c| (c.representative_text, c.cluster_size))
+        .collect();
+    let new_chunks: Vec<(String, i32)> = new
+        .chunks
+        .iter()
+        .map(|(text, size, _)| (text.clone(), *size))
+        .collect();
+
+    let old_entities: BTreeSet<(String, String)> = storage
+        .database
+        .get_entities_for_capture(capture.id)?
+        .into_iter()
+        .map(|e| (e.entity_type, e.value))
+        .collect();
+    let new_entities: BTreeSet<(String, String)> = new
+        .entities
+        .iter()
+        .map(|(entity_type, value, _, _)| (entity_type.clone(), value.clone()))
+        .collect();
+
+    let chunks_changed = old_chunks != new_chunks;
+    if !chunks_changed && old_entities == new_entities {
+        return Ok(None);
+    }
+
+    Ok(Some(CaptureRefilter {
+        capture_id: capture.id,
+        session_id: capture.session_id.clone(),
+        tool: capture.tool.clone(),
+        chunks_changed,
+        chunks_before: old_chunks.len(),
+        chunks_after: new_chunks.len(),
+        entities_added: new_entities.difference(&old_entities).cloned().collect(),
+        entities_removed: old_entities.difference(&new_entities).cloned().collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_refilter_replaces_stale_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let output = b"Nmap scan report for 10.0.0.7\n22/tcp open ssh OpenSSH 8.2\n80/tcp open http Apache 2.4.41\n";
+        storage
+            .database
+            .ensure_session("s1", "s1", 0, "stopped")
+            .unwrap();
+        // Results of an older, weaker pattern set
+        let capture_id = capture("s1", "nmap -sV 10.0.0.7", output)
+            .tool("nmap")
+            .chunks(&["stale chunk"])
+            .insert(&storage)
+            .id;
+        storage
+            .database
+            .insert_entities(
+                capture_id,
+                &[(
+                    "hostname".to_string(),
+                    "stale.example".to_string(),
+                    String::new(),
+                    0.5,
+                )],
+            )
+            .unwrap();
+
+        let preview = refilter(&storage, PatternRegistry::bundled().unwrap(), None, true).unwrap();
+        assert_eq!(preview.captures, 1);
+        assert_eq!(preview.changed.len(), 1);
+        let change = &preview.changed[0];
+        assert!(change.chunks_changed);
+        assert_eq!(change.chunks_before, 1);
+        assert!(change
+            .entities_added
+            .contains(&("ip_address".to_string(), "10.0.0.7".to_string())));
+        assert_eq!(
+            change.entities_removed,
+            vec![("hostname".to_string(), "stale.example".to_string())]
+        );
+        assert_eq!(
+            storage
+                .database
+                .get_capture_chunks(capture_id)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let report = refilter(
+            &storage,
+            PatternRegistry::bundled().unwrap(),
+            Some("s1"),
+            false,
+        )
+        .unwrap();
+        assert_eq!(report.entities_added(), preview.entities_added());
+        let chunks = storage.database.get_capture_chunks(capture_id).unwrap();
+        assert_eq!(chunks.len(), report.chunks_after());
+        assert!(chunks
+            .iter()
+            .all(|c| c.representative_text != "stale chunk"));
+        assert_eq!(
+            storage.open_keyword_index().unwrap().len(),
+            report.chunks_after() as u64
+        );
+
+        let aggregates = storage.database.get_session_entities("s1", None).unwrap();
+        assert!(aggregates.iter().all(|e| e.value != "stale.example"));
+        assert!(aggregates.iter().any(|e| e.value == "10.0.0.7"));
+
+        // Replaying the same patterns again changes nothing
+        let again = refilter(&storage, PatternRegistry::bundled().unwrap(), None, false).unwrap();
+        assert!(again.changed.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+//! Fixture replay
+//!
+//! `yinx replay <dir>` feeds saved tool output through the capture
+//! pipeline as if it had just been run, one file per command, so filtering
+//! and reports can be judged on known input and a bug seen on some output
+//! can be reproduced from the output alone. Files are replayed in name
+//! order (`01-nmap.txt`, `02-gobuster.txt`, ...) with timestamps spaced a
+//! fixed interval apart from a chosen start.
+//!
+//! A file whose first line is `$ <command>` records that command line;
+//! otherwise the command is the file name without its extension and any
+//! leading ordering digits.
+
+use crate::daemon::CaptureEvent;
+use crate::error::{Result, YinxError};
+use std::path::{Path, PathBuf};
+
+/// Tag and source recorded on replayed captures
+pub const REPLAY_SOURCE: &str = "replay";
+
+/// A saved output to replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    pub path: PathBuf,
+    pub command: String,
+    pub output: String,
+}
+
+impl Fixture {
+    /// Read a fixture file; bytes that aren't UTF-8 are replaced
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read {}", path.display()),
+        })?;
+        let text = String::from_utf8_lossy(&bytes);
+        let (command, output) = match text.split_once('\n') {
+            Some((first, rest)) if first.starts_with("$ ") => {
+                (first[2..].trim().to_string(), rest.to_string())
+            }
+            _ => (command_from_name(path), text.into_owned()),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            command,
+            output,
+        })
+    }
+
+    /// Capture event for this fixture at `timestamp`
+    pub fn event(&self, session_id: &str, timestamp: i64) -> CaptureEvent {
+        CaptureEvent {
+            session_id: session_id.to_string(),
+            timestamp,
+            command: self.command.clone(),
+            output: self.output.clone(),
+            exit_code: 0,
+            cwd: self
+                .path
+                .parent()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+            username: None,
+            source: Some(REPLAY_SOURCE.to_string()),
+            tool: None,
+            context: None,
+        }
+    }
+}
+
+/// `01-nmap.txt` -> `nmap`
+fn command_from_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let trimmed = stem.trim_start_matches(|c: char| c.is_ascii_digit() || c == '-' || c == '_');
+    if trimmed.is_empty() {
+        stem
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Every fixture in `dir`, in file name order
+///
+/// Hidden files and subdirectories are skipped, as are empty files.
+pub fn load_fixtures(dir: &Path) -> Result<Vec<Fixture>> {
+    let entries = std::fs::read_dir(dir).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to read {}", dir.display()),
+    })?;
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    paths.sort();
+
+    let mut fixtures = Vec::with_capacity(paths.len());
+    for path in paths {
+        let fixture = Fixture::load(&path)?;
+        if !fixture.output.trim().is_empty() {
+            fixtures.push(fixture);
+        }
+    }
+    Ok(fixtures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Yinx;
+
+    fn bundled_fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/replay")
+    }
+
+    #[test]
+    fn test_fixture_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("02-whatweb.txt"),
+            "http://10.10.10.5 [200 OK]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("01-scan.txt"),
+            "$ nmap -sV 10.10.10.5\n22/tcp open ssh\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(".notes"), "skip me\n").unwrap();
+        std::fs::write(dir.path().join("03-empty.txt"), "\n").unwrap();
+
+        let fixtures = load_fixtures(dir.path()).unwrap();
+        let commands: Vec<_> = fixtures.iter().map(|f| f.command.as_str()).collect();
+        assert_eq!(commands, ["nmap -sV 10.10.10.5", "whatweb"]);
+        assert_eq!(fixtures[0].output, "22/tcp open ssh\n");
+
+        let event = fixtures[0].event("s1", 1_700_000_000);
+        assert_eq!(event.source.as_deref(), Some(REPLAY_SOURCE));
+        assert_eq!(event.cwd, dir.path().display().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_replay_bundled_fixtures() {
+        let fixtures = load_fixtures(&bundled_fixtures()).unwrap();
+        let tools: Vec<_> = fixtures
+            .iter()
+            .map(|f| f.command.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(tools, ["nmap", "gobuster", "hydra"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let yinx = Yinx::open(dir.path()).unwrap();
+        let session_id = yinx.start_session("replay").unwrap().id.to_string();
+        for (i, fixture) in fixtures.iter().enumerate() {
+            let event = fixture.event(&session_id, 1_700_000_000 + 60 * i as i64);
+            assert!(yinx.ingest(event).await.unwrap().is_some());
+        }
+
+        let hosts = yinx.hosts(Some(&session_id), None).unwrap();
+        assert!(hosts.contains(&"10.10.10.40".to_string()));
+        let captures = yinx
+            .storage()
+            .database
+            .get_session_captures(&session_id)
+            .unwrap();
+        let detected: Vec<_> = captures.iter().map(|c| c.tool.as_deref()).collect();
+        assert_eq!(detected, [Some("nmap"), Some("gobuster"), Some("hydra")]);
+    }
+}
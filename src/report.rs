@@ -0,0 +1,495 @@
+//! Engagement reports
+//!
+//! `yinx report` lays a session out the way a penetration test report
+//! reads: an executive summary, how the testing was done, the findings with
+//! the capture behind each one, what was learned per host, and an appendix
+//! with the activity log and evidence files. `[report]` in the config picks
+//! the sections and their order, the header, and the severity scale; the
+//! same [`Report`] is written as Markdown or serialized as JSON.
+
+use crate::activity::{summarize_activity, ActivitySummary};
+use crate::config::{ReportConfig, ReportSection};
+use crate::error::Result;
+use crate::session::Session;
+use crate::storage::custody::MANIFEST_FILE;
+use crate::storage::notes::{NoteNode, OTHER_FINDINGS};
+use crate::storage::StorageManager;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Output lines quoted under each finding
+const EVIDENCE_LINES: usize = 20;
+
+/// Severities from most to least serious
+const SEVERITY_ORDER: &[&str] = &["critical", "high", "medium", "low", "info"];
+
+/// A session assembled into report sections
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub title: String,
+    pub session_id: String,
+    pub session_name: String,
+    pub generated_at: i64,
+    pub client: Option<String>,
+    pub author: Option<String>,
+    pub logo: Option<String>,
+    pub classification: Option<String>,
+    /// Sections in the order they are rendered
+    pub sections: Vec<ReportSection>,
+    /// Only set for sections that are rendered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executive_summary: Option<ExecutiveSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub methodology: Option<Methodology>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub findings: Option<Vec<ReportFinding>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts: Option<Vec<NoteNode>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appendix: Option<Appendix>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutiveSummary {
+    pub hosts: usize,
+    pub captures: usize,
+    /// Findings per severity, most serious first; severities without findings are left out
+    pub findings: Vec<SeverityCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeverityCount {
+    pub severity: String,
+    /// Name under the configured severity scheme
+    pub label: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Methodology {
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub captures: usize,
+    /// Captures per tool, busiest first
+    pub tools: Vec<(String, usize)>,
+    /// Captures per user, busiest first; captures without one are left out
+    pub operators: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportFinding {
+    pub id: i64,
+    pub title: String,
+    pub severity: String,
+    pub label: String,
+    pub rule: Option<String>,
+    pub capture_id: Option<i64>,
+    /// User who ran the command behind the finding
+    pub captured_by: Option<String>,
+    /// Command and the start of its output
+    pub evidence: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Appendix {
+    pub activity: ActivitySummary,
+    /// Evidence files, listed with `--include-evidence`
+    pub attachments: Vec<ReportAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportAttachment {
+    pub filename: String,
+    /// Relative to the session's report directory
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+    pub capture_id: Option<i64>,
+    pub finding_id: Option<i64>,
+    pub is_image: bool,
+}
+
+/// Assemble the sections `config` asks for from a session's captures
+pub fn build_report(
+    storage: &StorageManager,
+    session: &Session,
+    config: &ReportConfig,
+    include_evidence: bool,
+) -> Result<Report> {
+    let session_id = session.id.to_string();
+    let wants = |section| config.sections.contains(&section);
+    let generated_at = chrono::Utc::now().timestamp();
+
+    let captures = storage.database.get_session_captures(&session_id)?;
+    let started_at = captures
+        .iter()
+        .map(|c| c.timestamp)
+        .min()
+        .unwrap_or(generated_at)
+        .min(session.started_at.timestamp());
+    let ended_at = captures
+        .iter()
+        .map(|c| c.timestamp)
+        .max()
+        .unwrap_or(started_at)
+        .max(session.stopped_at.map_or(started_at, |t| t.timestamp()));
+    let activity = summarize_activity(storage, Some(&session_id), started_at, ended_at)?;
+    let notes = storage.session_notes(&session_id, &session.name)?;
+
+    let mut findings = storage.database.list_findings(Some(&session_id))?;
+    findings.sort_by_key(|f| (severity_rank(&f.severity), f.id));
+
+    let executive_summary = wants(ReportSection::ExecutiveSummary).then(|| ExecutiveSummary {
+        hosts: notes
+            .children
+            .iter()
+            .filter(|node| node.title != OTHER_FINDINGS)
+            .count(),
+        captures: captures.len(),
+        findings: SEVERITY_ORDER
+            .iter()
+            .map(|severity| SeverityCount {
+                severity: severity.to_string(),
+                label: config.severity_label(severity),
+                count: findings.iter().filter(|f| f.severity == *severity).count(),
+            })
+            .filter(|count| count.count > 0)
+            .collect(),
+    });
+
+    let methodology = wants(ReportSection::Methodology).then(|| {
+        let mut operators: Vec<(String, usize)> = Vec::new();
+        for username in captures.iter().filter_map(|c| c.username.as_deref()) {
+            match operators.iter_mut().find(|(name, _)| name == username) {
+                Some((_, count)) => *count += 1,
+                None => operators.push((username.to_string(), 1)),
+            }
+        }
+        operators.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Methodology {
+            started_at,
+            ended_at,
+            captures: captures.len(),
+            tools: activity.tools.clone(),
+            operators,
+        }
+    });
+
+    let findings = if wants(ReportSection::Findings) {
+        let mut listed = Vec::with_capacity(findings.len());
+        for finding in &findings {
+            let capture = match finding.capture_id {
+                Some(id) => storage.database.get_capture(id)?,
+                None => None,
+            };
+            let evidence = capture
+                .as_ref()
+                .map(|capture| storage.capture_excerpt(capture, EVIDENCE_LINES));
+            listed.push(ReportFinding {
+                id: finding.id,
+                title: finding.title.clone(),
+                severity: finding.severity.clone(),
+                label: config.severity_label(&finding.severity),
+                rule: finding.rule.clone(),
+                capture_id: finding.capture_id,
+                captured_by: capture.and_then(|capture| capture.username),
+                evidence,
+            });
+        }
+        Some(listed)
+    } else {
+        None
+    };
+
+    let appendix = if wants(ReportSection::Appendix) {
+        let attachments = if include_evidence {
+            storage
+                .database
+                .list_attachments(&session_id)?
+                .iter()
+                .map(|a| ReportAttachment {
+                    filename: a.filename.clone(),
+                    path: a.report_path(),
+                    hash: a.hash.clone(),
+                    size: a.size,
+                    capture_id: a.capture_id,
+                    finding_id: a.finding_id,
+                    is_image: a.is_image(),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Some(Appendix {
+            activity,
+            attachments,
+        })
+    } else {
+        None
+    };
+
+    Ok(Report {
+        title: config
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Penetration Test Report: {}", session.name)),
+        session_id,
+        session_name: session.name.clone(),
+        generated_at,
+        client: config.client.clone(),
+        author: config.author.clone(),
+        logo: config.logo.clone(),
+        classification: config.classification.clone(),
+        sections: config.sections.clone(),
+        executive_summary,
+        methodology,
+        findings,
+        hosts: wants(ReportSection::HostDetails).then_some(notes.children),
+        appendix,
+    })
+}
+
+fn severity_rank(severity: &str) -> usize {
+    SEVERITY_ORDER
+        .iter()
+        .position(|s| *s == severity)
+        .unwrap_or(SEVERITY_ORDER.len())
+}
+
+fn date(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+impl Report {
+    /// Render the configured sections as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        if let Some(classification) = &self.classification {
+            let _ = writeln!(md, "**{}**\n", classification);
+        }
+        if let Some(logo) = &self.logo {
+            let _ = writeln!(md, "![logo]({})\n", logo);
+        }
+        let _ = writeln!(md, "# {}\n", self.title);
+        if let Some(client) = &self.client {
+            let _ = writeln!(md, "**Client:** {}  ", client);
+        }
+        if let Some(author) = &self.author {
+            let _ = writeln!(md, "**Author:** {}  ", author);
+        }
+        let _ = writeln!(md, "**Session:** {}  ", self.session_name);
+        let _ = writeln!(md, "**Date:** {}\n", date(self.generated_at));
+
+        for section in &self.sections {
+            let _ = writeln!(md, "## {}\n", section.title());
+            match section {
+                ReportSection::ExecutiveSummary => self.write_summary(&mut md),
+                ReportSection::Methodology => self.write_methodology(&mut md),
+                ReportSection::Findings => self.write_findings(&mut md),
+                ReportSection::HostDetails => self.write_hosts(&mut md),
+                ReportSection::Appendix => self.write_appendix(&mut md),
+            }
+        }
+
+        if let Some(classification) = &self.classification {
+            let _ = writeln!(md, "**{}**", classification);
+        }
+        md.trim_end().to_string() + "\n"
+    }
+
+    fn write_summary(&self, md: &mut String) {
+        let Some(summary) = &self.executive_summary else {
+            return;
+        };
+        let total: usize = summary.findings.iter().map(|f| f.count).sum();
+        let _ = writeln!(
+            md,
+            "Testing covered {} host(s) over {} captured command(s) and identified {} finding(s).\n",
+            summary.hosts, summary.captures, total
+        );
+        if total > 0 {
+            md.push_str("| Severity | Findings |\n|---|---|\n");
+            for count in &summary.findings {
+                let _ = writeln!(md, "| {} | {} |", count.label, count.count);
+            }
+            md.push('\n');
+        }
+    }
+
+    fn write_methodology(&self, md: &mut String) {
+        let Some(methodology) = &self.methodology else {
+            return;
+        };
+        let _ = writeln!(
+            md,
+            "Testing ran from {} to {}; {} command(s) were captured.\n",
+            date(methodology.started_at),
+            date(methodology.ended_at),
+            methodology.captures
+        );
+        if !methodology.tools.is_empty() {
+            md.push_str("| Tool | Commands |\n|---|---|\n");
+            for (tool, count) in &methodology.tools {
+                let _ = writeln!(md, "| {} | {} |", tool, count);
+            }
+            md.push('\n');
+        }
+        // Only worth a table when a shared box had several people on it
+        if methodology.operators.len() > 1 {
+            md.push_str("| Operator | Commands |\n|---|---|\n");
+            for (operator, count) in &methodology.operators {
+                let _ = writeln!(md, "| {} | {} |", operator, count);
+            }
+            md.push('\n');
+        }
+    }
+
+    fn write_findings(&self, md: &mut String) {
+        let Some(findings) = &self.findings else {
+            return;
+        };
+        if findings.is_empty() {
+            md.push_str("No findings were recorded.\n\n");
+        }
+        for finding in findings {
+            let _ = writeln!(md, "### [{}] {}\n", finding.label, finding.title);
+            if let Some(rule) = &finding.rule {
+                let _ = writeln!(md, "Detected by rule `{}`.\n", rule);
+            }
+            if let Some(user) = &finding.captured_by {
+                let _ = writeln!(md, "Captured by {}.\n", user);
+            }
+            if let Some(evidence) = &finding.evidence {
+                let _ = writeln!(md, "```\n{}\n```\n", evidence.trim_end());
+            }
+        }
+    }
+
+    fn write_hosts(&self, md: &mut String) {
+        let Some(hosts) = &self.hosts else {
+            return;
+        };
+        if hosts.is_empty() {
+            md.push_str("No hosts were identified.\n\n");
+        }
+        for host in hosts {
+            write_note(md, host, 3);
+        }
+    }
+
+    fn write_appendix(&self, md: &mut String) {
+        let Some(appendix) = &self.appendix else {
+            return;
+        };
+        // The activity log's own headings sit one level below the appendix
+        for line in appendix.activity.to_markdown().lines() {
+            if line.starts_with('#') {
+                md.push('#');
+            }
+            md.push_str(line);
+            md.push('\n');
+        }
+        md.push('\n');
+        if !appendix.attachments.is_empty() {
+            let _ = writeln!(
+                md,
+                "### Evidence\n\nHashes and provenance of these files: [{0}]({0})\n",
+                MANIFEST_FILE
+            );
+            for attachment in &appendix.attachments {
+                if attachment.is_image {
+                    let _ = writeln!(md, "![{}]({})\n", attachment.filename, attachment.path);
+                } else {
+                    let _ = writeln!(md, "- [{}]({})", attachment.filename, attachment.path);
+                }
+            }
+            md.push('\n');
+        }
+    }
+}
+
+/// A note and the notes under it, as headings from `level` down
+fn write_note(md: &mut String, note: &NoteNode, level: usize) {
+    let _ = writeln!(md, "{} {}\n", "#".repeat(level.min(6)), note.title);
+    if !note.text.is_empty() {
+        let _ = writeln!(md, "{}\n", note.text);
+    }
+    for evidence in &note.evidence {
+        let _ = writeln!(md, "```\n{}\n```\n", evidence.trim_end());
+    }
+    for child in &note.children {
+        write_note(md, child, level + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SeverityScheme;
+    use crate::daemon::CaptureEvent;
+    use crate::Yinx;
+
+    #[tokio::test]
+    async fn test_report_follows_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let yinx = Yinx::open(dir.path()).unwrap();
+        let session = yinx.start_session("acme-external").unwrap();
+        let session_id = session.id.to_string();
+        let capture_id = yinx
+            .ingest(CaptureEvent {
+                session_id: session_id.clone(),
+                timestamp: 1_700_000_000,
+                command: "nmap -sV 10.10.10.3".to_string(),
+                output: "Nmap scan report for 10.10.10.3\n21/tcp open ftp vsftpd 2.3.4\n"
+                    .to_string(),
+                exit_code: 0,
+                cwd: "/tmp".to_string(),
+                username: Some("alice".to_string()),
+                source: None,
+                tool: None,
+                context: None,
+            })
+            .await
+            .unwrap();
+        let database = &yinx.storage().database;
+        database
+            .insert_finding(
+                &session_id,
+                capture_id,
+                "vsftpd 2.3.4 backdoor",
+                "critical",
+                None,
+            )
+            .unwrap();
+        database
+            .insert_finding(&session_id, None, "Anonymous FTP", "low", None)
+            .unwrap();
+
+        let config = ReportConfig {
+            sections: vec![ReportSection::Findings, ReportSection::ExecutiveSummary],
+            classification: Some("CONFIDENTIAL".to_string()),
+            severity_scheme: SeverityScheme::Cvss,
+            severity_labels: [("low".to_string(), "Minor".to_string())].into(),
+            ..ReportConfig::default()
+        };
+        let report = yinx.report("acme-external", &config, false).unwrap();
+        assert!(report.methodology.is_none() && report.hosts.is_none());
+
+        let findings = report.findings.as_ref().unwrap();
+        assert_eq!(findings[0].label, "Critical (9.0-10.0)");
+        assert!(findings[0].evidence.as_ref().unwrap().contains("vsftpd"));
+        assert_eq!(findings[0].captured_by.as_deref(), Some("alice"));
+        assert_eq!(findings[1].label, "Minor");
+
+        let md = report.to_markdown();
+        assert!(md.starts_with("**CONFIDENTIAL**"));
+        assert!(md.trim_end().ends_with("**CONFIDENTIAL**"));
+        let findings_at = md.find("## Findings").unwrap();
+        assert!(findings_at < md.find("## Executive Summary").unwrap());
+        assert!(!md.contains("## Methodology"));
+        assert!(md.contains("### [Critical (9.0-10.0)] vsftpd 2.3.4 backdoor"));
+        assert!(md.contains("Captured by alice."));
+    }
+}
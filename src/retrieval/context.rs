@@ -0,0 +1,330 @@
+//! Token-budgeted context assembly for LLM prompts
+//!
+//! Retrieved chunks are packed greedily into a token budget. Each pick goes
+//! to the highest (reranked) score, discounted for every chunk already taken
+//! from the same capture so one noisy scan can't crowd out everything else.
+//! A chunk that doesn't fit is cut back to whole lines instead of being
+//! dropped or sliced mid-line.
+
+use crate::config::LlmConfig;
+use crate::retrieval::ScoredChunk;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use thiserror::Error;
+
+/// Chunks cut down to fewer tokens than this aren't worth including
+const MIN_CHUNK_TOKENS: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum ContextError {
+    #[error("Failed to load tokenizer: {0}")]
+    Tokenizer(String),
+}
+
+/// Counts tokens the way the target model will
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Character-based estimate for models without a local tokenizer
+#[derive(Debug, Clone, Copy)]
+pub struct CharEstimate {
+    pub chars_per_token: f32,
+}
+
+impl CharEstimate {
+    /// Typical ratio for a model family; English and tool output average
+    /// around four characters per token for most BPE vocabularies
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        let chars_per_token = if model.contains("claude") { 3.5 } else { 4.0 };
+        Self { chars_per_token }
+    }
+}
+
+impl TokenCounter for CharEstimate {
+    fn count(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.chars_per_token).ceil() as usize
+    }
+}
+
+/// Exact counts from a Hugging Face `tokenizer.json`
+pub struct TokenizerFile(tokenizers::Tokenizer);
+
+impl TokenizerFile {
+    pub fn from_file(path: &Path) -> Result<Self, ContextError> {
+        tokenizers::Tokenizer::from_file(path)
+            .map(Self)
+            .map_err(|e| ContextError::Tokenizer(format!("{}: {}", path.display(), e)))
+    }
+}
+
+impl TokenCounter for TokenizerFile {
+    fn count(&self, text: &str) -> usize {
+        match self.0.encode(text, false) {
+            Ok(encoding) => encoding.len(),
+            Err(_) => CharEstimate::for_model("").count(text),
+        }
+    }
+}
+
+/// Token counter for the configured model: its tokenizer file when one is
+/// set, an estimate otherwise
+pub fn token_counter(config: &LlmConfig) -> Result<Box<dyn TokenCounter>, ContextError> {
+    match &config.tokenizer_file {
+        Some(path) => Ok(Box::new(TokenizerFile::from_file(path)?)),
+        None => Ok(Box::new(CharEstimate::for_model(&config.model))),
+    }
+}
+
+/// A chunk as it was placed in the context
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedChunk {
+    pub chunk_id: i64,
+    pub capture_id: i64,
+    /// Header and text as they appear in the prompt
+    pub text: String,
+    pub tokens: usize,
+    /// Lines left out to fit the budget
+    pub omitted_lines: usize,
+}
+
+/// Chunks packed into a token budget
+#[derive(Debug, Clone, Default)]
+pub struct PackedContext {
+    pub chunks: Vec<PackedChunk>,
+    pub tokens: usize,
+    pub budget: usize,
+    /// Candidates left out: over budget or repeating text already included
+    pub skipped: Vec<i64>,
+}
+
+impl PackedContext {
+    /// Prompt text, one block per chunk
+    pub fn render(&self) -> String {
+        self.chunks
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Packs retrieved chunks into a token budget
+pub struct ContextBuilder {
+    counter: Box<dyn TokenCounter>,
+    budget: usize,
+    max_chunk_tokens: usize,
+    diversity: f32,
+}
+
+impl ContextBuilder {
+    /// Create a builder for `budget` tokens
+    pub fn new(counter: Box<dyn TokenCounter>, budget: usize) -> Self {
+        Self {
+            counter,
+            budget,
+            max_chunk_tokens: budget / 4,
+            diversity: 0.5,
+        }
+    }
+
+    /// Builder for the configured model and `llm.context_tokens`
+    pub fn from_config(config: &LlmConfig) -> Result<Self, ContextError> {
+        Ok(Self::new(token_counter(config)?, config.context_tokens))
+    }
+
+    /// Cap on the tokens a single chunk may take (default: a quarter of the budget)
+    pub fn with_max_chunk_tokens(mut self, max_chunk_tokens: usize) -> Self {
+        self.max_chunk_tokens = max_chunk_tokens.max(MIN_CHUNK_TOKENS);
+        self
+    }
+
+    /// Score discount per chunk already taken from the same capture,
+    /// from 0.0 (rank by score alone) to 1.0 (one chunk per capture first)
+    pub fn with_diversity(mut self, diversity: f32) -> Self {
+        self.diversity = diversity.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Pack `candidates` into the budget
+    pub fn build(&self, candidates: &[ScoredChunk]) -> PackedContext {
+        let mut packed = PackedContext {
+            budget: self.budget,
+            ..PackedContext::default()
+        };
+        let mut remaining: Vec<&ScoredChunk> = candidates.iter().collect();
+        let mut per_capture: HashMap<i64, i32> = HashMap::new();
+        let mut seen_text: HashSet<&str> = HashSet::new();
+
+        while !remaining.is_empty() {
+            let (index, _) = remaining
+                .iter()
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let taken = per_capture
+                        .get(&chunk.provenance.capture_id)
+                        .copied()
+                        .unwrap_or(0);
+                    (i, chunk.score * (1.0 - self.diversity).powi(taken))
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .expect("remaining is not empty");
+            let chunk = remaining.remove(index);
+
+            let left = self.budget.saturating_sub(packed.tokens);
+            if !seen_text.insert(chunk.text.trim()) || left < MIN_CHUNK_TOKENS {
+                packed.skipped.push(chunk.chunk_id);
+                continue;
+            }
+            match self.fit(chunk, left.min(self.max_chunk_tokens)) {
+                Some(fitted) => {
+                    packed.tokens += fitted.tokens;
+                    *per_capture.entry(chunk.provenance.capture_id).or_default() += 1;
+                    packed.chunks.push(fitted);
+                }
+                None => packed.skipped.push(chunk.chunk_id),
+            }
+        }
+
+        packed
+    }
+
+    /// Render a chunk within `limit` tokens, dropping trailing lines if needed
+    fn fit(&self, chunk: &ScoredChunk, limit: usize) -> Option<PackedChunk> {
+        let header = format!(
+            "[capture {} · {} · {}]",
+            chunk.provenance.capture_id, chunk.provenance.tool, chunk.provenance.command
+        );
+        let lines: Vec<&str> = chunk.text.lines().collect();
+        let render = |keep: usize| {
+            let mut text = format!("{}\n{}", header, lines[..keep].join("\n"));
+            if keep < lines.len() {
+                text.push_str(&format!("\n… [{} more lines]", lines.len() - keep));
+            }
+            text
+        };
+
+        let full = render(lines.len());
+        let tokens = self.counter.count(&full);
+        if tokens <= limit {
+            return Some(PackedChunk {
+                chunk_id: chunk.chunk_id,
+                capture_id: chunk.provenance.capture_id,
+                text: full,
+                tokens,
+                omitted_lines: 0,
+            });
+        }
+
+        // Most lines that still fit; token counts grow with the line count
+        let (mut low, mut high) = (0, lines.len());
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.counter.count(&render(mid)) <= limit {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        if low == 0 {
+            return None;
+        }
+        let text = render(low);
+        let tokens = self.counter.count(&text);
+        (tokens >= MIN_CHUNK_TOKENS).then(|| PackedChunk {
+            chunk_id: chunk.chunk_id,
+            capture_id: chunk.provenance.capture_id,
+            text,
+            tokens,
+            omitted_lines: lines.len() - low,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retrieval::{ChunkMetadata, Provenance};
+    use chrono::Utc;
+
+    /// One token per word keeps the arithmetic readable
+    struct Words;
+
+    impl TokenCounter for Words {
+        fn count(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+    }
+
+    fn chunk(chunk_id: i64, capture_id: i64, score: f32, text: &str) -> ScoredChunk {
+        ScoredChunk::new(
+            chunk_id,
+            text.to_string(),
+            score,
+            ChunkMetadata {
+                cluster_size: 1,
+                pattern: String::new(),
+                scores: serde_json::json!({}),
+                entities: vec![],
+                sections: vec![],
+                host: None,
+            },
+            Provenance {
+                capture_id,
+                blob_hash: "h".to_string(),
+                command: "cmd".to_string(),
+                timestamp: Utc::now(),
+                tool: "nmap".to_string(),
+                username: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_packs_within_budget() {
+        let long: String = (0..40)
+            .map(|i| format!("line {} of the scan", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let candidates = vec![
+            chunk(1, 10, 0.9, &long),
+            chunk(
+                2,
+                10,
+                0.8,
+                "second chunk from the same capture with some words in it",
+            ),
+            chunk(
+                3,
+                20,
+                0.7,
+                "another capture entirely with enough words to count",
+            ),
+            chunk(
+                4,
+                30,
+                0.6,
+                "another capture entirely with enough words to count",
+            ),
+        ];
+
+        let context = ContextBuilder::new(Box::new(Words), 100)
+            .with_max_chunk_tokens(60)
+            .build(&candidates);
+        assert!(context.tokens <= 100);
+
+        // The long chunk is cut to whole lines
+        let first = &context.chunks[0];
+        assert_eq!(first.chunk_id, 1);
+        assert!(first.omitted_lines > 0);
+        assert!(first.tokens <= 60);
+        assert!(first.text.ends_with("more lines]"));
+
+        // Diversity puts the other capture ahead of the second chunk of capture 10
+        assert_eq!(context.chunks[1].chunk_id, 3);
+        // Identical text is only included once
+        assert!(context.skipped.contains(&4));
+        assert!(context.render().contains("[capture 20 · nmap · cmd]"));
+    }
+}
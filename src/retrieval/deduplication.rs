@@ -33,6 +33,7 @@ mod tests {
             command: "test".to_string(),
             timestamp: Utc::now(),
             tool: "test".to_string(),
+            username: None,
         };
 
         let meta = ChunkMetadata {
@@ -40,6 +41,8 @@ mod tests {
             pattern: "test".to_string(),
             scores: serde_json::json!({}),
             entities: vec![],
+            sections: vec![],
+            host: None,
         };
 
         let chunks = vec![
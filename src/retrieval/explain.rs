@@ -0,0 +1,247 @@
+//! Score breakdowns for explaining why a result was returned
+
+use crate::config::FusionStrategy;
+use crate::retrieval::fusion::{channel_contributions, combine};
+use crate::retrieval::FusionConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Where a result stood in one search channel
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChannelHit {
+    /// 1-based rank within the channel
+    pub rank: usize,
+    /// Score reported by the channel (cosine similarity or BM25)
+    pub score: f32,
+    /// What the hit added to the fused score: weight / (k + rank) under RRF,
+    /// the weighted normalized score otherwise
+    pub contribution: f32,
+}
+
+/// How the cross-encoder moved a result
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RerankDelta {
+    pub rank_before: usize,
+    pub rank_after: usize,
+    pub score_before: f32,
+    pub score_after: f32,
+}
+
+impl RerankDelta {
+    /// Positions gained (negative when the result moved down)
+    pub fn rank_change(&self) -> i64 {
+        self.rank_before as i64 - self.rank_after as i64
+    }
+}
+
+/// Why a result ended up where it did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Explanation {
+    /// Vector channel hit, None when the chunk wasn't among its results
+    pub semantic: Option<ChannelHit>,
+    /// Keyword channel hit, None when the chunk wasn't among its results
+    pub keyword: Option<ChannelHit>,
+    /// Fused score: the sum of the channel contributions, or the larger of
+    /// them under `max_score`
+    pub fused_score: f32,
+    /// 1-based rank after fusion
+    pub fused_rank: usize,
+    /// Reranker movement, None when reranking didn't run
+    pub rerank: Option<RerankDelta>,
+}
+
+/// Why a candidate was left out of the results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// Indexed, but the chunk is no longer stored
+    Missing,
+    Session,
+    Tool,
+    /// Command wasn't run against the requested host
+    Target,
+    /// Evidence isn't about the requested host
+    Host,
+    /// No address the evidence is about is in the requested network
+    Network,
+    /// Captured under a different environment
+    Context,
+    Section,
+    User,
+    /// Captured after the `--as-of` time
+    AsOf,
+    /// Score under `min_similarity_threshold`
+    BelowThreshold,
+    /// Past the reranker's candidate limit or its top-k
+    RerankCutoff,
+    /// Past the result limit
+    Limit,
+    /// Another result already carries the chunk
+    Duplicate,
+}
+
+impl DropReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::Missing => "chunk no longer stored",
+            DropReason::Session => "session filter",
+            DropReason::Tool => "tool filter",
+            DropReason::Target => "target filter",
+            DropReason::Host => "host filter",
+            DropReason::Network => "network filter",
+            DropReason::Context => "context filter",
+            DropReason::Section => "section filter",
+            DropReason::User => "user filter",
+            DropReason::AsOf => "captured after --as-of",
+            DropReason::BelowThreshold => "below similarity threshold",
+            DropReason::RerankCutoff => "cut by reranker",
+            DropReason::Limit => "over result limit",
+            DropReason::Duplicate => "duplicate chunk",
+        }
+    }
+}
+
+/// A candidate that didn't make it into the results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dropped {
+    pub chunk_id: i64,
+    pub reason: DropReason,
+}
+
+/// Break down `fuse` for every chunk in either result list
+///
+/// Mirrors the configured strategy exactly, so `fused_score` matches what
+/// the fused ranking was sorted by. Disabled channels are left out.
+pub fn explain_fusion(
+    semantic_results: &[(i64, f32)],
+    keyword_results: &[(i64, f32)],
+    config: &FusionConfig,
+) -> HashMap<i64, Explanation> {
+    let mut explanations: HashMap<i64, Explanation> = HashMap::new();
+    record_channel(
+        &mut explanations,
+        semantic_results,
+        config.semantic_weight,
+        config.semantic_k,
+        config.strategy,
+        |e| &mut e.semantic,
+    );
+    record_channel(
+        &mut explanations,
+        keyword_results,
+        config.keyword_weight,
+        config.keyword_k,
+        config.strategy,
+        |e| &mut e.keyword,
+    );
+
+    for explanation in explanations.values_mut() {
+        let contributions = [explanation.semantic, explanation.keyword];
+        explanation.fused_score = contributions
+            .iter()
+            .flatten()
+            .map(|hit| hit.contribution)
+            .reduce(|a, b| combine(config.strategy, a, b))
+            .unwrap_or(0.0);
+    }
+
+    let mut order: Vec<(i64, f32)> = explanations
+        .iter()
+        .map(|(id, e)| (*id, e.fused_score))
+        .collect();
+    order.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.0.cmp(&b.0))
+    });
+    for (rank, (chunk_id, _)) in order.into_iter().enumerate() {
+        if let Some(explanation) = explanations.get_mut(&chunk_id) {
+            explanation.fused_rank = rank + 1;
+        }
+    }
+
+    explanations
+}
+
+/// Add one channel's hits to the explanations
+///
+/// A chunk listed twice by the channel keeps its best rank, but every
+/// listing is combined into the contribution just as it is in the fusion.
+fn record_channel(
+    explanations: &mut HashMap<i64, Explanation>,
+    results: &[(i64, f32)],
+    weight: f32,
+    k: f32,
+    strategy: FusionStrategy,
+    slot: fn(&mut Explanation) -> &mut Option<ChannelHit>,
+) {
+    if weight <= 0.0 {
+        return;
+    }
+    let contributions = channel_contributions(results, weight, k, strategy);
+    for (rank, ((chunk_id, score), contribution)) in results.iter().zip(contributions).enumerate() {
+        let entry = explanations.entry(*chunk_id).or_insert(Explanation {
+            semantic: None,
+            keyword: None,
+            fused_score: 0.0,
+            fused_rank: 0,
+            rerank: None,
+        });
+        match slot(entry) {
+            Some(existing) => {
+                existing.contribution = combine(strategy, existing.contribution, contribution)
+            }
+            hit @ None => {
+                *hit = Some(ChannelHit {
+                    rank: rank + 1,
+                    score: *score,
+                    contribution,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_fusion() {
+        let semantic = vec![(1, 0.9), (2, 0.8)];
+        let keyword = vec![(2, 7.5), (3, 4.0)];
+        let config = FusionConfig::new(60.0, 0.7, 0.3).unwrap();
+
+        let explained = explain_fusion(&semantic, &keyword, &config);
+        assert_eq!(explained.len(), 3);
+
+        let both = &explained[&2];
+        assert_eq!(both.semantic.unwrap().rank, 2);
+        assert_eq!(both.keyword.unwrap().rank, 1);
+        assert!((both.keyword.unwrap().contribution - 0.3 / 61.0).abs() < 1e-6);
+        assert!((both.fused_score - (0.7 / 62.0 + 0.3 / 61.0)).abs() < 1e-6);
+
+        assert!(explained[&3].semantic.is_none());
+
+        // Ranks agree with the fusion the search actually uses
+        let fused =
+            crate::retrieval::reciprocal_rank_fusion(semantic.clone(), keyword.clone(), &config);
+        for (rank, (chunk_id, score)) in fused.iter().enumerate() {
+            assert_eq!(explained[chunk_id].fused_rank, rank + 1);
+            assert!((explained[chunk_id].fused_score - score).abs() < 1e-6);
+        }
+
+        let config = FusionConfig {
+            strategy: FusionStrategy::MaxScore,
+            ..config
+        };
+        let explained = explain_fusion(&semantic, &keyword, &config);
+        let fused = crate::retrieval::fuse(semantic, keyword, &config);
+        for (chunk_id, score) in &fused {
+            assert!((explained[chunk_id].fused_score - score).abs() < 1e-6);
+        }
+        // Normalized: chunk 1 tops the vector channel, chunk 2 the keyword one
+        assert_eq!(explained[&1].fused_score, 0.7);
+        assert_eq!(explained[&2].fused_score, 0.3);
+    }
+}
@@ -1,5 +1,10 @@
-//! Reciprocal Rank Fusion algorithm for combining search results
+//! Fusion strategies for combining search results
+//!
+//! Reciprocal Rank Fusion only looks at ranks. The score-based strategies
+//! min-max normalize each channel first, since cosine similarity and BM25
+//! live on different scales.
 
+use crate::config::{FusionStrategy, RetrievalConfig};
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -15,30 +20,145 @@ pub enum FusionError {
 /// Configuration for fusion algorithm
 #[derive(Debug, Clone)]
 pub struct FusionConfig {
-    /// RRF K constant (typically 60)
-    pub rrf_k: f32,
+    /// How the channels are combined
+    pub strategy: FusionStrategy,
 
-    /// Weight for semantic results
+    /// RRF K constant for semantic results (typically 60)
+    pub semantic_k: f32,
+
+    /// RRF K constant for keyword results (typically 60)
+    pub keyword_k: f32,
+
+    /// Weight for semantic results (zero when the channel is disabled)
     pub semantic_weight: f32,
 
-    /// Weight for keyword results
+    /// Weight for keyword results (zero when the channel is disabled)
     pub keyword_weight: f32,
 }
 
 impl FusionConfig {
+    /// RRF over both channels with one K constant
     pub fn new(rrf_k: f32, semantic_weight: f32, keyword_weight: f32) -> Result<Self, FusionError> {
         if semantic_weight <= 0.0 || keyword_weight <= 0.0 {
             return Err(FusionError::InvalidWeights);
         }
 
         Ok(Self {
-            rrf_k,
+            strategy: FusionStrategy::Rrf,
+            semantic_k: rrf_k,
+            keyword_k: rrf_k,
+            semantic_weight,
+            keyword_weight,
+        })
+    }
+
+    /// Build from the retrieval settings; a disabled channel gets no weight
+    pub fn from_config(config: &RetrievalConfig) -> Result<Self, FusionError> {
+        let semantic_weight = if config.mode.uses_vector() {
+            config.semantic_weight
+        } else {
+            0.0
+        };
+        let keyword_weight = if config.mode.uses_keyword() {
+            config.keyword_weight
+        } else {
+            0.0
+        };
+        let enabled_positive = |enabled: bool, weight: f32| !enabled || weight > 0.0;
+        if !enabled_positive(config.mode.uses_vector(), semantic_weight)
+            || !enabled_positive(config.mode.uses_keyword(), keyword_weight)
+        {
+            return Err(FusionError::InvalidWeights);
+        }
+
+        Ok(Self {
+            strategy: config.fusion_strategy,
+            semantic_k: config.semantic_rrf_k.unwrap_or(config.rrf_k),
+            keyword_k: config.keyword_rrf_k.unwrap_or(config.rrf_k),
             semantic_weight,
             keyword_weight,
         })
     }
 }
 
+/// What each listing in one channel's results adds to the fused score
+///
+/// Returned in the channel's order, one entry per input pair.
+pub(crate) fn channel_contributions(
+    results: &[(i64, f32)],
+    weight: f32,
+    k: f32,
+    strategy: FusionStrategy,
+) -> Vec<f32> {
+    match strategy {
+        FusionStrategy::Rrf => (0..results.len())
+            .map(|rank| weight / (k + (rank as f32) + 1.0))
+            .collect(),
+        FusionStrategy::WeightedLinear | FusionStrategy::MaxScore => {
+            let min = results.iter().map(|r| r.1).fold(f32::INFINITY, f32::min);
+            let max = results
+                .iter()
+                .map(|r| r.1)
+                .fold(f32::NEG_INFINITY, f32::max);
+            results
+                .iter()
+                .map(|(_, score)| {
+                    let normalized = if max > min {
+                        (score - min) / (max - min)
+                    } else {
+                        1.0
+                    };
+                    weight * normalized
+                })
+                .collect()
+        }
+    }
+}
+
+/// Combine two channel contributions under `strategy`
+pub(crate) fn combine(strategy: FusionStrategy, a: f32, b: f32) -> f32 {
+    match strategy {
+        FusionStrategy::Rrf | FusionStrategy::WeightedLinear => a + b,
+        FusionStrategy::MaxScore => a.max(b),
+    }
+}
+
+/// Fuse two ranked lists with the configured strategy
+///
+/// A channel with zero weight is ignored entirely, so a disabled channel's
+/// results can't sneak in with a zero score.
+///
+/// # Returns
+/// Fused results as (id, fused_score) pairs, sorted by score descending
+pub fn fuse(
+    semantic_results: Vec<(i64, f32)>,
+    keyword_results: Vec<(i64, f32)>,
+    config: &FusionConfig,
+) -> Vec<(i64, f32)> {
+    let mut scores: HashMap<i64, f32> = HashMap::new();
+    let channels = [
+        (&semantic_results, config.semantic_weight, config.semantic_k),
+        (&keyword_results, config.keyword_weight, config.keyword_k),
+    ];
+    for (results, weight, k) in channels {
+        if weight <= 0.0 {
+            continue;
+        }
+        let contributions = channel_contributions(results, weight, k, config.strategy);
+        for ((chunk_id, _), contribution) in results.iter().zip(contributions) {
+            scores
+                .entry(*chunk_id)
+                .and_modify(|score| *score = combine(config.strategy, *score, contribution))
+                .or_insert(contribution);
+        }
+    }
+
+    let mut results: Vec<(i64, f32)> = scores.into_iter().collect();
+    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    results
+}
+
 /// Apply Reciprocal Rank Fusion to combine two ranked lists
 ///
 /// RRF formula: score(id) = sum over all rankings of: weight / (k + rank)
@@ -59,13 +179,13 @@ pub fn reciprocal_rank_fusion(
 
     // Process semantic results
     for (rank, (chunk_id, _original_score)) in semantic_results.iter().enumerate() {
-        let rrf_score = config.semantic_weight / (config.rrf_k + (rank as f32) + 1.0);
+        let rrf_score = config.semantic_weight / (config.semantic_k + (rank as f32) + 1.0);
         *scores.entry(*chunk_id).or_insert(0.0) += rrf_score;
     }
 
     // Process keyword results
     for (rank, (chunk_id, _original_score)) in keyword_results.iter().enumerate() {
-        let rrf_score = config.keyword_weight / (config.rrf_k + (rank as f32) + 1.0);
+        let rrf_score = config.keyword_weight / (config.keyword_k + (rank as f32) + 1.0);
         *scores.entry(*chunk_id).or_insert(0.0) += rrf_score;
     }
 
@@ -106,4 +226,46 @@ mod tests {
 
         assert_eq!(fused[0].0, 1); // Semantic result should win
     }
+
+    #[test]
+    fn test_fusion_strategies() {
+        let semantic = vec![(1, 0.9), (2, 0.5)];
+        let keyword = vec![(2, 12.0), (3, 2.0)];
+        let mut config = FusionConfig::new(60.0, 0.5, 0.5).unwrap();
+
+        // Matches plain RRF
+        let rrf = fuse(semantic.clone(), keyword.clone(), &config);
+        assert_eq!(rrf[0].0, 2);
+
+        // Normalized scores: 1 -> 0.5, 2 -> 0 + 0.5, 3 -> 0
+        config.strategy = FusionStrategy::WeightedLinear;
+        let linear: HashMap<i64, f32> = fuse(semantic.clone(), keyword.clone(), &config)
+            .into_iter()
+            .collect();
+        assert_eq!(linear[&1], 0.5);
+        assert_eq!(linear[&2], 0.5);
+        assert_eq!(linear[&3], 0.0);
+
+        config.strategy = FusionStrategy::MaxScore;
+        config.keyword_weight = 1.0;
+        let max = fuse(semantic.clone(), keyword.clone(), &config);
+        assert_eq!(max[0], (2, 1.0));
+    }
+
+    #[test]
+    fn test_disabled_channel() {
+        let mut retrieval = RetrievalConfig {
+            mode: crate::config::RetrievalMode::Keyword,
+            ..RetrievalConfig::default()
+        };
+        let config = FusionConfig::from_config(&retrieval).unwrap();
+        assert_eq!(config.semantic_weight, 0.0);
+
+        let fused = fuse(vec![(1, 0.9)], vec![(2, 3.0)], &config);
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].0, 2);
+
+        retrieval.keyword_weight = 0.0;
+        assert!(FusionConfig::from_config(&retrieval).is_err());
+    }
 }
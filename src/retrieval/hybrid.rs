@@ -1,12 +1,25 @@
 //! Hybrid search combining semantic and keyword search
+//!
+//! An index that comes up empty while the database has chunks is rebuilt
+//! in the background; until the keyword index is back, its channel is
+//! served from SQLite's full-text index over the chunks. One that is only
+//! behind catches up in the background from its watermark: the keyword
+//! index's is kept in SQLite, the vector index's in memory along with it.
 
 use crate::config::RetrievalConfig;
 use crate::embedding::{EmbeddingProvider, KeywordIndex, VectorIndex};
+use crate::error::YinxError;
 use crate::retrieval::{
-    deduplicate_chunks, reciprocal_rank_fusion, ChunkMetadata, FusionConfig, Provenance, Reranker,
-    ScoredChunk, SearchQuery,
+    deduplicate_chunks, explain_fusion, fuse, index_is_behind, is_cold, rebuild_from_chunks,
+    sync_keyword_index, ChunkMetadata, DropReason, Dropped, FusionConfig, IndexRebuild, Provenance,
+    RebuildProgress, RerankDelta, RerankOptions, Reranker, ScoredChunk, SearchQuery, KEYWORD_INDEX,
+    REBUILD_BATCH,
 };
-use crate::storage::Database;
+use crate::storage::{
+    latency, ArchiveStats, Database, PurgeStats, ScrubPattern, ScrubReport, StorageManager,
+};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::RwLock;
@@ -32,6 +45,19 @@ pub enum SearchError {
     InvalidQuery(String),
 }
 
+/// Results of a search along with the candidates that were left out
+#[derive(Debug, Clone, Default)]
+pub struct ExplainedResults {
+    /// Results in final order, each with its `explanation` filled in
+    pub results: Vec<ScoredChunk>,
+    /// Fused candidates that didn't make it, in the order they were dropped
+    pub dropped: Vec<Dropped>,
+    /// How far the index rebuild has got, while one is running
+    pub rebuild: Option<RebuildProgress>,
+    /// HNSW ef_search the vector channel ran with, if it was searched
+    pub ef_search: Option<usize>,
+}
+
 /// Hybrid searcher combining semantic and keyword search
 pub struct HybridSearcher {
     embedding_provider: Arc<dyn EmbeddingProvider>,
@@ -40,6 +66,9 @@ pub struct HybridSearcher {
     database: Arc<Database>,
     reranker: Option<Arc<Reranker>>,
     config: RetrievalConfig,
+    rebuild: Arc<IndexRebuild>,
+    /// Highest chunk id in the vector index, which isn't persisted
+    vector_watermark: Arc<AtomicI64>,
 }
 
 impl HybridSearcher {
@@ -53,8 +82,9 @@ impl HybridSearcher {
     ) -> Result<Self, SearchError> {
         // Initialize reranker if enabled
         let reranker = if config.enable_reranking {
-            let r = Reranker::new(&config.reranker_model)
-                .map_err(|e| SearchError::RerankingError(e.to_string()))?;
+            let r =
+                Reranker::with_options(&config.reranker_model, RerankOptions::from_config(&config))
+                    .map_err(|e| SearchError::RerankingError(e.to_string()))?;
             Some(Arc::new(r))
         } else {
             None
@@ -67,70 +97,422 @@ impl HybridSearcher {
             database,
             reranker,
             config,
+            rebuild: Arc::new(IndexRebuild::new()),
+            vector_watermark: Arc::new(AtomicI64::new(0)),
         })
     }
 
+    /// Progress of the background index rebuild, if one is running
+    pub fn rebuild_progress(&self) -> Option<RebuildProgress> {
+        self.rebuild.is_running().then(|| self.rebuild.progress())
+    }
+
+    /// Start rebuilding whichever searched index is empty while the
+    /// database has chunks, unless a rebuild is already running
+    async fn warm_up(&self) -> Result<(), SearchError> {
+        if self.rebuild.is_running() {
+            return Ok(());
+        }
+        let mode = self.config.mode;
+        let cold = |indexed| {
+            is_cold(indexed, &self.database).map_err(|e| SearchError::DatabaseError(e.to_string()))
+        };
+        let keywords = mode.uses_keyword() && cold(self.keyword_index.read().await.len())?;
+        let vectors = mode.uses_vector() && cold(self.vector_index.read().await.len())?;
+        if !(keywords || vectors) {
+            return self.catch_up();
+        }
+        if !self.rebuild.start() {
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Rebuilding {} from the database in the background",
+            match (keywords, vectors) {
+                (true, true) => "keyword and vector indexes",
+                (true, false) => "keyword index",
+                _ => "vector index",
+            }
+        );
+        let database = self.database.clone();
+        let rebuild = self.rebuild.clone();
+        let keyword_index = self.keyword_index.clone();
+        let vector_index = self.vector_index.clone();
+        let provider = self.embedding_provider.clone();
+        let vector_watermark = self.vector_watermark.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut last = 0;
+            let result = rebuild_from_chunks(&database, &rebuild, |items| {
+                last = items.last().map_or(last, |(id, _, _)| *id as i64);
+                if keywords {
+                    keyword_index
+                        .blocking_write()
+                        .insert_hosted_batch(items)
+                        .map_err(|e| YinxError::Other(e.into()))?;
+                }
+                if vectors {
+                    let texts: Vec<String> =
+                        items.iter().map(|(_, text, _)| text.clone()).collect();
+                    let embeddings = provider
+                        .embed_batch(&texts)
+                        .map_err(|e| YinxError::Other(e.into()))?;
+                    let items: Vec<(u64, Vec<f32>)> =
+                        items.iter().map(|(id, _, _)| *id).zip(embeddings).collect();
+                    vector_index
+                        .blocking_read()
+                        .insert_batch(&items)
+                        .map_err(|e| YinxError::Other(e.into()))?;
+                }
+                Ok(())
+            })
+            .and_then(|indexed| {
+                if keywords {
+                    keyword_index
+                        .blocking_write()
+                        .commit()
+                        .map_err(|e| YinxError::Other(e.into()))?;
+                    database.reset_index_watermark(KEYWORD_INDEX, last)?;
+                }
+                if vectors {
+                    vector_watermark.store(last, Ordering::SeqCst);
+                }
+                Ok(indexed)
+            });
+            rebuild.finish();
+            match result {
+                Ok(indexed) => tracing::info!("Index rebuild done: {} chunks", indexed),
+                Err(e) => tracing::warn!("Index rebuild failed: {}", e),
+            }
+        });
+        Ok(())
+    }
+
+    /// Start indexing chunks committed since each searched index was last
+    /// brought up to date, unless a rebuild is already running
+    fn catch_up(&self) -> Result<(), SearchError> {
+        let database_error = |e: YinxError| SearchError::DatabaseError(e.to_string());
+        let mode = self.config.mode;
+        let after = self.vector_watermark.load(Ordering::SeqCst);
+        let keywords = mode.uses_keyword()
+            && index_is_behind(&self.database, KEYWORD_INDEX).map_err(database_error)?;
+        let vectors = mode.uses_vector()
+            && !self
+                .database
+                .chunk_texts_after(after, 1)
+                .map_err(database_error)?
+                .is_empty();
+        if !(keywords || vectors) || !self.rebuild.start() {
+            return Ok(());
+        }
+
+        let database = self.database.clone();
+        let rebuild = self.rebuild.clone();
+        let keyword_index = self.keyword_index.clone();
+        let vector_index = self.vector_index.clone();
+        let provider = self.embedding_provider.clone();
+        let vector_watermark = self.vector_watermark.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = (|| -> crate::error::Result<usize> {
+                let mut indexed = 0;
+                if keywords {
+                    indexed += sync_keyword_index(&database, &mut keyword_index.blocking_write())?;
+                }
+                let mut after = after;
+                if vectors {
+                    loop {
+                        let batch = database.chunk_texts_after(after, REBUILD_BATCH)?;
+                        let Some(&(last, _, _)) = batch.last() else {
+                            break;
+                        };
+                        let texts: Vec<String> =
+                            batch.iter().map(|(_, text, _)| text.clone()).collect();
+                        let embeddings = provider
+                            .embed_batch(&texts)
+                            .map_err(|e| YinxError::Other(e.into()))?;
+                        let items: Vec<(u64, Vec<f32>)> = batch
+                            .iter()
+                            .map(|(id, _, _)| *id as u64)
+                            .zip(embeddings)
+                            .collect();
+                        vector_index
+                            .blocking_read()
+                            .insert_batch(&items)
+                            .map_err(|e| YinxError::Other(e.into()))?;
+                        vector_watermark.store(last, Ordering::SeqCst);
+                        indexed += items.len();
+                        after = last;
+                    }
+                }
+                Ok(indexed)
+            })();
+            rebuild.finish();
+            match result {
+                Ok(indexed) => tracing::debug!("Indexes caught up: {} chunks", indexed),
+                Err(e) => tracing::warn!("Failed to catch the indexes up: {}", e),
+            }
+        });
+        Ok(())
+    }
+
+    /// Drop deleted chunks from both indexes
+    ///
+    /// The vector index only tombstones them; once tombstones pass its
+    /// rebuild threshold the graph is rebuilt in the background.
+    pub async fn remove_chunks(&self, ids: &[u64]) -> Result<(), SearchError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        {
+            let mut keyword_index = self.keyword_index.write().await;
+            for id in ids {
+                keyword_index
+                    .delete(*id)
+                    .map_err(|e| SearchError::KeywordSearchError(e.to_string()))?;
+            }
+            keyword_index
+                .commit()
+                .map_err(|e| SearchError::KeywordSearchError(e.to_string()))?;
+        }
+
+        self.tombstone_vectors(ids).await;
+        Ok(())
+    }
+
+    /// Delete a session's stored data and drop its chunks from both indexes
+    pub async fn purge_session(
+        &self,
+        storage: &StorageManager,
+        session_id: &str,
+    ) -> Result<PurgeStats, SearchError> {
+        let stats = storage
+            .purge_session_data(session_id, false)
+            .map_err(|e| SearchError::DatabaseError(e.to_string()))?;
+        self.remove_chunks(&stats.chunk_ids).await?;
+        Ok(stats)
+    }
+
+    /// Archive a session and drop its chunks from both indexes
+    pub async fn archive_session(
+        &self,
+        storage: &StorageManager,
+        session_id: &str,
+    ) -> Result<ArchiveStats, SearchError> {
+        let stats = storage
+            .archive_session_data(session_id)
+            .map_err(|e| SearchError::DatabaseError(e.to_string()))?;
+        self.remove_chunks(&stats.chunk_ids).await?;
+        Ok(stats)
+    }
+
+    /// Scrub a value from stored history and from both indexes
+    ///
+    /// Scrubbed chunks are re-indexed with their new text; their vectors
+    /// are tombstoned, as the embeddings were of the old text.
+    pub async fn scrub(
+        &self,
+        storage: &StorageManager,
+        pattern: &ScrubPattern,
+    ) -> Result<ScrubReport, SearchError> {
+        let mut report = storage
+            .scrub_data(pattern, false)
+            .map_err(|e| SearchError::DatabaseError(e.to_string()))?;
+        if report.rewritten_chunks.is_empty() {
+            return Ok(report);
+        }
+        {
+            let mut keyword_index = self.keyword_index.write().await;
+            keyword_index
+                .replace_hosted_batch(&report.rewritten_chunks)
+                .map_err(|e| SearchError::KeywordSearchError(e.to_string()))?;
+            keyword_index
+                .commit()
+                .map_err(|e| SearchError::KeywordSearchError(e.to_string()))?;
+        }
+        report.chunks_reindexed = report.rewritten_chunks.len();
+
+        let ids: Vec<u64> = report.rewritten_chunks.iter().map(|c| c.0).collect();
+        self.tombstone_vectors(&ids).await;
+        Ok(report)
+    }
+
+    /// Tombstone vectors, rebuilding the graph in the background once
+    /// tombstones pass the threshold
+    async fn tombstone_vectors(&self, ids: &[u64]) {
+        let vector_index = self.vector_index.clone();
+        let tombstoned = vector_index.read().await.delete(ids);
+        if tombstoned > 0 && vector_index.read().await.needs_rebuild() {
+            tokio::task::spawn_blocking(move || match vector_index.blocking_read().rebuild() {
+                Ok(kept) => tracing::info!("Vector index rebuilt: {} vectors", kept),
+                Err(e) => tracing::warn!("Vector index rebuild failed: {}", e),
+            });
+        }
+    }
+
     /// Perform hybrid search
     pub async fn search(&self, query: &SearchQuery) -> Result<Vec<ScoredChunk>, SearchError> {
+        Ok(self.search_explained(query).await?.results)
+    }
+
+    /// Perform hybrid search, recording how each result was ranked
+    ///
+    /// Every result carries the channel ranks and RRF breakdown it was
+    /// fused from, plus the reranker's adjustment; candidates removed by a
+    /// filter, the reranker, the limit or deduplication are listed with why.
+    pub async fn search_explained(
+        &self,
+        query: &SearchQuery,
+    ) -> Result<ExplainedResults, SearchError> {
         if query.text.is_empty() {
             return Err(SearchError::InvalidQuery(
                 "Query text cannot be empty".to_string(),
             ));
         }
 
+        let _timer = latency::timer("search.hybrid", || {
+            format!("query={:?} limit={}", query.text, query.limit)
+        });
         let search_limit = query.limit * self.config.search_multiplier;
+        self.warm_up().await?;
 
-        // Step 1: Parallel semantic + keyword search
+        // Step 1: Parallel semantic + keyword search, skipping disabled channels
+        let mode = self.config.mode;
+        let ef_search = query.ef_search.unwrap_or(self.config.hnsw_ef_search);
         let (semantic_results, keyword_results) = tokio::join!(
-            self.semantic_search(&query.text, search_limit),
-            self.keyword_search(&query.text, search_limit)
+            async {
+                if mode.uses_vector() {
+                    self.semantic_search(&query.text, search_limit, ef_search)
+                        .await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+            async {
+                if mode.uses_keyword() {
+                    self.keyword_search(&query.text, search_limit).await
+                } else {
+                    Ok(Vec::new())
+                }
+            }
         );
 
         let semantic_results = semantic_results?;
         let keyword_results = keyword_results?;
 
-        // Step 2: Reciprocal Rank Fusion
-        let fusion_config = FusionConfig::new(
-            self.config.rrf_k,
-            self.config.semantic_weight,
-            self.config.keyword_weight,
-        )
-        .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+        // Step 2: Fuse with the configured strategy
+        let fusion_config = FusionConfig::from_config(&self.config)
+            .map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
 
-        let fused_results =
-            reciprocal_rank_fusion(semantic_results, keyword_results, &fusion_config);
+        let explanations = explain_fusion(&semantic_results, &keyword_results, &fusion_config);
+        let fused_results = fuse(semantic_results, keyword_results, &fusion_config);
+        let fused_ids: Vec<i64> = fused_results.iter().map(|(id, _)| *id).collect();
 
         // Step 3: Hydrate chunks from database
         let mut candidates = self.hydrate_chunks(fused_results).await?;
+        let mut dropped = Vec::new();
+        let hydrated: HashSet<i64> = candidates.iter().map(|c| c.chunk_id).collect();
+        dropped.extend(
+            fused_ids
+                .into_iter()
+                .filter(|id| !hydrated.contains(id))
+                .map(|chunk_id| Dropped {
+                    chunk_id,
+                    reason: DropReason::Missing,
+                }),
+        );
+        for candidate in &mut candidates {
+            candidate.explanation = explanations.get(&candidate.chunk_id).cloned();
+        }
 
         // Step 4: Apply filters if specified
         if let Some(session_id) = &query.session_id {
-            candidates.retain(|c| c.provenance.capture_id.to_string() == *session_id);
+            retain_or_drop(&mut candidates, &mut dropped, DropReason::Session, |c| {
+                c.provenance.capture_id.to_string() == *session_id
+            });
         }
 
         if let Some(tool) = &query.tool_filter {
-            candidates.retain(|c| c.provenance.tool == *tool);
+            retain_or_drop(&mut candidates, &mut dropped, DropReason::Tool, |c| {
+                c.provenance.tool == *tool
+            });
+        }
+
+        if let Some(host) = &query.host {
+            retain_or_drop(&mut candidates, &mut dropped, DropReason::Host, |c| {
+                c.metadata
+                    .host
+                    .as_deref()
+                    .is_some_and(|h| h.eq_ignore_ascii_case(host))
+            });
+        }
+        if let Some(sections) = &query.sections {
+            retain_or_drop(&mut candidates, &mut dropped, DropReason::Section, |c| {
+                c.metadata.sections.iter().any(|s| sections.contains(s))
+            });
+        }
+
+        if let Some(users) = &query.users {
+            retain_or_drop(&mut candidates, &mut dropped, DropReason::User, |c| {
+                c.provenance
+                    .username
+                    .as_ref()
+                    .is_some_and(|u| users.contains(u))
+            });
         }
 
         // Step 5: Apply similarity threshold
         if self.config.min_similarity_threshold > 0.0 {
-            candidates.retain(|c| c.score >= self.config.min_similarity_threshold);
+            retain_or_drop(
+                &mut candidates,
+                &mut dropped,
+                DropReason::BelowThreshold,
+                |c| c.score >= self.config.min_similarity_threshold,
+            );
         }
 
         // Step 6: Rerank if enabled
-        let results = if self.reranker.is_some() && candidates.len() > 1 {
-            self.rerank_chunks(&query.text, candidates, query.limit)
-                .await?
-        } else {
-            // Just truncate to limit
-            candidates.truncate(query.limit);
-            candidates
-        };
+        let results =
+            if self.reranker.is_some() && candidates.len() > 1 {
+                let before: Vec<i64> = candidates.iter().map(|c| c.chunk_id).collect();
+                let results = self
+                    .rerank_chunks(&query.text, candidates, query.limit)
+                    .await?;
+                let kept: HashSet<i64> = results.iter().map(|c| c.chunk_id).collect();
+                dropped.extend(before.into_iter().filter(|id| !kept.contains(id)).map(
+                    |chunk_id| Dropped {
+                        chunk_id,
+                        reason: DropReason::RerankCutoff,
+                    },
+                ));
+                results
+            } else {
+                // Just truncate to limit
+                dropped.extend(candidates.iter().skip(query.limit).map(|c| Dropped {
+                    chunk_id: c.chunk_id,
+                    reason: DropReason::Limit,
+                }));
+                candidates.truncate(query.limit);
+                candidates
+            };
 
         // Step 7: Deduplicate by chunk_id
+        let mut seen = HashSet::new();
+        dropped.extend(
+            results
+                .iter()
+                .filter(|c| !seen.insert(c.chunk_id))
+                .map(|c| Dropped {
+                    chunk_id: c.chunk_id,
+                    reason: DropReason::Duplicate,
+                }),
+        );
         let final_results = deduplicate_chunks(results);
 
-        Ok(final_results)
+        Ok(ExplainedResults {
+            results: final_results,
+            dropped,
+            rebuild: self.rebuild_progress(),
+            ef_search: mode.uses_vector().then_some(ef_search),
+        })
     }
 
     /// Semantic search using vector index
@@ -138,6 +520,7 @@ impl HybridSearcher {
         &self,
         query: &str,
         limit: usize,
+        ef_search: usize,
     ) -> Result<Vec<(i64, f32)>, SearchError> {
         // Generate query embedding
         let query_embedding = self
@@ -148,7 +531,7 @@ impl HybridSearcher {
         // Search vector index
         let vector_index = self.vector_index.read().await;
         let results = vector_index
-            .search(&query_embedding, limit, self.config.hnsw_ef_search)
+            .search(&query_embedding, limit, ef_search)
             .map_err(|e| SearchError::VectorSearchError(e.to_string()))?;
 
         Ok(results
@@ -157,13 +540,20 @@ impl HybridSearcher {
             .collect())
     }
 
-    /// Keyword search using tantivy index
+    /// Keyword search using tantivy index, or SQLite's full-text index
+    /// while the keyword index is being rebuilt
     async fn keyword_search(
         &self,
         query: &str,
         limit: usize,
     ) -> Result<Vec<(i64, f32)>, SearchError> {
         let keyword_index = self.keyword_index.read().await;
+        if keyword_index.is_empty() && self.rebuild.is_running() {
+            return self
+                .database
+                .search_chunk_texts(query, limit)
+                .map_err(|e| SearchError::KeywordSearchError(e.to_string()));
+        }
         let results = keyword_index
             .search(query, limit)
             .map_err(|e| SearchError::KeywordSearchError(e.to_string()))?;
@@ -210,22 +600,24 @@ impl HybridSearcher {
                     ))
                 })?;
 
-            // Parse metadata
-            let metadata: ChunkMetadata = if let Some(metadata_json) = &chunk_record.metadata {
-                serde_json::from_str(metadata_json).unwrap_or_else(|_| ChunkMetadata {
-                    cluster_size: chunk_record.cluster_size as usize,
-                    pattern: String::new(),
-                    scores: serde_json::json!({}),
-                    entities: vec![],
-                })
-            } else {
-                ChunkMetadata {
+            // Parse metadata; filter-written metadata only carries some fields
+            let raw: serde_json::Value = chunk_record
+                .metadata
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default();
+            let metadata: ChunkMetadata =
+                serde_json::from_value(raw.clone()).unwrap_or_else(|_| ChunkMetadata {
                     cluster_size: chunk_record.cluster_size as usize,
                     pattern: String::new(),
                     scores: serde_json::json!({}),
                     entities: vec![],
-                }
-            };
+                    sections: raw
+                        .get("sections")
+                        .and_then(|s| serde_json::from_value(s.clone()).ok())
+                        .unwrap_or_default(),
+                    host: raw.get("host").and_then(|h| h.as_str()).map(str::to_string),
+                });
 
             // Build provenance
             let provenance = Provenance {
@@ -235,6 +627,7 @@ impl HybridSearcher {
                 timestamp: chrono::DateTime::from_timestamp(capture.timestamp, 0)
                     .unwrap_or_else(chrono::Utc::now),
                 tool: capture.tool.unwrap_or_else(|| String::from("unknown")),
+                username: capture.username,
             };
 
             // Create scored chunk
@@ -277,8 +670,17 @@ impl HybridSearcher {
         // Reorder chunks and update scores
         let reranked_chunks: Vec<ScoredChunk> = reranked_indices
             .into_iter()
-            .map(|(idx, new_score)| {
+            .enumerate()
+            .map(|(rank, (idx, new_score))| {
                 let mut chunk = candidates[idx].clone();
+                if let Some(explanation) = &mut chunk.explanation {
+                    explanation.rerank = Some(RerankDelta {
+                        rank_before: idx + 1,
+                        rank_after: rank + 1,
+                        score_before: chunk.score,
+                        score_after: new_score,
+                    });
+                }
                 chunk.score = new_score;
                 chunk
             })
@@ -288,8 +690,166 @@ impl HybridSearcher {
     }
 }
 
+/// Keep the candidates `keep` accepts, recording the rest as dropped
+fn retain_or_drop(
+    candidates: &mut Vec<ScoredChunk>,
+    dropped: &mut Vec<Dropped>,
+    reason: DropReason,
+    keep: impl Fn(&ScoredChunk) -> bool,
+) {
+    candidates.retain(|c| {
+        let kept = keep(c);
+        if !kept {
+            dropped.push(Dropped {
+                chunk_id: c.chunk_id,
+                reason,
+            });
+        }
+        kept
+    });
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::embedding::EmbeddingError;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    struct ConstantProvider;
+
+    impl EmbeddingProvider for ConstantProvider {
+        fn embed(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            Ok(vec![1.0, 0.0, 0.0, 0.0])
+        }
 
-    // TODO: Add integration tests
+        fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+            texts.iter().map(|text| self.embed(text)).collect()
+        }
+
+        fn dimension(&self) -> usize {
+            4
+        }
+
+        fn model_name(&self) -> &str {
+            "constant"
+        }
+    }
+
+    /// A searcher over `chunks`, every one embedded as the same vector
+    fn searcher(
+        storage: &StorageManager,
+        dir: &std::path::Path,
+        chunks: &[(i64, &str)],
+    ) -> (
+        HybridSearcher,
+        Arc<RwLock<VectorIndex>>,
+        Arc<RwLock<KeywordIndex>>,
+    ) {
+        let mut keyword_index = storage.open_keyword_index().unwrap();
+        let vector_index = VectorIndex::new(4, 100, 16, dir.join("vectors.hnsw")).unwrap();
+        for (id, text) in chunks {
+            keyword_index.insert(*id as u64, text).unwrap();
+            vector_index
+                .insert(*id as u64, &[1.0, 0.0, 0.0, 0.0])
+                .unwrap();
+        }
+        keyword_index.commit().unwrap();
+
+        let vector_index = Arc::new(RwLock::new(vector_index));
+        let keyword_index = Arc::new(RwLock::new(keyword_index));
+        let searcher = HybridSearcher::new(
+            Arc::new(ConstantProvider),
+            vector_index.clone(),
+            keyword_index.clone(),
+            Arc::new(storage.database.clone()),
+            RetrievalConfig {
+                enable_reranking: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        (searcher, vector_index, keyword_index)
+    }
+
+    #[tokio::test]
+    async fn test_purge_and_scrub_reach_both_indexes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let purged = capture("s1", "nmap 10.0.0.1", b"22/tcp open ssh\n")
+            .chunks(&["22/tcp open ssh", "80/tcp open http"])
+            .insert(&storage);
+        let kept = capture("s2", "env", b"DB_PASSWORD=Hunter2Secret\n")
+            .chunks(&["DB_PASSWORD=Hunter2Secret"])
+            .insert(&storage);
+        let (searcher, vector_index, keyword_index) = searcher(
+            &storage,
+            temp_dir.path(),
+            &[
+                (purged.chunk_ids[0], "22/tcp open ssh"),
+                (purged.chunk_ids[1], "80/tcp open http"),
+                (kept.chunk_ids[0], "DB_PASSWORD=Hunter2Secret"),
+            ],
+        );
+
+        let stats = searcher.purge_session(&storage, "s1").await.unwrap();
+        assert_eq!(stats.chunks, 2);
+        assert_eq!(vector_index.read().await.len(), 1);
+        assert_eq!(keyword_index.read().await.len(), 1);
+
+        let pattern = ScrubPattern::literal("Hunter2Secret").unwrap();
+        let report = searcher.scrub(&storage, &pattern).await.unwrap();
+        assert_eq!(report.chunks_reindexed, 1);
+        // The vector was of the old text
+        assert_eq!(vector_index.read().await.len(), 0);
+        let keyword_index = keyword_index.read().await;
+        assert!(keyword_index
+            .search("Hunter2Secret", 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(keyword_index.search("DB_PASSWORD", 10).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_archived_chunks_leave_semantic_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        for session in ["s1", "s2"] {
+            storage
+                .database
+                .ensure_session(session, session, 0, "stopped")
+                .unwrap();
+        }
+        let archived = capture("s1", "nmap 10.0.0.1", b"22/tcp open ssh\n")
+            .chunks(&["22/tcp open ssh"])
+            .insert(&storage);
+        let kept = capture("s2", "nmap 10.0.0.2", b"80/tcp open http\n")
+            .chunks(&["80/tcp open http"])
+            .insert(&storage);
+        let (searcher, _, _) = searcher(
+            &storage,
+            temp_dir.path(),
+            &[
+                (archived.chunk_ids[0], "22/tcp open ssh"),
+                (kept.chunk_ids[0], "80/tcp open http"),
+            ],
+        );
+        let semantic = || async {
+            let mut ids: Vec<i64> = searcher
+                .semantic_search("open port", 10, 50)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(semantic().await, [archived.chunk_ids[0], kept.chunk_ids[0]]);
+
+        let stats = searcher.archive_session(&storage, "s1").await.unwrap();
+        assert_eq!(stats.chunks_indexed, 1);
+        assert!(storage.database.is_session_archived("s1").unwrap());
+        assert_eq!(semantic().await, [kept.chunk_ids[0]]);
+    }
 }
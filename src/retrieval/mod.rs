@@ -3,17 +3,33 @@
 //! This module implements hybrid search combining semantic and keyword search,
 //! with Reciprocal Rank Fusion and optional cross-encoder reranking.
 
+mod context;
 mod deduplication;
+mod explain;
 mod fusion;
+mod guard;
 mod hybrid;
 mod provenance;
 mod reranker;
+mod warm;
 
+pub use context::{
+    token_counter, CharEstimate, ContextBuilder, ContextError, PackedChunk, PackedContext,
+    TokenCounter, TokenizerFile,
+};
 pub use deduplication::deduplicate_chunks;
-pub use fusion::{reciprocal_rank_fusion, FusionConfig};
-pub use hybrid::{HybridSearcher, SearchError};
+pub use explain::{explain_fusion, ChannelHit, DropReason, Dropped, Explanation, RerankDelta};
+pub use fusion::{fuse, reciprocal_rank_fusion, FusionConfig};
+pub use guard::{
+    check_outbound, fence_untrusted, system_prompt, GuardError, InjectionFinding, PromptGuard,
+};
+pub use hybrid::{ExplainedResults, HybridSearcher, SearchError};
 pub use provenance::{ChunkMetadata, Provenance, ScoredChunk};
-pub use reranker::{RerankError, Reranker};
+pub use reranker::{RerankError, RerankOptions, Reranker};
+pub use warm::{
+    index_is_behind, is_cold, rebuild_from_chunks, rebuild_keyword_index, sync_keyword_index,
+    IndexRebuild, RebuildProgress, KEYWORD_INDEX, REBUILD_BATCH,
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -34,6 +50,22 @@ pub struct SearchQuery {
 
     /// Optional time range filter
     pub time_range: Option<(i64, i64)>,
+
+    /// Optional filter on the users whose captures may be returned
+    #[serde(default)]
+    pub users: Option<Vec<String>>,
+
+    /// Optional filter on tool output sections (e.g. "open_port")
+    #[serde(default)]
+    pub sections: Option<Vec<String>>,
+
+    /// Optional filter on the host a chunk's evidence is about
+    #[serde(default)]
+    pub host: Option<String>,
+
+    /// HNSW ef_search for this query (default: retrieval.hnsw_ef_search)
+    #[serde(default)]
+    pub ef_search: Option<usize>,
 }
 
 impl SearchQuery {
@@ -44,6 +76,10 @@ impl SearchQuery {
             session_id: None,
             tool_filter: None,
             time_range: None,
+            users: None,
+            sections: None,
+            host: None,
+            ef_search: None,
         }
     }
 }
@@ -1,5 +1,6 @@
 //! Provenance tracking and scored chunk structures
 
+use crate::retrieval::Explanation;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -21,6 +22,10 @@ pub struct Provenance {
 
     /// Tool that generated the output
     pub tool: String,
+
+    /// User who ran the command (shared daemons only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
 }
 
 /// Metadata about a chunk (from filtering pipeline)
@@ -37,6 +42,13 @@ pub struct ChunkMetadata {
 
     /// Extracted entities from this chunk
     pub entities: Vec<String>,
+
+    /// Tool output sections the chunk's lines came from
+    #[serde(default)]
+    pub sections: Vec<String>,
+    /// Host of a multi-host scan the chunk's lines are about
+    #[serde(default)]
+    pub host: Option<String>,
 }
 
 /// A chunk with relevance score and full provenance
@@ -56,6 +68,10 @@ pub struct ScoredChunk {
 
     /// Provenance information
     pub provenance: Provenance,
+
+    /// Score breakdown, filled in by the hybrid searcher
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Explanation>,
 }
 
 impl ScoredChunk {
@@ -73,6 +89,7 @@ impl ScoredChunk {
             score,
             metadata,
             provenance,
+            explanation: None,
         }
     }
 
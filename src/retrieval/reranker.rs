@@ -1,6 +1,14 @@
 //! Cross-encoder reranking using FastEmbed
+//!
+//! Candidates are scored in batches, one cross-encoder forward pass per
+//! batch, with batches spread over a thread pool. The model can run on a
+//! CUDA or ROCm device when yinx is built with the matching feature and
+//! ONNX Runtime ships that execution provider; otherwise it falls back to
+//! the CPU.
 
-use fastembed::{RerankInitOptions, TextRerank};
+use crate::config::{RerankDevice, RetrievalConfig};
+use fastembed::{ExecutionProviderDispatch, RerankInitOptions, TextRerank};
+use ort::execution_providers::{CUDAExecutionProvider, ROCmExecutionProvider};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -16,10 +24,76 @@ pub enum RerankError {
     InvalidInput(String),
 }
 
+/// Inference settings for the cross-encoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RerankOptions {
+    /// Candidates scored per forward pass
+    pub batch_size: usize,
+    /// Threads running batches in parallel; 0 uses the global pool (one per core)
+    ///
+    /// ONNX Runtime sizes its own intra-op pool per session, so this bounds
+    /// how many batches are in flight rather than threads inside one batch.
+    pub threads: usize,
+    pub device: RerankDevice,
+    pub device_id: i32,
+}
+
+impl Default for RerankOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 32,
+            threads: 0,
+            device: RerankDevice::Cpu,
+            device_id: 0,
+        }
+    }
+}
+
+impl RerankOptions {
+    pub fn from_config(config: &RetrievalConfig) -> Self {
+        Self {
+            batch_size: config.rerank_batch_size.max(1),
+            threads: config.rerank_threads,
+            device: config.rerank_device,
+            device_id: config.rerank_device_id,
+        }
+    }
+
+    /// Execution providers for the configured device; empty means CPU
+    fn execution_providers(&self) -> Vec<ExecutionProviderDispatch> {
+        let (built_with, provider) = match self.device {
+            RerankDevice::Cpu => return Vec::new(),
+            RerankDevice::Cuda => (
+                cfg!(feature = "cuda"),
+                CUDAExecutionProvider::default()
+                    .with_device_id(self.device_id)
+                    .build(),
+            ),
+            RerankDevice::Rocm => (
+                cfg!(feature = "rocm"),
+                ROCmExecutionProvider::default()
+                    .with_device_id(self.device_id)
+                    .build(),
+            ),
+        };
+        if !built_with {
+            tracing::warn!(
+                "Reranker device {} requested but yinx was built without the `{}` feature; using CPU",
+                self.device.as_str(),
+                self.device.as_str()
+            );
+            return Vec::new();
+        }
+        vec![provider]
+    }
+}
+
 /// Cross-encoder reranker for improving result precision
 pub struct Reranker {
     model: Arc<TextRerank>,
     model_name: String,
+    options: RerankOptions,
+    pool: Option<rayon::ThreadPool>,
 }
 
 impl Reranker {
@@ -28,18 +102,42 @@ impl Reranker {
     /// # Arguments
     /// * `model_name` - Model name (e.g., "Xenova/ms-marco-MiniLM-L-6-v2")
     pub fn new(model_name: &str) -> Result<Self, RerankError> {
-        tracing::info!("Initializing reranker model: {}", model_name);
+        Self::with_options(model_name, RerankOptions::default())
+    }
+
+    /// Create a reranker with explicit batching, threading and device settings
+    pub fn with_options(model_name: &str, options: RerankOptions) -> Result<Self, RerankError> {
+        tracing::info!(
+            "Initializing reranker model: {} (batch {}, device {})",
+            model_name,
+            options.batch_size,
+            options.device.as_str()
+        );
 
         // FastEmbed v4.x uses RerankInitOptions
         let init_options = RerankInitOptions::new(fastembed::RerankerModel::BGERerankerBase)
-            .with_show_download_progress(true);
+            .with_show_download_progress(true)
+            .with_execution_providers(options.execution_providers());
 
         let model = TextRerank::try_new(init_options)
             .map_err(|e| RerankError::InitializationError(e.to_string()))?;
 
+        let pool = if options.threads > 0 {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(options.threads)
+                .thread_name(|i| format!("yinx-rerank-{}", i))
+                .build()
+                .map_err(|e| RerankError::InitializationError(e.to_string()))?;
+            Some(pool)
+        } else {
+            None
+        };
+
         Ok(Self {
             model: Arc::new(model),
             model_name: model_name.to_string(),
+            options,
+            pool,
         })
     }
 
@@ -76,11 +174,14 @@ impl Reranker {
         // Create document references for reranking
         let documents: Vec<&str> = candidates.iter().map(|s| s.as_str()).collect();
 
-        // Rerank using FastEmbed
-        let results = self
-            .model
-            .rerank(query, documents, true, Some(top_k))
-            .map_err(|e| RerankError::RerankingError(e.to_string()))?;
+        // One forward pass per batch; batches run on the reranker's pool
+        let batch_size = Some(self.options.batch_size);
+        let run = || self.model.rerank(query, documents, false, batch_size);
+        let results = match &self.pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        }
+        .map_err(|e| RerankError::RerankingError(e.to_string()))?;
 
         // Convert to (index, score) pairs
         let mut scored: Vec<(usize, f32)> =
@@ -88,6 +189,7 @@ impl Reranker {
 
         // Sort by score descending
         scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(top_k);
 
         Ok(scored)
     }
@@ -95,12 +197,38 @@ impl Reranker {
     pub fn model_name(&self) -> &str {
         &self.model_name
     }
+
+    pub fn options(&self) -> &RerankOptions {
+        &self.options
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_options_from_config() {
+        let config = RetrievalConfig {
+            rerank_batch_size: 0,
+            rerank_threads: 4,
+            rerank_device: RerankDevice::Cuda,
+            rerank_device_id: 1,
+            ..RetrievalConfig::default()
+        };
+        let options = RerankOptions::from_config(&config);
+        assert_eq!(options.batch_size, 1);
+        assert_eq!(options.threads, 4);
+        assert_eq!(options.device, RerankDevice::Cuda);
+
+        // Without the cuda feature the device falls back to CPU
+        assert_eq!(
+            options.execution_providers().len(),
+            usize::from(cfg!(feature = "cuda"))
+        );
+        assert!(RerankOptions::default().execution_providers().is_empty());
+    }
+
     #[test]
     #[ignore] // Requires model download
     fn test_reranker_creation() {
@@ -0,0 +1,350 @@
+//! Index warm standby
+//!
+//! The keyword and vector indexes live in the machine zone and hold nothing
+//! SQLite doesn't, so losing them (a fresh import, a deleted `store/`)
+//! shouldn't mean empty results. A search that finds an index empty while
+//! the database has chunks starts rebuilding it in the background and
+//! answers from SQLite's full-text index over the chunks until it's done.
+//!
+//! A rebuild run by another process reports its progress through a small
+//! JSON file beside the index, so `yinx query` can say how far along it is.
+//!
+//! Between rebuilds the daemon writes new chunks through to the keyword
+//! index. SQLite keeps a watermark per index: the highest chunk id known to
+//! be in it, and the highest one an update was started for. Both phases
+//! are recorded around the index commit, so after a crash the chunks above
+//! the committed mark are exactly the ones to index again, and those up to
+//! the pending mark are replaced rather than added twice.
+
+use crate::embedding::{KeywordIndex, KeywordIndexError, KeywordTokenizers};
+use crate::error::{Result, YinxError};
+use crate::storage::Database;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Chunks read from SQLite and indexed at a time
+pub const REBUILD_BATCH: usize = 500;
+
+/// Watermark name of the keyword index
+pub const KEYWORD_INDEX: &str = "keywords";
+
+/// A progress file not touched for this long belongs to a rebuild that died
+const STALE_AFTER: Duration = Duration::from_secs(120);
+
+/// How far a rebuild has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebuildProgress {
+    pub indexed: usize,
+    pub total: usize,
+}
+
+impl RebuildProgress {
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.indexed as f64 * 100.0 / self.total as f64
+        }
+    }
+
+    /// Progress recorded at `path`, unless there is none or it's stale
+    pub fn read(path: &Path) -> Option<Self> {
+        let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age > STALE_AFTER {
+            return None;
+        }
+        serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+    }
+
+    /// Record this progress at `path`
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+}
+
+/// A rebuild in progress, shared with whoever searches meanwhile
+#[derive(Debug, Default)]
+pub struct IndexRebuild {
+    running: AtomicBool,
+    indexed: AtomicUsize,
+    total: AtomicUsize,
+    progress_file: Option<PathBuf>,
+}
+
+impl IndexRebuild {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also record progress at `path` for other processes to read
+    pub fn with_progress_file(path: PathBuf) -> Self {
+        Self {
+            progress_file: Some(path),
+            ..Self::default()
+        }
+    }
+
+    /// Claim the rebuild; false if one is already running
+    pub fn start(&self) -> bool {
+        !self.running.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn progress(&self) -> RebuildProgress {
+        RebuildProgress {
+            indexed: self.indexed.load(Ordering::SeqCst),
+            total: self.total.load(Ordering::SeqCst),
+        }
+    }
+
+    fn record(&self, indexed: usize, total: usize) {
+        self.indexed.store(indexed, Ordering::SeqCst);
+        self.total.store(total, Ordering::SeqCst);
+        if let Some(path) = &self.progress_file {
+            if let Err(e) = self.progress().write(path) {
+                tracing::warn!("Failed to record rebuild progress: {}", e);
+            }
+        }
+    }
+
+    /// Mark the rebuild over, whether it finished or failed
+    pub fn finish(&self) {
+        if let Some(path) = &self.progress_file {
+            let _ = std::fs::remove_file(path);
+        }
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Whether an index holding `indexed` documents needs rebuilding
+pub fn is_cold(indexed: u64, database: &Database) -> Result<bool> {
+    Ok(indexed == 0 && database.count_chunks()? > 0)
+}
+
+/// Hand every chunk in the database to `index` with its host, a batch at
+/// a time, recording progress on `rebuild`
+///
+/// Returns the number of chunks indexed. The caller commits and calls
+/// [`IndexRebuild::finish`].
+pub fn rebuild_from_chunks(
+    database: &Database,
+    rebuild: &IndexRebuild,
+    mut index: impl FnMut(&[(u64, String, Option<String>)]) -> Result<()>,
+) -> Result<usize> {
+    let total = database.count_chunks()?;
+    let mut indexed = 0;
+    let mut after = 0;
+    rebuild.record(0, total);
+    loop {
+        let batch = database.chunk_texts_after(after, REBUILD_BATCH)?;
+        let Some(&(last, _, _)) = batch.last() else {
+            break;
+        };
+        after = last;
+        let items: Vec<(u64, String, Option<String>)> = batch
+            .into_iter()
+            .map(|(id, text, host)| (id as u64, text, host))
+            .collect();
+        index(&items)?;
+        indexed += items.len();
+        // Chunks added since counting can take it past the total
+        rebuild.record(indexed, total.max(indexed));
+    }
+    Ok(indexed)
+}
+
+/// Rebuild the keyword index at `path` from the database
+///
+/// The index is recreated empty with `tokenizers` first. Runs in whichever
+/// process calls it; `yinx query` starts one in a detached
+/// `yinx _internal reindex`.
+pub fn rebuild_keyword_index(
+    database: &Database,
+    path: PathBuf,
+    tokenizers: &KeywordTokenizers,
+    rebuild: &IndexRebuild,
+) -> Result<usize> {
+    let other = |e: KeywordIndexError| YinxError::Other(e.into());
+    let result = KeywordIndex::recreate(path, tokenizers)
+        .map_err(other)
+        .and_then(|mut index| {
+            let mut last = 0;
+            let indexed = rebuild_from_chunks(database, rebuild, |items| {
+                last = items.last().map_or(last, |(id, _, _)| *id as i64);
+                index.insert_hosted_batch(items).map_err(other)
+            })?;
+            index.commit().map_err(other)?;
+            database.reset_index_watermark(KEYWORD_INDEX, last)?;
+            Ok(indexed)
+        });
+    rebuild.finish();
+    result
+}
+
+/// Whether the database has chunks past the watermark of the index `name`
+pub fn index_is_behind(database: &Database, name: &str) -> Result<bool> {
+    let watermark = database.index_watermark(name)?;
+    Ok(!database
+        .chunk_texts_after(watermark.committed, 1)?
+        .is_empty())
+}
+
+/// Index the chunks committed to the database since the keyword index's
+/// watermark, a batch at a time
+///
+/// Each batch is recorded as pending before the index commit and as
+/// committed after it. Chunks are replaced by id, so neither a batch left
+/// pending by a crash nor chunks indexed meanwhile by an import or refilter
+/// end up in the index twice. Returns the number of chunks indexed.
+pub fn sync_keyword_index(database: &Database, index: &mut KeywordIndex) -> Result<usize> {
+    let other = |e: KeywordIndexError| YinxError::Other(e.into());
+    let watermark = database.index_watermark(KEYWORD_INDEX)?;
+    if watermark.interrupted() {
+        tracing::info!(
+            "Re-indexing chunks {} to {} after an interrupted keyword index update",
+            watermark.committed + 1,
+            watermark.pending
+        );
+    }
+
+    let mut indexed = 0;
+    let mut after = watermark.committed;
+    loop {
+        let batch = database.chunk_texts_after(after, REBUILD_BATCH)?;
+        let Some(&(last, _, _)) = batch.last() else {
+            break;
+        };
+        let items: Vec<(u64, String, Option<String>)> = batch
+            .into_iter()
+            .map(|(id, text, host)| (id as u64, text, host))
+            .collect();
+        database.begin_index_update(KEYWORD_INDEX, last)?;
+        index.replace_hosted_batch(&items).map_err(other)?;
+        index.commit().map_err(other)?;
+        database.commit_index_update(KEYWORD_INDEX, last)?;
+        indexed += items.len();
+        after = last;
+    }
+    Ok(indexed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use crate::storage::StorageManager;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rebuild_keyword_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let texts: Vec<String> = (0..REBUILD_BATCH + 3)
+            .map(|i| format!("{}/tcp open service{}", i, i))
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        capture("s1", "nmap", b"open")
+            .chunks(&texts)
+            .insert(&storage);
+        let database = &storage.database;
+
+        let path = temp_dir.path().join("keywords");
+        assert!(is_cold(0, database).unwrap());
+        let progress_file = temp_dir.path().join("keywords.rebuild");
+        let rebuild = IndexRebuild::with_progress_file(progress_file.clone());
+        assert!(rebuild.start());
+        assert!(!rebuild.start());
+
+        let indexed =
+            rebuild_keyword_index(database, path.clone(), &Default::default(), &rebuild).unwrap();
+        assert_eq!(indexed, REBUILD_BATCH + 3);
+        assert_eq!(
+            rebuild.progress(),
+            RebuildProgress {
+                indexed,
+                total: indexed
+            }
+        );
+        assert!(!rebuild.is_running());
+        assert!(!progress_file.exists());
+
+        let index = KeywordIndex::new(path).unwrap();
+        assert_eq!(index.len(), indexed as u64);
+        assert!(!is_cold(index.len(), database).unwrap());
+        assert_eq!(index.search("service7", 5).unwrap().len(), 1);
+        assert_eq!(
+            database.index_watermark(KEYWORD_INDEX).unwrap().committed,
+            indexed as i64
+        );
+    }
+
+    #[test]
+    fn test_sync_keyword_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let database = &storage.database;
+        database.ensure_session("s2", "old", 0, "archived").unwrap();
+        let hash = capture("s1", "nmap", b"open").insert(&storage).hash;
+        capture("s2", "nmap", b"open").insert(&storage);
+        let add_chunks = |capture: i64, n: usize| {
+            let conn = database.get_conn().unwrap();
+            for i in 0..n {
+                conn.execute(
+                    "INSERT INTO chunks (capture_id, blob_hash, representative_text)
+                     VALUES (?1, ?2, ?3)",
+                    rusqlite::params![capture, &hash, format!("{}/tcp open", i)],
+                )
+                .unwrap();
+            }
+        };
+        add_chunks(1, 3);
+        add_chunks(2, 2);
+
+        let mut index = KeywordIndex::new(temp_dir.path().join("keywords")).unwrap();
+        assert!(index_is_behind(database, KEYWORD_INDEX).unwrap());
+        // Archived sessions stay out of the index
+        assert_eq!(sync_keyword_index(database, &mut index).unwrap(), 3);
+        assert!(!index_is_behind(database, KEYWORD_INDEX).unwrap());
+        assert_eq!(sync_keyword_index(database, &mut index).unwrap(), 0);
+
+        // A crash after the index commit but before it was recorded
+        add_chunks(1, 2);
+        database.begin_index_update(KEYWORD_INDEX, 7).unwrap();
+        for id in [6, 7] {
+            index.insert(id, "tcp open").unwrap();
+        }
+        index.commit().unwrap();
+        let watermark = database.index_watermark(KEYWORD_INDEX).unwrap();
+        assert!(watermark.interrupted());
+        assert_eq!(watermark.committed, 3);
+
+        assert_eq!(sync_keyword_index(database, &mut index).unwrap(), 2);
+        assert_eq!(index.len(), 5);
+        let watermark = database.index_watermark(KEYWORD_INDEX).unwrap();
+        assert!(!watermark.interrupted());
+        assert_eq!(watermark.committed, 7);
+    }
+
+    #[test]
+    fn test_progress_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("keywords.rebuild");
+        assert_eq!(RebuildProgress::read(&path), None);
+
+        let rebuild = IndexRebuild::with_progress_file(path.clone());
+        rebuild.record(250, 1000);
+        let progress = RebuildProgress::read(&path).unwrap();
+        assert_eq!(progress.indexed, 250);
+        assert_eq!(progress.percent(), 25.0);
+        rebuild.finish();
+        assert_eq!(RebuildProgress::read(&path), None);
+    }
+}
@@ -0,0 +1,351 @@
+//! Rule engine for automatic tagging, findings and alerts
+//!
+//! Rules live in `rules.toml`. Each one pairs a condition on a processed
+//! capture (tool, command, output, exit code, extracted entities) with the
+//! actions to take when it matches. Every condition that is set must hold;
+//! an empty condition matches every capture.
+
+use crate::entities::Entity;
+use crate::error::{Result, YinxError};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// `rules.toml` file structure
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RulesConfig {
+    #[serde(default)]
+    pub rule: Vec<RuleConfig>,
+}
+
+/// One `[[rule]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub name: String,
+    #[serde(default)]
+    pub when: ConditionConfig,
+    pub then: Vec<RuleAction>,
+}
+
+/// Conditions a capture must meet; regexes are unanchored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConditionConfig {
+    /// Detected tool name
+    pub tool: Option<String>,
+    /// Regex over the (redacted) command line
+    pub command: Option<String>,
+    /// Regex over the raw output
+    pub output: Option<String>,
+    pub exit_code: Option<i32>,
+    /// Entity type; a trailing `*` matches by prefix (`credential_*`)
+    pub entity_type: Option<String>,
+    /// Regex the value of an entity of `entity_type` must match
+    pub entity_value: Option<String>,
+}
+
+/// What to do when a rule matches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Attach a tag to the capture
+    Tag { tag: String },
+    /// Open a finding in the capture's session (once per rule and title)
+    Finding {
+        title: String,
+        #[serde(default)]
+        severity: Severity,
+    },
+    /// Desktop notification, falling back to the daemon log
+    Notify { message: Option<String> },
+    /// POST a JSON summary of the match
+    Webhook { url: String },
+}
+
+/// Finding severity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// A processed capture as seen by the rules
+#[derive(Debug)]
+pub struct RuleContext<'a> {
+    pub tool: Option<&'a str>,
+    pub command: &'a str,
+    pub output: &'a str,
+    pub exit_code: i32,
+    pub entities: &'a [Entity],
+}
+
+/// A rule that matched a capture
+#[derive(Debug)]
+pub struct RuleMatch<'a> {
+    pub rule: &'a str,
+    pub actions: &'a [RuleAction],
+    /// Entities that satisfied the entity condition, if there was one
+    /// (first of each value)
+    pub entities: Vec<&'a Entity>,
+}
+
+struct CompiledRule {
+    name: String,
+    tool: Option<String>,
+    command: Option<Regex>,
+    output: Option<Regex>,
+    exit_code: Option<i32>,
+    entity_type: Option<String>,
+    entity_value: Option<Regex>,
+    actions: Vec<RuleAction>,
+}
+
+impl CompiledRule {
+    fn matches<'a>(&self, ctx: &RuleContext<'a>) -> Option<Vec<&'a Entity>> {
+        if self.tool.is_some() && self.tool.as_deref() != ctx.tool {
+            return None;
+        }
+        if self.exit_code.is_some_and(|code| code != ctx.exit_code) {
+            return None;
+        }
+        if self
+            .command
+            .as_ref()
+            .is_some_and(|re| !re.is_match(ctx.command))
+        {
+            return None;
+        }
+        if self
+            .output
+            .as_ref()
+            .is_some_and(|re| !re.is_match(ctx.output))
+        {
+            return None;
+        }
+        if self.entity_type.is_none() && self.entity_value.is_none() {
+            return Some(Vec::new());
+        }
+
+        let mut values: Vec<&Entity> = Vec::new();
+        for entity in ctx.entities {
+            let value_matches = self
+                .entity_value
+                .as_ref()
+                .is_none_or(|re| re.is_match(&entity.value));
+            if self.entity_type_matches(&entity.entity_type)
+                && value_matches
+                && !values.iter().any(|e| e.value == entity.value)
+            {
+                values.push(entity);
+            }
+        }
+        (!values.is_empty()).then_some(values)
+    }
+
+    fn entity_type_matches(&self, entity_type: &str) -> bool {
+        match self.entity_type.as_deref() {
+            None => true,
+            Some(pattern) => match pattern.strip_suffix('*') {
+                Some(prefix) => entity_type.starts_with(prefix),
+                None => entity_type == pattern,
+            },
+        }
+    }
+}
+
+/// Compiled rule set
+#[derive(Default)]
+pub struct RuleEngine {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleEngine {
+    /// Load rules from a file; a missing file means no rules
+    pub fn from_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read rules file: {:?}", path),
+        })?;
+        Self::from_config(toml::from_str(&content)?)
+    }
+
+    /// Compile a parsed rules file
+    pub fn from_config(config: RulesConfig) -> Result<Self> {
+        let rules = config
+            .rule
+            .into_iter()
+            .map(|rule| {
+                let compile = |field: &str, pattern: Option<String>| {
+                    pattern.map(|p| Regex::new(&p)).transpose().map_err(|e| {
+                        YinxError::Config(format!(
+                            "Invalid {} regex in rule '{}': {}",
+                            field, rule.name, e
+                        ))
+                    })
+                };
+                if rule.then.is_empty() {
+                    return Err(YinxError::Config(format!(
+                        "Rule '{}' has no actions",
+                        rule.name
+                    )));
+                }
+                Ok(CompiledRule {
+                    command: compile("command", rule.when.command.clone())?,
+                    output: compile("output", rule.when.output.clone())?,
+                    entity_value: compile("entity_value", rule.when.entity_value.clone())?,
+                    tool: rule.when.tool,
+                    exit_code: rule.when.exit_code,
+                    entity_type: rule.when.entity_type,
+                    actions: rule.then,
+                    name: rule.name,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rules matching a capture, in file order
+    pub fn evaluate<'a>(&'a self, ctx: &RuleContext<'a>) -> Vec<RuleMatch<'a>> {
+        self.rules
+            .iter()
+            .filter_map(|rule| {
+                rule.matches(ctx).map(|entities| RuleMatch {
+                    rule: &rule.name,
+                    actions: &rule.actions,
+                    entities,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+[[rule]]
+name = "dc-credential"
+[rule.when]
+entity_type = "credential_*"
+output = '(?i)domain controller'
+[[rule.then]]
+action = "finding"
+title = "Credential on domain controller"
+severity = "critical"
+[[rule.then]]
+action = "tag"
+tag = "dc-creds"
+
+[[rule]]
+name = "failed-nmap"
+[rule.when]
+tool = "nmap"
+exit_code = 1
+[[rule.then]]
+action = "notify"
+"#;
+
+    fn entity(entity_type: &str, value: &str) -> Entity {
+        Entity {
+            entity_type: entity_type.to_string(),
+            value: value.to_string(),
+            context: String::new(),
+            start: 0,
+            end: 0,
+            confidence: 0.9,
+            should_redact: false,
+        }
+    }
+
+    #[test]
+    fn test_rules_match_conditions() {
+        let engine = RuleEngine::from_config(toml::from_str(RULES).unwrap()).unwrap();
+        let entities = vec![
+            entity("ip_address", "10.0.0.1"),
+            entity("credential_password", "Winter2025!"),
+        ];
+        let ctx = RuleContext {
+            tool: Some("crackmapexec"),
+            command: "cme smb 10.0.0.1 -u admin -p [REDACTED]",
+            output: "Windows Server 2019 (Domain Controller)",
+            exit_code: 0,
+            entities: &entities,
+        };
+
+        let matches = engine.evaluate(&ctx);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].rule, "dc-credential");
+        assert_eq!(matches[0].entities[0].value, "Winter2025!");
+        assert_eq!(matches[0].entities.len(), 1);
+        assert_eq!(
+            matches[0].actions[0],
+            RuleAction::Finding {
+                title: "Credential on domain controller".to_string(),
+                severity: Severity::Critical,
+            }
+        );
+
+        // Same output without a credential doesn't match
+        let ctx = RuleContext {
+            entities: &entities[..1],
+            ..ctx
+        };
+        assert!(engine.evaluate(&ctx).is_empty());
+
+        let ctx = RuleContext {
+            tool: Some("nmap"),
+            exit_code: 1,
+            ..ctx
+        };
+        assert_eq!(engine.evaluate(&ctx)[0].rule, "failed-nmap");
+    }
+
+    #[test]
+    fn test_template_compiles() {
+        let template = include_str!("../config-templates/rules.toml");
+        let engine = RuleEngine::from_config(toml::from_str(template).unwrap()).unwrap();
+        assert!(!engine.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rules_rejected() {
+        let bad_regex = r#"
+[[rule]]
+name = "broken"
+when = { output = "(" }
+then = [{ action = "tag", tag = "x" }]
+"#;
+        assert!(RuleEngine::from_config(toml::from_str(bad_regex).unwrap()).is_err());
+
+        let no_actions = r#"
+[[rule]]
+name = "idle"
+then = []
+"#;
+        assert!(RuleEngine::from_config(toml::from_str(no_actions).unwrap()).is_err());
+    }
+}
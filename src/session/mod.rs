@@ -18,6 +18,43 @@ pub enum SessionStatus {
     Paused,
     /// Session has been stopped
     Stopped,
+    /// Session is in cold storage and excluded from search indexes
+    Archived,
+    /// Daemon died while the session was active
+    Interrupted,
+}
+
+/// Parse a `--deadline` value
+///
+/// Accepts RFC 3339, a local date and time such as `2025-07-01T08:00`, or
+/// a duration from `now` such as `24h`.
+pub fn parse_deadline(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    use chrono::{Local, NaiveDateTime, TimeZone};
+
+    let value = value.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+            return Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc))
+                .ok_or_else(|| {
+                    YinxError::Session(format!("Deadline {} does not exist locally", value))
+                });
+        }
+    }
+    if let Some(duration) = crate::config::parse_duration(value) {
+        let duration = chrono::Duration::from_std(duration)
+            .map_err(|_| YinxError::Session(format!("Deadline too far away: {}", value)))?;
+        return Ok(now + duration);
+    }
+    Err(YinxError::Session(format!(
+        "Invalid deadline '{}': expected e.g. 2025-07-01T08:00 or 24h",
+        value
+    )))
 }
 
 /// A capture session
@@ -86,6 +123,91 @@ impl Session {
         self.status = SessionStatus::Active;
     }
 
+    /// Mark the session as interrupted, recording the capture gap in metadata
+    ///
+    /// # Arguments
+    /// * `last_activity` - Last known capture time (falls back to start time)
+    pub fn interrupt(&mut self, last_activity: Option<DateTime<Utc>>) {
+        let now = Utc::now();
+        let last = last_activity.unwrap_or(self.started_at);
+
+        self.status = SessionStatus::Interrupted;
+        self.metadata.insert(
+            "interrupted_at".to_string(),
+            serde_json::Value::String(now.to_rfc3339()),
+        );
+        self.metadata.insert(
+            "last_activity_at".to_string(),
+            serde_json::Value::String(last.to_rfc3339()),
+        );
+        self.metadata.insert(
+            "gap_seconds".to_string(),
+            serde_json::Value::from((now - last).num_seconds().max(0)),
+        );
+    }
+
+    /// Seconds between the last capture and detection of the interruption
+    pub fn interruption_gap(&self) -> Option<i64> {
+        self.metadata.get("gap_seconds").and_then(|v| v.as_i64())
+    }
+
+    /// Close an interrupted session at its last known activity time
+    pub fn close_interrupted(&mut self) {
+        let last = self
+            .metadata
+            .get("last_activity_at")
+            .and_then(|v| v.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc));
+        self.stopped_at = Some(last.unwrap_or_else(Utc::now));
+        self.status = SessionStatus::Stopped;
+    }
+
+    /// Set the engagement deadline (exam clock)
+    pub fn set_deadline(&mut self, deadline: DateTime<Utc>) {
+        self.metadata.insert(
+            "deadline".to_string(),
+            serde_json::Value::String(deadline.to_rfc3339()),
+        );
+    }
+
+    /// Engagement deadline, if the session was started with one
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        self.metadata
+            .get("deadline")
+            .and_then(|v| v.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    /// Time left until the deadline at `now`; negative once it has passed
+    pub fn time_remaining(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        self.deadline().map(|deadline| deadline - now)
+    }
+
+    /// Hours between the session start and `at`, for hours-into-exam markers
+    pub fn hours_into(&self, at: DateTime<Utc>) -> f64 {
+        (at - self.started_at).num_seconds() as f64 / 3600.0
+    }
+
+    /// Move the session into cold storage
+    pub fn archive(&mut self) {
+        if self.stopped_at.is_none() {
+            self.stopped_at = Some(Utc::now());
+        }
+        self.status = SessionStatus::Archived;
+    }
+
+    /// Bring an archived session back to a stopped, queryable state
+    pub fn unarchive(&mut self) {
+        self.status = SessionStatus::Stopped;
+    }
+
+    /// Check if the session is archived
+    pub fn is_archived(&self) -> bool {
+        self.status == SessionStatus::Archived
+    }
+
     /// Get session duration
     pub fn duration(&self) -> chrono::Duration {
         let end = self.stopped_at.unwrap_or_else(Utc::now);
@@ -251,7 +373,7 @@ impl SessionManager {
         }
 
         // Sort by started_at descending (newest first)
-        sessions.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.started_at));
 
         Ok(sessions)
     }
@@ -262,6 +384,59 @@ impl SessionManager {
         Ok(sessions.into_iter().find(|s| s.name == name))
     }
 
+    /// Find the most recent session that has not been stopped
+    pub fn find_active(&self) -> Result<Option<Session>> {
+        let sessions = self.list_sessions()?;
+        Ok(sessions
+            .into_iter()
+            .find(|s| matches!(s.status, SessionStatus::Active | SessionStatus::Paused)))
+    }
+
+    /// Mark sessions left Active/Paused by a dead daemon as interrupted
+    ///
+    /// # Arguments
+    /// * `keep` - Session the daemon is starting with, which is never touched
+    /// * `last_activity` - Lookup for a session's last capture time
+    ///
+    /// # Returns
+    /// The sessions that were marked interrupted
+    pub fn reconcile_stale<F>(&self, keep: Option<&Uuid>, last_activity: F) -> Result<Vec<Session>>
+    where
+        F: Fn(&Session) -> Option<DateTime<Utc>>,
+    {
+        let mut interrupted = Vec::new();
+
+        for mut session in self.list_sessions()? {
+            if Some(&session.id) == keep
+                || !matches!(
+                    session.status,
+                    SessionStatus::Active | SessionStatus::Paused
+                )
+            {
+                continue;
+            }
+
+            let last = last_activity(&session);
+            session.interrupt(last);
+            session.save(&self.data_dir)?;
+            interrupted.push(session);
+        }
+
+        Ok(interrupted)
+    }
+
+    /// Resolve a session by UUID or name
+    pub fn resolve(&self, id_or_name: &str) -> Result<Session> {
+        if let Ok(id) = Uuid::parse_str(id_or_name) {
+            return Session::load(&self.data_dir, &id);
+        }
+
+        self.find_by_name(id_or_name)?
+            .ok_or_else(|| YinxError::SessionNotFound {
+                id: id_or_name.to_string(),
+            })
+    }
+
     /// Delete a session
     pub fn delete_session(&self, id: &Uuid) -> Result<()> {
         let session_dir = self.data_dir.join("sessions").join(id.to_string());
@@ -302,6 +477,29 @@ mod tests {
         assert!(session.stopped_at.is_some());
     }
 
+    #[test]
+    fn test_deadline() {
+        let now = Utc::now();
+        let mut session = Session::new("oscp");
+        assert!(session.deadline().is_none());
+
+        let deadline = parse_deadline("24h", now).unwrap();
+        assert_eq!(deadline, now + chrono::Duration::hours(24));
+        session.set_deadline(deadline);
+        assert_eq!(
+            session.time_remaining(now).unwrap().num_seconds(),
+            24 * 3600
+        );
+
+        let utc = parse_deadline("2025-07-01T08:00:00Z", now).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2025-07-01T08:00:00+00:00");
+        assert!(parse_deadline("2025-07-01T08:00", now).is_ok());
+        assert!(parse_deadline("tomorrow", now).is_err());
+
+        let at = session.started_at + chrono::Duration::minutes(90);
+        assert_eq!(session.hours_into(at), 1.5);
+    }
+
     #[test]
     fn test_session_manager() {
         let temp_dir = TempDir::new().unwrap();
@@ -313,4 +511,66 @@ mod tests {
         let sessions = manager.list_sessions().unwrap();
         assert_eq!(sessions.len(), 1);
     }
+
+    #[test]
+    fn test_session_archive_roundtrip() {
+        let mut session = Session::new("test");
+        session.archive();
+        assert!(session.is_archived());
+        assert!(session.stopped_at.is_some());
+
+        session.unarchive();
+        assert_eq!(session.status, SessionStatus::Stopped);
+    }
+
+    #[test]
+    fn test_resolve_by_id_or_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = SessionManager::new(temp_dir.path().to_path_buf());
+
+        let id = manager
+            .create_session(Some("htb-box".to_string()))
+            .unwrap()
+            .id;
+
+        assert_eq!(manager.resolve("htb-box").unwrap().id, id);
+        assert_eq!(manager.resolve(&id.to_string()).unwrap().name, "htb-box");
+        assert!(manager.resolve("missing").is_err());
+    }
+
+    #[test]
+    fn test_reconcile_stale() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = SessionManager::new(temp_dir.path().to_path_buf());
+
+        let stale = manager
+            .create_session(Some("stale".to_string()))
+            .unwrap()
+            .id;
+        let current = manager
+            .create_session(Some("current".to_string()))
+            .unwrap()
+            .id;
+
+        let last = Utc::now() - chrono::Duration::hours(2);
+        let interrupted = manager
+            .reconcile_stale(Some(&current), |_| Some(last))
+            .unwrap();
+
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].id, stale);
+        assert!(interrupted[0].interruption_gap().unwrap() >= 7200);
+
+        let on_disk = Session::load(temp_dir.path(), &stale).unwrap();
+        assert_eq!(on_disk.status, SessionStatus::Interrupted);
+        assert_eq!(
+            Session::load(temp_dir.path(), &current).unwrap().status,
+            SessionStatus::Active
+        );
+
+        let mut closed = on_disk;
+        closed.close_interrupted();
+        assert_eq!(closed.status, SessionStatus::Stopped);
+        assert_eq!(closed.stopped_at.unwrap().timestamp(), last.timestamp());
+    }
 }
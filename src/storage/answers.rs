@@ -0,0 +1,214 @@
+//! Semantic answer cache
+//!
+//! Answers to `yinx ask` are kept per session along with the question's
+//! embedding and a fingerprint of the session's indexed chunks. A later
+//! question close enough to a cached one gets the cached answer back, but
+//! only while the fingerprint still matches: a new capture, a scrub or a
+//! refilter all change what the answer would have been built from.
+
+use super::StorageManager;
+use crate::error::Result;
+use chrono::Utc;
+use rusqlite::params;
+
+/// An answer found in the cache
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedAnswer {
+    pub id: i64,
+    /// Question the answer was generated for
+    pub question: String,
+    pub answer: String,
+    /// Cosine similarity between the cached and the new question
+    pub similarity: f32,
+    pub created_at: i64,
+    /// Times the answer was served from the cache, this lookup included
+    pub hits: i64,
+}
+
+impl StorageManager {
+    /// Fingerprint of what a session's answers are built from
+    pub fn index_state(&self, session_id: &str) -> Result<String> {
+        let conn = self.database.read_conn()?;
+        let (count, max_id, text_len): (i64, Option<i64>, f64) = conn.query_row(
+            "SELECT COUNT(*), MAX(ch.id), TOTAL(LENGTH(ch.representative_text))
+             FROM chunks ch JOIN captures c ON ch.capture_id = c.id
+             WHERE c.session_id = ?1",
+            params![session_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        Ok(format!(
+            "{}:{}:{}",
+            count,
+            max_id.unwrap_or(0),
+            text_len as i64
+        ))
+    }
+
+    /// Find the cached answer closest to a question, if any is at least `threshold` similar
+    ///
+    /// Only answers embedded with `model` against the current index state
+    /// are considered.
+    pub fn lookup_answer(
+        &self,
+        session_id: &str,
+        embedding: &[f32],
+        model: &str,
+        threshold: f32,
+    ) -> Result<Option<CachedAnswer>> {
+        let state = self.index_state(session_id)?;
+        let conn = self.database.get_conn()?;
+        let candidates: Vec<(i64, String, String, Vec<u8>, i64, i64)> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, question, answer, embedding, created_at, hits
+                 FROM answer_cache
+                 WHERE session_id = ?1 AND model = ?2 AND index_state = ?3",
+            )?;
+            let rows = stmt.query_map(params![session_id, model, &state], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+
+        let best = candidates
+            .into_iter()
+            .map(|(id, question, answer, vector, created_at, hits)| {
+                let similarity = cosine(embedding, &decode_vector(&vector));
+                CachedAnswer {
+                    id,
+                    question,
+                    answer,
+                    similarity,
+                    created_at,
+                    hits,
+                }
+            })
+            .filter(|cached| cached.similarity >= threshold)
+            .max_by(|a, b| a.similarity.total_cmp(&b.similarity));
+
+        let Some(mut best) = best else {
+            return Ok(None);
+        };
+        conn.execute(
+            "UPDATE answer_cache SET hits = hits + 1 WHERE id = ?1",
+            params![best.id],
+        )?;
+        best.hits += 1;
+        Ok(Some(best))
+    }
+
+    /// Store an answer for later questions, keyed to the current index state
+    pub fn cache_answer(
+        &self,
+        session_id: &str,
+        question: &str,
+        embedding: &[f32],
+        model: &str,
+        answer: &str,
+    ) -> Result<i64> {
+        let state = self.index_state(session_id)?;
+        let conn = self.database.get_conn()?;
+        // Answers built from an older index can never be served again
+        conn.execute(
+            "DELETE FROM answer_cache WHERE session_id = ?1 AND index_state != ?2",
+            params![session_id, &state],
+        )?;
+        conn.execute(
+            "INSERT INTO answer_cache
+             (session_id, question, embedding, model, answer, index_state, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                session_id,
+                question,
+                encode_vector(embedding),
+                model,
+                answer,
+                &state,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_answer_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let add_chunk = |text: &str| {
+            capture("s1", "nmap", b"").chunks(&[text]).insert(&storage);
+        };
+        add_chunk("22/tcp open ssh");
+
+        let question = [1.0, 0.0, 0.2];
+        storage
+            .cache_answer("s1", "which ports are open?", &question, "m", "22/tcp")
+            .unwrap();
+
+        let hit = storage
+            .lookup_answer("s1", &[0.98, 0.05, 0.2], "m", 0.95)
+            .unwrap()
+            .unwrap();
+        assert_eq!(hit.answer, "22/tcp");
+        assert_eq!(hit.hits, 1);
+        assert!(hit.similarity > 0.95);
+
+        // Unrelated questions, other models and other sessions miss
+        assert!(storage
+            .lookup_answer("s1", &[0.0, 1.0, 0.0], "m", 0.95)
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .lookup_answer("s1", &question, "other", 0.95)
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .lookup_answer("s2", &question, "m", 0.95)
+            .unwrap()
+            .is_none());
+
+        // New output invalidates the answer
+        add_chunk("80/tcp open http");
+        assert!(storage
+            .lookup_answer("s1", &question, "m", 0.95)
+            .unwrap()
+            .is_none());
+    }
+}
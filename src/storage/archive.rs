@@ -0,0 +1,206 @@
+//! Session cold storage
+//!
+//! Archiving recompresses a session's blobs at the maximum zstd level and
+//! drops its entries from the search indexes. The vector index isn't
+//! persisted; a process searching one archives through
+//! `HybridSearcher::archive_session` so the vectors are tombstoned too.
+//! Unarchiving puts the chunks back into the keyword index and leaves
+//! embeddings to be regenerated by the embedding backlog.
+
+use super::StorageManager;
+use crate::embedding::{KeywordIndex, KeywordIndexError};
+use crate::error::{Result, YinxError};
+
+/// Outcome of archiving or unarchiving a session
+#[derive(Debug, Default)]
+pub struct ArchiveStats {
+    /// Blobs that were recompressed
+    pub blobs_recompressed: usize,
+    /// On-disk blob bytes before recompression
+    pub bytes_before: u64,
+    /// On-disk blob bytes after recompression
+    pub bytes_after: u64,
+    /// Chunks removed from or restored to the keyword index
+    pub chunks_indexed: usize,
+    /// Embeddings dropped from the database
+    pub embeddings_dropped: usize,
+    /// IDs of the chunks taken out of search, for dropping them from indexes
+    pub chunk_ids: Vec<u64>,
+}
+
+impl ArchiveStats {
+    /// Bytes saved by recompression
+    pub fn bytes_saved(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+impl StorageManager {
+    /// Move a session into cold storage
+    pub fn archive_session(&self, session_id: &str) -> Result<ArchiveStats> {
+        let stats = self.archive_session_data(session_id)?;
+        if stats.chunk_ids.is_empty() {
+            return Ok(stats);
+        }
+
+        let mut index = self.open_keyword_index()?;
+        for id in &stats.chunk_ids {
+            index.delete(*id).map_err(|e| YinxError::Other(e.into()))?;
+        }
+        index.commit().map_err(|e| YinxError::Other(e.into()))?;
+        Ok(stats)
+    }
+
+    /// Recompress a session's blobs and mark it archived, leaving its
+    /// chunks in the indexes
+    ///
+    /// For callers that hold the indexes open themselves; the chunks to
+    /// drop are in the stats.
+    pub fn archive_session_data(&self, session_id: &str) -> Result<ArchiveStats> {
+        let mut stats = ArchiveStats::default();
+        let level = *zstd::compression_level_range().end();
+
+        let hashes = self.database.get_session_blob_hashes(session_id)?;
+        let mut compressed = Vec::new();
+        for hash in &hashes {
+            if !self.blob_store.exists(hash) {
+                tracing::warn!("Blob {} missing while archiving {}", hash, session_id);
+                continue;
+            }
+            let (before, after) = self.blob_store.recompress(hash, level)?;
+            stats.bytes_before += before;
+            stats.bytes_after += after;
+            if after < before {
+                stats.blobs_recompressed += 1;
+                compressed.push(hash.clone());
+            }
+        }
+        self.database.mark_blobs_compressed(&compressed)?;
+
+        stats.chunk_ids = self
+            .database
+            .get_session_chunks(session_id)?
+            .iter()
+            .map(|chunk| chunk.id as u64)
+            .collect();
+        stats.chunks_indexed = stats.chunk_ids.len();
+
+        stats.embeddings_dropped = self.database.delete_session_embeddings(session_id)?;
+        self.database.set_session_archived(session_id, true)?;
+
+        Ok(stats)
+    }
+
+    /// Restore an archived session to a queryable state
+    pub fn unarchive_session(&self, session_id: &str) -> Result<ArchiveStats> {
+        let mut stats = ArchiveStats::default();
+
+        let chunks = self.database.get_session_chunks(session_id)?;
+        if !chunks.is_empty() {
+            let mut index = self.open_keyword_index()?;
+            let items: Vec<(u64, String, Option<String>)> = chunks
+                .iter()
+                .map(|c| (c.id as u64, c.representative_text.clone(), c.host()))
+                .collect();
+            // Delete first so a partially archived session doesn't end up duplicated
+            for (id, _, _) in &items {
+                index.delete(*id).map_err(|e| YinxError::Other(e.into()))?;
+            }
+            index
+                .insert_hosted_batch(&items)
+                .map_err(|e| YinxError::Other(e.into()))?;
+            index.commit().map_err(|e| YinxError::Other(e.into()))?;
+            stats.chunks_indexed = items.len();
+        }
+
+        self.database.set_session_archived(session_id, false)?;
+
+        Ok(stats)
+    }
+
+    pub(crate) fn open_keyword_index(&self) -> Result<KeywordIndex> {
+        KeywordIndex::with_tokenizers(
+            self.machine_zone().join("keywords"),
+            &self.keyword_tokenizers,
+        )
+        .map_err(|e| YinxError::Other(e.into()))
+    }
+
+    /// Open the chunk keyword index for searching, without its writer
+    ///
+    /// None when the index hasn't been built yet.
+    pub(crate) fn open_keyword_index_read_only(&self) -> Result<Option<KeywordIndex>> {
+        match KeywordIndex::open_read_only(self.machine_zone().join("keywords")) {
+            Ok(index) => Ok(Some(index)),
+            Err(KeywordIndexError::IndexNotFound(_)) => Ok(None),
+            Err(e) => Err(YinxError::Other(e.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    fn seed_session(storage: &StorageManager, session_id: &str) -> (String, i64) {
+        let data: Vec<u8> = (0..5000u32)
+            .flat_map(|i| format!("22/tcp open ssh OpenSSH 8.{}\n", i % 10).into_bytes())
+            .collect();
+        storage
+            .database
+            .ensure_session(session_id, session_id, 0, "stopped")
+            .unwrap();
+        let capture = capture(session_id, "nmap -sV 10.0.0.1", &data)
+            .chunks(&["22/tcp open ssh OpenSSH"])
+            .insert(storage);
+
+        (capture.hash, capture.chunk_ids[0])
+    }
+
+    #[test]
+    fn test_archive_and_unarchive() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let (hash, chunk_id) = seed_session(&storage, "s1");
+
+        let original = storage.blob_store.read(&hash).unwrap();
+        storage
+            .database
+            .insert_embedding(chunk_id, &[0u8; 16], "test-model")
+            .unwrap();
+
+        storage.unarchive_session("s1").unwrap();
+        assert_eq!(storage.open_keyword_index().unwrap().len(), 1);
+
+        let stats = storage.archive_session("s1").unwrap();
+        assert_eq!(stats.chunks_indexed, 1);
+        assert_eq!(stats.embeddings_dropped, 1);
+        assert!(storage.database.is_session_archived("s1").unwrap());
+        assert_eq!(storage.blob_store.read(&hash).unwrap(), original);
+        assert_eq!(storage.database.count_embeddings().unwrap(), 0);
+        // Not queued for embedding while archived
+        assert!(storage
+            .database
+            .get_chunks_without_embeddings()
+            .unwrap()
+            .is_empty());
+        assert_eq!(storage.database.embedding_backlog().unwrap().chunks, 0);
+
+        let stats = storage.unarchive_session("s1").unwrap();
+        assert_eq!(stats.chunks_indexed, 1);
+        assert!(!storage.database.is_session_archived("s1").unwrap());
+
+        let index = storage.open_keyword_index().unwrap();
+        assert_eq!(index.len(), 1);
+        assert_eq!(
+            storage
+                .database
+                .get_chunks_without_embeddings()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Networks discovered addresses belong to
+//!
+//! The daemon's ASN pass looks each address captures mention up in a local
+//! ASN database and records the result here, by address. Private and
+//! unrouted addresses are recorded without a network so they aren't looked
+//! up again until the database changes.
+
+use super::StorageManager;
+use crate::entities::AsnInfo;
+use crate::error::Result;
+use rusqlite::params;
+use std::collections::HashMap;
+
+impl StorageManager {
+    /// Addresses never annotated, or annotated before `annotated_before`
+    pub fn asn_candidates(&self, annotated_before: i64, limit: usize) -> Result<Vec<String>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT se.value FROM session_entities se
+             LEFT JOIN ip_asn a ON a.ip = se.value
+             WHERE se.type IN ('ip_address', 'ip_address_v6')
+                AND (a.ip IS NULL OR a.annotated_at < ?1)
+             ORDER BY se.value
+             LIMIT ?2",
+        )?;
+        let ips = stmt
+            .query_map(params![annotated_before, limit as i64], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(ips)
+    }
+
+    /// Record the network `ip` belongs to, None when it has none
+    pub fn store_asn(&self, ip: &str, asn: Option<&AsnInfo>, annotated_at: i64) -> Result<()> {
+        let conn = self.database.get_conn()?;
+        conn.execute(
+            "INSERT INTO ip_asn (ip, asn, org, country, annotated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(ip) DO UPDATE SET
+                asn = excluded.asn, org = excluded.org, country = excluded.country,
+                annotated_at = excluded.annotated_at",
+            params![
+                ip,
+                asn.map(|a| a.asn),
+                asn.map(|a| &a.org),
+                asn.map(|a| &a.country),
+                annotated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The network of every address that has one
+    pub fn asn_annotations(&self) -> Result<HashMap<String, AsnInfo>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT ip, asn, COALESCE(org, ''), COALESCE(country, '') FROM ip_asn
+             WHERE asn IS NOT NULL",
+        )?;
+        let annotations = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    AsnInfo {
+                        asn: row.get(1)?,
+                        org: row.get(2)?,
+                        country: row.get(3)?,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<HashMap<String, AsnInfo>, _>>()?;
+        Ok(annotations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_asn_annotations() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "s1", 0, "active")
+            .unwrap();
+        let conn = storage.database.get_conn().unwrap();
+        for (entity_type, value) in [
+            ("ip_address", "1.1.1.1"),
+            ("ip_address", "10.0.0.5"),
+            ("hostname", "one.one.one.one"),
+        ] {
+            conn.execute(
+                "INSERT INTO session_entities (session_id, type, value, occurrences, captures,
+                    max_confidence, first_seen, last_seen)
+                 VALUES ('s1', ?1, ?2, 1, 1, 1.0, 0, 0)",
+                params![entity_type, value],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        assert_eq!(
+            storage.asn_candidates(0, 10).unwrap(),
+            ["1.1.1.1", "10.0.0.5"]
+        );
+        let cloudflare = AsnInfo {
+            asn: 13335,
+            org: "CLOUDFLARENET".to_string(),
+            country: "US".to_string(),
+        };
+        storage
+            .store_asn("1.1.1.1", Some(&cloudflare), 100)
+            .unwrap();
+        storage.store_asn("10.0.0.5", None, 100).unwrap();
+        assert!(storage.asn_candidates(100, 10).unwrap().is_empty());
+        // A newer database annotates everything again
+        assert_eq!(storage.asn_candidates(200, 10).unwrap().len(), 2);
+
+        let annotations = storage.asn_annotations().unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations["1.1.1.1"], cloudflare);
+    }
+}
@@ -0,0 +1,101 @@
+//! Evidence attachments
+//!
+//! `yinx attach` copies screenshots, loot and other files into the session's
+//! `evidence/` directory in the human zone. Files are named by content hash
+//! so attaching the same file twice stores it once.
+
+use super::{AttachmentRecord, StorageManager};
+use crate::error::{Result, YinxError};
+use std::path::Path;
+
+/// Length of the hash prefix in stored file names
+const STORED_HASH_LEN: usize = 12;
+
+/// Session an attachment is filed under
+#[derive(Debug, Clone, Copy)]
+pub struct AttachTarget<'a> {
+    pub session_id: &'a str,
+    pub session_name: &'a str,
+    pub capture_id: Option<i64>,
+    pub finding_id: Option<i64>,
+}
+
+impl StorageManager {
+    /// Copy `source` into the session's evidence directory and record it
+    pub fn attach(&self, source: &Path, target: AttachTarget<'_>) -> Result<AttachmentRecord> {
+        let data = std::fs::read(source).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read attachment: {}", source.display()),
+        })?;
+        let filename = source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| YinxError::Other(anyhow::anyhow!("Not a file: {}", source.display())))?;
+
+        let hash = blake3::hash(&data).to_hex().to_string();
+        let stored_as = format!("{}-{}", &hash[..STORED_HASH_LEN], sanitize(&filename));
+        let evidence = self
+            .ensure_session_report_dir(target.session_name)?
+            .join("evidence");
+        let dest = evidence.join(&stored_as);
+        if !dest.exists() {
+            std::fs::write(&dest, &data).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to write attachment: {}", dest.display()),
+            })?;
+        }
+
+        self.database
+            .insert_attachment(target, &hash, &filename, &stored_as, data.len() as u64)
+    }
+}
+
+/// Keep stored names to a safe character set
+pub(super) fn sanitize(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_attach_dedupes_by_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "exam", 0, "active")
+            .unwrap();
+
+        let source = temp_dir.path().join("root flag.png");
+        std::fs::write(&source, b"not really a png").unwrap();
+        let target = AttachTarget {
+            session_id: "s1",
+            session_name: "exam",
+            capture_id: None,
+            finding_id: None,
+        };
+
+        let first = storage.attach(&source, target).unwrap();
+        let again = storage.attach(&source, target).unwrap();
+        assert_eq!(first.id, again.id);
+        assert_eq!(first.filename, "root flag.png");
+        assert!(first.stored_as.ends_with("-root_flag.png"));
+        assert!(first.markdown().starts_with("![root flag.png](evidence/"));
+
+        let stored = storage.session_report_dir("exam").join(first.report_path());
+        assert_eq!(std::fs::read(stored).unwrap(), b"not really a png");
+        assert_eq!(storage.database.list_attachments("s1").unwrap().len(), 1);
+    }
+}
@@ -4,9 +4,12 @@
 
 use crate::error::{Result, YinxError};
 use std::fs;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 
+/// First bytes of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
 /// Content-addressed blob storage
 pub struct BlobStore {
     base_path: PathBuf,
@@ -46,8 +49,45 @@ impl BlobStore {
         // Decide whether to compress
         let should_compress = self.compression_enabled && data.len() >= self.compression_threshold;
 
-        // Write to temporary file first (atomic write)
-        let temp_path = self.temp_path(&hash);
+        if should_compress {
+            // Compress with zstd
+            let compressed = zstd::encode_all(data, 3).map_err(|e| YinxError::Io {
+                source: e,
+                context: "Failed to compress blob data".to_string(),
+            })?;
+            self.write_atomic(&hash, &compressed)?;
+        } else {
+            self.write_atomic(&hash, data)?;
+        }
+
+        Ok((hash, should_compress, true))
+    }
+
+    /// Recompress an existing blob at the given zstd level
+    ///
+    /// Returns the on-disk size before and after. The recompressed copy is
+    /// only kept if it is smaller than what is already stored.
+    pub fn recompress(&self, hash: &str, level: i32) -> Result<(u64, u64)> {
+        let before = self.size(hash)?;
+        let data = self.read(hash)?;
+
+        let compressed = zstd::encode_all(&data[..], level).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to recompress blob: {}", hash),
+        })?;
+
+        if (compressed.len() as u64) >= before {
+            return Ok((before, before));
+        }
+
+        self.write_atomic(hash, &compressed)?;
+        Ok((before, compressed.len() as u64))
+    }
+
+    /// Write bytes to a temp file and atomically rename into place
+    fn write_atomic(&self, hash: &str, bytes: &[u8]) -> Result<()> {
+        let temp_path = self.temp_path(hash);
+        let blob_path = self.blob_path(hash);
         let parent = temp_path
             .parent()
             .ok_or_else(|| YinxError::Config("Invalid blob path".to_string()))?;
@@ -60,41 +100,16 @@ impl BlobStore {
             source: e,
             context: format!("Failed to create temp blob file: {}", temp_path.display()),
         })?;
-
-        if should_compress {
-            // Compress with zstd
-            let compressed = zstd::encode_all(data, 3).map_err(|e| YinxError::Io {
-                source: e,
-                context: "Failed to compress blob data".to_string(),
-            })?;
-            file.write_all(&compressed).map_err(|e| YinxError::Io {
-                source: e,
-                context: format!("Failed to write compressed blob: {}", temp_path.display()),
-            })?;
-        } else {
-            file.write_all(data).map_err(|e| YinxError::Io {
-                source: e,
-                context: format!("Failed to write blob data: {}", temp_path.display()),
-            })?;
-        }
-
+        file.write_all(bytes).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write blob data: {}", temp_path.display()),
+        })?;
         file.sync_all().map_err(|e| YinxError::Io {
             source: e,
             context: format!("Failed to sync blob file: {}", temp_path.display()),
         })?;
         drop(file);
 
-        // Atomically rename to final location
-        let final_parent = blob_path
-            .parent()
-            .ok_or_else(|| YinxError::Config("Invalid blob path".to_string()))?;
-        fs::create_dir_all(final_parent).map_err(|e| YinxError::Io {
-            source: e,
-            context: format!(
-                "Failed to create blob parent directory: {}",
-                final_parent.display()
-            ),
-        })?;
         fs::rename(&temp_path, &blob_path).map_err(|e| YinxError::Io {
             source: e,
             context: format!(
@@ -104,7 +119,7 @@ impl BlobStore {
             ),
         })?;
 
-        Ok((hash, should_compress, true))
+        Ok(())
     }
 
     /// Read data from blob storage
@@ -132,6 +147,23 @@ impl BlobStore {
         }
     }
 
+    /// Stream data from blob storage, decompressing as it's read
+    pub fn reader(&self, hash: &str) -> Result<Box<dyn BufRead + Send>> {
+        let blob_path = self.blob_path(hash);
+        let io_error = |e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read blob file: {}", blob_path.display()),
+        };
+
+        let mut file = BufReader::new(fs::File::open(&blob_path).map_err(io_error)?);
+        if file.fill_buf().map_err(io_error)?.starts_with(&ZSTD_MAGIC) {
+            let decoder = zstd::Decoder::with_buffer(file).map_err(io_error)?;
+            Ok(Box::new(BufReader::new(decoder)))
+        } else {
+            Ok(Box::new(file))
+        }
+    }
+
     /// Check if a blob exists
     pub fn exists(&self, hash: &str) -> bool {
         self.blob_path(hash).exists()
@@ -339,6 +371,34 @@ mod tests {
 
         let read_data = store.read(&hash).unwrap();
         assert_eq!(data, read_data);
+
+        let mut streamed = Vec::new();
+        store
+            .reader(&hash)
+            .unwrap()
+            .read_to_end(&mut streamed)
+            .unwrap();
+        assert_eq!(data, streamed);
+    }
+
+    #[test]
+    fn test_blob_recompress() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = BlobStore::new(temp_dir.path().to_path_buf(), 1024).unwrap();
+
+        let data: Vec<u8> = (0..20_000u32)
+            .flat_map(|i| format!("line {} open port {}\n", i % 97, i % 13).into_bytes())
+            .collect();
+        let (hash, _, _) = store.write(&data).unwrap();
+
+        let (before, after) = store.recompress(&hash, 19).unwrap();
+        assert!(after <= before);
+        assert_eq!(store.read(&hash).unwrap(), data);
+
+        // Small uncompressed blobs stay readable as well
+        let (small, _, _) = store.write(b"tiny").unwrap();
+        store.recompress(&small, 19).unwrap();
+        assert_eq!(store.read(&small).unwrap(), b"tiny");
     }
 
     #[test]
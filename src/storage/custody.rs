@@ -0,0 +1,200 @@
+//! Evidence chain of custody
+//!
+//! A report written with `--include-evidence` gets a `manifest.json` in the
+//! session's report directory. It lists every evidence file with its BLAKE3
+//! hash as read back from disk, the capture it came from, when that capture
+//! ran and when the file was attached, and the yinx version that wrote it.
+//! Signing the manifest with minisign lets whoever receives the report check
+//! that neither the manifest nor the files it hashes changed afterwards.
+
+use super::StorageManager;
+use crate::error::{Result, YinxError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Manifest file name, in the session's report directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceManifest {
+    pub yinx_version: String,
+    pub session_id: String,
+    pub session_name: String,
+    pub generated_at: i64,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// One evidence file and where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Relative to the session's report directory
+    pub path: String,
+    /// Name of the file when it was attached
+    pub filename: String,
+    /// Hash of the file as it is now; None when it is missing
+    pub blake3: Option<String>,
+    /// Hash recorded when the file was attached
+    pub recorded_blake3: String,
+    pub size: u64,
+    pub capture_id: Option<i64>,
+    /// When the source capture ran
+    pub captured_at: Option<i64>,
+    pub command: Option<String>,
+    pub finding_id: Option<i64>,
+    pub attached_at: i64,
+}
+
+impl ManifestEntry {
+    /// Whether the file on disk is still the one that was attached
+    pub fn intact(&self) -> bool {
+        self.blake3.as_deref() == Some(self.recorded_blake3.as_str())
+    }
+}
+
+impl EvidenceManifest {
+    /// Files that are missing or no longer match their recorded hash
+    pub fn tampered(&self) -> Vec<&ManifestEntry> {
+        self.files.iter().filter(|f| !f.intact()).collect()
+    }
+}
+
+impl StorageManager {
+    /// Hash a session's evidence files and trace each back to its capture
+    pub fn evidence_manifest(
+        &self,
+        session_id: &str,
+        session_name: &str,
+    ) -> Result<EvidenceManifest> {
+        let report_dir = self.session_report_dir(session_name);
+        let mut files = Vec::new();
+        for attachment in self.database.list_attachments(session_id)? {
+            let path = attachment.report_path();
+            let blake3 = std::fs::read(report_dir.join(&path))
+                .ok()
+                .map(|data| blake3::hash(&data).to_hex().to_string());
+            let capture = match attachment.capture_id {
+                Some(id) => self.database.get_capture(id)?,
+                None => None,
+            };
+            files.push(ManifestEntry {
+                path,
+                filename: attachment.filename,
+                blake3,
+                recorded_blake3: attachment.hash,
+                size: attachment.size,
+                capture_id: attachment.capture_id,
+                captured_at: capture.as_ref().map(|c| c.timestamp),
+                command: capture.and_then(|c| c.command),
+                finding_id: attachment.finding_id,
+                attached_at: attachment.created_at,
+            });
+        }
+        Ok(EvidenceManifest {
+            yinx_version: env!("CARGO_PKG_VERSION").to_string(),
+            session_id: session_id.to_string(),
+            session_name: session_name.to_string(),
+            generated_at: chrono::Utc::now().timestamp(),
+            files,
+        })
+    }
+
+    /// Write the manifest into the session's report directory
+    pub fn write_evidence_manifest(&self, manifest: &EvidenceManifest) -> Result<PathBuf> {
+        let path = self
+            .ensure_session_report_dir(&manifest.session_name)?
+            .join(MANIFEST_FILE);
+        let json = serde_json::to_string_pretty(manifest).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to serialize evidence manifest".to_string(),
+        })?;
+        std::fs::write(&path, json).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write evidence manifest: {}", path.display()),
+        })?;
+        Ok(path)
+    }
+}
+
+/// Sign `manifest` with a minisign secret key, returning the `.minisig` path
+///
+/// minisign prompts for the key's password on the terminal when it has one.
+pub fn sign_manifest(manifest: &Path, secret_key: &Path) -> Result<PathBuf> {
+    let signature = PathBuf::from(format!("{}.minisig", manifest.display()));
+    let status = Command::new("minisign")
+        .arg("-S")
+        .arg("-s")
+        .arg(secret_key)
+        .arg("-m")
+        .arg(manifest)
+        .arg("-x")
+        .arg(&signature)
+        .arg("-t")
+        .arg(format!(
+            "yinx {} evidence manifest",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .status()
+        .map_err(|e| {
+            YinxError::Other(anyhow::anyhow!(
+                "Failed to run minisign ({}); install it to sign evidence manifests",
+                e
+            ))
+        })?;
+    if !status.success() {
+        return Err(YinxError::Other(anyhow::anyhow!(
+            "minisign failed to sign {}: {}",
+            manifest.display(),
+            status
+        )));
+    }
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::AttachTarget;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_detects_changed_evidence() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "exam", 0, "active")
+            .unwrap();
+        let source = temp_dir.path().join("proof.png");
+        std::fs::write(&source, b"root flag screenshot").unwrap();
+        let attachment = storage
+            .attach(
+                &source,
+                AttachTarget {
+                    session_id: "s1",
+                    session_name: "exam",
+                    capture_id: None,
+                    finding_id: None,
+                },
+            )
+            .unwrap();
+
+        let manifest = storage.evidence_manifest("s1", "exam").unwrap();
+        assert_eq!(manifest.yinx_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(
+            manifest.files[0].blake3.as_deref(),
+            Some(blake3::hash(b"root flag screenshot").to_hex().as_str())
+        );
+        assert!(manifest.tampered().is_empty());
+        let path = storage.write_evidence_manifest(&manifest).unwrap();
+        assert_eq!(path, storage.session_report_dir("exam").join(MANIFEST_FILE));
+
+        let stored = storage
+            .session_report_dir("exam")
+            .join(attachment.report_path());
+        std::fs::write(&stored, b"edited afterwards").unwrap();
+        let manifest = storage.evidence_manifest("s1", "exam").unwrap();
+        assert_eq!(manifest.tampered().len(), 1);
+    }
+}
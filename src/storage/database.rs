@@ -2,24 +2,128 @@
 //!
 //! Provides structured storage for sessions, captures, and metadata
 
+use crate::board::HostStage;
+use crate::daemon::CaptureContext;
 use crate::error::{Result, YinxError};
+use crate::filtering::FilterStats;
+use crate::patterns::{DetectionMethod, HostSection, ToolArguments};
+use crate::storage::{latency, AttachTarget};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
 use std::path::Path;
+use std::sync::RwLock;
+use std::time::Duration;
 
 /// Database connection pool
 pub type DbPool = Pool<SqliteConnectionManager>;
 
+/// Options every database opened from here on uses; see [`configure`]
+static OPTIONS: RwLock<Option<SqliteOptions>> = RwLock::new(None);
+
+/// Connection pool size and per-connection SQLite settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteOptions {
+    /// Read connections; there is always exactly one write connection
+    pub pool_size: u32,
+    pub busy_timeout: Duration,
+    /// WAL pages between automatic checkpoints
+    pub wal_autocheckpoint: u32,
+    /// Bytes of the database file to memory-map
+    pub mmap_size: u64,
+    /// Bytes of page cache per connection
+    pub cache_size: u64,
+}
+
+impl SqliteOptions {
+    /// Settings for a data directory on flash storage
+    pub fn ssd() -> Self {
+        Self {
+            pool_size: 8,
+            busy_timeout: Duration::from_secs(5),
+            wal_autocheckpoint: 1000,
+            mmap_size: 256 << 20,
+            cache_size: 16 << 20,
+        }
+    }
+
+    /// Settings for a data directory on a spinning disk
+    pub fn hdd() -> Self {
+        Self {
+            pool_size: 4,
+            busy_timeout: Duration::from_secs(15),
+            wal_autocheckpoint: 4000,
+            mmap_size: 64 << 20,
+            cache_size: 32 << 20,
+        }
+    }
+
+    /// PRAGMAs applied to each connection as the pool opens it
+    fn connection_pragmas(&self) -> String {
+        format!(
+            "PRAGMA busy_timeout = {};\n\
+             PRAGMA mmap_size = {};\n\
+             PRAGMA cache_size = -{};\n",
+            self.busy_timeout.as_millis(),
+            self.mmap_size,
+            // A negative cache_size is in KiB rather than pages
+            (self.cache_size / 1024).max(1),
+        )
+    }
+
+    fn pool(&self, manager: SqliteConnectionManager, size: u32) -> Result<DbPool> {
+        // Queueing for the write connection is waiting on a lock like any other
+        Pool::builder()
+            .max_size(size)
+            .connection_timeout(self.busy_timeout)
+            .build(manager)
+            .map_err(|e| YinxError::Config(format!("Failed to create connection pool: {}", e)))
+    }
+}
+
+impl Default for SqliteOptions {
+    fn default() -> Self {
+        Self::ssd()
+    }
+}
+
+/// Open every database from here on with `options`
+///
+/// Called once the config is loaded; databases opened before then, and in
+/// processes that never call it, use [`SqliteOptions::default`].
+pub fn configure(options: SqliteOptions) {
+    *OPTIONS.write().unwrap_or_else(|e| e.into_inner()) = Some(options);
+}
+
+fn configured() -> SqliteOptions {
+    OPTIONS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
 /// Database manager with migration support
+///
+/// SQLite in WAL mode allows one writer at a time, and a pool of writers
+/// only turns that into `SQLITE_BUSY` under load. Writes therefore share a
+/// single connection, which callers queue for in the pool, while queries
+/// get a pool of their own that never waits on the writer.
 #[derive(Clone)]
 pub struct Database {
-    pool: DbPool,
+    writer: DbPool,
+    readers: DbPool,
 }
 
 impl Database {
-    /// Create a new database connection
+    /// Create a new database connection with the [`configure`]d options
     pub fn new(db_path: &Path) -> Result<Self> {
+        Self::with_options(db_path, &configured())
+    }
+
+    /// Create a new database connection with explicit pool and SQLite options
+    pub fn with_options(db_path: &Path, options: &SqliteOptions) -> Result<Self> {
         // Ensure parent directory exists
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent).map_err(|e| YinxError::Io {
@@ -28,33 +132,31 @@ impl Database {
             })?;
         }
 
-        // Create connection manager
-        let manager = SqliteConnectionManager::file(db_path);
+        // Connection settings don't persist, so each pooled connection gets them
+        let pragmas = format!(
+            "{}PRAGMA synchronous = NORMAL;\n\
+             PRAGMA foreign_keys = ON;\n\
+             PRAGMA wal_autocheckpoint = {};\n",
+            options.connection_pragmas(),
+            options.wal_autocheckpoint
+        );
+        let manager =
+            SqliteConnectionManager::file(db_path).with_init(move |c| c.execute_batch(&pragmas));
+        let writer = options.pool(manager, 1)?;
 
-        // Build pool with configuration
-        let pool = Pool::builder()
-            .max_size(16) // Max 16 connections
-            .build(manager)
-            .map_err(|e| YinxError::Config(format!("Failed to create connection pool: {}", e)))?;
+        // WAL mode is stored in the database file, so setting it once is enough
+        writer
+            .get()
+            .map_err(|e| YinxError::Config(format!("Failed to get connection: {}", e)))?
+            .execute_batch("PRAGMA journal_mode = WAL;")?;
 
-        // Configure connection
-        {
-            let conn = pool
-                .get()
-                .map_err(|e| YinxError::Config(format!("Failed to get connection: {}", e)))?;
-
-            // Enable WAL mode for better concurrency
-            conn.execute_batch(
-                "
-                PRAGMA journal_mode = WAL;
-                PRAGMA synchronous = NORMAL;
-                PRAGMA foreign_keys = ON;
-                PRAGMA busy_timeout = 5000;
-                ",
-            )?;
-        }
+        // query_only turns a write sent to a reader by mistake into an error
+        let pragmas = format!("{}PRAGMA query_only = ON;\n", options.connection_pragmas());
+        let manager =
+            SqliteConnectionManager::file(db_path).with_init(move |c| c.execute_batch(&pragmas));
+        let readers = options.pool(manager, options.pool_size)?;
 
-        let db = Self { pool };
+        let db = Self { writer, readers };
 
         // Run migrations
         db.migrate()?;
@@ -62,13 +164,87 @@ impl Database {
         Ok(db)
     }
 
-    /// Get a connection from the pool
+    /// Open an existing database without writing to it
+    ///
+    /// Migrations can't run on a read-only connection, so the store has to
+    /// be at the schema version this build expects.
+    pub fn open_read_only(db_path: &Path) -> Result<Self> {
+        if !db_path.is_file() {
+            return Err(YinxError::Config(format!(
+                "No yinx database at {}",
+                db_path.display()
+            )));
+        }
+        let options = configured();
+        let pragmas = options.connection_pragmas();
+        let manager = SqliteConnectionManager::file(db_path)
+            .with_flags(
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
+                    | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )
+            .with_init(move |conn| conn.execute_batch(&pragmas));
+        let readers = options.pool(manager, options.pool_size)?;
+        // Writes fail on these connections anyway
+        let db = Self {
+            writer: readers.clone(),
+            readers,
+        };
+
+        let version: i32 = db
+            .get_conn()?
+            .query_row(
+                "SELECT COALESCE(MAX(version), 0) FROM _migrations",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+        if version != MIGRATIONS.len() as i32 {
+            return Err(YinxError::Config(format!(
+                "Database {} is at schema version {}, this yinx expects {}; open it writable \
+                 with a matching yinx first",
+                db_path.display(),
+                version,
+                MIGRATIONS.len()
+            )));
+        }
+        Ok(db)
+    }
+
+    /// Get the write connection, waiting for whoever holds it
+    ///
+    /// Don't hold it across a call to another method that writes; that
+    /// waits on itself until the pool times out.
     pub fn get_conn(&self) -> Result<r2d2::PooledConnection<SqliteConnectionManager>> {
-        self.pool
+        self.writer
+            .get()
+            .map_err(|e| YinxError::Config(format!("Failed to get connection: {}", e)))
+    }
+
+    /// Get a connection for queries; writes on it fail
+    pub fn read_conn(&self) -> Result<r2d2::PooledConnection<SqliteConnectionManager>> {
+        self.readers
             .get()
             .map_err(|e| YinxError::Config(format!("Failed to get connection: {}", e)))
     }
 
+    /// Copy the WAL into the database file and sync it to disk
+    ///
+    /// Runs from a panicking daemon, where the thread that panicked may be
+    /// holding the write connection, so this borrows a reader instead and
+    /// gives up after a couple of seconds if none is free.
+    pub fn checkpoint(&self) -> Result<()> {
+        let conn = self
+            .readers
+            .get_timeout(Duration::from_secs(2))
+            .map_err(|e| YinxError::Config(format!("Failed to get connection: {}", e)))?;
+        conn.execute_batch(
+            "PRAGMA query_only = OFF;
+             PRAGMA wal_checkpoint(TRUNCATE);
+             PRAGMA query_only = ON;",
+        )?;
+        Ok(())
+    }
+
     /// Run database migrations
     fn migrate(&self) -> Result<()> {
         let conn = self.get_conn()?;
@@ -114,7 +290,8 @@ impl Database {
 
     /// Get database statistics
     pub fn stats(&self) -> Result<DbStats> {
-        let conn = self.get_conn()?;
+        let _timer = latency::timer("db.stats", String::new);
+        let conn = self.read_conn()?;
 
         let session_count: i64 =
             conn.query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))?;
@@ -146,196 +323,1191 @@ impl Database {
     }
 
     /// Insert entities for a capture
+    ///
+    /// Repeats of the same type and value are coalesced into one row per
+    /// capture and one per session. Returns the number of occurrences recorded.
     pub fn insert_entities(
         &self,
         capture_id: i64,
         entities: &[(String, String, String, f32)], // (type, value, context, confidence)
     ) -> Result<usize> {
-        let conn = self.get_conn()?;
-        let mut inserted = 0;
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
 
         for (entity_type, value, context, confidence) in entities {
-            conn.execute(
-                "INSERT INTO entities (capture_id, type, value, context, confidence)
-                 VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![capture_id, entity_type, value, context, confidence],
+            upsert_entity(
+                &tx,
+                capture_id,
+                entity_type,
+                value,
+                Some(context.as_str()),
+                *confidence,
+                1,
             )?;
-            inserted += 1;
         }
 
-        Ok(inserted)
+        tx.commit()?;
+        Ok(entities.len())
     }
 
-    /// Query entities by capture ID
-    pub fn get_entities_for_capture(&self, capture_id: i64) -> Result<Vec<EntityRecord>> {
-        let conn = self.get_conn()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, capture_id, type, value, context, confidence
-             FROM entities WHERE capture_id = ?1",
+    /// Replace the per-host sections recorded for a multi-host capture
+    pub fn set_subcaptures(&self, capture_id: i64, sections: &[HostSection]) -> Result<()> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM subcaptures WHERE capture_id = ?1",
+            params![capture_id],
         )?;
+        for section in sections {
+            tx.execute(
+                "INSERT INTO subcaptures
+                 (capture_id, host, start_line, end_line, start_offset, end_offset)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    capture_id,
+                    section.host,
+                    section.lines.start as i64,
+                    section.lines.end as i64,
+                    section.bytes.start as i64,
+                    section.bytes.end as i64
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
 
-        let entities = stmt
+    /// Per-host sections of a capture in output order; empty unless it was split
+    pub fn get_subcaptures(&self, capture_id: i64) -> Result<Vec<HostSection>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT host, start_line, end_line, start_offset, end_offset
+             FROM subcaptures WHERE capture_id = ?1 ORDER BY start_line",
+        )?;
+        let sections = stmt
             .query_map([capture_id], |row| {
-                Ok(EntityRecord {
-                    id: row.get(0)?,
-                    capture_id: row.get(1)?,
-                    entity_type: row.get(2)?,
-                    value: row.get(3)?,
-                    context: row.get(4)?,
-                    confidence: row.get(5)?,
+                let position = |i| row.get::<_, i64>(i).map(|v| v as usize);
+                Ok(HostSection {
+                    host: row.get(0)?,
+                    lines: position(1)?..position(2)?,
+                    bytes: position(3)?..position(4)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(sections)
+    }
 
-        Ok(entities)
+    /// Record where each entity first appears in its capture's output
+    ///
+    /// Positions already recorded are kept, so the first occurrence wins.
+    pub fn set_entity_positions(
+        &self,
+        capture_id: i64,
+        positions: &[EntityPosition],
+    ) -> Result<()> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        for position in positions {
+            tx.execute(
+                "UPDATE entities SET start_offset = ?4, end_offset = ?5, line = ?6
+                 WHERE capture_id = ?1 AND type = ?2 AND value = ?3 AND start_offset IS NULL",
+                params![
+                    capture_id,
+                    position.entity_type,
+                    position.value,
+                    position.start as i64,
+                    position.end as i64,
+                    position.line as i64
+                ],
+            )?;
+            if let Some(host) = &position.host {
+                tx.execute(
+                    "UPDATE entities SET host = ?4
+                     WHERE capture_id = ?1 AND type = ?2 AND value = ?3 AND host IS NULL",
+                    params![capture_id, position.entity_type, position.value, host],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
     }
 
-    /// Query entities by type
-    pub fn get_entities_by_type(&self, entity_type: &str) -> Result<Vec<EntityRecord>> {
+    /// Swap a capture's entities for a freshly extracted set
+    ///
+    /// Session aggregates of every value that was removed or added are
+    /// rebuilt from the remaining rows.
+    pub fn replace_capture_entities(
+        &self,
+        capture_id: i64,
+        entities: &[(String, String, String, f32)], // (type, value, context, confidence)
+    ) -> Result<()> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+
+        let session_id: String = tx.query_row(
+            "SELECT session_id FROM captures WHERE id = ?1",
+            params![capture_id],
+            |row| row.get(0),
+        )?;
+        let mut keys: Vec<(String, String)> = {
+            let mut stmt = tx.prepare("SELECT type, value FROM entities WHERE capture_id = ?1")?;
+            let rows = stmt.query_map(params![capture_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        tx.execute(
+            "DELETE FROM entities WHERE capture_id = ?1",
+            params![capture_id],
+        )?;
+
+        for (entity_type, value, context, confidence) in entities {
+            tx.execute(
+                "INSERT INTO entities (capture_id, type, value, context, confidence, occurrences)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 1)
+                 ON CONFLICT (capture_id, type, value) DO UPDATE SET
+                     occurrences = occurrences + 1,
+                     confidence = MAX(confidence, excluded.confidence)",
+                params![capture_id, entity_type, value, context, confidence],
+            )?;
+            keys.push((entity_type.clone(), value.clone()));
+        }
+        keys.sort();
+        keys.dedup();
+
+        for (entity_type, value) in &keys {
+            tx.execute(
+                "DELETE FROM session_entities WHERE session_id = ?1 AND type = ?2 AND value = ?3",
+                params![&session_id, entity_type, value],
+            )?;
+            tx.execute(
+                "INSERT INTO session_entities
+                 (session_id, type, value, occurrences, captures, max_confidence, first_seen, last_seen)
+                 SELECT c.session_id, e.type, e.value, SUM(e.occurrences), COUNT(*),
+                        MAX(e.confidence), MIN(c.timestamp), MAX(c.timestamp)
+                 FROM entities e JOIN captures c ON c.id = e.capture_id
+                 WHERE c.session_id = ?1 AND e.type = ?2 AND e.value = ?3
+                 GROUP BY c.session_id, e.type, e.value",
+                params![&session_id, entity_type, value],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Store the JSON a hook returned for a capture
+    pub fn insert_annotation(
+        &self,
+        capture_id: i64,
+        hook: &str,
+        stage: &str,
+        data: &str,
+    ) -> Result<()> {
         let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT INTO annotations (capture_id, hook, stage, data, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                capture_id,
+                hook,
+                stage,
+                data,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Hook annotations of a capture, oldest first
+    pub fn get_annotations_for_capture(&self, capture_id: i64) -> Result<Vec<AnnotationRecord>> {
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
-            "SELECT id, capture_id, type, value, context, confidence
-             FROM entities WHERE type = ?1",
+            "SELECT hook, stage, data, created_at FROM annotations
+             WHERE capture_id = ?1 ORDER BY id",
         )?;
 
-        let entities = stmt
-            .query_map([entity_type], |row| {
-                Ok(EntityRecord {
-                    id: row.get(0)?,
-                    capture_id: row.get(1)?,
-                    entity_type: row.get(2)?,
-                    value: row.get(3)?,
-                    context: row.get(4)?,
-                    confidence: row.get(5)?,
+        let annotations = stmt
+            .query_map([capture_id], |row| {
+                Ok(AnnotationRecord {
+                    hook: row.get(0)?,
+                    stage: row.get(1)?,
+                    data: row.get(2)?,
+                    created_at: row.get(3)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        Ok(entities)
+        Ok(annotations)
     }
 
-    /// Insert an embedding for a chunk
+    /// Fold a re-run into the latest identical capture seen since `since`
     ///
-    /// # Arguments
-    /// * `chunk_id` - Chunk ID (foreign key to chunks table)
-    /// * `vector` - Embedding vector as bytes (serialized f32 array)
-    /// * `model` - Model name used for embedding
-    pub fn insert_embedding(&self, chunk_id: i64, vector: &[u8], model: &str) -> Result<()> {
+    /// A capture is identical when its command and output hash match. Returns
+    /// the id of the capture that absorbed the repeat, if there was one.
+    pub fn coalesce_repeat(
+        &self,
+        session_id: &str,
+        command: &str,
+        output_hash: &str,
+        at: i64,
+        since: i64,
+    ) -> Result<Option<i64>> {
         let conn = self.get_conn()?;
-        let now = chrono::Utc::now().timestamp();
+        let capture_id = conn
+            .query_row(
+                "SELECT id FROM captures
+                 WHERE session_id = ?1 AND command = ?2 AND output_hash = ?3
+                   AND COALESCE(last_repeated_at, timestamp) >= ?4
+                 ORDER BY id DESC LIMIT 1",
+                params![session_id, command, output_hash, since],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        if let Some(id) = capture_id {
+            conn.execute(
+                "UPDATE captures SET repeat_count = repeat_count + 1, last_repeated_at = ?2
+                 WHERE id = ?1",
+                params![id, at],
+            )?;
+        }
+        Ok(capture_id)
+    }
 
+    /// Tag a capture; tagging twice is a no-op
+    pub fn add_capture_tag(&self, capture_id: i64, tag: &str) -> Result<()> {
+        let conn = self.get_conn()?;
         conn.execute(
-            "INSERT OR REPLACE INTO embeddings (chunk_id, vector, model, created_at)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![chunk_id, vector, model, now],
+            "INSERT OR IGNORE INTO capture_tags (capture_id, tag) VALUES (?1, ?2)",
+            params![capture_id, tag],
         )?;
-
         Ok(())
     }
 
-    /// Insert multiple embeddings in batch
-    pub fn insert_embeddings_batch(
-        &self,
-        embeddings: &[(i64, Vec<u8>, String)], // (chunk_id, vector, model)
-    ) -> Result<usize> {
+    /// Store the environment a capture's command ran in
+    pub fn insert_capture_context(&self, capture_id: i64, context: &CaptureContext) -> Result<()> {
+        let env = serde_json::to_string(&context.env).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to serialize capture environment".to_string(),
+        })?;
         let conn = self.get_conn()?;
-        let now = chrono::Utc::now().timestamp();
-        let mut inserted = 0;
-
-        for (chunk_id, vector, model) in embeddings {
-            conn.execute(
-                "INSERT OR REPLACE INTO embeddings (chunk_id, vector, model, created_at)
-                 VALUES (?1, ?2, ?3, ?4)",
-                params![chunk_id, vector, model, now],
-            )?;
-            inserted += 1;
-        }
+        conn.execute(
+            "INSERT OR REPLACE INTO capture_context
+                (capture_id, git_root, git_branch, venv, vpn_interface, env, hostname, terminal)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                capture_id,
+                context.git_root,
+                context.git_branch,
+                context.venv,
+                context.vpn_interface,
+                env,
+                context.hostname,
+                context.terminal
+            ],
+        )?;
+        Ok(())
+    }
 
-        Ok(inserted)
+    /// Environment a capture's command ran in, when it was recorded
+    pub fn get_capture_context(&self, capture_id: i64) -> Result<Option<CaptureContext>> {
+        let conn = self.read_conn()?;
+        let row = conn
+            .query_row(
+                "SELECT git_root, git_branch, venv, vpn_interface, env, hostname, terminal
+                 FROM capture_context WHERE capture_id = ?1",
+                [capture_id],
+                |row| {
+                    Ok((
+                        CaptureContext {
+                            git_root: row.get(0)?,
+                            git_branch: row.get(1)?,
+                            venv: row.get(2)?,
+                            vpn_interface: row.get(3)?,
+                            env: Default::default(),
+                            hostname: row.get(5)?,
+                            terminal: row.get(6)?,
+                        },
+                        row.get::<_, String>(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((mut context, env)) = row else {
+            return Ok(None);
+        };
+        context.env = serde_json::from_str(&env).map_err(|e| YinxError::Json {
+            source: e,
+            context: format!("Invalid environment stored for capture {}", capture_id),
+        })?;
+        Ok(Some(context))
     }
 
-    /// Get embedding for a chunk
-    pub fn get_embedding(&self, chunk_id: i64) -> Result<Option<EmbeddingRecord>> {
-        let conn = self.get_conn()?;
+    /// Captures per originating terminal in a session, busiest first
+    ///
+    /// Captures recorded without a terminal or hostname count as "unknown".
+    pub fn terminal_counts(&self, session_id: &str) -> Result<Vec<(String, usize)>> {
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
-            "SELECT chunk_id, vector, model, created_at
-             FROM embeddings WHERE chunk_id = ?1",
+            "SELECT x.hostname, x.terminal, COUNT(*) FROM captures c
+             LEFT JOIN capture_context x ON x.capture_id = c.id
+             WHERE c.session_id = ?1
+             GROUP BY x.hostname, x.terminal
+             ORDER BY COUNT(*) DESC, x.hostname, x.terminal",
         )?;
+        let counts = stmt
+            .query_map([session_id], |row| {
+                let context = CaptureContext {
+                    hostname: row.get(0)?,
+                    terminal: row.get(1)?,
+                    ..CaptureContext::default()
+                };
+                let label = context
+                    .terminal_label()
+                    .unwrap_or_else(|| "unknown".to_string());
+                Ok((label, row.get::<_, i64>(2)? as usize))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(counts)
+    }
 
-        let result = stmt.query_row([chunk_id], |row| {
-            Ok(EmbeddingRecord {
-                chunk_id: row.get(0)?,
-                vector: row.get(1)?,
-                model: row.get(2)?,
-                created_at: row.get(3)?,
-            })
-        });
-
-        match result {
-            Ok(record) => Ok(Some(record)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
+    /// Tags of a capture, sorted
+    pub fn get_capture_tags(&self, capture_id: i64) -> Result<Vec<String>> {
+        let conn = self.read_conn()?;
+        let mut stmt =
+            conn.prepare("SELECT tag FROM capture_tags WHERE capture_id = ?1 ORDER BY tag")?;
+        let tags = stmt
+            .query_map([capture_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(tags)
     }
 
-    /// Get all chunks that don't have embeddings yet
-    pub fn get_chunks_without_embeddings(&self) -> Result<Vec<ChunkRecord>> {
+    /// Open a finding
+    ///
+    /// A rule opens at most one finding per session and title; returns None
+    /// when that finding already exists.
+    pub fn insert_finding(
+        &self,
+        session_id: &str,
+        capture_id: Option<i64>,
+        title: &str,
+        severity: &str,
+        rule: Option<&str>,
+    ) -> Result<Option<i64>> {
         let conn = self.get_conn()?;
-        let mut stmt = conn.prepare(
-            "SELECT c.id, c.capture_id, c.blob_hash, c.representative_text,
-                    c.cluster_size, c.metadata
-             FROM chunks c
-             LEFT JOIN embeddings e ON c.id = e.chunk_id
-             WHERE e.chunk_id IS NULL",
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO findings (session_id, capture_id, title, severity, rule, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                session_id,
+                capture_id,
+                title,
+                severity,
+                rule,
+                chrono::Utc::now().timestamp()
+            ],
         )?;
+        Ok((inserted > 0).then(|| conn.last_insert_rowid()))
+    }
 
-        let chunks = stmt
-            .query_map([], |row| {
-                Ok(ChunkRecord {
+    /// Findings, newest first, optionally for one session
+    pub fn list_findings(&self, session_id: Option<&str>) -> Result<Vec<FindingRecord>> {
+        let _timer = latency::timer("db.list_findings", || format!("session={:?}", session_id));
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, capture_id, title, severity, rule, created_at
+             FROM findings
+             WHERE ?1 IS NULL OR session_id = ?1
+             ORDER BY created_at DESC, id DESC",
+        )?;
+        let findings = stmt
+            .query_map([session_id], |row| {
+                Ok(FindingRecord {
                     id: row.get(0)?,
-                    capture_id: row.get(1)?,
-                    blob_hash: row.get(2)?,
-                    representative_text: row.get(3)?,
-                    cluster_size: row.get(4)?,
-                    metadata: row.get(5)?,
+                    session_id: row.get(1)?,
+                    capture_id: row.get(2)?,
+                    title: row.get(3)?,
+                    severity: row.get(4)?,
+                    rule: row.get(5)?,
+                    created_at: row.get(6)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(findings)
+    }
 
-        Ok(chunks)
+    /// Record a proof artifact; returns None if the value was already recorded
+    pub fn insert_proof(
+        &self,
+        session_id: &str,
+        capture_id: i64,
+        host: Option<&str>,
+        file: &str,
+        value: &str,
+    ) -> Result<Option<i64>> {
+        let conn = self.get_conn()?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO proofs (session_id, capture_id, host, file, value, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                session_id,
+                capture_id,
+                host,
+                file,
+                value,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok((inserted > 0).then(|| conn.last_insert_rowid()))
     }
 
-    /// Get chunk by ID
-    pub fn get_chunk(&self, chunk_id: i64) -> Result<Option<ChunkRecord>> {
+    /// Record a cracked credential; returns None if the hash was already cracked
+    pub fn insert_credential(&self, credential: &CredentialRecord) -> Result<Option<i64>> {
         let conn = self.get_conn()?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO credentials
+                (session_id, capture_id, hash_type, hash, username, sealed, source, cracked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                credential.session_id,
+                credential.capture_id,
+                credential.hash_type,
+                credential.hash,
+                credential.username,
+                credential.sealed,
+                credential.source,
+                credential.cracked_at
+            ],
+        )?;
+        Ok((inserted > 0).then(|| conn.last_insert_rowid()))
+    }
+
+    /// Cracked credentials in the order they were cracked, optionally for one session
+    pub fn list_credentials(&self, session_id: Option<&str>) -> Result<Vec<CredentialRecord>> {
+        let conn = self.read_conn()?;
         let mut stmt = conn.prepare(
-            "SELECT id, capture_id, blob_hash, representative_text, cluster_size, metadata
-             FROM chunks WHERE id = ?1",
+            "SELECT id, session_id, capture_id, hash_type, hash, username, sealed, source, cracked_at
+             FROM credentials
+             WHERE ?1 IS NULL OR session_id = ?1
+             ORDER BY cracked_at, id",
         )?;
+        let credentials = stmt
+            .query_map([session_id], |row| {
+                Ok(CredentialRecord {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    capture_id: row.get(2)?,
+                    hash_type: row.get(3)?,
+                    hash: row.get(4)?,
+                    username: row.get(5)?,
+                    sealed: row.get(6)?,
+                    source: row.get(7)?,
+                    cracked_at: row.get(8)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(credentials)
+    }
 
-        let mut rows = stmt.query(params![chunk_id])?;
+    /// Raise findings opened on a capture to at least high severity
+    pub fn escalate_capture_findings(&self, capture_id: i64) -> Result<usize> {
+        let conn = self.get_conn()?;
+        let updated = conn.execute(
+            "UPDATE findings SET severity = 'high'
+             WHERE capture_id = ?1 AND severity IN ('info', 'low', 'medium')",
+            [capture_id],
+        )?;
+        Ok(updated)
+    }
 
-        if let Some(row) = rows.next()? {
-            Ok(Some(ChunkRecord {
-                id: row.get(0)?,
-                capture_id: row.get(1)?,
-                blob_hash: row.get(2)?,
-                representative_text: row.get(3)?,
-                cluster_size: row.get(4)?,
-                metadata: row.get(5)?,
-            }))
-        } else {
-            Ok(None)
-        }
+    /// Proof artifacts in the order they were found, optionally for one session
+    pub fn list_proofs(&self, session_id: Option<&str>) -> Result<Vec<ProofRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, capture_id, host, file, value, created_at
+             FROM proofs
+             WHERE ?1 IS NULL OR session_id = ?1
+             ORDER BY created_at, id",
+        )?;
+        let proofs = stmt
+            .query_map([session_id], |row| {
+                Ok(ProofRecord {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    capture_id: row.get(2)?,
+                    host: row.get(3)?,
+                    file: row.get(4)?,
+                    value: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(proofs)
     }
 
-    /// Get multiple chunks by IDs
+    /// Most recent non-loopback IP seen in a session before a capture
+    pub fn last_session_host(
+        &self,
+        session_id: &str,
+        before_capture: i64,
+    ) -> Result<Option<String>> {
+        let conn = self.read_conn()?;
+        let host = conn
+            .query_row(
+                "SELECT e.value FROM entities e
+                 JOIN captures c ON c.id = e.capture_id
+                 WHERE c.session_id = ?1 AND c.id < ?2
+                   AND e.type = 'ip_address' AND e.value NOT LIKE '127.%'
+                 ORDER BY c.id DESC, e.id LIMIT 1",
+                params![session_id, before_capture],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(host)
+    }
+
+    /// Move a host forward on the board; never moves it back
+    ///
+    /// Returns true if the host was added or advanced.
+    pub fn advance_host_stage(
+        &self,
+        session_id: &str,
+        host: &str,
+        stage: HostStage,
+    ) -> Result<bool> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+        let current: Option<String> = tx
+            .query_row(
+                "SELECT stage FROM host_stages WHERE session_id = ?1 AND host = ?2",
+                params![session_id, host],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if current
+            .and_then(|c| c.parse::<HostStage>().ok())
+            .is_some_and(|current| current >= stage)
+        {
+            return Ok(false);
+        }
+
+        tx.execute(
+            "INSERT INTO host_stages (session_id, host, stage, source, updated_at)
+             VALUES (?1, ?2, ?3, 'auto', ?4)
+             ON CONFLICT(session_id, host) DO UPDATE
+             SET stage = excluded.stage, source = 'auto', updated_at = excluded.updated_at",
+            params![
+                session_id,
+                host,
+                stage.as_str(),
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Put a host in a column by hand
+    pub fn set_host_stage(&self, session_id: &str, host: &str, stage: HostStage) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT INTO host_stages (session_id, host, stage, source, updated_at)
+             VALUES (?1, ?2, ?3, 'manual', ?4)
+             ON CONFLICT(session_id, host) DO UPDATE
+             SET stage = excluded.stage, source = 'manual', updated_at = excluded.updated_at",
+            params![
+                session_id,
+                host,
+                stage.as_str(),
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Hosts on a session's board, sorted by host
+    pub fn list_host_stages(&self, session_id: &str) -> Result<Vec<HostStageRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT host, stage, source, updated_at FROM host_stages
+             WHERE session_id = ?1 ORDER BY host",
+        )?;
+        let rows = stmt
+            .query_map([session_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(host, stage, source, updated_at)| {
+                let stage = stage.parse().map_err(YinxError::Session)?;
+                Ok(HostStageRecord {
+                    host,
+                    stage,
+                    manual: source == "manual",
+                    updated_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Issue a finding was pushed to on a tracker, if any
+    pub fn get_finding_ticket(
+        &self,
+        finding_id: i64,
+        target: &str,
+    ) -> Result<Option<FindingTicketRecord>> {
+        let conn = self.read_conn()?;
+        let ticket = conn
+            .query_row(
+                "SELECT finding_id, target, remote_id, url, pushed_at
+                 FROM finding_tickets WHERE finding_id = ?1 AND target = ?2",
+                params![finding_id, target],
+                |row| {
+                    Ok(FindingTicketRecord {
+                        finding_id: row.get(0)?,
+                        target: row.get(1)?,
+                        remote_id: row.get(2)?,
+                        url: row.get(3)?,
+                        pushed_at: row.get(4)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(ticket)
+    }
+
+    /// Remember the issue a finding was pushed to, replacing any earlier one
+    pub fn set_finding_ticket(&self, ticket: &FindingTicketRecord) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO finding_tickets (finding_id, target, remote_id, url, pushed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                ticket.finding_id,
+                ticket.target,
+                ticket.remote_id,
+                ticket.url,
+                ticket.pushed_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// A single finding by id
+    pub fn get_finding(&self, finding_id: i64) -> Result<Option<FindingRecord>> {
+        let conn = self.read_conn()?;
+        let finding = conn
+            .query_row(
+                "SELECT id, session_id, capture_id, title, severity, rule, created_at
+                 FROM findings WHERE id = ?1",
+                [finding_id],
+                |row| {
+                    Ok(FindingRecord {
+                        id: row.get(0)?,
+                        session_id: row.get(1)?,
+                        capture_id: row.get(2)?,
+                        title: row.get(3)?,
+                        severity: row.get(4)?,
+                        rule: row.get(5)?,
+                        created_at: row.get(6)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(finding)
+    }
+
+    /// Make sure a session has a row, e.g. before attaching files to it
+    pub fn ensure_session(
+        &self,
+        session_id: &str,
+        name: &str,
+        started_at: i64,
+        status: &str,
+    ) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO sessions (id, name, started_at, status) VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, name, started_at, status],
+        )?;
+        Ok(())
+    }
+
+    /// Record an attachment
+    ///
+    /// Attaching the same content to the same capture and finding again
+    /// returns the existing record.
+    pub fn insert_attachment(
+        &self,
+        target: AttachTarget<'_>,
+        hash: &str,
+        filename: &str,
+        stored_as: &str,
+        size: u64,
+    ) -> Result<AttachmentRecord> {
+        let AttachTarget {
+            session_id,
+            capture_id,
+            finding_id,
+            ..
+        } = target;
+        let conn = self.get_conn()?;
+        let existing = conn
+            .query_row(
+                "SELECT id FROM attachments
+                 WHERE session_id = ?1 AND hash = ?2 AND capture_id IS ?3 AND finding_id IS ?4",
+                params![session_id, hash, capture_id, finding_id],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    "INSERT INTO attachments
+                     (session_id, capture_id, finding_id, hash, filename, stored_as, size, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        session_id,
+                        capture_id,
+                        finding_id,
+                        hash,
+                        filename,
+                        stored_as,
+                        size as i64,
+                        chrono::Utc::now().timestamp()
+                    ],
+                )?;
+                conn.last_insert_rowid()
+            }
+        };
+        let attachment = conn.query_row(
+            &format!(
+                "SELECT {} FROM attachments WHERE id = ?1",
+                ATTACHMENT_COLUMNS
+            ),
+            [id],
+            attachment_from_row,
+        )?;
+        Ok(attachment)
+    }
+
+    /// Attachments of a session, oldest first
+    pub fn list_attachments(&self, session_id: &str) -> Result<Vec<AttachmentRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attachments WHERE session_id = ?1 ORDER BY id",
+            ATTACHMENT_COLUMNS
+        ))?;
+        let attachments = stmt
+            .query_map([session_id], attachment_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(attachments)
+    }
+
+    /// Attachments linked to a capture, oldest first
+    pub fn get_attachments_for_capture(&self, capture_id: i64) -> Result<Vec<AttachmentRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attachments WHERE capture_id = ?1 ORDER BY id",
+            ATTACHMENT_COLUMNS
+        ))?;
+        let attachments = stmt
+            .query_map([capture_id], attachment_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(attachments)
+    }
+
+    /// Unique entities of a session, most frequent first
+    pub fn get_session_entities(
+        &self,
+        session_id: &str,
+        entity_type: Option<&str>,
+    ) -> Result<Vec<SessionEntityRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT session_id, type, value, occurrences, captures, max_confidence,
+                    first_seen, last_seen
+             FROM session_entities
+             WHERE session_id = ?1 AND (?2 IS NULL OR type = ?2)
+             ORDER BY occurrences DESC, value",
+        )?;
+
+        let entities = stmt
+            .query_map(params![session_id, entity_type], |row| {
+                Ok(SessionEntityRecord {
+                    session_id: row.get(0)?,
+                    entity_type: row.get(1)?,
+                    value: row.get(2)?,
+                    occurrences: row.get(3)?,
+                    captures: row.get(4)?,
+                    max_confidence: row.get(5)?,
+                    first_seen: row.get(6)?,
+                    last_seen: row.get(7)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(entities)
+    }
+
+    /// Entities matching `search`, totalled across sessions, most frequent first
+    pub fn search_entities(&self, search: &EntitySearch) -> Result<Vec<EntityTotal>> {
+        let _timer = latency::timer("db.search_entities", || format!("{:?}", search));
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "{} ORDER BY occurrences DESC, type, value",
+            ENTITY_TOTALS_QUERY
+        ))?;
+        let entities = stmt
+            .query_map(search.params(), entity_total_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(entities)
+    }
+
+    /// The `per_type` most frequent values of each entity type in `search`
+    pub fn top_entities(&self, search: &EntitySearch, per_type: usize) -> Result<Vec<EntityTotal>> {
+        let _timer = latency::timer("db.top_entities", || {
+            format!("{:?} per_type={}", search, per_type)
+        });
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT type, value, occurrences, captures, sessions, max_confidence,
+                    first_seen, last_seen
+             FROM (SELECT *, ROW_NUMBER() OVER (
+                       PARTITION BY type ORDER BY occurrences DESC, value) AS position
+                   FROM ({}))
+             WHERE position <= ?4
+             ORDER BY type, position",
+            ENTITY_TOTALS_QUERY
+        ))?;
+        let (session_id, entity_type, since) = search.params();
+        let entities = stmt
+            .query_map(
+                params![session_id, entity_type, since, per_type as i64],
+                entity_total_from_row,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(entities)
+    }
+
+    /// Query entities by capture ID
+    pub fn get_entities_for_capture(&self, capture_id: i64) -> Result<Vec<EntityRecord>> {
+        let _timer = latency::timer("db.get_entities_for_capture", || {
+            format!("capture={}", capture_id)
+        });
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, capture_id, type, value, context, confidence, occurrences,
+                    start_offset, end_offset, line, host
+             FROM entities WHERE capture_id = ?1",
+        )?;
+
+        let entities = stmt
+            .query_map([capture_id], |row| {
+                Ok(EntityRecord {
+                    id: row.get(0)?,
+                    capture_id: row.get(1)?,
+                    entity_type: row.get(2)?,
+                    value: row.get(3)?,
+                    context: row.get(4)?,
+                    confidence: row.get(5)?,
+                    occurrences: row.get(6)?,
+                    start: offset_from_row(row, 7)?,
+                    end: offset_from_row(row, 8)?,
+                    line: offset_from_row(row, 9)?,
+                    host: row.get(10)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(entities)
+    }
+
+    /// Query entities by type
+    pub fn get_entities_by_type(&self, entity_type: &str) -> Result<Vec<EntityRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, capture_id, type, value, context, confidence, occurrences,
+                    start_offset, end_offset, line, host
+             FROM entities WHERE type = ?1",
+        )?;
+
+        let entities = stmt
+            .query_map([entity_type], |row| {
+                Ok(EntityRecord {
+                    id: row.get(0)?,
+                    capture_id: row.get(1)?,
+                    entity_type: row.get(2)?,
+                    value: row.get(3)?,
+                    context: row.get(4)?,
+                    confidence: row.get(5)?,
+                    occurrences: row.get(6)?,
+                    start: offset_from_row(row, 7)?,
+                    end: offset_from_row(row, 8)?,
+                    line: offset_from_row(row, 9)?,
+                    host: row.get(10)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(entities)
+    }
+
+    /// Insert an embedding for a chunk
+    ///
+    /// # Arguments
+    /// * `chunk_id` - Chunk ID (foreign key to chunks table)
+    /// * `vector` - Embedding vector as bytes (serialized f32 array)
+    /// * `model` - Model name used for embedding
+    pub fn insert_embedding(&self, chunk_id: i64, vector: &[u8], model: &str) -> Result<()> {
+        let conn = self.get_conn()?;
+        let now = chrono::Utc::now().timestamp();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO embeddings (chunk_id, vector, model, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![chunk_id, vector, model, now],
+        )?;
+
+        Ok(())
+    }
+
+    /// Insert multiple embeddings in batch
+    pub fn insert_embeddings_batch(
+        &self,
+        embeddings: &[(i64, Vec<u8>, String)], // (chunk_id, vector, model)
+    ) -> Result<usize> {
+        let conn = self.get_conn()?;
+        let now = chrono::Utc::now().timestamp();
+        let mut inserted = 0;
+
+        for (chunk_id, vector, model) in embeddings {
+            conn.execute(
+                "INSERT OR REPLACE INTO embeddings (chunk_id, vector, model, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![chunk_id, vector, model, now],
+            )?;
+            inserted += 1;
+        }
+
+        Ok(inserted)
+    }
+
+    /// Get embedding for a chunk
+    pub fn get_embedding(&self, chunk_id: i64) -> Result<Option<EmbeddingRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT chunk_id, vector, model, created_at
+             FROM embeddings WHERE chunk_id = ?1",
+        )?;
+
+        let result = stmt.query_row([chunk_id], |row| {
+            Ok(EmbeddingRecord {
+                chunk_id: row.get(0)?,
+                vector: row.get(1)?,
+                model: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        });
+
+        match result {
+            Ok(record) => Ok(Some(record)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Count chunks in database
+    pub fn count_chunks(&self) -> Result<usize> {
+        let conn = self.read_conn()?;
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Up to `limit` chunk texts with ids above `after`, in id order, with
+    /// the host each chunk is about
+    ///
+    /// Chunks of archived sessions are left out, as they are of the indexes.
+    pub fn chunk_texts_after(
+        &self,
+        after: i64,
+        limit: usize,
+    ) -> Result<Vec<(i64, String, Option<String>)>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT ch.id, ch.representative_text,
+                    CASE WHEN json_valid(ch.metadata) THEN json_extract(ch.metadata, '$.host') END
+             FROM chunks ch
+             JOIN captures c ON c.id = ch.capture_id
+             JOIN sessions s ON s.id = c.session_id
+             WHERE ch.id > ?1 AND s.status != 'archived'
+             ORDER BY ch.id LIMIT ?2",
+        )?;
+        let chunks = stmt
+            .query_map(params![after, limit as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(chunks)
+    }
+
+    /// How far the index named `index` has caught up with the chunks table
+    pub fn index_watermark(&self, index: &str) -> Result<IndexWatermark> {
+        let conn = self.read_conn()?;
+        let watermark = conn
+            .query_row(
+                "SELECT committed, pending FROM index_watermarks WHERE name = ?1",
+                params![index],
+                |row| {
+                    Ok(IndexWatermark {
+                        committed: row.get(0)?,
+                        pending: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(watermark.unwrap_or_default())
+    }
+
+    /// Record that an update of `index` with chunks up to `upto` is about
+    /// to be committed to it
+    pub fn begin_index_update(&self, index: &str, upto: i64) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT INTO index_watermarks (name, committed, pending, updated_at)
+             VALUES (?1, 0, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET
+                pending = MAX(pending, excluded.pending),
+                updated_at = excluded.updated_at",
+            params![index, upto, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Record that `index` holds every chunk up to `upto`
+    pub fn commit_index_update(&self, index: &str, upto: i64) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT INTO index_watermarks (name, committed, pending, updated_at)
+             VALUES (?1, ?2, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET
+                committed = excluded.committed,
+                pending = MAX(pending, excluded.pending),
+                updated_at = excluded.updated_at",
+            params![index, upto, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Record that `index` was rebuilt from scratch with every chunk up to
+    /// `upto`, forgetting any interrupted update
+    pub fn reset_index_watermark(&self, index: &str, upto: i64) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO index_watermarks (name, committed, pending, updated_at)
+             VALUES (?1, ?2, ?2, ?3)",
+            params![index, upto, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Full-text search over chunk texts, best match first
+    ///
+    /// Any word of `query` may match; operators in it are taken literally.
+    /// Scores are BM25, higher is better, and not comparable with the
+    /// keyword index's.
+    pub fn search_chunk_texts(&self, query: &str, limit: usize) -> Result<Vec<(i64, f32)>> {
+        let _timer = latency::timer("db.search_chunk_texts", || format!("query={:?}", query));
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT rowid, -bm25(chunks_fts) FROM chunks_fts
+             WHERE chunks_fts MATCH ?1 ORDER BY bm25(chunks_fts) LIMIT ?2",
+        )?;
+        let hits = stmt
+            .query_map(params![terms.join(" OR "), limit as i64], |row| {
+                Ok((row.get(0)?, row.get::<_, f64>(1)? as f32))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(hits)
+    }
+
+    /// Get all chunks that don't have embeddings yet
+    ///
+    /// Archived sessions are left out; their embeddings were dropped on
+    /// purpose.
+    pub fn get_chunks_without_embeddings(&self) -> Result<Vec<ChunkRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.capture_id, c.blob_hash, c.representative_text,
+                    c.cluster_size, c.metadata
+             FROM chunks c
+             JOIN captures cap ON cap.id = c.capture_id
+             JOIN sessions s ON s.id = cap.session_id
+             LEFT JOIN embeddings e ON c.id = e.chunk_id
+             WHERE e.chunk_id IS NULL AND s.archived_at IS NULL",
+        )?;
+
+        let chunks = stmt
+            .query_map([], |row| {
+                Ok(ChunkRecord {
+                    id: row.get(0)?,
+                    capture_id: row.get(1)?,
+                    blob_hash: row.get(2)?,
+                    representative_text: row.get(3)?,
+                    cluster_size: row.get(4)?,
+                    metadata: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(chunks)
+    }
+
+    /// Get chunk by ID
+    pub fn get_chunk(&self, chunk_id: i64) -> Result<Option<ChunkRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, capture_id, blob_hash, representative_text, cluster_size, metadata
+             FROM chunks WHERE id = ?1",
+        )?;
+
+        let mut rows = stmt.query(params![chunk_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(ChunkRecord {
+                id: row.get(0)?,
+                capture_id: row.get(1)?,
+                blob_hash: row.get(2)?,
+                representative_text: row.get(3)?,
+                cluster_size: row.get(4)?,
+                metadata: row.get(5)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get multiple chunks by IDs
     pub fn get_chunks(&self, chunk_ids: &[i64]) -> Result<Vec<ChunkRecord>> {
+        let _timer = latency::timer("db.get_chunks", || format!("chunks={}", chunk_ids.len()));
         if chunk_ids.is_empty() {
             return Ok(Vec::new());
         }
 
-        let conn = self.get_conn()?;
+        let conn = self.read_conn()?;
         let placeholders = chunk_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let query = format!(
             "SELECT id, capture_id, blob_hash, representative_text, cluster_size, metadata
@@ -343,14 +1515,212 @@ impl Database {
             placeholders
         );
 
-        let mut stmt = conn.prepare(&query)?;
-        let params: Vec<&dyn rusqlite::ToSql> = chunk_ids
-            .iter()
-            .map(|id| id as &dyn rusqlite::ToSql)
-            .collect();
+        let mut stmt = conn.prepare(&query)?;
+        let params: Vec<&dyn rusqlite::ToSql> = chunk_ids
+            .iter()
+            .map(|id| id as &dyn rusqlite::ToSql)
+            .collect();
+
+        let chunks = stmt
+            .query_map(params.as_slice(), |row| {
+                Ok(ChunkRecord {
+                    id: row.get(0)?,
+                    capture_id: row.get(1)?,
+                    blob_hash: row.get(2)?,
+                    representative_text: row.get(3)?,
+                    cluster_size: row.get(4)?,
+                    metadata: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(chunks)
+    }
+
+    /// Get capture info (for provenance)
+    pub fn get_capture(&self, capture_id: i64) -> Result<Option<CaptureRecord>> {
+        let _timer = latency::timer("db.get_capture", || format!("capture={}", capture_id));
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, timestamp, command, output_hash, tool, exit_code, cwd, username,
+                    original_size, repeat_count, target, ports, wordlist, url, service,
+                    tool_detection, tool_confidence
+             FROM captures WHERE id = ?1",
+        )?;
+
+        let mut rows = stmt.query(params![capture_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(CaptureRecord {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                timestamp: row.get(2)?,
+                command: row.get(3)?,
+                output_hash: row.get(4)?,
+                tool: row.get(5)?,
+                exit_code: row.get(6)?,
+                cwd: row.get(7)?,
+                username: row.get(8)?,
+                original_size: row.get(9)?,
+                repeat_count: row.get(10)?,
+                arguments: arguments_from_row(row, 11)?,
+                detection: row
+                    .get::<_, Option<String>>(16)?
+                    .as_deref()
+                    .and_then(DetectionMethod::parse),
+                detection_confidence: row.get(17)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get all captures in a session, oldest first
+    pub fn get_session_captures(&self, session_id: &str) -> Result<Vec<CaptureRecord>> {
+        let _timer = latency::timer("db.get_session_captures", || {
+            format!("session={}", session_id)
+        });
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, timestamp, command, output_hash, tool, exit_code, cwd, username,
+                    original_size, repeat_count, target, ports, wordlist, url, service,
+                    tool_detection, tool_confidence
+             FROM captures WHERE session_id = ?1 ORDER BY timestamp, id",
+        )?;
+
+        let captures = stmt
+            .query_map(params![session_id], |row| {
+                Ok(CaptureRecord {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    timestamp: row.get(2)?,
+                    command: row.get(3)?,
+                    output_hash: row.get(4)?,
+                    tool: row.get(5)?,
+                    exit_code: row.get(6)?,
+                    cwd: row.get(7)?,
+                    username: row.get(8)?,
+                    original_size: row.get(9)?,
+                    repeat_count: row.get(10)?,
+                    arguments: arguments_from_row(row, 11)?,
+                    detection: row
+                        .get::<_, Option<String>>(16)?
+                        .as_deref()
+                        .and_then(DetectionMethod::parse),
+                    detection_confidence: row.get(17)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(captures)
+    }
+
+    /// Get captures with an id above `after_id`, oldest first
+    pub fn get_captures_after(&self, after_id: i64) -> Result<Vec<CaptureRecord>> {
+        let _timer = latency::timer("db.get_captures_after", || format!("after={}", after_id));
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, timestamp, command, output_hash, tool, exit_code, cwd, username,
+                    original_size, repeat_count, target, ports, wordlist, url, service,
+                    tool_detection, tool_confidence
+             FROM captures WHERE id > ?1 ORDER BY id",
+        )?;
+
+        let captures = stmt
+            .query_map(params![after_id], |row| {
+                Ok(CaptureRecord {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    timestamp: row.get(2)?,
+                    command: row.get(3)?,
+                    output_hash: row.get(4)?,
+                    tool: row.get(5)?,
+                    exit_code: row.get(6)?,
+                    cwd: row.get(7)?,
+                    username: row.get(8)?,
+                    original_size: row.get(9)?,
+                    repeat_count: row.get(10)?,
+                    arguments: arguments_from_row(row, 11)?,
+                    detection: row
+                        .get::<_, Option<String>>(16)?
+                        .as_deref()
+                        .and_then(DetectionMethod::parse),
+                    detection_confidence: row.get(17)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(captures)
+    }
+
+    /// Highest capture id, or 0 when there are none
+    pub fn max_capture_id(&self) -> Result<i64> {
+        let conn = self.read_conn()?;
+        let id = conn.query_row("SELECT COALESCE(MAX(id), 0) FROM captures", [], |row| {
+            row.get(0)
+        })?;
+        Ok(id)
+    }
+
+    /// Get the distinct blob hashes referenced by a session's captures
+    pub fn get_session_blob_hashes(&self, session_id: &str) -> Result<Vec<String>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT output_hash FROM captures WHERE session_id = ?1
+             UNION
+             SELECT DISTINCT ch.blob_hash FROM chunks ch
+             JOIN captures c ON ch.capture_id = c.id
+             WHERE c.session_id = ?1",
+        )?;
+
+        let hashes = stmt
+            .query_map([session_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(hashes)
+    }
+
+    /// Get the chunks of one capture
+    pub fn get_capture_chunks(&self, capture_id: i64) -> Result<Vec<ChunkRecord>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, capture_id, blob_hash, representative_text, cluster_size, metadata
+             FROM chunks WHERE capture_id = ?1 ORDER BY id",
+        )?;
+
+        let chunks = stmt
+            .query_map([capture_id], |row| {
+                Ok(ChunkRecord {
+                    id: row.get(0)?,
+                    capture_id: row.get(1)?,
+                    blob_hash: row.get(2)?,
+                    representative_text: row.get(3)?,
+                    cluster_size: row.get(4)?,
+                    metadata: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(chunks)
+    }
+
+    /// Get all chunks belonging to a session
+    pub fn get_session_chunks(&self, session_id: &str) -> Result<Vec<ChunkRecord>> {
+        let _timer = latency::timer("db.get_session_chunks", || {
+            format!("session={}", session_id)
+        });
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT ch.id, ch.capture_id, ch.blob_hash, ch.representative_text,
+                    ch.cluster_size, ch.metadata
+             FROM chunks ch
+             JOIN captures c ON ch.capture_id = c.id
+             WHERE c.session_id = ?1
+             ORDER BY ch.id",
+        )?;
 
         let chunks = stmt
-            .query_map(params.as_slice(), |row| {
+            .query_map([session_id], |row| {
                 Ok(ChunkRecord {
                     id: row.get(0)?,
                     capture_id: row.get(1)?,
@@ -365,82 +1735,805 @@ impl Database {
         Ok(chunks)
     }
 
-    /// Get capture info (for provenance)
-    pub fn get_capture(&self, capture_id: i64) -> Result<Option<CaptureRecord>> {
+    /// Swap a capture's chunks for new clusters
+    ///
+    /// Embeddings of the old chunks are dropped. Returns the ids of the
+    /// removed and inserted chunks, for updating the keyword index.
+    pub fn replace_capture_chunks(
+        &self,
+        capture_id: i64,
+        blob_hash: &str,
+        chunks: &[(String, i32, String)], // (representative_text, cluster_size, metadata)
+    ) -> Result<(Vec<i64>, Vec<i64>)> {
+        let mut conn = self.get_conn()?;
+        let tx = conn.transaction()?;
+
+        let removed: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT id FROM chunks WHERE capture_id = ?1")?;
+            let rows = stmt.query_map(params![capture_id], |row| row.get(0))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        tx.execute(
+            "DELETE FROM embeddings WHERE chunk_id IN (SELECT id FROM chunks WHERE capture_id = ?1)",
+            params![capture_id],
+        )?;
+        tx.execute(
+            "DELETE FROM chunks WHERE capture_id = ?1",
+            params![capture_id],
+        )?;
+
+        let mut inserted = Vec::with_capacity(chunks.len());
+        for (text, size, metadata) in chunks {
+            tx.execute(
+                "INSERT INTO chunks (capture_id, blob_hash, representative_text, cluster_size, metadata)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![capture_id, blob_hash, text, size, metadata],
+            )?;
+            inserted.push(tx.last_insert_rowid());
+        }
+
+        tx.commit()?;
+        Ok((removed, inserted))
+    }
+
+    /// Delete embeddings for every chunk in a session
+    pub fn delete_session_embeddings(&self, session_id: &str) -> Result<usize> {
         let conn = self.get_conn()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, session_id, timestamp, command, output_hash, tool, exit_code, cwd
-             FROM captures WHERE id = ?1",
+        let deleted = conn.execute(
+            "DELETE FROM embeddings WHERE chunk_id IN (
+                SELECT ch.id FROM chunks ch
+                JOIN captures c ON ch.capture_id = c.id
+                WHERE c.session_id = ?1
+             )",
+            params![session_id],
         )?;
+        Ok(deleted)
+    }
 
-        let mut rows = stmt.query(params![capture_id])?;
+    /// Mark blobs as compressed
+    pub fn mark_blobs_compressed(&self, hashes: &[String]) -> Result<()> {
+        let conn = self.get_conn()?;
+        for hash in hashes {
+            conn.execute(
+                "UPDATE blobs SET compressed = 1 WHERE hash = ?1",
+                params![hash],
+            )?;
+        }
+        Ok(())
+    }
 
-        if let Some(row) = rows.next()? {
-            Ok(Some(CaptureRecord {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                timestamp: row.get(2)?,
-                command: row.get(3)?,
-                output_hash: row.get(4)?,
-                tool: row.get(5)?,
-                exit_code: row.get(6)?,
-                cwd: row.get(7)?,
-            }))
+    /// Store the sealed original of a redacted command line
+    pub fn insert_command_original(&self, capture_id: i64, sealed: &[u8]) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO command_originals (capture_id, sealed) VALUES (?1, ?2)",
+            params![capture_id, sealed],
+        )?;
+        Ok(())
+    }
+
+    /// Get the sealed original command line for a capture, if one was kept
+    pub fn get_command_original(&self, capture_id: i64) -> Result<Option<Vec<u8>>> {
+        let conn = self.read_conn()?;
+        let sealed = conn
+            .query_row(
+                "SELECT sealed FROM command_originals WHERE capture_id = ?1",
+                params![capture_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(sealed)
+    }
+
+    /// Timestamp of the most recent capture in a session
+    pub fn last_capture_timestamp(&self, session_id: &str) -> Result<Option<i64>> {
+        let conn = self.read_conn()?;
+        let ts = conn.query_row(
+            "SELECT MAX(timestamp) FROM captures WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )?;
+        Ok(ts)
+    }
+
+    /// Update a session's status (and stop time, if given)
+    ///
+    /// Returns false if the session has no row in the database.
+    pub fn set_session_status(
+        &self,
+        session_id: &str,
+        status: &str,
+        stopped_at: Option<i64>,
+    ) -> Result<bool> {
+        let conn = self.get_conn()?;
+        let updated = conn.execute(
+            "UPDATE sessions SET status = ?2, stopped_at = COALESCE(?3, stopped_at) WHERE id = ?1",
+            params![session_id, status, stopped_at],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// A session's status, or None if it has no row in the database
+    pub fn session_status(&self, session_id: &str) -> Result<Option<String>> {
+        let conn = self.read_conn()?;
+        let status = conn
+            .query_row(
+                "SELECT status FROM sessions WHERE id = ?1",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(status)
+    }
+
+    /// Set or clear the archived flag on a session
+    ///
+    /// Returns false if the session has no row in the database.
+    pub fn set_session_archived(&self, session_id: &str, archived: bool) -> Result<bool> {
+        let conn = self.get_conn()?;
+        let updated = if archived {
+            conn.execute(
+                "UPDATE sessions SET status = 'archived', archived_at = ?2 WHERE id = ?1",
+                params![session_id, chrono::Utc::now().timestamp()],
+            )?
         } else {
-            Ok(None)
+            conn.execute(
+                "UPDATE sessions SET status = 'stopped', archived_at = NULL WHERE id = ?1",
+                params![session_id],
+            )?
+        };
+        Ok(updated > 0)
+    }
+
+    /// Check whether a session is archived
+    pub fn is_session_archived(&self, session_id: &str) -> Result<bool> {
+        let conn = self.read_conn()?;
+        let result = conn.query_row(
+            "SELECT archived_at IS NOT NULL FROM sessions WHERE id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        );
+
+        match result {
+            Ok(archived) => Ok(archived),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(false),
+            Err(e) => Err(e.into()),
         }
     }
 
+    /// Record how the filter tiers handled a capture
+    pub fn insert_filter_stats(
+        &self,
+        capture_id: i64,
+        session_id: &str,
+        tool: Option<&str>,
+        stats: &FilterStats,
+    ) -> Result<()> {
+        let conn = self.get_conn()?;
+        conn.execute(
+            "INSERT INTO filter_stats
+             (capture_id, session_id, tool, input_lines, tier1_output, tier2_output,
+              tier3_clusters, tier2_percentile, tier2_threshold, score_median, score_p90,
+              processing_time_ms, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                capture_id,
+                session_id,
+                tool,
+                stats.input_lines as i64,
+                stats.tier1_output as i64,
+                stats.tier2_output as i64,
+                stats.tier3_clusters as i64,
+                stats.tier2_percentile,
+                stats.tier2_threshold,
+                stats.score_median,
+                stats.score_p90,
+                stats.processing_time_ms as i64,
+                chrono::Utc::now().timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Filter statistics aggregated per tool, optionally for one session
+    ///
+    /// Captures without a detected tool are grouped under an empty name.
+    pub fn filter_stats_by_tool(&self, session_id: Option<&str>) -> Result<Vec<ToolFilterStats>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(tool, ''), COUNT(*), SUM(input_lines), SUM(tier1_output),
+                    SUM(tier2_output), SUM(tier3_clusters), AVG(tier2_percentile),
+                    AVG(tier2_threshold), AVG(score_median), AVG(score_p90),
+                    AVG(processing_time_ms)
+             FROM filter_stats
+             WHERE ?1 IS NULL OR session_id = ?1
+             GROUP BY 1
+             ORDER BY SUM(input_lines) DESC",
+        )?;
+
+        let stats = stmt
+            .query_map(params![session_id], |row| {
+                Ok(ToolFilterStats {
+                    tool: row.get(0)?,
+                    captures: row.get::<_, i64>(1)? as usize,
+                    input_lines: row.get::<_, i64>(2)? as u64,
+                    tier1_output: row.get::<_, i64>(3)? as u64,
+                    tier2_output: row.get::<_, i64>(4)? as u64,
+                    tier3_clusters: row.get::<_, i64>(5)? as u64,
+                    avg_percentile: row.get(6)?,
+                    avg_threshold: row.get(7)?,
+                    avg_score_median: row.get(8)?,
+                    avg_score_p90: row.get(9)?,
+                    avg_processing_ms: row.get(10)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(stats)
+    }
+
+    /// Mean Tier 2 input lines per tool, for seeding the adaptive threshold
+    pub fn tier2_volume_means(&self) -> Result<Vec<(String, f32)>> {
+        let conn = self.read_conn()?;
+        let mut stmt = conn
+            .prepare("SELECT COALESCE(tool, ''), AVG(tier1_output) FROM filter_stats GROUP BY 1")?;
+        let means = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, f64>(1)? as f32)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(means)
+    }
+
     /// Count embeddings in database
     pub fn count_embeddings(&self) -> Result<usize> {
-        let conn = self.get_conn()?;
+        let conn = self.read_conn()?;
         let count: i64 = conn.query_row("SELECT COUNT(*) FROM embeddings", [], |row| row.get(0))?;
         Ok(count as usize)
     }
+
+    /// Chunks of unarchived sessions still waiting for an embedding, and when
+    /// the oldest was captured
+    pub fn embedding_backlog(&self) -> Result<EmbeddingBacklog> {
+        let conn = self.read_conn()?;
+        let (chunks, oldest): (i64, Option<i64>) = conn.query_row(
+            "SELECT COUNT(*), MIN(cap.timestamp)
+             FROM chunks c
+             JOIN captures cap ON cap.id = c.capture_id
+             JOIN sessions s ON s.id = cap.session_id
+             LEFT JOIN embeddings e ON c.id = e.chunk_id
+             WHERE e.chunk_id IS NULL AND s.archived_at IS NULL",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(EmbeddingBacklog {
+            chunks: chunks as usize,
+            oldest,
+        })
+    }
+}
+
+/// How far an index has caught up with the chunks table
+///
+/// Chunk ids only grow, so one id per index says what's in it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexWatermark {
+    /// Every chunk up to this id is in the index
+    pub committed: i64,
+    /// Highest id of an update that was started; chunks between
+    /// `committed` and here may or may not have made it in
+    pub pending: i64,
+}
+
+impl IndexWatermark {
+    /// Whether an update was interrupted between the index commit and
+    /// recording it
+    pub fn interrupted(&self) -> bool {
+        self.pending > self.committed
+    }
+}
+
+/// Chunks not embedded yet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct EmbeddingBacklog {
+    pub chunks: usize,
+    /// Capture time of the oldest waiting chunk
+    pub oldest: Option<i64>,
+}
+
+/// Embedding database record
+#[derive(Debug, Clone)]
+pub struct EmbeddingRecord {
+    pub chunk_id: i64,
+    pub vector: Vec<u8>,
+    pub model: String,
+    pub created_at: i64,
+}
+
+/// Chunk database record
+#[derive(Debug, Clone)]
+pub struct ChunkRecord {
+    pub id: i64,
+    pub capture_id: i64,
+    pub blob_hash: String,
+    pub representative_text: String,
+    pub cluster_size: i32,
+    pub metadata: Option<String>,
+}
+
+impl ChunkRecord {
+    /// Tool output sections recorded in the chunk's metadata
+    pub fn sections(&self) -> Vec<String> {
+        self.metadata
+            .as_deref()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(json).ok())
+            .and_then(|value| serde_json::from_value(value.get("sections")?.clone()).ok())
+            .unwrap_or_default()
+    }
+    /// Host the chunk is about
+    pub fn host(&self) -> Option<String> {
+        self.metadata.as_deref().and_then(metadata_host)
+    }
+}
+
+/// The `host` recorded in a chunk's metadata JSON
+pub fn metadata_host(metadata: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(metadata).ok()?;
+    value.get("host")?.as_str().map(str::to_string)
+}
+
+/// Capture database record
+#[derive(Debug, Clone)]
+pub struct CaptureRecord {
+    pub id: i64,
+    pub session_id: String,
+    pub timestamp: i64,
+    pub command: Option<String>,
+    pub output_hash: String,
+    pub tool: Option<String>,
+    pub exit_code: Option<i32>,
+    pub cwd: Option<String>,
+    pub username: Option<String>,
+    /// Output size before truncation, when the stored output was cut down
+    pub original_size: Option<i64>,
+    /// Times the capture was seen, counting identical re-runs coalesced into it
+    pub repeat_count: i64,
+    /// Target, ports and other arguments parsed from the command line
+    pub arguments: ToolArguments,
+    /// How `tool` was identified; None for captures stored before this was
+    /// recorded or with no tool
+    pub detection: Option<DetectionMethod>,
+    pub detection_confidence: Option<f32>,
+}
+
+/// Tool arguments stored in five consecutive columns starting at `first`
+fn arguments_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<ToolArguments> {
+    Ok(ToolArguments {
+        target: row.get(first)?,
+        ports: row.get(first + 1)?,
+        wordlist: row.get(first + 2)?,
+        url: row.get(first + 3)?,
+        service: row.get(first + 4)?,
+    })
+}
+
+/// Entity database record
+#[derive(Debug, Clone)]
+pub struct EntityRecord {
+    pub id: i64,
+    pub capture_id: i64,
+    pub entity_type: String,
+    pub value: String,
+    pub context: Option<String>,
+    pub confidence: f32,
+    /// Times the value appeared in this capture
+    pub occurrences: u32,
+    /// Byte range of the first occurrence in the capture's output (marker
+    /// line of truncated output excluded); None for entities stored before
+    /// positions were recorded
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+    /// 1-based line of the first occurrence
+    pub line: Option<usize>,
+    /// Host whose part of a multi-host capture the first occurrence is in
+    pub host: Option<String>,
+}
+
+impl EntityRecord {
+    /// Context re-derived from the capture's output at any width: up to
+    /// `window` bytes either side of the first occurrence
+    ///
+    /// `output` is the text entities were extracted from (see
+    /// [`StorageManager::indexed_output`](super::StorageManager::indexed_output)).
+    /// None without a recorded position or when the output no longer holds
+    /// the value there.
+    pub fn context_in(&self, output: &str, window: usize) -> Option<String> {
+        let (start, end) = (self.start?, self.end?);
+        if output.get(start..end)? != self.value {
+            return None;
+        }
+        let mut from = start.saturating_sub(window);
+        while !output.is_char_boundary(from) {
+            from -= 1;
+        }
+        let mut to = (end + window).min(output.len());
+        while !output.is_char_boundary(to) {
+            to += 1;
+        }
+        Some(output[from..to].to_string())
+    }
+}
+
+/// Where an extracted value first appears in a capture's output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityPosition {
+    pub entity_type: String,
+    pub value: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    /// Host whose part of a multi-host capture it appears in
+    pub host: Option<String>,
+}
+
+impl EntityPosition {
+    /// Position of `start..end` in `text`, with its line number
+    pub fn in_text(text: &str, entity_type: &str, value: &str, start: usize, end: usize) -> Self {
+        let line = text
+            .get(..start)
+            .map_or(0, |before| before.matches('\n').count())
+            + 1;
+        Self {
+            entity_type: entity_type.to_string(),
+            value: value.to_string(),
+            start,
+            end,
+            line,
+            host: None,
+        }
+    }
+
+    pub fn with_host(mut self, host: Option<&str>) -> Self {
+        self.host = host.map(str::to_string);
+        self
+    }
+}
+
+/// Optional non-negative integer column as usize
+fn offset_from_row(row: &rusqlite::Row, column: usize) -> rusqlite::Result<Option<usize>> {
+    Ok(row.get::<_, Option<i64>>(column)?.map(|v| v as usize))
+}
+
+/// JSON a pipeline hook attached to a capture
+#[derive(Debug, Clone)]
+pub struct AnnotationRecord {
+    pub hook: String,
+    pub stage: String,
+    pub data: String,
+    pub created_at: i64,
+}
+
+/// Finding database record
+#[derive(Debug, Clone)]
+pub struct FindingRecord {
+    pub id: i64,
+    pub session_id: String,
+    /// Capture that triggered it, if it still exists
+    pub capture_id: Option<i64>,
+    pub title: String,
+    pub severity: String,
+    /// Rule that opened it; None for findings added by hand
+    pub rule: Option<String>,
+    pub created_at: i64,
+}
+
+const ATTACHMENT_COLUMNS: &str =
+    "id, session_id, capture_id, finding_id, hash, filename, stored_as, size, created_at";
+
+fn attachment_from_row(row: &rusqlite::Row) -> rusqlite::Result<AttachmentRecord> {
+    Ok(AttachmentRecord {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        capture_id: row.get(2)?,
+        finding_id: row.get(3)?,
+        hash: row.get(4)?,
+        filename: row.get(5)?,
+        stored_as: row.get(6)?,
+        size: row.get::<_, i64>(7)? as u64,
+        created_at: row.get(8)?,
+    })
+}
+
+/// A host's place on the status board
+#[derive(Debug, Clone)]
+pub struct HostStageRecord {
+    pub host: String,
+    pub stage: HostStage,
+    /// Set with `yinx board set` rather than by a capture
+    pub manual: bool,
+    pub updated_at: i64,
+}
+
+/// Exam proof artifact found in a capture
+#[derive(Debug, Clone)]
+pub struct ProofRecord {
+    pub id: i64,
+    pub session_id: String,
+    pub capture_id: Option<i64>,
+    /// Host the proof was read on, if it could be told
+    pub host: Option<String>,
+    pub file: String,
+    pub value: String,
+    pub created_at: i64,
+}
+
+/// Issue tracker entry created from a finding
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindingTicketRecord {
+    pub finding_id: i64,
+    /// Tracker name (`jira`, `gitlab`)
+    pub target: String,
+    /// Issue key or number on the tracker
+    pub remote_id: String,
+    pub url: Option<String>,
+    pub pushed_at: i64,
+}
+
+/// Cracked password for a hash seen in a capture
+#[derive(Debug, Clone)]
+pub struct CredentialRecord {
+    pub id: i64,
+    pub session_id: String,
+    /// Capture the hash was extracted from, if it still exists
+    pub capture_id: Option<i64>,
+    /// Entity type of the hash (`hash_ntlm`, ...)
+    pub hash_type: String,
+    pub hash: String,
+    pub username: Option<String>,
+    /// Plaintext, sealed with the vault
+    pub sealed: Vec<u8>,
+    /// Cracking tool the password came from
+    pub source: String,
+    pub cracked_at: i64,
+}
+
+/// Evidence file copied into a session's report directory
+#[derive(Debug, Clone)]
+pub struct AttachmentRecord {
+    pub id: i64,
+    pub session_id: String,
+    pub capture_id: Option<i64>,
+    pub finding_id: Option<i64>,
+    /// BLAKE3 hash of the content
+    pub hash: String,
+    /// Original file name
+    pub filename: String,
+    /// File name under the session's `evidence/` directory
+    pub stored_as: String,
+    pub size: u64,
+    pub created_at: i64,
+}
+
+impl AttachmentRecord {
+    /// Path relative to the session's report directory, for report links
+    pub fn report_path(&self) -> String {
+        format!("evidence/{}", self.stored_as)
+    }
+
+    /// Whether reports can show the file inline
+    pub fn is_image(&self) -> bool {
+        let extension = self
+            .filename
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase());
+        matches!(
+            extension.as_deref(),
+            Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "svg")
+        )
+    }
+
+    /// Markdown embed (images) or link (anything else)
+    pub fn markdown(&self) -> String {
+        let link = format!("[{}]({})", self.filename, self.report_path());
+        if self.is_image() {
+            format!("!{}", link)
+        } else {
+            link
+        }
+    }
+
+    /// HTML embed (images) or link (anything else)
+    pub fn html(&self) -> String {
+        let name = html_escape(&self.filename);
+        let path = html_escape(&self.report_path());
+        if self.is_image() {
+            format!(r#"<a href="{0}"><img src="{0}" alt="{1}"></a>"#, path, name)
+        } else {
+            format!(r#"<a href="{}">{}</a>"#, path, name)
+        }
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An entity coalesced across all captures of a session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEntityRecord {
+    pub session_id: String,
+    pub entity_type: String,
+    pub value: String,
+    pub occurrences: u32,
+    /// Number of distinct captures the value appeared in
+    pub captures: u32,
+    pub max_confidence: f32,
+    /// Timestamp of the first capture it appeared in
+    pub first_seen: i64,
+    /// Timestamp of the latest capture it appeared in
+    pub last_seen: i64,
+}
+
+impl SessionEntityRecord {
+    /// Confidence after counting each capture as an independent sighting
+    ///
+    /// A value that a 0.5-confidence pattern matched in three separate
+    /// captures is more likely real than one seen once.
+    pub fn calibrated_confidence(&self) -> f32 {
+        1.0 - (1.0 - self.max_confidence).powi(self.captures.max(1) as i32)
+    }
+}
+
+/// Which entities `yinx entities` looks at; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct EntitySearch {
+    pub session_id: Option<String>,
+    pub entity_type: Option<String>,
+    /// Only values seen at or after this timestamp
+    pub since: Option<i64>,
+}
+
+impl EntitySearch {
+    fn params(&self) -> (Option<&str>, Option<&str>, i64) {
+        (
+            self.session_id.as_deref(),
+            self.entity_type.as_deref(),
+            self.since.unwrap_or(i64::MIN),
+        )
+    }
+}
+
+/// An entity value totalled over every session it appeared in
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityTotal {
+    pub entity_type: String,
+    pub value: String,
+    pub occurrences: u32,
+    pub captures: u32,
+    /// Number of sessions the value appeared in
+    pub sessions: u32,
+    pub max_confidence: f32,
+    pub first_seen: i64,
+    pub last_seen: i64,
 }
 
-/// Embedding database record
-#[derive(Debug, Clone)]
-pub struct EmbeddingRecord {
-    pub chunk_id: i64,
-    pub vector: Vec<u8>,
-    pub model: String,
-    pub created_at: i64,
+/// Session aggregates grouped by value, bound to an [`EntitySearch`]
+const ENTITY_TOTALS_QUERY: &str = "
+    SELECT type, value, SUM(occurrences) AS occurrences, SUM(captures) AS captures,
+           COUNT(*) AS sessions, MAX(max_confidence) AS max_confidence,
+           MIN(first_seen) AS first_seen, MAX(last_seen) AS last_seen
+    FROM session_entities
+    WHERE (?1 IS NULL OR session_id = ?1) AND (?2 IS NULL OR type = ?2) AND last_seen >= ?3
+    GROUP BY type, value";
+
+fn entity_total_from_row(row: &rusqlite::Row) -> rusqlite::Result<EntityTotal> {
+    Ok(EntityTotal {
+        entity_type: row.get(0)?,
+        value: row.get(1)?,
+        occurrences: row.get(2)?,
+        captures: row.get(3)?,
+        sessions: row.get(4)?,
+        max_confidence: row.get(5)?,
+        first_seen: row.get(6)?,
+        last_seen: row.get(7)?,
+    })
 }
 
-/// Chunk database record
-#[derive(Debug, Clone)]
-pub struct ChunkRecord {
-    pub id: i64,
-    pub capture_id: i64,
-    pub blob_hash: String,
-    pub representative_text: String,
-    pub cluster_size: i32,
-    pub metadata: Option<String>,
+/// Record `occurrences` sightings of an entity in a capture
+///
+/// Updates both the per-capture row and the session-wide aggregate. The
+/// first context seen is kept; confidence is the highest seen.
+pub(crate) fn upsert_entity(
+    conn: &rusqlite::Connection,
+    capture_id: i64,
+    entity_type: &str,
+    value: &str,
+    context: Option<&str>,
+    confidence: f32,
+    occurrences: u32,
+) -> Result<()> {
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM entities WHERE capture_id = ?1 AND type = ?2 AND value = ?3",
+            params![capture_id, entity_type, value],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match existing {
+        Some(id) => {
+            conn.execute(
+                "UPDATE entities
+                 SET occurrences = occurrences + ?1, confidence = MAX(confidence, ?2)
+                 WHERE id = ?3",
+                params![occurrences, confidence, id],
+            )?;
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO entities (capture_id, type, value, context, confidence, occurrences)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    capture_id,
+                    entity_type,
+                    value,
+                    context,
+                    confidence,
+                    occurrences
+                ],
+            )?;
+        }
+    }
+    let new_capture = existing.is_none() as u32;
+
+    conn.execute(
+        "INSERT INTO session_entities
+         (session_id, type, value, occurrences, captures, max_confidence, first_seen, last_seen)
+         SELECT session_id, ?2, ?3, ?4, ?5, ?6, timestamp, timestamp
+         FROM captures WHERE id = ?1
+         ON CONFLICT (session_id, type, value) DO UPDATE SET
+             occurrences = occurrences + excluded.occurrences,
+             captures = captures + excluded.captures,
+             max_confidence = MAX(max_confidence, excluded.max_confidence),
+             first_seen = MIN(first_seen, excluded.first_seen),
+             last_seen = MAX(last_seen, excluded.last_seen)",
+        params![
+            capture_id,
+            entity_type,
+            value,
+            occurrences,
+            new_capture,
+            confidence
+        ],
+    )?;
+    Ok(())
 }
 
-/// Capture database record
+/// Filter statistics for one tool
 #[derive(Debug, Clone)]
-pub struct CaptureRecord {
-    pub id: i64,
-    pub session_id: String,
-    pub timestamp: i64,
-    pub command: Option<String>,
-    pub output_hash: String,
-    pub tool: Option<String>,
-    pub exit_code: Option<i32>,
-    pub cwd: Option<String>,
+pub struct ToolFilterStats {
+    pub tool: String,
+    pub captures: usize,
+    pub input_lines: u64,
+    pub tier1_output: u64,
+    pub tier2_output: u64,
+    pub tier3_clusters: u64,
+    pub avg_percentile: f64,
+    pub avg_threshold: f64,
+    pub avg_score_median: f64,
+    pub avg_score_p90: f64,
+    pub avg_processing_ms: f64,
 }
 
-/// Entity database record
-#[derive(Debug, Clone)]
-pub struct EntityRecord {
-    pub id: i64,
-    pub capture_id: i64,
-    pub entity_type: String,
-    pub value: String,
-    pub context: Option<String>,
-    pub confidence: f32,
+impl ToolFilterStats {
+    /// Fraction of input lines that did not become a cluster
+    pub fn reduction(&self) -> f64 {
+        if self.input_lines == 0 {
+            0.0
+        } else {
+            1.0 - self.tier3_clusters as f64 / self.input_lines as f64
+        }
+    }
 }
 
 /// Database statistics
@@ -541,11 +2634,366 @@ const MIGRATIONS: &[&str] = &[
     CREATE INDEX idx_entities_type ON entities(type);
     CREATE INDEX idx_entities_value ON entities(value);
     "#,
+    // Migration 2: Session archiving
+    r#"
+    ALTER TABLE sessions ADD COLUMN archived_at INTEGER;
+    "#,
+    // Migration 3: Vault-sealed originals of redacted command lines
+    r#"
+    CREATE TABLE command_originals (
+        capture_id INTEGER PRIMARY KEY,
+        sealed BLOB NOT NULL,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE CASCADE
+    );
+    "#,
+    // Migration 4: Capture attribution for shared daemons
+    r#"
+    ALTER TABLE captures ADD COLUMN username TEXT;
+    CREATE INDEX idx_captures_username ON captures(username);
+    "#,
+    // Migration 5: Per-capture filter effectiveness
+    r#"
+    CREATE TABLE filter_stats (
+        capture_id INTEGER PRIMARY KEY,
+        session_id TEXT NOT NULL,
+        tool TEXT,
+        input_lines INTEGER NOT NULL,
+        tier1_output INTEGER NOT NULL,
+        tier2_output INTEGER NOT NULL,
+        tier3_clusters INTEGER NOT NULL,
+        tier2_percentile REAL NOT NULL,
+        tier2_threshold REAL NOT NULL,
+        score_median REAL NOT NULL,
+        score_p90 REAL NOT NULL,
+        processing_time_ms INTEGER NOT NULL,
+        created_at INTEGER NOT NULL,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_filter_stats_session ON filter_stats(session_id);
+    CREATE INDEX idx_filter_stats_tool ON filter_stats(tool);
+    "#,
+    // Migration 6: Coalesce repeated entities, per capture and per session
+    r#"
+    ALTER TABLE entities ADD COLUMN occurrences INTEGER NOT NULL DEFAULT 1;
+
+    UPDATE entities SET
+        occurrences = (SELECT COUNT(*) FROM entities d
+                       WHERE d.capture_id = entities.capture_id
+                         AND d.type = entities.type AND d.value = entities.value),
+        confidence = (SELECT MAX(d.confidence) FROM entities d
+                      WHERE d.capture_id = entities.capture_id
+                        AND d.type = entities.type AND d.value = entities.value);
+    DELETE FROM entities
+    WHERE id NOT IN (SELECT MIN(id) FROM entities GROUP BY capture_id, type, value);
+    CREATE UNIQUE INDEX idx_entities_occurrence ON entities(capture_id, type, value);
+
+    CREATE TABLE session_entities (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        type TEXT NOT NULL,
+        value TEXT NOT NULL,
+        occurrences INTEGER NOT NULL,
+        captures INTEGER NOT NULL,
+        max_confidence REAL NOT NULL,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        UNIQUE (session_id, type, value),
+        FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_session_entities_type ON session_entities(type);
+
+    INSERT INTO session_entities
+        (session_id, type, value, occurrences, captures, max_confidence, first_seen, last_seen)
+    SELECT c.session_id, e.type, e.value, SUM(e.occurrences), COUNT(*), MAX(e.confidence),
+           MIN(c.timestamp), MAX(c.timestamp)
+    FROM entities e JOIN captures c ON c.id = e.capture_id
+    GROUP BY c.session_id, e.type, e.value;
+    "#,
+    // Migration 7: Annotations returned by pipeline hooks
+    r#"
+    CREATE TABLE annotations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        capture_id INTEGER NOT NULL,
+        hook TEXT NOT NULL,
+        stage TEXT NOT NULL,
+        data TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_annotations_capture ON annotations(capture_id);
+    "#,
+    // Migration 8: Capture tags and findings
+    r#"
+    CREATE TABLE capture_tags (
+        capture_id INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (capture_id, tag),
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_capture_tags_tag ON capture_tags(tag);
+
+    CREATE TABLE findings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        capture_id INTEGER,
+        title TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        rule TEXT,
+        created_at INTEGER NOT NULL,
+        UNIQUE (session_id, rule, title),
+        FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_findings_session ON findings(session_id);
+    "#,
+    // Migration 9: Evidence attachments
+    r#"
+    CREATE TABLE attachments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        capture_id INTEGER,
+        finding_id INTEGER,
+        hash TEXT NOT NULL,
+        filename TEXT NOT NULL,
+        stored_as TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        created_at INTEGER NOT NULL,
+        FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE SET NULL,
+        FOREIGN KEY (finding_id) REFERENCES findings(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_attachments_session ON attachments(session_id);
+    "#,
+    // Migration 10: Exam proof artifacts
+    r#"
+    CREATE TABLE proofs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        capture_id INTEGER,
+        host TEXT,
+        file TEXT NOT NULL,
+        value TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        UNIQUE (session_id, value),
+        FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_proofs_session ON proofs(session_id);
+    "#,
+    // Migration 11: Host status board
+    r#"
+    CREATE TABLE host_stages (
+        session_id TEXT NOT NULL,
+        host TEXT NOT NULL,
+        stage TEXT NOT NULL,
+        source TEXT NOT NULL,
+        updated_at INTEGER NOT NULL,
+        PRIMARY KEY (session_id, host),
+        FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+    );
+    "#,
+    // Migration 12: Size of truncated capture output
+    r#"
+    ALTER TABLE captures ADD COLUMN original_size INTEGER;
+    "#,
+    // Migration 13: Coalesced repeat captures
+    r#"
+    ALTER TABLE captures ADD COLUMN repeat_count INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE captures ADD COLUMN last_repeated_at INTEGER;
+    "#,
+    // Migration 14: Cracked credentials
+    r#"
+    CREATE TABLE credentials (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        capture_id INTEGER,
+        hash_type TEXT NOT NULL,
+        hash TEXT NOT NULL,
+        username TEXT,
+        sealed BLOB NOT NULL,
+        source TEXT NOT NULL,
+        cracked_at INTEGER NOT NULL,
+        UNIQUE (session_id, hash),
+        FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE SET NULL
+    );
+    CREATE INDEX idx_credentials_session ON credentials(session_id);
+    "#,
+    // Migration 15: Issues created from findings
+    r#"
+    CREATE TABLE finding_tickets (
+        finding_id INTEGER NOT NULL,
+        target TEXT NOT NULL,
+        remote_id TEXT NOT NULL,
+        url TEXT,
+        pushed_at INTEGER NOT NULL,
+        PRIMARY KEY (finding_id, target),
+        FOREIGN KEY (finding_id) REFERENCES findings(id) ON DELETE CASCADE
+    );
+    "#,
+    // Migration 16: Cached answers to questions about a session
+    r#"
+    CREATE TABLE answer_cache (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        question TEXT NOT NULL,
+        embedding BLOB NOT NULL,
+        model TEXT NOT NULL,
+        answer TEXT NOT NULL,
+        index_state TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        hits INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_answer_cache_session ON answer_cache(session_id, model, index_state);
+    "#,
+    // Migration 17: Per-capture digests
+    r#"
+    CREATE TABLE summaries (
+        capture_id INTEGER PRIMARY KEY,
+        summary TEXT NOT NULL,
+        method TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE CASCADE
+    );
+    "#,
+    // Migration 18: Tool arguments parsed from the command line
+    r#"
+    ALTER TABLE captures ADD COLUMN target TEXT;
+    ALTER TABLE captures ADD COLUMN ports TEXT;
+    ALTER TABLE captures ADD COLUMN wordlist TEXT;
+    ALTER TABLE captures ADD COLUMN url TEXT;
+    ALTER TABLE captures ADD COLUMN service TEXT;
+    CREATE INDEX idx_captures_target ON captures(target);
+    "#,
+    // Migration 19: Environment captures ran in
+    r#"
+    CREATE TABLE capture_context (
+        capture_id INTEGER PRIMARY KEY,
+        git_root TEXT,
+        git_branch TEXT,
+        venv TEXT,
+        vpn_interface TEXT,
+        env TEXT NOT NULL DEFAULT '{}',
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE CASCADE
+    );
+    "#,
+    // Migration 20: Terminal and machine each capture came from
+    r#"
+    ALTER TABLE capture_context ADD COLUMN hostname TEXT;
+    ALTER TABLE capture_context ADD COLUMN terminal TEXT;
+    "#,
+    // Migration 21: Where each entity appears, so context can be re-derived
+    r#"
+    ALTER TABLE entities ADD COLUMN start_offset INTEGER;
+    ALTER TABLE entities ADD COLUMN end_offset INTEGER;
+    ALTER TABLE entities ADD COLUMN line INTEGER;
+    "#,
+    // Migration 22: Entity search across sessions, by type and recency
+    r#"
+    DROP INDEX idx_session_entities_type;
+    CREATE INDEX idx_session_entities_type ON session_entities(type, value);
+    CREATE INDEX idx_session_entities_last_seen ON session_entities(last_seen);
+    "#,
+    // Migration 23: Full-text index over chunks to search while the keyword
+    // index is rebuilt
+    r#"
+    CREATE VIRTUAL TABLE chunks_fts USING fts5(
+        representative_text,
+        content = 'chunks',
+        content_rowid = 'id'
+    );
+
+    CREATE TRIGGER chunks_fts_insert AFTER INSERT ON chunks BEGIN
+        INSERT INTO chunks_fts(rowid, representative_text)
+        VALUES (new.id, new.representative_text);
+    END;
+
+    CREATE TRIGGER chunks_fts_delete AFTER DELETE ON chunks BEGIN
+        INSERT INTO chunks_fts(chunks_fts, rowid, representative_text)
+        VALUES ('delete', old.id, old.representative_text);
+    END;
+
+    CREATE TRIGGER chunks_fts_update AFTER UPDATE OF representative_text ON chunks BEGIN
+        INSERT INTO chunks_fts(chunks_fts, rowid, representative_text)
+        VALUES ('delete', old.id, old.representative_text);
+        INSERT INTO chunks_fts(rowid, representative_text)
+        VALUES (new.id, new.representative_text);
+    END;
+
+    INSERT INTO chunks_fts(chunks_fts) VALUES ('rebuild');
+    "#,
+    // Migration 24: How each capture's tool was identified
+    r#"
+    ALTER TABLE captures ADD COLUMN tool_detection TEXT;
+    ALTER TABLE captures ADD COLUMN tool_confidence REAL;
+    "#,
+    // Migration 25: Per-host parts of multi-host captures, and the host
+    // each entity was found under
+    r#"
+    CREATE TABLE subcaptures (
+        capture_id INTEGER NOT NULL REFERENCES captures(id) ON DELETE CASCADE,
+        host TEXT,
+        start_line INTEGER NOT NULL,
+        end_line INTEGER NOT NULL,
+        start_offset INTEGER NOT NULL,
+        end_offset INTEGER NOT NULL,
+        PRIMARY KEY (capture_id, start_line)
+    );
+    CREATE INDEX idx_subcaptures_host ON subcaptures(host);
+
+    ALTER TABLE entities ADD COLUMN host TEXT;
+    "#,
+    // Migration 26: DNS lookups of discovered hostnames and addresses
+    r#"
+    CREATE TABLE dns_lookups (
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        answers TEXT NOT NULL DEFAULT '[]',
+        resolved_at INTEGER NOT NULL,
+        PRIMARY KEY (name, kind)
+    );
+    "#,
+    // Migration 27: Networks public addresses belong to, from a local ASN
+    // database
+    r#"
+    CREATE TABLE ip_asn (
+        ip TEXT PRIMARY KEY,
+        asn INTEGER,
+        org TEXT,
+        country TEXT,
+        annotated_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_ip_asn_asn ON ip_asn(asn);
+    "#,
+    // Migration 28: Why failed commands failed
+    r#"
+    CREATE TABLE triage_hints (
+        capture_id INTEGER PRIMARY KEY,
+        rule TEXT NOT NULL,
+        hint TEXT NOT NULL,
+        evidence TEXT NOT NULL,
+        explanation TEXT,
+        created_at INTEGER NOT NULL,
+        FOREIGN KEY (capture_id) REFERENCES captures(id) ON DELETE CASCADE
+    );
+    "#,
+    // Migration 29: How far the keyword and vector indexes have caught up
+    // with the chunks table
+    r#"
+    CREATE TABLE index_watermarks (
+        name TEXT PRIMARY KEY,
+        committed INTEGER NOT NULL,
+        pending INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    "#,
 ];
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::fixtures::capture;
+    use crate::storage::StorageManager;
     use tempfile::TempDir;
 
     #[test]
@@ -557,6 +3005,68 @@ mod tests {
         assert!(db_path.exists());
     }
 
+    #[test]
+    fn test_options_apply_to_writer_and_readers() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = SqliteOptions {
+            pool_size: 2,
+            busy_timeout: Duration::from_millis(1500),
+            wal_autocheckpoint: 500,
+            mmap_size: 0,
+            cache_size: 4 << 20,
+        };
+        let db = Database::with_options(&temp_dir.path().join("test.db"), &options).unwrap();
+
+        let pragma = |conn: &rusqlite::Connection, name: &str| -> i64 {
+            conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+                .unwrap()
+        };
+        let writer = db.get_conn().unwrap();
+        // Hold both so the second isn't reused from the first
+        let readers = [db.read_conn().unwrap(), db.read_conn().unwrap()];
+        for conn in std::iter::once(&writer).chain(&readers) {
+            assert_eq!(pragma(conn, "busy_timeout"), 1500);
+            assert_eq!(pragma(conn, "cache_size"), -4096);
+        }
+        assert_eq!(pragma(&writer, "wal_autocheckpoint"), 500);
+        assert_eq!(pragma(&writer, "foreign_keys"), 1);
+        assert_eq!(pragma(&writer, "query_only"), 0);
+        for reader in &readers {
+            assert_eq!(pragma(reader, "query_only"), 1);
+        }
+        let mode: String = writer
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+    }
+
+    #[test]
+    fn test_readers_dont_wait_for_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        db.ensure_session("s1", "first", 0, "active").unwrap();
+
+        // A long write transaction holds the only write connection
+        let writer = db.get_conn().unwrap();
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+        writer
+            .execute("UPDATE sessions SET name = 'renamed' WHERE id = 's1'", [])
+            .unwrap();
+
+        let reader = db.read_conn().unwrap();
+        let name: String = reader
+            .query_row("SELECT name FROM sessions WHERE id = 's1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(name, "first");
+        assert!(reader
+            .execute("UPDATE sessions SET name = 'sneaky' WHERE id = 's1'", [])
+            .is_err());
+
+        writer.execute_batch("COMMIT").unwrap();
+    }
+
     #[test]
     fn test_migrations() {
         let temp_dir = TempDir::new().unwrap();
@@ -573,6 +3083,55 @@ mod tests {
         assert_eq!(version, MIGRATIONS.len() as i32);
     }
 
+    #[test]
+    fn test_search_chunk_texts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let db = &storage.database;
+        capture("s1", "nmap", b"")
+            .chunks(&[
+                "22/tcp open ssh OpenSSH",
+                "80/tcp open http Apache",
+                "nothing \"here\"",
+            ])
+            .insert(&storage);
+        let conn = db.get_conn().unwrap();
+
+        let ids = |query: &str| -> Vec<i64> {
+            db.search_chunk_texts(query, 10)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()
+        };
+        assert_eq!(ids("openssh"), [1]);
+        assert_eq!(ids("apache OR"), [2]);
+        assert_eq!(ids("\"here"), [3]);
+        assert!(ids("   ").is_empty());
+
+        // Kept in step with the chunks table
+        conn.execute(
+            "UPDATE chunks SET representative_text = 'redacted' WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        conn.execute("DELETE FROM chunks WHERE id = 2", []).unwrap();
+        assert!(ids("openssh apache").is_empty());
+        assert_eq!(ids("redacted"), [1]);
+        assert_eq!(
+            db.chunk_texts_after(1, 10).unwrap(),
+            [(3, "nothing \"here\"".to_string(), None)]
+        );
+        assert_eq!(db.count_chunks().unwrap(), 2);
+
+        // Neither is embedded yet
+        drop(conn);
+        let backlog = db.embedding_backlog().unwrap();
+        assert_eq!((backlog.chunks, backlog.oldest), (2, Some(0)));
+        db.insert_embedding(1, &[0u8; 16], "test-model").unwrap();
+        assert_eq!(db.embedding_backlog().unwrap().chunks, 1);
+    }
+
     #[test]
     fn test_schema_exists() {
         let temp_dir = TempDir::new().unwrap();
@@ -589,6 +3148,13 @@ mod tests {
             "chunks",
             "embeddings",
             "entities",
+            "session_entities",
+            "annotations",
+            "capture_tags",
+            "findings",
+            "attachments",
+            "proofs",
+            "host_stages",
         ];
 
         for table in tables {
@@ -607,6 +3173,300 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_filter_stats_by_tool() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let db = &storage.database;
+        for (id, tool) in [(1, Some("nmap")), (2, Some("nmap")), (3, None)] {
+            capture("s1", "scan", b"").id(id).insert(&storage);
+            let stats = FilterStats {
+                input_lines: 100,
+                tier1_output: 50,
+                tier2_output: 10,
+                tier3_clusters: 5,
+                tier2_percentile: 0.8,
+                ..Default::default()
+            };
+            db.insert_filter_stats(id, "s1", tool, &stats).unwrap();
+        }
+
+        let by_tool = db.filter_stats_by_tool(None).unwrap();
+        assert_eq!(by_tool.len(), 2);
+        assert_eq!(by_tool[0].tool, "nmap");
+        assert_eq!(by_tool[0].captures, 2);
+        assert_eq!(by_tool[0].input_lines, 200);
+        assert!((by_tool[0].reduction() - 0.95).abs() < 1e-9);
+        assert!(db.filter_stats_by_tool(Some("other")).unwrap().is_empty());
+
+        let means = db.tier2_volume_means().unwrap();
+        assert!(means.contains(&("nmap".to_string(), 50.0)));
+    }
+
+    #[test]
+    fn test_entities_coalesce() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let db = &storage.database;
+        for (id, timestamp) in [(1, 100), (2, 200)] {
+            capture("s1", "nmap", b"")
+                .id(id)
+                .timestamp(timestamp)
+                .insert(&storage);
+        }
+
+        let ip = |confidence| {
+            (
+                "ip_address".to_string(),
+                "10.0.0.1".to_string(),
+                "host 10.0.0.1".to_string(),
+                confidence,
+            )
+        };
+        assert_eq!(
+            db.insert_entities(1, &[ip(0.5), ip(0.5), ip(0.5)]).unwrap(),
+            3
+        );
+        db.insert_entities(2, &[ip(0.6)]).unwrap();
+
+        let first = db.get_entities_for_capture(1).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].occurrences, 3);
+
+        let session = db.get_session_entities("s1", Some("ip_address")).unwrap();
+        assert_eq!(session.len(), 1);
+        assert_eq!(session[0].occurrences, 4);
+        assert_eq!(session[0].captures, 2);
+        assert_eq!(session[0].max_confidence, 0.6);
+        assert_eq!((session[0].first_seen, session[0].last_seen), (100, 200));
+        assert!((session[0].calibrated_confidence() - 0.84).abs() < 1e-6);
+        assert!(db
+            .get_session_entities("s1", Some("cve"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_entity_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let db = &storage.database;
+        for (session, id, timestamp) in [("s1", 1, 100), ("s1", 2, 200), ("s2", 3, 300)] {
+            capture(session, "nmap", b"")
+                .id(id)
+                .timestamp(timestamp)
+                .insert(&storage);
+        }
+        let entity = |entity_type: &str, value: &str| {
+            (
+                entity_type.to_string(),
+                value.to_string(),
+                String::new(),
+                0.9,
+            )
+        };
+        db.insert_entities(
+            1,
+            &[
+                entity("cve", "CVE-2021-44228"),
+                entity("ip_address", "10.0.0.1"),
+            ],
+        )
+        .unwrap();
+        db.insert_entities(
+            2,
+            &[
+                entity("cve", "CVE-2021-44228"),
+                entity("cve", "CVE-2017-0144"),
+            ],
+        )
+        .unwrap();
+        db.insert_entities(
+            3,
+            &[
+                entity("cve", "CVE-2021-44228"),
+                entity("ip_address", "10.0.0.2"),
+            ],
+        )
+        .unwrap();
+
+        let cves = db
+            .search_entities(&EntitySearch {
+                entity_type: Some("cve".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(cves.len(), 2);
+        assert_eq!(cves[0].value, "CVE-2021-44228");
+        assert_eq!((cves[0].occurrences, cves[0].sessions), (3, 2));
+        assert_eq!((cves[0].first_seen, cves[0].last_seen), (100, 300));
+
+        let recent = db
+            .search_entities(&EntitySearch {
+                session_id: Some("s1".to_string()),
+                since: Some(150),
+                ..Default::default()
+            })
+            .unwrap();
+        let values: Vec<_> = recent.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, ["CVE-2021-44228", "CVE-2017-0144"]);
+
+        let top = db.top_entities(&EntitySearch::default(), 1).unwrap();
+        let top: Vec<_> = top
+            .iter()
+            .map(|e| (e.entity_type.as_str(), e.value.as_str()))
+            .collect();
+        assert_eq!(top, [("cve", "CVE-2021-44228"), ("ip_address", "10.0.0.1")]);
+    }
+
+    #[test]
+    fn test_tags_and_findings() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let db = &storage.database;
+        capture("s1", "nxc smb dc", b"").insert(&storage);
+
+        db.add_capture_tag(1, "dc-creds").unwrap();
+        db.add_capture_tag(1, "dc-creds").unwrap();
+        assert_eq!(db.get_capture_tags(1).unwrap(), vec!["dc-creds"]);
+
+        let id = db
+            .insert_finding("s1", Some(1), "Credential on DC", "critical", Some("dc"))
+            .unwrap();
+        assert!(id.is_some());
+        let again = db
+            .insert_finding("s1", Some(1), "Credential on DC", "critical", Some("dc"))
+            .unwrap();
+        assert!(again.is_none());
+
+        let findings = db.list_findings(Some("s1")).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, "critical");
+        assert!(db.list_findings(Some("other")).unwrap().is_empty());
+
+        let id = id.unwrap();
+        assert!(db.get_finding_ticket(id, "jira").unwrap().is_none());
+        let mut ticket = FindingTicketRecord {
+            finding_id: id,
+            target: "jira".to_string(),
+            remote_id: "SEC-1".to_string(),
+            url: None,
+            pushed_at: 1,
+        };
+        db.set_finding_ticket(&ticket).unwrap();
+        ticket.url = Some("https://jira.example.com/browse/SEC-1".to_string());
+        db.set_finding_ticket(&ticket).unwrap();
+        assert_eq!(db.get_finding_ticket(id, "jira").unwrap(), Some(ticket));
+        assert!(db.get_finding_ticket(id, "gitlab").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_proofs() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let db = &storage.database;
+        for id in [1, 2] {
+            capture("s1", "cat proof.txt", b"").id(id).insert(&storage);
+        }
+        db.insert_entities(
+            1,
+            &[
+                (
+                    "ip_address".to_string(),
+                    "10.11.1.5".to_string(),
+                    String::new(),
+                    0.9,
+                ),
+                (
+                    "ip_address".to_string(),
+                    "127.0.0.1".to_string(),
+                    String::new(),
+                    0.9,
+                ),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            db.last_session_host("s1", 2).unwrap().as_deref(),
+            Some("10.11.1.5")
+        );
+        assert!(db.last_session_host("s1", 1).unwrap().is_none());
+
+        let id = db
+            .insert_proof("s1", 2, Some("10.11.1.5"), "proof.txt", "abc")
+            .unwrap();
+        assert!(id.is_some());
+        // Printing the same proof again isn't a new one
+        let again = db
+            .insert_proof("s1", 2, Some("10.11.1.5"), "proof.txt", "abc")
+            .unwrap();
+        assert!(again.is_none());
+
+        let proofs = db.list_proofs(Some("s1")).unwrap();
+        assert_eq!(proofs.len(), 1);
+        assert_eq!(proofs[0].host.as_deref(), Some("10.11.1.5"));
+    }
+
+    #[test]
+    fn test_coalesce_repeat() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().join("data")).unwrap();
+        let db = &storage.database;
+        let h1 = capture("s1", "id", b"uid=0(root)")
+            .timestamp(100)
+            .insert(&storage)
+            .hash;
+
+        assert_eq!(
+            db.coalesce_repeat("s1", "id", &h1, 110, 90).unwrap(),
+            Some(1)
+        );
+        // The window slides with each repeat
+        assert_eq!(
+            db.coalesce_repeat("s1", "id", &h1, 125, 105).unwrap(),
+            Some(1)
+        );
+        assert_eq!(db.get_capture(1).unwrap().unwrap().repeat_count, 3);
+
+        // Different output, or too long ago
+        assert_eq!(
+            db.coalesce_repeat("s1", "id", "h2", 130, 110).unwrap(),
+            None
+        );
+        assert_eq!(db.coalesce_repeat("s1", "id", &h1, 200, 180).unwrap(), None);
+    }
+
+    #[test]
+    fn test_host_stages() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        db.ensure_session("s1", "exam", 0, "active").unwrap();
+
+        assert!(db
+            .advance_host_stage("s1", "10.0.0.5", HostStage::Enumerating)
+            .unwrap());
+        assert!(db
+            .advance_host_stage("s1", "10.0.0.5", HostStage::Foothold)
+            .unwrap());
+        // Never moved back automatically
+        assert!(!db
+            .advance_host_stage("s1", "10.0.0.5", HostStage::NotStarted)
+            .unwrap());
+
+        db.set_host_stage("s1", "10.0.0.5", HostStage::Enumerating)
+            .unwrap();
+        db.advance_host_stage("s1", "10.0.0.7", HostStage::NotStarted)
+            .unwrap();
+
+        let board = db.list_host_stages("s1").unwrap();
+        assert_eq!(board.len(), 2);
+        assert_eq!(board[0].host, "10.0.0.5");
+        assert_eq!(board[0].stage, HostStage::Enumerating);
+        assert!(board[0].manual);
+        assert_eq!(board[1].stage, HostStage::NotStarted);
+    }
+
     #[test]
     fn test_foreign_keys_enabled() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,216 @@
+//! DNS lookups of discovered hosts
+//!
+//! Each hostname captures mention is looked up forward and each address in
+//! reverse, once per refresh period, by the daemon's enrichment pass. The
+//! answers are kept by name rather than per session: the same host seen in
+//! two engagements resolved the same way when it was last looked up.
+
+use super::StorageManager;
+use crate::error::{Result, YinxError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// Direction of a lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LookupKind {
+    /// Hostname to addresses
+    #[serde(rename = "A")]
+    Forward,
+    /// Address to hostnames
+    #[serde(rename = "PTR")]
+    Reverse,
+}
+
+impl LookupKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LookupKind::Forward => "A",
+            LookupKind::Reverse => "PTR",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "A" => Some(LookupKind::Forward),
+            "PTR" => Some(LookupKind::Reverse),
+            _ => None,
+        }
+    }
+
+    /// Lookup made for an entity of `entity_type`
+    pub fn for_entity(entity_type: &str) -> Option<Self> {
+        match entity_type {
+            "hostname" => Some(LookupKind::Forward),
+            "ip_address" => Some(LookupKind::Reverse),
+            _ => None,
+        }
+    }
+}
+
+/// What looking up a name or address returned, and when
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsLookup {
+    pub name: String,
+    pub kind: LookupKind,
+    /// Empty when nothing resolved, or the name wasn't worth a lookup
+    pub answers: Vec<String>,
+    pub resolved_at: i64,
+}
+
+impl StorageManager {
+    /// Hostnames and addresses never looked up, or last looked up before
+    /// `stale_before`, those never looked up first
+    pub fn dns_candidates(
+        &self,
+        stale_before: i64,
+        limit: usize,
+    ) -> Result<Vec<(LookupKind, String)>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT se.type, se.value FROM session_entities se
+             LEFT JOIN dns_lookups d ON d.name = se.value
+                AND d.kind = CASE se.type WHEN 'ip_address' THEN 'PTR' ELSE 'A' END
+             WHERE se.type IN ('hostname', 'ip_address')
+                AND (d.name IS NULL OR d.resolved_at < ?1)
+             GROUP BY se.type, se.value
+             ORDER BY COALESCE(MAX(d.resolved_at), 0), MIN(se.first_seen), se.value
+             LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![stale_before, limit as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(entity_type, value)| Some((LookupKind::for_entity(&entity_type)?, value)))
+            .collect())
+    }
+
+    /// Record a lookup, replacing the last one of the same name and kind
+    pub fn store_dns_lookup(&self, lookup: &DnsLookup) -> Result<()> {
+        let answers = serde_json::to_string(&lookup.answers).map_err(|e| YinxError::Json {
+            source: e,
+            context: "Failed to serialize DNS answers".to_string(),
+        })?;
+        let conn = self.database.get_conn()?;
+        conn.execute(
+            "INSERT INTO dns_lookups (name, kind, answers, resolved_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name, kind) DO UPDATE SET
+                answers = excluded.answers, resolved_at = excluded.resolved_at",
+            params![
+                lookup.name,
+                lookup.kind.as_str(),
+                answers,
+                lookup.resolved_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every lookup that resolved to something, by name
+    pub fn dns_lookups(&self) -> Result<Vec<DnsLookup>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT name, kind, answers, resolved_at FROM dns_lookups
+             WHERE answers != '[]' ORDER BY name, kind",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut lookups = Vec::new();
+        for (name, kind, answers, resolved_at) in rows {
+            let Some(kind) = LookupKind::parse(&kind) else {
+                continue;
+            };
+            let answers = serde_json::from_str(&answers).map_err(|e| YinxError::Json {
+                source: e,
+                context: format!("Invalid DNS answers stored for {}", name),
+            })?;
+            lookups.push(DnsLookup {
+                name,
+                kind,
+                answers,
+                resolved_at,
+            });
+        }
+        Ok(lookups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_dns_candidates() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "s1", 0, "active")
+            .unwrap();
+        let conn = storage.database.get_conn().unwrap();
+        for (entity_type, value, first_seen) in [
+            ("hostname", "dc01.corp.local", 10),
+            ("ip_address", "10.0.0.5", 20),
+            ("port", "445/tcp", 30),
+        ] {
+            conn.execute(
+                "INSERT INTO session_entities (session_id, type, value, occurrences, captures,
+                    max_confidence, first_seen, last_seen)
+                 VALUES ('s1', ?1, ?2, 1, 1, 1.0, ?3, ?3)",
+                params![entity_type, value, first_seen],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        assert_eq!(
+            storage.dns_candidates(1_000, 10).unwrap(),
+            vec![
+                (LookupKind::Forward, "dc01.corp.local".to_string()),
+                (LookupKind::Reverse, "10.0.0.5".to_string()),
+            ]
+        );
+
+        storage
+            .store_dns_lookup(&DnsLookup {
+                name: "dc01.corp.local".to_string(),
+                kind: LookupKind::Forward,
+                answers: vec!["10.0.0.5".to_string()],
+                resolved_at: 500,
+            })
+            .unwrap();
+        storage
+            .store_dns_lookup(&DnsLookup {
+                name: "10.0.0.5".to_string(),
+                kind: LookupKind::Reverse,
+                answers: Vec::new(),
+                resolved_at: 2_000,
+            })
+            .unwrap();
+        // The forward lookup is stale, the reverse one isn't
+        assert_eq!(
+            storage.dns_candidates(1_000, 10).unwrap(),
+            vec![(LookupKind::Forward, "dc01.corp.local".to_string())]
+        );
+        assert!(storage.dns_candidates(400, 10).unwrap().is_empty());
+
+        // Lookups that found nothing aren't listed
+        let lookups = storage.dns_lookups().unwrap();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(lookups[0].answers, ["10.0.0.5"]);
+        assert_eq!(lookups[0].resolved_at, 500);
+    }
+}
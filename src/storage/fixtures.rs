@@ -0,0 +1,154 @@
+//! Test fixtures
+//!
+//! Stored history set up the way the capture pipeline leaves it, for tests
+//! that need sessions, blobs and captures without running output through
+//! the whole pipeline.
+
+use super::StorageManager;
+use rusqlite::params;
+
+/// A capture to insert; start one with [`capture`]
+pub(crate) struct Capture<'a> {
+    session_id: &'a str,
+    command: &'a str,
+    output: &'a [u8],
+    id: Option<i64>,
+    timestamp: i64,
+    tool: Option<&'a str>,
+    target: Option<&'a str>,
+    exit_code: Option<i32>,
+    cwd: Option<&'a str>,
+    username: Option<&'a str>,
+    chunks: Vec<&'a str>,
+}
+
+/// What [`Capture::insert`] stored
+pub(crate) struct Inserted {
+    pub id: i64,
+    pub hash: String,
+    pub chunk_ids: Vec<i64>,
+}
+
+/// A capture of `output` by `command` in `session_id`
+pub(crate) fn capture<'a>(session_id: &'a str, command: &'a str, output: &'a [u8]) -> Capture<'a> {
+    Capture {
+        session_id,
+        command,
+        output,
+        id: None,
+        timestamp: 0,
+        tool: None,
+        target: None,
+        exit_code: None,
+        cwd: None,
+        username: None,
+        chunks: Vec::new(),
+    }
+}
+
+impl<'a> Capture<'a> {
+    pub fn id(mut self, id: i64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn tool(mut self, tool: &'a str) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    pub fn target(mut self, target: &'a str) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = Some(exit_code);
+        self
+    }
+
+    /// The user and working directory the shell prompt showed
+    pub fn prompt(mut self, username: &'a str, cwd: &'a str) -> Self {
+        self.username = Some(username);
+        self.cwd = Some(cwd);
+        self
+    }
+
+    /// Chunks to store for the capture, one per representative text
+    pub fn chunks(mut self, texts: &[&'a str]) -> Self {
+        self.chunks.extend_from_slice(texts);
+        self
+    }
+
+    /// Store the output blob, the capture and its chunks
+    ///
+    /// The session is created as active unless it exists already; use
+    /// `Database::ensure_session` first for another status.
+    pub fn insert(self, storage: &StorageManager) -> Inserted {
+        let database = &storage.database;
+        database
+            .ensure_session(self.session_id, self.session_id, 0, "active")
+            .unwrap();
+        let (hash, compressed, _) = storage.blob_store.write(self.output).unwrap();
+
+        let conn = database.get_conn().unwrap();
+        conn.execute(
+            "INSERT INTO blobs (hash, size, created_at, compressed, ref_count)
+             VALUES (?1, ?2, 0, ?3, 1)
+             ON CONFLICT(hash) DO UPDATE SET ref_count = ref_count + 1",
+            params![&hash, self.output.len() as i64, compressed],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO captures
+             (id, session_id, timestamp, command, output_hash, tool, target, exit_code,
+              cwd, username)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                self.id,
+                self.session_id,
+                self.timestamp,
+                self.command,
+                &hash,
+                self.tool,
+                self.target,
+                self.exit_code,
+                self.cwd,
+                self.username
+            ],
+        )
+        .unwrap();
+        let id = conn.last_insert_rowid();
+        conn.execute(
+            "UPDATE sessions SET capture_count = capture_count + 1 WHERE id = ?1",
+            params![self.session_id],
+        )
+        .unwrap();
+        drop(conn);
+
+        let chunks: Vec<(String, i32, String)> = self
+            .chunks
+            .iter()
+            .map(|text| (text.to_string(), 1, "{}".to_string()))
+            .collect();
+        let chunk_ids = if chunks.is_empty() {
+            Vec::new()
+        } else {
+            database
+                .replace_capture_chunks(id, &hash, &chunks)
+                .unwrap()
+                .1
+        };
+
+        Inserted {
+            id,
+            hash,
+            chunk_ids,
+        }
+    }
+}
@@ -0,0 +1,454 @@
+//! Host relationship graph
+//!
+//! Hosts are nodes; two hosts share an edge when they appear in the same
+//! capture, weighted by how many captures they share. The web UI draws this
+//! graph and the library facade hands it to scripts.
+//!
+//! An `as_of` time builds the graph from captures taken up to then, to show
+//! what was known at that point, say when a client asked for testing to stop.
+//!
+//! Nodes are also grouped by IPv4 subnet, with how many hosts in each were
+//! scanned and how many answered with an open port, so `yinx hosts` can
+//! show which parts of the network still need enumerating.
+//!
+//! The correlation graph goes further, replaying each capture's entities in
+//! time order into what is known about every host: its ports, services and
+//! versions, with the history of captures that disagreed on them.
+
+use super::StorageManager;
+use crate::entities::{AsnInfo, CorrelationGraph, Entity};
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::net::Ipv4Addr;
+
+/// Entity types that name a host
+pub const HOST_ENTITY_TYPES: &[&str] = &["ip_address", "ip_address_v6", "hostname"];
+
+/// Prefix length hosts are grouped by unless asked otherwise
+pub const DEFAULT_SUBNET_PREFIX: u8 = 24;
+
+/// A host and the number of captures it appears in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostNode {
+    pub id: String,
+    /// Entity type the host was first seen as
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub captures: usize,
+    /// The /24 an IPv4 host is in
+    pub subnet: Option<String>,
+    /// Whether a command was aimed at the host, directly or by a range
+    pub scanned: bool,
+    /// Ports captures last saw open
+    pub open_ports: Vec<u16>,
+    /// Network a public address belongs to
+    pub asn: Option<AsnInfo>,
+}
+
+/// Two hosts seen in `weight` captures together
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostEdge {
+    pub source: String,
+    pub target: String,
+    pub weight: usize,
+}
+
+/// Hosts of one subnet and how far enumeration of them has got
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubnetCoverage {
+    /// None for hosts known only by name or by IPv6 address
+    pub subnet: Option<String>,
+    pub hosts: Vec<String>,
+    /// Hosts a command was aimed at
+    pub scanned: usize,
+    /// Hosts with an open port
+    pub responsive: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HostGraph {
+    pub nodes: Vec<HostNode>,
+    pub edges: Vec<HostEdge>,
+    /// Nodes grouped by /24, subnets in address order and named hosts last
+    pub subnets: Vec<SubnetCoverage>,
+}
+
+impl HostGraph {
+    /// Group the nodes by IPv4 subnets of `prefix` bits
+    pub fn group_by_subnet(&self, prefix: u8) -> Vec<SubnetCoverage> {
+        let mut subnets: BTreeMap<Ipv4Addr, Vec<&HostNode>> = BTreeMap::new();
+        let mut named = Vec::new();
+        for node in &self.nodes {
+            match network_of(&node.id, prefix) {
+                Some(network) => subnets.entry(network).or_default().push(node),
+                None => named.push(node),
+            }
+        }
+        let coverage = |subnet: Option<String>, mut nodes: Vec<&HostNode>| {
+            nodes.sort_by_key(|n| (n.id.parse::<Ipv4Addr>().ok(), n.id.clone()));
+            SubnetCoverage {
+                subnet,
+                scanned: nodes.iter().filter(|n| n.scanned).count(),
+                responsive: nodes.iter().filter(|n| !n.open_ports.is_empty()).count(),
+                hosts: nodes.into_iter().map(|n| n.id.clone()).collect(),
+            }
+        };
+        let mut groups: Vec<SubnetCoverage> = subnets
+            .into_iter()
+            .map(|(network, nodes)| {
+                coverage(Some(format!("{}/{}", network, prefix.min(32))), nodes)
+            })
+            .collect();
+        if !named.is_empty() {
+            groups.push(coverage(None, named));
+        }
+        groups
+    }
+}
+
+/// The network address of the `prefix`-bit IPv4 subnet `host` is in
+fn network_of(host: &str, prefix: u8) -> Option<Ipv4Addr> {
+    let address: Ipv4Addr = host.parse().ok()?;
+    let mask = u32::MAX
+        .checked_shl(32 - u32::from(prefix.min(32)))
+        .unwrap_or(0);
+    Some(Ipv4Addr::from(u32::from(address) & mask))
+}
+
+/// The `prefix`-bit IPv4 subnet `host` is in, e.g. `10.0.0.0/24`
+pub fn subnet_of(host: &str, prefix: u8) -> Option<String> {
+    network_of(host, prefix).map(|network| format!("{}/{}", network, prefix.min(32)))
+}
+
+impl StorageManager {
+    /// Replay the entities of one session's captures, or every session's
+    /// when None, into a correlation graph, oldest capture first
+    ///
+    /// A multi-host capture's entities are correlated with the host whose
+    /// section they were found in, not with every host in the capture.
+    /// Hosts DNS resolved to each other are linked as aliases, and public
+    /// addresses carry the network they belong to.
+    pub fn correlation_graph(
+        &self,
+        session_id: Option<&str>,
+        as_of: Option<i64>,
+    ) -> Result<CorrelationGraph> {
+        let mut captures = match session_id {
+            Some(id) => self.database.get_session_captures(id)?,
+            None => self.database.get_captures_after(0)?,
+        };
+        captures.retain(|c| as_of.is_none_or(|as_of| c.timestamp <= as_of));
+        captures.sort_by_key(|c| (c.timestamp, c.id));
+
+        let mut graph = CorrelationGraph::new();
+        for capture in captures {
+            let mut by_host: BTreeMap<Option<String>, Vec<Entity>> = BTreeMap::new();
+            for record in self.database.get_entities_for_capture(capture.id)? {
+                by_host
+                    .entry(record.host.clone())
+                    .or_default()
+                    .push(Entity {
+                        entity_type: record.entity_type,
+                        value: record.value,
+                        context: record.context.unwrap_or_default(),
+                        start: record.start.unwrap_or_default(),
+                        end: record.end.unwrap_or_default(),
+                        confidence: record.confidence,
+                        should_redact: false,
+                    });
+            }
+            for (host, mut entities) in by_host {
+                if let Some(host) = host {
+                    if !entities.iter().any(|e| e.value == host) {
+                        entities.push(Entity {
+                            entity_type: "hostname".to_string(),
+                            value: host,
+                            context: String::new(),
+                            start: 0,
+                            end: 0,
+                            confidence: 1.0,
+                            should_redact: false,
+                        });
+                    }
+                }
+                graph.process_entities(&entities, capture.timestamp);
+            }
+        }
+        for lookup in self.dns_lookups()? {
+            for answer in &lookup.answers {
+                graph.link_alias(&lookup.name, answer, lookup.resolved_at);
+            }
+        }
+        for (ip, asn) in self.asn_annotations()? {
+            graph.annotate_asn(&ip, asn);
+        }
+        Ok(graph)
+    }
+
+    /// Build the host graph of one session, or of every session when None,
+    /// from captures taken at or before `as_of` when set
+    pub fn host_graph(&self, session_id: Option<&str>, as_of: Option<i64>) -> Result<HostGraph> {
+        let mut taken = match session_id {
+            Some(id) => self.database.get_session_captures(id)?,
+            None => self.database.get_captures_after(0)?,
+        };
+        taken.retain(|c| as_of.is_none_or(|as_of| c.timestamp <= as_of));
+        let captures: Option<HashSet<i64>> = match (session_id, as_of) {
+            (None, None) => None,
+            _ => Some(taken.iter().map(|c| c.id).collect()),
+        };
+
+        let mut by_capture: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+        let mut nodes: BTreeMap<String, (String, usize)> = BTreeMap::new();
+        for entity_type in HOST_ENTITY_TYPES {
+            for entity in self.database.get_entities_by_type(entity_type)? {
+                if captures
+                    .as_ref()
+                    .is_some_and(|ids| !ids.contains(&entity.capture_id))
+                {
+                    continue;
+                }
+                let hosts = by_capture.entry(entity.capture_id).or_default();
+                if hosts.contains(&entity.value) {
+                    continue;
+                }
+                hosts.push(entity.value.clone());
+                nodes
+                    .entry(entity.value)
+                    .or_insert_with(|| (entity_type.to_string(), 0))
+                    .1 += 1;
+            }
+        }
+
+        let mut edges: BTreeMap<(String, String), usize> = BTreeMap::new();
+        for hosts in by_capture.values() {
+            for (i, a) in hosts.iter().enumerate() {
+                for b in &hosts[i + 1..] {
+                    let key = if a < b {
+                        (a.clone(), b.clone())
+                    } else {
+                        (b.clone(), a.clone())
+                    };
+                    *edges.entry(key).or_default() += 1;
+                }
+            }
+        }
+
+        let correlation = self.correlation_graph(session_id, as_of)?;
+        let nodes = nodes
+            .into_iter()
+            .map(|(id, (kind, captures))| HostNode {
+                subnet: subnet_of(&id, DEFAULT_SUBNET_PREFIX),
+                scanned: taken.iter().any(|c| c.arguments.targets(&id)),
+                open_ports: correlation
+                    .get_host(&id)
+                    .map(|h| h.open_ports())
+                    .unwrap_or_default(),
+                asn: correlation.get_host(&id).and_then(|h| h.asn.clone()),
+                id,
+                kind,
+                captures,
+            })
+            .collect();
+
+        let mut graph = HostGraph {
+            nodes,
+            edges: edges
+                .into_iter()
+                .map(|((source, target), weight)| HostEdge {
+                    source,
+                    target,
+                    weight,
+                })
+                .collect(),
+            subnets: Vec::new(),
+        };
+        graph.subnets = graph.group_by_subnet(DEFAULT_SUBNET_PREFIX);
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_host_graph() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        for (id, session, timestamp, hosts) in [
+            (1, "s1", 100, vec!["10.0.0.1", "10.0.0.2"]),
+            (2, "s1", 200, vec!["10.0.0.2", "10.0.0.1", "dc01.local"]),
+            (3, "s2", 300, vec!["10.0.0.9"]),
+        ] {
+            capture(session, "nmap", b"")
+                .id(id)
+                .timestamp(timestamp)
+                .insert(&storage);
+            let entities: Vec<_> = hosts
+                .iter()
+                .map(|h| {
+                    let kind = if h.starts_with("10.") {
+                        "ip_address"
+                    } else {
+                        "hostname"
+                    };
+                    (kind.to_string(), h.to_string(), String::new(), 0.9)
+                })
+                .collect();
+            storage.database.insert_entities(id, &entities).unwrap();
+        }
+
+        let graph = storage.host_graph(Some("s1"), None).unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.nodes[0].id, "10.0.0.1");
+        assert_eq!(graph.nodes[0].captures, 2);
+        assert_eq!(graph.nodes[2].kind, "hostname");
+        let pair = graph
+            .edges
+            .iter()
+            .find(|e| e.source == "10.0.0.1" && e.target == "10.0.0.2")
+            .unwrap();
+        assert_eq!(pair.weight, 2);
+
+        assert_eq!(storage.host_graph(None, None).unwrap().nodes.len(), 4);
+
+        // As of the first capture, the domain controller wasn't known yet
+        let before = storage.host_graph(Some("s1"), Some(150)).unwrap();
+        assert_eq!(before.nodes.len(), 2);
+        assert_eq!(before.nodes[0].captures, 1);
+        assert_eq!(before.edges[0].weight, 1);
+        let everywhere = storage.host_graph(None, Some(250)).unwrap();
+        assert!(everywhere.nodes.iter().all(|n| n.id != "10.0.0.9"));
+        assert_eq!(everywhere.nodes.len(), 3);
+    }
+
+    #[test]
+    fn test_subnet_coverage() {
+        assert_eq!(
+            subnet_of("10.10.10.37", 24).as_deref(),
+            Some("10.10.10.0/24")
+        );
+        assert_eq!(
+            subnet_of("10.10.10.37", 16).as_deref(),
+            Some("10.10.0.0/16")
+        );
+        assert_eq!(subnet_of("10.10.10.37", 0).as_deref(), Some("0.0.0.0/0"));
+        assert_eq!(subnet_of("dc01.corp.local", 24), None);
+
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        // A sweep of one /24 that found three hosts, one with a port open,
+        // and a host on another network nobody has scanned yet
+        capture("s1", "nmap -sn 10.0.0.0/24", b"")
+            .id(1)
+            .timestamp(100)
+            .target("10.0.0.0/24")
+            .insert(&storage);
+        capture("s1", "nmap", b"")
+            .id(2)
+            .timestamp(200)
+            .insert(&storage);
+        let entity = |kind: &str, value: &str, context: &str| {
+            (
+                kind.to_string(),
+                value.to_string(),
+                context.to_string(),
+                0.9,
+            )
+        };
+        storage
+            .database
+            .insert_entities(
+                1,
+                &[
+                    entity("ip_address", "10.0.0.20", ""),
+                    entity("ip_address", "10.0.0.3", ""),
+                    entity("ip_address", "10.0.0.5", ""),
+                ],
+            )
+            .unwrap();
+        storage
+            .database
+            .insert_entities(
+                2,
+                &[
+                    entity("ip_address", "10.0.0.5", ""),
+                    entity("port", "445/tcp", "445/tcp open microsoft-ds\n"),
+                    entity("ip_address", "172.16.1.9", ""),
+                    entity("hostname", "dc01.corp.local", ""),
+                ],
+            )
+            .unwrap();
+
+        let graph = storage.host_graph(Some("s1"), None).unwrap();
+        let subnets: Vec<_> = graph.subnets.iter().map(|s| s.subnet.as_deref()).collect();
+        assert_eq!(subnets, [Some("10.0.0.0/24"), Some("172.16.1.0/24"), None]);
+        let lab = &graph.subnets[0];
+        assert_eq!(lab.hosts, ["10.0.0.3", "10.0.0.5", "10.0.0.20"]);
+        assert_eq!(lab.scanned, 3);
+        assert_eq!(lab.responsive, 1);
+        assert_eq!(graph.subnets[1].scanned, 0);
+
+        let wide = graph.group_by_subnet(8);
+        assert_eq!(wide[0].subnet.as_deref(), Some("10.0.0.0/8"));
+        assert_eq!(wide[0].hosts.len(), 3);
+    }
+
+    #[test]
+    fn test_correlation_graph_keeps_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        // Inserted newest first; replayed oldest first
+        for (id, timestamp, line) in [
+            (1, 200, "22/tcp closed ssh\n"),
+            (2, 100, "22/tcp open  ssh OpenSSH 8.2p1\n"),
+        ] {
+            capture("s1", "nmap", b"")
+                .id(id)
+                .timestamp(timestamp)
+                .insert(&storage);
+            let entities = vec![
+                (
+                    "ip_address".to_string(),
+                    "10.0.0.5".to_string(),
+                    String::new(),
+                    0.9,
+                ),
+                (
+                    "port".to_string(),
+                    "22/tcp".to_string(),
+                    line.to_string(),
+                    0.9,
+                ),
+            ];
+            storage.database.insert_entities(id, &entities).unwrap();
+        }
+
+        let graph = storage.correlation_graph(Some("s1"), None).unwrap();
+        let host = graph.get_host("10.0.0.5").unwrap();
+        assert!(host.open_ports().is_empty());
+        let conflicts = host.conflicts();
+        assert_eq!(conflicts[0].subject, "port 22");
+        assert_eq!(conflicts[0].history[0].value, "open");
+        assert_eq!(conflicts[0].resolved.value, "closed");
+
+        let before = storage.correlation_graph(Some("s1"), Some(150)).unwrap();
+        assert_eq!(before.get_host("10.0.0.5").unwrap().open_ports(), vec![22]);
+
+        storage
+            .store_dns_lookup(&crate::storage::DnsLookup {
+                name: "10.0.0.5".to_string(),
+                kind: crate::storage::LookupKind::Reverse,
+                answers: vec!["web01.corp.local".to_string()],
+                resolved_at: 300,
+            })
+            .unwrap();
+        let graph = storage.correlation_graph(Some("s1"), None).unwrap();
+        let host = graph.get_host("10.0.0.5").unwrap();
+        assert_eq!(host.aliases.get("web01.corp.local"), Some(&300));
+    }
+}
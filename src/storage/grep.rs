@@ -0,0 +1,269 @@
+//! Regex search over stored output
+//!
+//! `yinx grep` scans the output blobs themselves rather than the chunks the
+//! indexes hold, for when an exact pattern matters more than ranking. Blobs
+//! are decompressed as a stream and read a line at a time on a pool of
+//! threads, and matches are reported in capture order.
+
+use super::{CaptureRecord, StorageManager};
+use crate::error::{Result, YinxError};
+use rayon::prelude::*;
+use regex::bytes::Regex;
+use std::io::BufRead;
+
+/// Matched lines longer than this are cut short
+const MAX_LINE_BYTES: usize = 1024;
+
+/// Which captures to scan and how
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    /// Only this session's captures
+    pub session_id: Option<String>,
+    /// Only captures of this tool
+    pub tool: Option<String>,
+    /// Threads scanning blobs; 0 for one per CPU
+    pub jobs: usize,
+    /// Bytes of matched lines held before they are reported; a capture's
+    /// matches beyond its share are counted but not kept
+    pub memory_limit: usize,
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        Self {
+            session_id: None,
+            tool: None,
+            jobs: 0,
+            memory_limit: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// A matching line of output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepLine {
+    /// 1-based line number in the stored output
+    pub number: usize,
+    pub text: String,
+}
+
+/// A capture with matching lines
+#[derive(Debug, Clone)]
+pub struct GrepHit {
+    pub capture: CaptureRecord,
+    pub lines: Vec<GrepLine>,
+    /// Matches not kept because of the memory limit
+    pub dropped: usize,
+}
+
+/// Totals of a search
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GrepStats {
+    pub captures_scanned: usize,
+    pub captures_matched: usize,
+    pub matches: usize,
+    /// Captures whose output blob is missing
+    pub missing_blobs: usize,
+}
+
+/// What one capture's scan kept
+struct Scanned {
+    lines: Vec<GrepLine>,
+    dropped: usize,
+}
+
+impl StorageManager {
+    /// Scan stored output for `regex`, handing each matching capture to
+    /// `on_hit` in capture order
+    pub fn grep(
+        &self,
+        regex: &Regex,
+        options: &GrepOptions,
+        mut on_hit: impl FnMut(GrepHit) -> Result<()>,
+    ) -> Result<GrepStats> {
+        let captures: Vec<CaptureRecord> = match &options.session_id {
+            Some(session_id) => self.database.get_session_captures(session_id)?,
+            None => self.database.get_captures_after(0)?,
+        }
+        .into_iter()
+        .filter(|capture| {
+            options
+                .tool
+                .as_ref()
+                .is_none_or(|tool| capture.tool.as_ref() == Some(tool))
+        })
+        .collect();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(options.jobs)
+            .build()
+            .map_err(|e| YinxError::Other(e.into()))?;
+        let batch = pool.current_num_threads() * 4;
+        let budget = options.memory_limit / batch;
+
+        let mut stats = GrepStats::default();
+        for captures in captures.chunks(batch) {
+            let scanned: Vec<Result<Option<Scanned>>> = pool.install(|| {
+                captures
+                    .par_iter()
+                    .map(|capture| self.grep_capture(capture, regex, budget))
+                    .collect()
+            });
+            for (capture, scanned) in captures.iter().zip(scanned) {
+                stats.captures_scanned += 1;
+                let Some(Scanned { lines, dropped }) = scanned? else {
+                    stats.missing_blobs += 1;
+                    continue;
+                };
+                let total = lines.len() + dropped;
+                if total == 0 {
+                    continue;
+                }
+                stats.captures_matched += 1;
+                stats.matches += total;
+                on_hit(GrepHit {
+                    capture: capture.clone(),
+                    lines,
+                    dropped,
+                })?;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Matching lines of one capture kept within `budget` bytes, and the
+    /// number of matches dropped; None when its blob is missing
+    fn grep_capture(
+        &self,
+        capture: &CaptureRecord,
+        regex: &Regex,
+        budget: usize,
+    ) -> Result<Option<Scanned>> {
+        if !self.blob_store.exists(&capture.output_hash) {
+            tracing::warn!("Blob {} missing while searching", capture.output_hash);
+            return Ok(None);
+        }
+        let mut reader = self.blob_store.reader(&capture.output_hash)?;
+
+        let mut lines = Vec::new();
+        let mut held = 0;
+        let mut dropped = 0;
+        let mut line = Vec::new();
+        let mut number = 0;
+        loop {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .map_err(|e| YinxError::Io {
+                    source: e,
+                    context: format!("Failed to read blob {}", capture.output_hash),
+                })?;
+            if read == 0 {
+                break;
+            }
+            number += 1;
+            let text = line.strip_suffix(b"\n").unwrap_or(&line);
+            let text = text.strip_suffix(b"\r").unwrap_or(text);
+            if !regex.is_match(text) {
+                continue;
+            }
+            let kept = &text[..text.len().min(MAX_LINE_BYTES)];
+            if held + kept.len() > budget {
+                dropped += 1;
+                continue;
+            }
+            held += kept.len();
+            let mut kept_text = String::from_utf8_lossy(kept).into_owned();
+            if kept.len() < text.len() {
+                kept_text.push('…');
+            }
+            lines.push(GrepLine {
+                number,
+                text: kept_text,
+            });
+        }
+        Ok(Some(Scanned { lines, dropped }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::CaptureEvent;
+    use crate::Yinx;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_grep() {
+        let temp_dir = TempDir::new().unwrap();
+        let yinx = Yinx::open(temp_dir.path()).unwrap();
+        let session_id = yinx.start_session("grep").unwrap().id.to_string();
+        // Long enough to be stored compressed
+        let listing: String = (0..400)
+            .map(|i| format!("/files/{:03} (Status: 200) [Size: {}]\n", i, 1000 + i))
+            .collect();
+        let captures = [
+            (
+                "nmap -sV 10.0.0.5",
+                "22/tcp open ssh OpenSSH 8.2p1\n80/tcp open http\n".to_string(),
+            ),
+            ("gobuster dir -u http://10.0.0.5", listing),
+        ];
+        for (i, (command, output)) in captures.into_iter().enumerate() {
+            let event = CaptureEvent {
+                session_id: session_id.clone(),
+                timestamp: 1_700_000_000 + i as i64,
+                command: command.to_string(),
+                output,
+                exit_code: 0,
+                cwd: "/tmp".to_string(),
+                username: None,
+                source: None,
+                tool: None,
+                context: None,
+            };
+            yinx.ingest(event).await.unwrap();
+        }
+        let storage = yinx.storage();
+
+        let mut hits = Vec::new();
+        let regex = Regex::new(r"OpenSSH \d|/files/04[0-2]").unwrap();
+        let stats = storage
+            .grep(&regex, &GrepOptions::default(), |hit| {
+                hits.push(hit);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(stats.captures_scanned, 2);
+        assert_eq!(stats.matches, 4);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].capture.tool.as_deref(), Some("nmap"));
+        assert_eq!(
+            hits[0].lines,
+            [GrepLine {
+                number: 1,
+                text: "22/tcp open ssh OpenSSH 8.2p1".to_string()
+            }]
+        );
+        assert_eq!(hits[1].lines[0].number, 41);
+
+        // Filtered by tool, and over the memory limit
+        let options = GrepOptions {
+            tool: Some("gobuster".to_string()),
+            jobs: 1,
+            memory_limit: 4 * 40,
+            ..Default::default()
+        };
+        let mut hits = Vec::new();
+        let stats = storage
+            .grep(&Regex::new("Status: 200").unwrap(), &options, |hit| {
+                hits.push(hit);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(stats.captures_scanned, 1);
+        assert_eq!(stats.matches, 400);
+        assert_eq!(hits[0].lines.len(), 1);
+        assert_eq!(hits[0].dropped, 399);
+    }
+}
@@ -0,0 +1,308 @@
+//! Latency of storage and search calls
+//!
+//! Repository methods and index searches run under a [`Timer`]. Each call's
+//! duration goes into an in-memory window that [`flush`] merges into
+//! `logs/latency.json` in the data directory, so `yinx status` can show
+//! percentiles for calls made by the daemon and by earlier CLI runs alike.
+//! Calls slower than `storage.slow_query` are also appended, with their
+//! parameters, to `logs/slow.log` as they happen.
+//!
+//! Processes flushing at the same moment can drop each other's samples;
+//! the window is a health signal, not an audit trail.
+
+use super::readonly;
+use crate::error::{Result, YinxError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Samples kept per operation, in memory and on disk
+const WINDOW: usize = 500;
+
+const SAMPLES_FILE: &str = "latency.json";
+pub(crate) const SLOW_LOG: &str = "slow.log";
+
+/// Where samples and slow calls go; see [`configure`]
+static OPTIONS: RwLock<Option<LatencyOptions>> = RwLock::new(None);
+
+/// Durations in microseconds per operation, not yet flushed
+static PENDING: Mutex<BTreeMap<&'static str, Vec<u64>>> = Mutex::new(BTreeMap::new());
+
+/// Set while a [`Paused`] guard is alive
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Where latency data is written and what counts as slow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyOptions {
+    /// Directory holding the samples file and the slow log
+    pub log_dir: PathBuf,
+    /// Calls taking at least this long are logged; None logs nothing
+    pub slow_threshold: Option<Duration>,
+}
+
+/// Record latency from here on according to `options`
+///
+/// Until it is called, calls are timed but nothing is written.
+pub fn configure(options: LatencyOptions) {
+    *OPTIONS.write().unwrap_or_else(|e| e.into_inner()) = Some(options);
+}
+
+fn configured() -> Option<LatencyOptions> {
+    OPTIONS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Stop recording until the returned guard is dropped
+///
+/// For work on scratch stores, such as `yinx bench`, whose timings would
+/// skew the user's window.
+pub fn pause() -> Paused {
+    PAUSED.store(true, Ordering::Relaxed);
+    Paused(())
+}
+
+/// Guard returned by [`pause`]
+pub struct Paused(());
+
+impl Drop for Paused {
+    fn drop(&mut self) {
+        PAUSED.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Times a call from creation until drop
+///
+/// `params` is only rendered when the call turns out to be slow.
+pub struct Timer<F: FnOnce() -> String> {
+    op: &'static str,
+    started: Instant,
+    params: Option<F>,
+}
+
+/// Start timing `op`
+pub fn timer<F: FnOnce() -> String>(op: &'static str, params: F) -> Timer<F> {
+    Timer {
+        op,
+        started: Instant::now(),
+        params: Some(params),
+    }
+}
+
+impl<F: FnOnce() -> String> Drop for Timer<F> {
+    fn drop(&mut self) {
+        if PAUSED.load(Ordering::Relaxed) {
+            return;
+        }
+        let elapsed = self.started.elapsed();
+        {
+            let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+            let samples = pending.entry(self.op).or_default();
+            samples.push(elapsed.as_micros() as u64);
+            if samples.len() > WINDOW {
+                samples.drain(..samples.len() - WINDOW);
+            }
+        }
+
+        let Some(options) = configured() else {
+            return;
+        };
+        if options.slow_threshold.is_some_and(|t| elapsed >= t) && !readonly::is_read_only() {
+            let params = self.params.take().map(|f| f()).unwrap_or_default();
+            let entry = SlowCall {
+                at: chrono::Utc::now().timestamp(),
+                op: self.op.to_string(),
+                ms: elapsed.as_secs_f64() * 1000.0,
+                params,
+            };
+            if let Err(e) = append_slow_call(&options.log_dir.join(SLOW_LOG), &entry) {
+                tracing::debug!("Failed to write slow query log: {}", e);
+            }
+        }
+    }
+}
+
+/// A line of the slow log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowCall {
+    pub at: i64,
+    pub op: String,
+    pub ms: f64,
+    pub params: String,
+}
+
+fn append_slow_call(path: &Path, entry: &SlowCall) -> Result<()> {
+    let io_err = |e| YinxError::Io {
+        source: e,
+        context: format!("Failed to append to {}", path.display()),
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_err)?;
+    }
+    let line = serde_json::to_string(entry).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize slow call".to_string(),
+    })?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_err)?;
+    writeln!(file, "{}", line).map_err(io_err)
+}
+
+/// Merge this process's samples into the data directory's window
+///
+/// Does nothing before [`configure`] or in a read-only store.
+pub fn flush() -> Result<()> {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap_or_else(|e| e.into_inner()));
+    let Some(options) = configured() else {
+        return Ok(());
+    };
+    if pending.is_empty() || readonly::is_read_only() {
+        return Ok(());
+    }
+
+    let path = options.log_dir.join(SAMPLES_FILE);
+    let mut samples = read_samples(&path)?;
+    for (op, durations) in pending {
+        let window = samples.entry(op.to_string()).or_default();
+        window.extend(durations);
+        if window.len() > WINDOW {
+            window.drain(..window.len() - WINDOW);
+        }
+    }
+
+    let io_err = |e| YinxError::Io {
+        source: e,
+        context: format!("Failed to write {}", path.display()),
+    };
+    std::fs::create_dir_all(&options.log_dir).map_err(io_err)?;
+    let json = serde_json::to_string(&samples).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize latency samples".to_string(),
+    })?;
+    // Written aside and renamed so a reader never sees half a file
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, json).map_err(io_err)?;
+    std::fs::rename(&temp, &path).map_err(io_err)
+}
+
+fn read_samples(path: &Path) -> Result<BTreeMap<String, Vec<u64>>> {
+    match std::fs::read_to_string(path) {
+        // A damaged window is only stale numbers; start a new one
+        Ok(json) => Ok(serde_json::from_str(&json).unwrap_or_default()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(YinxError::Io {
+            source: e,
+            context: format!("Failed to read {}", path.display()),
+        }),
+    }
+}
+
+/// Recent latency of one operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpLatency {
+    pub op: String,
+    pub samples: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+/// Percentiles of every operation in the samples under `log_dir`, by name
+pub fn summarize(log_dir: &Path) -> Result<Vec<OpLatency>> {
+    Ok(read_samples(&log_dir.join(SAMPLES_FILE))?
+        .into_iter()
+        .filter(|(_, samples)| !samples.is_empty())
+        .map(|(op, mut samples)| {
+            samples.sort_unstable();
+            OpLatency {
+                op,
+                samples: samples.len(),
+                p50: Duration::from_micros(percentile(&samples, 50)),
+                p95: Duration::from_micros(percentile(&samples, 95)),
+            }
+        })
+        .collect())
+}
+
+/// Nearest-rank percentile of sorted, non-empty `samples`
+fn percentile(samples: &[u64], pct: usize) -> u64 {
+    let rank = (samples.len() * pct).div_ceil(100).max(1);
+    samples[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&samples, 50), 50);
+        assert_eq!(percentile(&samples, 95), 95);
+        assert_eq!(percentile(&[7], 95), 7);
+        assert_eq!(percentile(&[1, 2, 3], 50), 2);
+    }
+
+    #[test]
+    fn test_summarize_reads_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let samples = BTreeMap::from([
+            (
+                "db.get_chunks".to_string(),
+                (1..=20).map(|ms| ms * 1000).collect(),
+            ),
+            ("keyword.search".to_string(), Vec::new()),
+        ]);
+        std::fs::write(
+            temp_dir.path().join(SAMPLES_FILE),
+            serde_json::to_string(&samples).unwrap(),
+        )
+        .unwrap();
+
+        let summary = summarize(temp_dir.path()).unwrap();
+        assert_eq!(
+            summary,
+            vec![OpLatency {
+                op: "db.get_chunks".to_string(),
+                samples: 20,
+                p50: Duration::from_millis(10),
+                p95: Duration::from_millis(19),
+            }]
+        );
+        assert!(summarize(&temp_dir.path().join("missing"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_slow_calls_are_appended() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("logs").join(SLOW_LOG);
+        for op in ["keyword.search", "db.stats"] {
+            append_slow_call(
+                &path,
+                &SlowCall {
+                    at: 0,
+                    op: op.to_string(),
+                    ms: 812.5,
+                    params: "query=\"smb\"".to_string(),
+                },
+            )
+            .unwrap();
+        }
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let calls: Vec<SlowCall> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1].op, "db.stats");
+        assert_eq!(calls[0].params, "query=\"smb\"");
+    }
+}
@@ -2,27 +2,78 @@
 //!
 //! Provides content-addressed blob storage and structured database access
 
+pub mod answers;
+pub mod archive;
+pub mod asn;
+pub mod attachments;
 pub mod blob;
+pub mod custody;
 pub mod database;
-
+pub mod dns;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod graph;
+pub mod grep;
+pub mod latency;
+pub mod notes;
+pub mod purge;
+pub mod readonly;
+pub mod scrub;
+pub mod selection;
+pub mod summaries;
+pub mod transcript;
+pub mod triage;
+pub mod truncate;
+
+use crate::embedding::KeywordTokenizers;
 use crate::error::Result;
+use crate::filtering::ChunkPolicy;
+use crate::vault::Vault;
 use std::path::{Path, PathBuf};
 
+pub use answers::CachedAnswer;
+pub use archive::ArchiveStats;
+pub use attachments::AttachTarget;
 pub use blob::{BlobStore, GcStats};
+pub use custody::{EvidenceManifest, ManifestEntry};
 pub use database::{
-    CaptureRecord, ChunkRecord, Database, DbPool, DbStats, EmbeddingRecord, EntityRecord,
+    metadata_host, AnnotationRecord, AttachmentRecord, CaptureRecord, ChunkRecord,
+    CredentialRecord, Database, DbPool, DbStats, EmbeddingBacklog, EmbeddingRecord, EntityPosition,
+    EntityRecord, EntitySearch, EntityTotal, FindingRecord, FindingTicketRecord, HostStageRecord,
+    IndexWatermark, ProofRecord, SessionEntityRecord, SqliteOptions, ToolFilterStats,
+};
+pub use dns::{DnsLookup, LookupKind};
+pub use graph::{
+    subnet_of, HostEdge, HostGraph, HostNode, SubnetCoverage, DEFAULT_SUBNET_PREFIX,
+    HOST_ENTITY_TYPES,
 };
+pub use grep::{GrepHit, GrepLine, GrepOptions, GrepStats};
+pub use notes::NoteNode;
+pub use purge::PurgeStats;
+pub use scrub::{ScrubHit, ScrubLocation, ScrubPattern, ScrubReport};
+pub use selection::CaptureSelection;
+pub use transcript::TranscriptFilter;
+pub use triage::RecentFailure;
+pub use truncate::{strip_marker, OutputLimit, TruncatedOutput};
 
 /// Storage manager that coordinates blob and database storage
 pub struct StorageManager {
     pub blob_store: BlobStore,
     pub database: Database,
     base_path: PathBuf,
+    vault: Option<Vault>,
+    output_limit: Option<OutputLimit>,
+    chunk_policy: ChunkPolicy,
+    keyword_tokenizers: KeywordTokenizers,
 }
 
 impl StorageManager {
     /// Create a new storage manager
     pub fn new(base_path: PathBuf) -> Result<Self> {
+        if readonly::is_read_only() {
+            return Self::open_read_only(base_path);
+        }
+
         // Initialize dual-zone structure
         let machine_zone = base_path.join("store");
         let human_zone = base_path.join("reports");
@@ -66,9 +117,62 @@ impl StorageManager {
             blob_store,
             database,
             base_path,
+            vault: None,
+            output_limit: None,
+            chunk_policy: ChunkPolicy::default(),
+            keyword_tokenizers: KeywordTokenizers::default(),
         })
     }
 
+    /// Seal redacted originals with this vault
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    /// Truncate capture output above this limit
+    pub fn with_output_limit(mut self, limit: OutputLimit) -> Self {
+        self.output_limit = Some(limit);
+        self
+    }
+
+    /// Get the capture output limit, if one is set
+    pub fn output_limit(&self) -> Option<&OutputLimit> {
+        self.output_limit.as_ref()
+    }
+
+    /// Assemble captured clusters into chunks with this policy
+    pub fn with_chunk_policy(mut self, policy: ChunkPolicy) -> Self {
+        self.chunk_policy = policy;
+        self
+    }
+
+    /// Get the chunk assembly policy
+    pub fn chunk_policy(&self) -> &ChunkPolicy {
+        &self.chunk_policy
+    }
+
+    /// Create a missing keyword index with these tokenizers
+    pub fn with_keyword_tokenizers(mut self, tokenizers: KeywordTokenizers) -> Self {
+        self.keyword_tokenizers = tokenizers;
+        self
+    }
+
+    /// Get the tokenizers a new keyword index is created with
+    pub fn keyword_tokenizers(&self) -> &KeywordTokenizers {
+        &self.keyword_tokenizers
+    }
+
+    /// Get the vault, if sealing is enabled
+    pub fn vault(&self) -> Option<&Vault> {
+        self.vault.as_ref()
+    }
+
+    /// Get the data directory this store lives in
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
     /// Get the machine zone path (internal, rebuildable data)
     pub fn machine_zone(&self) -> PathBuf {
         self.base_path.join("store")
@@ -79,6 +183,11 @@ impl StorageManager {
         self.base_path.join("reports")
     }
 
+    /// Get the directory holding per-session state files
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.base_path.join("sessions")
+    }
+
     /// Get path for session reports
     pub fn session_report_dir(&self, session_name: &str) -> PathBuf {
         self.human_zone().join(session_name)
@@ -86,6 +195,9 @@ impl StorageManager {
 
     /// Ensure session report directory exists
     pub fn ensure_session_report_dir(&self, session_name: &str) -> Result<PathBuf> {
+        if readonly::is_read_only() {
+            return Err(readonly::read_only_error("writing to the report directory"));
+        }
         let dir = self.session_report_dir(session_name);
         std::fs::create_dir_all(&dir).map_err(|e| crate::error::YinxError::Io {
             source: e,
@@ -105,6 +217,26 @@ impl StorageManager {
         Ok(dir)
     }
 
+    /// A capture's output as entities were extracted from it: the stored
+    /// blob, minus the marker line when it was truncated
+    pub fn indexed_output(&self, capture: &CaptureRecord) -> Result<String> {
+        let stored =
+            String::from_utf8_lossy(&self.blob_store.read(&capture.output_hash)?).into_owned();
+        Ok(match capture.original_size {
+            Some(_) => strip_marker(&stored),
+            None => stored,
+        })
+    }
+
+    /// Context around an entity at `window` bytes either side, re-derived
+    /// from its capture's output; None when no position was recorded
+    pub fn entity_context(&self, entity: &EntityRecord, window: usize) -> Result<Option<String>> {
+        let Some(capture) = self.database.get_capture(entity.capture_id)? else {
+            return Ok(None);
+        };
+        Ok(entity.context_in(&self.indexed_output(&capture)?, window))
+    }
+
     /// Get combined storage statistics
     pub fn stats(&self) -> Result<StorageStats> {
         let db_stats = self.database.stats()?;
@@ -210,6 +342,48 @@ mod tests {
         assert!(session_dir.join("export").exists());
     }
 
+    #[tokio::test]
+    async fn test_entity_context_rederived() {
+        let temp_dir = TempDir::new().unwrap();
+        let yinx = crate::Yinx::open(temp_dir.path()).unwrap();
+        let session = yinx.start_session("ctx").unwrap();
+        let output = "Starting Nmap\nNmap scan report for 10.10.10.40\n445/tcp open microsoft-ds\n";
+        let capture_id = yinx
+            .ingest(crate::CaptureEvent {
+                session_id: session.id.to_string(),
+                timestamp: 0,
+                command: "nmap 10.10.10.40".to_string(),
+                output: output.to_string(),
+                exit_code: 0,
+                cwd: "/tmp".to_string(),
+                username: None,
+                source: None,
+                tool: None,
+                context: None,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        let storage = yinx.storage();
+        let ip = storage
+            .database
+            .get_entities_for_capture(capture_id)
+            .unwrap()
+            .into_iter()
+            .find(|e| e.value == "10.10.10.40")
+            .unwrap();
+        assert_eq!(ip.line, Some(2));
+        assert_eq!(
+            storage.entity_context(&ip, 4).unwrap().as_deref(),
+            Some("for 10.10.10.40\n445")
+        );
+        assert_eq!(
+            storage.entity_context(&ip, 100).unwrap().as_deref(),
+            Some(output)
+        );
+    }
+
     #[test]
     fn test_format_size() {
         assert_eq!(StorageStats::format_size(0), "0.00 B");
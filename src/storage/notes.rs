@@ -0,0 +1,450 @@
+//! Note-taking exports
+//!
+//! Lays a session out the way notes are usually kept for an engagement:
+//! one node per host, its services under it, and findings under the service
+//! they came from, each with a snippet of the capture that backs it up.
+//! Host nodes also carry the OS, MAC address and banners captures showed.
+//! The tree is written as a CherryTree document (`.ctd`) or as OPML, which
+//! outliners and mind-mapping tools such as XMind import.
+
+use super::transcript::Entry;
+use super::StorageManager;
+use crate::entities::HostInfo;
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Title of the node holding findings not tied to a host
+pub const OTHER_FINDINGS: &str = "Other findings";
+
+/// Output lines kept in a finding's evidence snippet
+const SNIPPET_LINES: usize = 20;
+
+/// A note, its text and the notes under it
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NoteNode {
+    pub title: String,
+    pub text: String,
+    /// Verbatim snippets, shown in a monospace block
+    pub evidence: Vec<String>,
+    pub children: Vec<NoteNode>,
+}
+
+impl NoteNode {
+    fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Number of nodes below this one
+    pub fn descendants(&self) -> usize {
+        self.children.iter().map(|c| 1 + c.descendants()).sum()
+    }
+
+    /// Render as a CherryTree XML document
+    pub fn to_cherrytree(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<cherrytree>\n");
+        let mut next_id = 1;
+        self.write_cherrytree(&mut out, 1, &mut next_id);
+        out.push_str("</cherrytree>\n");
+        out
+    }
+
+    fn write_cherrytree(&self, out: &mut String, depth: usize, next_id: &mut usize) {
+        let indent = "  ".repeat(depth);
+        let _ = writeln!(
+            out,
+            "{}<node name=\"{}\" unique_id=\"{}\" prog_lang=\"custom-colors\" tags=\"\" \
+             readonly=\"0\" custom_icon_id=\"0\" is_bold=\"0\" foreground=\"\" \
+             ts_creation=\"0\" ts_lastsave=\"0\">",
+            indent,
+            escape_attribute(&self.title),
+            next_id
+        );
+        *next_id += 1;
+        if !self.text.is_empty() {
+            let _ = writeln!(
+                out,
+                "{}  <rich_text>{}\n</rich_text>",
+                indent,
+                escape_xml(&self.text)
+            );
+        }
+        for snippet in &self.evidence {
+            let _ = writeln!(
+                out,
+                "{}  <rich_text family=\"monospace\">{}\n</rich_text>",
+                indent,
+                escape_xml(snippet)
+            );
+        }
+        for child in &self.children {
+            child.write_cherrytree(out, depth + 1, next_id);
+        }
+        let _ = writeln!(out, "{}</node>", indent);
+    }
+
+    /// Render as an OPML outline; text and evidence become each outline's note
+    pub fn to_opml(&self) -> String {
+        let mut out = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  \
+             <head>\n    <title>{}</title>\n  </head>\n  <body>\n",
+            escape_xml(&self.title)
+        );
+        self.write_opml(&mut out, 2);
+        out.push_str("  </body>\n</opml>\n");
+        out
+    }
+
+    fn write_opml(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let note: Vec<&str> = std::iter::once(self.text.as_str())
+            .chain(self.evidence.iter().map(String::as_str))
+            .filter(|part| !part.is_empty())
+            .collect();
+        let _ = write!(
+            out,
+            "{}<outline text=\"{}\"",
+            indent,
+            escape_attribute(&self.title)
+        );
+        if !note.is_empty() {
+            let _ = write!(out, " _note=\"{}\"", escape_attribute(&note.join("\n\n")));
+        }
+        if self.children.is_empty() {
+            out.push_str("/>\n");
+            return;
+        }
+        out.push_str(">\n");
+        for child in &self.children {
+            child.write_opml(out, depth + 1);
+        }
+        let _ = writeln!(out, "{}</outline>", indent);
+    }
+}
+
+/// What the notes hold for one host
+#[derive(Default)]
+struct HostNotes {
+    captures: usize,
+    /// `445/tcp` -> (service name, evidence lines)
+    services: BTreeMap<String, (Option<String>, BTreeSet<String>)>,
+    /// Findings under a service, by port
+    service_findings: BTreeMap<String, Vec<NoteNode>>,
+    findings: Vec<NoteNode>,
+}
+
+impl StorageManager {
+    /// Build the host → service → finding tree of a session
+    pub fn session_notes(&self, session_id: &str, session_name: &str) -> Result<NoteNode> {
+        let entries = self.transcript_entries(session_id)?;
+        let mut hosts: BTreeMap<String, HostNotes> = BTreeMap::new();
+        let mut ports: HashMap<i64, Vec<String>> = HashMap::new();
+
+        for entry in &entries {
+            let capture_ports: Vec<_> = self
+                .database
+                .get_entities_for_capture(entry.capture.id)?
+                .into_iter()
+                .filter(|e| e.entity_type == "port")
+                .collect();
+            ports.insert(
+                entry.capture.id,
+                capture_ports.iter().map(|e| e.value.clone()).collect(),
+            );
+            for host in targets(entry) {
+                let notes = hosts.entry(host.to_string()).or_default();
+                notes.captures += 1;
+                for port in &capture_ports {
+                    let context = port.context.as_deref().unwrap_or_default();
+                    let (name, line) = service_line(&port.value, context);
+                    let service = notes.services.entry(port.value.clone()).or_default();
+                    if service.0.is_none() {
+                        service.0 = name;
+                    }
+                    if let Some(line) = line {
+                        service.1.insert(line);
+                    }
+                }
+            }
+        }
+
+        let mut unassigned = Vec::new();
+        let mut findings = self.database.list_findings(Some(session_id))?;
+        findings.reverse();
+        for finding in findings {
+            let entry = finding
+                .capture_id
+                .and_then(|id| entries.iter().find(|e| e.capture.id == id));
+            let mut node = NoteNode::new(format!(
+                "[{}] {}",
+                finding.severity.to_uppercase(),
+                finding.title
+            ));
+            if let Some(rule) = &finding.rule {
+                node.text = format!("Rule: {}", rule);
+            }
+            let Some(entry) = entry else {
+                unassigned.push(node);
+                continue;
+            };
+            node.evidence
+                .push(self.capture_excerpt(&entry.capture, SNIPPET_LINES));
+
+            let targets = targets(entry);
+            if targets.is_empty() {
+                unassigned.push(node);
+                continue;
+            }
+            // A finding belongs to a service when its capture was about one port
+            let port = match ports.get(&entry.capture.id).map(Vec::as_slice) {
+                Some([port]) => Some(port.clone()),
+                _ => None,
+            };
+            for host in targets {
+                let notes = hosts.entry(host.to_string()).or_default();
+                match &port {
+                    Some(port) => notes
+                        .service_findings
+                        .entry(port.clone())
+                        .or_default()
+                        .push(node.clone()),
+                    None => notes.findings.push(node.clone()),
+                }
+            }
+        }
+
+        let stages: HashMap<String, String> = self
+            .database
+            .list_host_stages(session_id)?
+            .into_iter()
+            .map(|record| (record.host, record.stage.label().to_string()))
+            .collect();
+
+        let graph = self.correlation_graph(Some(session_id), None)?;
+
+        let mut root = NoteNode::new(session_name);
+        root.text = format!("{} capture(s)", entries.len());
+        for (host, mut notes) in hosts {
+            let mut node = NoteNode::new(host.as_str());
+            node.text = format!("{} capture(s)", notes.captures);
+            if let Some(stage) = stages.get(&host) {
+                let _ = write!(node.text, "\nStatus: {}", stage);
+            }
+            if let Some(info) = graph.get_host(&host) {
+                write_fingerprint(&mut node, info);
+            }
+            for (port, (name, lines)) in notes.services {
+                let mut service = NoteNode::new(match name {
+                    Some(name) => format!("{} {}", port, name),
+                    None => port.clone(),
+                });
+                if !lines.is_empty() {
+                    service
+                        .evidence
+                        .push(lines.into_iter().collect::<Vec<_>>().join("\n"));
+                }
+                service.children = notes.service_findings.remove(&port).unwrap_or_default();
+                node.children.push(service);
+            }
+            // Findings tied to a port no capture reported as a service
+            for findings in notes.service_findings.into_values() {
+                node.children.extend(findings);
+            }
+            node.children.extend(notes.findings);
+            root.children.push(node);
+        }
+        if !unassigned.is_empty() {
+            let mut node = NoteNode::new(OTHER_FINDINGS);
+            node.children = unassigned;
+            root.children.push(node);
+        }
+        Ok(root)
+    }
+}
+
+/// Add a host's OS, MAC address, network, DNS names and banners to its node
+fn write_fingerprint(node: &mut NoteNode, info: &HostInfo) {
+    if let Some(os) = info.os.resolved() {
+        let _ = write!(node.text, "\nOS: {}", os.value);
+    }
+    if let Some(mac) = info.mac.resolved() {
+        let _ = write!(node.text, "\nMAC: {}", mac.value);
+        if let Some(vendor) = info.vendor.resolved() {
+            let _ = write!(node.text, " ({})", vendor.value);
+        }
+    }
+    if let Some(asn) = &info.asn {
+        let _ = write!(node.text, "\nNetwork: {}", asn);
+    }
+    for (alias, resolved_at) in &info.aliases {
+        let _ = write!(node.text, "\nDNS: {}", alias);
+        if let Some(at) = chrono::DateTime::from_timestamp(*resolved_at, 0) {
+            let _ = write!(node.text, " (resolved {})", at.format("%Y-%m-%d %H:%M UTC"));
+        }
+    }
+    if !info.banners.is_empty() {
+        node.evidence
+            .push(info.banners.iter().cloned().collect::<Vec<_>>().join("\n"));
+    }
+}
+
+/// Hosts a capture was aimed at: those its command names, or the only host
+/// it involves
+fn targets(entry: &Entry) -> Vec<&str> {
+    let command = entry.capture.command.as_deref().unwrap_or("");
+    let named: Vec<&str> = entry
+        .hosts
+        .iter()
+        .map(String::as_str)
+        .filter(|host| command.contains(host))
+        .collect();
+    match (named.is_empty(), entry.hosts.len()) {
+        (false, _) => named,
+        (true, 1) => entry.hosts.iter().map(String::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Service name and the line a port was reported on, e.g. nmap's
+/// `445/tcp open  microsoft-ds`
+fn service_line(port: &str, context: &str) -> (Option<String>, Option<String>) {
+    let Some(pos) = context.find(port) else {
+        return (None, None);
+    };
+    let start = context[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let end = context[pos..].find('\n').map_or(context.len(), |i| pos + i);
+    let line = context[start..end].trim();
+
+    let mut words = context[pos + port.len()..end].split_whitespace();
+    let name = match words.next() {
+        Some("open") => words.next(),
+        _ => None,
+    };
+    (
+        name.map(str::to_string),
+        (!line.is_empty()).then(|| line.to_string()),
+    )
+}
+
+/// Escape element text; newlines are kept
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c if c.is_control() && c != '\t' && c != '\n' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape an attribute value, where newlines must be encoded to survive
+fn escape_attribute(text: &str) -> String {
+    escape_xml(text).replace('\n', "&#10;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures;
+    use tempfile::TempDir;
+
+    fn capture(storage: &StorageManager, command: &str, output: &str) -> i64 {
+        let id = fixtures::capture("s1", command, output.as_bytes())
+            .insert(storage)
+            .id;
+        let mut entities = Vec::new();
+        for line in output.lines() {
+            for word in line.split_whitespace() {
+                if word.starts_with("10.") {
+                    entities.push((
+                        "ip_address".to_string(),
+                        word.to_string(),
+                        line.to_string(),
+                        0.9,
+                    ));
+                } else if word.ends_with("/tcp") {
+                    entities.push(("port".to_string(), word.to_string(), line.to_string(), 0.9));
+                }
+            }
+        }
+        storage.database.insert_entities(id, &entities).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_session_notes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "exam", 0, "active")
+            .unwrap();
+        let nmap = capture(
+            &storage,
+            "nmap 10.0.0.5",
+            "Nmap scan report for 10.0.0.5\n22/tcp open ssh\n445/tcp open microsoft-ds",
+        );
+        storage
+            .database
+            .insert_entities(
+                nmap,
+                &[(
+                    "os_guess".to_string(),
+                    "Linux 5.4".to_string(),
+                    String::new(),
+                    0.8,
+                )],
+            )
+            .unwrap();
+        let smb = capture(
+            &storage,
+            "nxc smb 10.0.0.5",
+            "SMB 10.0.0.5 445/tcp signing:False <vulnerable>",
+        );
+        storage
+            .database
+            .insert_finding("s1", Some(smb), "SMB signing disabled", "medium", None)
+            .unwrap();
+        storage
+            .database
+            .insert_finding("s1", None, "Password reuse", "high", None)
+            .unwrap();
+
+        let notes = storage.session_notes("s1", "exam").unwrap();
+        assert_eq!(notes.title, "exam");
+        let host = &notes.children[0];
+        assert_eq!(host.title, "10.0.0.5");
+        assert_eq!(host.text, "2 capture(s)\nOS: Linux 5.4");
+        assert_eq!(host.children[0].title, "22/tcp ssh");
+        let smb = &host.children[1];
+        assert_eq!(smb.title, "445/tcp microsoft-ds");
+        assert_eq!(
+            smb.evidence[0],
+            "445/tcp open microsoft-ds\nSMB 10.0.0.5 445/tcp signing:False <vulnerable>"
+        );
+        assert_eq!(smb.children[0].title, "[MEDIUM] SMB signing disabled");
+        assert!(smb.children[0].evidence[0].starts_with("$ nxc smb 10.0.0.5\nSMB"));
+        assert_eq!(notes.children[1].children[0].title, "[HIGH] Password reuse");
+        assert_eq!(notes.descendants(), 6);
+
+        let ctd = notes.to_cherrytree();
+        assert!(ctd.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<cherrytree>\n  <node name=\"exam\" unique_id=\"1\""));
+        assert!(ctd.contains("<rich_text family=\"monospace\">$ nxc smb 10.0.0.5\nSMB 10.0.0.5 445/tcp signing:False &lt;vulnerable&gt;\n</rich_text>"));
+        assert_eq!(ctd.matches("<node ").count(), 7);
+        assert_eq!(ctd.matches("</node>").count(), 7);
+
+        let opml = notes.to_opml();
+        assert!(opml.contains("<title>exam</title>"));
+        assert!(opml.contains("<outline text=\"22/tcp ssh\" _note=\"22/tcp open ssh\"/>"));
+        assert_eq!(opml.matches("<outline ").count(), 7);
+    }
+}
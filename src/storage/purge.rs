@@ -0,0 +1,280 @@
+//! Deleting a session's data
+//!
+//! Removing the session row cascades to its captures and everything hung off
+//! them: chunks, embeddings, entities, findings. Blobs are shared between
+//! sessions by content hash, so each loses the references the session held
+//! and only blobs nothing else points at are deleted, files included. The
+//! session's chunks are dropped from the keyword index. DNS lookups and
+//! network annotations are cached per name rather than per session, so
+//! those of names no other session has seen are deleted too. The vector index
+//! isn't persisted; a process searching one purges through
+//! `HybridSearcher::purge_session` so the chunks are tombstoned there too.
+//! Reports and evidence in the human zone are left alone.
+
+use super::StorageManager;
+use crate::error::{Result, YinxError};
+use rusqlite::{params, OptionalExtension};
+
+/// What deleting a session removed, or would remove
+#[derive(Debug, Default)]
+pub struct PurgeStats {
+    pub captures: usize,
+    pub chunks: usize,
+    pub embeddings: usize,
+    pub entities: usize,
+    pub findings: usize,
+    /// Blobs only this session referenced, deleted with their files
+    pub blobs_deleted: usize,
+    /// On-disk bytes of the deleted blobs
+    pub bytes_freed: u64,
+    /// Blobs kept because another session's captures share them
+    pub blobs_shared: usize,
+    /// DNS lookups and ASN annotations of names only this session saw
+    pub lookups: usize,
+    /// IDs of the chunks removed, for dropping them from indexes
+    pub chunk_ids: Vec<u64>,
+}
+
+impl StorageManager {
+    /// Delete everything stored for a session
+    ///
+    /// With `dry_run` nothing is modified, but the stats are the same.
+    pub fn purge_session(&self, session_id: &str, dry_run: bool) -> Result<PurgeStats> {
+        if dry_run {
+            return self.purge_session_data(session_id, true);
+        }
+
+        // Taken before anything is deleted: once the rows are gone their
+        // chunk ids are too, and a failed index update couldn't be retried
+        let mut index = self.open_keyword_index().map_err(|e| {
+            YinxError::Other(anyhow::anyhow!(
+                "Cannot update the keyword index ({}); stop the daemon and try again",
+                e
+            ))
+        })?;
+        let stats = self.purge_session_data(session_id, false)?;
+        if stats.chunk_ids.is_empty() {
+            return Ok(stats);
+        }
+        for id in &stats.chunk_ids {
+            index.delete(*id).map_err(|e| YinxError::Other(e.into()))?;
+        }
+        index.commit().map_err(|e| YinxError::Other(e.into()))?;
+        Ok(stats)
+    }
+
+    /// Delete a session's rows and blobs, leaving its chunks in the indexes
+    ///
+    /// For callers that hold the indexes open themselves; the removed
+    /// chunk ids are in the stats.
+    pub fn purge_session_data(&self, session_id: &str, dry_run: bool) -> Result<PurgeStats> {
+        let mut stats = PurgeStats::default();
+        let hashes = self.database.get_session_blob_hashes(session_id)?;
+        stats.chunk_ids = self
+            .database
+            .get_session_chunks(session_id)?
+            .iter()
+            .map(|chunk| chunk.id as u64)
+            .collect();
+        stats.chunks = stats.chunk_ids.len();
+
+        let mut conn = self.database.get_conn()?;
+        let tx = conn.transaction()?;
+        let count = |sql: &str| -> Result<usize> {
+            let n: i64 = tx.query_row(sql, params![session_id], |row| row.get(0))?;
+            Ok(n as usize)
+        };
+        stats.captures = count("SELECT COUNT(*) FROM captures WHERE session_id = ?1")?;
+        stats.embeddings = count(
+            "SELECT COUNT(*) FROM embeddings e
+             JOIN chunks ch ON ch.id = e.chunk_id
+             JOIN captures c ON c.id = ch.capture_id
+             WHERE c.session_id = ?1",
+        )?;
+        stats.entities = count(
+            "SELECT COUNT(*) FROM entities e
+             JOIN captures c ON c.id = e.capture_id
+             WHERE c.session_id = ?1",
+        )?;
+        stats.findings = count("SELECT COUNT(*) FROM findings WHERE session_id = ?1")?;
+        // Names and addresses no other session has come across
+        const OWN_VALUES: &str = "SELECT value FROM session_entities WHERE session_id = ?1
+             EXCEPT SELECT value FROM session_entities WHERE session_id != ?1";
+        stats.lookups = count(&format!(
+            "SELECT (SELECT COUNT(*) FROM dns_lookups WHERE name IN ({0}))
+                  + (SELECT COUNT(*) FROM ip_asn WHERE ip IN ({0}))",
+            OWN_VALUES
+        ))?;
+
+        // A blob survives if a capture or chunk of another session uses it
+        let mut unreferenced = Vec::new();
+        for hash in &hashes {
+            let shared = tx
+                .query_row(
+                    "SELECT 1 FROM captures WHERE output_hash = ?1 AND session_id != ?2
+                     UNION ALL
+                     SELECT 1 FROM chunks ch JOIN captures c ON c.id = ch.capture_id
+                     WHERE ch.blob_hash = ?1 AND c.session_id != ?2
+                     LIMIT 1",
+                    params![hash, session_id],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if shared {
+                stats.blobs_shared += 1;
+            } else {
+                unreferenced.push(hash.clone());
+            }
+        }
+        stats.blobs_deleted = unreferenced.len();
+        for hash in &unreferenced {
+            stats.bytes_freed += self.blob_store.size(hash).unwrap_or(0);
+        }
+
+        if dry_run {
+            return Ok(stats);
+        }
+
+        // Shared blobs give up one reference per capture of this session
+        tx.execute(
+            "UPDATE blobs SET ref_count = MAX(ref_count - (
+                SELECT COUNT(*) FROM captures WHERE session_id = ?1 AND output_hash = blobs.hash
+             ), 0)
+             WHERE hash IN (SELECT output_hash FROM captures WHERE session_id = ?1)",
+            params![session_id],
+        )?;
+        // Cached answers name the session but hang off nothing that cascades
+        tx.execute(
+            "DELETE FROM answer_cache WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        // Lookup caches are keyed by name, so they go before the entities do
+        tx.execute(
+            &format!("DELETE FROM dns_lookups WHERE name IN ({})", OWN_VALUES),
+            params![session_id],
+        )?;
+        tx.execute(
+            &format!("DELETE FROM ip_asn WHERE ip IN ({})", OWN_VALUES),
+            params![session_id],
+        )?;
+        tx.execute("DELETE FROM sessions WHERE id = ?1", params![session_id])?;
+        for hash in &unreferenced {
+            tx.execute("DELETE FROM blobs WHERE hash = ?1", params![hash])?;
+        }
+        tx.commit()?;
+
+        for hash in &unreferenced {
+            self.blob_store.delete(hash)?;
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_purge_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        for session in ["s1", "s2"] {
+            storage
+                .database
+                .ensure_session(session, session, 0, "stopped")
+                .unwrap();
+        }
+        let own = capture("s1", "id", b"uid=0(root)\n")
+            .chunks(&["open ssh"])
+            .insert(&storage)
+            .hash;
+        let shared = capture("s1", "nmap", b"22/tcp open ssh\n")
+            .chunks(&["open ssh"])
+            .insert(&storage);
+        let other = capture("s2", "nmap", b"22/tcp open ssh\n")
+            .chunks(&["open ssh"])
+            .insert(&storage);
+        let ip = |value: &str| {
+            (
+                "ip_address".to_string(),
+                value.to_string(),
+                String::new(),
+                1.0,
+            )
+        };
+        storage
+            .database
+            .insert_entities(shared.id, &[ip("10.0.0.5"), ip("8.8.8.8")])
+            .unwrap();
+        storage
+            .database
+            .insert_entities(other.id, &[ip("8.8.8.8")])
+            .unwrap();
+        let shared = shared.hash;
+
+        let conn = storage.database.get_conn().unwrap();
+        conn.execute(
+            "INSERT INTO answer_cache (session_id, question, embedding, model, answer,
+                index_state, created_at)
+             VALUES ('s1', 'q', x'00', 'm', 'a', 'i', 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute_batch(
+            "INSERT INTO dns_lookups (name, kind, resolved_at)
+             VALUES ('10.0.0.5', 'PTR', 0), ('8.8.8.8', 'PTR', 0);
+             INSERT INTO ip_asn (ip, asn, annotated_at) VALUES ('8.8.8.8', 15169, 0);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let mut index = storage.open_keyword_index().unwrap();
+        for id in 1..=3u64 {
+            index.insert(id, "open ssh").unwrap();
+        }
+        index.commit().unwrap();
+        drop(index);
+
+        let preview = storage.purge_session("s1", true).unwrap();
+        assert_eq!((preview.captures, preview.chunks), (2, 2));
+        assert_eq!((preview.blobs_deleted, preview.blobs_shared), (1, 1));
+        assert_eq!(preview.lookups, 1);
+        assert!(storage.blob_store.exists(&own));
+
+        // Nothing is deleted while another process holds the index writer
+        let writer = storage.open_keyword_index().unwrap();
+        assert!(storage.purge_session("s1", false).is_err());
+        assert_eq!(storage.purge_session("s1", true).unwrap().captures, 2);
+        drop(writer);
+
+        let stats = storage.purge_session("s1", false).unwrap();
+        assert_eq!(stats.captures, 2);
+        assert!(!storage.blob_store.exists(&own));
+        assert!(storage.blob_store.exists(&shared));
+
+        let conn = storage.database.read_conn().unwrap();
+        let remaining = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(remaining("SELECT COUNT(*) FROM captures"), 1);
+        assert_eq!(remaining("SELECT COUNT(*) FROM chunks"), 1);
+        assert_eq!(remaining("SELECT COUNT(*) FROM blobs"), 1);
+        assert_eq!(remaining("SELECT ref_count FROM blobs"), 1);
+        assert_eq!(remaining("SELECT COUNT(*) FROM answer_cache"), 0);
+        assert_eq!(remaining("SELECT COUNT(*) FROM sessions"), 1);
+        // Only the address s2 saw too keeps its lookups
+        assert_eq!(remaining("SELECT COUNT(*) FROM dns_lookups"), 1);
+        assert_eq!(remaining("SELECT COUNT(*) FROM ip_asn"), 1);
+
+        let index = storage.open_keyword_index().unwrap();
+        let hits: Vec<u64> = index
+            .search("ssh", 10)
+            .unwrap()
+            .iter()
+            .map(|hit| hit.id)
+            .collect();
+        assert_eq!(hits, [3]);
+    }
+}
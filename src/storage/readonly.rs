@@ -0,0 +1,139 @@
+//! Read-only stores
+//!
+//! `yinx open --read-only` lets a lead browse an analyst's engagement data
+//! (a copied data directory or a tarball of one) without a daemon and
+//! without any chance of changing it. Once [`set_read_only`] is called,
+//! every store the process opens uses a read-only SQLite connection, and
+//! writes into the report directory are refused.
+
+use super::{BlobStore, Database, StorageManager};
+use crate::error::{Result, YinxError};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Open every store from here on read-only
+pub fn set_read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+/// Whether stores are being opened read-only
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+pub(super) fn read_only_error(what: &str) -> YinxError {
+    YinxError::Other(anyhow::anyhow!(
+        "The store is open read-only; {} is not allowed",
+        what
+    ))
+}
+
+impl StorageManager {
+    /// Open an existing store in `base_path` without writing to it
+    pub fn open_read_only(base_path: PathBuf) -> Result<Self> {
+        let machine_zone = base_path.join("store");
+        let database = Database::open_read_only(&machine_zone.join("db.sqlite"))?;
+        Ok(Self {
+            blob_store: BlobStore::new(machine_zone, 1024)?,
+            database,
+            base_path,
+            vault: None,
+            output_limit: None,
+            chunk_policy: Default::default(),
+            keyword_tokenizers: Default::default(),
+        })
+    }
+}
+
+/// The data directory in `path`: the directory itself, or one directory
+/// down as when a data directory was archived by name
+pub fn find_data_dir(path: &Path) -> Result<PathBuf> {
+    let is_store = |dir: &Path| dir.join("store").join("db.sqlite").is_file();
+    if is_store(path) {
+        return Ok(path.to_path_buf());
+    }
+    let entries = std::fs::read_dir(path).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to read {}", path.display()),
+    })?;
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|dir| dir.is_dir() && is_store(dir))
+        .ok_or_else(|| {
+            YinxError::Config(format!(
+                "{} is not a yinx data directory (no store/db.sqlite)",
+                path.display()
+            ))
+        })
+}
+
+/// Unpack a `.tar`, `.tar.gz`/`.tgz` or `.tar.zst` archive into `dest`
+pub fn unpack_archive(archive: &Path, dest: &Path) -> Result<()> {
+    let name = archive.to_string_lossy();
+    let file = std::fs::File::open(archive).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to open {}", archive.display()),
+    })?;
+    let reader: Box<dyn std::io::Read> = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Box::new(flate2::read::GzDecoder::new(file))
+    } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+        Box::new(zstd::Decoder::new(file).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to read {}", archive.display()),
+        })?)
+    } else if name.ends_with(".tar") {
+        Box::new(file)
+    } else {
+        return Err(YinxError::Config(format!(
+            "{} is not a data directory or a .tar, .tar.gz or .tar.zst archive",
+            archive.display()
+        )));
+    };
+    tar::Archive::new(reader)
+        .unpack(dest)
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to unpack {}", archive.display()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_open_archived_store_read_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = temp_dir.path().join("yinx");
+        {
+            let storage = StorageManager::new(data_dir.clone()).unwrap();
+            storage
+                .database
+                .ensure_session("s1", "exam", 0, "active")
+                .unwrap();
+        }
+
+        let archive = temp_dir.path().join("exam.tar");
+        let mut builder = tar::Builder::new(std::fs::File::create(&archive).unwrap());
+        builder.append_dir_all("yinx", &data_dir).unwrap();
+        builder.finish().unwrap();
+        drop(builder);
+
+        let unpacked = temp_dir.path().join("unpacked");
+        unpack_archive(&archive, &unpacked).unwrap();
+        let found = find_data_dir(&unpacked).unwrap();
+        assert_eq!(found, unpacked.join("yinx"));
+
+        let storage = StorageManager::open_read_only(found).unwrap();
+        assert_eq!(storage.database.stats().unwrap().session_count, 1);
+        assert!(storage
+            .database
+            .ensure_session("s2", "other", 0, "active")
+            .is_err());
+        assert!(find_data_dir(&unpacked.join("yinx").join("store")).is_err());
+    }
+}
@@ -0,0 +1,905 @@
+//! Retroactive scrubbing
+//!
+//! Removes a value from everything already stored: command lines and the
+//! arguments parsed from them, sealed originals, output blobs, chunks,
+//! entities, capture context, hook annotations, findings, summaries, triage
+//! hints, cached answers, the slow query log and the keyword index. Blobs
+//! containing the value are rewritten under their new hash and every
+//! reference is moved over. Embeddings of rewritten chunks are dropped so
+//! the embedding backlog regenerates them from the scrubbed text.
+//!
+//! Entity positions and per-host sections are byte offsets into the old
+//! output, so they are dropped for captures whose blob was rewritten;
+//! `yinx refilter` derives them again from the scrubbed output.
+
+use super::latency::{SlowCall, SLOW_LOG};
+use super::{metadata_host, StorageManager};
+use crate::error::{Result, YinxError};
+use regex::Regex;
+use rusqlite::{params, OptionalExtension, Transaction};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Text that replaces every scrubbed occurrence
+pub const SCRUB_REPLACEMENT: &str = "[SCRUBBED]";
+
+/// Value to purge from history
+#[derive(Debug, Clone)]
+pub struct ScrubPattern {
+    text: Regex,
+    bytes: regex::bytes::Regex,
+}
+
+impl ScrubPattern {
+    /// Match an exact string
+    pub fn literal(value: &str) -> Result<Self> {
+        Self::regex(&regex::escape(value))
+    }
+
+    /// Match a regular expression
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let text = Regex::new(pattern).map_err(|e| YinxError::Other(e.into()))?;
+        if text.is_match("") {
+            return Err(YinxError::Other(anyhow::anyhow!(
+                "Scrub pattern '{}' matches the empty string",
+                pattern
+            )));
+        }
+        let bytes = regex::bytes::Regex::new(pattern).map_err(|e| YinxError::Other(e.into()))?;
+
+        Ok(Self { text, bytes })
+    }
+
+    fn count(&self, text: &str) -> usize {
+        self.text.find_iter(text).count()
+    }
+
+    fn replace(&self, text: &str) -> String {
+        self.text.replace_all(text, SCRUB_REPLACEMENT).into_owned()
+    }
+
+    /// Occurrences in the strings of a JSON document, and the document with
+    /// them replaced
+    ///
+    /// Keys and string values are scrubbed one by one so the document stays
+    /// valid; text that isn't JSON is scrubbed as a whole.
+    fn replace_json(&self, text: &str) -> (usize, String) {
+        fn walk(pattern: &ScrubPattern, value: &mut serde_json::Value) -> usize {
+            match value {
+                serde_json::Value::String(s) => {
+                    let occurrences = pattern.count(s);
+                    if occurrences > 0 {
+                        *s = pattern.replace(s);
+                    }
+                    occurrences
+                }
+                serde_json::Value::Array(items) => {
+                    items.iter_mut().map(|item| walk(pattern, item)).sum()
+                }
+                serde_json::Value::Object(map) => {
+                    let mut occurrences = 0;
+                    for (key, mut item) in std::mem::take(map) {
+                        occurrences += pattern.count(&key) + walk(pattern, &mut item);
+                        map.insert(pattern.replace(&key), item);
+                    }
+                    occurrences
+                }
+                _ => 0,
+            }
+        }
+
+        match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(mut value) => {
+                let occurrences = walk(self, &mut value);
+                (occurrences, value.to_string())
+            }
+            Err(_) => (self.count(text), self.replace(text)),
+        }
+    }
+}
+
+/// Kind of record a scrubbed value was found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrubLocation {
+    Command,
+    SealedCommand,
+    Output,
+    Chunk,
+    Entity,
+    Arguments,
+    Context,
+    Annotation,
+    Finding,
+    Summary,
+    TriageHint,
+    CachedAnswer,
+}
+
+impl ScrubLocation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScrubLocation::Command => "command",
+            ScrubLocation::SealedCommand => "sealed command",
+            ScrubLocation::Output => "output",
+            ScrubLocation::Chunk => "chunk",
+            ScrubLocation::Entity => "entity",
+            ScrubLocation::Arguments => "arguments",
+            ScrubLocation::Context => "capture context",
+            ScrubLocation::Annotation => "annotation",
+            ScrubLocation::Finding => "finding",
+            ScrubLocation::Summary => "summary",
+            ScrubLocation::TriageHint => "triage hint",
+            ScrubLocation::CachedAnswer => "cached answer",
+        }
+    }
+}
+
+/// One record the value appeared in
+#[derive(Debug, Clone)]
+pub struct ScrubHit {
+    pub location: ScrubLocation,
+    pub session_id: String,
+    /// None for records that aren't tied to a capture
+    pub capture_id: Option<i64>,
+    pub occurrences: usize,
+}
+
+/// Everywhere a scrubbed value appeared and what was rewritten
+#[derive(Debug, Default)]
+pub struct ScrubReport {
+    pub hits: Vec<ScrubHit>,
+    /// Output blobs rewritten under a new hash
+    pub blobs_rewritten: usize,
+    /// Chunks re-inserted into the keyword index
+    pub chunks_reindexed: usize,
+    /// Chunks whose text was scrubbed: (id, new text, host), for re-indexing
+    pub rewritten_chunks: Vec<(u64, String, Option<String>)>,
+    /// Entities deleted because their value matched
+    pub entities_removed: usize,
+    /// Captures whose entity positions and host sections were dropped
+    /// because their output moved
+    pub positions_dropped: usize,
+    /// Cached answers deleted because their question or answer matched
+    pub answers_dropped: usize,
+    /// Slow query log entries the value appeared in
+    pub slow_log_entries: usize,
+    /// Embeddings dropped for rewritten chunks
+    pub embeddings_dropped: usize,
+    /// Sealed originals that could not be searched because the vault is closed
+    pub sealed_unchecked: usize,
+}
+
+impl ScrubReport {
+    /// Total number of occurrences found
+    pub fn total_occurrences(&self) -> usize {
+        self.hits.iter().map(|h| h.occurrences).sum()
+    }
+
+    /// Check if the value was not found anywhere
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty() && self.slow_log_entries == 0
+    }
+}
+
+/// Replacement for a blob that contained the value
+struct BlobRewrite {
+    new_hash: String,
+    size: i64,
+    compressed: bool,
+    occurrences: usize,
+}
+
+impl StorageManager {
+    /// Purge every occurrence of `pattern` from stored history
+    ///
+    /// With `dry_run` nothing is modified, but the report is the same.
+    pub fn scrub(&self, pattern: &ScrubPattern, dry_run: bool) -> Result<ScrubReport> {
+        let mut report = self.scrub_data(pattern, dry_run)?;
+        if report.rewritten_chunks.is_empty() {
+            return Ok(report);
+        }
+
+        let mut index = self.open_keyword_index()?;
+        index
+            .replace_hosted_batch(&report.rewritten_chunks)
+            .map_err(|e| YinxError::Other(e.into()))?;
+        index.commit().map_err(|e| YinxError::Other(e.into()))?;
+        report.chunks_reindexed = report.rewritten_chunks.len();
+        Ok(report)
+    }
+
+    /// Scrub the database and blobs, leaving the indexes to the caller
+    ///
+    /// For callers that hold the indexes open themselves; the rewritten
+    /// chunks are in the report.
+    pub fn scrub_data(&self, pattern: &ScrubPattern, dry_run: bool) -> Result<ScrubReport> {
+        let mut report = ScrubReport::default();
+        let mut conn = self.database.get_conn()?;
+        let tx = conn.transaction()?;
+
+        let captures: Vec<(i64, String, Option<String>, String)> = {
+            let mut stmt = tx
+                .prepare("SELECT id, session_id, command, output_hash FROM captures ORDER BY id")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        let sessions: HashMap<i64, &str> = captures
+            .iter()
+            .map(|(id, session_id, _, _)| (*id, session_id.as_str()))
+            .collect();
+
+        // Command lines and output blobs
+        let mut rewrites: HashMap<String, Option<BlobRewrite>> = HashMap::new();
+        for (capture_id, session_id, command, output_hash) in &captures {
+            if let Some(command) = command {
+                let occurrences = pattern.count(command);
+                if occurrences > 0 {
+                    report.hits.push(ScrubHit {
+                        location: ScrubLocation::Command,
+                        session_id: session_id.clone(),
+                        capture_id: Some(*capture_id),
+                        occurrences,
+                    });
+                    if !dry_run {
+                        tx.execute(
+                            "UPDATE captures SET command = ?1 WHERE id = ?2",
+                            params![pattern.replace(command), capture_id],
+                        )?;
+                    }
+                }
+            }
+
+            if !rewrites.contains_key(output_hash) {
+                let rewrite = self.scrub_blob(output_hash, pattern, dry_run)?;
+                rewrites.insert(output_hash.clone(), rewrite);
+            }
+            if let Some(Some(rewrite)) = rewrites.get(output_hash) {
+                report.hits.push(ScrubHit {
+                    location: ScrubLocation::Output,
+                    session_id: session_id.clone(),
+                    capture_id: Some(*capture_id),
+                    occurrences: rewrite.occurrences,
+                });
+            }
+        }
+
+        // Move references from the old blobs to their scrubbed replacements
+        let mut stale_blobs = Vec::new();
+        for (old_hash, rewrite) in &rewrites {
+            let Some(rewrite) = rewrite else { continue };
+            report.blobs_rewritten += 1;
+            if dry_run {
+                continue;
+            }
+
+            // The replacement row must exist before chunks can reference it
+            let (created_at, ref_count): (i64, i64) = tx
+                .query_row(
+                    "SELECT created_at, ref_count FROM blobs WHERE hash = ?1",
+                    params![old_hash],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?
+                .unwrap_or((chrono::Utc::now().timestamp(), 1));
+            tx.execute(
+                "INSERT INTO blobs (hash, size, created_at, compressed, ref_count)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(hash) DO UPDATE SET ref_count = ref_count + excluded.ref_count",
+                params![
+                    &rewrite.new_hash,
+                    rewrite.size,
+                    created_at,
+                    rewrite.compressed,
+                    ref_count
+                ],
+            )?;
+            // Offsets into the old output no longer line up with the new one
+            tx.execute(
+                "UPDATE entities SET start_offset = NULL, end_offset = NULL, line = NULL
+                 WHERE capture_id IN (SELECT id FROM captures WHERE output_hash = ?1)",
+                params![old_hash],
+            )?;
+            tx.execute(
+                "DELETE FROM subcaptures
+                 WHERE capture_id IN (SELECT id FROM captures WHERE output_hash = ?1)",
+                params![old_hash],
+            )?;
+            report.positions_dropped += tx.query_row(
+                "SELECT COUNT(*) FROM captures WHERE output_hash = ?1",
+                params![old_hash],
+                |row| row.get::<_, i64>(0),
+            )? as usize;
+            tx.execute(
+                "UPDATE captures SET output_hash = ?1 WHERE output_hash = ?2",
+                params![&rewrite.new_hash, old_hash],
+            )?;
+            tx.execute(
+                "UPDATE chunks SET blob_hash = ?1 WHERE blob_hash = ?2",
+                params![&rewrite.new_hash, old_hash],
+            )?;
+            tx.execute("DELETE FROM blobs WHERE hash = ?1", params![old_hash])?;
+            stale_blobs.push(old_hash.clone());
+        }
+
+        // Sealed originals of redacted command lines
+        let sealed: Vec<(i64, Vec<u8>)> = {
+            let mut stmt = tx.prepare("SELECT capture_id, sealed FROM command_originals")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        match self.vault() {
+            Some(vault) => {
+                for (capture_id, sealed) in sealed {
+                    let original = String::from_utf8_lossy(&vault.open(&sealed)?).into_owned();
+                    let occurrences = pattern.count(&original);
+                    if occurrences == 0 {
+                        continue;
+                    }
+                    report.hits.push(ScrubHit {
+                        location: ScrubLocation::SealedCommand,
+                        session_id: sessions.get(&capture_id).unwrap_or(&"").to_string(),
+                        capture_id: Some(capture_id),
+                        occurrences,
+                    });
+                    if !dry_run {
+                        let resealed = vault.seal(pattern.replace(&original).as_bytes())?;
+                        tx.execute(
+                            "UPDATE command_originals SET sealed = ?1 WHERE capture_id = ?2",
+                            params![resealed, capture_id],
+                        )?;
+                    }
+                }
+            }
+            None => report.sealed_unchecked = sealed.len(),
+        }
+
+        // Chunks and their embeddings
+        let chunks: Vec<(i64, i64, String, Option<String>)> = {
+            let mut stmt =
+                tx.prepare("SELECT id, capture_id, representative_text, metadata FROM chunks")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        for (chunk_id, capture_id, text, metadata) in chunks {
+            let occurrences =
+                pattern.count(&text) + metadata.as_deref().map_or(0, |m| pattern.count(m));
+            if occurrences == 0 {
+                continue;
+            }
+            report.hits.push(ScrubHit {
+                location: ScrubLocation::Chunk,
+                session_id: sessions.get(&capture_id).unwrap_or(&"").to_string(),
+                capture_id: Some(capture_id),
+                occurrences,
+            });
+            if dry_run {
+                continue;
+            }
+
+            let text = pattern.replace(&text);
+            let metadata = metadata.map(|m| pattern.replace(&m));
+            tx.execute(
+                "UPDATE chunks SET representative_text = ?1, metadata = ?2 WHERE id = ?3",
+                params![&text, metadata, chunk_id],
+            )?;
+            report.embeddings_dropped += tx.execute(
+                "DELETE FROM embeddings WHERE chunk_id = ?1",
+                params![chunk_id],
+            )?;
+            report.rewritten_chunks.push((
+                chunk_id as u64,
+                text,
+                metadata.as_deref().and_then(metadata_host),
+            ));
+        }
+
+        // Entities: drop those whose value matched, scrub the context of the rest
+        let entities: Vec<(i64, i64, String, Option<String>)> = {
+            let mut stmt = tx.prepare("SELECT id, capture_id, value, context FROM entities")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        for (entity_id, capture_id, value, context) in entities {
+            let in_value = pattern.count(&value);
+            let occurrences = in_value + context.as_deref().map_or(0, |c| pattern.count(c));
+            if occurrences == 0 {
+                continue;
+            }
+            report.hits.push(ScrubHit {
+                location: ScrubLocation::Entity,
+                session_id: sessions.get(&capture_id).unwrap_or(&"").to_string(),
+                capture_id: Some(capture_id),
+                occurrences,
+            });
+            if in_value > 0 {
+                report.entities_removed += 1;
+            }
+            if dry_run {
+                continue;
+            }
+
+            if in_value > 0 {
+                tx.execute("DELETE FROM entities WHERE id = ?1", params![entity_id])?;
+            } else {
+                tx.execute(
+                    "UPDATE entities SET context = ?1 WHERE id = ?2",
+                    params![context.map(|c| pattern.replace(&c)), entity_id],
+                )?;
+            }
+        }
+
+        // Everything else recorded about captures
+        let records = [
+            (
+                ScrubLocation::Arguments,
+                "captures",
+                "t.session_id",
+                &["target", "ports", "wordlist", "url", "service"][..],
+            ),
+            (
+                ScrubLocation::Context,
+                "capture_context",
+                "c.session_id",
+                &[
+                    "git_root",
+                    "git_branch",
+                    "venv",
+                    "vpn_interface",
+                    "env",
+                    "hostname",
+                    "terminal",
+                ][..],
+            ),
+            (
+                ScrubLocation::Annotation,
+                "annotations",
+                "c.session_id",
+                &["data"][..],
+            ),
+            (
+                ScrubLocation::Finding,
+                "findings",
+                "t.session_id",
+                &["title"][..],
+            ),
+            (
+                ScrubLocation::Summary,
+                "summaries",
+                "c.session_id",
+                &["summary"][..],
+            ),
+            (
+                ScrubLocation::TriageHint,
+                "triage_hints",
+                "c.session_id",
+                &["hint", "evidence", "explanation"][..],
+            ),
+        ];
+        for (location, table, session, columns) in records {
+            for (session_id, capture_id, occurrences) in
+                scrub_rows(&tx, pattern, table, session, columns, dry_run)?
+            {
+                report.hits.push(ScrubHit {
+                    location,
+                    session_id,
+                    capture_id,
+                    occurrences,
+                });
+            }
+        }
+
+        // Cached answers are dropped rather than rewritten; the next question
+        // is answered from the scrubbed history
+        let answers: Vec<(i64, String, String, String)> = {
+            let mut stmt =
+                tx.prepare("SELECT id, session_id, question, answer FROM answer_cache")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        for (id, session_id, question, answer) in answers {
+            let occurrences = pattern.count(&question) + pattern.count(&answer);
+            if occurrences == 0 {
+                continue;
+            }
+            report.hits.push(ScrubHit {
+                location: ScrubLocation::CachedAnswer,
+                session_id,
+                capture_id: None,
+                occurrences,
+            });
+            report.answers_dropped += 1;
+            if !dry_run {
+                tx.execute("DELETE FROM answer_cache WHERE id = ?1", params![id])?;
+            }
+        }
+
+        let slow_log = self.base_path.join("logs").join(SLOW_LOG);
+        if dry_run {
+            report.slow_log_entries = scrub_slow_log(&slow_log, pattern, true)?;
+            return Ok(report);
+        }
+
+        // Session-wide entity aggregates carry the value too
+        let aggregates: Vec<(i64, String)> = {
+            let mut stmt = tx.prepare("SELECT id, value FROM session_entities")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        for (id, value) in aggregates {
+            if pattern.count(&value) > 0 {
+                tx.execute("DELETE FROM session_entities WHERE id = ?1", params![id])?;
+            }
+        }
+        tx.commit()?;
+
+        for hash in &stale_blobs {
+            self.blob_store.delete(hash)?;
+        }
+        report.slow_log_entries = scrub_slow_log(&slow_log, pattern, false)?;
+
+        Ok(report)
+    }
+
+    /// Scrub a single blob, writing the replacement unless this is a dry run
+    fn scrub_blob(
+        &self,
+        hash: &str,
+        pattern: &ScrubPattern,
+        dry_run: bool,
+    ) -> Result<Option<BlobRewrite>> {
+        if !self.blob_store.exists(hash) {
+            tracing::warn!("Blob {} missing while scrubbing", hash);
+            return Ok(None);
+        }
+
+        let data = self.blob_store.read(hash)?;
+        let occurrences = pattern.bytes.find_iter(&data).count();
+        if occurrences == 0 {
+            return Ok(None);
+        }
+
+        let scrubbed = pattern
+            .bytes
+            .replace_all(&data, SCRUB_REPLACEMENT.as_bytes());
+        let (new_hash, compressed) = if dry_run {
+            (blake3::hash(&scrubbed).to_hex().to_string(), false)
+        } else {
+            let (hash, compressed, _) = self.blob_store.write(&scrubbed)?;
+            (hash, compressed)
+        };
+
+        Ok(Some(BlobRewrite {
+            new_hash,
+            size: scrubbed.len() as i64,
+            compressed,
+            occurrences,
+        }))
+    }
+}
+
+/// Scrub text `columns` of every row in `table`, returning the session,
+/// capture and occurrences of each row the value was found in
+///
+/// Rows are joined to their capture as `c`; `session` selects the session
+/// id. JSON columns are scrubbed string by string so they stay parseable.
+fn scrub_rows(
+    tx: &Transaction,
+    pattern: &ScrubPattern,
+    table: &str,
+    session: &str,
+    columns: &[&str],
+    dry_run: bool,
+) -> Result<Vec<(String, Option<i64>, usize)>> {
+    let capture = if table == "captures" {
+        "id"
+    } else {
+        "capture_id"
+    };
+    // rowid, capture, session and the columns' values
+    type Row = (i64, Option<i64>, Option<String>, Vec<Option<String>>);
+    let rows: Vec<Row> = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT t.rowid, t.{capture}, {session}, {columns}
+             FROM {table} t LEFT JOIN captures c ON c.id = t.{capture}",
+            capture = capture,
+            session = session,
+            columns = columns
+                .iter()
+                .map(|c| format!("t.{}", c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            table = table,
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            let values = (0..columns.len())
+                .map(|i| row.get(3 + i))
+                .collect::<std::result::Result<Vec<Option<String>>, _>>()?;
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, values))
+        })?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()?
+    };
+
+    let mut hits = Vec::new();
+    for (rowid, capture_id, session_id, values) in rows {
+        let mut occurrences = 0;
+        for (column, value) in columns.iter().zip(values) {
+            let Some(value) = value else { continue };
+            let (found, scrubbed) = if matches!(*column, "env" | "data") {
+                pattern.replace_json(&value)
+            } else {
+                (pattern.count(&value), pattern.replace(&value))
+            };
+            if found == 0 {
+                continue;
+            }
+            occurrences += found;
+            if !dry_run {
+                // OR REPLACE: a scrubbed finding title may now equal another's
+                tx.execute(
+                    &format!(
+                        "UPDATE OR REPLACE {} SET {} = ?1 WHERE rowid = ?2",
+                        table, column
+                    ),
+                    params![scrubbed, rowid],
+                )?;
+            }
+        }
+        if occurrences > 0 {
+            hits.push((session_id.unwrap_or_default(), capture_id, occurrences));
+        }
+    }
+    Ok(hits)
+}
+
+/// Scrub the params of slow query log entries, returning how many matched
+fn scrub_slow_log(path: &Path, pattern: &ScrubPattern, dry_run: bool) -> Result<usize> {
+    let io_err = |e| YinxError::Io {
+        source: e,
+        context: format!("Failed to scrub {}", path.display()),
+    };
+    let log = match std::fs::read_to_string(path) {
+        Ok(log) => log,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(io_err(e)),
+    };
+
+    let mut matched = 0;
+    let mut scrubbed = String::with_capacity(log.len());
+    for line in log.lines() {
+        if pattern.count(line) == 0 {
+            scrubbed.push_str(line);
+        } else {
+            matched += 1;
+            match serde_json::from_str::<SlowCall>(line) {
+                Ok(mut call) => {
+                    call.op = pattern.replace(&call.op);
+                    call.params = pattern.replace(&call.params);
+                    let line = serde_json::to_string(&call).map_err(|e| YinxError::Json {
+                        source: e,
+                        context: "Failed to serialize slow call".to_string(),
+                    })?;
+                    scrubbed.push_str(&line);
+                }
+                Err(_) => scrubbed.push_str(&pattern.replace(line)),
+            }
+        }
+        scrubbed.push('\n');
+    }
+    if matched == 0 || dry_run {
+        return Ok(matched);
+    }
+
+    // Written aside and renamed so a reader never sees half a file
+    let temp = path.with_extension("log.tmp");
+    std::fs::write(&temp, scrubbed).map_err(io_err)?;
+    std::fs::rename(&temp, path).map_err(io_err)?;
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::HostSection;
+    use crate::storage::fixtures::capture;
+    use crate::storage::EntityPosition;
+    use tempfile::TempDir;
+
+    fn seed(storage: &StorageManager) -> (i64, String) {
+        storage
+            .database
+            .ensure_session("s1", "s1", 0, "stopped")
+            .unwrap();
+        let seeded = capture(
+            "s1",
+            "sshpass -p Hunter2Secret ssh admin@10.0.0.5",
+            b"login: admin\npassword accepted for Hunter2Secret\n",
+        )
+        .target("Hunter2Secret@10.0.0.5")
+        .chunks(&["password accepted for Hunter2Secret"])
+        .insert(storage);
+        let (capture_id, hash, chunk_id) = (seeded.id, seeded.hash, seeded.chunk_ids[0]);
+        storage
+            .database
+            .insert_entities(
+                capture_id,
+                &[
+                    (
+                        "credential_password".to_string(),
+                        "Hunter2Secret".to_string(),
+                        "accepted for Hunter2Secret".to_string(),
+                        0.7,
+                    ),
+                    (
+                        "ip_address".to_string(),
+                        "10.0.0.5".to_string(),
+                        "-p Hunter2Secret ssh admin@10.0.0.5".to_string(),
+                        0.95,
+                    ),
+                ],
+            )
+            .unwrap();
+        storage
+            .database
+            .insert_embedding(chunk_id, &[0u8; 16], "test-model")
+            .unwrap();
+        storage
+            .database
+            .set_entity_positions(
+                capture_id,
+                &[EntityPosition {
+                    entity_type: "ip_address".to_string(),
+                    value: "10.0.0.5".to_string(),
+                    start: 40,
+                    end: 48,
+                    line: 2,
+                    host: None,
+                }],
+            )
+            .unwrap();
+        storage
+            .database
+            .set_subcaptures(
+                capture_id,
+                &[HostSection {
+                    host: Some("10.0.0.5".to_string()),
+                    lines: 0..2,
+                    bytes: 0..49,
+                }],
+            )
+            .unwrap();
+
+        let conn = storage.database.get_conn().unwrap();
+        conn.execute_batch(&format!(
+            "INSERT INTO capture_context (capture_id, env) VALUES ({id}, '{{\"SSHPASS\":\"Hunter2Secret\"}}');
+             INSERT INTO annotations (capture_id, hook, stage, data, created_at)
+             VALUES ({id}, 'notify', 'post', '[\"Hunter2Secret\"]', 0);
+             INSERT INTO findings (session_id, capture_id, title, severity, created_at)
+             VALUES ('s1', {id}, 'Password Hunter2Secret reused', 'high', 0);
+             INSERT INTO summaries (capture_id, summary, method, created_at)
+             VALUES ({id}, 'logged in with Hunter2Secret', 'extractive', 0);
+             INSERT INTO triage_hints (capture_id, rule, hint, evidence, created_at)
+             VALUES ({id}, 'auth', 'check Hunter2Secret', 'for Hunter2Secret', 0);
+             INSERT INTO answer_cache
+             (session_id, question, embedding, model, answer, index_state, created_at)
+             VALUES ('s1', 'admin password?', x'00', 'm', 'Hunter2Secret', '', 0);",
+            id = capture_id
+        ))
+        .unwrap();
+        drop(conn);
+
+        let slow = SlowCall {
+            at: 0,
+            op: "db.search".to_string(),
+            ms: 900.0,
+            params: "query=Hunter2Secret".to_string(),
+        };
+        let logs = storage.base_path().join("logs");
+        std::fs::create_dir_all(&logs).unwrap();
+        std::fs::write(
+            logs.join(SLOW_LOG),
+            format!(
+                "{}\n{}\n",
+                serde_json::to_string(&slow).unwrap(),
+                r#"{"at":0,"op":"db.get_capture","ms":800.0,"params":"capture=1"}"#
+            ),
+        )
+        .unwrap();
+
+        (capture_id, hash)
+    }
+
+    #[test]
+    fn test_scrub_rewrites_everything() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let (capture_id, old_hash) = seed(&storage);
+        let pattern = ScrubPattern::literal("Hunter2Secret").unwrap();
+
+        let preview = storage.scrub(&pattern, true).unwrap();
+        assert_eq!(preview.hits.len(), 12);
+        assert_eq!(preview.slow_log_entries, 1);
+        assert_eq!(preview.blobs_rewritten, 1);
+        assert!(storage.blob_store.exists(&old_hash));
+
+        let report = storage.scrub(&pattern, false).unwrap();
+        assert_eq!(report.total_occurrences(), preview.total_occurrences());
+        assert_eq!(report.entities_removed, 1);
+        assert_eq!(report.positions_dropped, 1);
+        assert_eq!(report.answers_dropped, 1);
+        assert_eq!(report.embeddings_dropped, 1);
+        assert_eq!(report.chunks_reindexed, 1);
+        assert!(!storage.blob_store.exists(&old_hash));
+
+        let capture = storage.database.get_capture(capture_id).unwrap().unwrap();
+        assert_eq!(
+            capture.command.as_deref(),
+            Some("sshpass -p [SCRUBBED] ssh admin@10.0.0.5")
+        );
+        let output = storage.blob_store.read(&capture.output_hash).unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("for [SCRUBBED]"));
+
+        let entities = storage
+            .database
+            .get_entities_for_capture(capture_id)
+            .unwrap();
+        assert_eq!(entities.len(), 1);
+        assert!(!entities[0]
+            .context
+            .as_deref()
+            .unwrap_or_default()
+            .contains("Hunter2Secret"));
+        let aggregates = storage
+            .database
+            .get_session_entities(&capture.session_id, None)
+            .unwrap();
+        assert!(aggregates.iter().all(|e| e.value != "Hunter2Secret"));
+        assert_eq!(aggregates.len(), 1);
+
+        // Offsets into the old output are gone
+        assert_eq!(entities[0].start, None);
+        assert!(storage
+            .database
+            .get_subcaptures(capture_id)
+            .unwrap()
+            .is_empty());
+
+        let conn = storage.database.get_conn().unwrap();
+        let env: String = conn
+            .query_row("SELECT env FROM capture_context", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(env, r#"{"SSHPASS":"[SCRUBBED]"}"#);
+        let answers: i64 = conn
+            .query_row("SELECT COUNT(*) FROM answer_cache", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(answers, 0);
+        drop(conn);
+        assert_eq!(
+            capture.arguments.target.as_deref(),
+            Some("[SCRUBBED]@10.0.0.5")
+        );
+        let slow_log =
+            std::fs::read_to_string(storage.base_path().join("logs").join(SLOW_LOG)).unwrap();
+        assert!(slow_log.contains("query=[SCRUBBED]"));
+        assert_eq!(slow_log.lines().count(), 2);
+
+        // Nothing left to find
+        assert!(storage.scrub(&pattern, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_empty_pattern_rejected() {
+        assert!(ScrubPattern::literal("").is_err());
+        assert!(ScrubPattern::regex("a*").is_err());
+        assert!(ScrubPattern::regex("(").is_err());
+    }
+}
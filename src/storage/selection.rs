@@ -0,0 +1,141 @@
+//! Carving out part of a session
+//!
+//! `yinx export --host/--tool/--tag/--since/--until` shares only the
+//! captures that matter to a teammate. A capture's hosts are worked out the
+//! same way as for transcripts.
+
+use super::StorageManager;
+use crate::error::Result;
+
+/// Which of a session's captures to take; empty lists match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaptureSelection {
+    /// Captures involving any of these hosts
+    pub hosts: Vec<String>,
+    /// Captures of any of these tools
+    pub tools: Vec<String>,
+    /// Captures carrying any of these tags
+    pub tags: Vec<String>,
+    /// Inclusive bounds on the capture time, Unix seconds
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl CaptureSelection {
+    /// Whether every capture matches
+    pub fn is_everything(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl StorageManager {
+    /// Ids of the session's captures matching `selection`, in execution order
+    pub fn select_captures(
+        &self,
+        session_id: &str,
+        selection: &CaptureSelection,
+    ) -> Result<Vec<i64>> {
+        let mut ids = Vec::new();
+        for entry in self.transcript_entries(session_id)? {
+            let capture = &entry.capture;
+            if selection
+                .since
+                .is_some_and(|since| capture.timestamp < since)
+                || selection
+                    .until
+                    .is_some_and(|until| capture.timestamp > until)
+            {
+                continue;
+            }
+            if !selection.tools.is_empty()
+                && !capture
+                    .tool
+                    .as_ref()
+                    .is_some_and(|tool| selection.tools.contains(tool))
+            {
+                continue;
+            }
+            if !selection.hosts.is_empty()
+                && !selection
+                    .hosts
+                    .iter()
+                    .any(|host| entry.hosts.contains(host))
+            {
+                continue;
+            }
+            if !selection.tags.is_empty() {
+                let tags = self.database.get_capture_tags(capture.id)?;
+                if !selection.tags.iter().any(|tag| tags.contains(tag)) {
+                    continue;
+                }
+            }
+            ids.push(capture.id);
+        }
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::CaptureEvent;
+    use crate::Yinx;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_select_captures() {
+        let temp_dir = TempDir::new().unwrap();
+        let yinx = Yinx::open(temp_dir.path()).unwrap();
+        let session_id = yinx.start_session("carve").unwrap().id.to_string();
+        let captures = [
+            ("nmap -sV 10.0.0.5", "10.0.0.5 22/tcp open ssh\n"),
+            ("nmap -sV 10.0.0.6", "10.0.0.6 80/tcp open http\n"),
+            ("gobuster dir -u http://10.0.0.6", "/admin (Status: 301)\n"),
+        ];
+        for (i, (command, output)) in captures.iter().enumerate() {
+            let event = CaptureEvent {
+                session_id: session_id.clone(),
+                timestamp: 1_700_000_000 + 100 * i as i64,
+                command: command.to_string(),
+                output: output.to_string(),
+                exit_code: 0,
+                cwd: "/tmp".to_string(),
+                username: None,
+                source: None,
+                tool: None,
+                context: None,
+            };
+            yinx.ingest(event).await.unwrap();
+        }
+        let storage = yinx.storage();
+        let all = storage
+            .select_captures(&session_id, &CaptureSelection::default())
+            .unwrap();
+        assert_eq!(all.len(), 3);
+        storage.database.add_capture_tag(all[0], "pivot").unwrap();
+
+        let select =
+            |selection: CaptureSelection| storage.select_captures(&session_id, &selection).unwrap();
+        let host = select(CaptureSelection {
+            hosts: vec!["10.0.0.6".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(host, all[1..]);
+        let tool = select(CaptureSelection {
+            tools: vec!["gobuster".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(tool, all[2..]);
+        let tag = select(CaptureSelection {
+            tags: vec!["pivot".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(tag, all[..1]);
+        let window = select(CaptureSelection {
+            since: Some(1_700_000_100),
+            until: Some(1_700_000_100),
+            ..Default::default()
+        });
+        assert_eq!(window, all[1..2]);
+    }
+}
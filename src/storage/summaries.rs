@@ -0,0 +1,100 @@
+//! Stored per-capture digests and their keyword index
+
+use super::StorageManager;
+use crate::embedding::KeywordIndex;
+use crate::error::{Result, YinxError};
+use chrono::Utc;
+use rusqlite::params;
+use std::collections::HashMap;
+
+impl StorageManager {
+    /// Captures of at least `min_lines` input lines that have no digest yet, oldest first
+    pub fn captures_to_summarize(&self, min_lines: usize, limit: usize) -> Result<Vec<i64>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.id FROM captures c
+             JOIN filter_stats f ON f.capture_id = c.id
+             LEFT JOIN summaries s ON s.capture_id = c.id
+             WHERE s.capture_id IS NULL AND f.input_lines >= ?1
+             ORDER BY c.id LIMIT ?2",
+        )?;
+        let ids = stmt
+            .query_map(params![min_lines as i64, limit as i64], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<i64>, _>>()?;
+        Ok(ids)
+    }
+
+    /// Store a capture's digest; an empty summary marks it as not worth one
+    pub fn store_summary(&self, capture_id: i64, summary: &str, method: &str) -> Result<()> {
+        let conn = self.database.get_conn()?;
+        conn.execute(
+            "INSERT INTO summaries (capture_id, summary, method, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(capture_id) DO UPDATE SET
+                summary = excluded.summary, method = excluded.method,
+                created_at = excluded.created_at",
+            params![capture_id, summary, method, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Digests of the given captures, where one exists
+    pub fn get_summaries(&self, capture_ids: &[i64]) -> Result<HashMap<i64, String>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt =
+            conn.prepare("SELECT summary FROM summaries WHERE capture_id = ?1 AND summary != ''")?;
+        let mut summaries = HashMap::new();
+        for &capture_id in capture_ids {
+            let mut rows = stmt.query_map([capture_id], |row| row.get::<_, String>(0))?;
+            if let Some(summary) = rows.next().transpose()? {
+                summaries.insert(capture_id, summary);
+            }
+        }
+        Ok(summaries)
+    }
+
+    /// Keyword index over digests, keyed by capture id
+    pub fn open_summary_index(&self) -> Result<KeywordIndex> {
+        KeywordIndex::new(self.machine_zone().join("summaries"))
+            .map_err(|e| YinxError::Other(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pending_summaries() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        for lines in [500, 20, 300] {
+            let capture = capture("s1", "nmap", b"").insert(&storage);
+            storage
+                .database
+                .get_conn()
+                .unwrap()
+                .execute(
+                    "INSERT INTO filter_stats (capture_id, session_id, input_lines, tier1_output,
+                        tier2_output, tier3_clusters, tier2_percentile, tier2_threshold,
+                        score_median, score_p90, processing_time_ms, created_at)
+                     VALUES (?1, 's1', ?2, 0, 0, 0, 0, 0, 0, 0, 0, 0)",
+                    params![capture.id, lines],
+                )
+                .unwrap();
+        }
+
+        assert_eq!(storage.captures_to_summarize(200, 10).unwrap(), vec![1, 3]);
+        storage
+            .store_summary(1, "nmap output of 500 lines.", "extractive")
+            .unwrap();
+        storage.store_summary(3, "", "extractive").unwrap();
+        assert!(storage.captures_to_summarize(200, 10).unwrap().is_empty());
+
+        let summaries = storage.get_summaries(&[1, 2, 3]).unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[&1], "nmap output of 500 lines.");
+    }
+}
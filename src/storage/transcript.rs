@@ -0,0 +1,301 @@
+//! Session transcripts
+//!
+//! Stitches a session's captured output back together in execution order,
+//! each command under a header with its time, so the session reads like one
+//! terminal log. Transcripts can be narrowed to a tool, a host or the
+//! terminal commands were typed in, or split into one file per host under
+//! the session's `evidence/` directory.
+
+use super::attachments::sanitize;
+use super::{CaptureRecord, StorageManager};
+use crate::daemon::CaptureContext;
+use crate::error::{Result, YinxError};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Which captures go into a transcript
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptFilter {
+    pub tool: Option<String>,
+    pub host: Option<String>,
+    /// Terminal, hostname or `host:terminal` the commands were typed in
+    pub terminal: Option<String>,
+}
+
+/// A capture and the hosts it involves
+pub(super) struct Entry {
+    pub(super) capture: CaptureRecord,
+    pub(super) hosts: BTreeSet<String>,
+    /// Recorded environment, if any; carries the originating terminal
+    pub(super) context: CaptureContext,
+}
+
+impl Entry {
+    fn matches(&self, filter: &TranscriptFilter) -> bool {
+        filter
+            .tool
+            .as_ref()
+            .is_none_or(|tool| self.capture.tool.as_ref() == Some(tool))
+            && filter
+                .host
+                .as_ref()
+                .is_none_or(|host| self.hosts.contains(host))
+            && filter
+                .terminal
+                .as_ref()
+                .is_none_or(|terminal| self.context.on_terminal(terminal))
+    }
+}
+
+impl StorageManager {
+    /// Render a session's captures as one log
+    pub fn transcript(&self, session_id: &str, filter: &TranscriptFilter) -> Result<String> {
+        let entries = self.transcript_entries(session_id)?;
+        self.render_transcript(entries.iter().filter(|e| e.matches(filter)))
+    }
+
+    /// Write one transcript per host into the session's evidence directory
+    ///
+    /// Returns each host with the file written for it.
+    pub fn write_host_transcripts(
+        &self,
+        session_id: &str,
+        session_name: &str,
+        filter: &TranscriptFilter,
+    ) -> Result<Vec<(String, PathBuf)>> {
+        let entries = self.transcript_entries(session_id)?;
+        let mut by_host: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+        for entry in entries.iter().filter(|e| e.matches(filter)) {
+            for host in &entry.hosts {
+                if filter.host.as_ref().is_none_or(|h| h == host) {
+                    by_host.entry(host).or_default().push(entry);
+                }
+            }
+        }
+
+        let evidence = self
+            .ensure_session_report_dir(session_name)?
+            .join("evidence");
+        let mut written = Vec::new();
+        for (host, entries) in by_host {
+            let path = evidence.join(format!("transcript-{}.log", sanitize(host)));
+            let log = self.render_transcript(entries.into_iter())?;
+            std::fs::write(&path, log).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to write transcript: {}", path.display()),
+            })?;
+            written.push((host.to_string(), path));
+        }
+        Ok(written)
+    }
+
+    /// Session captures in execution order with their hosts
+    ///
+    /// A capture involves the hosts extracted from its output, the single
+    /// host its command was parsed as targeting, and any host seen elsewhere
+    /// in the session that its command line names.
+    pub(super) fn transcript_entries(&self, session_id: &str) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for capture in self.database.get_session_captures(session_id)? {
+            let mut hosts: BTreeSet<String> = self
+                .database
+                .get_entities_for_capture(capture.id)?
+                .into_iter()
+                .filter(|e| match e.entity_type.as_str() {
+                    "ip_address" => !e.value.starts_with("127."),
+                    "hostname" => true,
+                    _ => false,
+                })
+                .map(|e| e.value)
+                .collect();
+            if let Some(target) = &capture.arguments.target {
+                if !target.contains(['/', ',']) {
+                    hosts.insert(target.clone());
+                }
+            }
+            let context = self
+                .database
+                .get_capture_context(capture.id)?
+                .unwrap_or_default();
+            entries.push(Entry {
+                capture,
+                hosts,
+                context,
+            });
+        }
+
+        let known: BTreeSet<String> = entries.iter().flat_map(|e| e.hosts.clone()).collect();
+        for entry in &mut entries {
+            let command = entry.capture.command.as_deref().unwrap_or("");
+            entry.hosts.extend(
+                known
+                    .iter()
+                    .filter(|host| command.contains(host.as_str()))
+                    .cloned(),
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Command line and the first `max_lines` lines of a capture's output
+    pub fn capture_excerpt(&self, capture: &CaptureRecord, max_lines: usize) -> String {
+        let mut excerpt = format!("$ {}\n", capture.command.as_deref().unwrap_or(""));
+        match self.blob_store.read(&capture.output_hash) {
+            Ok(output) => {
+                let output = String::from_utf8_lossy(&output);
+                let mut lines = output.lines();
+                for line in lines.by_ref().take(max_lines) {
+                    excerpt.push_str(line);
+                    excerpt.push('\n');
+                }
+                if lines.next().is_some() {
+                    excerpt.push_str("[...]\n");
+                }
+            }
+            Err(_) => excerpt.push_str("[output unavailable]\n"),
+        }
+        excerpt.trim_end().to_string()
+    }
+
+    fn render_transcript<'a>(&self, entries: impl Iterator<Item = &'a Entry>) -> Result<String> {
+        let entries: Vec<&Entry> = entries.collect();
+        let ids: Vec<i64> = entries.iter().map(|e| e.capture.id).collect();
+        let hints = self.triage_hints(&ids)?;
+        let mut log = String::new();
+        for Entry {
+            capture, context, ..
+        } in entries
+        {
+            let time = chrono::DateTime::from_timestamp(capture.timestamp, 0)
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| capture.timestamp.to_string());
+            let _ = write!(log, "=== {} | capture {}", time, capture.id);
+            if let Some(tool) = &capture.tool {
+                let _ = write!(log, " | {}", tool);
+            }
+            if let Some(terminal) = context.terminal_label() {
+                let _ = write!(log, " | {}", terminal);
+            }
+            log.push_str(" ===\n");
+
+            let _ = writeln!(
+                log,
+                "{}:{}$ {}",
+                capture.username.as_deref().unwrap_or("?"),
+                capture.cwd.as_deref().unwrap_or("?"),
+                capture.command.as_deref().unwrap_or("")
+            );
+            match self.blob_store.read(&capture.output_hash) {
+                Ok(output) => {
+                    let output = String::from_utf8_lossy(&output);
+                    log.push_str(&output);
+                    if !output.is_empty() && !output.ends_with('\n') {
+                        log.push('\n');
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Output of capture {} unavailable: {}", capture.id, e);
+                    log.push_str("[output unavailable]\n");
+                }
+            }
+            if let Some(code) = capture.exit_code.filter(|code| *code != 0) {
+                let _ = writeln!(log, "[exit code {}]", code);
+            }
+            if let Some(hint) = hints.get(&capture.id) {
+                let _ = writeln!(log, "[hint: {}]", hint.hint);
+                if let Some(explanation) = &hint.explanation {
+                    let _ = writeln!(log, "[explanation: {}]", explanation);
+                }
+            }
+            log.push('\n');
+        }
+        Ok(log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures;
+    use tempfile::TempDir;
+
+    fn capture(storage: &StorageManager, timestamp: i64, command: &str, tool: &str, output: &str) {
+        let id = fixtures::capture("s1", command, output.as_bytes())
+            .timestamp(timestamp)
+            .tool(tool)
+            .exit_code(0)
+            .prompt("alice", "/tmp")
+            .insert(storage)
+            .id;
+        let hosts: Vec<_> = output
+            .split_whitespace()
+            .filter(|w| w.starts_with("10."))
+            .map(|ip| ("ip_address".to_string(), ip.to_string(), String::new(), 0.9))
+            .collect();
+        storage.database.insert_entities(id, &hosts).unwrap();
+    }
+
+    #[test]
+    fn test_transcript_order_and_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        storage
+            .database
+            .ensure_session("s1", "exam", 0, "active")
+            .unwrap();
+        capture(&storage, 200, "whoami", "", "alice");
+        capture(
+            &storage,
+            100,
+            "nmap 10.0.0.5",
+            "nmap",
+            "Nmap scan report for 10.0.0.5",
+        );
+        capture(&storage, 300, "curl http://10.0.0.5/", "curl", "<html>");
+
+        let log = storage
+            .transcript("s1", &TranscriptFilter::default())
+            .unwrap();
+        let nmap = log.find("alice:/tmp$ nmap").unwrap();
+        let whoami = log.find("alice:/tmp$ whoami").unwrap();
+        assert!(nmap < whoami);
+        assert!(log.contains("=== 1970-01-01 00:01:40 UTC | capture 2 | nmap ==="));
+
+        // curl's output has no address, but its command names a known host
+        let filter = TranscriptFilter {
+            host: Some("10.0.0.5".to_string()),
+            ..Default::default()
+        };
+        let log = storage.transcript("s1", &filter).unwrap();
+        assert!(log.contains("curl http://10.0.0.5/"));
+        assert!(!log.contains("whoami"));
+
+        let kali = CaptureContext {
+            hostname: Some("kali".to_string()),
+            terminal: Some("pts/1".to_string()),
+            ..CaptureContext::default()
+        };
+        storage.database.insert_capture_context(2, &kali).unwrap();
+        let filter = TranscriptFilter {
+            terminal: Some("pts/1".to_string()),
+            ..Default::default()
+        };
+        let log = storage.transcript("s1", &filter).unwrap();
+        assert!(log.contains("| capture 2 | nmap | kali:pts/1 ==="));
+        assert!(!log.contains("curl"));
+        assert_eq!(
+            storage.database.terminal_counts("s1").unwrap(),
+            vec![("unknown".to_string(), 2), ("kali:pts/1".to_string(), 1)]
+        );
+
+        let written = storage
+            .write_host_transcripts("s1", "exam", &TranscriptFilter::default())
+            .unwrap();
+        assert_eq!(written.len(), 1);
+        assert!(written[0].1.ends_with("evidence/transcript-10.0.0.5.log"));
+        assert!(std::fs::read_to_string(&written[0].1)
+            .unwrap()
+            .contains("<html>"));
+    }
+}
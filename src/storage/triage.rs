@@ -0,0 +1,150 @@
+//! Triage hints on failed captures
+//!
+//! The pipeline records one hint per failed capture whose output matched a
+//! triage rule, and fills in the explanation when a `triage` hook gave one.
+
+use super::StorageManager;
+use crate::error::Result;
+use crate::triage::TriageHint;
+use chrono::Utc;
+use rusqlite::params;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A failed capture of a session and why it failed
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentFailure {
+    pub capture_id: i64,
+    pub timestamp: i64,
+    pub command: Option<String>,
+    pub exit_code: Option<i32>,
+    pub triage: TriageHint,
+}
+
+fn hint_from_row(row: &rusqlite::Row, offset: usize) -> rusqlite::Result<TriageHint> {
+    Ok(TriageHint {
+        rule: row.get(offset)?,
+        hint: row.get(offset + 1)?,
+        evidence: row.get(offset + 2)?,
+        explanation: row.get(offset + 3)?,
+    })
+}
+
+impl StorageManager {
+    /// Record the hint for a capture, replacing any earlier one
+    pub fn store_triage_hint(&self, capture_id: i64, hint: &TriageHint) -> Result<()> {
+        let conn = self.database.get_conn()?;
+        conn.execute(
+            "INSERT INTO triage_hints (capture_id, rule, hint, evidence, explanation, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(capture_id) DO UPDATE SET
+                rule = excluded.rule, hint = excluded.hint, evidence = excluded.evidence,
+                explanation = excluded.explanation, created_at = excluded.created_at",
+            params![
+                capture_id,
+                hint.rule,
+                hint.hint,
+                hint.evidence,
+                hint.explanation,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Hints of the given captures, where one exists
+    pub fn triage_hints(&self, capture_ids: &[i64]) -> Result<HashMap<i64, TriageHint>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT rule, hint, evidence, explanation FROM triage_hints WHERE capture_id = ?1",
+        )?;
+        let mut hints = HashMap::new();
+        for &capture_id in capture_ids {
+            let mut rows = stmt.query_map([capture_id], |row| hint_from_row(row, 0))?;
+            if let Some(hint) = rows.next().transpose()? {
+                hints.insert(capture_id, hint);
+            }
+        }
+        Ok(hints)
+    }
+
+    /// The session's latest failed captures that were triaged, newest first
+    pub fn recent_failures(&self, session_id: &str, limit: usize) -> Result<Vec<RecentFailure>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.timestamp, c.command, c.exit_code,
+                t.rule, t.hint, t.evidence, t.explanation
+             FROM triage_hints t
+             JOIN captures c ON c.id = t.capture_id
+             WHERE c.session_id = ?1
+             ORDER BY c.timestamp DESC, c.id DESC
+             LIMIT ?2",
+        )?;
+        let failures = stmt
+            .query_map(params![session_id, limit as i64], |row| {
+                Ok(RecentFailure {
+                    capture_id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    command: row.get(2)?,
+                    exit_code: row.get(3)?,
+                    triage: hint_from_row(row, 4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_triage_hints() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        for (timestamp, command, exit_code) in [
+            (10, "curl http://10.0.0.5:8080", 7),
+            (20, "nmap -sS 10.0.0.5", 1),
+            (30, "id", 0),
+        ] {
+            capture("s1", command, b"")
+                .timestamp(timestamp)
+                .exit_code(exit_code)
+                .insert(&storage);
+        }
+
+        let mut refused = TriageHint {
+            rule: "connection_refused".to_string(),
+            hint: "Check the port".to_string(),
+            evidence: "Connection refused".to_string(),
+            explanation: None,
+        };
+        storage.store_triage_hint(1, &refused).unwrap();
+        let root = TriageHint {
+            rule: "permission_denied".to_string(),
+            hint: "Retry with sudo".to_string(),
+            evidence: "requires root privileges".to_string(),
+            explanation: None,
+        };
+        storage.store_triage_hint(2, &root).unwrap();
+
+        refused.explanation = Some("Nothing listens on 8080".to_string());
+        storage.store_triage_hint(1, &refused).unwrap();
+
+        let hints = storage.triage_hints(&[1, 2, 3]).unwrap();
+        assert_eq!(hints.len(), 2);
+        assert_eq!(hints[&1], refused);
+
+        let failures = storage.recent_failures("s1", 10).unwrap();
+        assert_eq!(
+            failures.iter().map(|f| f.capture_id).collect::<Vec<_>>(),
+            [2, 1]
+        );
+        assert_eq!(failures[0].exit_code, Some(1));
+        assert_eq!(failures[0].triage, root);
+        assert!(storage.recent_failures("s2", 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Capture size limits
+//!
+//! Output larger than `storage.max_blob_size` is cut down to a head and a
+//! tail segment around a marker before it is stored. The middle of a
+//! `strings` run over a disk image is rarely worth keeping, while the start
+//! and end usually show what was run and how it finished.
+
+/// How much output a single capture may store
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputLimit {
+    pub max_bytes: usize,
+    /// Share of `max_bytes` given to the head; the tail gets the rest
+    pub head_ratio: f32,
+}
+
+/// Output that went over the limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruncatedOutput<'a> {
+    pub head: &'a str,
+    pub tail: &'a str,
+    /// Size in bytes before truncation
+    pub original_size: usize,
+}
+
+impl TruncatedOutput<'_> {
+    /// Bytes dropped from the middle
+    pub fn omitted(&self) -> usize {
+        self.original_size - self.head.len() - self.tail.len()
+    }
+
+    /// Head and tail joined by the truncation marker, as stored in the blob
+    pub fn stored(&self) -> String {
+        format!(
+            "{}\n[... yinx: {} bytes truncated ...]\n{}",
+            self.head.trim_end_matches('\n'),
+            self.omitted(),
+            self.tail
+        )
+    }
+
+    /// Head and tail without the marker, for extraction and indexing
+    pub fn indexed(&self) -> String {
+        format!("{}\n{}", self.head.trim_end_matches('\n'), self.tail)
+    }
+}
+
+impl OutputLimit {
+    /// Cut `output` down to the limit; None when it already fits
+    ///
+    /// Segments end on line boundaries where a line break is close enough,
+    /// so the stored output doesn't start or stop mid-line.
+    pub fn apply<'a>(&self, output: &'a str) -> Option<TruncatedOutput<'a>> {
+        if output.len() <= self.max_bytes {
+            return None;
+        }
+        let head_budget = (self.max_bytes as f64 * self.head_ratio.clamp(0.0, 1.0) as f64) as usize;
+        let tail_budget = self.max_bytes - head_budget;
+
+        let mut head_end = floor_char_boundary(output, head_budget);
+        if let Some(newline) = output[..head_end].rfind('\n') {
+            head_end = newline + 1;
+        }
+        let mut tail_start = ceil_char_boundary(output, output.len() - tail_budget);
+        if let Some(newline) = output[tail_start..].find('\n') {
+            if tail_start + newline + 1 < output.len() {
+                tail_start += newline + 1;
+            }
+        }
+
+        Some(TruncatedOutput {
+            head: &output[..head_end],
+            tail: &output[tail_start..],
+            original_size: output.len(),
+        })
+    }
+}
+
+/// Recover the indexed text from a stored blob by dropping the marker line
+///
+/// Only meaningful for captures that were truncated; the first marker is the
+/// one yinx wrote, anything after it came from the tail of the output.
+pub fn strip_marker(stored: &str) -> String {
+    let Some(start) = stored.find("\n[... yinx: ") else {
+        return stored.to_string();
+    };
+    let rest = &stored[start + 1..];
+    match rest.find(" bytes truncated ...]\n") {
+        Some(end) => {
+            let after = start + 1 + end + " bytes truncated ...]\n".len();
+            format!("{}{}", &stored[..start + 1], &stored[after..])
+        }
+        None => stored.to_string(),
+    }
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_char_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation() {
+        let limit = OutputLimit {
+            max_bytes: 100,
+            head_ratio: 0.25,
+        };
+        assert!(limit.apply("short\n").is_none());
+
+        let output: String = (0..1000).map(|i| format!("line {:04}\n", i)).collect();
+        let cut = limit.apply(&output).unwrap();
+        assert!(cut.head.len() <= 25 && cut.head.ends_with('\n'));
+        assert!(cut.tail.len() <= 75 && cut.tail.starts_with("line "));
+        assert!(cut.tail.ends_with("line 0999\n"));
+        assert_eq!(cut.original_size, output.len());
+
+        let stored = cut.stored();
+        assert!(stored.starts_with("line 0000\n"));
+        assert!(stored.contains(&format!(
+            "[... yinx: {} bytes truncated ...]",
+            cut.omitted()
+        )));
+        assert!(!cut.indexed().contains("truncated"));
+        assert_eq!(strip_marker(&stored), cut.indexed());
+        assert_eq!(strip_marker("no marker\n"), "no marker\n");
+
+        // Never splits a multi-byte character
+        let wide = "é".repeat(200);
+        let cut = limit.apply(&wide).unwrap();
+        assert!(cut.head.len() <= 25 && cut.tail.len() <= 75);
+    }
+}
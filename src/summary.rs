@@ -0,0 +1,287 @@
+//! Per-capture digests
+//!
+//! Large captures get a one-paragraph abstract: what produced them, how
+//! many lines and hosts they cover, the entity types they mention most,
+//! and the few lines that carry the most signal. The abstract is
+//! extractive (built from the output's own lines), stored per capture and
+//! indexed in its own keyword index, keyed by capture id, so a query can
+//! land on a scan as a whole and show the digest instead of raw lines.
+
+use crate::config::SummariesConfig;
+use crate::error::{Result, YinxError};
+use crate::patterns::PatternRegistry;
+use crate::storage::{strip_marker, EntityRecord, StorageManager};
+use std::collections::{BTreeMap, HashMap};
+
+/// Method recorded for extractive summaries
+pub const EXTRACTIVE: &str = "extractive";
+
+/// Words that usually mark a line worth reading in tool output
+const SIGNAL_WORDS: &[&str] = &[
+    "open",
+    "vulnerable",
+    "found",
+    "success",
+    "valid",
+    "login",
+    "admin",
+    "password",
+    "allowed",
+    "exposed",
+    "writable",
+    "anonymous",
+    "status: 200",
+    "[+]",
+];
+
+/// Builds extractive digests of large captures; the default summarizes nothing
+#[derive(Debug, Clone, Default)]
+pub struct Summarizer {
+    enabled: bool,
+    min_lines: usize,
+    max_chars: usize,
+    batch_size: usize,
+}
+
+impl Summarizer {
+    pub fn new(config: &SummariesConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            min_lines: config.min_lines,
+            max_chars: config.max_chars,
+            batch_size: config.batch_size,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Digest of a capture's output, None when it's too small to need one
+    pub fn summarize(
+        &self,
+        label: &str,
+        output: &str,
+        entities: &[EntityRecord],
+    ) -> Option<String> {
+        let lines: Vec<&str> = output
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        if lines.len() < self.min_lines.max(1) {
+            return None;
+        }
+
+        let mut summary = format!("{} output of {} lines", label, lines.len());
+        let hosts: Vec<&str> = entities
+            .iter()
+            .filter(|e| matches!(e.entity_type.as_str(), "ip_address" | "hostname"))
+            .map(|e| e.value.as_str())
+            .collect();
+        if !hosts.is_empty() {
+            let shown = hosts.iter().take(3).copied().collect::<Vec<_>>().join(", ");
+            let more = if hosts.len() > 3 { ", …" } else { "" };
+            summary.push_str(&format!(
+                " covering {} host{} ({}{})",
+                hosts.len(),
+                if hosts.len() == 1 { "" } else { "s" },
+                shown,
+                more
+            ));
+        }
+        let mut by_type: BTreeMap<&str, u32> = BTreeMap::new();
+        for entity in entities {
+            *by_type.entry(&entity.entity_type).or_default() += entity.occurrences.max(1);
+        }
+        let mut by_type: Vec<(&str, u32)> = by_type.into_iter().collect();
+        by_type.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+        if !by_type.is_empty() {
+            let top: Vec<String> = by_type
+                .iter()
+                .take(3)
+                .map(|(t, n)| format!("{} ({})", t, n))
+                .collect();
+            summary.push_str(&format!(", mostly {}", top.join(", ")));
+        }
+        summary.push('.');
+
+        let notable = self.notable_lines(
+            &lines,
+            entities,
+            self.max_chars.saturating_sub(summary.len()),
+        );
+        if !notable.is_empty() {
+            summary.push_str(&format!(" Notable: {}.", notable.join("; ")));
+        }
+        Some(summary)
+    }
+
+    /// Highest-signal lines that fit in `room` characters, in output order
+    ///
+    /// Lines repeated throughout the output score low however many
+    /// entities they carry: they're the noise the filter already clusters.
+    /// A line needs the weight of about one entity to be picked at all.
+    fn notable_lines<'a>(
+        &self,
+        lines: &[&'a str],
+        entities: &[EntityRecord],
+        room: usize,
+    ) -> Vec<&'a str> {
+        let mut shape_counts: HashMap<String, usize> = HashMap::new();
+        for line in lines {
+            *shape_counts.entry(shape(line)).or_default() += 1;
+        }
+
+        let mut scored: Vec<(usize, f32)> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.len() <= 160)
+            .map(|(i, line)| {
+                let lower = line.to_lowercase();
+                let signal = SIGNAL_WORDS.iter().filter(|w| lower.contains(*w)).count();
+                let named = entities
+                    .iter()
+                    .filter(|e| line.contains(e.value.as_str()))
+                    .count();
+                let repeats = shape_counts[&shape(line)] as f32;
+                (i, (signal as f32 * 2.0 + named as f32) / repeats.sqrt())
+            })
+            .filter(|(_, score)| *score >= 1.0)
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut picked = Vec::new();
+        let mut used = 0;
+        for (i, _) in scored {
+            let cost = lines[i].len() + 2;
+            if used + cost > room {
+                continue;
+            }
+            if picked.iter().any(|&j: &usize| lines[j] == lines[i]) {
+                continue;
+            }
+            used += cost;
+            picked.push(i);
+        }
+        picked.sort_unstable();
+        picked.into_iter().map(|i| lines[i]).collect()
+    }
+}
+
+/// Line with digits collapsed, so `22/tcp open` and `80/tcp open` share a shape
+fn shape(line: &str) -> String {
+    line.chars()
+        .map(|c| if c.is_ascii_digit() { '0' } else { c })
+        .collect()
+}
+
+/// Summarize the next batch of large captures that have no digest yet
+///
+/// Secrets are redacted from the digest before it is stored or indexed.
+/// Returns the number of captures summarized.
+pub fn summarize_pending(
+    storage: &StorageManager,
+    summarizer: &Summarizer,
+    patterns: &PatternRegistry,
+) -> Result<usize> {
+    if !summarizer.is_enabled() {
+        return Ok(0);
+    }
+    let pending = storage.captures_to_summarize(summarizer.min_lines, summarizer.batch_size)?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let mut digests = Vec::new();
+    for capture_id in pending {
+        let Some(capture) = storage.database.get_capture(capture_id)? else {
+            continue;
+        };
+        let summary = if storage.blob_store.exists(&capture.output_hash) {
+            let stored = String::from_utf8_lossy(&storage.blob_store.read(&capture.output_hash)?)
+                .into_owned();
+            let output = match capture.original_size {
+                Some(_) => strip_marker(&stored),
+                None => stored,
+            };
+            let entities = storage.database.get_entities_for_capture(capture_id)?;
+            let label = capture
+                .tool
+                .as_deref()
+                .or_else(|| capture.command.as_deref()?.split_whitespace().next())
+                .unwrap_or("Command");
+            summarizer
+                .summarize(label, &output, &entities)
+                .map(|s| patterns.redact_sensitive(&s).unwrap_or(s))
+        } else {
+            None
+        };
+        // An empty digest marks the capture done so it isn't retried
+        digests.push((capture_id, summary.unwrap_or_default()));
+    }
+
+    let indexed: Vec<(u64, String)> = digests
+        .iter()
+        .filter(|(_, s)| !s.is_empty())
+        .map(|(id, s)| (*id as u64, s.clone()))
+        .collect();
+    if !indexed.is_empty() {
+        let mut index = storage.open_summary_index()?;
+        index
+            .insert_batch(&indexed)
+            .and_then(|_| index.commit())
+            .map_err(|e| YinxError::Other(e.into()))?;
+    }
+    for (capture_id, summary) in &digests {
+        storage.store_summary(*capture_id, summary, EXTRACTIVE)?;
+    }
+
+    Ok(indexed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(entity_type: &str, value: &str) -> EntityRecord {
+        EntityRecord {
+            id: 0,
+            capture_id: 1,
+            entity_type: entity_type.to_string(),
+            value: value.to_string(),
+            context: None,
+            confidence: 1.0,
+            occurrences: 1,
+            start: None,
+            end: None,
+            line: None,
+            host: None,
+        }
+    }
+
+    #[test]
+    fn test_extractive_summary() {
+        let summarizer = Summarizer::new(&SummariesConfig {
+            enabled: true,
+            min_lines: 10,
+            max_chars: 400,
+            ..SummariesConfig::default()
+        });
+        let mut output = String::from("Nmap scan report for 10.0.0.7\n");
+        for port in 1000..1040 {
+            output.push_str(&format!("{}/tcp filtered unknown\n", port));
+        }
+        output.push_str("22/tcp open ssh OpenSSH 8.2\n445/tcp open microsoft-ds\n");
+        let entities = vec![entity("ip_address", "10.0.0.7"), entity("port", "22")];
+
+        let summary = summarizer.summarize("nmap", &output, &entities).unwrap();
+        assert!(summary.starts_with("nmap output of 43 lines covering 1 host (10.0.0.7)"));
+        assert!(summary.contains("22/tcp open ssh OpenSSH 8.2"));
+        assert!(summary.contains("445/tcp open microsoft-ds"));
+        assert!(!summary.contains("filtered"));
+        assert!(summary.len() <= 400);
+
+        assert!(summarizer.summarize("nmap", "one line\n", &[]).is_none());
+    }
+}
@@ -0,0 +1,567 @@
+//! Export archives
+//!
+//! `yinx export` writes captures, with their entities, chunks and output
+//! blobs, to one file that `yinx import archive` loads into another store.
+//! The file is a zstd stream of sync frames: a JSON header, then the same
+//! bundle a peer would send over `yinx sync`, blob contents included.
+//!
+//! Exports leave the machine with client data in them, so they can be
+//! encrypted with age(1), to recipients' public keys or under a passphrase
+//! age prompts for. Import spots an age file by its header and decrypts it
+//! through age the same way.
+//!
+//! Importing into a store that already has some of the captures resolves
+//! each overlap with a [`ConflictStrategy`]; a dry run reports the overlap
+//! without touching the store.
+
+use super::protocol::{self, recv, SyncMessage};
+use super::store::{ConflictStrategy, ImportDiff, SyncCapture};
+use crate::error::{Result, YinxError};
+use crate::session::Session;
+use crate::storage::StorageManager;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+/// Written first in every archive
+const FORMAT: &str = "yinx-archive";
+
+/// Archive layout version, bumped on incompatible changes
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// How age files start, binary and ASCII-armored
+const AGE_MAGIC: [&[u8]; 2] = [
+    b"age-encryption.org/",
+    b"-----BEGIN AGE ENCRYPTED FILE-----",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveHeader {
+    format: String,
+    version: u32,
+    created_at: i64,
+}
+
+/// How an archive is encrypted on its way to disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Encryption {
+    None,
+    /// age prompts for a passphrase on the terminal
+    Passphrase,
+    /// age public keys (`age1...`) or SSH public keys
+    Recipients(Vec<String>),
+}
+
+/// What an export wrote
+#[derive(Debug, Default)]
+pub struct ExportStats {
+    pub captures: usize,
+    pub blobs: usize,
+    /// Size of the archive file
+    pub bytes: u64,
+}
+
+/// How to load an archive
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions<'a> {
+    /// age identity file for encrypted archives
+    pub identity: Option<&'a Path>,
+    pub strategy: ConflictStrategy,
+    /// Work out the diff without importing anything
+    pub dry_run: bool,
+}
+
+/// What an import read and kept
+#[derive(Debug, Default)]
+pub struct ImportStats {
+    pub sessions: usize,
+    /// Captures in the archive
+    pub captures: usize,
+    /// Captures that weren't in the store yet
+    pub imported: usize,
+    /// Local captures overwritten or merged into
+    pub updated: usize,
+    pub blobs: usize,
+    pub encrypted: bool,
+    /// How the archive overlapped with the store before the import
+    pub diff: ImportDiff,
+}
+
+/// Write the captures `ids` to an archive at `path`
+pub fn export_archive(
+    storage: &StorageManager,
+    ids: &[i64],
+    path: &Path,
+    encryption: &Encryption,
+) -> Result<ExportStats> {
+    let io_err = |e| YinxError::Io {
+        source: e,
+        context: format!("Failed to write {}", path.display()),
+    };
+
+    let (writer, child): (Box<dyn Write>, Option<Child>) = match encryption {
+        Encryption::None => (Box::new(create_private(path).map_err(io_err)?), None),
+        _ => {
+            let mut child = age_command(encryption)
+                .arg("-o")
+                .arg(path)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(age_missing)?;
+            let stdin = child.stdin.take().expect("stdin is piped");
+            (Box::new(stdin), Some(child))
+        }
+    };
+
+    let blobs = match write_archive(writer, storage, ids, path) {
+        Ok(blobs) => blobs,
+        Err(e) => {
+            // Stopped before it can finish encrypting a partial archive
+            if let Some(mut child) = child {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            return Err(e);
+        }
+    };
+
+    if let Some(mut child) = child {
+        let status = child.wait().map_err(age_missing)?;
+        if !status.success() {
+            return Err(YinxError::Vault(format!(
+                "age failed to encrypt {}: {}",
+                path.display(),
+                status
+            )));
+        }
+    }
+
+    Ok(ExportStats {
+        captures: ids.len(),
+        blobs,
+        bytes: std::fs::metadata(path).map_err(io_err)?.len(),
+    })
+}
+
+/// Create (or truncate) a file only the owner can read
+fn create_private(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // On Windows the profile directory's ACL already keeps others out
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // An existing file keeps its mode when truncated
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+/// Write the archive stream for `ids` to `writer`, returning the number of
+/// blobs written
+fn write_archive(
+    writer: Box<dyn Write>,
+    storage: &StorageManager,
+    ids: &[i64],
+    path: &Path,
+) -> Result<usize> {
+    let io_err = |e| YinxError::Io {
+        source: e,
+        context: format!("Failed to write {}", path.display()),
+    };
+    let mut encoder = zstd::Encoder::new(writer, 0).map_err(io_err)?;
+    let header = ArchiveHeader {
+        format: FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    let header = serde_json::to_vec(&header).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize archive header".to_string(),
+    })?;
+    protocol::write_frame(&mut encoder, &header)?;
+    let blobs = super::send_bundle(storage, &mut encoder, ids, &HashSet::new())?;
+    // Closing the pipe lets age finish the file
+    drop(encoder.finish().map_err(io_err)?);
+    Ok(blobs)
+}
+
+fn age_command(encryption: &Encryption) -> Command {
+    let mut command = Command::new("age");
+    command.arg("--encrypt");
+    match encryption {
+        Encryption::Recipients(recipients) => {
+            for recipient in recipients {
+                command.arg("-r").arg(recipient);
+            }
+        }
+        _ => {
+            command.arg("--passphrase");
+        }
+    }
+    command
+}
+
+fn age_missing(e: std::io::Error) -> YinxError {
+    YinxError::Vault(format!(
+        "Failed to run age ({}); install it to encrypt or decrypt archives",
+        e
+    ))
+}
+
+/// Whether the file at `path` is age-encrypted
+pub fn is_encrypted(path: &Path) -> Result<bool> {
+    let mut file = std::fs::File::open(path).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to open {}", path.display()),
+    })?;
+    let mut head = [0u8; 34];
+    let mut read = 0;
+    while read < head.len() {
+        match file.read(&mut head[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) => {
+                return Err(YinxError::Io {
+                    source: e,
+                    context: format!("Failed to read {}", path.display()),
+                })
+            }
+        }
+    }
+    Ok(AGE_MAGIC
+        .iter()
+        .any(|magic| head[..read].starts_with(magic)))
+}
+
+/// Load an archive into the store
+///
+/// An encrypted archive is decrypted with the age identity file in
+/// `options`, or with a passphrase age prompts for when there is none.
+/// Captures the store already has are resolved with `options.strategy`.
+pub fn import_archive(
+    storage: &StorageManager,
+    path: &Path,
+    options: &ImportOptions,
+) -> Result<ImportStats> {
+    let encrypted = is_encrypted(path)?;
+    let mut child = None;
+    let reader: Box<dyn Read> = if encrypted {
+        let mut command = Command::new("age");
+        command.arg("--decrypt");
+        if let Some(identity) = options.identity {
+            command.arg("-i").arg(identity);
+        }
+        let mut spawned = command
+            .arg(path)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(age_missing)?;
+        let stdout = spawned.stdout.take().expect("stdout is piped");
+        child = Some(spawned);
+        Box::new(stdout)
+    } else {
+        Box::new(std::fs::File::open(path).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to open {}", path.display()),
+        })?)
+    };
+
+    let result = read_archive(reader, path);
+    if let Some(mut child) = child {
+        let status = child.wait().map_err(age_missing)?;
+        if !status.success() {
+            return Err(YinxError::Vault(format!(
+                "age failed to decrypt {}: {}",
+                path.display(),
+                status
+            )));
+        }
+    }
+    let (sessions, captures, blobs) = result?;
+
+    let hashes: Vec<String> = blobs.iter().map(|(hash, _)| hash.clone()).collect();
+    let diff = storage.diff_import(&sessions, &captures, &hashes)?;
+    let counts = if options.dry_run {
+        Default::default()
+    } else {
+        storage.import_captures_with(&sessions, &captures, &blobs, options.strategy)?
+    };
+    Ok(ImportStats {
+        sessions: sessions.len(),
+        captures: captures.len(),
+        imported: counts.imported,
+        updated: counts.updated,
+        blobs: blobs.len(),
+        encrypted,
+        diff,
+    })
+}
+
+type Bundle = (Vec<Session>, Vec<SyncCapture>, Vec<(String, Vec<u8>)>);
+
+fn read_archive<R: Read>(reader: R, path: &Path) -> Result<Bundle> {
+    let not_archive =
+        || YinxError::Config(format!("{} is not a yinx export archive", path.display()));
+    let mut decoder = zstd::Decoder::new(reader).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to read {}", path.display()),
+    })?;
+
+    let header: ArchiveHeader = protocol::read_frame(&mut decoder)
+        .ok()
+        .and_then(|frame| serde_json::from_slice(&frame).ok())
+        .ok_or_else(not_archive)?;
+    if header.format != FORMAT {
+        return Err(not_archive());
+    }
+    if header.version != ARCHIVE_VERSION {
+        return Err(YinxError::Sync(format!(
+            "Archive version {} is not supported (expected {})",
+            header.version, ARCHIVE_VERSION
+        )));
+    }
+
+    let (sessions, captures, hashes) = match recv(&mut decoder)? {
+        SyncMessage::Bundle {
+            sessions,
+            captures,
+            blobs,
+        } => (sessions, captures, blobs),
+        other => return Err(super::unexpected("bundle", &other)),
+    };
+    let mut blobs = Vec::with_capacity(hashes.len());
+    for hash in hashes {
+        blobs.push((hash, protocol::read_frame(&mut decoder)?));
+    }
+    Ok((sessions, captures, blobs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Yinx;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_archive_roundtrip() {
+        let source_dir = TempDir::new().unwrap();
+        let source = Yinx::open(source_dir.path()).unwrap();
+        let session = source.start_session("client-a").unwrap();
+        for (i, output) in ["22/tcp open ssh\n", "80/tcp open http\n"]
+            .iter()
+            .enumerate()
+        {
+            let event = crate::daemon::CaptureEvent {
+                session_id: session.id.to_string(),
+                timestamp: 1_700_000_000 + i as i64,
+                command: "nmap -sV 10.0.0.5".to_string(),
+                output: output.to_string(),
+                exit_code: 0,
+                cwd: "/tmp".to_string(),
+                username: None,
+                source: None,
+                tool: None,
+                context: None,
+            };
+            source.ingest(event).await.unwrap();
+        }
+        let ids: Vec<i64> = source
+            .storage()
+            .database
+            .get_session_captures(&session.id.to_string())
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        let database = &source.storage().database;
+        database.add_capture_tag(ids[0], "initial-scan").unwrap();
+        database
+            .insert_finding(
+                &session.id.to_string(),
+                Some(ids[0]),
+                "SSH exposed",
+                "low",
+                None,
+            )
+            .unwrap();
+        let context = crate::daemon::CaptureContext {
+            hostname: Some("kali".to_string()),
+            terminal: Some("pts/3".to_string()),
+            env: [(
+                "HTTP_PROXY".to_string(),
+                "http://127.0.0.1:8080".to_string(),
+            )]
+            .into(),
+            ..Default::default()
+        };
+        database.insert_capture_context(ids[0], &context).unwrap();
+        database
+            .get_conn()
+            .unwrap()
+            .execute(
+                "UPDATE captures SET target = '10.0.0.5', ports = '22' WHERE id = ?1",
+                [ids[0]],
+            )
+            .unwrap();
+
+        let archive = source_dir.path().join("client-a.yinx");
+        let exported = export_archive(source.storage(), &ids, &archive, &Encryption::None).unwrap();
+        assert_eq!((exported.captures, exported.blobs), (2, 2));
+        assert!(!is_encrypted(&archive).unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&archive).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let dest_dir = TempDir::new().unwrap();
+        let dest = StorageManager::new(dest_dir.path().to_path_buf()).unwrap();
+        let imported = import_archive(&dest, &archive, &ImportOptions::default()).unwrap();
+        assert_eq!((imported.sessions, imported.imported), (1, 2));
+        let copies = dest
+            .database
+            .get_session_captures(&session.id.to_string())
+            .unwrap();
+        assert_eq!(copies.len(), 2);
+
+        // Everything derived from the capture came along
+        let copy = copies
+            .iter()
+            .find(|c| c.timestamp == 1_700_000_000)
+            .unwrap();
+        assert_eq!(copy.arguments.target.as_deref(), Some("10.0.0.5"));
+        assert_eq!(copy.arguments.ports.as_deref(), Some("22"));
+        assert_eq!(
+            dest.database.get_capture_tags(copy.id).unwrap(),
+            vec!["initial-scan".to_string()]
+        );
+        assert_eq!(
+            dest.database.get_capture_context(copy.id).unwrap(),
+            Some(context)
+        );
+        let findings = dest.database.list_findings(None).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            (findings[0].title.as_str(), findings[0].capture_id),
+            ("SSH exposed", Some(copy.id))
+        );
+
+        // Importing again adds nothing
+        let again = import_archive(&dest, &archive, &ImportOptions::default()).unwrap();
+        assert_eq!((again.captures, again.imported), (2, 0));
+        let merged = ImportOptions {
+            strategy: ConflictStrategy::Merge,
+            ..ImportOptions::default()
+        };
+        import_archive(&dest, &archive, &merged).unwrap();
+        assert_eq!(dest.database.list_findings(None).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_strategies() {
+        let source_dir = TempDir::new().unwrap();
+        let source = Yinx::open(source_dir.path()).unwrap();
+        let session = source.start_session("client-b").unwrap();
+        let event = crate::daemon::CaptureEvent {
+            session_id: session.id.to_string(),
+            timestamp: 1_700_000_000,
+            command: "nmap -sV 10.0.0.7".to_string(),
+            output: "Nmap scan report for 10.0.0.7\n22/tcp open ssh\n".to_string(),
+            exit_code: 0,
+            cwd: "/tmp".to_string(),
+            username: None,
+            source: None,
+            tool: None,
+            context: None,
+        };
+        let id = source.ingest(event).await.unwrap().unwrap();
+        let archive = source_dir.path().join("client-b.yinx");
+        export_archive(source.storage(), &[id], &archive, &Encryption::None).unwrap();
+
+        let dest_dir = TempDir::new().unwrap();
+        let dest = StorageManager::new(dest_dir.path().to_path_buf()).unwrap();
+        let import = |strategy, dry_run| {
+            let options = ImportOptions {
+                identity: None,
+                strategy,
+                dry_run,
+            };
+            import_archive(&dest, &archive, &options).unwrap()
+        };
+        import(ConflictStrategy::Skip, false);
+        let local = dest
+            .database
+            .get_session_captures(&session.id.to_string())
+            .unwrap()[0]
+            .id;
+        let entities = dest.database.get_entities_for_capture(local).unwrap().len();
+        assert!(entities > 0);
+
+        // Lose an entity and the tool locally, then see what each strategy does
+        let conn = dest.database.get_conn().unwrap();
+        conn.execute(
+            "DELETE FROM entities WHERE id = (SELECT MIN(id) FROM entities WHERE capture_id = ?1)",
+            [local],
+        )
+        .unwrap();
+        conn.execute("UPDATE captures SET tool = NULL WHERE id = ?1", [local])
+            .unwrap();
+        drop(conn);
+
+        let dry = import(ConflictStrategy::Merge, true);
+        assert_eq!(dry.diff.existing_sessions, 1);
+        assert_eq!((dry.diff.new_captures, dry.diff.existing_captures), (0, 1));
+        assert_eq!(dry.diff.new_entities, 1);
+        assert_eq!((dry.imported, dry.updated), (0, 0));
+        let count = || dest.database.get_entities_for_capture(local).unwrap().len();
+        assert_eq!(count(), entities - 1);
+
+        let merged = import(ConflictStrategy::Merge, false);
+        assert_eq!((merged.imported, merged.updated), (0, 1));
+        assert_eq!(count(), entities);
+        let tool = || dest.database.get_capture(local).unwrap().unwrap().tool;
+        assert_eq!(tool(), None);
+
+        let overwritten = import(ConflictStrategy::Overwrite, false);
+        assert_eq!((overwritten.imported, overwritten.updated), (0, 1));
+        assert_eq!(tool().as_deref(), Some("nmap"));
+        assert_eq!(count(), entities);
+
+        let duplicated = import(ConflictStrategy::Duplicate, false);
+        assert_eq!(duplicated.imported, 1);
+        let names: Vec<String> = crate::session::SessionManager::new(dest_dir.path().to_path_buf())
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert!(names.contains(&"client-b-imported".to_string()));
+    }
+
+    #[test]
+    fn test_detects_age_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("a.age");
+        std::fs::write(&binary, b"age-encryption.org/v1\n-> X25519 abc\n").unwrap();
+        let armored = temp_dir.path().join("b.age");
+        std::fs::write(&armored, b"-----BEGIN AGE ENCRYPTED FILE-----\nYWdl\n").unwrap();
+        let plain = temp_dir.path().join("c.yinx");
+        std::fs::write(&plain, b"\x28\xb5\x2f\xfd").unwrap();
+
+        assert!(is_encrypted(&binary).unwrap());
+        assert!(is_encrypted(&armored).unwrap());
+        assert!(!is_encrypted(&plain).unwrap());
+
+        let store_dir = TempDir::new().unwrap();
+        let store = StorageManager::new(store_dir.path().to_path_buf()).unwrap();
+        assert!(import_archive(&store, &plain, &ImportOptions::default()).is_err());
+    }
+}
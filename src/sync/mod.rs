@@ -0,0 +1,343 @@
+//! Store-to-store sync
+//!
+//! `yinx sync --peer <host>` runs `yinx _internal sync-serve` on the peer
+//! over SSH and speaks a small framed protocol on its stdin/stdout:
+//!
+//! 1. The initiator sends its capture keys and blob hashes.
+//! 2. The peer sends a bundle of the captures the initiator lacks (skipping
+//!    blobs it already has), then lists the captures it wants in return.
+//! 3. The initiator sends those, and the peer reports how many it imported.
+//!
+//! Blobs are content-addressed, so they are never sent twice.
+
+pub mod archive;
+pub mod protocol;
+pub mod store;
+
+pub use store::{
+    capture_key, ConflictStrategy, ImportCounts, ImportDiff, SyncCapture, SyncChunk, SyncEntity,
+    SyncFinding,
+};
+
+use crate::error::{Result, YinxError};
+use crate::storage::StorageManager;
+use protocol::{recv, send, SyncMessage, PROTOCOL_VERSION};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+/// What a sync exchanged
+#[derive(Debug, Default)]
+pub struct SyncStats {
+    /// Captures sent to the peer and imported there
+    pub sent: usize,
+    /// Captures received from the peer and imported here
+    pub received: usize,
+    /// Blob contents sent
+    pub blobs_sent: usize,
+    /// Blob contents received
+    pub blobs_received: usize,
+}
+
+/// Sync with a peer reached over SSH
+///
+/// `remote_bin` is the yinx binary to run on the peer.
+pub fn sync_with_peer(storage: &StorageManager, peer: &str, remote_bin: &str) -> Result<SyncStats> {
+    let mut child = Command::new("ssh")
+        .arg("-o")
+        .arg("BatchMode=yes")
+        .arg(peer)
+        .arg(remote_bin)
+        .arg("_internal")
+        .arg("sync-serve")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to run ssh".to_string(),
+        })?;
+
+    let mut writer = child.stdin.take().expect("stdin is piped");
+    let mut reader = std::io::BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+    let result = sync(storage, &mut reader, &mut writer);
+    drop(writer);
+
+    let status = child.wait().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to wait for ssh".to_string(),
+    })?;
+    match result {
+        Err(e) if !status.success() => Err(YinxError::Sync(format!(
+            "ssh to {} exited with {}: {}",
+            peer, status, e
+        ))),
+        result => result,
+    }
+}
+
+/// Run the initiating side of a sync
+pub fn sync<R: Read, W: Write>(
+    storage: &StorageManager,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<SyncStats> {
+    let mut stats = SyncStats::default();
+    let keys = storage.capture_keys()?;
+
+    send(
+        writer,
+        &SyncMessage::Hello {
+            version: PROTOCOL_VERSION,
+            captures: keys.keys().cloned().collect(),
+            blobs: storage.blob_hashes()?,
+        },
+    )?;
+
+    // What the peer has that we don't
+    let (received, blobs_received) = receive_bundle(storage, reader)?;
+    stats.received = received;
+    stats.blobs_received = blobs_received;
+
+    // What we have that the peer doesn't
+    let (wanted, peer_blobs) = match recv(reader)? {
+        SyncMessage::Want { captures, blobs } => (captures, blobs),
+        other => return Err(unexpected("want", &other)),
+    };
+    let ids: Vec<i64> = wanted.iter().filter_map(|k| keys.get(k).copied()).collect();
+    stats.blobs_sent = send_bundle(storage, writer, &ids, &peer_blobs.into_iter().collect())?;
+
+    stats.sent = match recv(reader)? {
+        SyncMessage::Done { imported } => imported,
+        other => return Err(unexpected("done", &other)),
+    };
+
+    Ok(stats)
+}
+
+/// Run the responding side of a sync (`yinx _internal sync-serve`)
+///
+/// Returns the number of captures imported. Failures are reported to the
+/// initiator before being returned.
+pub fn serve<R: Read, W: Write>(
+    storage: &StorageManager,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<usize> {
+    let result = serve_inner(storage, reader, writer);
+    if let Err(e) = &result {
+        let _ = send(
+            writer,
+            &SyncMessage::Error {
+                message: e.to_string(),
+            },
+        );
+    }
+    result
+}
+
+fn serve_inner<R: Read, W: Write>(
+    storage: &StorageManager,
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<usize> {
+    let (peer_captures, peer_blobs) = match recv(reader)? {
+        SyncMessage::Hello {
+            version,
+            captures,
+            blobs,
+        } => {
+            if version != PROTOCOL_VERSION {
+                return Err(YinxError::Sync(format!(
+                    "Protocol version mismatch (peer {}, local {})",
+                    version, PROTOCOL_VERSION
+                )));
+            }
+            (captures.into_iter().collect::<HashSet<_>>(), blobs)
+        }
+        other => return Err(unexpected("hello", &other)),
+    };
+
+    let keys = storage.capture_keys()?;
+    let missing_there: Vec<i64> = keys
+        .iter()
+        .filter(|(key, _)| !peer_captures.contains(*key))
+        .map(|(_, id)| *id)
+        .collect();
+    send_bundle(
+        storage,
+        writer,
+        &missing_there,
+        &peer_blobs.into_iter().collect(),
+    )?;
+
+    let missing_here: Vec<String> = peer_captures
+        .into_iter()
+        .filter(|key| !keys.contains_key(key))
+        .collect();
+    send(
+        writer,
+        &SyncMessage::Want {
+            captures: missing_here,
+            blobs: storage.blob_hashes()?,
+        },
+    )?;
+
+    let (imported, _) = receive_bundle(storage, reader)?;
+    send(writer, &SyncMessage::Done { imported })?;
+
+    Ok(imported)
+}
+
+/// Send captures and any blob contents the peer lacks; returns blobs sent
+fn send_bundle<W: Write>(
+    storage: &StorageManager,
+    writer: &mut W,
+    ids: &[i64],
+    peer_blobs: &HashSet<String>,
+) -> Result<usize> {
+    let captures = storage.export_captures(ids)?;
+    let blobs: Vec<String> = captures
+        .iter()
+        .map(|c| c.output_hash.clone())
+        .filter(|h| !peer_blobs.contains(h))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    send(
+        writer,
+        &SyncMessage::Bundle {
+            sessions: storage.export_sessions(&captures),
+            captures,
+            blobs: blobs.clone(),
+        },
+    )?;
+    for hash in &blobs {
+        protocol::write_frame(writer, &storage.blob_store.read(hash)?)?;
+    }
+    writer.flush().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to flush sync stream".to_string(),
+    })?;
+
+    Ok(blobs.len())
+}
+
+/// Receive and import a bundle; returns (captures imported, blobs received)
+fn receive_bundle<R: Read>(storage: &StorageManager, reader: &mut R) -> Result<(usize, usize)> {
+    let (sessions, captures, hashes) = match recv(reader)? {
+        SyncMessage::Bundle {
+            sessions,
+            captures,
+            blobs,
+        } => (sessions, captures, blobs),
+        other => return Err(unexpected("bundle", &other)),
+    };
+
+    let mut blobs = Vec::with_capacity(hashes.len());
+    for hash in hashes {
+        blobs.push((hash, protocol::read_frame(reader)?));
+    }
+
+    let imported = storage.import_captures(&sessions, &captures, &blobs)?;
+    Ok((imported, blobs.len()))
+}
+
+fn unexpected(expected: &str, got: &SyncMessage) -> YinxError {
+    let got = serde_json::to_value(got)
+        .ok()
+        .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(str::to_string))
+        .unwrap_or_default();
+    YinxError::Sync(format!("Expected '{}' message, got '{}'", expected, got))
+}
+
+// UnixStream::pair stands in for the ssh connection
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::session::SessionManager;
+    use crate::storage::fixtures::capture;
+    use std::os::unix::net::UnixStream;
+    use tempfile::TempDir;
+
+    fn add_capture(storage: &StorageManager, session_id: &str, ts: i64, output: &str) {
+        let capture_id = capture(session_id, "nmap 10.0.0.1", output.as_bytes())
+            .timestamp(ts)
+            .chunks(&[output])
+            .insert(storage)
+            .id;
+        storage
+            .database
+            .insert_entities(
+                capture_id,
+                &[(
+                    "ip_address".to_string(),
+                    "10.0.0.1".to_string(),
+                    String::new(),
+                    0.9,
+                )],
+            )
+            .unwrap();
+    }
+
+    fn run_sync(laptop: &StorageManager, vm: &StorageManager) -> SyncStats {
+        let (mut local, mut remote) = UnixStream::pair().unwrap();
+        std::thread::scope(|scope| {
+            let server = scope.spawn(|| {
+                let mut reader = remote.try_clone().unwrap();
+                serve(vm, &mut reader, &mut remote).unwrap()
+            });
+            let mut reader = local.try_clone().unwrap();
+            let stats = sync(laptop, &mut reader, &mut local).unwrap();
+            assert_eq!(server.join().unwrap(), stats.sent);
+            stats
+        })
+    }
+
+    #[test]
+    fn test_two_way_sync() {
+        let laptop_dir = TempDir::new().unwrap();
+        let vm_dir = TempDir::new().unwrap();
+        let laptop = StorageManager::new(laptop_dir.path().to_path_buf()).unwrap();
+        let vm = StorageManager::new(vm_dir.path().to_path_buf()).unwrap();
+
+        let session = SessionManager::new(vm_dir.path().to_path_buf())
+            .create_session(Some("vm-work".to_string()))
+            .unwrap()
+            .id
+            .to_string();
+        add_capture(&vm, &session, 1, "22/tcp open ssh");
+        add_capture(&vm, &session, 2, "80/tcp open http");
+        add_capture(&laptop, "laptop-session", 3, "22/tcp open ssh");
+
+        let stats = run_sync(&laptop, &vm);
+        assert_eq!(stats.received, 2);
+        assert_eq!(stats.sent, 1);
+        // The laptop already had the "22/tcp" blob, so only one blob each way
+        assert_eq!(stats.blobs_received, 1);
+        assert_eq!(stats.blobs_sent, 0);
+
+        assert_eq!(laptop.capture_keys().unwrap().len(), 3);
+        assert_eq!(vm.capture_keys().unwrap().len(), 3);
+        assert_eq!(
+            laptop
+                .database
+                .get_entities_by_type("ip_address")
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // The synced session is visible, and not left active on the laptop
+        let copied = SessionManager::new(laptop_dir.path().to_path_buf())
+            .resolve("vm-work")
+            .unwrap();
+        assert_eq!(copied.status, crate::session::SessionStatus::Stopped);
+
+        // Nothing left to exchange
+        let stats = run_sync(&laptop, &vm);
+        assert_eq!((stats.sent, stats.received), (0, 0));
+    }
+}
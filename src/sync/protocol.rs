@@ -0,0 +1,149 @@
+//! Wire format for store-to-store sync
+//!
+//! Frames are a 4-byte big-endian length followed by the payload. Control
+//! messages are JSON; blob contents follow a `Bundle` as raw frames in the
+//! order of its `blobs` list.
+
+use super::store::SyncCapture;
+use crate::error::{Result, YinxError};
+use crate::session::Session;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Largest frame accepted from a peer (256MB)
+const MAX_FRAME_SIZE: u32 = 256 * 1024 * 1024;
+
+/// Protocol version, bumped on incompatible changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Control messages exchanged by the two sides
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SyncMessage {
+    /// Initiator's inventory
+    Hello {
+        version: u32,
+        captures: Vec<String>,
+        blobs: Vec<String>,
+    },
+    /// Captures the receiver is missing; `blobs` raw frames follow
+    Bundle {
+        sessions: Vec<Session>,
+        captures: Vec<SyncCapture>,
+        blobs: Vec<String>,
+    },
+    /// Captures the responder is missing, plus the blobs it already holds
+    Want {
+        captures: Vec<String>,
+        blobs: Vec<String>,
+    },
+    /// Responder finished importing
+    Done { imported: usize },
+    /// The other side failed
+    Error { message: String },
+}
+
+/// Write one raw frame
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> Result<()> {
+    if payload.len() > MAX_FRAME_SIZE as usize {
+        return Err(YinxError::Sync(format!(
+            "Frame too large: {} bytes (max: {})",
+            payload.len(),
+            MAX_FRAME_SIZE
+        )));
+    }
+
+    writer
+        .write_all(&(payload.len() as u32).to_be_bytes())
+        .and_then(|_| writer.write_all(payload))
+        .map_err(|e| YinxError::Io {
+            source: e,
+            context: "Failed to write sync frame".to_string(),
+        })
+}
+
+/// Read one raw frame
+pub fn read_frame<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length).map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to read sync frame length".to_string(),
+    })?;
+
+    let length = u32::from_be_bytes(length);
+    if length > MAX_FRAME_SIZE {
+        return Err(YinxError::Sync(format!(
+            "Frame too large: {} bytes (max: {})",
+            length, MAX_FRAME_SIZE
+        )));
+    }
+
+    let mut payload = vec![0u8; length as usize];
+    reader.read_exact(&mut payload).map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to read sync frame payload".to_string(),
+    })?;
+
+    Ok(payload)
+}
+
+/// Write a control message
+pub fn send<W: Write>(writer: &mut W, message: &SyncMessage) -> Result<()> {
+    let payload = serde_json::to_vec(message).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize sync message".to_string(),
+    })?;
+    write_frame(writer, &payload)?;
+    writer.flush().map_err(|e| YinxError::Io {
+        source: e,
+        context: "Failed to flush sync stream".to_string(),
+    })
+}
+
+/// Read a control message, turning a peer error into a local one
+pub fn recv<R: Read>(reader: &mut R) -> Result<SyncMessage> {
+    let payload = read_frame(reader)?;
+    let message: SyncMessage = serde_json::from_slice(&payload).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to parse sync message".to_string(),
+    })?;
+
+    match message {
+        SyncMessage::Error { message } => Err(YinxError::Sync(format!("Peer error: {}", message))),
+        message => Ok(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"blob bytes").unwrap();
+        send(&mut buf, &SyncMessage::Done { imported: 3 }).unwrap();
+
+        let mut reader = buf.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), b"blob bytes");
+        assert!(matches!(
+            recv(&mut reader).unwrap(),
+            SyncMessage::Done { imported: 3 }
+        ));
+    }
+
+    #[test]
+    fn test_peer_error_surfaces() {
+        let mut buf = Vec::new();
+        send(
+            &mut buf,
+            &SyncMessage::Error {
+                message: "disk full".to_string(),
+            },
+        )
+        .unwrap();
+
+        let err = recv(&mut buf.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("disk full"));
+    }
+}
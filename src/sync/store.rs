@@ -0,0 +1,758 @@
+//! Exporting and importing captures between stores
+//!
+//! Captures have no global id, so each one is identified by a content key
+//! over its session, timestamp, command and output hash. Chunks, entities,
+//! tags, findings, parsed arguments and the environment travel with their
+//! capture, and chunks go straight into the keyword index; embeddings stay
+//! behind since they depend on the local model.
+
+use crate::daemon::CaptureContext;
+use crate::error::{Result, YinxError};
+use crate::patterns::ToolArguments;
+use crate::session::{Session, SessionStatus};
+use crate::storage::database::upsert_entity;
+use crate::storage::{metadata_host, StorageManager};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// A capture with everything derived from it that is worth sending
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncCapture {
+    pub key: String,
+    pub session_id: String,
+    pub timestamp: i64,
+    pub command: Option<String>,
+    pub output_hash: String,
+    pub tool: Option<String>,
+    pub exit_code: Option<i32>,
+    pub cwd: Option<String>,
+    pub username: Option<String>,
+    pub entities: Vec<SyncEntity>,
+    pub chunks: Vec<SyncChunk>,
+    /// Target, ports and other arguments parsed from the command line
+    #[serde(default, skip_serializing_if = "ToolArguments::is_empty")]
+    pub arguments: ToolArguments,
+    /// Environment the command ran in, when it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<CaptureContext>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Findings the capture triggered
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<SyncFinding>,
+}
+
+/// Entity extracted from a synced capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncEntity {
+    pub entity_type: String,
+    pub value: String,
+    pub context: Option<String>,
+    pub confidence: f32,
+    /// Peers from before entity coalescing send one entry per occurrence
+    #[serde(default = "one")]
+    pub occurrences: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Filtered chunk of a synced capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncChunk {
+    pub representative_text: String,
+    pub cluster_size: i64,
+    pub metadata: Option<String>,
+}
+
+/// Finding opened on a synced capture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncFinding {
+    pub title: String,
+    pub severity: String,
+    pub rule: Option<String>,
+    pub created_at: i64,
+}
+
+/// What an import does with captures the store already has
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Keep the local copy
+    #[default]
+    Skip,
+    /// Replace the local copy's details, entities and chunks
+    Overwrite,
+    /// Import sessions the store already has as new sessions beside them
+    Duplicate,
+    /// Keep the local copy and add the entities it lacks
+    Merge,
+}
+
+impl ConflictStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Overwrite => "overwrite",
+            Self::Duplicate => "duplicate",
+            Self::Merge => "merge",
+        }
+    }
+}
+
+impl std::str::FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "duplicate" | "duplicate-as-new" => Ok(Self::Duplicate),
+            "merge" | "merge-entities" => Ok(Self::Merge),
+            _ => Err(format!(
+                "unknown strategy '{}' (expected skip, overwrite, duplicate or merge)",
+                s
+            )),
+        }
+    }
+}
+
+/// How incoming captures overlap with the store
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportDiff {
+    pub new_sessions: usize,
+    pub existing_sessions: usize,
+    pub new_captures: usize,
+    /// Captures the store already has
+    pub existing_captures: usize,
+    /// Entities of existing captures that the local copies lack
+    pub new_entities: usize,
+    pub new_blobs: usize,
+    pub existing_blobs: usize,
+}
+
+/// Captures an import added and local captures it changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportCounts {
+    pub imported: usize,
+    /// Local captures overwritten or merged into
+    pub updated: usize,
+}
+
+/// Content key identifying a capture across stores
+pub fn capture_key(
+    session_id: &str,
+    timestamp: i64,
+    command: Option<&str>,
+    output_hash: &str,
+) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for part in [
+        session_id,
+        &timestamp.to_string(),
+        command.unwrap_or(""),
+        output_hash,
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+impl StorageManager {
+    /// Content keys of all captures, mapped to their local ids
+    pub fn capture_keys(&self) -> Result<HashMap<String, i64>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt =
+            conn.prepare("SELECT id, session_id, timestamp, command, output_hash FROM captures")?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let session_id: String = row.get(1)?;
+            let command: Option<String> = row.get(3)?;
+            let output_hash: String = row.get(4)?;
+            Ok((
+                capture_key(&session_id, row.get(2)?, command.as_deref(), &output_hash),
+                id,
+            ))
+        })?;
+
+        Ok(rows.collect::<std::result::Result<HashMap<_, _>, _>>()?)
+    }
+
+    /// Hashes of all blobs in the database
+    pub fn blob_hashes(&self) -> Result<Vec<String>> {
+        let conn = self.database.read_conn()?;
+        let mut stmt = conn.prepare("SELECT hash FROM blobs")?;
+        let hashes = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(hashes)
+    }
+
+    /// Build sync records for the given capture ids
+    pub fn export_captures(&self, ids: &[i64]) -> Result<Vec<SyncCapture>> {
+        let conn = self.database.read_conn()?;
+        let mut captures = Vec::with_capacity(ids.len());
+
+        for &id in ids {
+            let Some(capture) = self.database.get_capture(id)? else {
+                continue;
+            };
+
+            let entities = self
+                .database
+                .get_entities_for_capture(id)?
+                .into_iter()
+                .map(|e| SyncEntity {
+                    entity_type: e.entity_type,
+                    value: e.value,
+                    context: e.context,
+                    confidence: e.confidence,
+                    occurrences: e.occurrences,
+                })
+                .collect();
+
+            let mut stmt = conn.prepare_cached(
+                "SELECT representative_text, cluster_size, metadata FROM chunks
+                 WHERE capture_id = ?1 ORDER BY id",
+            )?;
+            let chunks = stmt
+                .query_map(params![id], |row| {
+                    Ok(SyncChunk {
+                        representative_text: row.get(0)?,
+                        cluster_size: row.get(1)?,
+                        metadata: row.get(2)?,
+                    })
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            let mut stmt = conn.prepare_cached(
+                "SELECT title, severity, rule, created_at FROM findings
+                 WHERE capture_id = ?1 ORDER BY id",
+            )?;
+            let findings = stmt
+                .query_map(params![id], |row| {
+                    Ok(SyncFinding {
+                        title: row.get(0)?,
+                        severity: row.get(1)?,
+                        rule: row.get(2)?,
+                        created_at: row.get(3)?,
+                    })
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            captures.push(SyncCapture {
+                key: capture_key(
+                    &capture.session_id,
+                    capture.timestamp,
+                    capture.command.as_deref(),
+                    &capture.output_hash,
+                ),
+                session_id: capture.session_id,
+                timestamp: capture.timestamp,
+                command: capture.command,
+                output_hash: capture.output_hash,
+                tool: capture.tool,
+                exit_code: capture.exit_code,
+                cwd: capture.cwd,
+                username: capture.username,
+                entities,
+                chunks,
+                arguments: capture.arguments,
+                context: self.database.get_capture_context(id)?,
+                tags: self.database.get_capture_tags(id)?,
+                findings,
+            });
+        }
+
+        Ok(captures)
+    }
+
+    /// Session state for the sessions the captures belong to, where known
+    pub fn export_sessions(&self, captures: &[SyncCapture]) -> Vec<Session> {
+        let ids: HashSet<&str> = captures.iter().map(|c| c.session_id.as_str()).collect();
+        ids.into_iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .filter_map(|id| Session::load(self.base_path(), &id).ok())
+            .collect()
+    }
+
+    /// How importing `captures` and `blobs` would overlap with the store
+    pub fn diff_import(
+        &self,
+        sessions: &[Session],
+        captures: &[SyncCapture],
+        blobs: &[String],
+    ) -> Result<ImportDiff> {
+        let mut diff = ImportDiff::default();
+        for session in sessions {
+            if self.has_session(&session.id)? {
+                diff.existing_sessions += 1;
+            } else {
+                diff.new_sessions += 1;
+            }
+        }
+
+        let existing = self.capture_keys()?;
+        for capture in captures {
+            let Some(&id) = existing.get(&capture.key) else {
+                diff.new_captures += 1;
+                continue;
+            };
+            diff.existing_captures += 1;
+            let local: HashSet<(String, String)> = self
+                .database
+                .get_entities_for_capture(id)?
+                .into_iter()
+                .map(|e| (e.entity_type, e.value))
+                .collect();
+            diff.new_entities += capture
+                .entities
+                .iter()
+                .filter(|e| !local.contains(&(e.entity_type.clone(), e.value.clone())))
+                .count();
+        }
+
+        for hash in blobs {
+            if self.blob_store.exists(hash) {
+                diff.existing_blobs += 1;
+            } else {
+                diff.new_blobs += 1;
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Whether the store knows the session, from its state file or the database
+    fn has_session(&self, id: &Uuid) -> Result<bool> {
+        if Session::load(self.base_path(), id).is_ok() {
+            return Ok(true);
+        }
+        let conn = self.database.read_conn()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sessions WHERE id = ?1",
+            params![id.to_string()],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Import captures received from a peer
+    ///
+    /// `blobs` holds the contents the peer sent; any other blob a capture
+    /// refers to must already exist locally. Captures already present are
+    /// skipped. Returns the number of captures imported.
+    pub fn import_captures(
+        &self,
+        sessions: &[Session],
+        captures: &[SyncCapture],
+        blobs: &[(String, Vec<u8>)],
+    ) -> Result<usize> {
+        Ok(self
+            .import_captures_with(sessions, captures, blobs, ConflictStrategy::Skip)?
+            .imported)
+    }
+
+    /// Import captures, resolving those already present with `strategy`
+    pub fn import_captures_with(
+        &self,
+        sessions: &[Session],
+        captures: &[SyncCapture],
+        blobs: &[(String, Vec<u8>)],
+        strategy: ConflictStrategy,
+    ) -> Result<ImportCounts> {
+        if strategy == ConflictStrategy::Duplicate {
+            let (sessions, captures) = self.as_new_sessions(sessions, captures)?;
+            return self.import_captures_with(&sessions, &captures, blobs, ConflictStrategy::Skip);
+        }
+
+        // Blobs first, verifying they match the hash they were sent under
+        let mut blob_meta: HashMap<&str, (i64, bool)> = HashMap::new();
+        for (hash, data) in blobs {
+            let (written, compressed, is_new) = self.blob_store.write(data)?;
+            if written != *hash {
+                if is_new {
+                    self.blob_store.delete(&written)?;
+                }
+                return Err(YinxError::Sync(format!(
+                    "Blob content does not match its hash {}",
+                    hash
+                )));
+            }
+            blob_meta.insert(hash, (data.len() as i64, compressed));
+        }
+
+        for session in sessions {
+            if Session::load(self.base_path(), &session.id).is_err() {
+                // A copy is never live here; left active it would be flagged as interrupted
+                let mut session = session.clone();
+                if matches!(
+                    session.status,
+                    SessionStatus::Active | SessionStatus::Paused | SessionStatus::Interrupted
+                ) {
+                    session.stop();
+                }
+                session.save(self.base_path())?;
+            }
+        }
+        let sessions: HashMap<String, &Session> =
+            sessions.iter().map(|s| (s.id.to_string(), s)).collect();
+
+        let existing = self.capture_keys()?;
+        let now = chrono::Utc::now().timestamp();
+        let mut conn = self.database.get_conn()?;
+        let tx = conn.transaction()?;
+        let mut counts = ImportCounts::default();
+        let mut conflicts = Vec::new();
+        let mut indexed = Vec::new();
+
+        for capture in captures {
+            if let Some(&id) = existing.get(&capture.key) {
+                conflicts.push((id, capture));
+                continue;
+            }
+            let key = capture_key(
+                &capture.session_id,
+                capture.timestamp,
+                capture.command.as_deref(),
+                &capture.output_hash,
+            );
+            if key != capture.key {
+                return Err(YinxError::Sync(format!(
+                    "Capture key mismatch for {}",
+                    capture.key
+                )));
+            }
+
+            let (name, started_at) = match sessions.get(&capture.session_id) {
+                Some(s) => (s.name.clone(), s.started_at.timestamp()),
+                None => (capture.session_id.clone(), capture.timestamp),
+            };
+            tx.execute(
+                "INSERT OR IGNORE INTO sessions (id, name, started_at, status)
+                 VALUES (?1, ?2, ?3, 'stopped')",
+                params![&capture.session_id, name, started_at],
+            )?;
+
+            let (size, compressed) = match blob_meta.get(capture.output_hash.as_str()) {
+                Some(meta) => *meta,
+                None if self.blob_store.exists(&capture.output_hash) => (
+                    self.blob_store.read(&capture.output_hash)?.len() as i64,
+                    false,
+                ),
+                None => {
+                    return Err(YinxError::Sync(format!(
+                        "Blob {} was neither sent nor present locally",
+                        capture.output_hash
+                    )))
+                }
+            };
+            tx.execute(
+                "INSERT INTO blobs (hash, size, created_at, compressed, ref_count)
+                 VALUES (?1, ?2, ?3, ?4, 1)
+                 ON CONFLICT(hash) DO UPDATE SET ref_count = ref_count + 1",
+                params![&capture.output_hash, size, now, compressed],
+            )?;
+
+            let arguments = &capture.arguments;
+            tx.execute(
+                "INSERT INTO captures
+                 (session_id, timestamp, command, output_hash, tool, exit_code, cwd, username,
+                  target, ports, wordlist, url, service)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    &capture.session_id,
+                    capture.timestamp,
+                    &capture.command,
+                    &capture.output_hash,
+                    &capture.tool,
+                    capture.exit_code,
+                    &capture.cwd,
+                    &capture.username,
+                    &arguments.target,
+                    &arguments.ports,
+                    &arguments.wordlist,
+                    &arguments.url,
+                    &arguments.service,
+                ],
+            )?;
+            let capture_id = tx.last_insert_rowid();
+            if let Some(context) = &capture.context {
+                insert_context(&tx, capture_id, context)?;
+            }
+            insert_tags_and_findings(&tx, capture_id, capture)?;
+
+            for entity in &capture.entities {
+                upsert_entity(
+                    &tx,
+                    capture_id,
+                    &entity.entity_type,
+                    &entity.value,
+                    entity.context.as_deref(),
+                    entity.confidence,
+                    entity.occurrences,
+                )?;
+            }
+
+            for chunk in &capture.chunks {
+                tx.execute(
+                    "INSERT INTO chunks
+                     (capture_id, blob_hash, representative_text, cluster_size, metadata)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        capture_id,
+                        &capture.output_hash,
+                        &chunk.representative_text,
+                        chunk.cluster_size,
+                        &chunk.metadata,
+                    ],
+                )?;
+                indexed.push((
+                    tx.last_insert_rowid() as u64,
+                    chunk.representative_text.clone(),
+                    chunk.metadata.as_deref().and_then(metadata_host),
+                ));
+            }
+
+            tx.execute(
+                "UPDATE sessions SET capture_count = capture_count + 1 WHERE id = ?1",
+                params![&capture.session_id],
+            )?;
+            counts.imported += 1;
+        }
+
+        if strategy == ConflictStrategy::Merge {
+            for (id, capture) in &conflicts {
+                for entity in &capture.entities {
+                    let present: i64 = tx.query_row(
+                        "SELECT COUNT(*) FROM entities WHERE capture_id = ?1 AND type = ?2 AND value = ?3",
+                        params![id, &entity.entity_type, &entity.value],
+                        |row| row.get(0),
+                    )?;
+                    if present == 0 {
+                        upsert_entity(
+                            &tx,
+                            *id,
+                            &entity.entity_type,
+                            &entity.value,
+                            entity.context.as_deref(),
+                            entity.confidence,
+                            entity.occurrences,
+                        )?;
+                    }
+                }
+                insert_tags_and_findings(&tx, *id, capture)?;
+                counts.updated += 1;
+            }
+        }
+
+        tx.commit()?;
+        drop(conn);
+
+        let mut removed = Vec::new();
+        if strategy == ConflictStrategy::Overwrite {
+            for (id, capture) in &conflicts {
+                removed.extend(self.overwrite_capture(*id, capture, &mut indexed)?);
+                counts.updated += 1;
+            }
+        }
+
+        if !indexed.is_empty() || !removed.is_empty() {
+            let mut index = self.open_keyword_index()?;
+            for id in removed {
+                index
+                    .delete(id as u64)
+                    .map_err(|e| YinxError::Other(e.into()))?;
+            }
+            index
+                .replace_hosted_batch(&indexed)
+                .map_err(|e| YinxError::Other(e.into()))?;
+            index.commit().map_err(|e| YinxError::Other(e.into()))?;
+        }
+
+        Ok(counts)
+    }
+
+    /// Give every session the store already has a new id and name, moving
+    /// its captures along, so nothing conflicts
+    fn as_new_sessions(
+        &self,
+        sessions: &[Session],
+        captures: &[SyncCapture],
+    ) -> Result<(Vec<Session>, Vec<SyncCapture>)> {
+        let mut renamed: HashMap<String, String> = HashMap::new();
+        let mut sessions = sessions.to_vec();
+        for session in &mut sessions {
+            if self.has_session(&session.id)? {
+                let id = Uuid::new_v4();
+                renamed.insert(session.id.to_string(), id.to_string());
+                session.id = id;
+                session.name = format!("{}-imported", session.name);
+            }
+        }
+
+        let captures = captures
+            .iter()
+            .cloned()
+            .map(|mut capture| {
+                if let Some(id) = renamed.get(&capture.session_id) {
+                    capture.session_id = id.clone();
+                    capture.key = capture_key(
+                        &capture.session_id,
+                        capture.timestamp,
+                        capture.command.as_deref(),
+                        &capture.output_hash,
+                    );
+                }
+                capture
+            })
+            .collect();
+        Ok((sessions, captures))
+    }
+
+    /// Replace a local capture's details, entities and chunks with an
+    /// imported copy; returns the ids of the chunks removed
+    fn overwrite_capture(
+        &self,
+        id: i64,
+        capture: &SyncCapture,
+        indexed: &mut Vec<(u64, String, Option<String>)>,
+    ) -> Result<Vec<i64>> {
+        let arguments = &capture.arguments;
+        let mut conn = self.database.get_conn()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE captures SET tool = ?2, exit_code = ?3, cwd = ?4, username = ?5,
+                target = ?6, ports = ?7, wordlist = ?8, url = ?9, service = ?10
+             WHERE id = ?1",
+            params![
+                id,
+                &capture.tool,
+                capture.exit_code,
+                &capture.cwd,
+                &capture.username,
+                &arguments.target,
+                &arguments.ports,
+                &arguments.wordlist,
+                &arguments.url,
+                &arguments.service,
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM capture_context WHERE capture_id = ?1",
+            params![id],
+        )?;
+        if let Some(context) = &capture.context {
+            insert_context(&tx, id, context)?;
+        }
+        insert_tags_and_findings(&tx, id, capture)?;
+        tx.commit()?;
+        drop(conn);
+
+        let entities: Vec<(String, String, String, f32)> = capture
+            .entities
+            .iter()
+            .map(|e| {
+                (
+                    e.entity_type.clone(),
+                    e.value.clone(),
+                    e.context.clone().unwrap_or_default(),
+                    e.confidence,
+                )
+            })
+            .collect();
+        self.database.replace_capture_entities(id, &entities)?;
+
+        let chunks: Vec<(String, i32, String)> = capture
+            .chunks
+            .iter()
+            .map(|c| {
+                (
+                    c.representative_text.clone(),
+                    c.cluster_size as i32,
+                    c.metadata.clone().unwrap_or_else(|| "{}".to_string()),
+                )
+            })
+            .collect();
+        let (removed, inserted) =
+            self.database
+                .replace_capture_chunks(id, &capture.output_hash, &chunks)?;
+        indexed.extend(
+            inserted
+                .into_iter()
+                .zip(&capture.chunks)
+                .map(|(id, chunk)| {
+                    (
+                        id as u64,
+                        chunk.representative_text.clone(),
+                        chunk.metadata.as_deref().and_then(metadata_host),
+                    )
+                }),
+        );
+        Ok(removed)
+    }
+}
+
+/// Store the environment an imported capture ran in
+fn insert_context(
+    conn: &rusqlite::Connection,
+    capture_id: i64,
+    context: &CaptureContext,
+) -> Result<()> {
+    let env = serde_json::to_string(&context.env).map_err(|e| YinxError::Json {
+        source: e,
+        context: "Failed to serialize capture environment".to_string(),
+    })?;
+    conn.execute(
+        "INSERT OR REPLACE INTO capture_context
+            (capture_id, git_root, git_branch, venv, vpn_interface, env, hostname, terminal)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            capture_id,
+            context.git_root,
+            context.git_branch,
+            context.venv,
+            context.vpn_interface,
+            env,
+            context.hostname,
+            context.terminal
+        ],
+    )?;
+    Ok(())
+}
+
+/// Add an imported capture's tags and findings to a local capture
+///
+/// Tags and findings the local capture's session has already are kept.
+fn insert_tags_and_findings(
+    conn: &rusqlite::Connection,
+    capture_id: i64,
+    capture: &SyncCapture,
+) -> Result<()> {
+    for tag in &capture.tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO capture_tags (capture_id, tag) VALUES (?1, ?2)",
+            params![capture_id, tag],
+        )?;
+    }
+    for finding in &capture.findings {
+        conn.execute(
+            "INSERT INTO findings (session_id, capture_id, title, severity, rule, created_at)
+             SELECT c.session_id, c.id, ?2, ?3, ?4, ?5 FROM captures c
+             WHERE c.id = ?1 AND NOT EXISTS (
+                 SELECT 1 FROM findings f
+                 WHERE f.session_id = c.session_id AND f.title = ?2 AND f.rule IS ?4
+             )",
+            params![
+                capture_id,
+                &finding.title,
+                &finding.severity,
+                &finding.rule,
+                finding.created_at
+            ],
+        )?;
+    }
+    Ok(())
+}
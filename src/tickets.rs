@@ -0,0 +1,467 @@
+//! Issue tracker integration
+//!
+//! `yinx findings push` files findings as Jira or GitLab issues, with the
+//! severity mapped onto the tracker and an excerpt of the capture behind
+//! the finding in the description. The issue created for a finding is
+//! remembered, so pushing it again updates that issue instead of opening
+//! a second one.
+
+use crate::config::TicketsConfig;
+use crate::error::{Result, YinxError};
+use crate::patterns::PatternRegistry;
+use crate::storage::{FindingRecord, FindingTicketRecord, StorageManager};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Output lines quoted from the finding's capture
+const EXCERPT_LINES: usize = 30;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tracker a finding is pushed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TicketTarget {
+    Jira,
+    Gitlab,
+}
+
+impl TicketTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Jira => "jira",
+            Self::Gitlab => "gitlab",
+        }
+    }
+}
+
+impl FromStr for TicketTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jira" => Ok(Self::Jira),
+            "gitlab" => Ok(Self::Gitlab),
+            _ => Err(format!(
+                "unknown ticket target '{}' (expected jira or gitlab)",
+                s
+            )),
+        }
+    }
+}
+
+/// What pushing a finding did
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushResult {
+    pub finding_id: i64,
+    /// False when an existing issue was updated
+    pub created: bool,
+    pub remote_id: String,
+    pub url: Option<String>,
+}
+
+/// Issue contents for a finding
+#[derive(Debug, Clone, PartialEq)]
+struct Issue {
+    title: String,
+    severity: String,
+    details: Vec<String>,
+    excerpt: Option<String>,
+}
+
+impl Issue {
+    fn new(finding: &FindingRecord, excerpt: Option<String>) -> Self {
+        let mut details = vec![
+            format!("Severity: {}", finding.severity),
+            format!("Session: {}", finding.session_id),
+        ];
+        if let Some(rule) = &finding.rule {
+            details.push(format!("Rule: {}", rule));
+        }
+        if let Some(opened) = chrono::DateTime::from_timestamp(finding.created_at, 0) {
+            details.push(format!("Opened: {}", opened.format("%Y-%m-%d %H:%M UTC")));
+        }
+        details.push(format!("yinx finding #{}", finding.id));
+        Self {
+            title: finding.title.clone(),
+            severity: finding.severity.clone(),
+            details,
+            excerpt,
+        }
+    }
+
+    /// Description in Jira wiki markup
+    fn jira_description(&self) -> String {
+        let mut description = self.details.join("\n");
+        if let Some(excerpt) = &self.excerpt {
+            description.push_str(&format!(
+                "\n\nh3. Evidence\n{{noformat}}\n{}\n{{noformat}}",
+                excerpt
+            ));
+        }
+        description
+    }
+
+    /// Description in GitLab Markdown
+    fn gitlab_description(&self) -> String {
+        let mut description = self
+            .details
+            .iter()
+            .map(|line| format!("- {}", line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Some(excerpt) = &self.excerpt {
+            // A fence longer than any backtick run in the output can't be closed early
+            let longest = excerpt.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+            let fence = "`".repeat(longest.max(2) + 1);
+            description.push_str(&format!(
+                "\n\n### Evidence\n\n{}\n{}\n{}",
+                fence, excerpt, fence
+            ));
+        }
+        description
+    }
+}
+
+/// Jira priority for a finding severity
+fn jira_priority(severity: &str) -> &'static str {
+    match severity {
+        "critical" => "Highest",
+        "high" => "High",
+        "medium" => "Medium",
+        "low" => "Low",
+        _ => "Lowest",
+    }
+}
+
+/// A configured tracker with its credentials
+struct Tracker<'a> {
+    target: TicketTarget,
+    config: &'a TicketsConfig,
+    authorization: (&'static str, String),
+}
+
+impl<'a> Tracker<'a> {
+    fn new(config: &'a TicketsConfig, target: TicketTarget) -> Result<Self> {
+        let (url, token_env) = match target {
+            TicketTarget::Jira => (&config.jira.url, &config.jira.token_env),
+            TicketTarget::Gitlab => (&config.gitlab.url, &config.gitlab.token_env),
+        };
+        if url.is_empty() {
+            return Err(YinxError::Config(format!(
+                "Set tickets.{}.url and tickets.{}.project to push findings there",
+                target.as_str(),
+                target.as_str()
+            )));
+        }
+        let token = std::env::var(token_env).map_err(|_| {
+            YinxError::Config(format!(
+                "{} must hold the {} token",
+                token_env,
+                target.as_str()
+            ))
+        })?;
+
+        let authorization = match target {
+            // Cloud takes the account email and an API token; Data Center a bearer token
+            TicketTarget::Jira => match std::env::var(&config.jira.user_env) {
+                Ok(user) => (
+                    "Authorization",
+                    format!(
+                        "Basic {}",
+                        encode_base64(format!("{}:{}", user, token).as_bytes())
+                    ),
+                ),
+                Err(_) => ("Authorization", format!("Bearer {}", token)),
+            },
+            TicketTarget::Gitlab => ("PRIVATE-TOKEN", token),
+        };
+        Ok(Self {
+            target,
+            config,
+            authorization,
+        })
+    }
+
+    /// Create an issue; returns its key or number and web URL
+    fn create(&self, issue: &Issue) -> Result<(String, Option<String>)> {
+        match self.target {
+            TicketTarget::Jira => {
+                let jira = &self.config.jira;
+                let mut fields = jira_fields(issue);
+                fields["project"] = json!({ "key": jira.project });
+                fields["issuetype"] = json!({ "name": jira.issue_type });
+                let response = self.send(
+                    "POST",
+                    &format!("{}/rest/api/2/issue", base(&jira.url)),
+                    json!({ "fields": fields }),
+                )?;
+                let key = response["key"]
+                    .as_str()
+                    .ok_or_else(|| self.error("response has no issue key"))?;
+                Ok((
+                    key.to_string(),
+                    Some(format!("{}/browse/{}", base(&jira.url), key)),
+                ))
+            }
+            TicketTarget::Gitlab => {
+                let response = self.send("POST", &self.gitlab_issues(), self.gitlab_body(issue))?;
+                let iid = response["iid"]
+                    .as_i64()
+                    .ok_or_else(|| self.error("response has no issue number"))?;
+                Ok((
+                    iid.to_string(),
+                    response["web_url"].as_str().map(str::to_string),
+                ))
+            }
+        }
+    }
+
+    /// Update the issue created earlier
+    fn update(&self, remote_id: &str, issue: &Issue) -> Result<()> {
+        match self.target {
+            TicketTarget::Jira => {
+                let url = format!(
+                    "{}/rest/api/2/issue/{}",
+                    base(&self.config.jira.url),
+                    remote_id
+                );
+                self.send("PUT", &url, json!({ "fields": jira_fields(issue) }))?;
+            }
+            TicketTarget::Gitlab => {
+                let url = format!("{}/{}", self.gitlab_issues(), remote_id);
+                self.send("PUT", &url, self.gitlab_body(issue))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn gitlab_issues(&self) -> String {
+        let gitlab = &self.config.gitlab;
+        format!(
+            "{}/api/v4/projects/{}/issues",
+            base(&gitlab.url),
+            encode_path(&gitlab.project)
+        )
+    }
+
+    fn gitlab_body(&self, issue: &Issue) -> Value {
+        let mut labels = self.config.gitlab.labels.clone();
+        labels.push(format!("severity::{}", issue.severity));
+        json!({
+            "title": issue.title,
+            "description": issue.gitlab_description(),
+            "labels": labels.join(","),
+        })
+    }
+
+    fn send(&self, method: &str, url: &str, body: Value) -> Result<Value> {
+        let (header, value) = &self.authorization;
+        let response = ureq::request(method, url)
+            .timeout(REQUEST_TIMEOUT)
+            .set(header, value)
+            .set("Accept", "application/json")
+            .send_json(body);
+        match response {
+            // Jira answers updates with 204 and no body
+            Ok(response) => Ok(response.into_json().unwrap_or(Value::Null)),
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                Err(self.error(&format!("HTTP {}: {}", code, body.trim())))
+            }
+            Err(e) => Err(self.error(&e.to_string())),
+        }
+    }
+
+    fn error(&self, message: &str) -> YinxError {
+        YinxError::Other(anyhow::anyhow!(
+            "{} request failed: {}",
+            self.target.as_str(),
+            message
+        ))
+    }
+}
+
+/// Push a finding, creating its issue or updating the one pushed before
+///
+/// The capture excerpt is redacted with `patterns` first: blobs are stored
+/// as captured, and the tracker is outside the engagement's data.
+pub fn push_finding(
+    storage: &StorageManager,
+    patterns: &PatternRegistry,
+    config: &TicketsConfig,
+    target: TicketTarget,
+    finding: &FindingRecord,
+) -> Result<PushResult> {
+    let tracker = Tracker::new(config, target)?;
+    let issue = Issue::new(finding, redacted_excerpt(storage, patterns, finding)?);
+
+    let existing = storage
+        .database
+        .get_finding_ticket(finding.id, target.as_str())?;
+    let (remote_id, url, created) = match existing {
+        Some(ticket) => {
+            tracker.update(&ticket.remote_id, &issue)?;
+            (ticket.remote_id, ticket.url, false)
+        }
+        None => {
+            let (remote_id, url) = tracker.create(&issue)?;
+            (remote_id, url, true)
+        }
+    };
+
+    storage.database.set_finding_ticket(&FindingTicketRecord {
+        finding_id: finding.id,
+        target: target.as_str().to_string(),
+        remote_id: remote_id.clone(),
+        url: url.clone(),
+        pushed_at: chrono::Utc::now().timestamp(),
+    })?;
+    Ok(PushResult {
+        finding_id: finding.id,
+        created,
+        remote_id,
+        url,
+    })
+}
+
+/// Excerpt of the finding's capture with sensitive values replaced
+fn redacted_excerpt(
+    storage: &StorageManager,
+    patterns: &PatternRegistry,
+    finding: &FindingRecord,
+) -> Result<Option<String>> {
+    let Some(id) = finding.capture_id else {
+        return Ok(None);
+    };
+    Ok(storage.database.get_capture(id)?.map(|capture| {
+        let excerpt = storage.capture_excerpt(&capture, EXCERPT_LINES);
+        patterns.redact_sensitive(&excerpt).unwrap_or(excerpt)
+    }))
+}
+
+/// Fields a Jira issue gets on both create and update
+fn jira_fields(issue: &Issue) -> Value {
+    json!({
+        "summary": issue.title,
+        "description": issue.jira_description(),
+        "priority": { "name": jira_priority(&issue.severity) },
+        "labels": ["yinx", format!("severity-{}", issue.severity)],
+    })
+}
+
+fn base(url: &str) -> &str {
+    url.trim_end_matches('/')
+}
+
+/// Percent-encode a project path (`group/project`) for use as an ID
+fn encode_path(project: &str) -> String {
+    project
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn encode_base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+
+    fn finding() -> FindingRecord {
+        FindingRecord {
+            id: 7,
+            session_id: "s1".to_string(),
+            capture_id: Some(3),
+            title: "SMB signing disabled".to_string(),
+            severity: "high".to_string(),
+            rule: Some("smb-signing".to_string()),
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_issue_contents() {
+        let issue = Issue::new(&finding(), Some("$ nxc smb 10.0.0.5\n```".to_string()));
+
+        let fields = jira_fields(&issue);
+        assert_eq!(fields["summary"], "SMB signing disabled");
+        assert_eq!(fields["priority"]["name"], "High");
+        let description = fields["description"].as_str().unwrap();
+        assert!(description.starts_with("Severity: high\nSession: s1\nRule: smb-signing\n"));
+        assert!(description.ends_with("{noformat}\n$ nxc smb 10.0.0.5\n```\n{noformat}"));
+
+        let description = issue.gitlab_description();
+        assert!(description.contains("- yinx finding #7"));
+        assert!(description.ends_with("````\n$ nxc smb 10.0.0.5\n```\n````"));
+        assert_eq!(jira_priority("info"), "Lowest");
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(encode_path("red team/exam-1"), "red%20team%2Fexam-1");
+        assert_eq!(
+            encode_base64(b"user@example.com:tok"),
+            "dXNlckBleGFtcGxlLmNvbTp0b2s="
+        );
+        assert_eq!(encode_base64(b"ab"), "YWI=");
+        assert_eq!("GitLab".parse(), Ok(TicketTarget::Gitlab));
+        assert!("github".parse::<TicketTarget>().is_err());
+    }
+
+    #[test]
+    fn test_push_requires_configured_target() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let result = push_finding(
+            &storage,
+            &PatternRegistry::bundled().unwrap(),
+            &TicketsConfig::default(),
+            TicketTarget::Jira,
+            &finding(),
+        );
+        assert!(matches!(result, Err(YinxError::Config(_))));
+    }
+
+    #[test]
+    fn test_excerpt_is_redacted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        capture("s1", "env", b"Connected to db01\nDB_PASSWORD=hunter2\n")
+            .id(3)
+            .insert(&storage);
+
+        let patterns = PatternRegistry::bundled().unwrap();
+        let excerpt = redacted_excerpt(&storage, &patterns, &finding())
+            .unwrap()
+            .unwrap();
+        assert!(excerpt.contains("Connected to db01"));
+        assert!(!excerpt.contains("hunter2"));
+        assert!(excerpt.contains("[REDACTED:credential_"));
+    }
+}
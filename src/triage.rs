@@ -0,0 +1,261 @@
+//! Hints for commands that failed
+//!
+//! A nonzero exit usually comes with a line saying why: the port was
+//! closed, the certificate didn't verify, the tool needs root. The first
+//! rule matching the output names the failure and suggests what to try
+//! next; `[[triage.rules]]` entries are tried before the built-in ones, and
+//! a `triage` hook can add a longer explanation.
+
+use crate::config::TriageConfig;
+use crate::error::{Result, YinxError};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Longest matched line kept as evidence
+const MAX_EVIDENCE: usize = 200;
+
+/// Common failures, by rule name, with what to try next
+const BUILTIN_RULES: &[(&str, &str, &str)] = &[
+    (
+        "connection_refused",
+        r"connection refused|ECONNREFUSED|actively refused|connection reset by peer",
+        "Nothing accepted the connection: check the port is open and the service is up",
+    ),
+    (
+        "unreachable",
+        r"no route to host|network is unreachable|host is unreachable|host seems down",
+        "The host isn't reachable from here: check the VPN and the route to it",
+    ),
+    (
+        "timeout",
+        r"timed? ?out|timeout expired",
+        "The target didn't answer in time: it may be filtered, or try a longer timeout",
+    ),
+    (
+        "tls",
+        r"\b(ssl|tls)\b.{0,40}\b(error|fail(ed|ure)?|alert|problem)|handshake fail|certificate (verify failed|has expired|is not trusted)|self[- ]signed certificate|wrong version number",
+        "TLS failed: try skipping certificate checks (-k) or the plain-text port",
+    ),
+    (
+        "dns",
+        r"could not resolve|name or service not known|temporary failure in name resolution|nodename nor servname|NXDOMAIN|unknown host",
+        "The name didn't resolve: add it to /etc/hosts or use the address",
+    ),
+    (
+        "permission_denied",
+        r"permission denied|operation not permitted|requires root|must be run as root|you don't have permission|EACCES",
+        "Not enough privileges: retry with sudo or check the file permissions",
+    ),
+    (
+        "auth_failed",
+        r"authentication fail|login fail|invalid credentials|logon failure|access denied|STATUS_LOGON_FAILURE|401 unauthorized",
+        "The credentials were rejected: check the username, password and domain",
+    ),
+    (
+        "not_found",
+        r"command not found|no such file or directory|not recognized as an internal or external command",
+        "A command or file is missing: check the path, or install the tool",
+    ),
+];
+
+/// Why a command failed, as far as its output tells
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriageHint {
+    /// Name of the rule that matched
+    pub rule: String,
+    pub hint: String,
+    /// Output line the rule matched, empty when only the exit code did
+    pub evidence: String,
+    /// Longer explanation from a `triage` hook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+impl TriageHint {
+    fn new(rule: &str, hint: &str, evidence: &str) -> Self {
+        let evidence = evidence.trim();
+        let evidence = match evidence.char_indices().nth(MAX_EVIDENCE) {
+            Some((end, _)) => format!("{}...", &evidence[..end]),
+            None => evidence.to_string(),
+        };
+        Self {
+            rule: rule.to_string(),
+            hint: hint.to_string(),
+            evidence,
+            explanation: None,
+        }
+    }
+}
+
+struct Rule {
+    name: String,
+    pattern: Regex,
+    hint: String,
+}
+
+/// Compiled `[triage]` settings; the default triages nothing
+#[derive(Default)]
+pub struct Triage {
+    rules: Vec<Rule>,
+}
+
+impl Triage {
+    pub fn new(config: &TriageConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        let custom = config.rules.iter().map(|rule| {
+            let pattern = compile(&rule.pattern).map_err(|e| {
+                YinxError::Config(format!("Invalid triage rule '{}': {}", rule.name, e))
+            })?;
+            Ok(Rule {
+                name: rule.name.clone(),
+                pattern,
+                hint: rule.hint.clone(),
+            })
+        });
+        let builtin = BUILTIN_RULES.iter().map(|(name, pattern, hint)| {
+            Ok(Rule {
+                name: name.to_string(),
+                pattern: compile(pattern).expect("built-in triage rule compiles"),
+                hint: hint.to_string(),
+            })
+        });
+        Ok(Self {
+            rules: custom.chain(builtin).collect::<Result<_>>()?,
+        })
+    }
+
+    /// Hint for a capture, None when the command succeeded or nothing matched
+    pub fn diagnose(&self, output: &str, exit_code: i32) -> Option<TriageHint> {
+        if exit_code == 0 || self.rules.is_empty() {
+            return None;
+        }
+        for rule in &self.rules {
+            if let Some(line) = output.lines().find(|line| rule.pattern.is_match(line)) {
+                return Some(TriageHint::new(&rule.name, &rule.hint, line));
+            }
+        }
+        // Shells report these codes without printing much
+        match exit_code {
+            126 => Some(TriageHint::new(
+                "not_executable",
+                "The command isn't executable: check its permissions (chmod +x)",
+                "",
+            )),
+            127 => Some(TriageHint::new(
+                "not_found",
+                "Command not found: check the spelling or install the tool",
+                "",
+            )),
+            130 => Some(TriageHint::new(
+                "interrupted",
+                "Interrupted with Ctrl-C; the output may be incomplete",
+                "",
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn compile(pattern: &str) -> std::result::Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TriageRuleConfig;
+
+    #[test]
+    fn test_builtin_rules() {
+        let triage = Triage::new(&TriageConfig::default()).unwrap();
+        let hint = triage
+            .diagnose(
+                "Trying 10.0.0.5...\ncurl: (7) Failed to connect to 10.0.0.5 port 8080: Connection refused\n",
+                7,
+            )
+            .unwrap();
+        assert_eq!(hint.rule, "connection_refused");
+        assert!(hint.evidence.starts_with("curl: (7)"));
+
+        let hint = triage
+            .diagnose(
+                "curl: (60) SSL certificate problem: self-signed certificate",
+                60,
+            )
+            .unwrap();
+        assert_eq!(hint.rule, "tls");
+
+        let hint = triage
+            .diagnose(
+                "You requested a scan type which requires root privileges.\nQUITTING!",
+                1,
+            )
+            .unwrap();
+        assert_eq!(hint.rule, "permission_denied");
+
+        // Successful commands are never triaged, whatever they printed
+        assert!(triage.diagnose("Connection refused", 0).is_none());
+        assert!(triage.diagnose("done", 1).is_none());
+    }
+
+    #[test]
+    fn test_exit_code_fallback() {
+        let triage = Triage::new(&TriageConfig::default()).unwrap();
+        let hint = triage.diagnose("", 127).unwrap();
+        assert_eq!(hint.rule, "not_found");
+        assert!(hint.evidence.is_empty());
+        assert_eq!(triage.diagnose("^C", 130).unwrap().rule, "interrupted");
+    }
+
+    #[test]
+    fn test_custom_rules_first() {
+        let config = TriageConfig {
+            rules: vec![TriageRuleConfig {
+                name: "kerberos_skew".to_string(),
+                pattern: r"KRB_AP_ERR_SKEW|clock skew too great".to_string(),
+                hint: "Sync the clock with the DC: ntpdate <dc>".to_string(),
+            }],
+            ..Default::default()
+        };
+        let triage = Triage::new(&config).unwrap();
+        let hint = triage
+            .diagnose(
+                "Kerberos SessionError: KRB_AP_ERR_SKEW(Clock skew too great)\naccess denied",
+                1,
+            )
+            .unwrap();
+        assert_eq!(hint.rule, "kerberos_skew");
+    }
+
+    #[test]
+    fn test_disabled_and_invalid() {
+        let disabled = TriageConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(Triage::new(&disabled)
+            .unwrap()
+            .diagnose("Connection refused", 1)
+            .is_none());
+
+        let invalid = TriageConfig {
+            rules: vec![TriageRuleConfig {
+                name: "broken".to_string(),
+                pattern: "(".to_string(),
+                hint: String::new(),
+            }],
+            ..Default::default()
+        };
+        assert!(Triage::new(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_long_evidence_truncated() {
+        let triage = Triage::new(&TriageConfig::default()).unwrap();
+        let line = format!("{} permission denied", "x".repeat(500));
+        let hint = triage.diagnose(&line, 1).unwrap();
+        assert_eq!(hint.evidence.chars().count(), MAX_EVIDENCE + 3);
+    }
+}
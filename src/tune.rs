@@ -0,0 +1,252 @@
+//! Side-by-side filter tuning
+//!
+//! Replays one session's stored output under two filter configurations, each
+//! taken from a config profile, so thresholds can be compared before they
+//! are committed to. Every profile gets its own fresh pipeline, fed the
+//! captures in order the way the daemon saw them. Nothing is written: the
+//! stored chunks and indexes are left as they are.
+
+use crate::daemon::chunk_output;
+use crate::error::Result;
+use crate::filtering::{ChunkPolicy, FilterPipeline};
+use crate::patterns::PatternRegistry;
+use crate::storage::{CaptureRecord, StorageManager};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// One side of a comparison: a profile's filter patterns and chunk policy
+pub struct TuneProfile {
+    pub name: String,
+    pub patterns: PatternRegistry,
+    pub chunk_policy: ChunkPolicy,
+}
+
+/// Line and chunk counts for a capture, or summed over a session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TuneStats {
+    pub input_lines: usize,
+    pub boilerplate_lines: usize,
+    /// Lines left after Tier 1 deduplication
+    pub tier1_lines: usize,
+    /// Lines that passed Tier 2 scoring and ended up in chunks
+    pub kept_lines: usize,
+    pub chunks: usize,
+}
+
+impl TuneStats {
+    /// Fraction of input lines that didn't survive filtering
+    pub fn reduction(&self) -> f64 {
+        if self.input_lines == 0 {
+            return 0.0;
+        }
+        1.0 - self.kept_lines as f64 / self.input_lines as f64
+    }
+
+    fn add(&mut self, other: &TuneStats) {
+        self.input_lines += other.input_lines;
+        self.boilerplate_lines += other.boilerplate_lines;
+        self.tier1_lines += other.tier1_lines;
+        self.kept_lines += other.kept_lines;
+        self.chunks += other.chunks;
+    }
+}
+
+/// How the two profiles treated one capture
+#[derive(Debug, Clone)]
+pub struct CaptureTune {
+    pub capture_id: i64,
+    pub command: Option<String>,
+    pub tool: Option<String>,
+    pub a: TuneStats,
+    pub b: TuneStats,
+    /// Kept lines only profile A keeps, in output order
+    pub only_a: Vec<String>,
+    /// Kept lines only profile B keeps, in output order
+    pub only_b: Vec<String>,
+}
+
+impl CaptureTune {
+    pub fn differs(&self) -> bool {
+        !self.only_a.is_empty() || !self.only_b.is_empty() || self.a.chunks != self.b.chunks
+    }
+}
+
+/// Outcome of comparing two profiles over a session
+#[derive(Debug, Default)]
+pub struct TuneReport {
+    pub profile_a: String,
+    pub profile_b: String,
+    pub a: TuneStats,
+    pub b: TuneStats,
+    pub captures: Vec<CaptureTune>,
+    /// Captures skipped because their blob is gone
+    pub missing_blobs: usize,
+}
+
+impl TuneReport {
+    /// Captures the profiles filter differently
+    pub fn differing(&self) -> impl Iterator<Item = &CaptureTune> {
+        self.captures.iter().filter(|c| c.differs())
+    }
+}
+
+/// A profile ready to replay captures
+struct Side {
+    patterns: Arc<PatternRegistry>,
+    pipeline: FilterPipeline,
+    policy: ChunkPolicy,
+}
+
+impl Side {
+    fn new(storage: &StorageManager, profile: TuneProfile) -> Result<Self> {
+        let patterns = Arc::new(profile.patterns);
+        let pipeline = FilterPipeline::new(patterns.clone());
+        if patterns.tier2_config.auto_threshold.enabled {
+            pipeline.seed_volumes(&storage.database.tier2_volume_means()?);
+        }
+        Ok(Self {
+            patterns,
+            pipeline,
+            policy: profile.chunk_policy,
+        })
+    }
+
+    /// Stats and kept lines, deduplicated in output order
+    fn replay(&self, capture: &CaptureRecord, output: &str) -> Result<(TuneStats, Vec<String>)> {
+        let tool = capture
+            .tool
+            .as_deref()
+            .and_then(|name| self.patterns.tool(name));
+        let sections = tool.map(|t| t.host_sections(output)).unwrap_or_default();
+        let (chunks, stats) = chunk_output(
+            &self.pipeline,
+            &self.policy,
+            &capture.session_id,
+            capture.tool.as_deref(),
+            tool,
+            output,
+            &sections,
+        )?;
+
+        let mut seen = HashSet::new();
+        let kept: Vec<String> = chunks
+            .iter()
+            .flat_map(|chunk| &chunk.members)
+            .filter(|line| seen.insert(line.as_str()))
+            .cloned()
+            .collect();
+        let stats = TuneStats {
+            input_lines: stats.input_lines,
+            boilerplate_lines: stats.boilerplate_lines,
+            tier1_lines: stats.tier1_output,
+            kept_lines: kept.len(),
+            chunks: chunks.len(),
+        };
+        Ok((stats, kept))
+    }
+}
+
+/// Replay a session's captures under two profiles and compare what each keeps
+pub fn tune(
+    storage: &StorageManager,
+    session_id: &str,
+    profile_a: TuneProfile,
+    profile_b: TuneProfile,
+) -> Result<TuneReport> {
+    let mut report = TuneReport {
+        profile_a: profile_a.name.clone(),
+        profile_b: profile_b.name.clone(),
+        ..TuneReport::default()
+    };
+    let a = Side::new(storage, profile_a)?;
+    let b = Side::new(storage, profile_b)?;
+
+    for capture in storage.database.get_session_captures(session_id)? {
+        if !storage.blob_store.exists(&capture.output_hash) {
+            tracing::warn!(
+                "Blob {} missing while replaying capture {}",
+                capture.output_hash,
+                capture.id
+            );
+            report.missing_blobs += 1;
+            continue;
+        }
+        // Truncated output was indexed without the marker line
+        let output = storage.indexed_output(&capture)?;
+        let (a_stats, a_kept) = a.replay(&capture, &output)?;
+        let (b_stats, b_kept) = b.replay(&capture, &output)?;
+
+        let only = |ours: &[String], theirs: &[String]| -> Vec<String> {
+            let theirs: HashSet<&str> = theirs.iter().map(String::as_str).collect();
+            ours.iter()
+                .filter(|line| !theirs.contains(line.as_str()))
+                .cloned()
+                .collect()
+        };
+        report.a.add(&a_stats);
+        report.b.add(&b_stats);
+        report.captures.push(CaptureTune {
+            capture_id: capture.id,
+            command: capture.command.clone(),
+            tool: capture.tool.clone(),
+            only_a: only(&a_kept, &b_kept),
+            only_b: only(&b_kept, &a_kept),
+            a: a_stats,
+            b: b_stats,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tune_compares_profiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let output: String = (0..40)
+            .map(|i| format!("{}/tcp open service-{} banner {}\n", 1000 + i, i, i * 7))
+            .collect();
+        capture("s1", "nmap 10.0.0.1", output.as_bytes()).insert(&storage);
+        let gone = capture("s1", "nmap 10.0.0.1", b"gone").insert(&storage);
+        storage.blob_store.delete(&gone.hash).unwrap();
+
+        let profile = |name: &str, percentile: f32| {
+            let mut patterns = PatternRegistry::bundled().unwrap();
+            patterns.tier2_config.score_threshold_percentile = percentile;
+            patterns.tier2_config.auto_threshold.enabled = false;
+            TuneProfile {
+                name: name.to_string(),
+                patterns,
+                chunk_policy: ChunkPolicy::default(),
+            }
+        };
+        let report = tune(&storage, "s1", profile("loose", 0.0), profile("tight", 0.9)).unwrap();
+
+        assert_eq!(report.missing_blobs, 1);
+        assert_eq!(report.captures.len(), 1);
+        assert_eq!(report.a.input_lines, 40);
+        assert!(report.a.kept_lines > report.b.kept_lines);
+        assert!(report.a.reduction() < report.b.reduction());
+
+        let capture = &report.captures[0];
+        assert!(capture.differs());
+        assert!(capture.only_b.is_empty());
+        assert_eq!(
+            capture.only_a.len(),
+            capture.a.kept_lines - capture.b.kept_lines
+        );
+
+        // Nothing is written back
+        assert!(storage
+            .database
+            .get_session_chunks("s1")
+            .unwrap()
+            .is_empty());
+    }
+}
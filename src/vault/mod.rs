@@ -0,0 +1,138 @@
+//! Local secret vault
+//!
+//! Seals sensitive originals (such as unredacted command lines) with
+//! ChaCha20-Poly1305 under a key stored in `vault.key_file`. Nothing is
+//! sealed unless `vault.enabled` is set.
+
+use crate::error::{Result, YinxError};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::path::Path;
+
+/// Nonce length prepended to every sealed value
+const NONCE_LEN: usize = 12;
+
+/// Symmetric vault for sealing sensitive values at rest
+pub struct Vault {
+    cipher: ChaCha20Poly1305,
+}
+
+impl Vault {
+    /// Open the vault, generating a new key file on first use
+    pub fn open_or_create(key_file: &Path) -> Result<Self> {
+        if key_file.exists() {
+            let key = std::fs::read(key_file).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to read vault key: {}", key_file.display()),
+            })?;
+            if key.len() != 32 {
+                return Err(YinxError::Vault(format!(
+                    "Vault key {} must be 32 bytes, found {}",
+                    key_file.display(),
+                    key.len()
+                )));
+            }
+            return Ok(Self::from_key(Key::from_slice(&key)));
+        }
+
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        Self::write_key(key_file, &key)?;
+        tracing::info!("Created vault key at {}", key_file.display());
+
+        Ok(Self::from_key(&key))
+    }
+
+    /// Build a vault from raw key material
+    pub fn from_key(key: &Key) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(key),
+        }
+    }
+
+    /// Encrypt a value; output is `nonce || ciphertext`
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| YinxError::Vault("Encryption failed".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a value produced by [`Vault::seal`]
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(YinxError::Vault("Sealed value is truncated".to_string()));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                YinxError::Vault("Decryption failed (wrong key or corrupt data)".to_string())
+            })
+    }
+
+    /// Write a new key file readable only by the owner
+    fn write_key(key_file: &Path, key: &Key) -> Result<()> {
+        use std::io::Write;
+        #[cfg(unix)]
+        use std::os::unix::fs::OpenOptionsExt;
+
+        if let Some(parent) = key_file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| YinxError::Io {
+                source: e,
+                context: format!("Failed to create vault directory: {}", parent.display()),
+            })?;
+        }
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // On Windows the profile directory's ACL already keeps others out
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(key_file).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to create vault key: {}", key_file.display()),
+        })?;
+        file.write_all(key).map_err(|e| YinxError::Io {
+            source: e,
+            context: format!("Failed to write vault key: {}", key_file.display()),
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_file = temp_dir.path().join("vault.key");
+
+        let vault = Vault::open_or_create(&key_file).unwrap();
+        let sealed = vault.seal(b"mysql -pS3cret").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"mysql -pS3cret");
+
+        // Reopening uses the same key
+        let reopened = Vault::open_or_create(&key_file).unwrap();
+        assert_eq!(reopened.open(&sealed).unwrap(), b"mysql -pS3cret");
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = Vault::open_or_create(&temp_dir.path().join("a.key")).unwrap();
+        let b = Vault::open_or_create(&temp_dir.path().join("b.key")).unwrap();
+
+        let sealed = a.seal(b"secret").unwrap();
+        assert!(b.open(&sealed).is_err());
+        assert!(a.open(&sealed[..4]).is_err());
+    }
+}
@@ -0,0 +1,503 @@
+//! End-to-end consistency check
+//!
+//! Walks the database, blob store and keyword index looking for things
+//! that disagree: rows whose parent is gone, blob files that went missing,
+//! a keyword index holding more or fewer chunks than its watermark says,
+//! and session counters that drifted from the rows they count. Chunks
+//! without an embedding aren't a problem, they're the embedding queue, so
+//! they're only counted.
+//!
+//! With repair, each problem is fixed the way the database would have:
+//! rows of a foreign key with `ON DELETE CASCADE` are deleted and those
+//! with `SET NULL` are cleared, counters are recounted, and the keyword
+//! index is caught up, then rebuilt if it still doesn't add up. Missing
+//! blob files and rows without a delete action are only reported.
+
+use crate::error::Result;
+use crate::retrieval::{
+    index_is_behind, rebuild_keyword_index, sync_keyword_index, IndexRebuild, KEYWORD_INDEX,
+};
+use crate::storage::StorageManager;
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+
+/// Kind of inconsistency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyCheck {
+    /// Rows pointing at a parent row that doesn't exist
+    ForeignKey,
+    /// Blob rows whose file is gone
+    MissingBlob,
+    /// Keyword index document count differs from the chunks it should hold
+    KeywordIndex,
+    /// A session's capture count differs from its captures
+    SessionCounter,
+    /// A session entity's totals differ from its entities
+    EntityCounter,
+}
+
+impl VerifyCheck {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyCheck::ForeignKey => "foreign_key",
+            VerifyCheck::MissingBlob => "missing_blob",
+            VerifyCheck::KeywordIndex => "keyword_index",
+            VerifyCheck::SessionCounter => "session_counter",
+            VerifyCheck::EntityCounter => "entity_counter",
+        }
+    }
+}
+
+/// One inconsistency, with how many rows or files it covers
+#[derive(Debug, Clone)]
+pub struct VerifyIssue {
+    pub check: VerifyCheck,
+    /// Table, index or session the issue is in
+    pub subject: String,
+    pub count: usize,
+    pub detail: String,
+    pub repaired: bool,
+}
+
+/// Outcome of a consistency check
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub sessions: usize,
+    pub captures: usize,
+    pub chunks: usize,
+    pub blobs: usize,
+    /// Chunks waiting for an embedding
+    pub embeddings_queued: usize,
+    /// Chunks the keyword index hasn't caught up with yet
+    pub index_pending: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    /// Issues left after repair, if any was attempted
+    pub fn unrepaired(&self) -> usize {
+        self.issues.iter().filter(|issue| !issue.repaired).count()
+    }
+}
+
+/// Check everything stored for consistency, repairing what can be
+pub fn verify(storage: &StorageManager, repair: bool) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+    {
+        let conn = storage.database.read_conn()?;
+        let count = |sql: &str| -> Result<usize> {
+            let n: i64 = conn.query_row(sql, [], |row| row.get(0))?;
+            Ok(n as usize)
+        };
+        report.sessions = count("SELECT COUNT(*) FROM sessions")?;
+        report.captures = count("SELECT COUNT(*) FROM captures")?;
+        report.chunks = count("SELECT COUNT(*) FROM chunks")?;
+        report.blobs = count("SELECT COUNT(*) FROM blobs")?;
+    }
+    report.embeddings_queued = storage.database.embedding_backlog()?.chunks;
+
+    check_foreign_keys(storage, repair, &mut report)?;
+    check_blobs(storage, &mut report)?;
+    check_session_counters(storage, repair, &mut report)?;
+    check_entity_counters(storage, repair, &mut report)?;
+    check_keyword_index(storage, repair, &mut report)?;
+
+    Ok(report)
+}
+
+/// Foreign key of a table, as `PRAGMA foreign_key_list` describes it
+struct ForeignKey {
+    parent: String,
+    columns: Vec<String>,
+    on_delete: String,
+}
+
+fn foreign_keys(conn: &Connection, table: &str) -> Result<BTreeMap<i64, ForeignKey>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA foreign_key_list(\"{}\")", table))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>("id")?,
+            row.get::<_, String>("table")?,
+            row.get::<_, String>("from")?,
+            row.get::<_, String>("on_delete")?,
+        ))
+    })?;
+    let mut keys: BTreeMap<i64, ForeignKey> = BTreeMap::new();
+    for row in rows {
+        let (id, parent, column, on_delete) = row?;
+        keys.entry(id)
+            .or_insert_with(|| ForeignKey {
+                parent,
+                columns: Vec::new(),
+                on_delete,
+            })
+            .columns
+            .push(column);
+    }
+    Ok(keys)
+}
+
+fn check_foreign_keys(
+    storage: &StorageManager,
+    repair: bool,
+    report: &mut VerifyReport,
+) -> Result<()> {
+    // (table, foreign key id) -> rowids pointing nowhere
+    let mut violations: BTreeMap<(String, i64), Vec<Option<i64>>> = BTreeMap::new();
+    {
+        let conn = storage.database.read_conn()?;
+        let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (table, rowid, fkid) = row?;
+            violations.entry((table, fkid)).or_default().push(rowid);
+        }
+    }
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = storage.database.get_conn()?;
+    let tx = conn.transaction()?;
+    for ((table, fkid), rowids) in violations {
+        let keys = foreign_keys(&tx, &table)?;
+        let Some(key) = keys.get(&fkid) else {
+            continue;
+        };
+        let mut issue = VerifyIssue {
+            check: VerifyCheck::ForeignKey,
+            subject: table.clone(),
+            count: rowids.len(),
+            detail: format!(
+                "{} row(s) point at a missing {} ({})",
+                rowids.len(),
+                key.parent,
+                key.columns.join(", ")
+            ),
+            repaired: false,
+        };
+
+        let rowids: Vec<i64> = rowids.into_iter().flatten().collect();
+        let statement = match key.on_delete.as_str() {
+            "CASCADE" => Some(format!("DELETE FROM \"{}\" WHERE rowid = ?1", table)),
+            "SET NULL" => Some(format!(
+                "UPDATE \"{}\" SET {} WHERE rowid = ?1",
+                table,
+                key.columns
+                    .iter()
+                    .map(|column| format!("\"{}\" = NULL", column))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            _ => None,
+        };
+        if let (true, Some(statement)) = (repair, statement) {
+            // Tables without rowids come back with none to act on
+            if rowids.len() == issue.count {
+                for rowid in &rowids {
+                    tx.execute(&statement, params![rowid])?;
+                }
+                issue.repaired = true;
+            }
+        }
+        report.issues.push(issue);
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn check_blobs(storage: &StorageManager, report: &mut VerifyReport) -> Result<()> {
+    let hashes: Vec<String> = {
+        let conn = storage.database.read_conn()?;
+        let mut stmt = conn.prepare("SELECT hash FROM blobs ORDER BY hash")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<std::result::Result<_, _>>()?
+    };
+    let missing: Vec<&String> = hashes
+        .iter()
+        .filter(|hash| !storage.blob_store.exists(hash))
+        .collect();
+    if !missing.is_empty() {
+        let shown: Vec<&str> = missing.iter().take(5).map(|hash| hash.as_str()).collect();
+        report.issues.push(VerifyIssue {
+            check: VerifyCheck::MissingBlob,
+            subject: "blobs".to_string(),
+            count: missing.len(),
+            detail: format!(
+                "{} blob file(s) missing: {}{}",
+                missing.len(),
+                shown.join(", "),
+                if missing.len() > shown.len() {
+                    ", ..."
+                } else {
+                    ""
+                }
+            ),
+            repaired: false,
+        });
+    }
+    Ok(())
+}
+
+fn check_session_counters(
+    storage: &StorageManager,
+    repair: bool,
+    report: &mut VerifyReport,
+) -> Result<()> {
+    let drifted: Vec<(String, i64, i64)> = {
+        let conn = storage.database.read_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.capture_count,
+                    (SELECT COUNT(*) FROM captures c WHERE c.session_id = s.id) AS actual
+             FROM sessions s
+             WHERE s.capture_count != actual
+             ORDER BY s.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<std::result::Result<_, _>>()?
+    };
+
+    let conn = storage.database.get_conn()?;
+    for (session_id, recorded, actual) in drifted {
+        if repair {
+            conn.execute(
+                "UPDATE sessions SET capture_count = ?2 WHERE id = ?1",
+                params![session_id, actual],
+            )?;
+        }
+        report.issues.push(VerifyIssue {
+            check: VerifyCheck::SessionCounter,
+            subject: session_id,
+            count: 1,
+            detail: format!("capture_count is {} but there are {}", recorded, actual),
+            repaired: repair,
+        });
+    }
+    Ok(())
+}
+
+/// Totals of each (session, type, value) entities add up to
+const ENTITY_TOTALS: &str =
+    "SELECT c.session_id, e.type, e.value, SUM(e.occurrences) AS occurrences,
+        COUNT(*) AS captures, MAX(e.confidence) AS max_confidence,
+        MIN(c.timestamp) AS first_seen, MAX(c.timestamp) AS last_seen
+ FROM entities e JOIN captures c ON c.id = e.capture_id
+ GROUP BY c.session_id, e.type, e.value";
+
+fn check_entity_counters(
+    storage: &StorageManager,
+    repair: bool,
+    report: &mut VerifyReport,
+) -> Result<()> {
+    // Rows enrichment added without entities behind them are left alone
+    let drifted: Vec<(String, usize)> = {
+        let conn = storage.database.read_conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT t.session_id, COUNT(*)
+             FROM ({}) t
+             LEFT JOIN session_entities se
+               ON se.session_id = t.session_id AND se.type = t.type AND se.value = t.value
+             WHERE se.session_id IS NULL
+                OR se.occurrences != t.occurrences OR se.captures != t.captures
+             GROUP BY t.session_id
+             ORDER BY t.session_id",
+            ENTITY_TOTALS
+        ))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?;
+        rows.collect::<std::result::Result<_, _>>()?
+    };
+    if drifted.is_empty() {
+        return Ok(());
+    }
+
+    if repair {
+        let conn = storage.database.get_conn()?;
+        conn.execute(
+            &format!(
+                "INSERT INTO session_entities
+                 (session_id, type, value, occurrences, captures, max_confidence, first_seen,
+                  last_seen)
+                 SELECT session_id, type, value, occurrences, captures, max_confidence,
+                        first_seen, last_seen
+                 FROM ({}) WHERE true
+                 ON CONFLICT (session_id, type, value) DO UPDATE SET
+                     occurrences = excluded.occurrences,
+                     captures = excluded.captures,
+                     max_confidence = excluded.max_confidence,
+                     first_seen = excluded.first_seen,
+                     last_seen = excluded.last_seen",
+                ENTITY_TOTALS
+            ),
+            [],
+        )?;
+    }
+    for (session_id, count) in drifted {
+        report.issues.push(VerifyIssue {
+            check: VerifyCheck::EntityCounter,
+            subject: session_id,
+            count,
+            detail: format!(
+                "{} session entit{} missing or not matching their entities",
+                count,
+                if count == 1 { "y" } else { "ies" }
+            ),
+            repaired: repair,
+        });
+    }
+    Ok(())
+}
+
+/// Chunks the keyword index should hold once caught up to its watermark
+fn indexed_chunks(storage: &StorageManager) -> Result<usize> {
+    let watermark = storage.database.index_watermark(KEYWORD_INDEX)?;
+    let conn = storage.database.read_conn()?;
+    let n: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM chunks ch
+         JOIN captures c ON c.id = ch.capture_id
+         JOIN sessions s ON s.id = c.session_id
+         WHERE ch.id <= ?1 AND s.status != 'archived'",
+        params![watermark.committed],
+        |row| row.get(0),
+    )?;
+    Ok(n as usize)
+}
+
+fn check_keyword_index(
+    storage: &StorageManager,
+    repair: bool,
+    report: &mut VerifyReport,
+) -> Result<()> {
+    // Only a repair writes, so checking leaves the writer to the daemon
+    let index = if repair {
+        let mut index = storage.open_keyword_index()?;
+        if index_is_behind(&storage.database, KEYWORD_INDEX)? {
+            sync_keyword_index(&storage.database, &mut index)?;
+        }
+        Some(index)
+    } else {
+        storage.open_keyword_index_read_only()?
+    };
+
+    // Until it catches up the count only means something below the watermark
+    if index_is_behind(&storage.database, KEYWORD_INDEX)? {
+        let watermark = storage.database.index_watermark(KEYWORD_INDEX)?;
+        let conn = storage.database.read_conn()?;
+        let n: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM chunks WHERE id > ?1",
+            params![watermark.committed],
+            |row| row.get(0),
+        )?;
+        report.index_pending = n as usize;
+        return Ok(());
+    }
+
+    let expected = indexed_chunks(storage)?;
+    let actual = index.as_ref().map_or(0, |index| index.len() as usize);
+    if actual == expected {
+        return Ok(());
+    }
+
+    let mut issue = VerifyIssue {
+        check: VerifyCheck::KeywordIndex,
+        subject: KEYWORD_INDEX.to_string(),
+        count: actual.abs_diff(expected),
+        detail: format!("holds {} chunk(s), expected {}", actual, expected),
+        repaired: false,
+    };
+    if let Some(index) = index.filter(|_| repair) {
+        let tokenizers = index.tokenizers().clone();
+        drop(index);
+        let rebuild = IndexRebuild::new();
+        rebuild.start();
+        rebuild_keyword_index(
+            &storage.database,
+            storage.machine_zone().join("keywords"),
+            &tokenizers,
+            &rebuild,
+        )?;
+        let index = storage.open_keyword_index()?;
+        issue.repaired = index.len() as usize == indexed_chunks(storage)?;
+    }
+    report.issues.push(issue);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fixtures::capture;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_verify_and_repair() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let capture = capture("s1", "nmap", b"22/tcp open ssh\n")
+            .chunks(&["22/tcp open ssh"])
+            .insert(&storage);
+
+        let conn = storage.database.get_conn().unwrap();
+        conn.execute("UPDATE sessions SET capture_count = 5", [])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO blobs (hash, size, created_at, compressed) VALUES ('gone', 1, 0, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO entities (capture_id, type, value, occurrences) VALUES (?1, 'port', '22', 1)",
+            params![capture.id],
+        )
+        .unwrap();
+        // Rows left behind by writes made with foreign keys off
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO embeddings (chunk_id, vector, model, created_at) VALUES (9, x'00', 'm', 0);
+             INSERT INTO findings (session_id, capture_id, title, severity, created_at)
+                 VALUES ('s1', 9, 'ssh', 'info', 0);
+             PRAGMA foreign_keys = ON;",
+        )
+        .unwrap();
+        drop(conn);
+
+        let report = verify(&storage, false).unwrap();
+        assert_eq!((report.chunks, report.embeddings_queued), (1, 1));
+        assert_eq!(report.index_pending, 1);
+        let checks: Vec<(&str, &str)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.check.as_str(), issue.subject.as_str()))
+            .collect();
+        assert_eq!(
+            checks,
+            [
+                ("foreign_key", "embeddings"),
+                ("foreign_key", "findings"),
+                ("missing_blob", "blobs"),
+                ("session_counter", "s1"),
+                ("entity_counter", "s1"),
+            ]
+        );
+        assert_eq!(report.unrepaired(), 5);
+
+        let report = verify(&storage, true).unwrap();
+        assert_eq!(report.index_pending, 0);
+        // Only the missing blob file can't be fixed
+        assert_eq!(report.unrepaired(), 1);
+
+        let report = verify(&storage, false).unwrap();
+        let checks: Vec<&str> = report.issues.iter().map(|i| i.check.as_str()).collect();
+        assert_eq!(checks, ["missing_blob"]);
+        let conn = storage.database.read_conn().unwrap();
+        let capture: Option<i64> = conn
+            .query_row("SELECT capture_id FROM findings", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(capture, None);
+    }
+}
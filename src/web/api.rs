@@ -0,0 +1,350 @@
+//! JSON API handlers for the web UI
+
+use super::WebState;
+use crate::error::YinxError;
+use crate::session::SessionManager;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Largest search limit a client may ask for
+const MAX_SEARCH_LIMIT: usize = 200;
+
+/// Widest entity context a client may ask for, in bytes either side
+const MAX_CONTEXT_WINDOW: usize = 4096;
+
+/// Handler failure with the HTTP status to report
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: 400,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<YinxError> for ApiError {
+    fn from(e: YinxError) -> Self {
+        Self {
+            status: 500,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// `Ok(None)` means the resource does not exist
+pub type ApiResult = std::result::Result<Option<Value>, ApiError>;
+
+/// GET /api/sessions
+pub fn sessions(state: &WebState) -> ApiResult {
+    let sessions = SessionManager::new(state.data_dir.clone()).list_sessions()?;
+    Ok(Some(json!(sessions)))
+}
+
+/// GET /api/sessions/{id or name}/captures
+pub fn session_captures(state: &WebState, session: &str) -> ApiResult {
+    let session = match SessionManager::new(state.data_dir.clone()).resolve(session) {
+        Ok(session) => session,
+        Err(YinxError::SessionNotFound { .. }) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let records = state
+        .storage
+        .database
+        .get_session_captures(&session.id.to_string())?;
+    let failed: Vec<i64> = records
+        .iter()
+        .filter(|c| c.exit_code.is_some_and(|code| code != 0))
+        .map(|c| c.id)
+        .collect();
+    let hints = state.storage.triage_hints(&failed)?;
+    let captures: Vec<Value> = records
+        .into_iter()
+        .map(|c| {
+            json!({
+                "id": c.id,
+                "timestamp": c.timestamp,
+                "command": c.command,
+                "tool": c.tool,
+                "tool_detection": c.detection,
+                "tool_confidence": c.detection_confidence,
+                "exit_code": c.exit_code,
+                "cwd": c.cwd,
+                "username": c.username,
+                "repeat_count": c.repeat_count,
+                "triage": hints.get(&c.id),
+            })
+        })
+        .collect();
+
+    Ok(Some(json!({ "session": session, "captures": captures })))
+}
+
+/// GET /api/captures/{id}/output
+///
+/// `context=N` re-derives each entity's context at N bytes either side.
+pub fn capture_output(state: &WebState, id: &str, params: &HashMap<String, String>) -> ApiResult {
+    let id: i64 = id
+        .parse()
+        .map_err(|_| ApiError::bad_request("Capture id must be a number"))?;
+    let Some(capture) = state.storage.database.get_capture(id)? else {
+        return Ok(None);
+    };
+
+    let output = state.storage.blob_store.read(&capture.output_hash)?;
+    let window = match params.get("context") {
+        Some(window) => Some(
+            window
+                .parse::<usize>()
+                .map_err(|_| ApiError::bad_request("'context' must be a number of bytes"))?
+                .min(MAX_CONTEXT_WINDOW),
+        ),
+        None => None,
+    };
+    let rederive = match window {
+        Some(window) => Some((state.storage.indexed_output(&capture)?, window)),
+        None => None,
+    };
+    let entities: Vec<Value> = state
+        .storage
+        .database
+        .get_entities_for_capture(id)?
+        .into_iter()
+        .map(|e| {
+            let context = rederive
+                .as_ref()
+                .and_then(|(output, window)| e.context_in(output, *window));
+            json!({
+                "type": e.entity_type,
+                "value": e.value,
+                "line": e.line,
+                "context": context,
+            })
+        })
+        .collect();
+    let annotations: Vec<Value> = state
+        .storage
+        .database
+        .get_annotations_for_capture(id)?
+        .into_iter()
+        .map(|a| {
+            let data: Value = serde_json::from_str(&a.data).unwrap_or(Value::Null);
+            json!({ "hook": a.hook, "stage": a.stage, "data": data })
+        })
+        .collect();
+    let attachments: Vec<Value> = state
+        .storage
+        .database
+        .get_attachments_for_capture(id)?
+        .into_iter()
+        .map(|a| json!({ "filename": a.filename, "path": a.report_path(), "hash": a.hash }))
+        .collect();
+
+    Ok(Some(json!({
+        "id": capture.id,
+        "command": capture.command,
+        "output": String::from_utf8_lossy(&output),
+        "entities": entities,
+        "annotations": annotations,
+        "tags": state.storage.database.get_capture_tags(id)?,
+        "attachments": attachments,
+    })))
+}
+
+/// GET /api/search?q=...&limit=...&section=...
+pub fn search(state: &WebState, params: &HashMap<String, String>) -> ApiResult {
+    let query = params
+        .get("q")
+        .map(|q| q.trim())
+        .filter(|q| !q.is_empty())
+        .ok_or_else(|| ApiError::bad_request("Missing query parameter 'q'"))?;
+    let limit = params
+        .get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(20)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let section = params
+        .get("section")
+        .map(String::as_str)
+        .filter(|s| !s.is_empty());
+
+    let Some(keywords) = &state.keywords else {
+        return Err(ApiError {
+            status: 503,
+            message: "Keyword index unavailable".to_string(),
+        });
+    };
+    let hits = keywords
+        .lock()
+        .unwrap()
+        .search(query, limit)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+    let ids: Vec<i64> = hits.iter().map(|h| h.id as i64).collect();
+    let chunks: HashMap<i64, _> = state
+        .storage
+        .database
+        .get_chunks(&ids)?
+        .into_iter()
+        .map(|c| (c.id, c))
+        .collect();
+
+    let mut results = Vec::with_capacity(hits.len());
+    for hit in hits {
+        let Some(chunk) = chunks.get(&(hit.id as i64)) else {
+            continue;
+        };
+        let sections = chunk.sections();
+        if section.is_some_and(|wanted| !sections.iter().any(|s| s == wanted)) {
+            continue;
+        }
+        let capture = state.storage.database.get_capture(chunk.capture_id)?;
+        results.push(json!({
+            "chunk_id": chunk.id,
+            "score": hit.score,
+            "text": chunk.representative_text,
+            "sections": sections,
+            "capture_id": chunk.capture_id,
+            "session_id": capture.as_ref().map(|c| c.session_id.clone()),
+            "command": capture.as_ref().and_then(|c| c.command.clone()),
+            "tool": capture.as_ref().and_then(|c| c.tool.clone()),
+            "timestamp": capture.as_ref().map(|c| c.timestamp),
+        }));
+    }
+
+    Ok(Some(json!(results)))
+}
+
+/// GET /api/hosts
+///
+/// `as_of=T` (Unix seconds) draws the graph from captures taken by then.
+pub fn hosts(state: &WebState, params: &HashMap<String, String>) -> ApiResult {
+    let as_of = match params.get("as_of") {
+        Some(as_of) => Some(
+            as_of
+                .parse::<i64>()
+                .map_err(|_| ApiError::bad_request("'as_of' must be a Unix timestamp"))?,
+        ),
+        None => None,
+    };
+    Ok(Some(json!(state.storage.host_graph(None, as_of)?)))
+}
+
+/// GET /api/reports
+pub fn reports(state: &WebState) -> ApiResult {
+    let mut reports = Vec::new();
+    let root = state.storage.human_zone();
+    let Ok(sessions) = std::fs::read_dir(&root) else {
+        return Ok(Some(json!(reports)));
+    };
+
+    for session in sessions.flatten().filter(|e| e.path().is_dir()) {
+        let Ok(files) = std::fs::read_dir(session.path()) else {
+            continue;
+        };
+        for file in files.flatten().filter(|e| e.path().is_file()) {
+            reports.push(json!({
+                "session": session.file_name().to_string_lossy(),
+                "file": file.file_name().to_string_lossy(),
+                "size": file.metadata().map(|m| m.len()).unwrap_or(0),
+            }));
+        }
+    }
+
+    Ok(Some(json!(reports)))
+}
+
+/// GET /api/reports/{session}/{file}, returned as raw text
+pub fn report_file(
+    state: &WebState,
+    session: &str,
+    file: &str,
+) -> std::result::Result<Option<Vec<u8>>, YinxError> {
+    let session = percent_decode(session);
+    let file = percent_decode(file);
+    if !is_plain_name(&session) || !is_plain_name(&file) {
+        return Ok(None);
+    }
+
+    let path = state.storage.human_zone().join(session).join(file);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    std::fs::read(&path).map(Some).map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to read report: {}", path.display()),
+    })
+}
+
+/// A single path component that can't escape its directory
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
+
+/// Parse `a=1&b=two` into a map, percent-decoding values
+pub(super) fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes and `+` as space
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let params = parse_query("q=open+ssh%2F22&limit=5&flag");
+        assert_eq!(params["q"], "open ssh/22");
+        assert_eq!(params["limit"], "5");
+        assert_eq!(params["flag"], "");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_plain_names() {
+        assert!(is_plain_name("report.md"));
+        assert!(!is_plain_name(".."));
+        assert!(!is_plain_name("a/b"));
+        assert!(!is_plain_name(""));
+    }
+}
@@ -0,0 +1,323 @@
+//! Read-only local web UI
+//!
+//! `yinx serve --web 127.0.0.1:7777` serves an embedded single-page app and
+//! a small JSON API over the session, storage and keyword search layers.
+//! Only GET and HEAD are accepted, and the store is opened read-only, so
+//! nothing can be modified through the UI. Requests must name the bound
+//! address in their Host header (and Origin, when sent), so a page on
+//! another site can't reach the UI through DNS rebinding. The HTTP handling is deliberately minimal: one request per connection,
+//! no keep-alive, no request bodies.
+
+mod api;
+
+use crate::embedding::KeywordIndex;
+use crate::error::{Result, YinxError};
+use crate::storage::StorageManager;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Embedded single-page UI
+const INDEX_HTML: &str = include_str!("index.html");
+
+/// Largest request head accepted
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+
+/// Data shared by all requests
+pub struct WebState {
+    data_dir: PathBuf,
+    storage: StorageManager,
+    keywords: Option<Mutex<KeywordIndex>>,
+}
+
+impl WebState {
+    /// Open the store in `data_dir` read-only
+    pub fn new(data_dir: PathBuf) -> Result<Self> {
+        let storage = StorageManager::open_read_only(data_dir.clone())?;
+        // Searching only; the daemon may hold the writer
+        let keywords = match storage.open_keyword_index_read_only() {
+            Ok(Some(index)) => Some(Mutex::new(index)),
+            Ok(None) => {
+                tracing::warn!("Keyword index not built yet, search disabled");
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Keyword index unavailable, search disabled: {}", e);
+                None
+            }
+        };
+
+        Ok(Self {
+            data_dir,
+            storage,
+            keywords,
+        })
+    }
+}
+
+/// HTTP response produced by the router
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(value: &serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(&serde_json::json!({ "error": message.into() }))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            403 => "Forbidden",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Serve the UI until Ctrl-C
+pub async fn serve(addr: SocketAddr, state: WebState) -> Result<()> {
+    if !addr.ip().is_loopback() {
+        tracing::warn!(
+            "Web UI bound to non-loopback address {}; captured data is exposed without authentication",
+            addr
+        );
+    }
+
+    let listener = TcpListener::bind(addr).await.map_err(|e| YinxError::Io {
+        source: e,
+        context: format!("Failed to bind web UI to {}", addr),
+    })?;
+    let state = Arc::new(state);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = accepted.map_err(|e| YinxError::Io {
+                    source: e,
+                    context: "Failed to accept web connection".to_string(),
+                })?;
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, addr, &state).await {
+                        tracing::debug!("Web request from {} failed: {}", peer, e);
+                    }
+                });
+            }
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("Web UI shutting down");
+                return Ok(());
+            }
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    state: &WebState,
+) -> Result<()> {
+    let io_err = |e: std::io::Error| YinxError::Io {
+        source: e,
+        context: "Web connection failed".to_string(),
+    };
+
+    // Read until the end of the request head
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    let response = loop {
+        let n = stream.read(&mut chunk).await.map_err(io_err)?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buf) {
+            Ok(httparse::Status::Complete(_)) => {
+                let header = |name: &str| {
+                    request
+                        .headers
+                        .iter()
+                        .find(|h| h.name.eq_ignore_ascii_case(name))
+                        .and_then(|h| std::str::from_utf8(h.value).ok())
+                };
+                if !is_same_origin(addr, header("Host"), header("Origin")) {
+                    break Response::error(403, "Host or Origin doesn't match the web UI");
+                }
+                let method = request.method.unwrap_or("");
+                let path = request.path.unwrap_or("/");
+                let mut response = route(state, method, path);
+                if method == "HEAD" {
+                    response.body.clear();
+                }
+                break response;
+            }
+            Ok(httparse::Status::Partial) if buf.len() < MAX_REQUEST_SIZE => continue,
+            Ok(httparse::Status::Partial) => break Response::error(413, "Request too large"),
+            Err(e) => break Response::error(400, e.to_string()),
+        }
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\
+         X-Content-Type-Options: nosniff\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await.map_err(io_err)?;
+    stream.write_all(&response.body).await.map_err(io_err)?;
+    stream.shutdown().await.map_err(io_err)?;
+
+    Ok(())
+}
+
+/// Whether a request's Host, and Origin if it has one, name `addr`
+///
+/// A rebound DNS name reaches the right socket but still carries the
+/// attacker's name in Host; a cross-site fetch carries the site's Origin.
+/// `localhost` stands for a loopback address; an unspecified one takes
+/// any address with the right port.
+fn is_same_origin(addr: SocketAddr, host: Option<&str>, origin: Option<&str>) -> bool {
+    let names_addr = |host: &str| match host.parse::<SocketAddr>() {
+        Ok(named) => {
+            named.port() == addr.port() && (addr.ip().is_unspecified() || named.ip() == addr.ip())
+        }
+        Err(_) => {
+            (addr.ip().is_loopback() || addr.ip().is_unspecified())
+                && host.eq_ignore_ascii_case(&format!("localhost:{}", addr.port()))
+        }
+    };
+    let Some(host) = host else {
+        return false;
+    };
+    names_addr(host)
+        && origin.is_none_or(|origin| origin.strip_prefix("http://").is_some_and(names_addr))
+}
+
+/// Dispatch a request to the UI or an API handler
+pub fn route(state: &WebState, method: &str, target: &str) -> Response {
+    if method != "GET" && method != "HEAD" {
+        return Response::error(405, "The web UI is read-only");
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = api::parse_query(query);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let result = match segments.as_slice() {
+        [""] | ["index.html"] => {
+            return Response {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: INDEX_HTML.as_bytes().to_vec(),
+            }
+        }
+        ["api", "sessions"] => api::sessions(state),
+        ["api", "sessions", session, "captures"] => api::session_captures(state, session),
+        ["api", "captures", id, "output"] => api::capture_output(state, id, &params),
+        ["api", "search"] => api::search(state, &params),
+        ["api", "hosts"] => api::hosts(state, &params),
+        ["api", "reports"] => api::reports(state),
+        ["api", "reports", session, file] => {
+            return match api::report_file(state, session, file) {
+                Ok(Some(body)) => Response {
+                    status: 200,
+                    content_type: "text/plain; charset=utf-8",
+                    body,
+                },
+                Ok(None) => Response::error(404, "Report not found"),
+                Err(e) => Response::error(500, e.to_string()),
+            }
+        }
+        _ => return Response::error(404, "Not found"),
+    };
+
+    match result {
+        Ok(Some(value)) => Response::json(&value),
+        Ok(None) => Response::error(404, "Not found"),
+        Err(e) => Response::error(e.status, e.message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_routes() {
+        let temp_dir = TempDir::new().unwrap();
+        StorageManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let state = WebState::new(temp_dir.path().to_path_buf()).unwrap();
+        // Opened read-only
+        assert!(state
+            .storage
+            .database
+            .ensure_session("s1", "s1", 0, "active")
+            .is_err());
+
+        let index = route(&state, "GET", "/");
+        assert_eq!(index.status, 200);
+        assert!(index.content_type.starts_with("text/html"));
+
+        let sessions = route(&state, "GET", "/api/sessions");
+        assert_eq!(sessions.status, 200);
+        assert_eq!(sessions.body, b"[]");
+
+        assert_eq!(route(&state, "POST", "/api/sessions").status, 405);
+        assert_eq!(route(&state, "GET", "/api/nope").status, 404);
+        assert_eq!(route(&state, "GET", "/api/search").status, 400);
+        assert_eq!(route(&state, "GET", "/api/reports/../etc").status, 404);
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let local: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        assert!(is_same_origin(local, Some("127.0.0.1:7777"), None));
+        assert!(is_same_origin(
+            local,
+            Some("localhost:7777"),
+            Some("http://localhost:7777")
+        ));
+        // DNS rebinding and cross-site requests
+        assert!(!is_same_origin(local, Some("evil.example:7777"), None));
+        assert!(!is_same_origin(
+            local,
+            Some("127.0.0.1:7777"),
+            Some("http://evil.example")
+        ));
+        assert!(!is_same_origin(local, Some("127.0.0.1:8080"), None));
+        assert!(!is_same_origin(local, None, None));
+
+        let any: SocketAddr = "0.0.0.0:7777".parse().unwrap();
+        assert!(is_same_origin(any, Some("10.0.0.5:7777"), None));
+        assert!(is_same_origin(any, Some("localhost:7777"), None));
+        assert!(!is_same_origin(any, Some("evil.example:7777"), None));
+    }
+}
@@ -1,8 +1,8 @@
 use std::time::Duration;
 use tempfile::TempDir;
 use tokio::time::sleep;
-use yinx::config::Config;
-use yinx::daemon::{Daemon, IpcClient, IpcMessage};
+use yinx::config::{Config, IpcEncoding};
+use yinx::daemon::{Daemon, IpcClient, IpcErrorCode, IpcMessage, OutputHandoff};
 use yinx::storage::StorageManager;
 
 #[tokio::test]
@@ -83,7 +83,7 @@ preserve_metadata = []
     sleep(Duration::from_millis(100)).await;
 
     // Create IPC client
-    let client = IpcClient::new(socket_path);
+    let client = IpcClient::new(socket_path.clone());
 
     // Test 1: Send status message
     let response = client
@@ -91,6 +91,18 @@ preserve_metadata = []
         .await
         .expect("Failed to send status message");
     assert!(response.success, "Status request should succeed");
+
+    // The same request after negotiating bincode
+    let binary_client = IpcClient::new(socket_path.clone()).with_encoding(IpcEncoding::Bincode);
+    let response = binary_client
+        .send(&IpcMessage::Status)
+        .await
+        .expect("Failed to send status message over bincode");
+    assert!(response.success, "Bincode status request should succeed");
+    assert!(
+        response.data.is_some(),
+        "Status data should survive bincode"
+    );
     println!("✓ Status IPC test passed");
 
     // Test 2: Send capture message
@@ -101,6 +113,9 @@ preserve_metadata = []
         output: "Starting Nmap 7.80\nNmap scan report...".to_string(),
         exit_code: 0,
         cwd: "/tmp".to_string(),
+        context: None,
+        dry_run: false,
+        handoff: None,
     };
 
     let response = client
@@ -110,6 +125,79 @@ preserve_metadata = []
     assert!(response.success, "Capture should be queued");
     println!("✓ Capture IPC test passed");
 
+    // Test 3: Excluded commands are acknowledged but not stored
+    let excluded_msg = IpcMessage::Capture {
+        session_id: "test-session".to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+        command: "history".to_string(),
+        output: "1  nmap -sV 192.168.1.1".to_string(),
+        exit_code: 0,
+        cwd: "/tmp".to_string(),
+        context: None,
+        dry_run: false,
+        handoff: None,
+    };
+    let response = client
+        .send(&excluded_msg)
+        .await
+        .expect("Failed to send excluded capture");
+    assert!(
+        response.success && response.skipped,
+        "Capture should be skipped"
+    );
+    println!("✓ Exclusion IPC test passed");
+
+    // Test 4: Big output handed off as a file is read and removed by the daemon
+    let output_file = base_path.join("yinx_handoff.out");
+    std::fs::write(
+        &output_file,
+        "Nmap scan report for 10.0.0.5
+"
+        .repeat(1000),
+    )
+    .unwrap();
+    let handoff_msg = IpcMessage::Capture {
+        session_id: "test-session".to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+        command: "nmap -sV 10.0.0.5".to_string(),
+        output: String::new(),
+        exit_code: 0,
+        cwd: "/tmp".to_string(),
+        context: None,
+        dry_run: false,
+        handoff: Some(OutputHandoff::for_file(&output_file).unwrap()),
+    };
+    let response = client
+        .send(&handoff_msg)
+        .await
+        .expect("Failed to send handed-off capture");
+    assert!(response.success, "Handed-off capture should be queued");
+    assert!(
+        !output_file.exists(),
+        "Daemon should remove the output file"
+    );
+    println!("✓ Output handoff IPC test passed");
+
+    // Test 5: Captures for a session nobody started are refused with a code
+    let unknown_msg = IpcMessage::Capture {
+        session_id: "no-such-session".to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+        command: "id".to_string(),
+        output: "uid=0(root)".to_string(),
+        exit_code: 0,
+        cwd: "/tmp".to_string(),
+        context: None,
+        dry_run: false,
+        handoff: None,
+    };
+    let response = client
+        .send(&unknown_msg)
+        .await
+        .expect("Failed to send capture for unknown session");
+    assert!(!response.success, "Unknown session should be refused");
+    assert_eq!(response.code, Some(IpcErrorCode::SessionUnknown));
+    println!("✓ Unknown session IPC test passed");
+
     // Wait for capture to be processed (flush interval is 1s in test)
     println!("  Waiting for storage worker to process capture...");
     sleep(Duration::from_millis(1500)).await;
@@ -121,7 +209,7 @@ preserve_metadata = []
         .query_row("SELECT COUNT(*) FROM captures", [], |row| row.get(0))
         .unwrap();
 
-    assert_eq!(count, 1, "Should have 1 capture stored");
+    assert_eq!(count, 2, "Should have 2 captures stored");
     println!("✓ Storage integration test passed");
 
     // Cleanup: abort daemon
@@ -160,3 +248,24 @@ fn test_process_manager() {
 
     println!("✅ Process manager tests passed!");
 }
+
+#[test]
+fn test_missing_patterns_fall_back_to_bundled() {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_path_buf();
+
+    // A fresh environment: `yinx config init` never ran
+    let mut config = Config::default();
+    config.storage.data_dir = base_path.clone();
+    config.daemon.pid_file = base_path.join("test.pid");
+    config.patterns.entities_file = base_path.join("missing").join("entities.toml");
+    config.patterns.tools_file = base_path.join("missing").join("tools.toml");
+    config.patterns.filters_file = base_path.join("missing").join("filters.toml");
+    config.patterns.rules_file = base_path.join("missing").join("rules.toml");
+
+    let daemon = Daemon::new(config).expect("daemon should start on bundled patterns");
+    let reason = daemon
+        .pattern_fallback()
+        .expect("fallback should be reported");
+    assert!(reason.contains("entities"), "unexpected reason: {}", reason);
+}
@@ -76,6 +76,7 @@ fn create_full_registry() -> PatternRegistry {
             score_threshold_percentile: 0.8,
             technical_patterns: vec![],
             max_technical_score: 10.0,
+            auto_threshold: Default::default(),
         },
         tier3: Tier3Config {
             cluster_min_size: 2,
@@ -84,6 +85,7 @@ fn create_full_registry() -> PatternRegistry {
             cluster_patterns: vec![],
             preserve_metadata: vec![],
         },
+        boilerplate: Default::default(),
     };
 
     PatternRegistry::from_configs(entities_config, tools_config, filters_config).unwrap()
@@ -244,8 +246,8 @@ CVE-2021-44228 detected in Apache Log4j
     // Verify host was added
     let host = graph.get_host("192.168.1.100").unwrap();
     assert_eq!(host.identifier, "192.168.1.100");
-    assert!(host.ports.contains(&22));
-    assert!(host.ports.contains(&80));
+    assert_eq!(host.open_ports(), vec![22, 80]);
+    assert_eq!(host.service(22), Some("ssh OpenSSH/8.2p1"));
 
     // Verify CVE was correlated
     let vulns: Vec<_> = host.vulnerabilities.iter().collect();
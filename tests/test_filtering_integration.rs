@@ -47,6 +47,7 @@ fn create_patterns() -> Arc<PatternRegistry> {
                     weight: 1.5,
                 },
             ],
+            auto_threshold: Default::default(),
         },
         tier3: Tier3Config {
             cluster_min_size: 2,
@@ -60,6 +61,7 @@ fn create_patterns() -> Arc<PatternRegistry> {
             }],
             preserve_metadata: vec![],
         },
+        boilerplate: Default::default(),
     };
 
     Arc::new(PatternRegistry::from_configs(entities, tools, filters).unwrap())
@@ -142,6 +142,7 @@ async fn test_phase7_hybrid_search() {
         reranker_model: "Xenova/ms-marco-MiniLM-L-6-v2".to_string(),
         rerank_candidates_limit: 100,
         min_similarity_threshold: 0.0,
+        ..RetrievalConfig::default()
     };
 
     // Create new indices for HybridSearcher (will read same persisted data)